@@ -0,0 +1,150 @@
+//! Folding rules that decide when two roster or dataset identifiers name the same person.
+
+use std::collections::HashMap;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+pub fn normalize_identifier_value(value: &str, strip_diacritics: bool) -> String {
+    fold_identifier_characters(value, strip_diacritics)
+        .split_whitespace()
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+const DEFAULT_EMAIL_DOMAIN_ALIASES: &[(&str, &str)] = &[("email.wustl.edu", "wustl.edu")];
+
+pub fn build_email_domain_aliases(overrides: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut aliases: HashMap<String, String> = DEFAULT_EMAIL_DOMAIN_ALIASES
+        .iter()
+        .map(|(alias, canonical)| (alias.to_string(), canonical.to_string()))
+        .collect();
+
+    for (alias, canonical) in overrides {
+        let alias = alias.trim().trim_start_matches('@').to_lowercase();
+        let canonical = canonical.trim().trim_start_matches('@').to_lowercase();
+        if !alias.is_empty() && !canonical.is_empty() {
+            aliases.insert(alias, canonical);
+        }
+    }
+
+    aliases
+}
+
+pub fn normalize_email_value(
+    value: &str,
+    domain_aliases: &HashMap<String, String>,
+    strip_diacritics: bool,
+) -> String {
+    let mut address = value.trim();
+    if let (Some(start), Some(end)) = (address.rfind('<'), address.rfind('>')) {
+        if start < end {
+            address = &address[start + 1..end];
+        }
+    }
+    let address = address.trim().to_lowercase();
+    let address = address.trim_start_matches("mailto:");
+
+    match address.rsplit_once('@') {
+        Some((local, domain)) => {
+            let local: String = local.chars().filter(|ch| *ch != '.').collect();
+            let domain = domain.trim_end_matches('.');
+            let domain = domain_aliases
+                .get(domain)
+                .map(String::as_str)
+                .unwrap_or(domain);
+            format!("{local}@{domain}")
+        }
+        None => normalize_identifier_value(address, strip_diacritics),
+    }
+}
+
+/// Column labels always fold accents; the identifier setting only governs values.
+pub fn normalize_identifier_label(value: &str) -> String {
+    fold_identifier_characters(value, true)
+        .chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub fn fold_identifier_characters(value: &str, strip_diacritics: bool) -> String {
+    // Punctuation folds first: NFKC would otherwise split an acute accent used as an
+    // apostrophe into a space and a combining mark.
+    let punctuation: String = value.chars().map(fold_punctuation_variant).collect();
+    let composed: String = punctuation.nfkc().collect();
+    if strip_diacritics {
+        composed
+            .nfd()
+            .filter(|ch| !is_combining_mark(*ch))
+            .nfc()
+            .collect()
+    } else {
+        composed
+    }
+}
+
+fn fold_punctuation_variant(ch: char) -> char {
+    match ch {
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' | '\u{00b4}' | '`' => '\'',
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' => '"',
+        '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2015}'
+        | '\u{2212}' => '-',
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifier_normalization_folds_unicode_forms_and_punctuation() {
+        let precomposed = "Jos\u{e9} Garc\u{ed}a";
+        let decomposed = "Jose\u{301} Garci\u{301}a";
+        assert_eq!(normalize_identifier_value(precomposed, true), "jose garcia");
+        assert_eq!(normalize_identifier_value(decomposed, true), "jose garcia");
+
+        for apostrophe in ["'", "\u{2019}", "\u{2018}", "`", "\u{b4}"] {
+            assert_eq!(
+                normalize_identifier_value(&format!("Sin\u{e9}ad O{apostrophe}Brien"), true),
+                "sinead o'brien"
+            );
+        }
+
+        for hyphen in ["-", "\u{2010}", "\u{2013}", "\u{2014}"] {
+            assert_eq!(
+                normalize_identifier_value(&format!("Mar\u{ed}a  L\u{f3}pez{hyphen}Ortega "), true),
+                "maria lopez-ortega"
+            );
+        }
+    }
+
+    #[test]
+    fn identifier_folding_keeps_accents_when_stripping_is_off() {
+        let precomposed = fold_identifier_characters("Garc\u{ed}a", false);
+        let decomposed = fold_identifier_characters("Garci\u{301}a", false);
+        assert_eq!(precomposed, "Garc\u{ed}a");
+        assert_eq!(decomposed, precomposed);
+        assert_eq!(fold_identifier_characters("Garc\u{ed}a", true), "Garcia");
+    }
+
+    #[test]
+    fn email_normalization_ignores_dots_display_names_and_domain_aliases() {
+        let aliases = build_email_domain_aliases(&HashMap::new());
+        for value in [
+            "Robert.Smith@email.wustl.edu",
+            "Robert Smith <robert.smith@wustl.edu>",
+            "mailto:robertsmith@WUSTL.EDU.",
+        ] {
+            assert_eq!(
+                normalize_email_value(value, &aliases, true),
+                "robertsmith@wustl.edu"
+            );
+        }
+        assert_eq!(
+            normalize_email_value("Jos\u{e9} Garc\u{ed}a", &aliases, false),
+            "jos\u{e9} garc\u{ed}a"
+        );
+    }
+}
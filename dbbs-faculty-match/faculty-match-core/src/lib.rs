@@ -0,0 +1,5 @@
+//! Matching logic that does not depend on Tauri, so it can be unit-tested and reused
+//! without a desktop host.
+
+pub mod identifiers;
+pub mod similarity;
@@ -0,0 +1,213 @@
+//! Cosine scoring kernels used to rank faculty embeddings against a prompt.
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+
+    let [dot, norm_a, norm_b] = dot_and_norms(a, b);
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+
+    Some((dot / (norm_a.sqrt() * norm_b.sqrt())) as f32)
+}
+
+const SIMD_LANES: usize = 8;
+
+/// Returns the dot product and both squared norms. Products accumulate in f32 vector
+/// lanes and each lane is reduced in f64, which stays within 1e-5 of a fully f64 loop
+/// for embedding-sized vectors.
+pub fn dot_and_norms(a: &[f32], b: &[f32]) -> [f64; 3] {
+    vector_products::<true>(a, b)
+}
+
+/// The dot product alone, for callers that normalized their vectors up front.
+pub fn dot_product(a: &[f32], b: &[f32]) -> f64 {
+    vector_products::<false>(a, b)[0]
+}
+
+/// Picks the widest kernel the running CPU supports: AVX2 with FMA when detected,
+/// otherwise `wide`'s portable lanes (SSE2/NEON, or scalar on other targets). The
+/// norms are skipped entirely unless `NORMS` is set.
+fn vector_products<const NORMS: bool>(a: &[f32], b: &[f32]) -> [f64; 3] {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") && std::arch::is_x86_feature_detected!("fma") {
+        // SAFETY: both target features the kernel is compiled for were just detected.
+        return unsafe { avx2_vector_products::<NORMS>(a, b) };
+    }
+    portable_vector_products::<NORMS>(a, b)
+}
+
+fn portable_vector_products<const NORMS: bool>(a: &[f32], b: &[f32]) -> [f64; 3] {
+    let mut dot = wide::f32x8::ZERO;
+    let mut norm_a = wide::f32x8::ZERO;
+    let mut norm_b = wide::f32x8::ZERO;
+
+    let (chunks_a, rest_a) = a.as_chunks::<SIMD_LANES>();
+    let (chunks_b, rest_b) = b.as_chunks::<SIMD_LANES>();
+    for (&x, &y) in chunks_a.iter().zip(chunks_b) {
+        let (x, y) = (wide::f32x8::from(x), wide::f32x8::from(y));
+        dot = x.mul_add(y, dot);
+        if NORMS {
+            norm_a = x.mul_add(x, norm_a);
+            norm_b = y.mul_add(y, norm_b);
+        }
+    }
+
+    let reduce = |lanes: wide::f32x8| lanes.to_array().iter().copied().map(f64::from).sum();
+    let totals = [reduce(dot), reduce(norm_a), reduce(norm_b)];
+    add_remainder_products::<NORMS>(totals, rest_a, rest_b)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn avx2_vector_products<const NORMS: bool>(a: &[f32], b: &[f32]) -> [f64; 3] {
+    use std::arch::x86_64::{
+        __m256, _mm256_fmadd_ps, _mm256_loadu_ps, _mm256_setzero_ps, _mm256_storeu_ps,
+    };
+
+    let mut dot = _mm256_setzero_ps();
+    let mut norm_a = _mm256_setzero_ps();
+    let mut norm_b = _mm256_setzero_ps();
+
+    let (chunks_a, rest_a) = a.as_chunks::<SIMD_LANES>();
+    let (chunks_b, rest_b) = b.as_chunks::<SIMD_LANES>();
+    for (x, y) in chunks_a.iter().zip(chunks_b) {
+        // SAFETY: each chunk is exactly eight f32s, and the loads are unaligned.
+        let (x, y) = unsafe { (_mm256_loadu_ps(x.as_ptr()), _mm256_loadu_ps(y.as_ptr())) };
+        dot = _mm256_fmadd_ps(x, y, dot);
+        if NORMS {
+            norm_a = _mm256_fmadd_ps(x, x, norm_a);
+            norm_b = _mm256_fmadd_ps(y, y, norm_b);
+        }
+    }
+
+    let reduce = |lanes: __m256| {
+        let mut values = [0.0f32; SIMD_LANES];
+        // SAFETY: `values` has room for all eight lanes.
+        unsafe { _mm256_storeu_ps(values.as_mut_ptr(), lanes) };
+        values.iter().copied().map(f64::from).sum()
+    };
+    let totals = [reduce(dot), reduce(norm_a), reduce(norm_b)];
+    add_remainder_products::<NORMS>(totals, rest_a, rest_b)
+}
+
+fn add_remainder_products<const NORMS: bool>(
+    mut totals: [f64; 3],
+    rest_a: &[f32],
+    rest_b: &[f32],
+) -> [f64; 3] {
+    for (&x, &y) in rest_a.iter().zip(rest_b) {
+        let (x, y) = (f64::from(x), f64::from(y));
+        totals[0] += x * y;
+        if NORMS {
+            totals[1] += x * x;
+            totals[2] += y * y;
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    type Kernel = fn(&[f32], &[f32]) -> [f64; 3];
+
+    fn synthetic_embeddings(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        (0..count)
+            .map(|_| {
+                (0..dimension)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6_364_136_223_846_793_005)
+                            .wrapping_add(1_442_695_040_888_963_407);
+                        ((state >> 40) as f32 / (1u64 << 23) as f32) - 1.0
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The fully f64 scalar loop the vector kernels replaced.
+    fn scalar_dot_and_norms(a: &[f32], b: &[f32]) -> [f64; 3] {
+        a.iter()
+            .zip(b)
+            .fold([0.0; 3], |[dot, norm_a, norm_b], (&x, &y)| {
+                let (x, y) = (f64::from(x), f64::from(y));
+                [dot + x * y, norm_a + x * x, norm_b + y * y]
+            })
+    }
+
+    fn cosine_from([dot, norm_a, norm_b]: [f64; 3]) -> f64 {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+
+    #[test]
+    fn vector_kernels_stay_within_1e5_of_the_scalar_loop() {
+        let mut kernels: Vec<(&str, Kernel)> = vec![
+            ("dispatched", dot_and_norms),
+            ("portable", portable_vector_products::<true>),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("avx2") && std::arch::is_x86_feature_detected!("fma")
+        {
+            // SAFETY: both target features were just detected.
+            kernels.push(("avx2", |a, b| unsafe { avx2_vector_products::<true>(a, b) }));
+        }
+
+        for (seed, dimension) in [1, 7, 8, 9, 31, 64, 100, 384, 768, 1_536]
+            .into_iter()
+            .enumerate()
+        {
+            let vectors = synthetic_embeddings(20, dimension, seed as u64 + 100);
+            for pair in vectors.chunks_exact(2) {
+                let (a, b) = (&pair[0], &pair[1]);
+                let expected = scalar_dot_and_norms(a, b);
+                for (name, kernel) in &kernels {
+                    let actual = kernel(a, b);
+                    for (value, reference) in actual.iter().zip(expected) {
+                        assert!(
+                            (value - reference).abs() <= 1e-5 * reference.abs().max(1.0),
+                            "{name}, {dimension} dims: {actual:?} vs {expected:?}"
+                        );
+                    }
+                    assert!(
+                        (cosine_from(actual) - cosine_from(expected)).abs() < 1e-5,
+                        "{name}, {dimension} dims"
+                    );
+                }
+                assert_eq!(dot_product(a, b), dot_and_norms(a, b)[0]);
+            }
+        }
+    }
+
+    /// Run with `cargo test --release bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_cosine_kernels() {
+        let index = synthetic_embeddings(5_000, 768, 31);
+        let prompts = synthetic_embeddings(20, 768, 37);
+        let time = |kernel: Kernel| {
+            let started = Instant::now();
+            for prompt in &prompts {
+                for embedding in &index {
+                    std::hint::black_box(kernel(prompt, embedding));
+                }
+            }
+            started.elapsed()
+        };
+
+        let scalar = time(scalar_dot_and_norms);
+        let portable = time(portable_vector_products::<true>);
+        let dispatched = time(dot_and_norms);
+        println!(
+            "5,000 x 768 index, 20 prompts: scalar f64 {scalar:?}, portable {portable:?} ({:.1}x), dispatched {dispatched:?} ({:.1}x)",
+            scalar.as_secs_f64() / portable.as_secs_f64(),
+            scalar.as_secs_f64() / dispatched.as_secs_f64()
+        );
+    }
+}
@@ -26,7 +26,7 @@ except ImportError as exc:
 hf_logging.set_verbosity_error()
 
 TokenizerCache = Dict[str, AutoTokenizer]
-ModelCache = Dict[str, AutoModel]
+ModelCache = Dict[Tuple[str, str], AutoModel]
 
 _TOKENIZERS: TokenizerCache = {}
 _MODELS: ModelCache = {}
@@ -116,23 +116,78 @@ def emit_progress(payload: dict) -> None:
     sys.stderr.flush()
 
 
-def load_model(model_name: str) -> Tuple[AutoTokenizer, AutoModel]:
+def resolve_device(requested) -> "torch.device":
+    name = requested.strip().lower() if isinstance(requested, str) else ""
+    if name == "auto":
+        if torch.cuda.is_available():
+            return torch.device("cuda")
+        mps = getattr(torch.backends, "mps", None)
+        if mps is not None and mps.is_available():
+            return torch.device("mps")
+        return torch.device("cpu")
+    if not name:
+        return torch.device("cpu")
+    return torch.device(name)
+
+
+def resolve_batch_size(requested) -> int:
+    try:
+        value = int(requested)
+    except (TypeError, ValueError):
+        return 1
+    return max(value, 1)
+
+
+def load_model(model_name: str, device: "torch.device") -> Tuple[AutoTokenizer, AutoModel]:
     tokenizer = _TOKENIZERS.get(model_name)
-    model = _MODELS.get(model_name)
+    model = _MODELS.get((model_name, str(device)))
 
-    if tokenizer is None or model is None:
+    if tokenizer is None:
         tokenizer = AutoTokenizer.from_pretrained(model_name)
+        _TOKENIZERS[model_name] = tokenizer
+
+    if model is None:
         model = AutoModel.from_pretrained(model_name)
+        model.to(device)
         model.eval()
-        _TOKENIZERS[model_name] = tokenizer
-        _MODELS[model_name] = model
+        _MODELS[(model_name, str(device))] = model
 
     return tokenizer, model
 
 
+def tokenize_texts(tokenizer, texts):
+    return tokenizer(
+        texts,
+        return_tensors="pt",
+        truncation=True,
+        max_length=512,
+        padding=True,
+    )
+
+
+def embed_inputs(model, device, inputs):
+    inputs = {key: value.to(device) for key, value in inputs.items()}
+
+    with torch.no_grad():
+        outputs = model(**inputs)
+
+    last_hidden = outputs.last_hidden_state
+    attention_mask = inputs["attention_mask"]
+    mask = attention_mask.unsqueeze(-1).expand(last_hidden.size()).float()
+    masked = last_hidden * mask
+    summed = masked.sum(dim=1)
+    counts = mask.sum(dim=1).clamp(min=1e-9)
+    return (summed / counts).cpu().tolist()
+
+
 def process_embedding_request(payload: dict) -> dict:
     model_name = payload.get("model") or "NeuML/pubmedbert-base-embeddings"
     texts = payload.get("texts") or []
+    batch_size = resolve_batch_size(payload.get("batchSize"))
+    try:
+        device = resolve_device(payload.get("device"))
+    except (RuntimeError, ValueError) as exc:
+        raise ValueError(f"Unsupported embedding device: {exc}") from exc
     total = len(texts)
 
     raw_label = payload.get("itemLabel")
@@ -170,7 +225,7 @@ def process_embedding_request(payload: dict) -> dict:
         }
     )
 
-    tokenizer, model = load_model(model_name)
+    tokenizer, model = load_model(model_name, device)
 
     emit_progress(
         {
@@ -186,6 +241,7 @@ def process_embedding_request(payload: dict) -> dict:
 
     rows = []
     skipped_rows = []
+    pending = []
     for index, item in enumerate(texts):
         if isinstance(item, Mapping):
             row_id = item.get("id")
@@ -229,50 +285,48 @@ def process_embedding_request(payload: dict) -> dict:
             )
             continue
 
-        try:
-            inputs = tokenizer(
-                text,
-                return_tensors="pt",
-                truncation=True,
-                max_length=512,
-                padding=True,
-            )
-        except Exception as exc:  # noqa: BLE001
-            reason = str(exc).splitlines()[0]
-            skipped_rows.append(
-                {
-                    "id": identifier,
-                    "reason": f"tokenizer-error: {reason}",
-                }
-            )
-            sys.stderr.write(
-                f"WARNING Skipping row {identifier}: unable to tokenize ({reason})\n"
-            )
-            sys.stderr.flush()
-            emit_progress(
-                {
-                    "phase": "embedding",
-                    "message": f"Skipped row {identifier}: unable to tokenize",
-                    "processedRows": len(rows),
-                    "totalRows": total,
-                    "elapsedSeconds": time.time() - start_time,
-                    "skippedRows": len(skipped_rows),
-                }
-            )
-            continue
-
-        with torch.no_grad():
-            outputs = model(**inputs)
-
-        last_hidden = outputs.last_hidden_state
-        attention_mask = inputs["attention_mask"]
-        mask = attention_mask.unsqueeze(-1).expand(last_hidden.size()).float()
-        masked = last_hidden * mask
-        summed = masked.sum(dim=1)
-        counts = mask.sum(dim=1).clamp(min=1e-9)
-        embedding = (summed / counts).squeeze(0)
+        pending.append((identifier, text))
 
-        rows.append({"id": identifier, "embedding": embedding.tolist()})
+    for start in range(0, len(pending), batch_size):
+        batch = pending[start : start + batch_size]
+        try:
+            inputs = tokenize_texts(tokenizer, [text for _, text in batch])
+        except Exception:  # noqa: BLE001
+            tokenizable = []
+            for identifier, text in batch:
+                try:
+                    tokenize_texts(tokenizer, [text])
+                    tokenizable.append((identifier, text))
+                except Exception as exc:  # noqa: BLE001
+                    reason = str(exc).splitlines()[0]
+                    skipped_rows.append(
+                        {
+                            "id": identifier,
+                            "reason": f"tokenizer-error: {reason}",
+                        }
+                    )
+                    sys.stderr.write(
+                        f"WARNING Skipping row {identifier}: unable to tokenize ({reason})\n"
+                    )
+                    sys.stderr.flush()
+                    emit_progress(
+                        {
+                            "phase": "embedding",
+                            "message": f"Skipped row {identifier}: unable to tokenize",
+                            "processedRows": len(rows),
+                            "totalRows": total,
+                            "elapsedSeconds": time.time() - start_time,
+                            "skippedRows": len(skipped_rows),
+                        }
+                    )
+            batch = tokenizable
+            if not batch:
+                continue
+            inputs = tokenize_texts(tokenizer, [text for _, text in batch])
+
+        embeddings = embed_inputs(model, device, inputs)
+        for (identifier, _), embedding in zip(batch, embeddings):
+            rows.append({"id": identifier, "embedding": embedding})
 
         processed = len(rows)
         elapsed = time.time() - start_time
@@ -0,0 +1,5 @@
+fn main() {
+    std::process::exit(dbbs_faculty_match_lib::run_cli(
+        std::env::args().skip(1).collect(),
+    ))
+}
@@ -0,0 +1,317 @@
+use super::{
+    emit_progress_from_line, perform_matching_request, write_file_atomically, AppError,
+    EmbeddingIndexCache, GeneratedSpreadsheet, MatchingHost, SidecarOutput, SubmissionPayload,
+    SubmissionResponse,
+};
+use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use tauri_plugin_shell::process::TerminatedPayload;
+use uuid::Uuid;
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_INVALID_INPUT: i32 = 2;
+const EXIT_HELPER_FAILURE: i32 = 3;
+const EXIT_SUCCESS_WITH_WARNINGS: i32 = 4;
+
+const USAGE: &str = "Usage: dbbs-faculty-match-cli --config <submission.json> --data-dir <directory> [--output <path>] [--helper <embedding-helper>]
+
+Runs a faculty matching request without opening the application window.
+
+  --config    JSON file with the same fields as the application's submission payload
+  --data-dir  Directory holding the faculty dataset, embeddings, and settings
+  --output    Where to write the results workbook (spreadsheet and directory runs) or
+              the results JSON (prompt and document runs)
+  --helper    Path to the embedding helper executable (defaults to the one installed
+              next to this program)
+
+Exit codes: 0 success, 1 failure, 2 invalid input or missing data, 3 embedding helper
+failure, 4 success with warnings.";
+
+struct CliOptions {
+    config: PathBuf,
+    data_dir: PathBuf,
+    output: Option<PathBuf>,
+    helper: Option<PathBuf>,
+}
+
+struct CliHost {
+    data_dir: PathBuf,
+    helper: PathBuf,
+    last_progress: Mutex<String>,
+    embedding_index_cache: EmbeddingIndexCache,
+}
+
+impl MatchingHost for CliHost {
+    fn data_directory(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir.clone())
+    }
+
+    fn app_version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    fn emit_progress(&self, _event: &str, payload: serde_json::Value) {
+        let phase = payload
+            .get("phase")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default();
+        let line = match payload.get("message").and_then(serde_json::Value::as_str) {
+            Some(message) => format!("[{phase}] {message}"),
+            None => format!("[{phase}]"),
+        };
+
+        if let Ok(mut last) = self.last_progress.lock() {
+            if *last != line {
+                eprintln!("{line}");
+                *last = line;
+            }
+        }
+    }
+
+    fn exchange_with_embedding_helper(
+        &self,
+        input: &[u8],
+        total_rows: usize,
+        request_id: Option<&str>,
+    ) -> Result<SidecarOutput, AppError> {
+        let mut child = Command::new(&self.helper)
+            .env("HF_HUB_DISABLE_PROGRESS_BARS", "1")
+            .env("TOKENIZERS_PARALLELISM", "false")
+            .env("PYTHONUTF8", "1")
+            .env("PYTHONUNBUFFERED", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                AppError::helper_failure(format!(
+                    "Unable to launch the embedding helper '{}': {err}",
+                    self.helper.display()
+                ))
+            })?;
+
+        let (Some(mut stdin), Some(mut stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(AppError::helper_failure(
+                "Unable to connect to the embedding helper.",
+            ));
+        };
+
+        stdin
+            .write_all(input)
+            .and_then(|_| stdin.write_all(b"\n{\"command\":\"shutdown\"}\n"))
+            .map_err(|err| {
+                AppError::helper_failure(format!(
+                    "Unable to send data to the embedding helper: {err}"
+                ))
+            })?;
+        drop(stdin);
+
+        let stdout_reader = std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            stdout.read_to_end(&mut buffer).map(|_| buffer)
+        });
+
+        let mut stderr_buffer = Vec::new();
+        for line in BufReader::new(stderr).split(b'\n') {
+            let line = line.map_err(|err| {
+                AppError::helper_failure(format!("Unable to read embedding helper output: {err}"))
+            })?;
+            if !emit_progress_from_line(self, total_rows, &line, request_id) {
+                stderr_buffer.extend_from_slice(&line);
+                stderr_buffer.push(b'\n');
+            }
+        }
+
+        let stdout = stdout_reader
+            .join()
+            .map_err(|_| AppError::helper_failure("Unable to read embedding helper output."))?
+            .map_err(|err| {
+                AppError::helper_failure(format!("Unable to read embedding helper output: {err}"))
+            })?;
+        let status = child.wait().map_err(|err| {
+            AppError::helper_failure(format!("Unable to wait for the embedding helper: {err}"))
+        })?;
+
+        let response = stdout
+            .split(|byte| *byte == b'\n')
+            .find(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .unwrap_or_default()
+            .to_vec();
+
+        Ok(SidecarOutput {
+            stdout: response,
+            stderr: stderr_buffer,
+            termination: (!status.success()).then_some(TerminatedPayload {
+                code: status.code(),
+                signal: None,
+            }),
+        })
+    }
+
+    fn embedding_index_cache(&self) -> Option<&EmbeddingIndexCache> {
+        Some(&self.embedding_index_cache)
+    }
+}
+
+pub fn run_cli(args: Vec<String>) -> i32 {
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return EXIT_SUCCESS;
+    }
+
+    let options = match parse_cli_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            return EXIT_INVALID_INPUT;
+        }
+    };
+
+    match run_cli_request(&options) {
+        Ok(response) => {
+            println!("{}", response.summary);
+            for warning in &response.warnings {
+                eprintln!("warning: {warning}");
+            }
+            if response.warnings.is_empty() {
+                EXIT_SUCCESS
+            } else {
+                EXIT_SUCCESS_WITH_WARNINGS
+            }
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            match err {
+                AppError::InvalidInput { .. }
+                | AppError::MissingDataset { .. }
+                | AppError::MissingEmbeddings { .. }
+                | AppError::StaleEmbeddings { .. } => EXIT_INVALID_INPUT,
+                AppError::HelperFailure { stderr, .. } => {
+                    if !stderr.is_empty() {
+                        eprintln!("{stderr}");
+                    }
+                    EXIT_HELPER_FAILURE
+                }
+                _ => EXIT_FAILURE,
+            }
+        }
+    }
+}
+
+fn parse_cli_args(args: &[String]) -> Result<CliOptions, String> {
+    let mut config = None;
+    let mut data_dir = None;
+    let mut output = None;
+    let mut helper = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let target = match arg.as_str() {
+            "--config" => &mut config,
+            "--data-dir" => &mut data_dir,
+            "--output" => &mut output,
+            "--helper" => &mut helper,
+            other => return Err(format!("Unknown argument '{other}'.")),
+        };
+        let value = iter
+            .next()
+            .filter(|value| !value.trim().is_empty())
+            .ok_or_else(|| format!("Provide a value for {arg}."))?;
+        *target = Some(PathBuf::from(value));
+    }
+
+    Ok(CliOptions {
+        config: config.ok_or("Provide a submission config with --config.")?,
+        data_dir: data_dir.ok_or("Provide the application data directory with --data-dir.")?,
+        output,
+        helper,
+    })
+}
+
+fn run_cli_request(options: &CliOptions) -> Result<SubmissionResponse, AppError> {
+    let config = fs::read(&options.config).map_err(|err| {
+        AppError::io(
+            &options.config,
+            format!("Unable to read the submission config: {err}"),
+        )
+    })?;
+    let mut payload: SubmissionPayload = serde_json::from_slice(&config).map_err(|err| {
+        AppError::invalid_input(
+            "config",
+            format!("Unable to parse the submission config: {err}"),
+        )
+    })?;
+
+    let writes_workbook = matches!(
+        payload.task_type,
+        super::TaskType::Spreadsheet | super::TaskType::Directory
+    );
+    if let Some(output) = options.output.as_deref().filter(|_| writes_workbook) {
+        // `--output` names the destination explicitly, so it replaces an existing file
+        // just as the JSON results written below do.
+        payload.output_path = Some(output.to_string_lossy().into_owned());
+        payload.overwrite_output = true;
+    }
+
+    let host = CliHost {
+        data_dir: options.data_dir.clone(),
+        helper: options
+            .helper
+            .clone()
+            .unwrap_or_else(default_embedding_helper_path),
+        last_progress: Mutex::new(String::new()),
+        embedding_index_cache: EmbeddingIndexCache::default(),
+    };
+    let request_id = Uuid::new_v4().to_string();
+    let response = perform_matching_request(&host, payload, &request_id)?;
+
+    if let Some(output) = options.output.as_deref() {
+        if writes_workbook {
+            if let Some(json_export) = &response.json_export {
+                write_generated_output(json_export, &output.with_extension("json"))?;
+            }
+        } else {
+            let json = serde_json::to_vec_pretty(&response)
+                .map_err(|err| format!("Unable to serialize the match results: {err}"))?;
+            write_file_atomically(output, &json).map_err(|err| {
+                AppError::io(output, format!("Unable to write the match results: {err}"))
+            })?;
+            eprintln!("Wrote the match results to {}.", output.display());
+        }
+    }
+
+    Ok(response)
+}
+
+fn write_generated_output(file: &GeneratedSpreadsheet, destination: &Path) -> Result<(), AppError> {
+    let data = if file.encoding.as_deref() == Some("base64") {
+        Base64Engine
+            .decode(file.content.as_bytes())
+            .map_err(|err| format!("Unable to decode {}: {err}", file.filename))?
+    } else {
+        file.content.as_bytes().to_vec()
+    };
+    write_file_atomically(destination, &data).map_err(|err| {
+        AppError::io(
+            destination,
+            format!("Unable to write {}: {err}", file.filename),
+        )
+    })?;
+    eprintln!("Wrote {} to {}.", file.filename, destination.display());
+    Ok(())
+}
+
+fn default_embedding_helper_path() -> PathBuf {
+    let name = format!("embedding-helper{}", std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(|directory| directory.join(&name)))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
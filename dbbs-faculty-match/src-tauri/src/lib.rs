@@ -1,24 +1,36 @@
 use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
-use calamine::{open_workbook_auto, DataType, Reader};
+use calamine::{open_workbook_auto, open_workbook_auto_from_rs, DataType, Reader, Sheets};
 use chrono::{DateTime, Utc};
 use docx_rs::{
-    read_docx, DocumentChild, Insert, InsertChild, Paragraph, ParagraphChild, Run, RunChild,
-    StructuredDataTag, StructuredDataTagChild, Table, TableCellContent, TableChild, TableRowChild,
+    read_docx, DocumentChild, Docx, Insert, InsertChild, Paragraph, ParagraphChild, Run, RunChild,
+    StructuredDataTag, StructuredDataTagChild, Table, TableCell, TableCellContent, TableChild,
+    TableRow, TableRowChild,
 };
+use faculty_match_core::identifiers::{
+    build_email_domain_aliases, fold_identifier_characters, normalize_email_value,
+    normalize_identifier_label, normalize_identifier_value,
+};
+use faculty_match_core::similarity::{cosine_similarity, dot_and_norms, dot_product};
 use pdf_extract::extract_text_from_mem;
+use rayon::prelude::*;
 use rtf_parser::RtfDocument;
-use rust_xlsxwriter::{Format, Workbook};
+use rust_xlsxwriter::{
+    ConditionalFormatCell, ConditionalFormatCellRule, DataValidation, DataValidationRule, Format,
+    FormatAlign, Note, Url, Workbook, Worksheet,
+};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::char;
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
 use std::fs;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Cursor};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
-use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Instant, SystemTime};
 use tauri::{Emitter, Manager};
 use tauri_plugin_shell::{
@@ -26,6 +38,13 @@ use tauri_plugin_shell::{
     ShellExt,
 };
 use tokio::sync::mpsc::Receiver;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+mod cli;
+
+pub use cli::run_cli;
 
 const FACULTY_DATASET_BASENAME: &str = "faculty_dataset";
 const FACULTY_DATASET_DEFAULT_EXTENSION: &str = "tsv";
@@ -34,11 +53,216 @@ const DEFAULT_FACULTY_DATASET: &[u8] = include_bytes!("../assets/default_faculty
 const FACULTY_DATASET_METADATA_NAME: &str = "faculty_dataset_metadata.json";
 const FACULTY_DATASET_SOURCE_NAME: &str = "faculty_dataset_source.txt";
 const FACULTY_EMBEDDINGS_NAME: &str = "faculty_embeddings.json";
+const FACULTY_EMBEDDINGS_SUMMARY_NAME: &str = "faculty_embeddings_summary.json";
+const FACULTY_EMBEDDING_MODELS_DIRECTORY: &str = "faculty_embedding_models";
+const ROSTER_MAPPING_PRESETS_NAME: &str = "roster_mapping_presets.json";
+const APP_SETTINGS_NAME: &str = "app_settings.json";
+const APP_SETTINGS_VERSION: u32 = 1;
+const EMBEDDING_BATCH_SIZE_MAX: usize = 512;
+const LOG_DIRECTORY: &str = "logs";
+const LOG_FILE_NAME: &str = "dbbs-faculty-match.log";
+const LOG_FILE_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const LOG_RECENT_LINES_DEFAULT: usize = 200;
+const LOG_RECENT_LINES_MAX: usize = 5_000;
+const DIAGNOSTICS_FILE_NAME: &str = "diagnostics.json";
+const AUDIT_LOG_NAME: &str = "audit.log";
+const AUDIT_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const AUDIT_LOG_DEFAULT_LIMIT: usize = 100;
+const DIAGNOSTICS_LOG_NAME: &str = "recent.log";
+const RUN_HISTORY_DIRECTORY: &str = "run_history";
+const RUN_HISTORY_INDEX_NAME: &str = "index.json";
+const RUN_HISTORY_LIMIT: usize = 20;
+const RUN_HISTORY_DISABLE_ENV: &str = "DBBS_DISABLE_RUN_HISTORY";
+const RUN_EMBEDDINGS_SUFFIX: &str = "_embeddings.json";
+const RUN_EMBEDDINGS_VERSION: u32 = 1;
+const OUTPUT_REPORT_SUFFIX: &str = "_report.pdf";
+const OUTPUT_BUNDLE_SUFFIX: &str = "_bundle.zip";
+const OUTPUT_STUDENT_REPORTS_SUFFIX: &str = "_student_reports.zip";
+const EMBEDDING_EXPORT_METADATA_SUFFIX: &str = ".metadata.json";
+const EMBEDDING_EXPORT_IDENTIFIERS_SUFFIX: &str = "_identifiers.csv";
+const EMBEDDING_IMPORT_MISALIGNED_PERCENT: f64 = 10.0;
+const EMBEDDING_IMPORT_ROW_ERROR_LIMIT: usize = 10;
+const FACULTY_ID_HASH_CHARACTERS: usize = 12;
 const DEFAULT_FACULTY_EMBEDDINGS: &[u8] =
     include_bytes!("../assets/default_faculty_embeddings.json");
 const DEFAULT_EMBEDDING_MODEL: &str = "NeuML/pubmedbert-base-embeddings";
 const FACULTY_EMBEDDING_PROGRESS_EVENT: &str = "faculty-embedding-progress";
+const MATCHING_PROGRESS_EVENT: &str = "matching-progress";
 const FACULTY_EMBEDDING_STANDBY_MESSAGE: &str = "Embedding helper ready.";
+const RESULTS_JSON_SCHEMA: &str = "dbbs-faculty-match/results";
+const RESULTS_JSON_SCHEMA_VERSION: u32 = 1;
+const EXPORT_BUNDLE_SCHEMA: &str = "dbbs-faculty-match/bundle";
+const EXPORT_BUNDLE_SCHEMA_VERSION: u32 = 1;
+const PROMPT_TEXT_COLUMN_MAX_CHARS: usize = 32_000;
+/// Excel refuses to open a workbook with a cell longer than this many characters.
+const EXCEL_CELL_MAX_CHARS: usize = 32_767;
+const PROMPT_COLUMN_VALUES_DEFAULT_CHARS: usize = 500;
+const SIMILARITY_HISTOGRAM_BUCKETS: usize = 10;
+// Roughly four characters per token within the embedding model's 512-token window.
+const PROMPT_MAX_CHARACTERS_DEFAULT: usize = 2_000;
+const PROMPT_MAX_CHARACTERS_MIN: usize = 100;
+const PROMPT_TRUNCATION_MARKER: &str = " […]";
+const PROMPT_PREVIEW_CHARACTERS_DEFAULT: usize = 280;
+const PROMPT_PREVIEW_CHARACTERS_MIN: usize = 40;
+const PROMPT_PREVIEW_CHARACTERS_MAX: usize = 5_000;
+const FACULTY_TEXT_MAX_CHARACTERS_DEFAULT: usize = 4_000;
+const FACULTY_TEXT_MAX_CHARACTERS_MIN: usize = 100;
+const STUDENT_WORKSHEET_LIMIT: usize = 200;
+const WORKSHEET_NAME_MAX_CHARS: usize = 31;
+const WORKSHEET_COLUMN_MIN_WIDTH: usize = 8;
+const WORKSHEET_COLUMN_MAX_WIDTH: usize = 50;
+const WORKSHEET_MAX_ROWS: usize = 1_048_576;
+// Above this many matches the Matches worksheets flush each row to a temporary file
+// instead of holding every cell in memory until the workbook is saved. Only the writer
+// is constant-memory: every run still collects all of its match entries first, since
+// the summary sheets, statistics and mail merge each take another pass over them.
+const MATCHES_CONSTANT_MEMORY_THRESHOLD: usize = 50_000;
+const WORKSHEET_MAX_COLUMNS: usize = 16_384;
+// Excel ignores hyperlinks past this many on one worksheet, so later rows get plain text.
+const EXCEL_HYPERLINK_LIMIT: u32 = 65_530;
+const DOCUMENT_PATH_HEADER: &str = "Document path";
+const STUDENT_REPORT_PROMPT_MAX_CHARS: usize = 1_500;
+const STUDENT_REPORT_FILENAME_MAX_CHARS: usize = 80;
+const PDF_PAGE_WIDTH: f32 = 612.0;
+const PDF_PAGE_HEIGHT: f32 = 792.0;
+const PDF_PAGE_MARGIN: f32 = 54.0;
+const PDF_REPORT_TOP_FACULTY: usize = 10;
+const PDF_REPORT_WARNING_LIMIT: usize = 5;
+const DEFAULT_MAIL_MERGE_TEMPLATE: &str =
+    "Dear {faculty_name},\n\nYou have been recommended as a reviewer for the following students:\n{student_list}";
+const FILENAME_PATTERN_PLACEHOLDERS: [&str; 6] =
+    ["date", "time", "task", "scope", "programs", "model"];
+const FILENAME_PATTERN_MAX_CHARS: usize = 120;
+const DEFAULT_PREVIEW_ROW_LIMIT: usize = 20;
+const MAX_PREVIEW_ROW_LIMIT: usize = 1_000;
+const SIMILARITY_LOW_THRESHOLD_PERCENT: f32 = 25.0;
+const SIMILARITY_HIGH_THRESHOLD_PERCENT: f32 = 45.0;
+const SIMILAR_PROMPT_THRESHOLD_PERCENT: f32 = 97.0;
+const SIMILAR_PROMPT_PAIR_LIMIT: usize = 500;
+const SIMILAR_PROMPT_WARNING_LIMIT: usize = 10;
+const BOILERPLATE_ROW_PERCENT: f32 = 50.0;
+const BOILERPLATE_MIN_CHARS: usize = 40;
+const BOILERPLATE_MIN_REMAINING_CHARS: usize = 20;
+const BOILERPLATE_SNIPPET_CHARS: usize = 120;
+const INLINE_CONTENT_MAX_BYTES: usize = 25 * 1024 * 1024;
+const SEARCH_FACULTY_DEFAULT_LIMIT: usize = 10;
+const SEARCH_FACULTY_MAX_LIMIT: usize = 100;
+const EVALUATION_DEFAULT_K_VALUES: &[usize] = &[1, 3, 5, 10];
+const COMPARE_MODELS_DEFAULT_K: usize = 5;
+const COMPARE_MODELS_CHUNK_SIZE: usize = 64;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum AppError {
+    InvalidInput {
+        field: String,
+        message: String,
+    },
+    MissingDataset {
+        message: String,
+    },
+    MissingEmbeddings {
+        message: String,
+    },
+    StaleEmbeddings {
+        message: String,
+    },
+    HelperFailure {
+        code: Option<i32>,
+        stderr: String,
+        message: String,
+    },
+    Io {
+        path: String,
+        message: String,
+    },
+    FileExists {
+        path: String,
+        message: String,
+    },
+    Busy {
+        activity: HelperActivity,
+        request_id: Option<String>,
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl AppError {
+    fn invalid_input(field: &str, message: impl Into<String>) -> Self {
+        Self::InvalidInput {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn helper_failure(message: impl Into<String>) -> Self {
+        Self::HelperFailure {
+            code: None,
+            stderr: String::new(),
+            message: message.into(),
+        }
+    }
+
+    fn io(path: &Path, message: impl Into<String>) -> Self {
+        Self::Io {
+            path: path.to_string_lossy().into_owned(),
+            message: message.into(),
+        }
+    }
+
+    fn file_exists(path: &Path) -> Self {
+        Self::FileExists {
+            path: path.to_string_lossy().into_owned(),
+            message: format!(
+                "'{}' already exists. Choose another name or allow overwriting.",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidInput { message, .. }
+            | Self::MissingDataset { message }
+            | Self::MissingEmbeddings { message }
+            | Self::StaleEmbeddings { message }
+            | Self::HelperFailure { message, .. }
+            | Self::Io { message, .. }
+            | Self::FileExists { message, .. }
+            | Self::Busy { message, .. }
+            | Self::Other { message } => formatter.write_str(message),
+        }
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::Other {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -49,15 +273,113 @@ enum TaskType {
     Directory,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 enum FacultyScope {
+    #[default]
     All,
     Program,
     Custom,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum OutputFormat {
+    #[default]
+    Xlsx,
+    Csv,
+    Tsv,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+enum StorageCategory {
+    Dataset,
+    Embeddings,
+    ModelEmbeddings,
+    PromptCache,
+    RunHistory,
+    Logs,
+    Backups,
+    Settings,
+    Other,
+}
+
+impl StorageCategory {
+    fn removable(self) -> bool {
+        matches!(
+            self,
+            Self::ModelEmbeddings | Self::PromptCache | Self::RunHistory | Self::Backups
+        )
+    }
+}
+
+const STORAGE_ARTIFACTS: &[(&str, StorageCategory)] = &[
+    (FACULTY_DATASET_METADATA_NAME, StorageCategory::Dataset),
+    (FACULTY_DATASET_SOURCE_NAME, StorageCategory::Dataset),
+    (FACULTY_EMBEDDINGS_NAME, StorageCategory::Embeddings),
+    (FACULTY_EMBEDDINGS_SUMMARY_NAME, StorageCategory::Embeddings),
+    (
+        FACULTY_EMBEDDING_MODELS_DIRECTORY,
+        StorageCategory::ModelEmbeddings,
+    ),
+    (RUN_HISTORY_DIRECTORY, StorageCategory::RunHistory),
+    (LOG_DIRECTORY, StorageCategory::Logs),
+    (AUDIT_LOG_NAME, StorageCategory::Logs),
+    (APP_SETTINGS_NAME, StorageCategory::Settings),
+    (ROSTER_MAPPING_PRESETS_NAME, StorageCategory::Settings),
+];
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum EmbeddingExportFormat {
+    Csv,
+    Npy,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum ReviewerInputMode {
+    #[default]
+    Number,
+    Checkbox,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum SummaryMode {
+    #[default]
+    Formulas,
+    Static,
+    LiveCounts,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum MatchesSort {
+    #[default]
+    Student,
+    Faculty,
+    Similarity,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum CustomFacultyMode {
+    #[default]
+    Include,
+    Exclude,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum RosterMatchMode {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct SubmissionPayload {
     task_type: TaskType,
@@ -66,15 +388,27 @@ struct SubmissionPayload {
     #[serde(default)]
     document_path: Option<String>,
     #[serde(default)]
+    document_content: Option<String>,
+    #[serde(default)]
+    document_filename: Option<String>,
+    #[serde(default)]
     spreadsheet_path: Option<String>,
     #[serde(default)]
+    spreadsheet_content: Option<String>,
+    #[serde(default)]
+    spreadsheet_filename: Option<String>,
+    #[serde(default)]
     directory_path: Option<String>,
-    faculty_scope: FacultyScope,
+    #[serde(default)]
+    faculty_scope: Option<FacultyScope>,
     #[serde(default)]
     program_filters: Vec<String>,
     #[serde(default)]
     custom_faculty_path: Option<String>,
-    faculty_recs_per_student: u32,
+    #[serde(default)]
+    custom_faculty_mode: CustomFacultyMode,
+    #[serde(default)]
+    faculty_recs_per_student: Option<u32>,
     #[serde(default)]
     spreadsheet_prompt_columns: Vec<String>,
     #[serde(default)]
@@ -83,6 +417,187 @@ struct SubmissionPayload {
     faculty_roster_column_map: HashMap<String, String>,
     #[serde(default)]
     faculty_roster_warnings: Vec<String>,
+    #[serde(default)]
+    roster_fuzzy_matching: bool,
+    #[serde(default)]
+    roster_match_mode: RosterMatchMode,
+    #[serde(default)]
+    roster_value_identifier: Option<String>,
+    #[serde(default)]
+    strip_identifier_diacritics: Option<bool>,
+    #[serde(default)]
+    roster_mapping_preset: Option<String>,
+    #[serde(default)]
+    roster_text_column: Option<String>,
+    #[serde(default)]
+    output_format: OutputFormat,
+    #[serde(default)]
+    include_json_export: bool,
+    #[serde(default)]
+    include_prompt_text_column: bool,
+    #[serde(default)]
+    include_faculty_text_column: bool,
+    #[serde(default)]
+    include_faculty_id_column: bool,
+    #[serde(default)]
+    include_raw_cosine_column: Option<bool>,
+    #[serde(default)]
+    reviewer_input_mode: ReviewerInputMode,
+    #[serde(default)]
+    summary_mode: SummaryMode,
+    #[serde(default)]
+    matches_sort: MatchesSort,
+    #[serde(default)]
+    prompt_text_column_max_chars: Option<usize>,
+    #[serde(default)]
+    include_prompt_column_values: bool,
+    #[serde(default)]
+    prompt_column_values_max_chars: Option<usize>,
+    #[serde(default)]
+    sheet_per_student: bool,
+    #[serde(default)]
+    preview_row_limit: Option<usize>,
+    #[serde(default)]
+    output_path: Option<String>,
+    /// Allows the run to replace files already at the output path and its companions.
+    #[serde(default)]
+    overwrite_output: bool,
+    #[serde(default)]
+    generate_student_reports: bool,
+    #[serde(default)]
+    include_pdf_report: bool,
+    #[serde(default)]
+    mail_merge_template: Option<String>,
+    #[serde(default)]
+    export_bundle: bool,
+    #[serde(default)]
+    filename_pattern: Option<String>,
+    #[serde(default)]
+    similarity_low_threshold: Option<f32>,
+    #[serde(default)]
+    similarity_high_threshold: Option<f32>,
+    #[serde(default)]
+    email_domain_aliases: HashMap<String, String>,
+    #[serde(default)]
+    save_prompt_embeddings: bool,
+    #[serde(default)]
+    detect_similar_prompts: bool,
+    #[serde(default)]
+    similar_prompt_threshold: Option<f32>,
+    #[serde(default)]
+    scrub_personal_information: bool,
+    #[serde(default)]
+    strip_boilerplate: bool,
+    #[serde(default)]
+    boilerplate_text: Option<String>,
+    #[serde(default)]
+    boilerplate_row_percent: Option<f32>,
+    #[serde(default)]
+    max_prompt_characters: Option<usize>,
+    #[serde(default)]
+    prompt_preview_characters: Option<usize>,
+    #[serde(default, alias = "studentIdentifier")]
+    student_label: Option<String>,
+    #[serde(default)]
+    prompt_texts: Vec<LabeledPrompt>,
+    #[serde(default)]
+    model_override: Option<String>,
+    #[serde(default)]
+    attention_threshold: Option<f32>,
+}
+
+/// One of several pasted prompts in a batched Prompt task.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LabeledPrompt {
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum LogLevelSetting {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogLevelSetting {
+    fn level_filter(self) -> log::LevelFilter {
+        match self {
+            Self::Error => log::LevelFilter::Error,
+            Self::Warn => log::LevelFilter::Warn,
+            Self::Info => log::LevelFilter::Info,
+            Self::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+struct AppSettings {
+    version: u32,
+    faculty_recs_per_student: u32,
+    faculty_scope: FacultyScope,
+    program_filters: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similarity_low_threshold: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similarity_high_threshold: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview_row_limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename_pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding_batch_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding_device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_prompt_characters: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt_preview_characters: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faculty_text_max_characters: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding_precision: Option<EmbeddingPrecision>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attention_threshold: Option<f32>,
+    /// Whether identifier matching ignores accents ("García" matches "Garcia"). On
+    /// unless turned off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strip_identifier_diacritics: Option<bool>,
+    log_level: LogLevelSetting,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            version: APP_SETTINGS_VERSION,
+            faculty_recs_per_student: 1,
+            faculty_scope: FacultyScope::All,
+            program_filters: Vec::new(),
+            similarity_low_threshold: None,
+            similarity_high_threshold: None,
+            preview_row_limit: None,
+            filename_pattern: None,
+            embedding_model: None,
+            embedding_batch_size: None,
+            embedding_device: None,
+            max_prompt_characters: None,
+            prompt_preview_characters: None,
+            faculty_text_max_characters: None,
+            embedding_precision: None,
+            attention_threshold: None,
+            strip_identifier_diacritics: None,
+            log_level: LogLevelSetting::Info,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -99,22 +614,92 @@ impl PathConfirmation {
             path: path.to_string_lossy().into_owned(),
         }
     }
+
+    fn inline(label: &str, filename: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            path: format!("{filename} (uploaded content)"),
+        }
+    }
+}
+
+enum SpreadsheetSource {
+    Path(PathBuf),
+    Inline { filename: String, data: Vec<u8> },
+}
+
+impl SpreadsheetSource {
+    fn read_rows(&self) -> Result<NumberedSpreadsheetRows, String> {
+        match self {
+            Self::Path(path) => read_numbered_spreadsheet_with_limit(path, None),
+            Self::Inline { filename, data } => read_spreadsheet_bytes(data, filename, None),
+        }
+    }
+}
+
+fn decode_inline_content(content: &str, field: &str, label: &str) -> Result<Vec<u8>, AppError> {
+    let content = content.trim();
+    if content.len() / 4 * 3 > INLINE_CONTENT_MAX_BYTES {
+        return Err(AppError::invalid_input(
+            field,
+            format!(
+                "The uploaded {label} is larger than the {} limit for uploaded content. Provide a file path instead.",
+                format_file_size(INLINE_CONTENT_MAX_BYTES as u64)
+            ),
+        ));
+    }
+
+    let data = Base64Engine.decode(content.as_bytes()).map_err(|err| {
+        AppError::invalid_input(
+            field,
+            format!("Unable to decode the uploaded {label}: {err}"),
+        )
+    })?;
+    if data.is_empty() {
+        return Err(AppError::invalid_input(
+            field,
+            format!("The uploaded {label} is empty."),
+        ));
+    }
+    Ok(data)
+}
+
+fn inline_content_filename(filename: Option<&str>, default_name: &str) -> String {
+    filename
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .and_then(|name| Path::new(name).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| default_name.to_string())
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct SubmissionDetails {
+    request_id: String,
     task_type: TaskType,
     faculty_scope: FacultyScope,
     validated_paths: Vec<PathConfirmation>,
     program_filters: Vec<String>,
     custom_faculty_path: Option<String>,
+    custom_faculty_mode: CustomFacultyMode,
     recommendations_per_student: u32,
     prompt_preview: Option<String>,
     spreadsheet_prompt_columns: Vec<String>,
     spreadsheet_identifier_columns: Vec<String>,
     faculty_roster_column_map: HashMap<String, String>,
     faculty_roster_warnings: Vec<String>,
+    roster_fuzzy_matching: bool,
+    roster_match_mode: RosterMatchMode,
+    roster_value_identifier: Option<String>,
+    roster_mapping_preset: Option<RosterMappingPresetReference>,
+    roster_faculty_count: Option<usize>,
+    faculty_in_scope: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student_label: Option<String>,
+    embedding_model: Option<String>,
+    model_overridden: bool,
+    output_filename: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -128,6 +713,108 @@ struct SubmissionResponse {
     directory_results: Option<DirectoryMatchResults>,
     #[serde(skip_serializing_if = "Option::is_none")]
     spreadsheet_results: Option<SpreadsheetMatchResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roster_diagnostics: Option<RosterDiagnostics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faculty_exclusions: Option<FacultyExclusionDiagnostics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_export: Option<GeneratedSpreadsheet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pdf_report: Option<GeneratedSpreadsheet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bundle: Option<GeneratedSpreadsheet>,
+    statistics: RunStatistics,
+}
+
+/// How well a run matched overall, computed from the finished result lists.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct RunStatistics {
+    students_matched: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best_similarity: Option<SimilaritySummary>,
+    best_similarity_histogram: Vec<SimilarityBucket>,
+    distinct_faculty: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faculty_in_scope: Option<usize>,
+    /// Fraction of in-scope faculty recommended to at least one student.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope_coverage: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct SimilaritySummary {
+    mean: f32,
+    median: f32,
+    min: f32,
+    max: f32,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct SimilarityBucket {
+    lower_percent: u32,
+    upper_percent: u32,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportBundleManifest<'a> {
+    schema: &'static str,
+    schema_version: u32,
+    created_at: String,
+    run_info: &'a RunInfo,
+    artifacts: Vec<ExportBundleArtifact>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportBundleArtifact {
+    name: String,
+    description: String,
+    mime_type: String,
+}
+
+/// Versioned wrapper for the JSON results export. Faculty and prompt
+/// embeddings are never included, only the similarities derived from them.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubmissionJsonExport<'a> {
+    schema: &'static str,
+    schema_version: u32,
+    exported_at: String,
+    run_info: &'a RunInfo,
+    results: &'a SubmissionResponse,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RunInfo {
+    generated_at: String,
+    app_version: String,
+    task_type: TaskType,
+    faculty_scope: FacultyScope,
+    program_filters: Vec<String>,
+    custom_faculty_path: Option<String>,
+    custom_faculty_mode: CustomFacultyMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roster_faculty_count: Option<usize>,
+    recommendations_per_student: u32,
+    embedding_model: Option<String>,
+    embeddings_generated_at: Option<String>,
+    dataset_path: Option<String>,
+    dataset_source_path: Option<String>,
+    dataset_row_count: Option<usize>,
+    similarity_low_threshold: f32,
+    similarity_high_threshold: f32,
+    attention_threshold: f32,
+    summary_mode: SummaryMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt_text_max_chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student_worksheet_limit: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -137,6 +824,10 @@ struct SpreadsheetPreview {
     rows: Vec<Vec<String>>,
     suggested_prompt_columns: Vec<usize>,
     suggested_identifier_columns: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delimiter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delimiter_consistency: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -153,7 +844,12 @@ struct FacultyDatasetPreviewResponse {
 struct FacultyRosterPreviewResponse {
     preview: SpreadsheetPreview,
     suggested_identifier_matches: HashMap<String, Option<usize>>,
+    suggested_identifier_overlaps: HashMap<String, f32>,
     warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_summary: Option<RosterMatchSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_list_identifier: Option<String>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -162,6 +858,8 @@ struct FacultyDatasetStatus {
     path: Option<String>,
     canonical_path: Option<String>,
     source_path: Option<String>,
+    source_changed: bool,
+    source_missing: bool,
     last_modified: Option<String>,
     row_count: Option<usize>,
     column_count: Option<usize>,
@@ -180,6 +878,9 @@ struct FacultyDatasetAnalysis {
     identifier_columns: Vec<String>,
     program_columns: Vec<String>,
     available_programs: Vec<String>,
+    /// Spreadsheet row numbers that have embedding text but no identifier values.
+    #[serde(default)]
+    blank_identifier_rows: Vec<usize>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -208,88 +909,579 @@ struct FacultyProgramMembership {
     programs: Vec<String>,
 }
 
-#[tauri::command]
-async fn submit_matching_request(
-    app_handle: tauri::AppHandle,
-    payload: SubmissionPayload,
-) -> Result<SubmissionResponse, String> {
-    tauri::async_runtime::spawn_blocking(move || perform_matching_request(app_handle, payload))
-        .await
-        .map_err(|err| format!("Matching task failed: {err}"))?
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ProgramFacultyCount {
+    program: String,
+    faculty_count: usize,
 }
 
-fn perform_matching_request(
-    app_handle: tauri::AppHandle,
-    payload: SubmissionPayload,
-) -> Result<SubmissionResponse, String> {
-    let SubmissionPayload {
-        task_type,
-        prompt_text,
-        document_path,
-        spreadsheet_path,
-        directory_path,
-        faculty_scope,
-        program_filters,
-        custom_faculty_path,
-        faculty_recs_per_student,
-        spreadsheet_prompt_columns,
-        spreadsheet_identifier_columns,
-        faculty_roster_column_map,
-        faculty_roster_warnings,
-    } = payload;
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AvailableProgramsResponse {
+    metadata_available: bool,
+    programs: Vec<ProgramFacultyCount>,
+}
 
-    if faculty_recs_per_student == 0 {
-        return Err("Specify at least one faculty recommendation per student.".into());
+trait MatchingHost {
+    fn data_directory(&self) -> Result<PathBuf, String>;
+    fn app_version(&self) -> String;
+    fn emit_progress(&self, event: &str, payload: serde_json::Value);
+    fn exchange_with_embedding_helper(
+        &self,
+        input: &[u8],
+        total_rows: usize,
+        request_id: Option<&str>,
+    ) -> Result<SidecarOutput, AppError>;
+    fn prompt_embeddings(&self) -> Option<&PromptEmbeddingStore> {
+        None
     }
 
-    let mut warnings = faculty_roster_warnings.clone();
-    let mut validated_paths = Vec::new();
+    fn embedding_index_cache(&self) -> Option<&EmbeddingIndexCache> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptEmbeddingMode {
+    Record,
+    Replay,
+}
+
+struct PromptEmbeddingStore {
+    mode: PromptEmbeddingMode,
+    embeddings: Mutex<HashMap<String, Vec<f32>>>,
+}
+
+impl PromptEmbeddingStore {
+    fn replay(
+        &self,
+        payload: &EmbeddingRequestPayload,
+    ) -> Result<Option<EmbeddingResponsePayload>, AppError> {
+        if self.mode != PromptEmbeddingMode::Replay {
+            return Ok(None);
+        }
+
+        let embeddings = self
+            .embeddings
+            .lock()
+            .map_err(|_| AppError::from("Unable to read the saved prompt embeddings."))?;
+        let mut rows = Vec::with_capacity(payload.texts.len());
+        for row in &payload.texts {
+            let embedding = embeddings.get(&row.text).ok_or_else(|| AppError::MissingEmbeddings {
+                message: "The saved run does not include embeddings for every prompt. Submit the run again to re-embed it.".into(),
+            })?;
+            rows.push(EmbeddingResponseRow {
+                id: row.id,
+                embedding: embedding.clone(),
+            });
+        }
+
+        Ok(Some(EmbeddingResponsePayload {
+            model: payload.model.clone(),
+            dimension: rows
+                .first()
+                .map(|row| row.embedding.len())
+                .unwrap_or_default(),
+            rows,
+        }))
+    }
+
+    fn record(&self, payload: &EmbeddingRequestPayload, response: &EmbeddingResponsePayload) {
+        if self.mode != PromptEmbeddingMode::Record {
+            return;
+        }
+
+        if let Ok(mut embeddings) = self.embeddings.lock() {
+            for row in &response.rows {
+                if let Some(request) = payload.texts.iter().find(|request| request.id == row.id) {
+                    embeddings.insert(request.text.clone(), row.embedding.clone());
+                }
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<SavedPromptEmbedding> {
+        self.embeddings
+            .lock()
+            .map(|embeddings| {
+                embeddings
+                    .iter()
+                    .map(|(text, embedding)| SavedPromptEmbedding {
+                        text: text.clone(),
+                        embedding: embedding.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+struct PromptEmbeddingHost<'a> {
+    inner: &'a dyn MatchingHost,
+    store: PromptEmbeddingStore,
+}
+
+impl<'a> PromptEmbeddingHost<'a> {
+    fn new(
+        inner: &'a dyn MatchingHost,
+        mode: PromptEmbeddingMode,
+        embeddings: HashMap<String, Vec<f32>>,
+    ) -> Self {
+        Self {
+            inner,
+            store: PromptEmbeddingStore {
+                mode,
+                embeddings: Mutex::new(embeddings),
+            },
+        }
+    }
+}
+
+impl MatchingHost for PromptEmbeddingHost<'_> {
+    fn data_directory(&self) -> Result<PathBuf, String> {
+        self.inner.data_directory()
+    }
+
+    fn app_version(&self) -> String {
+        self.inner.app_version()
+    }
+
+    fn emit_progress(&self, event: &str, payload: serde_json::Value) {
+        self.inner.emit_progress(event, payload);
+    }
+
+    fn exchange_with_embedding_helper(
+        &self,
+        input: &[u8],
+        total_rows: usize,
+        request_id: Option<&str>,
+    ) -> Result<SidecarOutput, AppError> {
+        self.inner
+            .exchange_with_embedding_helper(input, total_rows, request_id)
+    }
+
+    fn prompt_embeddings(&self) -> Option<&PromptEmbeddingStore> {
+        Some(&self.store)
+    }
+
+    fn embedding_index_cache(&self) -> Option<&EmbeddingIndexCache> {
+        self.inner.embedding_index_cache()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SavedPromptEmbedding {
+    text: String,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SavedRunEmbeddings {
+    version: u32,
+    model: String,
+    dimension: usize,
+    payload: SubmissionPayload,
+    embeddings: Vec<SavedPromptEmbedding>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct RerankParameters {
+    #[serde(default)]
+    faculty_recs_per_student: Option<u32>,
+    #[serde(default)]
+    similarity_low_threshold: Option<f32>,
+    #[serde(default)]
+    similarity_high_threshold: Option<f32>,
+    #[serde(default)]
+    faculty_scope: Option<FacultyScope>,
+    #[serde(default)]
+    program_filters: Option<Vec<String>>,
+    #[serde(default)]
+    output_path: Option<String>,
+    #[serde(default)]
+    overwrite_output: bool,
+}
+
+impl MatchingHost for tauri::AppHandle {
+    fn data_directory(&self) -> Result<PathBuf, String> {
+        self.path()
+            .app_data_dir()
+            .map_err(|err| format!("Unable to resolve the application data directory: {err}"))
+    }
+
+    fn app_version(&self) -> String {
+        self.package_info().version.to_string()
+    }
+
+    fn emit_progress(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit(event, payload);
+    }
+
+    fn exchange_with_embedding_helper(
+        &self,
+        input: &[u8],
+        total_rows: usize,
+        request_id: Option<&str>,
+    ) -> Result<SidecarOutput, AppError> {
+        exchange_with_sidecar(self, input, total_rows, request_id)
+    }
+
+    fn embedding_index_cache(&self) -> Option<&EmbeddingIndexCache> {
+        self.try_state::<EmbeddingIndexCache>()
+            .map(|state| state.inner())
+    }
+}
+
+#[tauri::command]
+async fn submit_matching_request(
+    app_handle: tauri::AppHandle,
+    payload: SubmissionPayload,
+) -> Result<SubmissionResponse, AppError> {
+    let request_id = Uuid::new_v4().to_string();
+    let run_guard = begin_helper_activity(HelperActivity::Matching, Some(&request_id))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let _run_guard = run_guard;
+        let result = perform_matching_request(&app_handle, payload, &request_id);
+        match &result {
+            Ok(_) => emit_matching_phase(&app_handle, &request_id, "complete", None),
+            Err(err) => {
+                emit_matching_phase(&app_handle, &request_id, "failed", Some(err.to_string()))
+            }
+        }
+        result
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Matching task failed: {err}")))?
+}
+
+struct PreparedSubmission {
+    warnings: WarningCollector,
+    details: SubmissionDetails,
+    output_destination: Option<PathBuf>,
+    filename_pattern: Option<String>,
+    prepared_prompt_text: Option<String>,
+    truncated_prompts: usize,
+    directory_source: Option<PathBuf>,
+    spreadsheet_source: Option<SpreadsheetSource>,
+    selected_prompt_columns: Vec<String>,
+    selected_identifier_columns: Vec<String>,
+    allowed_faculty_rows: Option<HashSet<usize>>,
+    roster_diagnostics: Option<RosterDiagnostics>,
+    ad_hoc_roster_faculty: Vec<AdHocRosterFaculty>,
+    faculty_embedding_index: Option<Arc<FacultyEmbeddingIndex>>,
+}
+
+fn prepare_submission(
+    app_handle: &dyn MatchingHost,
+    payload: &SubmissionPayload,
+    request_id: &str,
+) -> Result<PreparedSubmission, AppError> {
+    let SubmissionPayload {
+        task_type,
+        prompt_text,
+        document_path,
+        document_content,
+        document_filename,
+        spreadsheet_path,
+        spreadsheet_content,
+        spreadsheet_filename,
+        directory_path,
+        faculty_scope,
+        program_filters,
+        custom_faculty_path,
+        custom_faculty_mode,
+        faculty_recs_per_student,
+        spreadsheet_prompt_columns,
+        spreadsheet_identifier_columns,
+        mut faculty_roster_column_map,
+        faculty_roster_warnings,
+        roster_fuzzy_matching,
+        mut roster_match_mode,
+        roster_value_identifier,
+        strip_identifier_diacritics,
+        roster_mapping_preset,
+        roster_text_column,
+        output_path,
+        overwrite_output,
+        include_pdf_report,
+        export_bundle,
+        generate_student_reports,
+        filename_pattern,
+        email_domain_aliases,
+        scrub_personal_information,
+        max_prompt_characters,
+        prompt_preview_characters,
+        student_label,
+        prompt_texts,
+        model_override,
+        ..
+    } = payload.clone();
+    let prompt_preview_characters = resolve_prompt_preview_characters(prompt_preview_characters);
+    let mut student_label = student_label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+    let batched_prompts = matches!(task_type, TaskType::Prompt) && !prompt_texts.is_empty();
+    let faculty_scope = faculty_scope.unwrap_or_default();
+    let faculty_recs_per_student = faculty_recs_per_student.unwrap_or_default();
+
+    if faculty_recs_per_student == 0 {
+        return Err(AppError::invalid_input(
+            "facultyRecsPerStudent",
+            "Specify at least one faculty recommendation per student.",
+        ));
+    }
+
+    let filename_pattern = filename_pattern
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty());
+    if let Some(pattern) = filename_pattern.as_deref() {
+        validate_filename_pattern(pattern)
+            .map_err(|message| AppError::invalid_input("filenamePattern", message))?;
+    }
+
+    let mut warnings = WarningCollector::default();
+    let output_destination = match output_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        Some(path)
+            if batched_prompts
+                || matches!(task_type, TaskType::Spreadsheet | TaskType::Directory) =>
+        {
+            let destination = resolve_save_destination(path, false)
+                .map_err(|message| AppError::invalid_input("outputPath", message))?;
+            // Checked before matching so a run never stops halfway through its outputs.
+            let derived = [
+                (include_pdf_report, OUTPUT_REPORT_SUFFIX),
+                (export_bundle, OUTPUT_BUNDLE_SUFFIX),
+                (generate_student_reports, OUTPUT_STUDENT_REPORTS_SUFFIX),
+            ]
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, suffix)| derived_output_path(&destination, suffix));
+            for target in std::iter::once(destination.clone()).chain(derived) {
+                ensure_save_target_available(&target, overwrite_output)?;
+            }
+            Some(destination)
+        }
+        Some(_) => {
+            warnings.push(
+                "The output path only applies to spreadsheet and directory runs and was ignored."
+                    .into(),
+            );
+            None
+        }
+        None => None,
+    };
+    let mut validated_paths = Vec::new();
     let mut prompt_preview = None;
     let mut selected_prompt_columns = Vec::new();
     let mut selected_identifier_columns = Vec::new();
     let mut detail_identifier_columns = Vec::new();
     let mut prepared_prompt_text: Option<String> = None;
+    let mut truncated_prompts = 0usize;
     let mut directory_source: Option<PathBuf> = None;
-    let mut spreadsheet_source: Option<PathBuf> = None;
+    let mut spreadsheet_source: Option<SpreadsheetSource> = None;
     let mut detail_roster_column_map: HashMap<String, String> = HashMap::new();
-    let mut roster_warning_messages = faculty_roster_warnings;
+    // Seeded once from the roster preview; the roster check below reports into it alone.
+    let mut roster_warnings: WarningCollector = faculty_roster_warnings.into_iter().collect();
 
     match task_type {
+        // Several pasted prompts run as a small in-memory spreadsheet so they share one
+        // embedding request, cross-student rankings and the spreadsheet workbook.
+        TaskType::Prompt if batched_prompts => {
+            let mut entries: Vec<(Option<String>, String)> = Vec::new();
+            if let Some(text) = prompt_text.as_deref().map(str::trim) {
+                if !text.is_empty() {
+                    entries.push((student_label.take(), text.to_string()));
+                }
+            }
+            entries.extend(
+                prompt_texts
+                    .iter()
+                    .map(|entry| (entry.label.clone(), entry.text.trim().to_string())),
+            );
+
+            let mut rows = Vec::new();
+            for (position, (label, text)) in entries.into_iter().enumerate() {
+                let label = label
+                    .map(|label| label.trim().to_string())
+                    .filter(|label| !label.is_empty())
+                    .unwrap_or_else(|| format!("Prompt {}", position + 1));
+                if text.is_empty() {
+                    warnings.push(format!("{label} is empty and was skipped."));
+                    continue;
+                }
+                rows.push(vec![label, text]);
+            }
+            if rows.is_empty() {
+                return Err(AppError::invalid_input(
+                    "promptTexts",
+                    "Provide at least one prompt describing a student's interests.",
+                ));
+            }
+
+            let headers = vec!["Student".to_string(), "Prompt".to_string()];
+            let data = write_delimited_table(&headers, &rows, b'\t')?.into_bytes();
+            prompt_preview = Some(format!(
+                "{} pasted prompt{}",
+                rows.len(),
+                if rows.len() == 1 { "" } else { "s" }
+            ));
+            spreadsheet_source = Some(SpreadsheetSource::Inline {
+                filename: "prompts.tsv".into(),
+                data,
+            });
+            selected_prompt_columns = vec!["Prompt".into()];
+            selected_identifier_columns = vec!["Student".into()];
+            detail_identifier_columns = selected_identifier_columns.clone();
+        }
         TaskType::Prompt => {
             let text = prompt_text.as_deref().map(str::trim).unwrap_or_default();
             if text.is_empty() {
-                return Err("Provide a prompt describing the student's interests.".into());
+                return Err(AppError::invalid_input(
+                    "promptText",
+                    "Provide a prompt describing the student's interests.",
+                ));
             }
-            prompt_preview = Some(build_prompt_preview(text));
-            prepared_prompt_text = Some(text.to_string());
+            let text = if scrub_personal_information {
+                let (scrubbed, redactions) = scrub_personal_information_text(text, &[]);
+                if let Some(summary) = redactions.describe() {
+                    warnings.push(format!("Redacted {summary} from the prompt."));
+                }
+                scrubbed
+            } else {
+                text.to_string()
+            };
+            let max_characters = resolve_max_prompt_characters(max_prompt_characters);
+            let text = match truncate_prompt_text(&text, max_characters) {
+                Some(truncated) => {
+                    warnings.push(format!(
+                        "The prompt was truncated from {} to {} characters to fit the embedding model.",
+                        text.chars().count(),
+                        truncated.chars().count()
+                    ));
+                    truncated_prompts += 1;
+                    truncated
+                }
+                None => text,
+            };
+            prompt_preview = Some(build_prompt_preview(&text, prompt_preview_characters));
+            prepared_prompt_text = Some(text);
         }
         TaskType::Document => {
-            let document = resolve_existing_path(document_path, false, "Single document")?;
-            if let Some(message) =
-                validate_extension(&document, &["txt", "pdf", "doc", "docx"], "document")
-            {
-                warnings.push(message);
-            }
-            let extraction = extract_document_prompt(&document)?;
+            let (extraction, field) = match document_content.as_deref() {
+                Some(content) => {
+                    if document_path
+                        .as_deref()
+                        .is_some_and(|path| !path.trim().is_empty())
+                    {
+                        warnings.push(
+                            "Both a document path and uploaded document content were provided; the uploaded content was used.".into(),
+                        );
+                    }
+                    let data = decode_inline_content(content, "documentContent", "document")?;
+                    let filename =
+                        inline_content_filename(document_filename.as_deref(), "document");
+                    let filename_hint = Path::new(&filename);
+                    if let Some(message) = validate_extension(
+                        filename_hint,
+                        &["txt", "pdf", "doc", "docx"],
+                        "document",
+                    ) {
+                        warnings.push(message);
+                    }
+                    let extraction = extract_document_bytes(
+                        &data,
+                        filename_hint
+                            .extension()
+                            .and_then(|ext| ext.to_str())
+                            .map(|ext| ext.to_ascii_lowercase())
+                            .as_deref(),
+                    )
+                    .map_err(|message| AppError::invalid_input("documentContent", message))?;
+                    validated_paths.push(PathConfirmation::inline("Document", &filename));
+                    (extraction, "documentContent")
+                }
+                None => {
+                    let document =
+                        resolve_existing_path(document_path, false, "Single document")
+                            .map_err(|message| AppError::invalid_input("documentPath", message))?;
+                    if let Some(message) =
+                        validate_extension(&document, &["txt", "pdf", "doc", "docx"], "document")
+                    {
+                        warnings.push(message);
+                    }
+                    let extraction = extract_document_prompt(&document)?;
+                    validated_paths.push(PathConfirmation::new("Document", &document));
+                    (extraction, "documentPath")
+                }
+            };
             if extraction.text.trim().is_empty() {
-                return Err(
-                    "The selected document did not contain any readable text to embed.".into(),
-                );
+                return Err(AppError::invalid_input(
+                    field,
+                    "The selected document did not contain any readable text to embed.",
+                ));
             }
             warnings.extend(extraction.warnings);
-            prompt_preview = Some(build_prompt_preview(&extraction.text));
-            prepared_prompt_text = Some(extraction.text);
-            validated_paths.push(PathConfirmation::new("Document", &document));
+            let text = if scrub_personal_information {
+                let (scrubbed, redactions) = scrub_personal_information_text(&extraction.text, &[]);
+                if let Some(summary) = redactions.describe() {
+                    warnings.push(format!("Redacted {summary} from the document."));
+                }
+                scrubbed
+            } else {
+                extraction.text
+            };
+            prompt_preview = Some(build_prompt_preview(&text, prompt_preview_characters));
+            prepared_prompt_text = Some(text);
         }
         TaskType::Spreadsheet => {
-            let spreadsheet = resolve_existing_path(spreadsheet_path, false, "Spreadsheet file")?;
-            if let Some(message) =
-                validate_extension(&spreadsheet, &["tsv", "txt", "xlsx", "xls"], "spreadsheet")
-            {
-                warnings.push(message);
+            match spreadsheet_content.as_deref() {
+                Some(content) => {
+                    if spreadsheet_path
+                        .as_deref()
+                        .is_some_and(|path| !path.trim().is_empty())
+                    {
+                        warnings.push(
+                            "Both a spreadsheet path and uploaded spreadsheet content were provided; the uploaded content was used.".into(),
+                        );
+                    }
+                    let data = decode_inline_content(content, "spreadsheetContent", "spreadsheet")?;
+                    let filename =
+                        inline_content_filename(spreadsheet_filename.as_deref(), "spreadsheet.tsv");
+                    if let Some(message) = validate_extension(
+                        Path::new(&filename),
+                        &["tsv", "txt", "xlsx", "xls"],
+                        "spreadsheet",
+                    ) {
+                        warnings.push(message);
+                    }
+                    validated_paths.push(PathConfirmation::inline("Spreadsheet", &filename));
+                    spreadsheet_source = Some(SpreadsheetSource::Inline { filename, data });
+                }
+                None => {
+                    let spreadsheet =
+                        resolve_existing_path(spreadsheet_path, false, "Spreadsheet file")
+                            .map_err(|message| {
+                                AppError::invalid_input("spreadsheetPath", message)
+                            })?;
+                    if let Some(message) = validate_extension(
+                        &spreadsheet,
+                        &["tsv", "txt", "xlsx", "xls"],
+                        "spreadsheet",
+                    ) {
+                        warnings.push(message);
+                    }
+                    validated_paths.push(PathConfirmation::new("Spreadsheet", &spreadsheet));
+                    spreadsheet_source = Some(SpreadsheetSource::Path(spreadsheet));
+                }
             }
-            validated_paths.push(PathConfirmation::new("Spreadsheet", &spreadsheet));
-            spreadsheet_source = Some(spreadsheet.clone());
 
             selected_prompt_columns = normalize_columns(spreadsheet_prompt_columns);
             selected_identifier_columns = normalize_columns(spreadsheet_identifier_columns);
@@ -300,11 +1492,15 @@ fn perform_matching_request(
             };
 
             if selected_prompt_columns.is_empty() {
-                return Err("Select at least one column containing student prompts.".into());
+                return Err(AppError::invalid_input(
+                    "spreadsheetPromptColumns",
+                    "Select at least one column containing student prompts.",
+                ));
             }
         }
         TaskType::Directory => {
-            let directory = resolve_existing_path(directory_path, true, "Directory")?;
+            let directory = resolve_existing_path(directory_path, true, "Directory")
+                .map_err(|message| AppError::invalid_input("directoryPath", message))?;
             if let Ok(mut entries) = fs::read_dir(&directory) {
                 if entries.next().is_none() {
                     warnings.push("The selected directory appears to be empty.".into());
@@ -316,11 +1512,18 @@ fn perform_matching_request(
     }
 
     let normalized_programs = normalize_programs(program_filters);
+    let mut matched_programs = normalized_programs.clone();
     let mut allowed_faculty_rows: Option<HashSet<usize>> = None;
     let mut faculty_roster_path = None;
+    let mut roster_diagnostics: Option<RosterDiagnostics> = None;
+    let mut detail_value_identifier: Option<String> = None;
+    let mut detail_mapping_preset: Option<RosterMappingPresetReference> = None;
+    let mut detail_roster_faculty_count: Option<usize> = None;
+    let mut ad_hoc_roster_faculty: Vec<AdHocRosterFaculty> = Vec::new();
 
     if matches!(faculty_scope, FacultyScope::Custom) {
-        let roster = resolve_existing_path(custom_faculty_path, false, "Faculty list")?;
+        let roster = resolve_existing_path(custom_faculty_path, false, "Faculty list")
+            .map_err(|message| AppError::invalid_input("customFacultyPath", message))?;
         if let Some(message) =
             validate_extension(&roster, &["tsv", "txt", "xlsx", "xls"], "faculty list")
         {
@@ -329,4361 +1532,19784 @@ fn perform_matching_request(
         faculty_roster_path = Some(roster.to_string_lossy().into_owned());
         validated_paths.push(PathConfirmation::new("Faculty list", &roster));
 
-        let metadata = load_faculty_dataset_metadata(&app_handle)?.ok_or_else(|| {
-            "The faculty dataset metadata is unavailable. Refresh the dataset analysis before limiting faculty by roster.".to_string()
+        let metadata = load_faculty_dataset_metadata(app_handle)?.ok_or_else(|| {
+            AppError::MissingDataset {
+                message: "The faculty dataset metadata is unavailable. Refresh the dataset analysis before limiting faculty by roster.".into(),
+            }
         })?;
 
-        let mut identifier_lookup: HashMap<String, String> = HashMap::new();
-        for identifier in &metadata.analysis.identifier_columns {
-            identifier_lookup.insert(identifier.trim().to_lowercase(), identifier.clone());
-        }
+        if let Some(preset_name) = roster_mapping_preset
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let preset = load_roster_mapping_presets(app_handle)?
+                .into_iter()
+                .find(|preset| preset.name.eq_ignore_ascii_case(preset_name))
+                .ok_or_else(|| {
+                    AppError::invalid_input(
+                        "rosterMappingPreset",
+                        format!("No roster mapping preset named '{preset_name}' was found."),
+                    )
+                })?;
 
-        let mut resolved_map: HashMap<String, String> = HashMap::new();
-        for (raw_identifier, roster_label) in faculty_roster_column_map.iter() {
-            let normalized_identifier = raw_identifier.trim().to_lowercase();
-            if normalized_identifier.is_empty() {
-                continue;
+            faculty_roster_column_map.clear();
+            for (identifier, roster_label) in &preset.column_map {
+                if metadata.analysis.identifier_columns.contains(identifier) {
+                    faculty_roster_column_map.insert(identifier.clone(), roster_label.clone());
+                } else {
+                    let message = format!(
+                        "The roster mapping preset '{}' maps '{roster_label}' to identifier '{identifier}', which is not in the current faculty dataset.",
+                        preset.name
+                    );
+                    roster_warnings.push(message);
+                }
             }
+            roster_match_mode = preset.match_mode;
+            detail_mapping_preset = Some(RosterMappingPresetReference {
+                name: preset.name,
+                version: preset.version,
+            });
+        }
 
-            let trimmed_label = roster_label.trim();
-            if trimmed_label.is_empty() {
-                continue;
-            }
+        let outcome = match_roster_against_dataset(
+            &roster,
+            &metadata,
+            &RosterMatchOptions {
+                column_map: &faculty_roster_column_map,
+                fuzzy_matching: roster_fuzzy_matching,
+                match_mode: roster_match_mode,
+                value_identifier: roster_value_identifier.as_deref(),
+                email_domain_aliases: &email_domain_aliases,
+                strip_diacritics: strip_identifier_diacritics.unwrap_or(true),
+            },
+        )?;
 
-            if let Some(original_identifier) = identifier_lookup.get(&normalized_identifier) {
-                resolved_map
-                    .entry(original_identifier.clone())
-                    .or_insert_with(|| trimmed_label.to_string());
-            } else {
-                let message = format!(
-                    "The roster mapping includes an unknown identifier '{raw_identifier}'.",
+        roster_warnings.extend(outcome.warnings);
+        detail_roster_column_map = outcome.resolved_column_map;
+        detail_value_identifier = outcome.value_identifier;
+        detail_roster_faculty_count = Some(outcome.summary.matched_faculty);
+
+        let roster_rows = outcome.matched_rows;
+        let program_rows = if normalized_programs.is_empty() {
+            None
+        } else {
+            let program_filter = filter_faculty_rows_by_program(&metadata, &normalized_programs);
+            warnings.extend(program_filter.warnings);
+            matched_programs = program_filter.programs;
+            let program_rows = program_filter.rows;
+            if program_rows.is_empty() {
+                warnings.push(
+                    "No faculty members in the dataset matched the selected programs.".into(),
                 );
-                warnings.push(message.clone());
-                roster_warning_messages.push(message);
             }
-        }
-
-        if resolved_map.is_empty() {
-            return Err("Map at least one roster column to a faculty identifier.".into());
-        }
+            Some(program_rows)
+        };
 
-        let (mut headers, mut rows) = read_full_spreadsheet(&roster)?;
-        align_row_lengths(&mut headers, &mut rows);
+        match custom_faculty_mode {
+            CustomFacultyMode::Include => {
+                let mut included_rows = roster_rows;
+                // A program filter that matched nothing leaves nobody in scope, as in the
+                // Exclude branch and Program scope, rather than being ignored.
+                if let Some(program_rows) = program_rows {
+                    let roster_count = included_rows.len();
+                    included_rows.retain(|row_index| program_rows.contains(row_index));
+
+                    if roster_count > 0 && included_rows.is_empty() {
+                        warnings.push(format!(
+                            "The roster matched {roster_count} faculty, but none of them are in the selected programs."
+                        ));
+                    } else if included_rows.len() < roster_count {
+                        warnings.push(format!(
+                            "The roster matched {roster_count} faculty, but only {} are in the selected programs.",
+                            included_rows.len()
+                        ));
+                    }
+                }
+                allowed_faculty_rows = Some(included_rows);
+            }
+            CustomFacultyMode::Exclude => {
+                let mut remaining_rows = program_rows.unwrap_or_else(|| {
+                    metadata
+                        .memberships
+                        .iter()
+                        .map(|membership| membership.row_index)
+                        .collect()
+                });
+                let available_count = remaining_rows.len();
+                remaining_rows.retain(|row_index| !roster_rows.contains(row_index));
+                let excluded_count = available_count - remaining_rows.len();
 
-        detail_roster_column_map = resolved_map.clone();
+                warnings.push(format!(
+                    "Excluded {excluded_count} faculty member{plural} listed in the roster.",
+                    plural = if excluded_count == 1 { "" } else { "s" }
+                ));
 
-        let header_map = build_header_index_map(&headers);
-        let mut roster_column_indexes: HashMap<String, usize> = HashMap::new();
-
-        for (identifier, roster_label) in resolved_map.iter() {
-            let normalized_label = roster_label.trim().to_lowercase();
-            let mut column_index = header_map.get(&normalized_label).copied();
-
-            if column_index.is_none() {
-                let normalized_target = normalize_identifier_label(roster_label);
-                if !normalized_target.is_empty() {
-                    for (candidate_index, header) in headers.iter().enumerate() {
-                        if normalize_identifier_label(header) == normalized_target {
-                            column_index = Some(candidate_index);
-                            break;
-                        }
-                    }
+                let unmatched_count = outcome.summary.unmatched_roster_rows;
+                if unmatched_count > 0 {
+                    warnings.push(format!(
+                        "{unmatched_count} roster row{plural} could not be matched and {verb} not excluded; those faculty may still appear in the recommendations.",
+                        plural = if unmatched_count == 1 { "" } else { "s" },
+                        verb = if unmatched_count == 1 { "was" } else { "were" }
+                    ));
                 }
+                if remaining_rows.is_empty() {
+                    warnings
+                        .push("Every eligible faculty member was excluded by the roster.".into());
+                }
+                allowed_faculty_rows = Some(remaining_rows);
             }
+        }
 
-            if let Some(found_index) = column_index {
-                roster_column_indexes.insert(identifier.clone(), found_index);
-            } else {
-                let message = format!(
-                    "The roster does not contain a column named '{roster_label}' for identifier '{identifier}'.",
+        if let Some(text_column) = roster_text_column
+            .as_deref()
+            .map(str::trim)
+            .filter(|column| !column.is_empty())
+        {
+            if matches!(custom_faculty_mode, CustomFacultyMode::Exclude) {
+                warnings.push(
+                    "Roster-only faculty are not embedded when the roster excludes faculty.".into(),
                 );
-                warnings.push(message.clone());
-                roster_warning_messages.push(message);
+            } else {
+                ad_hoc_roster_faculty = collect_ad_hoc_roster_faculty(
+                    &outcome.diagnostics,
+                    &detail_roster_column_map,
+                    text_column,
+                )?;
             }
         }
 
-        if roster_column_indexes.is_empty() {
-            return Err(
-                "None of the mapped roster columns were found in the roster spreadsheet.".into(),
-            );
+        if !outcome.diagnostics.unmatched_rows.is_empty() {
+            roster_diagnostics = Some(outcome.diagnostics);
         }
+    }
 
-        let identifier_order: Vec<String> = metadata
-            .analysis
-            .identifier_columns
-            .iter()
-            .filter(|identifier| roster_column_indexes.contains_key(*identifier))
-            .cloned()
-            .collect();
+    if matches!(faculty_scope, FacultyScope::Program) && normalized_programs.is_empty() {
+        return Err(AppError::invalid_input(
+            "programFilters",
+            "Provide at least one program to limit the faculty list.",
+        ));
+    }
 
-        if identifier_order.is_empty() {
-            return Err("Map at least one roster column to a faculty identifier.".into());
+    if matches!(faculty_scope, FacultyScope::Program) {
+        let metadata = load_faculty_dataset_metadata(app_handle)?
+            .ok_or_else(|| AppError::MissingDataset {
+                message: "The faculty dataset metadata is unavailable. Refresh the dataset analysis before filtering by program.".into(),
+            })?;
+        let program_filter = filter_faculty_rows_by_program(&metadata, &normalized_programs);
+        warnings.extend(program_filter.warnings);
+        matched_programs = program_filter.programs;
+        let filtered_rows = program_filter.rows;
+        if filtered_rows.is_empty() {
+            warnings
+                .push("No faculty members in the dataset matched the selected programs.".into());
         }
+        allowed_faculty_rows = Some(filtered_rows);
+    }
 
-        let mut dataset_index: HashMap<String, HashSet<usize>> = HashMap::new();
-        for membership in &metadata.memberships {
-            let mut parts = Vec::new();
-            for identifier in &identifier_order {
-                if let Some(value) = membership.identifiers.get(identifier) {
-                    let normalized = normalize_identifier_value(value);
-                    if normalized.is_empty() {
-                        parts.clear();
-                        break;
-                    }
-                    parts.push(normalized);
-                } else {
-                    parts.clear();
-                    break;
-                }
-            }
-
-            if parts.is_empty() {
-                continue;
-            }
-
-            let key = parts.join("|");
-            dataset_index
-                .entry(key)
-                .or_default()
-                .insert(membership.row_index);
-        }
+    if matches!(faculty_scope, FacultyScope::Custom) && faculty_roster_path.is_none() {
+        return Err(AppError::invalid_input(
+            "customFacultyPath",
+            "Provide a faculty roster spreadsheet to limit the faculty list.",
+        ));
+    }
 
-        if dataset_index.is_empty() {
-            let message =
-                "No faculty dataset identifiers were available for the selected roster columns."
-                    .to_string();
-            warnings.push(message.clone());
-            roster_warning_messages.push(message);
+    let student_label = match student_label {
+        Some(_) if !matches!(task_type, TaskType::Prompt | TaskType::Document) => {
+            warnings.push(
+                "The student label only applies to prompt and document tasks; spreadsheet and directory runs label students from their own identifiers."
+                    .into(),
+            );
+            None
         }
+        label => label,
+    };
 
-        let mut matched_rows: HashSet<usize> = HashSet::new();
-        let mut unmatched_roster_rows = 0usize;
-
-        for row in &rows {
-            let mut parts = Vec::new();
-            let mut row_missing = false;
-
-            for identifier in &identifier_order {
-                let column_index = match roster_column_indexes.get(identifier) {
-                    Some(index) => *index,
-                    None => {
-                        row_missing = true;
-                        break;
-                    }
-                };
-
-                let value = row
-                    .get(column_index)
-                    .map(|value| normalize_identifier_value(value));
-                match value {
-                    Some(normalized) if !normalized.is_empty() => parts.push(normalized),
-                    _ => {
-                        row_missing = true;
-                        break;
-                    }
-                }
-            }
-
-            if row_missing || parts.is_empty() {
-                unmatched_roster_rows += 1;
-                continue;
-            }
-
-            let key = parts.join("|");
-            if let Some(rows) = dataset_index.get(&key) {
-                matched_rows.extend(rows.iter().copied());
-            } else {
-                unmatched_roster_rows += 1;
-            }
-        }
-
-        if unmatched_roster_rows > 0 {
-            let message = format!(
-                "{unmatched_roster_rows} roster row{plural} did not match any faculty dataset entries.",
-                plural = if unmatched_roster_rows == 1 { "" } else { "s" }
-            );
-            warnings.push(message.clone());
-            roster_warning_messages.push(message);
-        }
-
-        if matched_rows.is_empty() {
-            let message =
-                "No faculty in the dataset matched the provided roster identifiers.".to_string();
-            warnings.push(message.clone());
-            roster_warning_messages.push(message);
-        }
-
-        for (identifier, &index) in &roster_column_indexes {
-            detail_roster_column_map.insert(identifier.clone(), header_label(&headers, index));
-        }
-
-        allowed_faculty_rows = Some(matched_rows);
-    }
-
-    if matches!(faculty_scope, FacultyScope::Program) && normalized_programs.is_empty() {
-        return Err("Provide at least one program to limit the faculty list.".into());
-    }
-
-    if matches!(faculty_scope, FacultyScope::Program) {
-        let metadata = load_faculty_dataset_metadata(&app_handle)?
-            .ok_or_else(|| {
-                "The faculty dataset metadata is unavailable. Refresh the dataset analysis before filtering by program.".to_string()
-            })?;
-        let filtered_rows =
-            filter_faculty_rows_by_program(&metadata.memberships, &normalized_programs);
-        if filtered_rows.is_empty() {
-            warnings
-                .push("No faculty members in the dataset matched the selected programs.".into());
-        }
-        allowed_faculty_rows = Some(filtered_rows);
-    }
-
-    if matches!(faculty_scope, FacultyScope::Custom) && faculty_roster_path.is_none() {
-        return Err("Provide a faculty roster spreadsheet to limit the faculty list.".into());
-    }
-
-    let details = SubmissionDetails {
+    let roster_warning_messages = roster_warnings.into_vec();
+    // Roster problems lead the run warnings.
+    warnings.prepend(roster_warning_messages.iter().cloned());
+    let mut details = SubmissionDetails {
+        request_id: request_id.to_string(),
         task_type: task_type.clone(),
         faculty_scope: faculty_scope.clone(),
         validated_paths,
         program_filters: match faculty_scope {
-            FacultyScope::Program => normalized_programs.clone(),
-            _ => Vec::new(),
+            FacultyScope::Program | FacultyScope::Custom => matched_programs,
+            FacultyScope::All => Vec::new(),
         },
         custom_faculty_path: faculty_roster_path.clone(),
+        custom_faculty_mode,
         recommendations_per_student: faculty_recs_per_student,
         prompt_preview,
         spreadsheet_prompt_columns: selected_prompt_columns.clone(),
         spreadsheet_identifier_columns: detail_identifier_columns.clone(),
         faculty_roster_column_map: detail_roster_column_map.clone(),
-        faculty_roster_warnings: roster_warning_messages.clone(),
+        faculty_roster_warnings: roster_warning_messages,
+        roster_fuzzy_matching,
+        roster_match_mode,
+        roster_value_identifier: detail_value_identifier,
+        roster_mapping_preset: detail_mapping_preset,
+        roster_faculty_count: detail_roster_faculty_count,
+        faculty_in_scope: None,
+        student_label,
+        embedding_model: None,
+        model_overridden: false,
+        output_filename: None,
     };
 
-    let summary = build_summary(
-        &task_type,
-        &faculty_scope,
-        faculty_recs_per_student,
-        details.program_filters.len(),
-        faculty_roster_path.is_some(),
-    );
-
-    let mut prompt_matches = Vec::new();
-    let mut directory_results = None;
-    let mut spreadsheet_results = None;
-
     let needs_prompt_embedding = prepared_prompt_text.is_some()
+        || spreadsheet_source.is_some()
         || matches!(task_type, TaskType::Directory | TaskType::Spreadsheet);
-    let mut faculty_embedding_index: Option<FacultyEmbeddingIndex> = None;
+    let mut faculty_embedding_index: Option<Arc<FacultyEmbeddingIndex>> = None;
 
+    let model_override = model_override
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty());
     if needs_prompt_embedding {
-        let index = load_faculty_embedding_index(&app_handle)?;
+        // An override only switches to an index already embedded with that model, so
+        // prompts and faculty are always compared in the same vector space.
+        let index = match model_override.as_deref() {
+            Some(model) => load_model_embedding_index(app_handle, model)?,
+            None => load_faculty_embedding_index(app_handle)?,
+        };
         if index.entries.is_empty() {
-            return Err(
-                "No faculty embeddings are available. Generate embeddings before matching.".into(),
-            );
-        }
-        faculty_embedding_index = Some(index);
-    }
-
-    if let Some(prompt_text) = prepared_prompt_text {
-        let limit = faculty_recs_per_student.max(1) as usize;
-        let embedding_index = faculty_embedding_index
-            .as_ref()
-            .ok_or_else(|| "The faculty embedding index was not loaded.".to_string())?;
-        let prompt_embedding = embed_prompt(&app_handle, embedding_index, &prompt_text)?;
-        let mut matches = find_best_faculty_matches(
-            embedding_index,
-            &prompt_embedding,
-            limit,
-            allowed_faculty_rows.as_ref(),
-        );
-
-        if matches!(task_type, TaskType::Prompt | TaskType::Document) {
-            if let Err(err) = enrich_matches_with_faculty_text(
-                &app_handle,
-                &embedding_index.embedding_columns,
-                &mut matches,
-            ) {
-                warnings.push(format!(
-                    "Unable to include faculty text in the match results: {err}"
-                ));
-            }
+            return Err(AppError::MissingEmbeddings {
+                message:
+                    "No faculty embeddings are available. Generate embeddings before matching."
+                        .into(),
+            });
         }
-
-        prompt_matches.push(PromptMatchResult {
-            prompt: match task_type {
-                TaskType::Document => build_prompt_preview(&prompt_text),
-                _ => prompt_text.clone(),
-            },
-            faculty_matches: matches,
-        });
-    }
-
-    if matches!(task_type, TaskType::Directory) {
-        let directory_path = directory_source
-            .as_ref()
-            .ok_or_else(|| "The directory path was not preserved during processing.".to_string())?;
-        let embedding_index = faculty_embedding_index
-            .as_ref()
-            .ok_or_else(|| "The faculty embedding index was not loaded.".to_string())?;
-        let limit = faculty_recs_per_student.max(1) as usize;
-
-        let outcome = process_directory_documents(
-            &app_handle,
-            directory_path,
-            embedding_index,
-            limit,
-            allowed_faculty_rows.as_ref(),
-        )?;
-
-        warnings.extend(outcome.warnings);
-        prompt_matches.extend(outcome.prompt_matches);
-        directory_results = Some(outcome.results);
-    }
-
-    if matches!(task_type, TaskType::Spreadsheet) {
-        let spreadsheet_path = spreadsheet_source.as_ref().ok_or_else(|| {
-            "The spreadsheet path was not preserved during processing.".to_string()
-        })?;
-        let embedding_index = faculty_embedding_index
-            .as_ref()
-            .ok_or_else(|| "The faculty embedding index was not loaded.".to_string())?;
-        let limit = faculty_recs_per_student.max(1) as usize;
-
-        let outcome = process_prompt_spreadsheet(
-            &app_handle,
-            spreadsheet_path,
-            embedding_index,
-            &selected_prompt_columns,
-            &selected_identifier_columns,
-            limit,
+        let in_scope = count_faculty_in_scope(&index, allowed_faculty_rows.as_ref())
+            + ad_hoc_roster_faculty.len();
+        // Listed first so a scope that cannot fill the request is hard to miss.
+        warnings.prepend(faculty_scope_warnings(
+            &index,
             allowed_faculty_rows.as_ref(),
-        )?;
-
-        warnings.extend(outcome.warnings);
-        prompt_matches.extend(outcome.prompt_matches);
-        spreadsheet_results = Some(outcome.results);
-    }
-
-    {
-        let mut match_refs: Vec<&mut Vec<FacultyMatchResult>> = prompt_matches
-            .iter_mut()
-            .map(|result| &mut result.faculty_matches)
-            .collect();
-        assign_student_rankings(&mut match_refs);
+            in_scope,
+            details.recommendations_per_student,
+        ));
+        details.faculty_in_scope = Some(in_scope);
+        details.embedding_model = Some(index.model.clone());
+        details.model_overridden = model_override.is_some();
+        faculty_embedding_index = Some(index);
     }
 
-    Ok(SubmissionResponse {
-        summary,
+    Ok(PreparedSubmission {
         warnings,
         details,
-        prompt_matches,
-        directory_results,
-        spreadsheet_results,
+        output_destination,
+        filename_pattern,
+        prepared_prompt_text,
+        truncated_prompts,
+        directory_source,
+        spreadsheet_source,
+        selected_prompt_columns,
+        selected_identifier_columns,
+        allowed_faculty_rows,
+        roster_diagnostics,
+        ad_hoc_roster_faculty,
+        faculty_embedding_index,
     })
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct EmbeddingRequestPayload {
-    model: String,
-    texts: Vec<EmbeddingRequestRow>,
-    #[serde(default)]
+struct SubmissionValidation {
+    details: SubmissionDetails,
+    warnings: Vec<String>,
+    students_to_process: usize,
+    faculty_in_scope: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
-    item_label: Option<String>,
-    #[serde(default)]
+    embedding_model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    item_label_plural: Option<String>,
+    embedding_dimension: Option<usize>,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct EmbeddingRequestRow {
-    id: usize,
-    text: String,
+#[tauri::command]
+async fn search_faculty(
+    app_handle: tauri::AppHandle,
+    query: String,
+    limit: u32,
+    program_filters: Vec<String>,
+) -> Result<Vec<FacultyMatchResult>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        perform_faculty_search(&app_handle, &query, limit, program_filters)
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Faculty search task failed: {err}")))?
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EmbeddingResponsePayload {
-    model: String,
-    dimension: usize,
-    rows: Vec<EmbeddingResponseRow>,
-}
+fn perform_faculty_search(
+    app_handle: &dyn MatchingHost,
+    query: &str,
+    limit: u32,
+    program_filters: Vec<String>,
+) -> Result<Vec<FacultyMatchResult>, AppError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(AppError::invalid_input(
+            "query",
+            "Enter a few words describing the research interests to search for.",
+        ));
+    }
+    let limit = match limit as usize {
+        0 => SEARCH_FACULTY_DEFAULT_LIMIT,
+        limit => limit.min(SEARCH_FACULTY_MAX_LIMIT),
+    };
+    let _run_guard = begin_helper_activity(HelperActivity::FacultySearch, None)?;
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct EmbeddingResponseRow {
-    id: usize,
-    embedding: Vec<f32>,
-}
+    let programs = normalize_programs(program_filters);
+    let allowed_rows = if programs.is_empty() {
+        None
+    } else {
+        let metadata = load_faculty_dataset_metadata(app_handle)?.ok_or_else(|| {
+            AppError::MissingDataset {
+                message: "The faculty dataset metadata is unavailable. Refresh the dataset analysis before filtering by program.".into(),
+            }
+        })?;
+        Some(filter_faculty_rows_by_program(&metadata, &programs).rows)
+    };
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct FacultyEmbeddingEntry {
-    row_index: usize,
-    identifiers: HashMap<String, String>,
-    embedding: Vec<f32>,
+    let index = load_faculty_embedding_index(app_handle)?;
+    if index.entries.is_empty() {
+        return Err(AppError::MissingEmbeddings {
+            message: "No faculty embeddings are available. Generate embeddings before searching."
+                .into(),
+        });
+    }
+
+    let embedding = embed_prompt(app_handle, &index, query)?;
+    let mut matches = find_best_faculty_matches(&index, &embedding, limit, allowed_rows.as_ref());
+    if let Err(err) = enrich_matches_with_faculty_text(app_handle, &index, &mut matches) {
+        log::warn!("Unable to include faculty text in the search results: {err}");
+    }
+    Ok(matches)
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct FacultyEmbeddingIndex {
-    model: String,
+struct EvaluationPayload {
+    assignments_path: String,
     #[serde(default)]
-    generated_at: Option<String>,
-    dimension: usize,
+    k_values: Vec<usize>,
     #[serde(default)]
-    total_rows: Option<usize>,
+    student_columns: Vec<String>,
     #[serde(default)]
-    embedded_rows: Option<usize>,
+    prompt_columns: Vec<String>,
     #[serde(default)]
-    skipped_rows: Option<usize>,
+    document_column: Option<String>,
     #[serde(default)]
-    embedding_columns: Vec<String>,
+    faculty_column_map: HashMap<String, String>,
     #[serde(default)]
-    identifier_columns: Vec<String>,
-    entries: Vec<FacultyEmbeddingEntry>,
+    email_domain_aliases: HashMap<String, String>,
+    #[serde(default)]
+    program_filters: Vec<String>,
+    #[serde(default)]
+    include_workbook: bool,
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct PromptMatchResult {
-    prompt: String,
-    faculty_matches: Vec<FacultyMatchResult>,
-}
-
-struct DocumentExtractionResult {
-    text: String,
-    warnings: Vec<String>,
+struct EvaluationMetric {
+    k: usize,
+    hits: usize,
+    recall: f64,
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct FacultyMatchResult {
-    row_index: usize,
-    similarity: f32,
-    identifiers: HashMap<String, String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    faculty_text: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    student_rank_for_faculty: Option<usize>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    student_rank_total: Option<usize>,
+struct EvaluationAssignment {
+    student: String,
+    faculty: String,
+    rank: Option<usize>,
+    similarity: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct GeneratedSpreadsheet {
-    filename: String,
-    mime_type: String,
-    content: String,
+struct EvaluationReport {
+    request_id: String,
+    students_evaluated: usize,
+    assignments_evaluated: usize,
+    metrics: Vec<EvaluationMetric>,
+    mean_reciprocal_rank: f64,
+    assignments: Vec<EvaluationAssignment>,
+    unmatched_students: Vec<String>,
+    unmatched_faculty: Vec<String>,
+    warnings: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    encoding: Option<String>,
+    workbook: Option<GeneratedSpreadsheet>,
 }
 
-#[derive(Debug, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct DirectoryMatchResults {
-    processed_documents: usize,
-    matched_documents: usize,
-    skipped_documents: usize,
-    total_rows: usize,
-    preview: SpreadsheetPreview,
-    spreadsheet: GeneratedSpreadsheet,
-}
-
-#[derive(Debug, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct SpreadsheetMatchResults {
-    processed_rows: usize,
-    matched_rows: usize,
-    skipped_rows: usize,
-    total_rows: usize,
-    preview: SpreadsheetPreview,
-    spreadsheet: GeneratedSpreadsheet,
+struct EvaluationStudent {
+    label: String,
+    prompt: Option<String>,
+    faculty: Vec<(String, usize)>,
 }
 
-#[derive(Debug, Clone)]
-struct MatchEntry {
-    student_values: Vec<String>,
-    faculty_values: Vec<String>,
-    similarity: Option<f32>,
-    student_rank: Option<(usize, Option<usize>)>,
-    faculty_rank: Option<usize>,
+#[tauri::command]
+async fn evaluate_matching(
+    app_handle: tauri::AppHandle,
+    payload: EvaluationPayload,
+) -> Result<EvaluationReport, AppError> {
+    let request_id = Uuid::new_v4().to_string();
+    let run_guard = begin_helper_activity(HelperActivity::Matching, Some(&request_id))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let _run_guard = run_guard;
+        perform_matching_evaluation(&app_handle, payload, &request_id)
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Evaluation task failed: {err}")))?
 }
 
-#[derive(Debug)]
-struct DirectoryProcessingOutcome {
-    warnings: Vec<String>,
-    prompt_matches: Vec<PromptMatchResult>,
-    results: DirectoryMatchResults,
-}
+fn perform_matching_evaluation(
+    app_handle: &dyn MatchingHost,
+    payload: EvaluationPayload,
+    request_id: &str,
+) -> Result<EvaluationReport, AppError> {
+    let assignments_path = resolve_existing_path(
+        Some(payload.assignments_path),
+        false,
+        "Assignments spreadsheet",
+    )
+    .map_err(|message| AppError::invalid_input("assignmentsPath", message))?;
+    let mut k_values: Vec<usize> = payload.k_values.into_iter().filter(|k| *k > 0).collect();
+    if k_values.is_empty() {
+        k_values = EVALUATION_DEFAULT_K_VALUES.to_vec();
+    }
+    k_values.sort_unstable();
+    k_values.dedup();
+
+    let document_column = payload
+        .document_column
+        .map(|column| column.trim().to_string())
+        .filter(|column| !column.is_empty());
+    if payload.prompt_columns.is_empty() && document_column.is_none() {
+        return Err(AppError::invalid_input(
+            "promptColumns",
+            "Select the prompt columns or a column of document paths for the students.",
+        ));
+    }
 
-#[derive(Debug)]
-struct SpreadsheetProcessingOutcome {
-    warnings: Vec<String>,
-    prompt_matches: Vec<PromptMatchResult>,
-    results: SpreadsheetMatchResults,
-}
+    let index = load_faculty_embedding_index(app_handle)?;
+    if index.entries.is_empty() {
+        return Err(AppError::MissingEmbeddings {
+            message: "No faculty embeddings are available. Generate embeddings before evaluating."
+                .into(),
+        });
+    }
 
-fn load_faculty_embedding_index(
-    app_handle: &tauri::AppHandle,
-) -> Result<FacultyEmbeddingIndex, String> {
-    let embeddings_path = dataset_directory(app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
-    let data = if embeddings_path.exists() {
-        fs::read(&embeddings_path)
-            .map_err(|err| format!("Unable to read faculty embeddings: {err}"))?
-    } else {
-        DEFAULT_FACULTY_EMBEDDINGS.to_vec()
+    let identifier_lookup: HashMap<String, &String> = index
+        .identifier_columns
+        .iter()
+        .map(|identifier| (identifier.trim().to_lowercase(), identifier))
+        .collect();
+    let mut faculty_identifiers = Vec::new();
+    let mut faculty_columns = Vec::new();
+    for (identifier, column) in &payload.faculty_column_map {
+        if column.trim().is_empty() {
+            continue;
+        }
+        let Some(&resolved) = identifier_lookup.get(&identifier.trim().to_lowercase()) else {
+            return Err(AppError::invalid_input(
+                "facultyColumnMap",
+                format!(
+                    "The faculty embeddings do not include an identifier named '{identifier}'."
+                ),
+            ));
+        };
+        faculty_identifiers.push(resolved.clone());
+        faculty_columns.push(column.trim().to_string());
+    }
+    if faculty_identifiers.is_empty() {
+        return Err(AppError::invalid_input(
+            "facultyColumnMap",
+            "Map at least one assignments column to a faculty identifier.",
+        ));
+    }
+
+    let domain_aliases = build_email_domain_aliases(&payload.email_domain_aliases);
+    let strip_diacritics = configured_strip_identifier_diacritics(app_handle)?;
+    let faculty_key = |values: &[&str]| -> String {
+        let parts: Vec<String> = values
+            .iter()
+            .map(|value| {
+                if value.contains('@') {
+                    normalize_email_value(value, &domain_aliases, strip_diacritics)
+                } else {
+                    normalize_identifier_value(value, strip_diacritics)
+                }
+            })
+            .collect();
+        if parts.iter().any(String::is_empty) {
+            String::new()
+        } else {
+            parts.join("|")
+        }
     };
+    let mut faculty_index: HashMap<String, usize> = HashMap::new();
+    for entry in &index.entries {
+        let values: Vec<&str> = faculty_identifiers
+            .iter()
+            .map(|identifier| {
+                entry
+                    .identifiers
+                    .get(identifier)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            })
+            .collect();
+        let key = faculty_key(&values);
+        if !key.is_empty() {
+            faculty_index.entry(key).or_insert(entry.row_index);
+        }
+    }
 
-    serde_json::from_slice(&data)
-        .map_err(|err| format!("Unable to parse faculty embeddings: {err}"))
-}
+    emit_matching_phase(app_handle, request_id, "reading-input", None);
+    let mut lookup_columns = payload.student_columns.clone();
+    lookup_columns.extend(faculty_columns.iter().cloned());
+    lookup_columns.extend(document_column.iter().cloned());
+    let PromptSpreadsheetRows {
+        rows,
+        row_numbers,
+        prompt_indexes,
+        identifier_indexes: lookup_indexes,
+    } = read_prompt_spreadsheet(
+        &SpreadsheetSource::Path(assignments_path.clone()),
+        &payload.prompt_columns,
+        &lookup_columns,
+    )
+    .map_err(|message| AppError::invalid_input("assignmentsPath", message))?;
+    let (student_indexes, rest) = lookup_indexes.split_at(payload.student_columns.len());
+    let (faculty_indexes, document_index) = rest.split_at(faculty_columns.len());
+    let document_base = assignments_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
 
-fn embed_prompt(
-    app_handle: &tauri::AppHandle,
-    index: &FacultyEmbeddingIndex,
-    prompt: &str,
-) -> Result<Vec<f32>, String> {
-    let model = if index.model.trim().is_empty() {
-        DEFAULT_EMBEDDING_MODEL.to_string()
-    } else {
-        index.model.clone()
-    };
+    let mut warnings = Vec::new();
+    let mut students: Vec<EvaluationStudent> = Vec::new();
+    let mut student_lookup: HashMap<String, usize> = HashMap::new();
+    let mut unmatched_faculty = BTreeSet::new();
+    for (row, &row_number) in rows.iter().zip(&row_numbers) {
+        let cell = |index: usize| row.get(index).map(|value| value.trim()).unwrap_or_default();
+        let student_values: Vec<&str> = student_indexes.iter().map(|&index| cell(index)).collect();
+        let prompt_parts = spreadsheet_prompt_parts(row, &prompt_indexes);
+        let student_key = if student_values.iter().any(|value| !value.is_empty()) {
+            student_values
+                .iter()
+                .map(|value| normalize_identifier_value(value, strip_diacritics))
+                .collect::<Vec<_>>()
+                .join("|")
+        } else {
+            format!("row:{row_number}")
+        };
 
-    let payload = EmbeddingRequestPayload {
-        model,
-        texts: vec![EmbeddingRequestRow {
-            id: 0,
-            text: prompt.to_string(),
-        }],
-        item_label: Some("text query".into()),
-        item_label_plural: Some("text queries".into()),
-    };
+        let position = match student_lookup.get(&student_key) {
+            Some(&position) => position,
+            None => {
+                let label = if student_values.iter().any(|value| !value.is_empty()) {
+                    student_values
+                        .iter()
+                        .filter(|value| !value.is_empty())
+                        .copied()
+                        .collect::<Vec<_>>()
+                        .join(" – ")
+                } else {
+                    format!("Row {row_number}")
+                };
+                students.push(EvaluationStudent {
+                    label,
+                    prompt: None,
+                    faculty: Vec::new(),
+                });
+                student_lookup.insert(student_key, students.len() - 1);
+                students.len() - 1
+            }
+        };
+        let student = &mut students[position];
+
+        if student.prompt.is_none() {
+            if !prompt_parts.is_empty() {
+                student.prompt = Some(prompt_parts.join("\n\n"));
+            } else if let Some(&index) = document_index.first() {
+                let value = cell(index);
+                if !value.is_empty() {
+                    let mut document = expand_user_path(trim_pasted_path(value));
+                    if document.is_relative() {
+                        document = document_base.join(document);
+                    }
+                    match extract_document_prompt(&document) {
+                        Ok(extracted) if !extracted.text.trim().is_empty() => {
+                            student.prompt = Some(extracted.text);
+                        }
+                        Ok(_) => warnings.push(format!(
+                            "The document for {} did not contain readable text.",
+                            student.label
+                        )),
+                        Err(err) => warnings.push(format!("{}: {err}", student.label)),
+                    }
+                }
+            }
+        }
 
-    let response = run_embedding_helper(app_handle, &payload)?;
-    if response.rows.is_empty() {
-        return Err("The embedding helper did not return an embedding for the prompt.".into());
+        let faculty_values: Vec<&str> = faculty_indexes.iter().map(|&index| cell(index)).collect();
+        if faculty_values.iter().all(|value| value.is_empty()) {
+            continue;
+        }
+        let faculty_label = faculty_values
+            .iter()
+            .filter(|value| !value.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" / ");
+        match faculty_index.get(&faculty_key(&faculty_values)) {
+            Some(&faculty_row) => {
+                if !student.faculty.iter().any(|(_, row)| *row == faculty_row) {
+                    student.faculty.push((faculty_label, faculty_row));
+                }
+            }
+            None => {
+                unmatched_faculty.insert(faculty_label);
+            }
+        }
     }
 
-    let embedding = response.rows.into_iter().next().unwrap().embedding;
-    if embedding.len() != index.dimension {
-        return Err(format!(
-            "The prompt embedding dimension ({}) does not match the faculty embedding dimension ({}).",
-            embedding.len(),
-            index.dimension
+    let mut unmatched_students = Vec::new();
+    let mut contexts = Vec::new();
+    for (position, student) in students.iter().enumerate() {
+        match (&student.prompt, student.faculty.is_empty()) {
+            (Some(prompt), false) => contexts.push(EmbeddingRequestRow {
+                id: position,
+                text: prompt.clone(),
+            }),
+            (None, _) => unmatched_students.push(format!("{} (no prompt text)", student.label)),
+            (Some(_), true) => unmatched_students.push(format!(
+                "{} (no assigned faculty found in the dataset)",
+                student.label
+            )),
+        }
+    }
+    if contexts.is_empty() {
+        return Err(AppError::invalid_input(
+            "assignmentsPath",
+            "None of the students in the assignments spreadsheet have both prompt text and an assigned faculty member in the dataset.",
         ));
     }
 
+    let programs = normalize_programs(payload.program_filters);
+    let allowed_rows = if programs.is_empty() {
+        None
+    } else {
+        let metadata = load_faculty_dataset_metadata(app_handle)?.ok_or_else(|| {
+            AppError::MissingDataset {
+                message: "The faculty dataset metadata is unavailable. Refresh the dataset analysis before filtering by program.".into(),
+            }
+        })?;
+        let outcome = filter_faculty_rows_by_program(&metadata, &programs);
+        warnings.extend(outcome.warnings);
+        Some(outcome.rows)
+    };
+
+    let request = EmbeddingRequestPayload {
+        model: if index.model.trim().is_empty() {
+            DEFAULT_EMBEDDING_MODEL.to_string()
+        } else {
+            index.model.clone()
+        },
+        texts: contexts,
+        item_label: Some("student".into()),
+        item_label_plural: Some("students".into()),
+    };
+    let response = run_embedding_helper_with_progress(app_handle, &request, Some(request_id))?;
     if response.dimension != index.dimension {
-        return Err(format!(
-            "The embedding helper reported dimension {} but the faculty index uses {}.",
-            response.dimension, index.dimension
-        ));
+        return Err(AppError::StaleEmbeddings {
+            message: format!(
+                "The student embedding dimension ({}) does not match the faculty embedding dimension ({}).",
+                response.dimension, index.dimension
+            ),
+        });
     }
+    emit_matching_phase(app_handle, request_id, "scoring", None);
 
-    Ok(embedding)
-}
-
-fn find_best_faculty_matches(
-    index: &FacultyEmbeddingIndex,
-    prompt_embedding: &[f32],
-    limit: usize,
-    allowed_rows: Option<&HashSet<usize>>,
-) -> Vec<FacultyMatchResult> {
-    if limit == 0 {
-        return Vec::new();
+    let mut embedding_map: HashMap<usize, Vec<f32>> = response
+        .rows
+        .into_iter()
+        .map(|row| (row.id, row.embedding))
+        .collect();
+    let mut assignments = Vec::new();
+    let mut students_evaluated = 0usize;
+    for (position, student) in students.iter().enumerate() {
+        if student.prompt.is_none() || student.faculty.is_empty() {
+            continue;
+        }
+        let Some(embedding) = embedding_map.remove(&position) else {
+            unmatched_students.push(format!("{} (no embedding returned)", student.label));
+            continue;
+        };
+        students_evaluated += 1;
+        let ranking = find_best_faculty_matches(
+            &index,
+            &embedding,
+            index.entries.len(),
+            allowed_rows.as_ref(),
+        );
+        for (faculty, faculty_row) in &student.faculty {
+            let found = ranking
+                .iter()
+                .position(|candidate| candidate.row_index == *faculty_row);
+            assignments.push(EvaluationAssignment {
+                student: student.label.clone(),
+                faculty: faculty.clone(),
+                rank: found.map(|position| position + 1),
+                similarity: found.map(|position| ranking[position].similarity),
+            });
+        }
     }
 
-    let mut candidates: Vec<FacultyMatchResult> = index
-        .entries
-        .iter()
-        .filter_map(|entry| {
-            if let Some(allowed) = allowed_rows {
-                if !allowed.contains(&entry.row_index) {
-                    return None;
-                }
+    let (metrics, mean_reciprocal_rank) = compute_evaluation_metrics(&assignments, &k_values);
+    if !unmatched_faculty.is_empty() {
+        warnings.push(format!(
+            "{count} assigned faculty member{plural} could not be found in the faculty dataset.",
+            count = unmatched_faculty.len(),
+            plural = if unmatched_faculty.len() == 1 {
+                ""
+            } else {
+                "s"
             }
-
-            if entry.embedding.len() != prompt_embedding.len() {
-                return None;
+        ));
+    }
+    if !unmatched_students.is_empty() {
+        warnings.push(format!(
+            "{count} student{plural} could not be evaluated.",
+            count = unmatched_students.len(),
+            plural = if unmatched_students.len() == 1 {
+                ""
+            } else {
+                "s"
             }
+        ));
+    }
 
-            let similarity = cosine_similarity(prompt_embedding, &entry.embedding)?;
-            let mut identifiers = entry.identifiers.clone();
-            identifiers.retain(|_, value| !value.trim().is_empty());
+    let mut report = EvaluationReport {
+        request_id: request_id.to_string(),
+        students_evaluated,
+        assignments_evaluated: assignments.len(),
+        metrics,
+        mean_reciprocal_rank,
+        assignments,
+        unmatched_students,
+        unmatched_faculty: unmatched_faculty.into_iter().collect(),
+        warnings,
+        workbook: None,
+    };
+    if payload.include_workbook {
+        let workbook = build_evaluation_workbook(&report)?;
+        report.workbook = Some(GeneratedSpreadsheet {
+            filename: "DBBS_evaluation.xlsx".into(),
+            mime_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".into(),
+            content: Base64Engine.encode(workbook),
+            encoding: Some("base64".into()),
+            companion_files: Vec::new(),
+            saved_path: None,
+            saved_bytes: None,
+            write_duration_ms: None,
+        });
+    }
+    emit_matching_phase(app_handle, request_id, "complete", None);
+    Ok(report)
+}
 
-            Some(FacultyMatchResult {
-                row_index: entry.row_index,
-                similarity,
-                identifiers,
-                faculty_text: None,
-                student_rank_for_faculty: None,
-                student_rank_total: None,
-            })
+fn compute_evaluation_metrics(
+    assignments: &[EvaluationAssignment],
+    k_values: &[usize],
+) -> (Vec<EvaluationMetric>, f64) {
+    let total = assignments.len();
+    let metrics = k_values
+        .iter()
+        .map(|&k| {
+            let hits = assignments
+                .iter()
+                .filter(|assignment| assignment.rank.is_some_and(|rank| rank <= k))
+                .count();
+            EvaluationMetric {
+                k,
+                hits,
+                recall: if total == 0 {
+                    0.0
+                } else {
+                    hits as f64 / total as f64
+                },
+            }
         })
         .collect();
-
-    candidates.sort_by(|a, b| {
-        b.similarity
-            .partial_cmp(&a.similarity)
-            .unwrap_or(Ordering::Equal)
-    });
-    candidates.truncate(limit);
-    candidates
+    let mean_reciprocal_rank = if total == 0 {
+        0.0
+    } else {
+        assignments
+            .iter()
+            .filter_map(|assignment| assignment.rank)
+            .map(|rank| 1.0 / rank as f64)
+            .sum::<f64>()
+            / total as f64
+    };
+    (metrics, mean_reciprocal_rank)
 }
 
-fn assign_student_rankings(match_sets: &mut [&mut Vec<FacultyMatchResult>]) {
-    if match_sets.is_empty() {
-        return;
-    }
-
-    let mut occurrences: HashMap<usize, Vec<(usize, usize, f32)>> = HashMap::new();
+fn build_evaluation_workbook(report: &EvaluationReport) -> Result<Vec<u8>, String> {
+    let mut summary_rows: Vec<Vec<String>> = report
+        .metrics
+        .iter()
+        .map(|metric| {
+            vec![
+                format!("Recall@{}", metric.k),
+                format!("{:.1}%", metric.recall * 100.0),
+                format!("{} of {}", metric.hits, report.assignments_evaluated),
+            ]
+        })
+        .collect();
+    summary_rows.push(vec![
+        "Mean reciprocal rank".into(),
+        format!("{:.3}", report.mean_reciprocal_rank),
+        String::new(),
+    ]);
+    summary_rows.push(vec![
+        "Students evaluated".into(),
+        report.students_evaluated.to_string(),
+        String::new(),
+    ]);
+    let assignment_rows: Vec<Vec<String>> = report
+        .assignments
+        .iter()
+        .map(|assignment| {
+            vec![
+                assignment.student.clone(),
+                assignment.faculty.clone(),
+                assignment
+                    .rank
+                    .map(|rank| rank.to_string())
+                    .unwrap_or_else(|| "Not ranked".into()),
+                assignment
+                    .similarity
+                    .map(format_similarity_percent)
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let unmatched_rows: Vec<Vec<String>> = report
+        .unmatched_students
+        .iter()
+        .map(|student| vec!["Student".to_string(), student.clone()])
+        .chain(
+            report
+                .unmatched_faculty
+                .iter()
+                .map(|faculty| vec!["Faculty".to_string(), faculty.clone()]),
+        )
+        .collect();
 
-    for (prompt_index, matches) in match_sets.iter().enumerate() {
-        for (match_index, faculty) in matches.iter().enumerate() {
-            occurrences.entry(faculty.row_index).or_default().push((
-                prompt_index,
-                match_index,
-                faculty.similarity,
-            ));
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+    let sheets = [
+        (
+            "Summary",
+            vec!["Metric".into(), "Value".into(), "Detail".into()],
+            &summary_rows,
+        ),
+        (
+            "Assignments",
+            vec![
+                "Student".into(),
+                "Assigned Faculty".into(),
+                "Rank".into(),
+                "Similarity".into(),
+            ],
+            &assignment_rows,
+        ),
+        (
+            "Unmatched",
+            vec!["Type".into(), "Label".into()],
+            &unmatched_rows,
+        ),
+    ];
+    for (name, headers, rows) in sheets {
+        let sheet = workbook.add_worksheet();
+        sheet
+            .set_name(name)
+            .map_err(|err| format!("Unable to configure the {name} worksheet: {err}"))?;
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the {name} header row: {err}"))?;
         }
-    }
-
-    if occurrences.is_empty() {
-        for matches in match_sets.iter_mut() {
-            for faculty in matches.iter_mut() {
-                faculty.student_rank_for_faculty = None;
-                faculty.student_rank_total = None;
+        for (row_index, values) in rows.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                sheet
+                    .write_string((row_index + 1) as u32, col as u16, excel_cell_text(value))
+                    .map_err(|err| format!("Unable to write the {name} worksheet: {err}"))?;
             }
         }
-        return;
+        apply_column_widths(sheet, &measure_column_widths(&headers, rows.iter()))?;
     }
 
-    let mut rank_map: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
-
-    for (_, mut entries) in occurrences {
-        entries.sort_by(|a, b| {
-            let primary = b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal);
-            if primary != Ordering::Equal {
-                return primary;
-            }
-
-            let secondary = a.0.cmp(&b.0);
-            if secondary != Ordering::Equal {
-                return secondary;
-            }
+    workbook
+        .save_to_buffer()
+        .map_err(|err| format!("Unable to finalize the evaluation workbook: {err}"))
+}
 
-            a.1.cmp(&b.1)
-        });
+const DEFAULT_REQUIRED_REVIEWERS: usize = 2;
+
+/// The fixed columns that `build_matches_export_headers` writes around the identifier block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewedColumn {
+    FirstReviewer,
+    Reviewer,
+    Similarity,
+    RawCosine,
+    StudentRank,
+    FacultyRank,
+    PromptText,
+    FacultyText,
+    FacultyId,
+}
 
-        let total = entries.len();
-        for (position, (prompt_index, match_index, _)) in entries.into_iter().enumerate() {
-            rank_map.insert((prompt_index, match_index), (position + 1, total));
+impl ReviewedColumn {
+    fn header(self) -> &'static str {
+        match self {
+            Self::FirstReviewer => "First reviewer",
+            Self::Reviewer => "Reviewer",
+            Self::Similarity => "Similarity %",
+            Self::RawCosine => "Cosine (raw)",
+            Self::StudentRank => "Student rank",
+            Self::FacultyRank => "Faculty rank",
+            Self::PromptText => "Prompt text",
+            Self::FacultyText => "Faculty text",
+            Self::FacultyId => "Faculty ID",
         }
     }
 
-    for (prompt_index, matches) in match_sets.iter_mut().enumerate() {
-        for (match_index, faculty) in matches.iter_mut().enumerate() {
-            if let Some((rank, total)) = rank_map.get(&(prompt_index, match_index)) {
-                faculty.student_rank_for_faculty = Some(*rank);
-                faculty.student_rank_total = Some(*total);
+    /// Recognizes the generated headers loosely so small edits such as "First Reviewer(s)"
+    /// or "Similarity" still resolve to the right column.
+    fn classify(header: &str) -> Option<Self> {
+        let key = normalize_identifier_label(header);
+        if key.contains("review") {
+            return Some(if key.contains("first") {
+                Self::FirstReviewer
             } else {
-                faculty.student_rank_for_faculty = None;
-                faculty.student_rank_total = None;
-            }
+                Self::Reviewer
+            });
+        }
+        if key.contains("similarity") {
+            Some(Self::Similarity)
+        } else if key.contains("cosine") {
+            Some(Self::RawCosine)
+        } else if key.contains("rank") && key.contains("student") {
+            Some(Self::StudentRank)
+        } else if key.contains("rank") && key.contains("faculty") {
+            Some(Self::FacultyRank)
+        } else if key.contains("prompt") {
+            Some(Self::PromptText)
+        } else if key.contains("faculty") && key.contains("text") {
+            Some(Self::FacultyText)
+        } else if key == "facultyid" {
+            Some(Self::FacultyId)
+        } else {
+            None
         }
     }
 }
 
-fn enrich_matches_with_faculty_text(
-    app_handle: &tauri::AppHandle,
-    embedding_columns: &[String],
-    matches: &mut [FacultyMatchResult],
-) -> Result<(), String> {
-    if matches.is_empty() {
-        return Ok(());
-    }
+struct ReviewedSheetLayout {
+    first_reviewer: usize,
+    reviewer: usize,
+    student_columns: Vec<usize>,
+    faculty_columns: Vec<usize>,
+}
 
-    if embedding_columns.is_empty() {
-        return Ok(());
-    }
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReviewedStudent {
+    label: String,
+    identifier_values: Vec<String>,
+    first_reviewer: Option<String>,
+    first_reviewer_count: usize,
+    reviewers: Vec<String>,
+}
 
-    let dataset_path = dataset_destination(app_handle)?;
-    if !dataset_path.exists() {
-        return Err("The faculty dataset could not be located.".into());
-    }
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReviewedFacultyLoad {
+    faculty: String,
+    first_reviewer_count: usize,
+    reviewer_count: usize,
+    over_cap: bool,
+}
 
-    let (headers, rows) = read_full_spreadsheet(&dataset_path)?;
-    if rows.is_empty() {
-        return Err("The faculty dataset does not include any rows.".into());
-    }
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReviewedWorkbookReport {
+    sheets: Vec<String>,
+    rows_read: usize,
+    required_reviewers: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faculty_cap: Option<usize>,
+    student_headers: Vec<String>,
+    students: Vec<ReviewedStudent>,
+    students_complete: usize,
+    faculty_loads: Vec<ReviewedFacultyLoad>,
+    issues: Vec<String>,
+    warnings: Vec<String>,
+    assignments: GeneratedSpreadsheet,
+}
 
-    let header_map = build_header_index_map(&headers);
-    let embedding_indexes = indexes_from_labels(&header_map, embedding_columns)?;
-    if embedding_indexes.is_empty() {
-        return Err(
-            "No embedding columns are available to retrieve faculty text. Re-run the dataset analysis.".into(),
-        );
+#[tauri::command]
+fn import_reviewed_workbook(
+    path: String,
+    required_reviewers: Option<usize>,
+    faculty_cap: Option<usize>,
+) -> Result<ReviewedWorkbookReport, AppError> {
+    if path.trim().is_empty() {
+        return Err(AppError::invalid_input(
+            "path",
+            "Provide the reviewed workbook to import.",
+        ));
     }
-
-    for faculty_match in matches {
-        if let Some(row) = rows.get(faculty_match.row_index) {
-            let mut text_parts = Vec::new();
-            for &index in &embedding_indexes {
-                if let Some(value) = row.get(index) {
-                    let trimmed = value.trim();
-                    if !trimmed.is_empty() {
-                        text_parts.push(trimmed.to_string());
-                    }
-                }
-            }
-
-            if !text_parts.is_empty() {
-                faculty_match.faculty_text = Some(text_parts.join("\n\n"));
-            }
-        }
+    let required_reviewers = required_reviewers.unwrap_or(DEFAULT_REQUIRED_REVIEWERS);
+    if required_reviewers == 0 {
+        return Err(AppError::invalid_input(
+            "requiredReviewers",
+            "Each student needs at least one reviewer.",
+        ));
+    }
+    if faculty_cap == Some(0) {
+        return Err(AppError::invalid_input(
+            "facultyCap",
+            "The faculty cap must allow at least one student.",
+        ));
     }
 
-    Ok(())
+    let workbook_path = resolve_existing_path(Some(path), false, "Reviewed workbook")?;
+    let mut workbook = open_workbook_auto(&workbook_path).map_err(|err| {
+        AppError::io(
+            &workbook_path,
+            format!("Unable to open the workbook: {err}"),
+        )
+    })?;
+    let mut report = read_reviewed_workbook(&mut workbook, required_reviewers, faculty_cap)?;
+    let stem = workbook_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "reviewed".into());
+    report.assignments.filename = format!("{}_final_assignments.xlsx", sanitize_filename(&stem));
+    Ok(report)
 }
 
-fn process_directory_documents(
-    app_handle: &tauri::AppHandle,
-    directory: &Path,
-    index: &FacultyEmbeddingIndex,
-    limit: usize,
-    allowed_rows: Option<&HashSet<usize>>,
-) -> Result<DirectoryProcessingOutcome, String> {
-    #[derive(Debug)]
-    struct DirectoryDocumentContext {
-        result_index: usize,
-        prompt: String,
+fn read_reviewed_workbook<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    required_reviewers: usize,
+    faculty_cap: Option<usize>,
+) -> Result<ReviewedWorkbookReport, String> {
+    let sheet_names = workbook.sheet_names().to_vec();
+    let matches_sheets: Vec<String> = sheet_names
+        .iter()
+        .filter(|name| {
+            normalize_identifier_label(name)
+                .strip_prefix("matches")
+                .is_some_and(|part| part.chars().all(|ch| ch.is_ascii_digit()))
+        })
+        .cloned()
+        .collect();
+    if matches_sheets.is_empty() {
+        return Err("The workbook does not contain a Matches worksheet.".into());
     }
+    let mut summary_identifiers = |sheet: &str, anchor: &str| -> Option<Vec<String>> {
+        let name = sheet_names
+            .iter()
+            .find(|name| normalize_identifier_label(name) == sheet)?;
+        let (rows, _) = read_reviewed_sheet(workbook, name).ok()?;
+        let headers = rows.into_iter().next()?;
+        let end = headers
+            .iter()
+            .position(|header| normalize_identifier_label(header) == anchor)?;
+        Some(headers[..end].to_vec())
+    };
+    let student_names = summary_identifiers("studentsummary", "totalfirstreviewers");
+    let faculty_names = summary_identifiers("facultysummary", "firstreviewercount");
+
+    let mut warnings = WarningCollector::default();
+    let mut student_headers: Option<Vec<String>> = None;
+    let mut faculty_headers: Option<Vec<String>> = None;
+    let mut sheets = Vec::new();
+    let mut rows_read = 0usize;
+    let mut students: Vec<ReviewedStudent> = Vec::new();
+    let mut student_positions: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut faculty_loads: Vec<ReviewedFacultyLoad> = Vec::new();
+    let mut faculty_positions: HashMap<String, usize> = HashMap::new();
+    for sheet in &matches_sheets {
+        let (rows, first_row) = read_reviewed_sheet(workbook, sheet)?;
+        let Some(headers) = rows.first() else {
+            warnings.push(format!("The '{sheet}' worksheet is empty and was skipped."));
+            continue;
+        };
+        let layout = warnings.append_with(|warnings| {
+            resolve_reviewed_layout(
+                headers,
+                student_names.as_deref(),
+                faculty_names.as_deref(),
+                sheet,
+                warnings,
+            )
+        })?;
+        let labels = |columns: &[usize]| -> Vec<String> {
+            columns
+                .iter()
+                .map(|&col| header_label(headers, col))
+                .collect()
+        };
+        let (sheet_students, sheet_faculty) = (
+            labels(&layout.student_columns),
+            labels(&layout.faculty_columns),
+        );
+        if student_headers
+            .as_ref()
+            .is_some_and(|expected| *expected != sheet_students)
+            || faculty_headers
+                .as_ref()
+                .is_some_and(|expected| *expected != sheet_faculty)
+        {
+            warnings.push(format!(
+                "The '{sheet}' worksheet uses different identifier columns from the first \
+                 Matches worksheet and was skipped."
+            ));
+            continue;
+        }
+        student_headers.get_or_insert(sheet_students);
+        faculty_headers.get_or_insert(sheet_faculty);
+        sheets.push(sheet.clone());
 
-    #[derive(Debug)]
-    struct DirectoryDocumentResult {
-        identifier: String,
-        preview: String,
-        prompt_label: Option<String>,
-        matches: Vec<FacultyMatchResult>,
-        status_message: Option<String>,
-    }
+        let cell = |row: &[String], col: usize| row.get(col).cloned().unwrap_or_default();
+        for (offset, row) in rows.iter().enumerate().skip(1) {
+            if row.iter().all(|value| value.is_empty()) {
+                continue;
+            }
+            rows_read += 1;
+            let row_number = first_row + offset;
+            let mut marked = |col: usize, column: ReviewedColumn| {
+                let value = cell(row, col);
+                reviewed_mark(&value).unwrap_or_else(|| {
+                    warnings.push(format!(
+                        "Row {row_number} on '{sheet}' has '{value}' in the {} column; only 1 \
+                         or TRUE count as an assignment.",
+                        column.header()
+                    ));
+                    false
+                })
+            };
+            let first = marked(layout.first_reviewer, ReviewedColumn::FirstReviewer);
+            let reviewer = marked(layout.reviewer, ReviewedColumn::Reviewer);
 
-    let mut warnings = Vec::new();
-    let mut document_results: Vec<DirectoryDocumentResult> = Vec::new();
-    let mut contexts: Vec<DirectoryDocumentContext> = Vec::new();
-    let mut file_paths: Vec<PathBuf> = Vec::new();
+            let identifier_values: Vec<String> = layout
+                .student_columns
+                .iter()
+                .map(|&col| cell(row, col))
+                .collect();
+            let position = *student_positions
+                .entry(identifier_values.clone())
+                .or_insert_with(|| {
+                    let label = identifier_values
+                        .iter()
+                        .filter(|value| !value.is_empty())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" / ");
+                    students.push(ReviewedStudent {
+                        label: if label.is_empty() {
+                            format!("Row {row_number}")
+                        } else {
+                            label
+                        },
+                        identifier_values,
+                        first_reviewer: None,
+                        first_reviewer_count: 0,
+                        reviewers: Vec::new(),
+                    });
+                    students.len() - 1
+                });
+            if !first && !reviewer {
+                continue;
+            }
 
-    let reader = fs::read_dir(directory).map_err(|err| {
-        format!(
-            "Unable to read the directory '{}': {err}",
-            directory.display()
-        )
-    })?;
+            let faculty = layout
+                .faculty_columns
+                .iter()
+                .map(|&col| cell(row, col))
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+                .join(" / ");
+            if faculty.is_empty() {
+                warnings.push(format!(
+                    "Row {row_number} on '{sheet}' is marked as a reviewer but has no faculty \
+                     identifiers, so it was skipped."
+                ));
+                continue;
+            }
+            let student = &mut students[position];
+            if first {
+                student
+                    .reviewers
+                    .insert(student.first_reviewer_count, faculty.clone());
+                student.first_reviewer_count += 1;
+                student
+                    .first_reviewer
+                    .get_or_insert_with(|| faculty.clone());
+            } else {
+                student.reviewers.push(faculty.clone());
+            }
+            let load_position = *faculty_positions.entry(faculty.clone()).or_insert_with(|| {
+                faculty_loads.push(ReviewedFacultyLoad {
+                    faculty,
+                    first_reviewer_count: 0,
+                    reviewer_count: 0,
+                    over_cap: false,
+                });
+                faculty_loads.len() - 1
+            });
+            let load = &mut faculty_loads[load_position];
+            load.first_reviewer_count += usize::from(first);
+            load.reviewer_count += 1;
+        }
+    }
 
-    for entry in reader {
-        match entry {
-            Ok(entry) => {
-                let path = entry.path();
-                match entry.file_type() {
-                    Ok(file_type) => {
-                        if file_type.is_file() {
-                            file_paths.push(path);
-                        }
-                    }
-                    Err(err) => warnings.push(format!(
-                        "Skipped '{}': unable to determine the file type ({err}).",
-                        path.to_string_lossy()
-                    )),
-                }
+    let mut issues = Vec::new();
+    let mut students_complete = 0usize;
+    for student in &students {
+        let reviewer_count = student.reviewers.len();
+        let mut complete = true;
+        if reviewer_count != required_reviewers {
+            complete = false;
+            issues.push(format!(
+                "{} has {reviewer_count} of the {required_reviewers} required reviewers.",
+                student.label
+            ));
+        }
+        if student.first_reviewer_count != 1 {
+            complete = false;
+            issues.push(if student.first_reviewer_count == 0 {
+                format!("{} has no first reviewer.", student.label)
+            } else {
+                format!(
+                    "{} has {} first reviewers; only one should be marked.",
+                    student.label, student.first_reviewer_count
+                )
+            });
+        }
+        students_complete += usize::from(complete);
+    }
+    if let Some(cap) = faculty_cap {
+        for load in &mut faculty_loads {
+            if load.reviewer_count > cap {
+                load.over_cap = true;
+                issues.push(format!(
+                    "{} reviews {} students, above the cap of {cap}.",
+                    load.faculty, load.reviewer_count
+                ));
             }
-            Err(err) => warnings.push(format!(
-                "Unable to read an entry in '{}': {err}",
-                directory.display()
-            )),
         }
     }
 
-    file_paths.sort();
+    let student_headers = student_headers.unwrap_or_default();
+    let workbook = build_final_assignments_workbook(
+        &student_headers,
+        &students,
+        &faculty_loads,
+        required_reviewers,
+        &issues,
+    )?;
+    Ok(ReviewedWorkbookReport {
+        sheets,
+        rows_read,
+        required_reviewers,
+        faculty_cap,
+        student_headers,
+        students,
+        students_complete,
+        faculty_loads,
+        issues,
+        warnings: warnings.into_vec(),
+        assignments: GeneratedSpreadsheet {
+            filename: "final_assignments.xlsx".into(),
+            mime_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".into(),
+            content: Base64Engine.encode(workbook),
+            encoding: Some("base64".into()),
+            companion_files: Vec::new(),
+            saved_path: None,
+            saved_bytes: None,
+            write_duration_ms: None,
+        },
+    })
+}
 
-    for path in file_paths {
-        let identifier = path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+fn read_reviewed_sheet<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    name: &str,
+) -> Result<(Vec<Vec<String>>, usize), String> {
+    let range = workbook
+        .worksheet_range(name)
+        .ok_or_else(|| format!("Unable to read the worksheet named '{name}'."))?
+        .map_err(|err| format!("Unable to read the '{name}' worksheet: {err}"))?;
+    let first_row = range.start().map(|(row, _)| row as usize + 1).unwrap_or(1);
+    let rows = range
+        .rows()
+        .map(|row| row.iter().map(cell_to_string).collect())
+        .collect();
+    Ok((rows, first_row))
+}
 
-        let mut result = DirectoryDocumentResult {
-            identifier: identifier.clone(),
-            preview: String::new(),
-            prompt_label: None,
-            matches: Vec::new(),
-            status_message: None,
-        };
-        let mut prompt_text: Option<String> = None;
+/// Reads a reviewer cell the way the summary formulas do: 1 or TRUE assigns, blank, 0 or
+/// FALSE does not, and anything else is reported back to the caller.
+fn reviewed_mark(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" => Some(true),
+        "" | "0" | "false" => Some(false),
+        _ => None,
+    }
+}
 
-        match extract_document_prompt(&path) {
-            Ok(DocumentExtractionResult {
-                text,
-                warnings: extraction_warnings,
-            }) => {
-                for warning in extraction_warnings {
-                    warnings.push(format!("{identifier}: {warning}"));
-                }
+/// Locates the reviewer columns and splits the identifier block between them and
+/// "Similarity %" into student and faculty columns. The summary sheets name the identifier
+/// headers; when they are missing or were edited, the split falls back to position.
+fn resolve_reviewed_layout(
+    headers: &[String],
+    student_names: Option<&[String]>,
+    faculty_names: Option<&[String]>,
+    sheet: &str,
+    warnings: &mut Vec<String>,
+) -> Result<ReviewedSheetLayout, String> {
+    let roles: Vec<Option<ReviewedColumn>> = headers
+        .iter()
+        .map(|header| ReviewedColumn::classify(header))
+        .collect();
+    let find = |role: ReviewedColumn, from: usize| {
+        (from..headers.len())
+            .find(|&col| roles[col] == Some(role))
+            .ok_or_else(|| {
+                format!(
+                    "The '{sheet}' worksheet does not have a '{}' column.",
+                    role.header()
+                )
+            })
+    };
+    let first_reviewer = find(ReviewedColumn::FirstReviewer, 0)?;
+    let reviewer = find(ReviewedColumn::Reviewer, 0)?;
+    let block_start = first_reviewer.max(reviewer) + 1;
+    let similarity = find(ReviewedColumn::Similarity, block_start)?;
 
-                if text.trim().is_empty() {
-                    let message =
-                        format!("Skipped '{identifier}' because it did not contain readable text.");
-                    warnings.push(message.clone());
-                    result.status_message = Some(message);
-                } else {
-                    result.preview = build_prompt_preview(&text);
-                    if result.preview.is_empty() {
-                        result.prompt_label = Some(result.identifier.clone());
-                    } else {
-                        result.prompt_label =
-                            Some(format!("{} — {}", result.identifier, result.preview));
-                    }
-                    prompt_text = Some(text);
+    for (col, header) in headers.iter().enumerate() {
+        if (block_start..similarity).contains(&col) {
+            continue;
+        }
+        match roles[col] {
+            Some(role)
+                if col == first_reviewer
+                    || col == reviewer
+                    || col == similarity
+                    || (col > similarity
+                        && !matches!(
+                            role,
+                            ReviewedColumn::FirstReviewer
+                                | ReviewedColumn::Reviewer
+                                | ReviewedColumn::Similarity
+                        )) =>
+            {
+                if header.trim() != role.header() {
+                    warnings.push(format!(
+                        "Treating the '{}' column on '{sheet}' as '{}'.",
+                        header.trim(),
+                        role.header()
+                    ));
                 }
             }
-            Err(err) => {
-                warnings.push(err.clone());
-                result.status_message = Some(err);
-            }
+            _ if header.trim().is_empty() => {}
+            _ => warnings.push(format!(
+                "Ignoring the '{}' column on '{sheet}', which is not part of the generated \
+                 layout.",
+                header.trim()
+            )),
         }
+    }
 
-        let result_index = document_results.len();
-        if let Some(text) = prompt_text {
-            contexts.push(DirectoryDocumentContext {
-                result_index,
-                prompt: text,
-            });
+    let block: Vec<usize> = (block_start..similarity).collect();
+    let claim = |columns: &[usize], names: &[String]| -> Option<(Vec<usize>, usize)> {
+        let mut claimed = Vec::new();
+        let mut cursor = 0usize;
+        for name in names {
+            let key = normalize_identifier_label(name);
+            let offset = columns[cursor..]
+                .iter()
+                .position(|&col| normalize_identifier_label(&headers[col]) == key)?;
+            claimed.push(columns[cursor + offset]);
+            cursor += offset + 1;
         }
-
-        document_results.push(result);
+        Some((claimed, cursor))
+    };
+    let (student_columns, student_end) = match student_names {
+        Some(names) => claim(&block, names).unwrap_or_else(|| {
+            warnings.push(format!(
+                "The student columns on '{sheet}' no longer match the Student Summary \
+                 headers; using the first {} identifier column(s).",
+                names.len()
+            ));
+            let count = names.len().min(block.len());
+            (block[..count].to_vec(), count)
+        }),
+        None => {
+            let count = faculty_names
+                .map(|names| block.len().saturating_sub(names.len()))
+                .unwrap_or(1)
+                .max(1)
+                .min(block.len());
+            warnings.push(format!(
+                "The workbook has no Student Summary sheet; treating the first {count} \
+                 identifier column(s) on '{sheet}' as student columns."
+            ));
+            (block[..count].to_vec(), count)
+        }
+    };
+    let remaining = &block[student_end..];
+    let faculty_columns = match faculty_names.and_then(|names| claim(remaining, names)) {
+        Some((claimed, _)) => claimed,
+        None => {
+            if faculty_names.is_some_and(|names| names.len() != remaining.len()) {
+                warnings.push(format!(
+                    "The faculty columns on '{sheet}' no longer match the Faculty Summary \
+                     headers; using every remaining identifier column."
+                ));
+            }
+            remaining.to_vec()
+        }
+    };
+    for &col in block
+        .iter()
+        .filter(|col| !student_columns.contains(col) && !faculty_columns.contains(col))
+    {
+        warnings.push(format!(
+            "Ignoring the '{}' column on '{sheet}', which is not part of the generated layout.",
+            header_label(headers, col)
+        ));
     }
-
-    if document_results.is_empty() {
-        warnings.push("The selected directory did not contain any files to process.".into());
+    if student_columns.is_empty() || faculty_columns.is_empty() {
+        return Err(format!(
+            "Unable to locate the student and faculty identifier columns on '{sheet}'."
+        ));
     }
 
-    let mut prompt_matches = Vec::new();
-    let mut missing_embeddings = 0usize;
+    Ok(ReviewedSheetLayout {
+        first_reviewer,
+        reviewer,
+        student_columns,
+        faculty_columns,
+    })
+}
 
-    if !contexts.is_empty() {
-        let model_name = if index.model.trim().is_empty() {
-            DEFAULT_EMBEDDING_MODEL.to_string()
-        } else {
-            index.model.clone()
-        };
-
-        let payload = EmbeddingRequestPayload {
-            model: model_name,
-            texts: contexts
-                .iter()
-                .enumerate()
-                .map(|(id, context)| EmbeddingRequestRow {
-                    id,
-                    text: context.prompt.clone(),
-                })
-                .collect(),
-            item_label: Some("document".into()),
-            item_label_plural: Some("documents".into()),
-        };
+fn build_final_assignments_workbook(
+    student_headers: &[String],
+    students: &[ReviewedStudent],
+    faculty_loads: &[ReviewedFacultyLoad],
+    required_reviewers: usize,
+    issues: &[String],
+) -> Result<Vec<u8>, String> {
+    let reviewer_columns = students
+        .iter()
+        .map(|student| student.reviewers.len())
+        .fold(required_reviewers, usize::max);
+    let mut assignment_headers = student_headers.to_vec();
+    assignment_headers.extend((1..=reviewer_columns).map(|slot| format!("Reviewer {slot}")));
+    let assignment_rows: Vec<Vec<String>> = students
+        .iter()
+        .map(|student| {
+            let mut row = student.identifier_values.clone();
+            row.extend(
+                (0..reviewer_columns)
+                    .map(|slot| student.reviewers.get(slot).cloned().unwrap_or_default()),
+            );
+            row
+        })
+        .collect();
+    let load_rows: Vec<Vec<String>> = faculty_loads
+        .iter()
+        .map(|load| {
+            vec![
+                load.faculty.clone(),
+                load.first_reviewer_count.to_string(),
+                load.reviewer_count.to_string(),
+            ]
+        })
+        .collect();
+    let issue_rows: Vec<Vec<String>> = issues.iter().map(|issue| vec![issue.clone()]).collect();
 
-        let response = run_embedding_helper(app_handle, &payload)?;
-        if response.dimension != index.dimension {
-            return Err(format!(
-                "The document embedding dimension ({}) does not match the faculty embedding dimension ({}).",
-                response.dimension, index.dimension
-            ));
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+    let mut sheets = vec![
+        ("Assignments", assignment_headers, &assignment_rows),
+        (
+            "Faculty Load",
+            vec![
+                "Faculty".into(),
+                "First reviewer count".into(),
+                "Total reviewer count".into(),
+            ],
+            &load_rows,
+        ),
+    ];
+    if !issue_rows.is_empty() {
+        sheets.push(("Issues", vec!["Issue".into()], &issue_rows));
+    }
+    for (name, headers, rows) in sheets {
+        let sheet = workbook.add_worksheet();
+        sheet
+            .set_name(name)
+            .map_err(|err| format!("Unable to configure the {name} worksheet: {err}"))?;
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the {name} header row: {err}"))?;
         }
-
-        let mut embedding_map: HashMap<usize, Vec<f32>> = HashMap::new();
-        for row in response.rows {
-            embedding_map.insert(row.id, row.embedding);
+        for (row_index, values) in rows.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                sheet
+                    .write_string((row_index + 1) as u32, col as u16, excel_cell_text(value))
+                    .map_err(|err| format!("Unable to write the {name} worksheet: {err}"))?;
+            }
         }
+        apply_column_widths(sheet, &measure_column_widths(&headers, rows.iter()))?;
+    }
 
-        for (context_index, context) in contexts.iter().enumerate() {
-            let identifier = document_results[context.result_index].identifier.clone();
+    workbook
+        .save_to_buffer()
+        .map_err(|err| format!("Unable to finalize the final assignments workbook: {err}"))
+}
 
-            match embedding_map.remove(&context_index) {
-                Some(embedding) => {
-                    let matches = find_best_faculty_matches(index, &embedding, limit, allowed_rows);
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ModelComparisonStudent {
+    student: String,
+    overlap: f64,
+    rank_correlation: Option<f64>,
+    shared_faculty: Vec<String>,
+    only_model_a: Vec<String>,
+    only_model_b: Vec<String>,
+}
 
-                    if matches.is_empty() {
-                        document_results[context.result_index].status_message =
-                            Some("No faculty matches were returned.".into());
-                    } else {
-                        document_results[context.result_index].status_message = None;
-                    }
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ModelComparisonReport {
+    request_id: String,
+    model_a: String,
+    model_b: String,
+    k: usize,
+    students_compared: usize,
+    mean_overlap: f64,
+    mean_rank_correlation: Option<f64>,
+    students: Vec<ModelComparisonStudent>,
+    warnings: Vec<String>,
+    workbook: GeneratedSpreadsheet,
+}
 
-                    document_results[context.result_index].matches = matches;
-                }
-                None => {
-                    missing_embeddings += 1;
-                    let message = "The embedding helper did not return a result for this document."
-                        .to_string();
-                    document_results[context.result_index].status_message = Some(message.clone());
-                    warnings.push(format!(
-                        "The embedding helper did not return an embedding for '{}'.",
-                        identifier
-                    ));
-                }
-            }
-        }
-    } else if !document_results.is_empty() {
-        warnings
-            .push("None of the files in the directory contained readable text to embed.".into());
-    }
+#[tauri::command]
+async fn compare_models(
+    app_handle: tauri::AppHandle,
+    payload: SubmissionPayload,
+    model_a: String,
+    model_b: String,
+) -> Result<ModelComparisonReport, AppError> {
+    let request_id = Uuid::new_v4().to_string();
+    let run_guard = begin_helper_activity(HelperActivity::Matching, Some(&request_id))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let _run_guard = run_guard;
+        perform_model_comparison(&app_handle, payload, &model_a, &model_b, &request_id)
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Model comparison task failed: {err}")))?
+}
 
-    {
-        let mut match_refs: Vec<&mut Vec<FacultyMatchResult>> = document_results
-            .iter_mut()
-            .map(|result| &mut result.matches)
-            .collect();
-        assign_student_rankings(&mut match_refs);
+fn perform_model_comparison(
+    app_handle: &dyn MatchingHost,
+    payload: SubmissionPayload,
+    model_a: &str,
+    model_b: &str,
+    request_id: &str,
+) -> Result<ModelComparisonReport, AppError> {
+    let (model_a, model_b) = (model_a.trim(), model_b.trim());
+    if model_a.is_empty() || model_b.is_empty() {
+        return Err(AppError::invalid_input(
+            "modelA",
+            "Choose the two embedding models to compare.",
+        ));
     }
-
-    for result in &document_results {
-        if let Some(label) = &result.prompt_label {
-            prompt_matches.push(PromptMatchResult {
-                prompt: label.clone(),
-                faculty_matches: result.matches.clone(),
-            });
-        }
+    if model_a == model_b {
+        return Err(AppError::invalid_input(
+            "modelB",
+            "Choose two different embedding models to compare.",
+        ));
+    }
+    if !matches!(payload.task_type, TaskType::Spreadsheet) {
+        return Err(AppError::invalid_input(
+            "taskType",
+            "Model comparisons run on a spreadsheet of student prompts.",
+        ));
     }
 
-    let processed_documents = if contexts.is_empty() {
-        0
+    let spreadsheet = match payload.spreadsheet_content.as_deref() {
+        Some(content) => SpreadsheetSource::Inline {
+            filename: inline_content_filename(
+                payload.spreadsheet_filename.as_deref(),
+                "spreadsheet.tsv",
+            ),
+            data: decode_inline_content(content, "spreadsheetContent", "spreadsheet")?,
+        },
+        None => SpreadsheetSource::Path(
+            resolve_existing_path(payload.spreadsheet_path, false, "Spreadsheet file")
+                .map_err(|message| AppError::invalid_input("spreadsheetPath", message))?,
+        ),
+    };
+    let prompt_columns = normalize_columns(payload.spreadsheet_prompt_columns);
+    if prompt_columns.is_empty() {
+        return Err(AppError::invalid_input(
+            "spreadsheetPromptColumns",
+            "Select at least one prompt column to compare models on.",
+        ));
+    }
+    let identifier_columns = normalize_columns(payload.spreadsheet_identifier_columns);
+    let k = payload
+        .faculty_recs_per_student
+        .map(|value| value as usize)
+        .filter(|value| *value > 0)
+        .unwrap_or(COMPARE_MODELS_DEFAULT_K);
+
+    let index_a = load_model_embedding_index(app_handle, model_a)?;
+    let index_b = load_model_embedding_index(app_handle, model_b)?;
+
+    let programs = normalize_programs(payload.program_filters);
+    let (allowed_rows, program_warnings) = if programs.is_empty() {
+        (None, Vec::new())
     } else {
-        contexts.len().saturating_sub(missing_embeddings)
+        let metadata = load_faculty_dataset_metadata(app_handle)?.ok_or_else(|| {
+            AppError::MissingDataset {
+                message: "The faculty dataset metadata is unavailable. Refresh the dataset analysis before filtering by program.".into(),
+            }
+        })?;
+        let outcome = filter_faculty_rows_by_program(&metadata, &programs);
+        (Some(outcome.rows), outcome.warnings)
     };
-    let skipped_documents = document_results.len().saturating_sub(processed_documents);
-    let matched_documents = document_results
-        .iter()
-        .filter(|result| !result.matches.is_empty())
-        .count();
-
-    let student_headers = vec!["Document".to_string()];
-    let faculty_headers = index.identifier_columns.clone();
-    let headers = build_matches_headers(&student_headers, &faculty_headers);
 
-    let mut preview_rows: Vec<Vec<String>> = Vec::new();
-    let mut match_entries: Vec<MatchEntry> = Vec::new();
-    let mut student_summary_rows: Vec<Vec<String>> = Vec::new();
+    emit_matching_phase(app_handle, request_id, "reading-input", None);
+    let PromptSpreadsheetRows {
+        rows,
+        row_numbers,
+        prompt_indexes,
+        identifier_indexes,
+    } = read_prompt_spreadsheet(&spreadsheet, &prompt_columns, &identifier_columns)
+        .map_err(|message| AppError::invalid_input("spreadsheetPath", message))?;
+    let mut warnings = program_warnings;
+    let mut students = Vec::new();
+    for (row, &row_number) in rows.iter().zip(&row_numbers) {
+        let identifier_label = identifier_indexes
+            .iter()
+            .filter_map(|&index| row.get(index))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(" – ");
+        let label = if identifier_label.is_empty() {
+            format!("Row {row_number}")
+        } else {
+            identifier_label
+        };
+        let prompt_parts = spreadsheet_prompt_parts(row, &prompt_indexes);
+        if prompt_parts.is_empty() {
+            warnings.push(format!(
+                "Skipped {label} because the selected prompt columns were empty."
+            ));
+            continue;
+        }
+        students.push((label, prompt_parts.join("\n\n")));
+    }
+    if students.is_empty() {
+        return Err(AppError::invalid_input(
+            "spreadsheetPath",
+            "None of the rows in the spreadsheet contained prompt text to compare.",
+        ));
+    }
 
-    for result in &document_results {
-        student_summary_rows.push(vec![result.identifier.clone()]);
+    let faculty_label = |index: &FacultyEmbeddingIndex, result: &FacultyMatchResult| {
+        let label = index
+            .identifier_columns
+            .iter()
+            .filter_map(|column| result.identifiers.get(column))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(" / ");
+        if label.is_empty() {
+            fallback_faculty_label(result.source, result.row_index)
+        } else {
+            label
+        }
+    };
 
-        if result.matches.is_empty() {
-            let message = result
-                .status_message
-                .clone()
-                .unwrap_or_else(|| "No faculty matches were returned.".into());
-            let mut preview_row = Vec::new();
-            preview_row.push(String::new());
-            preview_row.push(String::new());
-            preview_row.push(result.identifier.clone());
-            preview_row.extend(vec![String::new(); faculty_headers.len()]);
-            preview_row.push(message);
-            preview_row.push(String::new());
-            preview_row.push(String::new());
-            if preview_rows.len() < 20 {
-                preview_rows.push(preview_row);
+    let chunk_count = students.len().div_ceil(COMPARE_MODELS_CHUNK_SIZE);
+    let mut compared = Vec::new();
+    let mut top_lists: [Vec<Vec<String>>; 2] = [Vec::new(), Vec::new()];
+    for (chunk_index, chunk) in students.chunks(COMPARE_MODELS_CHUNK_SIZE).enumerate() {
+        let mut chunk_matches: [Vec<Option<Vec<FacultyMatchResult>>>; 2] = [Vec::new(), Vec::new()];
+        for (slot, (model, index)) in [(model_a, &index_a), (model_b, &index_b)]
+            .into_iter()
+            .enumerate()
+        {
+            emit_matching_phase(
+                app_handle,
+                request_id,
+                "embedding",
+                Some(if chunk_count > 1 {
+                    format!(
+                        "Embedding prompts with {model} (batch {} of {chunk_count})",
+                        chunk_index + 1
+                    )
+                } else {
+                    format!("Embedding prompts with {model}")
+                }),
+            );
+            let request = EmbeddingRequestPayload {
+                model: model.to_string(),
+                texts: chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(id, (_, prompt))| EmbeddingRequestRow {
+                        id,
+                        text: prompt.clone(),
+                    })
+                    .collect(),
+                item_label: Some(format!("prompt for {model}")),
+                item_label_plural: Some(format!("prompts for {model}")),
+            };
+            let response =
+                run_embedding_helper_with_progress(app_handle, &request, Some(request_id))?;
+            if response.dimension != index.dimension {
+                return Err(AppError::StaleEmbeddings {
+                    message: format!(
+                        "{model} returned {}-dimensional prompt embeddings but its faculty index uses {}.",
+                        response.dimension, index.dimension
+                    ),
+                });
             }
-            continue;
+            let mut embeddings: HashMap<usize, Vec<f32>> = response
+                .rows
+                .into_iter()
+                .map(|row| (row.id, row.embedding))
+                .collect();
+            chunk_matches[slot] = (0..chunk.len())
+                .map(|id| {
+                    embeddings.remove(&id).map(|embedding| {
+                        find_best_faculty_matches(index, &embedding, k, allowed_rows.as_ref())
+                    })
+                })
+                .collect();
         }
 
-        for (rank, faculty) in result.matches.iter().enumerate() {
-            let faculty_values: Vec<String> = faculty_headers
+        let [matches_a, matches_b] = chunk_matches;
+        for ((label, _), (matches_a, matches_b)) in
+            chunk.iter().zip(matches_a.into_iter().zip(matches_b))
+        {
+            let (Some(matches_a), Some(matches_b)) = (matches_a, matches_b) else {
+                warnings.push(format!(
+                    "{label} was skipped because one of the models did not return an embedding."
+                ));
+                continue;
+            };
+            let labels_a: Vec<String> = matches_a
                 .iter()
-                .map(|label| faculty.identifiers.get(label).cloned().unwrap_or_default())
+                .map(|result| faculty_label(&index_a, result))
                 .collect();
-            let similarity = faculty.similarity;
-            let student_rank = faculty
-                .student_rank_for_faculty
-                .map(|value| (value, faculty.student_rank_total));
-            let student_rank_text = student_rank
-                .map(|(position, total)| match total {
-                    Some(limit) => format!("{position} of {limit}"),
-                    None => position.to_string(),
-                })
-                .unwrap_or_default();
-
-            let mut preview_row = Vec::new();
-            preview_row.push(String::new());
-            preview_row.push(String::new());
-            preview_row.push(result.identifier.clone());
-            preview_row.extend(faculty_values.clone());
-            preview_row.push(format_similarity_percent(similarity));
-            preview_row.push(student_rank_text);
-            preview_row.push((rank + 1).to_string());
-            if preview_rows.len() < 20 {
-                preview_rows.push(preview_row);
-            }
-
-            match_entries.push(MatchEntry {
-                student_values: vec![result.identifier.clone()],
-                faculty_values,
-                similarity: Some(similarity),
-                student_rank,
-                faculty_rank: Some(rank + 1),
-            });
+            let labels_b: Vec<String> = matches_b
+                .iter()
+                .map(|result| faculty_label(&index_b, result))
+                .collect();
+            compared.push(compare_match_lists(
+                label, &matches_a, &matches_b, &labels_a, &labels_b, k,
+            ));
+            top_lists[0].push(labels_a);
+            top_lists[1].push(labels_b);
         }
     }
 
-    let preview = SpreadsheetPreview {
-        headers: headers.clone(),
-        rows: preview_rows,
-        suggested_prompt_columns: Vec::new(),
-        suggested_identifier_columns: Vec::new(),
+    let students_compared = compared.len();
+    let mean_overlap = if students_compared == 0 {
+        0.0
+    } else {
+        compared.iter().map(|student| student.overlap).sum::<f64>() / students_compared as f64
     };
-
-    let workbook_bytes = build_matches_workbook(
-        &student_headers,
-        &student_summary_rows,
-        &faculty_headers,
-        &match_entries,
+    let correlations: Vec<f64> = compared
+        .iter()
+        .filter_map(|student| student.rank_correlation)
+        .collect();
+    let mean_rank_correlation = (!correlations.is_empty())
+        .then(|| correlations.iter().sum::<f64>() / correlations.len() as f64);
+
+    let workbook = build_model_comparison_workbook(
+        &compared,
+        [model_a, model_b],
+        &top_lists,
+        k,
+        mean_overlap,
     )?;
-    let encoded_workbook = Base64Engine.encode(workbook_bytes);
-
-    let results = DirectoryMatchResults {
-        processed_documents,
-        matched_documents,
-        skipped_documents,
-        total_rows: match_entries.len(),
-        preview,
-        spreadsheet: GeneratedSpreadsheet {
-            filename: default_directory_workbook_name(),
+    emit_matching_phase(app_handle, request_id, "complete", None);
+    Ok(ModelComparisonReport {
+        request_id: request_id.to_string(),
+        model_a: model_a.to_string(),
+        model_b: model_b.to_string(),
+        k,
+        students_compared,
+        mean_overlap,
+        mean_rank_correlation,
+        students: compared,
+        warnings,
+        workbook: GeneratedSpreadsheet {
+            filename: "DBBS_model_comparison.xlsx".into(),
             mime_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".into(),
-            content: encoded_workbook,
+            content: Base64Engine.encode(workbook),
             encoding: Some("base64".into()),
+            companion_files: Vec::new(),
+            saved_path: None,
+            saved_bytes: None,
+            write_duration_ms: None,
         },
-    };
-
-    Ok(DirectoryProcessingOutcome {
-        warnings,
-        prompt_matches,
-        results,
     })
 }
 
-fn process_prompt_spreadsheet(
-    app_handle: &tauri::AppHandle,
-    spreadsheet_path: &Path,
-    index: &FacultyEmbeddingIndex,
-    prompt_columns: &[String],
-    identifier_columns: &[String],
-    limit: usize,
-    allowed_rows: Option<&HashSet<usize>>,
-) -> Result<SpreadsheetProcessingOutcome, String> {
-    #[derive(Debug)]
-    struct SpreadsheetRowContext {
-        result_index: usize,
-        prompt: String,
+fn compare_match_lists(
+    student: &str,
+    matches_a: &[FacultyMatchResult],
+    matches_b: &[FacultyMatchResult],
+    labels_a: &[String],
+    labels_b: &[String],
+    k: usize,
+) -> ModelComparisonStudent {
+    let ranks_b: HashMap<usize, usize> = matches_b
+        .iter()
+        .enumerate()
+        .map(|(rank, result)| (result.row_index, rank))
+        .collect();
+    let rows_a: HashSet<usize> = matches_a.iter().map(|result| result.row_index).collect();
+
+    let mut shared_faculty = Vec::new();
+    let mut shared_ranks = Vec::new();
+    let mut only_model_a = Vec::new();
+    for (rank_a, result) in matches_a.iter().enumerate() {
+        match ranks_b.get(&result.row_index) {
+            Some(&rank_b) => {
+                shared_faculty.push(labels_a[rank_a].clone());
+                shared_ranks.push((rank_a, rank_b));
+            }
+            None => only_model_a.push(labels_a[rank_a].clone()),
+        }
     }
+    let only_model_b = matches_b
+        .iter()
+        .zip(labels_b)
+        .filter(|(result, _)| !rows_a.contains(&result.row_index))
+        .map(|(_, label)| label.clone())
+        .collect();
 
-    #[derive(Debug)]
-    struct SpreadsheetRowResult {
-        warning_label: String,
-        identifier_values: Vec<String>,
-        identifier_label: String,
-        prompt_preview: String,
-        prompt_label: Option<String>,
-        matches: Vec<FacultyMatchResult>,
-        status_message: Option<String>,
+    ModelComparisonStudent {
+        student: student.to_string(),
+        overlap: shared_ranks.len() as f64 / k.max(1) as f64,
+        rank_correlation: spearman_rank_correlation(&shared_ranks),
+        shared_faculty,
+        only_model_a,
+        only_model_b,
     }
+}
 
-    let (headers, rows) = read_full_spreadsheet(spreadsheet_path)?;
-    let header_map = build_header_index_map(&headers);
-    let prompt_indexes = indexes_from_spreadsheet_labels(&header_map, prompt_columns)?;
-    let identifier_indexes = indexes_from_spreadsheet_labels(&header_map, identifier_columns)?;
-    let include_row_number_column = identifier_indexes.is_empty();
+fn spearman_rank_correlation(pairs: &[(usize, usize)]) -> Option<f64> {
+    let n = pairs.len();
+    if n < 2 {
+        return None;
+    }
+    let relative_ranks = |values: Vec<usize>| -> Vec<usize> {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by_key(|&position| values[position]);
+        let mut ranks = vec![0; values.len()];
+        for (rank, position) in order.into_iter().enumerate() {
+            ranks[position] = rank;
+        }
+        ranks
+    };
+    let ranks_a = relative_ranks(pairs.iter().map(|pair| pair.0).collect());
+    let ranks_b = relative_ranks(pairs.iter().map(|pair| pair.1).collect());
+    let squared: f64 = ranks_a
+        .iter()
+        .zip(&ranks_b)
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum();
+    let n = n as f64;
+    Some(1.0 - 6.0 * squared / (n * (n * n - 1.0)))
+}
 
-    let mut warnings = Vec::new();
-    let mut contexts: Vec<SpreadsheetRowContext> = Vec::new();
-    let mut row_results: Vec<SpreadsheetRowResult> = Vec::new();
+fn build_model_comparison_workbook(
+    students: &[ModelComparisonStudent],
+    models: [&str; 2],
+    top_lists: &[Vec<Vec<String>>; 2],
+    k: usize,
+    mean_overlap: f64,
+) -> Result<Vec<u8>, String> {
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+    let mut used_sheet_names = HashSet::new();
 
-    if rows.is_empty() {
-        warnings.push("The spreadsheet did not include any data rows to process.".into());
+    let mut model_headers = vec!["Student".to_string()];
+    model_headers.extend((1..=k).map(|rank| format!("Rank {rank}")));
+    let mut sheets = Vec::new();
+    for (model, lists) in models.iter().zip(top_lists) {
+        let rows: Vec<Vec<String>> = students
+            .iter()
+            .zip(lists)
+            .map(|(student, labels)| {
+                let mut row = vec![student.student.clone()];
+                row.extend(labels.iter().cloned());
+                row
+            })
+            .collect();
+        let name = model.rsplit('/').next().unwrap_or(model);
+        sheets.push((
+            sanitize_sheet_name(name, "Model", &mut used_sheet_names),
+            model_headers.clone(),
+            rows,
+        ));
     }
+    let mut comparison_rows: Vec<Vec<String>> = students
+        .iter()
+        .map(|student| {
+            vec![
+                student.student.clone(),
+                format!("{:.0}%", student.overlap * 100.0),
+                student
+                    .rank_correlation
+                    .map(|value| format!("{value:.2}"))
+                    .unwrap_or_default(),
+                student.only_model_a.join("; "),
+                student.only_model_b.join("; "),
+            ]
+        })
+        .collect();
+    comparison_rows.push(vec![
+        "All students".into(),
+        format!("{:.0}%", mean_overlap * 100.0),
+        String::new(),
+        String::new(),
+        String::new(),
+    ]);
+    sheets.push((
+        sanitize_sheet_name("Comparison", "Comparison", &mut used_sheet_names),
+        vec![
+            "Student".into(),
+            format!("Overlap@{k}"),
+            "Rank Correlation".into(),
+            format!("Only {}", models[0]),
+            format!("Only {}", models[1]),
+        ],
+        comparison_rows,
+    ));
 
-    for (row_index, row) in rows.iter().enumerate() {
-        let row_number = row_index + 2;
-        let mut identifier_values = Vec::new();
-        let mut label_segments = Vec::new();
-
-        if include_row_number_column {
-            identifier_values.push(row_number.to_string());
-            label_segments.push(format!("Row {}", row_number));
+    for (name, headers, rows) in &sheets {
+        let sheet = workbook.add_worksheet();
+        sheet
+            .set_name(name)
+            .map_err(|err| format!("Unable to configure the {name} worksheet: {err}"))?;
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the {name} header row: {err}"))?;
         }
-
-        for &index in &identifier_indexes {
-            let value = row.get(index).cloned().unwrap_or_default();
-            if !value.trim().is_empty() {
-                label_segments.push(value.trim().to_string());
+        for (row_index, values) in rows.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                sheet
+                    .write_string((row_index + 1) as u32, col as u16, excel_cell_text(value))
+                    .map_err(|err| format!("Unable to write the {name} worksheet: {err}"))?;
             }
-            identifier_values.push(value);
         }
+        apply_column_widths(sheet, &measure_column_widths(headers, rows.iter()))?;
+    }
 
-        let identifier_label = if label_segments.is_empty() {
-            format!("Row {}", row_number)
-        } else {
-            label_segments.join(" – ")
-        };
-
-        let warning_label = if label_segments.is_empty()
-            || (include_row_number_column && label_segments.len() == 1)
-        {
-            format!("row {}", row_number)
-        } else {
-            format!("row {} ({})", row_number, identifier_label.as_str())
-        };
+    workbook
+        .save_to_buffer()
+        .map_err(|err| format!("Unable to finalize the model comparison workbook: {err}"))
+}
 
-        let mut prompt_parts = Vec::new();
-        for &index in &prompt_indexes {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if !trimmed.is_empty() {
-                    prompt_parts.push(trimmed.to_string());
-                }
-            }
-        }
+#[tauri::command]
+async fn validate_submission(
+    app_handle: tauri::AppHandle,
+    payload: SubmissionPayload,
+) -> Result<SubmissionValidation, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        perform_submission_validation(&app_handle, payload)
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Validation task failed: {err}")))?
+}
 
-        let mut result = SpreadsheetRowResult {
-            warning_label,
-            identifier_values,
-            identifier_label,
-            prompt_preview: String::new(),
-            prompt_label: None,
-            matches: Vec::new(),
-            status_message: None,
-        };
+fn perform_submission_validation(
+    app_handle: &dyn MatchingHost,
+    payload: SubmissionPayload,
+) -> Result<SubmissionValidation, AppError> {
+    let request_id = Uuid::new_v4().to_string();
+    let payload = apply_app_settings(payload, &load_app_settings(app_handle)?);
+    let PreparedSubmission {
+        mut warnings,
+        details,
+        directory_source,
+        spreadsheet_source,
+        selected_prompt_columns,
+        selected_identifier_columns,
+        ad_hoc_roster_faculty,
+        faculty_embedding_index,
+        ..
+    } = prepare_submission(app_handle, &payload, &request_id)?;
+
+    warnings.append_with(|warnings| {
+        resolve_similarity_thresholds(
+            payload.similarity_low_threshold,
+            payload.similarity_high_threshold,
+            warnings,
+        )
+    });
 
-        if prompt_parts.is_empty() {
-            warnings.push(format!(
-                "Skipped {} because the selected prompt columns were empty.",
-                result.warning_label
-            ));
-            result.status_message =
-                Some("No prompt content was provided in the selected columns.".into());
-        } else {
-            let prompt_text = prompt_parts.join("\n\n");
-            result.prompt_preview = build_prompt_preview(&prompt_text);
-            if result.prompt_preview.is_empty() {
-                result.prompt_label = Some(result.identifier_label.clone());
-            } else {
-                result.prompt_label = Some(format!(
-                    "{} — {}",
-                    result.identifier_label, result.prompt_preview
+    let students_to_process = match (&directory_source, &spreadsheet_source) {
+        (Some(directory), _) => warnings
+            .append_with(|warnings| list_directory_files(directory, warnings))?
+            .len(),
+        (None, Some(spreadsheet)) => {
+            let PromptSpreadsheetRows {
+                rows,
+                prompt_indexes,
+                ..
+            } = read_prompt_spreadsheet(
+                spreadsheet,
+                &selected_prompt_columns,
+                &selected_identifier_columns,
+            )?;
+            let prompt_rows = rows
+                .iter()
+                .filter(|row| !spreadsheet_prompt_parts(row, &prompt_indexes).is_empty())
+                .count();
+            let skipped_rows = rows.len() - prompt_rows;
+            if rows.is_empty() {
+                warnings.push("The spreadsheet did not include any data rows to process.".into());
+            } else if skipped_rows > 0 {
+                warnings.push(format!(
+                    "{skipped_rows} spreadsheet row{plural} will be skipped because the selected prompt columns are empty.",
+                    plural = if skipped_rows == 1 { "" } else { "s" }
                 ));
             }
-            let result_index = row_results.len();
-            contexts.push(SpreadsheetRowContext {
-                result_index,
-                prompt: prompt_text,
-            });
+            prompt_rows
         }
+        (None, None) => 1,
+    };
 
-        row_results.push(result);
+    let faculty_in_scope = details
+        .faculty_in_scope
+        .unwrap_or(ad_hoc_roster_faculty.len());
+    if let Some(index) = &faculty_embedding_index {
+        let mismatched = index
+            .entries
+            .iter()
+            .filter(|entry| entry.embedding.len() != index.dimension)
+            .count();
+        if index.dimension == 0 || mismatched > 0 {
+            return Err(AppError::StaleEmbeddings { message: format!(
+                "The faculty embeddings are inconsistent: {mismatched} of {} entries do not match the recorded dimension of {}. Regenerate the embeddings before matching.",
+                index.entries.len(),
+                index.dimension
+            ) });
+        }
+    }
+    if faculty_in_scope == 0 {
+        warnings.push("No faculty embeddings are in scope for this submission.".into());
     }
 
-    let mut prompt_matches = Vec::new();
-    let mut missing_embeddings = 0usize;
+    Ok(SubmissionValidation {
+        details,
+        warnings: warnings.into_vec(),
+        students_to_process,
+        faculty_in_scope,
+        embedding_model: faculty_embedding_index
+            .as_ref()
+            .map(|index| index.model.clone()),
+        embedding_dimension: faculty_embedding_index
+            .as_ref()
+            .map(|index| index.dimension),
+    })
+}
 
-    if !contexts.is_empty() {
-        let model_name = if index.model.trim().is_empty() {
-            DEFAULT_EMBEDDING_MODEL.to_string()
-        } else {
-            index.model.clone()
-        };
+fn perform_matching_request(
+    app_handle: &dyn MatchingHost,
+    payload: SubmissionPayload,
+    request_id: &str,
+) -> Result<SubmissionResponse, AppError> {
+    let mut audit_entry = AuditLogEntry::for_payload(&payload, request_id);
+    let mut result = execute_matching_request(app_handle, payload, request_id);
+    audit_entry.record_outcome(&result);
+    if let Err(err) = append_audit_log(app_handle, &audit_entry) {
+        log::warn!("Unable to append to the audit log: {err}");
+        if let Ok(response) = result.as_mut() {
+            response
+                .warnings
+                .push(format!("Unable to record this run in the audit log: {err}"));
+        }
+    }
+    result
+}
 
-        let payload = EmbeddingRequestPayload {
-            model: model_name,
-            texts: contexts
-                .iter()
-                .enumerate()
-                .map(|(id, context)| EmbeddingRequestRow {
-                    id,
-                    text: context.prompt.clone(),
-                })
-                .collect(),
-            item_label: Some("spreadsheet row".into()),
-            item_label_plural: Some("spreadsheet rows".into()),
-        };
+fn execute_matching_request(
+    app_handle: &dyn MatchingHost,
+    payload: SubmissionPayload,
+    request_id: &str,
+) -> Result<SubmissionResponse, AppError> {
+    emit_matching_phase(app_handle, request_id, "validating", None);
+    let payload = apply_app_settings(payload, &load_app_settings(app_handle)?);
+    let recorder;
+    let app_handle: &dyn MatchingHost = if payload.save_prompt_embeddings
+        && run_history_enabled()
+        && app_handle.prompt_embeddings().is_none()
+    {
+        recorder =
+            PromptEmbeddingHost::new(app_handle, PromptEmbeddingMode::Record, HashMap::new());
+        &recorder
+    } else {
+        app_handle
+    };
+    let saved_payload = payload
+        .save_prompt_embeddings
+        .then(|| payload.clone())
+        .filter(|saved| {
+            !(saved.scrub_personal_information
+                && (saved.document_content.is_some() || saved.spreadsheet_content.is_some()))
+        })
+        .map(|mut saved| {
+            if saved.scrub_personal_information {
+                saved.prompt_text = saved
+                    .prompt_text
+                    .map(|text| scrub_personal_information_text(text.trim(), &[]).0);
+            }
+            saved
+        });
 
-        let response = run_embedding_helper(app_handle, &payload)?;
-        if response.dimension != index.dimension {
-            return Err(format!(
-                "The spreadsheet embedding dimension ({}) does not match the faculty embedding dimension ({}).",
-                response.dimension, index.dimension
-            ));
-        }
+    let PreparedSubmission {
+        mut warnings,
+        mut details,
+        output_destination,
+        filename_pattern,
+        prepared_prompt_text,
+        truncated_prompts,
+        directory_source,
+        spreadsheet_source,
+        selected_prompt_columns,
+        selected_identifier_columns,
+        mut allowed_faculty_rows,
+        roster_diagnostics,
+        ad_hoc_roster_faculty,
+        mut faculty_embedding_index,
+    } = prepare_submission(app_handle, &payload, request_id)?;
+    let faculty_scope = details.faculty_scope.clone();
+    let faculty_recs_per_student = details.recommendations_per_student;
 
-        let mut embedding_map: HashMap<usize, Vec<f32>> = HashMap::new();
-        for row in response.rows {
-            embedding_map.insert(row.id, row.embedding);
-        }
+    let SubmissionPayload {
+        task_type,
+        custom_faculty_mode,
+        output_format,
+        include_json_export,
+        include_prompt_text_column,
+        include_faculty_text_column,
+        include_faculty_id_column,
+        include_raw_cosine_column,
+        reviewer_input_mode,
+        summary_mode,
+        matches_sort,
+        prompt_text_column_max_chars,
+        include_prompt_column_values,
+        prompt_column_values_max_chars,
+        sheet_per_student,
+        preview_row_limit,
+        generate_student_reports,
+        include_pdf_report,
+        mail_merge_template,
+        export_bundle,
+        overwrite_output,
+        similarity_low_threshold,
+        similarity_high_threshold,
+        attention_threshold,
+        detect_similar_prompts,
+        similar_prompt_threshold,
+        scrub_personal_information,
+        strip_boilerplate,
+        boilerplate_text,
+        boilerplate_row_percent,
+        max_prompt_characters,
+        prompt_preview_characters,
+        ..
+    } = payload;
 
-        for (context_index, context) in contexts.iter().enumerate() {
-            let result = &mut row_results[context.result_index];
+    let mut summary = build_summary(
+        &task_type,
+        &faculty_scope,
+        faculty_recs_per_student,
+        details.program_filters.len(),
+        details.roster_faculty_count,
+        custom_faculty_mode,
+        details.faculty_in_scope,
+    );
 
-            match embedding_map.remove(&context_index) {
-                Some(embedding) => {
-                    let matches = find_best_faculty_matches(index, &embedding, limit, allowed_rows);
+    let mut prompt_matches = Vec::new();
+    let mut directory_results = None;
+    let mut spreadsheet_results = None;
 
-                    if matches.is_empty() {
-                        result.status_message = Some("No faculty matches were returned.".into());
-                    } else {
-                        result.status_message = None;
-                    }
+    if !ad_hoc_roster_faculty.is_empty() {
+        if let Some(index) = faculty_embedding_index.as_mut() {
+            let added_rows = append_ad_hoc_roster_embeddings(
+                app_handle,
+                Arc::make_mut(index),
+                &ad_hoc_roster_faculty,
+            )?;
+            let added_count = added_rows.len();
+            allowed_faculty_rows
+                .get_or_insert_with(HashSet::new)
+                .extend(added_rows);
+            warnings.push(format!(
+                "Embedded {added_count} roster-only faculty member{plural} for this run. They lack program metadata, so program filters do not apply to them, and they are not saved to the faculty embeddings.",
+                plural = if added_count == 1 { "" } else { "s" }
+            ));
+        }
+    }
 
-                    result.matches = matches;
-                }
-                None => {
-                    missing_embeddings += 1;
-                    let message =
-                        "The embedding helper did not return a result for this row.".to_string();
-                    warnings.push(format!(
-                        "The embedding helper did not return an embedding for {}.",
-                        result.warning_label
-                    ));
-                    result.status_message = Some(message.clone());
-                }
+    let faculty_exclusions = faculty_embedding_index.as_ref().and_then(|index| {
+        let diagnostics = find_excluded_faculty_entries(index, allowed_faculty_rows.as_ref());
+        warnings.extend(faculty_exclusion_warnings(&diagnostics));
+        (!diagnostics.entries.is_empty()).then_some(diagnostics)
+    });
+
+    if let Some(prompt_text) = prepared_prompt_text {
+        let limit = faculty_recs_per_student.max(1) as usize;
+        let embedding_index = faculty_embedding_index
+            .as_ref()
+            .ok_or_else(|| "The faculty embedding index was not loaded.".to_string())?;
+        emit_matching_phase(app_handle, request_id, "embedding", None);
+        let prompt_embedding = embed_prompt(app_handle, embedding_index, &prompt_text)?;
+        emit_matching_phase(app_handle, request_id, "scoring", None);
+        let mut matches = find_best_faculty_matches(
+            embedding_index,
+            &prompt_embedding,
+            limit,
+            allowed_faculty_rows.as_ref(),
+        );
+
+        if matches!(task_type, TaskType::Prompt | TaskType::Document) {
+            if let Err(err) =
+                enrich_matches_with_faculty_text(app_handle, embedding_index, &mut matches)
+            {
+                warnings.push(format!(
+                    "Unable to include faculty text in the match results: {err}"
+                ));
             }
         }
-    } else if !row_results.is_empty() {
-        warnings.push("None of the rows in the spreadsheet contained prompt text to embed.".into());
-    }
 
-    {
-        let mut match_refs: Vec<&mut Vec<FacultyMatchResult>> = row_results
-            .iter_mut()
-            .map(|result| &mut result.matches)
-            .collect();
-        assign_student_rankings(&mut match_refs);
+        prompt_matches.push(PromptMatchResult {
+            prompt: match (&details.student_label, &task_type) {
+                (Some(label), _) => label.clone(),
+                (None, TaskType::Document) => build_prompt_preview(
+                    &prompt_text,
+                    resolve_prompt_preview_characters(prompt_preview_characters),
+                ),
+                (None, _) => prompt_text.clone(),
+            },
+            faculty_matches: matches,
+        });
     }
 
-    for result in &row_results {
-        if let Some(label) = &result.prompt_label {
-            prompt_matches.push(PromptMatchResult {
-                prompt: label.clone(),
-                faculty_matches: result.matches.clone(),
-            });
+    let similarity_thresholds = warnings.append_with(|warnings| {
+        resolve_similarity_thresholds(
+            similarity_low_threshold,
+            similarity_high_threshold,
+            warnings,
+        )
+    });
+    let attention_threshold = warnings.append_with(|warnings| {
+        resolve_attention_threshold(attention_threshold, similarity_thresholds.0, warnings)
+    });
+    let similar_prompt_threshold = detect_similar_prompts.then(|| {
+        warnings.append_with(|warnings| {
+            resolve_similar_prompt_threshold(similar_prompt_threshold, warnings)
+        })
+    });
+    let boilerplate_text = boilerplate_text
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty());
+    let boilerplate_row_percent = match boilerplate_row_percent {
+        Some(percent) if percent.is_finite() && percent > 0.0 && percent <= 100.0 => percent,
+        Some(_) => {
+            warnings.push(format!(
+                "The boilerplate row percentage must be above 0 and at most 100; using {BOILERPLATE_ROW_PERCENT}% instead."
+            ));
+            BOILERPLATE_ROW_PERCENT
         }
+        None => BOILERPLATE_ROW_PERCENT,
+    };
+    let prompt_text_max_chars = prompt_text_column_max_chars
+        .filter(|limit| *limit > 0)
+        .unwrap_or(PROMPT_TEXT_COLUMN_MAX_CHARS)
+        .min(PROMPT_TEXT_COLUMN_MAX_CHARS);
+    let prompt_column_values_max_chars = include_prompt_column_values.then(|| {
+        prompt_column_values_max_chars
+            .filter(|limit| *limit > 0)
+            .unwrap_or(PROMPT_COLUMN_VALUES_DEFAULT_CHARS)
+            .min(PROMPT_TEXT_COLUMN_MAX_CHARS)
+    });
+    let run_info = RunInfo {
+        generated_at: Utc::now().to_rfc3339(),
+        app_version: app_handle.app_version(),
+        task_type: task_type.clone(),
+        faculty_scope: faculty_scope.clone(),
+        program_filters: details.program_filters.clone(),
+        custom_faculty_path: details.custom_faculty_path.clone(),
+        custom_faculty_mode,
+        roster_faculty_count: details.roster_faculty_count,
+        recommendations_per_student: faculty_recs_per_student,
+        embedding_model: faculty_embedding_index
+            .as_ref()
+            .map(|index| index.model.clone()),
+        embeddings_generated_at: faculty_embedding_index
+            .as_ref()
+            .and_then(|index| index.generated_at.clone()),
+        dataset_path: dataset_destination(app_handle)
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned()),
+        dataset_source_path: read_faculty_dataset_source_path(app_handle).ok().flatten(),
+        dataset_row_count: faculty_embedding_index
+            .as_ref()
+            .and_then(|index| index.total_rows),
+        similarity_low_threshold: (similarity_thresholds.0 * 100.0) as f32,
+        similarity_high_threshold: (similarity_thresholds.1 * 100.0) as f32,
+        attention_threshold: (attention_threshold * 100.0) as f32,
+        summary_mode,
+        prompt_text_max_chars: include_prompt_text_column.then_some(prompt_text_max_chars),
+        student_worksheet_limit: sheet_per_student.then_some(STUDENT_WORKSHEET_LIMIT),
+    };
+    let spreadsheet_run =
+        matches!(task_type, TaskType::Spreadsheet) || spreadsheet_source.is_some();
+    if generate_student_reports && !spreadsheet_run && !matches!(task_type, TaskType::Directory) {
+        warnings
+            .push("Student reports are only generated for spreadsheet and directory runs.".into());
     }
-
-    let processed_rows = if contexts.is_empty() {
-        0
+    let faculty_programs: Option<HashMap<usize, Vec<String>>> = if generate_student_reports
+        || include_pdf_report
+        || spreadsheet_run
+        || matches!(task_type, TaskType::Directory)
+    {
+        match load_faculty_dataset_metadata(app_handle) {
+            Ok(Some(metadata)) => Some(
+                metadata
+                    .memberships
+                    .into_iter()
+                    .map(|membership| (membership.row_index, membership.programs))
+                    .collect(),
+            ),
+            Ok(None) => None,
+            Err(err) => {
+                warnings.push(format!(
+                    "Unable to load faculty programs for the generated results: {err}"
+                ));
+                None
+            }
+        }
     } else {
-        contexts.len().saturating_sub(missing_embeddings)
+        None
     };
-    let skipped_rows = row_results.len().saturating_sub(processed_rows);
-    let matched_rows = row_results
-        .iter()
-        .filter(|result| !result.matches.is_empty())
-        .count();
-
-    let student_headers: Vec<String> = if include_row_number_column {
-        vec!["Row Number".into()]
-    } else {
-        identifier_columns
-            .iter()
-            .map(|label| label.trim().to_string())
-            .collect()
+    let filename_stem = filename_pattern.as_deref().map(|pattern| {
+        render_filename_pattern(
+            pattern,
+            &task_type,
+            &faculty_scope,
+            &details.program_filters,
+            faculty_embedding_index
+                .as_ref()
+                .map(|index| index.model.as_str()),
+        )
+    });
+    let run_warnings = warnings.to_vec();
+    let run_options = MatchRunOptions {
+        limit: faculty_recs_per_student.max(1) as usize,
+        allowed_rows: allowed_faculty_rows.as_ref(),
+        faculty_in_scope: details.faculty_in_scope,
+        roster_diagnostics: roster_diagnostics.as_ref(),
+        output_format,
+        include_prompt_text: include_prompt_text_column,
+        include_faculty_text: include_faculty_text_column,
+        include_faculty_id: include_faculty_id_column,
+        include_raw_cosine: include_raw_cosine_column.unwrap_or(true),
+        request_id,
+        reviewer_input_mode,
+        summary_mode,
+        matches_sort,
+        prompt_text_max_chars,
+        prompt_column_values_max_chars,
+        sheet_per_student,
+        similarity_thresholds,
+        attention_threshold,
+        similar_prompt_threshold,
+        scrub_personal_information,
+        strip_boilerplate,
+        boilerplate_text: boilerplate_text.as_deref(),
+        boilerplate_row_percent,
+        max_prompt_characters: resolve_max_prompt_characters(max_prompt_characters),
+        prompt_preview_characters: resolve_prompt_preview_characters(prompt_preview_characters),
+        run_warnings: &run_warnings,
+        run_info: &run_info,
+        preview_row_limit: preview_row_limit
+            .filter(|limit| *limit > 0)
+            .unwrap_or(DEFAULT_PREVIEW_ROW_LIMIT)
+            .min(MAX_PREVIEW_ROW_LIMIT),
+        output_path: output_destination.as_deref(),
+        overwrite_output,
+        student_reports: generate_student_reports,
+        faculty_programs: faculty_programs.as_ref(),
+        mail_merge_template: mail_merge_template
+            .as_deref()
+            .filter(|template| !template.trim().is_empty()),
+        filename_stem: filename_stem.as_deref(),
     };
-    let faculty_headers: Vec<String> = index.identifier_columns.clone();
 
-    let headers = build_matches_headers(&student_headers, &faculty_headers);
+    if matches!(task_type, TaskType::Directory) {
+        let directory_path = directory_source
+            .as_ref()
+            .ok_or_else(|| "The directory path was not preserved during processing.".to_string())?;
+        let embedding_index = faculty_embedding_index
+            .as_ref()
+            .ok_or_else(|| "The faculty embedding index was not loaded.".to_string())?;
 
-    let mut match_entries: Vec<MatchEntry> = Vec::new();
-    let mut preview_rows: Vec<Vec<String>> = Vec::new();
+        let outcome =
+            process_directory_documents(app_handle, directory_path, embedding_index, &run_options)?;
 
-    for result in &row_results {
-        if result.matches.is_empty() {
-            let message = result
-                .status_message
-                .clone()
-                .unwrap_or_else(|| "No faculty matches were returned.".into());
-            let mut preview_row = Vec::new();
-            preview_row.push(String::new());
-            preview_row.push(String::new());
-            preview_row.extend(result.identifier_values.clone());
-            preview_row.extend(vec![String::new(); faculty_headers.len()]);
-            preview_row.push(message.clone());
-            preview_row.push(String::new());
-            preview_row.push(String::new());
-            if preview_rows.len() < 20 {
-                preview_rows.push(preview_row);
+        warnings.extend(outcome.warnings);
+        prompt_matches.extend(outcome.prompt_matches);
+        directory_results = Some(outcome.results);
+    }
+
+    if spreadsheet_run {
+        let spreadsheet = spreadsheet_source
+            .as_ref()
+            .ok_or_else(|| "The spreadsheet was not preserved during processing.".to_string())?;
+        let embedding_index = faculty_embedding_index
+            .as_ref()
+            .ok_or_else(|| "The faculty embedding index was not loaded.".to_string())?;
+
+        let outcome = process_prompt_spreadsheet(
+            app_handle,
+            spreadsheet,
+            embedding_index,
+            &selected_prompt_columns,
+            &selected_identifier_columns,
+            &run_options,
+        )?;
+
+        warnings.extend(outcome.warnings);
+        prompt_matches.extend(outcome.prompt_matches);
+        spreadsheet_results = Some(outcome.results);
+    }
+
+    let truncated_prompts = truncated_prompts
+        + spreadsheet_results
+            .as_ref()
+            .map(|results| results.truncated_rows)
+            .unwrap_or_default();
+    if truncated_prompts > 0 {
+        summary.push_str(&format!(
+            " Truncated {truncated_prompts} prompt{plural} to the {} character limit.",
+            run_options.max_prompt_characters,
+            plural = if truncated_prompts == 1 { "" } else { "s" }
+        ));
+    }
+
+    let needs_attention = directory_results
+        .as_ref()
+        .map(|results| results.needs_attention.len())
+        .or_else(|| {
+            spreadsheet_results
+                .as_ref()
+                .map(|results| results.needs_attention.len())
+        })
+        .unwrap_or_default();
+    if needs_attention > 0 {
+        summary.push_str(&format!(
+            " {needs_attention} student{plural} had no match above {}%.",
+            run_info.attention_threshold,
+            plural = if needs_attention == 1 { "" } else { "s" }
+        ));
+    }
+
+    {
+        let mut match_refs: Vec<&mut Vec<FacultyMatchResult>> = prompt_matches
+            .iter_mut()
+            .map(|result| &mut result.faculty_matches)
+            .collect();
+        assign_student_rankings(&mut match_refs);
+    }
+
+    details.output_filename = directory_results
+        .as_ref()
+        .map(|results| results.spreadsheet.filename.clone())
+        .or_else(|| {
+            spreadsheet_results
+                .as_ref()
+                .map(|results| results.spreadsheet.filename.clone())
+        });
+
+    let saved_output = directory_results
+        .as_ref()
+        .map(|results| &results.spreadsheet)
+        .or(spreadsheet_results
+            .as_ref()
+            .map(|results| &results.spreadsheet));
+    if let Some(saved) = saved_output {
+        if let (Some(path), Some(bytes)) = (&saved.saved_path, saved.saved_bytes) {
+            let companion_count = saved.companion_files.len();
+            let write_duration_ms: u64 = saved.write_duration_ms.unwrap_or_default()
+                + saved
+                    .companion_files
+                    .iter()
+                    .filter_map(|file| file.write_duration_ms)
+                    .sum::<u64>();
+            summary.push_str(&format!(
+                " Saved the results to {path} ({}) in {write_duration_ms} ms.",
+                format_file_size(bytes)
+            ));
+            if companion_count > 0 {
+                summary.push_str(&format!(
+                    " Wrote {companion_count} companion file{plural} alongside it.",
+                    plural = if companion_count == 1 { "" } else { "s" }
+                ));
             }
-            continue;
         }
+    }
 
-        for (rank, faculty) in result.matches.iter().enumerate() {
-            let faculty_values: Vec<String> = faculty_headers
-                .iter()
-                .map(|label| faculty.identifiers.get(label).cloned().unwrap_or_default())
-                .collect();
-            let similarity = faculty.similarity;
-            let student_rank = faculty
-                .student_rank_for_faculty
-                .map(|value| (value, faculty.student_rank_total));
-            let student_rank_text = student_rank
-                .map(|(position, total)| match total {
-                    Some(limit) => format!("{position} of {limit}"),
-                    None => position.to_string(),
-                })
-                .unwrap_or_default();
+    let statistics = build_run_statistics(&prompt_matches, details.faculty_in_scope);
 
-            let mut preview_row = Vec::new();
-            preview_row.push(String::new());
-            preview_row.push(String::new());
-            preview_row.extend(result.identifier_values.clone());
-            preview_row.extend(faculty_values.clone());
-            preview_row.push(format_similarity_percent(similarity));
-            preview_row.push(student_rank_text.clone());
-            preview_row.push((rank + 1).to_string());
-            if preview_rows.len() < 20 {
-                preview_rows.push(preview_row);
+    log::info!(
+        "Matching request {request_id} produced {} result set{} with {} warning{}.",
+        prompt_matches.len(),
+        if prompt_matches.len() == 1 { "" } else { "s" },
+        warnings.len(),
+        if warnings.len() == 1 { "" } else { "s" }
+    );
+    for warning in warnings.iter() {
+        log::debug!("Matching request {request_id} warning: {warning}");
+    }
+
+    let mut response = SubmissionResponse {
+        summary,
+        warnings: warnings.into_vec(),
+        details,
+        prompt_matches,
+        directory_results,
+        spreadsheet_results,
+        roster_diagnostics,
+        faculty_exclusions,
+        json_export: None,
+        pdf_report: None,
+        bundle: None,
+        statistics,
+    };
+
+    if include_json_export {
+        response.json_export = Some(build_results_json_export(&response, &run_info)?);
+    }
+
+    if include_pdf_report {
+        let identifier_columns = faculty_embedding_index
+            .as_ref()
+            .map(|index| index.identifier_columns.clone())
+            .unwrap_or_default();
+        let mut report = GeneratedSpreadsheet {
+            filename: default_pdf_report_name(response.details.student_label.as_deref()),
+            mime_type: "application/pdf".into(),
+            content: String::new(),
+            encoding: Some("base64".into()),
+            companion_files: Vec::new(),
+            saved_path: None,
+            saved_bytes: None,
+            write_duration_ms: None,
+        };
+        let bytes = build_pdf_report(
+            &response,
+            &run_info,
+            &identifier_columns,
+            faculty_programs.as_ref(),
+        );
+        match output_destination.as_deref() {
+            Some(destination) => {
+                let report_path = derived_output_path(destination, OUTPUT_REPORT_SUFFIX);
+                write_generated_file(&mut report, &report_path, &bytes, overwrite_output)?;
             }
+            None => report.content = Base64Engine.encode(bytes),
+        }
+        response.pdf_report = Some(report);
+    }
 
-            match_entries.push(MatchEntry {
-                student_values: result.identifier_values.clone(),
-                faculty_values,
-                similarity: Some(similarity),
-                student_rank,
-                faculty_rank: Some(rank + 1),
-            });
+    if export_bundle {
+        let json_export = response.json_export.take();
+        let pdf_report = response.pdf_report.take();
+        let bundle = match output_destination.as_deref() {
+            Some(destination) => {
+                let bundle_path = derived_output_path(destination, OUTPUT_BUNDLE_SUFFIX);
+                ensure_save_target_available(&bundle_path, overwrite_output)?;
+                let started = Instant::now();
+                write_file_atomically_with(&bundle_path, |writer| {
+                    build_export_bundle(writer, &response, &run_info, pdf_report.as_ref())
+                        .map(|_| ())
+                        .map_err(std::io::Error::other)
+                })
+                .map_err(|err| {
+                    format!(
+                        "Unable to save the export bundle '{}': {err}",
+                        bundle_path.display()
+                    )
+                })?;
+                let saved_bytes = fs::metadata(&bundle_path)
+                    .map(|metadata| metadata.len())
+                    .unwrap_or_default();
+                GeneratedSpreadsheet {
+                    filename: bundle_path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| {
+                            default_export_bundle_name(response.details.student_label.as_deref())
+                        }),
+                    mime_type: "application/zip".into(),
+                    content: String::new(),
+                    encoding: Some("base64".into()),
+                    companion_files: Vec::new(),
+                    saved_path: Some(bundle_path.to_string_lossy().into_owned()),
+                    saved_bytes: Some(saved_bytes),
+                    write_duration_ms: Some(started.elapsed().as_millis() as u64),
+                }
+            }
+            None => {
+                let buffer = build_export_bundle(
+                    Cursor::new(Vec::new()),
+                    &response,
+                    &run_info,
+                    pdf_report.as_ref(),
+                )?;
+                GeneratedSpreadsheet {
+                    filename: default_export_bundle_name(response.details.student_label.as_deref()),
+                    mime_type: "application/zip".into(),
+                    content: Base64Engine.encode(buffer.into_inner()),
+                    encoding: Some("base64".into()),
+                    companion_files: Vec::new(),
+                    saved_path: None,
+                    saved_bytes: None,
+                    write_duration_ms: None,
+                }
+            }
+        };
+        response.json_export = json_export;
+        response.pdf_report = pdf_report;
+        response.bundle = Some(bundle);
+    }
+
+    if run_history_enabled() {
+        let saved_embeddings = match (saved_payload, app_handle.prompt_embeddings()) {
+            (Some(payload), Some(store)) => {
+                faculty_embedding_index
+                    .as_ref()
+                    .map(|index| SavedRunEmbeddings {
+                        version: RUN_EMBEDDINGS_VERSION,
+                        model: index.model.clone(),
+                        dimension: index.dimension,
+                        payload,
+                        embeddings: store.snapshot(),
+                    })
+            }
+            _ => None,
+        };
+        if let Err(err) = record_run_history(app_handle, &response, saved_embeddings.as_ref()) {
+            response
+                .warnings
+                .push(format!("Unable to save this run to the history: {err}"));
         }
     }
 
-    let preview = SpreadsheetPreview {
-        headers: headers.clone(),
-        rows: preview_rows,
-        suggested_prompt_columns: Vec::new(),
-        suggested_identifier_columns: Vec::new(),
+    Ok(response)
+}
+
+fn build_results_json_export(
+    response: &SubmissionResponse,
+    run_info: &RunInfo,
+) -> Result<GeneratedSpreadsheet, String> {
+    let export = SubmissionJsonExport {
+        schema: RESULTS_JSON_SCHEMA,
+        schema_version: RESULTS_JSON_SCHEMA_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        run_info,
+        results: response,
     };
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|err| format!("Unable to serialize the results for export: {err}"))?;
+
+    Ok(GeneratedSpreadsheet {
+        filename: default_results_json_name(response.details.student_label.as_deref()),
+        mime_type: "application/json".into(),
+        content: Base64Engine.encode(json),
+        encoding: Some("base64".into()),
+        companion_files: Vec::new(),
+        saved_path: None,
+        saved_bytes: None,
+        write_duration_ms: None,
+    })
+}
 
-    let student_summary_rows: Vec<Vec<String>> = row_results
-        .iter()
-        .map(|result| result.identifier_values.clone())
-        .collect();
-    let workbook_bytes = build_matches_workbook(
-        &student_headers,
-        &student_summary_rows,
-        &faculty_headers,
-        &match_entries,
-    )?;
-    let encoded_workbook = Base64Engine.encode(workbook_bytes);
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RosterMatchSummary {
+    total_roster_rows: usize,
+    matched_roster_rows: usize,
+    unmatched_roster_rows: usize,
+    duplicate_roster_rows: usize,
+    matched_faculty: usize,
+    unmatched_samples: Vec<String>,
+}
 
-    let results = SpreadsheetMatchResults {
-        processed_rows,
-        matched_rows,
-        skipped_rows,
-        total_rows: match_entries.len(),
-        preview,
-        spreadsheet: GeneratedSpreadsheet {
-            filename: default_matches_workbook_name(),
-            mime_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".into(),
-            content: encoded_workbook,
-            encoding: Some("base64".into()),
-        },
-    };
+struct RosterMatchOptions<'a> {
+    column_map: &'a HashMap<String, String>,
+    fuzzy_matching: bool,
+    match_mode: RosterMatchMode,
+    value_identifier: Option<&'a str>,
+    email_domain_aliases: &'a HashMap<String, String>,
+    /// Whether identifier values match ignoring accents.
+    strip_diacritics: bool,
+}
 
-    Ok(SpreadsheetProcessingOutcome {
-        warnings,
-        prompt_matches,
-        results,
-    })
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct UnmatchedRosterRow {
+    row_number: usize,
+    values: Vec<String>,
+    attempted_keys: Vec<String>,
+    reason: String,
 }
 
-fn build_matches_headers(student_headers: &[String], faculty_headers: &[String]) -> Vec<String> {
-    let mut headers: Vec<String> = Vec::new();
-    headers.push("First reviewer".into());
-    headers.push("Reviewer".into());
-    headers.extend(student_headers.iter().cloned());
-    headers.extend(faculty_headers.iter().cloned());
-    headers.push("Similarity %".into());
-    headers.push("Student rank".into());
-    headers.push("Faculty rank".into());
-    headers
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RosterDiagnostics {
+    headers: Vec<String>,
+    unmatched_rows: Vec<UnmatchedRosterRow>,
 }
 
-fn build_matches_workbook(
-    student_headers: &[String],
-    student_summary_rows: &[Vec<String>],
-    faculty_headers: &[String],
-    match_entries: &[MatchEntry],
-) -> Result<Vec<u8>, String> {
-    let mut workbook = Workbook::new();
-    let matches_sheet_name = "Matches";
-    let matches_sheet = workbook.add_worksheet();
-    matches_sheet
-        .set_name(matches_sheet_name)
-        .map_err(|err| format!("Unable to configure the matches worksheet: {err}"))?;
+struct RosterMatchOutcome {
+    matched_rows: HashSet<usize>,
+    resolved_column_map: HashMap<String, String>,
+    value_identifier: Option<String>,
+    summary: RosterMatchSummary,
+    diagnostics: RosterDiagnostics,
+    warnings: Vec<String>,
+}
 
-    let header_format = Format::new().set_bold();
-    let percent_format = Format::new().set_num_format("0.0%");
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SavedFileResult {
+    path: String,
+    bytes: u64,
+}
 
-    let headers = build_matches_headers(student_headers, faculty_headers);
-    for (col_index, header) in headers.iter().enumerate() {
-        matches_sheet
-            .write_string_with_format(0, col_index as u16, header, &header_format)
-            .map_err(|err| format!("Unable to write the matches header row: {err}"))?;
-    }
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RunHistoryEntry {
+    run_id: String,
+    created_at: String,
+    summary: String,
+    warnings: Vec<String>,
+    details: serde_json::Value,
+    artifacts: Vec<RunHistoryArtifact>,
+    #[serde(default)]
+    rerankable: bool,
+}
 
-    let student_offset = 2u32;
-    let faculty_offset = student_offset + student_headers.len() as u32;
-    let similarity_col = faculty_offset + faculty_headers.len() as u32;
-    let student_rank_col = similarity_col + 1;
-    let faculty_rank_col = student_rank_col + 1;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RunHistoryArtifact {
+    filename: String,
+    mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    saved_path: Option<String>,
+}
 
-    for (row_index, entry) in match_entries.iter().enumerate() {
-        let row = (row_index + 1) as u32;
-        matches_sheet
-            .write_string(row, 0, "")
-            .map_err(|err| format!("Unable to write the first reviewer column: {err}"))?;
-        matches_sheet
-            .write_string(row, 1, "")
-            .map_err(|err| format!("Unable to write the reviewer column: {err}"))?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RosterMappingPreset {
+    name: String,
+    version: u32,
+    column_map: HashMap<String, String>,
+    #[serde(default)]
+    match_mode: RosterMatchMode,
+    saved_at: String,
+}
 
-        for (offset, value) in entry.student_values.iter().enumerate() {
-            matches_sheet
-                .write_string(row, (student_offset + offset as u32) as u16, value)
-                .map_err(|err| format!("Unable to write a student identifier value: {err}"))?;
-        }
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RosterMappingPresetReference {
+    name: String,
+    version: u32,
+}
 
-        for (offset, value) in entry.faculty_values.iter().enumerate() {
-            matches_sheet
-                .write_string(row, (faculty_offset + offset as u32) as u16, value)
-                .map_err(|err| format!("Unable to write a faculty identifier value: {err}"))?;
+struct AdHocRosterFaculty {
+    identifiers: HashMap<String, String>,
+    text: String,
+}
+
+const ROSTER_UNMATCHED_SAMPLE_LIMIT: usize = 10;
+const ROSTER_VALUE_OVERLAP_THRESHOLD: f32 = 0.5;
+const ROSTER_HEADER_HINTS: &[&str] = &[
+    "name",
+    "names",
+    "fullname",
+    "facultyname",
+    "faculty",
+    "mentor",
+    "mentors",
+    "email",
+    "emails",
+    "emailaddress",
+    "id",
+    "identifier",
+];
+
+fn match_roster_against_dataset(
+    roster: &Path,
+    metadata: &FacultyDatasetMetadata,
+    options: &RosterMatchOptions,
+) -> Result<RosterMatchOutcome, String> {
+    let mut warnings = Vec::new();
+
+    let headerless_values =
+        read_headerless_roster_values(roster, metadata, options.strip_diacritics)?;
+    let value_identifier = match &headerless_values {
+        Some((values, _)) => {
+            let requested = options.value_identifier.or_else(|| {
+                if options.column_map.len() == 1 {
+                    options.column_map.keys().next().map(String::as_str)
+                } else {
+                    None
+                }
+            });
+            Some(resolve_roster_value_identifier(
+                metadata,
+                values,
+                requested,
+                options.strip_diacritics,
+            )?)
+        }
+        None => None,
+    };
+    let value_column_map: HashMap<String, String>;
+    let column_map = match &value_identifier {
+        Some(identifier) => {
+            value_column_map = HashMap::from([(identifier.clone(), identifier.clone())]);
+            &value_column_map
         }
+        None => options.column_map,
+    };
 
-        if let Some(value) = entry.similarity {
-            matches_sheet
-                .write_number_with_format(
-                    row,
-                    similarity_col as u16,
-                    f64::from(value),
-                    &percent_format,
-                )
-                .map_err(|err| format!("Unable to write the similarity percentage: {err}"))?;
-        } else {
-            matches_sheet
-                .write_string(row, similarity_col as u16, "")
-                .map_err(|err| format!("Unable to write the similarity placeholder: {err}"))?;
+    let mut identifier_lookup: HashMap<String, String> = HashMap::new();
+    for identifier in &metadata.analysis.identifier_columns {
+        identifier_lookup.insert(identifier.trim().to_lowercase(), identifier.clone());
+    }
+
+    let mut resolved_map: HashMap<String, String> = HashMap::new();
+    for (raw_identifier, roster_label) in column_map.iter() {
+        let normalized_identifier = raw_identifier.trim().to_lowercase();
+        if normalized_identifier.is_empty() {
+            continue;
         }
 
-        if let Some((position, total)) = entry.student_rank {
-            let text = match total {
-                Some(limit) => format!("{position} of {limit}"),
-                None => position.to_string(),
-            };
-            matches_sheet
-                .write_string(row, student_rank_col as u16, text)
-                .map_err(|err| format!("Unable to write the student rank: {err}"))?;
-        } else {
-            matches_sheet
-                .write_string(row, student_rank_col as u16, "")
-                .map_err(|err| format!("Unable to write the student rank placeholder: {err}"))?;
+        let trimmed_label = roster_label.trim();
+        if trimmed_label.is_empty() {
+            continue;
         }
 
-        if let Some(rank) = entry.faculty_rank {
-            matches_sheet
-                .write_number(row, faculty_rank_col as u16, rank as f64)
-                .map_err(|err| format!("Unable to write the faculty rank: {err}"))?;
+        if let Some(original_identifier) = identifier_lookup.get(&normalized_identifier) {
+            resolved_map
+                .entry(original_identifier.clone())
+                .or_insert_with(|| trimmed_label.to_string());
         } else {
-            matches_sheet
-                .write_string(row, faculty_rank_col as u16, "")
-                .map_err(|err| format!("Unable to write the faculty rank placeholder: {err}"))?;
+            let message =
+                format!("The roster mapping includes an unknown identifier '{raw_identifier}'.",);
+            warnings.push(message);
         }
     }
 
-    let match_row_count = match_entries.len() as u32;
-    let mut student_summary_headers = student_headers.to_vec();
-    student_summary_headers.push("Total first reviewers".into());
-    student_summary_headers.push("Total reviewers".into());
-
-    let student_summary_sheet = workbook.add_worksheet();
-    student_summary_sheet
-        .set_name("Student Summary")
-        .map_err(|err| format!("Unable to configure the student summary worksheet: {err}"))?;
-    for (col_index, header) in student_summary_headers.iter().enumerate() {
-        student_summary_sheet
-            .write_string_with_format(0, col_index as u16, header, &header_format)
-            .map_err(|err| format!("Unable to write the student summary header row: {err}"))?;
+    if resolved_map.is_empty() {
+        return Err("Map at least one roster column to a faculty identifier.".into());
     }
 
-    let first_reviewer_range = if match_row_count > 0 {
-        Some(excel_range_reference(
-            matches_sheet_name,
-            1,
-            0,
-            match_row_count,
-            0,
-        ))
-    } else {
-        None
-    };
-    let reviewer_range = if match_row_count > 0 {
-        Some(excel_range_reference(
-            matches_sheet_name,
-            1,
-            1,
-            match_row_count,
-            1,
-        ))
-    } else {
-        None
+    // Rows are labelled by their source row number so blank lines in the roster do not
+    // shift the rows reported after them.
+    let (mut headers, mut rows, row_numbers) = match (headerless_values, &value_identifier) {
+        (Some((values, line_numbers)), Some(identifier)) => (
+            vec![identifier.clone()],
+            values.into_iter().map(|value| vec![value]).collect(),
+            line_numbers,
+        ),
+        _ => read_numbered_spreadsheet_with_limit(roster, None)?,
     };
+    align_row_lengths(&mut headers, &mut rows);
 
-    for (row_index, identifiers) in student_summary_rows.iter().enumerate() {
-        let row = (row_index + 1) as u32;
-        for (col_offset, value) in identifiers.iter().enumerate() {
-            student_summary_sheet
-                .write_string(row, col_offset as u16, value)
-                .map_err(|err| {
-                    format!("Unable to write a student summary identifier value: {err}")
-                })?;
-        }
+    let mut resolved_column_map = resolved_map.clone();
 
-        let first_col = student_headers.len();
-        let total_col = first_col + 1;
+    let header_map = build_header_index_map(&headers);
+    let mut roster_column_indexes: HashMap<String, usize> = HashMap::new();
 
-        if match_row_count == 0 {
-            student_summary_sheet
-                .write_number(row, first_col as u16, 0.0)
-                .map_err(|err| {
-                    format!("Unable to write the student first reviewer placeholder: {err}")
-                })?;
-            student_summary_sheet
-                .write_number(row, total_col as u16, 0.0)
-                .map_err(|err| {
-                    format!("Unable to write the student reviewer count placeholder: {err}")
-                })?;
-            continue;
-        }
+    for (identifier, roster_label) in resolved_map.iter() {
+        let normalized_label = roster_label.trim().to_lowercase();
+        let mut column_index = header_map.get(&normalized_label).copied();
 
-        let mut first_factors = Vec::new();
-        first_factors.push(format!(
-            "--({first}=1)",
-            first = first_reviewer_range.as_ref().unwrap()
-        ));
-        for (col_offset, _) in student_headers.iter().enumerate() {
-            let student_range = excel_range_reference(
-                matches_sheet_name,
-                1,
-                student_offset + col_offset as u32,
-                match_row_count,
-                student_offset + col_offset as u32,
-            );
-            let summary_cell = excel_cell_reference(row, col_offset as u32, true, false);
-            first_factors.push(format!("--({student_range}={summary_cell})"));
+        if column_index.is_none() {
+            let normalized_target = normalize_identifier_label(roster_label);
+            if !normalized_target.is_empty() {
+                for (candidate_index, header) in headers.iter().enumerate() {
+                    if normalize_identifier_label(header) == normalized_target {
+                        column_index = Some(candidate_index);
+                        break;
+                    }
+                }
+            }
         }
-        let first_formula = build_sumproduct_formula(&first_factors);
-        student_summary_sheet
-            .write_formula(row, first_col as u16, first_formula.as_str())
-            .map_err(|err| format!("Unable to write the student first reviewer formula: {err}"))?;
 
-        let mut total_factors = Vec::new();
-        total_factors.push(format!(
-            "--((( {first}=1)+({reviewer}=1))>0)",
-            first = first_reviewer_range.as_ref().unwrap(),
-            reviewer = reviewer_range.as_ref().unwrap()
-        ));
-        for (col_offset, _) in student_headers.iter().enumerate() {
-            let student_range = excel_range_reference(
-                matches_sheet_name,
-                1,
-                student_offset + col_offset as u32,
-                match_row_count,
-                student_offset + col_offset as u32,
+        if let Some(found_index) = column_index {
+            roster_column_indexes.insert(identifier.clone(), found_index);
+        } else {
+            let message = format!(
+                "The roster does not contain a column named '{roster_label}' for identifier '{identifier}'.",
             );
-            let summary_cell = excel_cell_reference(row, col_offset as u32, true, false);
-            total_factors.push(format!("--({student_range}={summary_cell})"));
+            warnings.push(message);
         }
-        let total_formula = build_sumproduct_formula(&total_factors);
-        student_summary_sheet
-            .write_formula(row, total_col as u16, total_formula.as_str())
-            .map_err(|err| format!("Unable to write the student reviewer count formula: {err}"))?;
     }
 
-    let mut faculty_summary_headers = faculty_headers.to_vec();
-    faculty_summary_headers.push("First reviewer count".into());
-    faculty_summary_headers.push("Total reviewer count".into());
-
-    let faculty_summary_sheet = workbook.add_worksheet();
-    faculty_summary_sheet
-        .set_name("Faculty Summary")
-        .map_err(|err| format!("Unable to configure the faculty summary worksheet: {err}"))?;
-    for (col_index, header) in faculty_summary_headers.iter().enumerate() {
-        faculty_summary_sheet
-            .write_string_with_format(0, col_index as u16, header, &header_format)
-            .map_err(|err| format!("Unable to write the faculty summary header row: {err}"))?;
+    if roster_column_indexes.is_empty() {
+        return Err(
+            "None of the mapped roster columns were found in the roster spreadsheet.".into(),
+        );
     }
 
-    let mut seen_faculty = HashSet::new();
-    let mut faculty_summary_rows: Vec<Vec<String>> = Vec::new();
-    for entry in match_entries {
-        if entry.faculty_rank.is_none() {
-            continue;
-        }
-        let key = entry.faculty_values.join("\u{1f}");
-        if seen_faculty.insert(key) {
-            faculty_summary_rows.push(entry.faculty_values.clone());
-        }
+    let identifier_order: Vec<String> = metadata
+        .analysis
+        .identifier_columns
+        .iter()
+        .filter(|identifier| roster_column_indexes.contains_key(*identifier))
+        .cloned()
+        .collect();
+
+    if identifier_order.is_empty() {
+        return Err("Map at least one roster column to a faculty identifier.".into());
     }
 
-    for (row_index, identifiers) in faculty_summary_rows.iter().enumerate() {
-        let row = (row_index + 1) as u32;
-        for (col_offset, value) in identifiers.iter().enumerate() {
-            faculty_summary_sheet
-                .write_string(row, col_offset as u16, value)
-                .map_err(|err| {
-                    format!("Unable to write a faculty summary identifier value: {err}")
-                })?;
+    let domain_aliases = build_email_domain_aliases(options.email_domain_aliases);
+    let email_identifiers: HashSet<String> = identifier_order
+        .iter()
+        .filter(|identifier| {
+            let dataset_values = metadata
+                .memberships
+                .iter()
+                .filter_map(|membership| membership.identifiers.get(*identifier));
+            let roster_values = roster_column_indexes
+                .get(*identifier)
+                .map(|&index| {
+                    rows.iter()
+                        .filter_map(|row| row.get(index))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            values_look_like_emails(dataset_values)
+                || values_look_like_emails(roster_values.into_iter())
+        })
+        .cloned()
+        .collect();
+    let normalize_roster_value = |identifier: &str, value: &str| {
+        if email_identifiers.contains(identifier) {
+            normalize_email_value(value, &domain_aliases, options.strip_diacritics)
+        } else {
+            normalize_identifier_value(value, options.strip_diacritics)
         }
+    };
+    let name_identifiers: HashSet<String> = identifier_order
+        .iter()
+        .filter(|identifier| {
+            !email_identifiers.contains(*identifier)
+                && values_look_like_names(
+                    metadata
+                        .memberships
+                        .iter()
+                        .filter_map(|membership| membership.identifiers.get(*identifier)),
+                )
+        })
+        .cloned()
+        .collect();
 
-        let first_col = faculty_headers.len();
-        let total_col = first_col + 1;
+    let mut dataset_index: HashMap<String, HashSet<usize>> = HashMap::new();
+    let mut plain_dataset_keys: HashSet<String> = HashSet::new();
+    let mut identifier_index: HashMap<&str, HashMap<String, HashSet<usize>>> = HashMap::new();
+    let mut plain_identifier_keys: HashMap<&str, HashSet<String>> = HashMap::new();
+    for membership in &metadata.memberships {
+        for identifier in &identifier_order {
+            let Some(value) = membership.identifiers.get(identifier) else {
+                continue;
+            };
+            let normalized = normalize_roster_value(identifier, value);
+            if normalized.is_empty() {
+                continue;
+            }
+            identifier_index
+                .entry(identifier.as_str())
+                .or_default()
+                .entry(normalized)
+                .or_default()
+                .insert(membership.row_index);
+            if email_identifiers.contains(identifier) {
+                plain_identifier_keys
+                    .entry(identifier.as_str())
+                    .or_default()
+                    .insert(normalize_identifier_value(value, options.strip_diacritics));
+            }
+        }
 
-        if match_row_count == 0 {
-            faculty_summary_sheet
-                .write_number(row, first_col as u16, 0.0)
-                .map_err(|err| {
-                    format!("Unable to write the faculty first reviewer placeholder: {err}")
-                })?;
-            faculty_summary_sheet
-                .write_number(row, total_col as u16, 0.0)
-                .map_err(|err| {
-                    format!("Unable to write the faculty reviewer placeholder: {err}")
-                })?;
-            continue;
+        let mut parts = Vec::new();
+        let mut plain_parts = Vec::new();
+        for identifier in &identifier_order {
+            if let Some(value) = membership.identifiers.get(identifier) {
+                let normalized = normalize_roster_value(identifier, value);
+                if normalized.is_empty() {
+                    parts.clear();
+                    break;
+                }
+                parts.push(normalized);
+                plain_parts.push(normalize_identifier_value(value, options.strip_diacritics));
+            } else {
+                parts.clear();
+                break;
+            }
         }
 
-        let mut first_factors = Vec::new();
-        first_factors.push(format!(
-            "--({first}=1)",
-            first = first_reviewer_range.as_ref().unwrap()
-        ));
-        for (col_offset, _) in faculty_headers.iter().enumerate() {
-            let faculty_range = excel_range_reference(
-                matches_sheet_name,
-                1,
-                faculty_offset + col_offset as u32,
-                match_row_count,
-                faculty_offset + col_offset as u32,
-            );
-            let summary_cell = excel_cell_reference(row, col_offset as u32, true, false);
-            first_factors.push(format!("--({faculty_range}={summary_cell})"));
+        if parts.is_empty() {
+            continue;
         }
-        let first_formula = build_sumproduct_formula(&first_factors);
-        faculty_summary_sheet
-            .write_formula(row, first_col as u16, first_formula.as_str())
-            .map_err(|err| format!("Unable to write the faculty first reviewer formula: {err}"))?;
 
-        let mut total_factors = Vec::new();
-        total_factors.push(format!(
-            "--((( {first}=1)+({reviewer}=1))>0)",
-            first = first_reviewer_range.as_ref().unwrap(),
-            reviewer = reviewer_range.as_ref().unwrap()
-        ));
-        for (col_offset, _) in faculty_headers.iter().enumerate() {
-            let faculty_range = excel_range_reference(
-                matches_sheet_name,
-                1,
-                faculty_offset + col_offset as u32,
-                match_row_count,
-                faculty_offset + col_offset as u32,
-            );
-            let summary_cell = excel_cell_reference(row, col_offset as u32, true, false);
-            total_factors.push(format!("--({faculty_range}={summary_cell})"));
+        let key = parts.join("|");
+        dataset_index
+            .entry(key)
+            .or_default()
+            .insert(membership.row_index);
+        if !email_identifiers.is_empty() {
+            plain_dataset_keys.insert(plain_parts.join("|"));
         }
-        let total_formula = build_sumproduct_formula(&total_factors);
-        faculty_summary_sheet
-            .write_formula(row, total_col as u16, total_formula.as_str())
-            .map_err(|err| format!("Unable to write the faculty reviewer formula: {err}"))?;
     }
 
-    workbook
-        .save_to_buffer()
-        .map_err(|err| format!("Unable to finalize the match workbook: {err}"))
-}
-
-fn build_sumproduct_formula(factors: &[String]) -> String {
-    if factors.is_empty() {
-        "=0".into()
-    } else {
-        format!("=SUMPRODUCT({})", factors.join(", "))
+    let dataset_index_empty = match options.match_mode {
+        RosterMatchMode::All => dataset_index.is_empty(),
+        RosterMatchMode::Any => identifier_index.is_empty(),
+    };
+    if dataset_index_empty {
+        let message =
+            "No faculty dataset identifiers were available for the selected roster columns."
+                .to_string();
+        warnings.push(message);
     }
-}
 
-fn excel_column_name(mut index: u32) -> String {
-    let mut name = String::new();
-    loop {
-        let remainder = index % 26;
-        name.push((b'A' + remainder as u8) as char);
-        if index < 26 {
-            break;
+    let mut seen_roster_keys: HashSet<String> = HashSet::new();
+    let mut duplicate_roster_rows = 0usize;
+    let mut duplicate_values: Vec<String> = Vec::new();
+    for row in &rows {
+        let values: Vec<&String> = identifier_order
+            .iter()
+            .filter_map(|identifier| roster_column_indexes.get(identifier))
+            .filter_map(|&index| row.get(index))
+            .collect();
+        let key = identifier_order
+            .iter()
+            .zip(&values)
+            .map(|(identifier, value)| normalize_roster_value(identifier, value))
+            .collect::<Vec<_>>()
+            .join("|");
+        if key.chars().all(|ch| ch == '|') {
+            continue;
         }
-        index = index / 26 - 1;
-    }
-    name.chars().rev().collect()
-}
 
-fn excel_cell_reference(row: u32, col: u32, absolute_col: bool, absolute_row: bool) -> String {
-    let mut reference = String::new();
-    if absolute_col {
-        reference.push('$');
+        if !seen_roster_keys.insert(key) {
+            duplicate_roster_rows += 1;
+            let display = values
+                .iter()
+                .map(|value| value.trim())
+                .collect::<Vec<_>>()
+                .join(" / ");
+            if duplicate_values.len() < ROSTER_UNMATCHED_SAMPLE_LIMIT
+                && !duplicate_values.contains(&display)
+            {
+                duplicate_values.push(display);
+            }
+        }
     }
-    reference.push_str(&excel_column_name(col));
-    if absolute_row {
-        reference.push('$');
+
+    if duplicate_roster_rows > 0 {
+        let message = format!(
+            "{duplicate_roster_rows} roster row{plural} repeated an earlier entry: {}.",
+            duplicate_values.join("; "),
+            plural = if duplicate_roster_rows == 1 { "" } else { "s" }
+        );
+        warnings.push(message);
     }
-    reference.push_str(&(row + 1).to_string());
-    reference
-}
 
-fn excel_range_reference(
-    sheet: &str,
-    start_row: u32,
-    start_col: u32,
-    end_row: u32,
-    end_col: u32,
-) -> String {
-    let escaped_sheet = sheet.replace('\'', "''");
-    let start = excel_cell_reference(start_row, start_col, true, true);
-    let end = excel_cell_reference(end_row, end_col, true, true);
-    format!("'{}'!{}:{}", escaped_sheet, start, end)
-}
+    let mut matched_rows: HashSet<usize> = HashSet::new();
+    let mut unmatched_roster_indexes: BTreeSet<usize> = BTreeSet::new();
+    let mut fuzzy_candidates: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut email_only_matches = 0usize;
+    let mut conflicting_messages = Vec::new();
 
-fn default_matches_workbook_name() -> String {
-    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
-    format!("DBBS_matches_{timestamp}.xlsx")
-}
+    for (roster_index, row) in rows.iter().enumerate() {
+        if options.match_mode == RosterMatchMode::Any {
+            let mut identifier_matches: Vec<(&String, &HashSet<usize>)> = Vec::new();
+            let mut plain_match = false;
 
-fn default_directory_workbook_name() -> String {
-    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
-    format!("DBBS_directory_matches_{timestamp}.xlsx")
-}
+            for identifier in &identifier_order {
+                let Some(value) = roster_column_indexes
+                    .get(identifier)
+                    .and_then(|&index| row.get(index))
+                else {
+                    continue;
+                };
+                let normalized = normalize_roster_value(identifier, value);
+                if normalized.is_empty() {
+                    continue;
+                }
+                if let Some(found) = identifier_index
+                    .get(identifier.as_str())
+                    .and_then(|index| index.get(&normalized))
+                {
+                    identifier_matches.push((identifier, found));
+                    plain_match |= !email_identifiers.contains(identifier)
+                        || plain_identifier_keys
+                            .get(identifier.as_str())
+                            .is_some_and(|keys| {
+                                keys.contains(&normalize_identifier_value(
+                                    value,
+                                    options.strip_diacritics,
+                                ))
+                            });
+                }
+            }
 
-fn format_similarity_percent(value: f32) -> String {
-    if value.is_finite() {
-        format!("{:.1}%", value * 100.0)
-    } else {
-        "n/a".into()
-    }
-}
+            if identifier_matches.is_empty() {
+                unmatched_roster_indexes.insert(roster_index);
+                if options.fuzzy_matching {
+                    let raw_values = identifier_order
+                        .iter()
+                        .filter_map(|identifier| roster_column_indexes.get(identifier))
+                        .map(|&index| row.get(index).cloned().unwrap_or_default())
+                        .collect();
+                    fuzzy_candidates.push((roster_index, raw_values));
+                }
+                continue;
+            }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
-    if a.len() != b.len() || a.is_empty() {
-        return None;
-    }
+            let (_, first_rows) = identifier_matches[0];
+            if identifier_matches
+                .iter()
+                .any(|(_, found)| *found != first_rows)
+            {
+                let described = identifier_matches
+                    .iter()
+                    .map(|(identifier, found)| {
+                        let mut dataset_rows: Vec<usize> =
+                            found.iter().map(|row_index| row_index + 2).collect();
+                        dataset_rows.sort_unstable();
+                        let dataset_rows = dataset_rows
+                            .iter()
+                            .map(|row_number| row_number.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("{identifier} matched dataset row {dataset_rows}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                conflicting_messages.push(format!(
+                    "Roster row {} matched different faculty through different identifiers ({described}); all matches were included.",
+                    row_numbers[roster_index]
+                ));
+            }
 
-    let mut dot = 0.0f64;
-    let mut norm_a = 0.0f64;
-    let mut norm_b = 0.0f64;
+            for (_, found) in &identifier_matches {
+                matched_rows.extend(found.iter().copied());
+            }
+            if !email_identifiers.is_empty() && !plain_match {
+                email_only_matches += 1;
+            }
+            continue;
+        }
 
-    for (&x, &y) in a.iter().zip(b.iter()) {
-        let xf = f64::from(x);
-        let yf = f64::from(y);
-        dot += xf * yf;
-        norm_a += xf * xf;
-        norm_b += yf * yf;
-    }
+        let mut parts = Vec::new();
+        let mut plain_parts = Vec::new();
+        let mut row_missing = false;
 
-    if norm_a == 0.0 || norm_b == 0.0 {
-        return None;
-    }
+        for identifier in &identifier_order {
+            let column_index = match roster_column_indexes.get(identifier) {
+                Some(index) => *index,
+                None => {
+                    row_missing = true;
+                    break;
+                }
+            };
 
-    Some((dot / (norm_a.sqrt() * norm_b.sqrt())) as f32)
-}
+            let value = row.get(column_index).map(|value| {
+                (
+                    normalize_roster_value(identifier, value),
+                    normalize_identifier_value(value, options.strip_diacritics),
+                )
+            });
+            match value {
+                Some((normalized, plain)) if !normalized.is_empty() => {
+                    parts.push(normalized);
+                    plain_parts.push(plain);
+                }
+                _ => {
+                    row_missing = true;
+                    break;
+                }
+            }
+        }
 
-fn extract_document_prompt(path: &Path) -> Result<DocumentExtractionResult, String> {
-    let data = fs::read(path)
-        .map_err(|err| format!("Unable to read document '{}': {err}", path.display()))?;
-    let extension = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_ascii_lowercase());
+        if row_missing || parts.is_empty() {
+            unmatched_roster_indexes.insert(roster_index);
+            continue;
+        }
 
-    let mut warnings = Vec::new();
+        let key = parts.join("|");
+        if let Some(rows) = dataset_index.get(&key) {
+            matched_rows.extend(rows.iter().copied());
+            if !email_identifiers.is_empty() && !plain_dataset_keys.contains(&plain_parts.join("|"))
+            {
+                email_only_matches += 1;
+            }
+        } else {
+            unmatched_roster_indexes.insert(roster_index);
+            if options.fuzzy_matching {
+                let raw_values = identifier_order
+                    .iter()
+                    .filter_map(|identifier| roster_column_indexes.get(identifier))
+                    .map(|&index| row.get(index).cloned().unwrap_or_default())
+                    .collect();
+                fuzzy_candidates.push((roster_index, raw_values));
+            }
+        }
+    }
 
-    let raw_text = match extension.as_deref() {
-        Some("txt") => decode_text_bytes(&data, &mut warnings),
-        Some("pdf") => extract_pdf_text(&data)?,
-        Some("docx") => extract_docx_text(&data)?,
-        Some("doc") => extract_doc_text(&data, &mut warnings)?,
-        _ => detect_and_extract_unknown_document(&data, &mut warnings)?,
-    };
+    if options.fuzzy_matching && !fuzzy_candidates.is_empty() && name_identifiers.is_empty() {
+        warnings.push(
+            "Fuzzy matching was skipped because none of the mapped roster columns hold names; emails and IDs must match exactly."
+                .into(),
+        );
+    }
 
-    let normalized = normalize_document_text(&raw_text);
+    if options.fuzzy_matching && !fuzzy_candidates.is_empty() && !name_identifiers.is_empty() {
+        let fuzzy_indexes: Vec<Option<FuzzyRosterIndex>> = match options.match_mode {
+            RosterMatchMode::All => vec![Some(FuzzyRosterIndex::new(
+                &metadata.memberships,
+                &identifier_order,
+                &name_identifiers,
+                &normalize_roster_value,
+            ))],
+            RosterMatchMode::Any => identifier_order
+                .iter()
+                .map(|identifier| {
+                    name_identifiers.contains(identifier).then(|| {
+                        FuzzyRosterIndex::new(
+                            &metadata.memberships,
+                            std::slice::from_ref(identifier),
+                            &name_identifiers,
+                            &normalize_roster_value,
+                        )
+                    })
+                })
+                .collect(),
+        };
+        let mut fuzzy_messages = Vec::new();
+        let mut ambiguous_messages = Vec::new();
 
-    Ok(DocumentExtractionResult {
-        text: normalized,
-        warnings,
-    })
-}
+        for (roster_index, values) in &fuzzy_candidates {
+            let roster_label = format!("Roster row {}", row_numbers[*roster_index]);
+            let roster_value = values
+                .iter()
+                .map(|value| value.trim())
+                .collect::<Vec<_>>()
+                .join(" / ");
+
+            let resolved = match options.match_mode {
+                RosterMatchMode::All => fuzzy_indexes[0]
+                    .as_ref()
+                    .map(|fuzzy_index| fuzzy_index.resolve(values))
+                    .unwrap_or(FuzzyRosterMatch::NoMatch),
+                RosterMatchMode::Any => {
+                    let mut resolved = FuzzyRosterMatch::NoMatch;
+                    for (fuzzy_index, value) in fuzzy_indexes.iter().zip(values) {
+                        let Some(fuzzy_index) = fuzzy_index else {
+                            continue;
+                        };
+                        if value.trim().is_empty() {
+                            continue;
+                        }
+                        let candidate = fuzzy_index.resolve(std::slice::from_ref(value));
+                        if matches!(candidate, FuzzyRosterMatch::Matched { .. }) {
+                            resolved = candidate;
+                            break;
+                        }
+                        if matches!(resolved, FuzzyRosterMatch::NoMatch) {
+                            resolved = candidate;
+                        }
+                    }
+                    resolved
+                }
+            };
 
-fn detect_and_extract_unknown_document(
-    data: &[u8],
-    warnings: &mut Vec<String>,
-) -> Result<String, String> {
-    if looks_like_pdf(data) {
-        return extract_pdf_text(data);
-    }
-    if looks_like_docx(data) {
-        return extract_docx_text(data);
-    }
-    if looks_like_rtf(data) {
-        return extract_rtf_text(data);
-    }
-    if std::str::from_utf8(data).is_ok() {
-        return Ok(decode_text_bytes(data, warnings));
+            match resolved {
+                FuzzyRosterMatch::Matched {
+                    row_index,
+                    dataset_value,
+                    method,
+                } => {
+                    matched_rows.insert(row_index);
+                    unmatched_roster_indexes.remove(roster_index);
+                    fuzzy_messages.push(format!(
+                        "{roster_label} '{roster_value}' was fuzzy-matched to '{dataset_value}' ({method})."
+                    ));
+                }
+                FuzzyRosterMatch::Ambiguous { candidates } => {
+                    ambiguous_messages.push(format!(
+                        "{roster_label} '{roster_value}' resembles several faculty ({}) and was not matched automatically.",
+                        candidates.join("; ")
+                    ));
+                }
+                FuzzyRosterMatch::NoMatch => {}
+            }
+        }
+
+        for message in fuzzy_messages.into_iter().chain(ambiguous_messages) {
+            warnings.push(message);
+        }
     }
 
-    Err("The selected document format is not supported. Provide a PDF, Word document, or plain text file.".into())
-}
+    warnings.extend(conflicting_messages);
 
-fn extract_doc_text(data: &[u8], warnings: &mut Vec<String>) -> Result<String, String> {
-    if looks_like_docx(data) {
-        return extract_docx_text(data);
+    if email_only_matches > 0 {
+        let message = format!(
+            "{email_only_matches} roster row{plural} matched only after normalizing email addresses.",
+            plural = if email_only_matches == 1 { "" } else { "s" }
+        );
+        warnings.push(message);
     }
-    if looks_like_pdf(data) {
-        return extract_pdf_text(data);
+
+    let unmatched_roster_rows = unmatched_roster_indexes.len();
+    if unmatched_roster_rows > 0 {
+        let message = format!(
+            "{unmatched_roster_rows} roster row{plural} did not match any faculty dataset entries.",
+            plural = if unmatched_roster_rows == 1 { "" } else { "s" }
+        );
+        warnings.push(message);
     }
-    if looks_like_rtf(data) {
-        return extract_rtf_text(data);
+
+    if matched_rows.is_empty() {
+        let message =
+            "No faculty in the dataset matched the provided roster identifiers.".to_string();
+        warnings.push(message);
     }
 
-    warnings.push(
-        "The .doc file was treated as plain text. Save the document as .docx if formatting is important.".into(),
-    );
-    Ok(decode_text_bytes(data, warnings))
-}
+    for (identifier, &index) in &roster_column_indexes {
+        resolved_column_map.insert(identifier.clone(), header_label(&headers, index));
+    }
 
-fn extract_pdf_text(data: &[u8]) -> Result<String, String> {
-    extract_text_from_mem(data)
-        .map(|text| text.trim().to_string())
-        .map_err(|err| format!("Unable to extract text from the PDF document: {err}"))
-}
+    let unmatched_samples = unmatched_roster_indexes
+        .iter()
+        .take(ROSTER_UNMATCHED_SAMPLE_LIMIT)
+        .map(|&roster_index| {
+            let values: Vec<String> = identifier_order
+                .iter()
+                .filter_map(|identifier| roster_column_indexes.get(identifier))
+                .map(|&index| {
+                    rows.get(roster_index)
+                        .and_then(|row| row.get(index))
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                        .unwrap_or("(blank)")
+                        .to_string()
+                })
+                .collect();
+            format!("Row {}: {}", row_numbers[roster_index], values.join(" / "))
+        })
+        .collect();
 
-fn extract_docx_text(data: &[u8]) -> Result<String, String> {
-    let package =
-        read_docx(data).map_err(|err| format!("Unable to read the DOCX document: {err}"))?;
-    let mut segments = Vec::new();
+    let unmatched_rows = unmatched_roster_indexes
+        .iter()
+        .map(|&roster_index| {
+            let row = rows.get(roster_index).cloned().unwrap_or_default();
+            let mut attempted_keys = Vec::new();
+            let mut missing_columns = Vec::new();
+            for identifier in &identifier_order {
+                let Some(&column_index) = roster_column_indexes.get(identifier) else {
+                    continue;
+                };
+                let normalized = row
+                    .get(column_index)
+                    .map(|value| normalize_roster_value(identifier, value))
+                    .unwrap_or_default();
+                if normalized.is_empty() {
+                    missing_columns.push(header_label(&headers, column_index));
+                } else {
+                    attempted_keys.push(format!("{identifier}: {normalized}"));
+                }
+            }
 
-    for child in &package.document.children {
-        collect_docx_child_text(child, &mut segments);
-    }
+            let missing_value = match options.match_mode {
+                RosterMatchMode::All => !missing_columns.is_empty(),
+                RosterMatchMode::Any => attempted_keys.is_empty(),
+            };
+            let reason = if missing_value {
+                format!(
+                    "Missing value in mapped column{plural} '{columns}'",
+                    plural = if missing_columns.len() == 1 { "" } else { "s" },
+                    columns = missing_columns.join("', '")
+                )
+            } else {
+                "No dataset match".to_string()
+            };
 
-    Ok(segments.join("\n"))
-}
+            UnmatchedRosterRow {
+                row_number: row_numbers[roster_index],
+                values: row,
+                attempted_keys,
+                reason,
+            }
+        })
+        .collect();
 
-fn extract_rtf_text(data: &[u8]) -> Result<String, String> {
-    let content = String::from_utf8_lossy(data);
-    let document = RtfDocument::try_from(content.as_ref())
-        .map_err(|err| format!("Unable to parse the RTF document: {err}"))?;
-    Ok(document.get_text())
-}
+    let summary = RosterMatchSummary {
+        total_roster_rows: rows.len(),
+        matched_roster_rows: rows.len().saturating_sub(unmatched_roster_rows),
+        unmatched_roster_rows,
+        duplicate_roster_rows,
+        matched_faculty: matched_rows.len(),
+        unmatched_samples,
+    };
 
-fn decode_text_bytes(data: &[u8], warnings: &mut Vec<String>) -> String {
-    match std::str::from_utf8(data) {
-        Ok(text) => text.to_string(),
-        Err(_) => {
-            warnings.push(
-                "The document contained invalid UTF-8 characters. Some characters were replaced during decoding.".into(),
-            );
-            String::from_utf8_lossy(data).into_owned()
-        }
-    }
+    Ok(RosterMatchOutcome {
+        matched_rows,
+        resolved_column_map,
+        value_identifier,
+        summary,
+        diagnostics: RosterDiagnostics {
+            headers,
+            unmatched_rows,
+        },
+        warnings,
+    })
 }
 
-fn normalize_document_text(text: &str) -> String {
-    let mut normalized = text.replace('\u{0000}', "");
-    normalized = normalized.trim_start_matches('\u{FEFF}').to_string();
-    normalized = normalized.replace("\r\n", "\n");
-    normalized = normalized.replace('\r', "\n");
+fn collect_ad_hoc_roster_faculty(
+    diagnostics: &RosterDiagnostics,
+    column_map: &HashMap<String, String>,
+    text_column: &str,
+) -> Result<Vec<AdHocRosterFaculty>, String> {
+    let normalized_text_column = normalize_identifier_label(text_column);
+    let text_index = diagnostics
+        .headers
+        .iter()
+        .position(|header| normalize_identifier_label(header) == normalized_text_column)
+        .ok_or_else(|| {
+            format!(
+                "The roster does not contain a research description column named '{text_column}'."
+            )
+        })?;
 
-    let lines: Vec<&str> = normalized.lines().map(|line| line.trim_end()).collect();
-    lines.join("\n").trim().to_string()
-}
+    let identifier_indexes: Vec<(&String, usize)> = column_map
+        .iter()
+        .filter_map(|(identifier, label)| {
+            diagnostics
+                .headers
+                .iter()
+                .position(|header| header == label)
+                .map(|index| (identifier, index))
+        })
+        .collect();
 
-fn looks_like_pdf(data: &[u8]) -> bool {
-    data.starts_with(b"%PDF-")
-}
+    let mut faculty = Vec::new();
+    for unmatched in &diagnostics.unmatched_rows {
+        let text = unmatched
+            .values
+            .get(text_index)
+            .map(|value| value.trim())
+            .unwrap_or("");
+        if text.is_empty() {
+            continue;
+        }
 
-fn looks_like_docx(data: &[u8]) -> bool {
-    data.len() > 4 && data.starts_with(b"PK")
-}
+        let identifiers: HashMap<String, String> = identifier_indexes
+            .iter()
+            .filter_map(|(identifier, index)| {
+                unmatched
+                    .values
+                    .get(*index)
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| ((*identifier).clone(), value.to_string()))
+            })
+            .collect();
+        if identifiers.is_empty() {
+            continue;
+        }
 
-fn looks_like_rtf(data: &[u8]) -> bool {
-    let sample = String::from_utf8_lossy(data);
-    sample
-        .trim_start_matches(|c: char| c.is_ascii_whitespace())
-        .starts_with("{\\rtf")
+        faculty.push(AdHocRosterFaculty {
+            identifiers,
+            text: text.to_string(),
+        });
+    }
+
+    Ok(faculty)
 }
 
-fn collect_docx_child_text(child: &DocumentChild, segments: &mut Vec<String>) {
-    match child {
-        DocumentChild::Paragraph(paragraph) => {
-            if let Some(text) = collect_docx_paragraph_text(paragraph.as_ref(), segments) {
-                segments.push(text);
+fn best_overlapping_roster_column(
+    rows: &[Vec<String>],
+    dataset_keys: &HashSet<String>,
+    strip_diacritics: bool,
+) -> Option<(usize, f32)> {
+    if dataset_keys.is_empty() {
+        return None;
+    }
+
+    let column_count = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut best: Option<(usize, f32)> = None;
+    for column_index in 0..column_count {
+        let mut sampled = 0usize;
+        let mut overlapping = 0usize;
+        for value in rows.iter().filter_map(|row| row.get(column_index)) {
+            let normalized = normalize_identifier_value(value, strip_diacritics);
+            if normalized.is_empty() {
+                continue;
+            }
+            sampled += 1;
+            if dataset_keys.contains(&normalized) {
+                overlapping += 1;
             }
         }
-        DocumentChild::Table(table) => collect_docx_table_text(table.as_ref(), segments),
-        DocumentChild::StructuredDataTag(tag) => {
-            collect_docx_structured_data_tag(tag.as_ref(), segments)
+
+        if sampled == 0 {
+            continue;
+        }
+
+        let overlap = overlapping as f32 / sampled as f32;
+        if overlap >= ROSTER_VALUE_OVERLAP_THRESHOLD
+            && best.is_none_or(|(_, best_overlap)| overlap > best_overlap)
+        {
+            best = Some((column_index, overlap));
         }
-        _ => {}
     }
+
+    best
 }
 
-fn collect_docx_paragraph_text(
-    paragraph: &Paragraph,
-    segments: &mut Vec<String>,
-) -> Option<String> {
-    let mut buffer = String::new();
-    for child in &paragraph.children {
-        append_paragraph_child_text(child, &mut buffer, segments);
+/// A header-less roster's values and the 1-based line each came from.
+type HeaderlessRosterValues = (Vec<String>, Vec<usize>);
+
+/// Reads a pasted one-value-per-line roster. The first line is only treated as a value
+/// (not a header) when it is not label-like and something else vouches for it: it
+/// matches a dataset identifier, or it is an email or ID shaped like the lines below.
+/// Returns `None` when the roster looks like a regular spreadsheet.
+fn read_headerless_roster_values(
+    path: &Path,
+    metadata: &FacultyDatasetMetadata,
+    strip_diacritics: bool,
+) -> Result<Option<HeaderlessRosterValues>, String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if matches!(extension.as_str(), "xlsx" | "xlsm" | "xls" | "xlsb") {
+        return Ok(None);
     }
 
-    let trimmed = buffer.trim();
-    if trimmed.is_empty() {
-        None
-    } else {
-        Some(trimmed.to_string())
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read the faculty roster: {err}"))?;
+    let (values, line_numbers): (Vec<String>, Vec<usize>) = contents
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim().to_string())
+        .zip(1..)
+        .filter(|(line, _)| !line.is_empty())
+        .unzip();
+
+    if values.is_empty() || values.iter().any(|value| value.contains(['\t', ',', ';'])) {
+        return Ok(None);
     }
-}
 
-fn collect_docx_table_text(table: &Table, segments: &mut Vec<String>) {
-    for row in &table.rows {
-        let row = match row {
-            TableChild::TableRow(row) => row,
-        };
+    let first_label = normalize_identifier_label(&values[0]);
+    let looks_like_header = ROSTER_HEADER_HINTS.contains(&first_label.as_str())
+        || metadata
+            .analysis
+            .identifier_columns
+            .iter()
+            .any(|identifier| normalize_identifier_label(identifier) == first_label);
+    if looks_like_header {
+        return Ok(None);
+    }
 
-        for cell in &row.cells {
-            let cell = match cell {
-                TableRowChild::TableCell(cell) => cell,
-            };
+    let first_value = normalize_identifier_value(&values[0], strip_diacritics);
+    let matches_dataset = metadata.memberships.iter().any(|membership| {
+        membership
+            .identifiers
+            .values()
+            .any(|value| normalize_identifier_value(value, strip_diacritics) == first_value)
+    });
+    let first_shape = roster_value_shape(&values[0]);
+    let shaped_like_values = first_shape != RosterValueShape::Text
+        && values.len() > 1
+        && values[1..]
+            .iter()
+            .filter(|value| roster_value_shape(value) == first_shape)
+            .count()
+            * 2
+            > values.len() - 1;
+    if !matches_dataset && !shaped_like_values {
+        return Ok(None);
+    }
 
-            for content in &cell.children {
-                match content {
-                    TableCellContent::Paragraph(paragraph) => {
-                        if let Some(text) = collect_docx_paragraph_text(paragraph, segments) {
-                            segments.push(text);
-                        }
-                    }
-                    TableCellContent::Table(inner) => {
-                        collect_docx_table_text(inner, segments);
-                    }
-                    TableCellContent::StructuredDataTag(tag) => {
-                        collect_docx_structured_data_tag(tag.as_ref(), segments);
-                    }
-                    TableCellContent::TableOfContents(_) => {}
-                }
-            }
-        }
+    Ok(Some((values, line_numbers)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RosterValueShape {
+    Email,
+    Numeric,
+    Text,
+}
+
+fn roster_value_shape(value: &str) -> RosterValueShape {
+    let value = value.trim();
+    if value.contains('@') {
+        RosterValueShape::Email
+    } else if value.chars().any(|ch| ch.is_ascii_digit())
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || matches!(ch, '-' | ' '))
+    {
+        RosterValueShape::Numeric
+    } else {
+        RosterValueShape::Text
     }
 }
 
-fn collect_docx_structured_data_tag(tag: &StructuredDataTag, segments: &mut Vec<String>) {
-    let mut buffer = String::new();
-    append_structured_data_tag_text(tag, &mut buffer, segments);
-    let trimmed = buffer.trim();
-    if !trimmed.is_empty() {
-        segments.push(trimmed.to_string());
+fn resolve_roster_value_identifier(
+    metadata: &FacultyDatasetMetadata,
+    values: &[String],
+    requested: Option<&str>,
+    strip_diacritics: bool,
+) -> Result<String, String> {
+    if let Some(requested) = requested.map(str::trim).filter(|value| !value.is_empty()) {
+        return metadata
+            .analysis
+            .identifier_columns
+            .iter()
+            .find(|identifier| identifier.trim().eq_ignore_ascii_case(requested))
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "The faculty dataset does not have an identifier column named '{requested}'."
+                )
+            });
     }
+
+    infer_roster_value_identifier(metadata, values, strip_diacritics).ok_or_else(|| {
+        "No identifier columns are defined in the active faculty dataset.".to_string()
+    })
 }
 
-fn append_paragraph_child_text(
-    child: &ParagraphChild,
-    buffer: &mut String,
-    segments: &mut Vec<String>,
-) {
-    match child {
-        ParagraphChild::Run(run) => append_run_text(run.as_ref(), buffer),
-        ParagraphChild::Insert(insert) => append_insert_text(insert, buffer),
-        ParagraphChild::Hyperlink(hyperlink) => {
-            for inner in &hyperlink.children {
-                append_paragraph_child_text(inner, buffer, segments);
-            }
-        }
-        ParagraphChild::StructuredDataTag(tag) => {
-            append_structured_data_tag_text(tag.as_ref(), buffer, segments);
+fn infer_roster_value_identifier(
+    metadata: &FacultyDatasetMetadata,
+    values: &[String],
+    strip_diacritics: bool,
+) -> Option<String> {
+    let roster_keys: HashSet<String> = values
+        .iter()
+        .map(|value| normalize_identifier_value(value, strip_diacritics))
+        .filter(|value| !value.is_empty())
+        .collect();
+
+    let mut best: Option<(&String, usize)> = None;
+    for identifier in &metadata.analysis.identifier_columns {
+        let overlap = metadata
+            .memberships
+            .iter()
+            .filter_map(|membership| membership.identifiers.get(identifier))
+            .map(|value| normalize_identifier_value(value, strip_diacritics))
+            .filter(|value| roster_keys.contains(value))
+            .collect::<HashSet<_>>()
+            .len();
+        if best.is_none_or(|(_, best_overlap)| overlap > best_overlap) {
+            best = Some((identifier, overlap));
         }
-        ParagraphChild::BookmarkStart(_) | ParagraphChild::BookmarkEnd(_) => {}
-        ParagraphChild::CommentStart(_) | ParagraphChild::CommentEnd(_) => {}
-        ParagraphChild::Delete(_) => {}
-        ParagraphChild::PageNum(_) | ParagraphChild::NumPages(_) => {}
     }
+
+    best.map(|(identifier, _)| identifier.clone())
 }
 
-fn append_insert_text(insert: &Insert, buffer: &mut String) {
-    for child in &insert.children {
-        match child {
-            InsertChild::Run(run) => append_run_text(run.as_ref(), buffer),
-            InsertChild::Delete(_) => {}
-            InsertChild::CommentStart(_) | InsertChild::CommentEnd(_) => {}
-        }
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingRequestPayload {
+    model: String,
+    texts: Vec<EmbeddingRequestRow>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item_label: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item_label_plural: Option<String>,
 }
 
-fn append_structured_data_tag_text(
-    tag: &StructuredDataTag,
-    buffer: &mut String,
-    segments: &mut Vec<String>,
-) {
-    for child in &tag.children {
-        match child {
-            StructuredDataTagChild::Run(run) => append_run_text(run.as_ref(), buffer),
-            StructuredDataTagChild::Paragraph(paragraph) => {
-                if let Some(text) = collect_docx_paragraph_text(paragraph.as_ref(), segments) {
-                    if !buffer.is_empty() && !buffer.ends_with('\n') && !buffer.ends_with(' ') {
-                        buffer.push(' ');
-                    }
-                    buffer.push_str(&text);
-                }
-            }
-            StructuredDataTagChild::Table(table) => {
-                collect_docx_table_text(table.as_ref(), segments)
-            }
-            StructuredDataTagChild::StructuredDataTag(inner) => {
-                append_structured_data_tag_text(inner.as_ref(), buffer, segments);
-            }
-            StructuredDataTagChild::BookmarkStart(_) | StructuredDataTagChild::BookmarkEnd(_) => {}
-            StructuredDataTagChild::CommentStart(_) | StructuredDataTagChild::CommentEnd(_) => {}
-        }
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingRequestRow {
+    id: usize,
+    text: String,
 }
 
-fn append_run_text(run: &Run, buffer: &mut String) {
-    for child in &run.children {
-        match child {
-            RunChild::Text(text) => buffer.push_str(&text.text),
-            RunChild::Break(_) => buffer.push('\n'),
-            RunChild::Tab(_) | RunChild::PTab(_) => buffer.push('\t'),
-            RunChild::Sym(sym) => {
-                if let Ok(value) = u32::from_str_radix(&sym.char, 16) {
-                    if let Some(ch) = char::from_u32(value) {
-                        buffer.push(ch);
-                    }
-                }
-            }
-            RunChild::InstrTextString(value) => buffer.push_str(value),
-            RunChild::DeleteText(_) => {}
-            RunChild::FieldChar(_) => {}
-            RunChild::Drawing(_) => {}
-            RunChild::Shape(_) => {}
-            RunChild::CommentStart(_) | RunChild::CommentEnd(_) => {}
-            RunChild::FootnoteReference(_) => {}
-            RunChild::Shading(_) => {}
-            RunChild::InstrText(_) => {}
-            RunChild::DeleteInstrText(_) => {}
-        }
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingHelperRequest<'a> {
+    #[serde(flatten)]
+    payload: &'a EmbeddingRequestPayload,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device: Option<&'a str>,
 }
 
-struct RowEmbeddingContext {
-    row_index: usize,
-    text: String,
-    identifiers: HashMap<String, String>,
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingResponsePayload {
+    model: String,
+    dimension: usize,
+    rows: Vec<EmbeddingResponseRow>,
 }
 
-fn default_progress_phase() -> String {
-    "embedding".to_string()
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingResponseRow {
+    id: usize,
+    embedding: Vec<f32>,
+}
+
+/// How embeddings are written to disk, recorded as `precision` in the index header.
+/// Half precision stores each vector as base64 little-endian f16 values inside the same
+/// JSON index (there is no separate binary format); entries are always widened to f32
+/// when the index loads, so scoring still accumulates in f32.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum EmbeddingPrecision {
+    #[default]
+    F32,
+    F16,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct EmbeddingProgressUpdate {
-    #[serde(default = "default_progress_phase")]
-    phase: String,
-    #[serde(default)]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    message: Option<String>,
+struct FacultyEmbeddingEntry {
+    row_index: usize,
+    /// Stable identity that survives dataset edits; see `assign_faculty_ids`.
     #[serde(default)]
-    processed_rows: usize,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    faculty_id: String,
+    identifiers: HashMap<String, String>,
     #[serde(default)]
-    total_rows: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    embedding: Vec<f32>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    elapsed_seconds: Option<f64>,
+    embedding_f16: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "FacultySource::is_dataset")]
+    source: FacultySource,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    estimated_remaining_seconds: Option<f64>,
+    faculty_text: Option<String>,
 }
 
-fn emit_faculty_embedding_progress(
-    app_handle: &tauri::AppHandle,
-    progress: EmbeddingProgressUpdate,
-) {
-    let _ = app_handle.emit(FACULTY_EMBEDDING_PROGRESS_EVENT, progress);
+/// Where a faculty candidate came from. Roster-only faculty are embedded for a single
+/// run and have no dataset row, so their `row_index` is only a key within that run and
+/// must never be shown as a spreadsheet row number.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum FacultySource {
+    #[default]
+    Dataset,
+    Roster,
 }
 
-fn emit_embedding_error(app_handle: &tauri::AppHandle, total_rows: usize, message: &str) {
-    emit_faculty_embedding_progress(
-        app_handle,
-        EmbeddingProgressUpdate {
-            phase: "error".into(),
-            message: Some(message.to_string()),
-            processed_rows: 0,
-            total_rows,
-            elapsed_seconds: None,
-            estimated_remaining_seconds: None,
-        },
-    );
+impl FacultySource {
+    fn is_dataset(&self) -> bool {
+        *self == FacultySource::Dataset
+    }
 }
 
-#[tauri::command]
-async fn update_faculty_embeddings(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let result =
-        tauri::async_runtime::spawn_blocking(move || perform_faculty_embedding_refresh(app_handle))
-            .await
-            .map_err(|err| format!("Embedding refresh task failed: {err}"))?;
-
-    Ok(result?)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FacultyEmbeddingIndex {
+    model: String,
+    #[serde(default)]
+    generated_at: Option<String>,
+    dimension: usize,
+    #[serde(default)]
+    total_rows: Option<usize>,
+    #[serde(default)]
+    embedded_rows: Option<usize>,
+    #[serde(default)]
+    skipped_rows: Option<usize>,
+    #[serde(default)]
+    embedding_columns: Vec<String>,
+    #[serde(default)]
+    identifier_columns: Vec<String>,
+    #[serde(default)]
+    faculty_text_max_characters: Option<usize>,
+    #[serde(default)]
+    precision: EmbeddingPrecision,
+    entries: Vec<FacultyEmbeddingEntry>,
 }
 
-#[tauri::command]
-async fn ensure_embedding_helper_ready(app_handle: tauri::AppHandle) -> Result<bool, String> {
-    warmup_embedding_helper(app_handle).await?;
-    Ok(EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FacultyEmbeddingIndexHeader {
+    model: String,
+    #[serde(default)]
+    generated_at: Option<String>,
+    dimension: usize,
+    #[serde(default)]
+    total_rows: Option<usize>,
+    #[serde(default)]
+    embedded_rows: Option<usize>,
+    #[serde(default)]
+    skipped_rows: Option<usize>,
+    #[serde(default)]
+    embedding_columns: Vec<String>,
+    #[serde(default)]
+    identifier_columns: Vec<String>,
+    #[serde(default)]
+    faculty_text_max_characters: Option<usize>,
+    #[serde(default)]
+    precision: EmbeddingPrecision,
 }
 
-fn perform_faculty_embedding_refresh(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let started_at = Instant::now();
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "starting".into(),
-            message: Some("Preparing to refresh faculty embeddings…".into()),
-            processed_rows: 0,
-            total_rows: 0,
-            elapsed_seconds: Some(0.0),
-            estimated_remaining_seconds: None,
-        },
-    );
-
-    let status = build_faculty_dataset_status(&app_handle)?;
-
-    if !status.is_valid {
-        let message = status.message.unwrap_or_else(|| {
-            "Provide a valid faculty dataset before refreshing embeddings.".into()
-        });
-        return Err(message);
-    }
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingIndexStatus {
+    #[serde(flatten)]
+    header: FacultyEmbeddingIndexHeader,
+    file_size: u64,
+    #[serde(default)]
+    modified_at: Option<String>,
+    using_bundled_default: bool,
+}
 
-    let analysis = status.analysis.clone().ok_or_else(|| {
-        "Run the faculty dataset analysis before generating embeddings.".to_string()
-    })?;
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PromptMatchResult {
+    prompt: String,
+    faculty_matches: Vec<FacultyMatchResult>,
+}
 
-    let dataset_path = dataset_destination(&app_handle)?;
-    if !dataset_path.exists() {
-        return Err("No faculty dataset is available. Restore or configure the dataset before generating embeddings.".into());
-    }
+struct DocumentExtractionResult {
+    text: String,
+    warnings: Vec<String>,
+}
 
-    let (headers, rows) = read_full_spreadsheet(&dataset_path)?;
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FacultyMatchResult {
+    /// Internal: the dataset row behind this match, used for faculty text and program
+    /// lookups. Row indexes shift whenever the dataset is edited, so saved results should
+    /// be joined back to faculty by `faculty_id` instead.
+    row_index: usize,
+    faculty_id: String,
+    similarity: f32,
+    identifiers: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faculty_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student_rank_for_faculty: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student_rank_total: Option<usize>,
+    #[serde(skip_serializing_if = "FacultySource::is_dataset")]
+    source: FacultySource,
+}
 
-    if rows.is_empty() {
-        return Err("The faculty dataset does not include any data rows to embed.".into());
-    }
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct GeneratedSpreadsheet {
+    filename: String,
+    mime_type: String,
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    companion_files: Vec<GeneratedSpreadsheet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    saved_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    saved_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    write_duration_ms: Option<u64>,
+}
 
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "preparing".into(),
-            message: Some(format!(
-                "Scanning {row_count} faculty row{plural} for embedding content…",
-                row_count = rows.len(),
-                plural = if rows.len() == 1 { "" } else { "s" }
-            )),
-            processed_rows: 0,
-            total_rows: rows.len(),
-            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
-            estimated_remaining_seconds: None,
-        },
-    );
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DirectoryMatchResults {
+    processed_documents: usize,
+    matched_documents: usize,
+    skipped_documents: usize,
+    breakdown: ItemOutcomeBreakdown,
+    skipped_entries: Vec<SkippedEntry>,
+    needs_attention: Vec<AttentionEntry>,
+    total_rows: usize,
+    preview_total_rows: usize,
+    preview: SpreadsheetPreview,
+    spreadsheet: GeneratedSpreadsheet,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student_reports: Option<GeneratedSpreadsheet>,
+}
 
-    let header_map = build_header_index_map(&headers);
-    let embedding_indexes = indexes_from_labels(&header_map, &analysis.embedding_columns)?;
-    let identifier_indexes = indexes_from_labels(&header_map, &analysis.identifier_columns)?;
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SpreadsheetMatchResults {
+    processed_rows: usize,
+    matched_rows: usize,
+    skipped_rows: usize,
+    breakdown: ItemOutcomeBreakdown,
+    skipped_entries: Vec<SkippedEntry>,
+    needs_attention: Vec<AttentionEntry>,
+    truncated_rows: usize,
+    total_rows: usize,
+    preview_total_rows: usize,
+    preview: SpreadsheetPreview,
+    spreadsheet: GeneratedSpreadsheet,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    student_reports: Option<GeneratedSpreadsheet>,
+}
 
-    if embedding_indexes.is_empty() {
-        return Err("No embedding columns were identified for the faculty dataset.".into());
+/// Final state of one document or spreadsheet row in a batch run. Every input ends
+/// in exactly one state, so the breakdown counts always add up to the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemOutcome {
+    Pending,
+    Unreadable,
+    EmbeddingFailed,
+    Matched,
+    NoMatches,
+}
+
+impl ItemOutcome {
+    fn skipped(self) -> bool {
+        matches!(self, Self::Unreadable | Self::EmbeddingFailed)
     }
+}
 
-    let mut contexts = Vec::new();
-    let mut skipped_due_to_text = 0usize;
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct ItemOutcomeBreakdown {
+    total: usize,
+    /// Documents without readable text or rows whose prompt columns were empty.
+    unreadable: usize,
+    embedding_failed: usize,
+    matched: usize,
+    /// Embedded successfully but no faculty passed the scope and program filters.
+    no_matches: usize,
+}
 
-    for (row_index, row) in rows.iter().enumerate() {
-        let mut text_parts = Vec::new();
-        for &index in &embedding_indexes {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                text_parts.push(trimmed.to_string());
+impl ItemOutcomeBreakdown {
+    fn from_outcomes(outcomes: impl Iterator<Item = ItemOutcome>) -> Self {
+        let mut breakdown = Self::default();
+        for outcome in outcomes {
+            breakdown.total += 1;
+            match outcome {
+                ItemOutcome::Pending | ItemOutcome::Unreadable => breakdown.unreadable += 1,
+                ItemOutcome::EmbeddingFailed => breakdown.embedding_failed += 1,
+                ItemOutcome::Matched => breakdown.matched += 1,
+                ItemOutcome::NoMatches => breakdown.no_matches += 1,
             }
         }
+        breakdown
+    }
 
-        if text_parts.is_empty() {
-            skipped_due_to_text += 1;
-            continue;
-        }
+    fn processed(&self) -> usize {
+        self.matched + self.no_matches
+    }
 
-        let text = text_parts.join("\n\n");
+    fn skipped(&self) -> usize {
+        self.unreadable + self.embedding_failed
+    }
+}
 
-        let mut identifiers = HashMap::new();
-        for &index in &identifier_indexes {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                let label = header_label(&headers, index);
-                identifiers
-                    .entry(label)
-                    .or_insert_with(|| trimmed.to_string());
-            }
-        }
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SkippedEntry {
+    label: String,
+    identifier_values: Vec<String>,
+    reason: String,
+}
 
-        contexts.push(RowEmbeddingContext {
-            row_index,
-            text,
-            identifiers,
-        });
-    }
+struct MatchExportData<'a> {
+    student_headers: &'a [String],
+    /// How many leading student columns identify a student. Any after them echo
+    /// prompt text and stay off the Student Summary sheet.
+    summary_student_columns: usize,
+    student_summary_rows: &'a [Vec<String>],
+    faculty_headers: &'a [String],
+    match_entries: &'a [MatchEntry],
+    skipped_entries: &'a [SkippedEntry],
+    needs_attention: &'a [AttentionEntry],
+    similar_prompts: &'a [SimilarPromptPair],
+    statistics: &'a RunStatistics,
+    /// The student column holding document paths, which the workbook writes as links.
+    document_path_column: Option<usize>,
+}
 
-    if contexts.is_empty() {
-        return Err("None of the faculty rows include embedding content. Add research interest details before refreshing embeddings.".into());
-    }
+/// A processed student whose best match fell below the attention threshold, so a
+/// coordinator should consider matching them by hand.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AttentionEntry {
+    label: String,
+    identifier_values: Vec<String>,
+    best_similarity: Option<f32>,
+    best_faculty: Vec<String>,
+}
 
-    let total_contexts = contexts.len();
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "preparing".into(),
-            message: Some(format!(
-                "Prepared {total} faculty row{plural} for embedding.",
-                total = total_contexts,
-                plural = if total_contexts == 1 { "" } else { "s" }
-            )),
-            processed_rows: 0,
-            total_rows: total_contexts,
-            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
-            estimated_remaining_seconds: None,
-        },
-    );
+#[derive(Debug, Clone)]
+struct SimilarPromptPair {
+    first_values: Vec<String>,
+    second_values: Vec<String>,
+    similarity: f32,
+}
 
-    let request_payload = EmbeddingRequestPayload {
-        model: DEFAULT_EMBEDDING_MODEL.to_string(),
-        texts: contexts
-            .iter()
-            .map(|context| EmbeddingRequestRow {
-                id: context.row_index,
-                text: context.text.clone(),
-            })
-            .collect(),
-        item_label: Some("faculty row".into()),
-        item_label_plural: Some("faculty rows".into()),
-    };
+#[derive(Debug, Clone)]
+struct MatchEntry {
+    student_values: Vec<String>,
+    faculty_values: Vec<String>,
+    faculty_row: usize,
+    faculty_source: FacultySource,
+    faculty_id: String,
+    similarity: Option<f32>,
+    student_rank: Option<(usize, Option<usize>)>,
+    faculty_rank: Option<usize>,
+    prompt_text: Option<String>,
+    faculty_text: Option<String>,
+}
 
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "embedding".into(),
-            message: Some(format!(
-                "Starting embeddings for {total} faculty row{plural}…",
-                total = total_contexts,
-                plural = if total_contexts == 1 { "" } else { "s" }
-            )),
-            processed_rows: 0,
-            total_rows: total_contexts,
-            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
-            estimated_remaining_seconds: None,
-        },
-    );
+struct StudentSummaryRow {
+    top_faculty_values: Vec<String>,
+    top_similarity: Option<f32>,
+    above_threshold: usize,
+    notes: String,
+}
 
-    let response = run_embedding_helper(&app_handle, &request_payload)?;
+struct FacultySummaryRow {
+    faculty_values: Vec<String>,
+    student_count: usize,
+    average_similarity: Option<f32>,
+    max_similarity: Option<f32>,
+    best_student: String,
+    programs: String,
+}
 
-    if response.dimension == 0 || response.rows.is_empty() {
-        return Err("The embedding helper returned an empty result. Verify the Python environment can load the PubMedBERT model.".into());
-    }
+struct MatchRunOptions<'a> {
+    limit: usize,
+    allowed_rows: Option<&'a HashSet<usize>>,
+    faculty_in_scope: Option<usize>,
+    roster_diagnostics: Option<&'a RosterDiagnostics>,
+    output_format: OutputFormat,
+    include_prompt_text: bool,
+    include_faculty_text: bool,
+    include_faculty_id: bool,
+    include_raw_cosine: bool,
+    request_id: &'a str,
+    reviewer_input_mode: ReviewerInputMode,
+    summary_mode: SummaryMode,
+    matches_sort: MatchesSort,
+    prompt_text_max_chars: usize,
+    /// When set, spreadsheet runs copy each selected prompt column into an extra
+    /// student column, truncated to this many characters.
+    prompt_column_values_max_chars: Option<usize>,
+    sheet_per_student: bool,
+    similarity_thresholds: (f64, f64),
+    attention_threshold: f64,
+    similar_prompt_threshold: Option<f32>,
+    scrub_personal_information: bool,
+    strip_boilerplate: bool,
+    boilerplate_text: Option<&'a str>,
+    boilerplate_row_percent: f32,
+    max_prompt_characters: usize,
+    prompt_preview_characters: usize,
+    run_warnings: &'a [String],
+    run_info: &'a RunInfo,
+    preview_row_limit: usize,
+    output_path: Option<&'a Path>,
+    overwrite_output: bool,
+    student_reports: bool,
+    faculty_programs: Option<&'a HashMap<usize, Vec<String>>>,
+    mail_merge_template: Option<&'a str>,
+    filename_stem: Option<&'a str>,
+}
 
-    let EmbeddingResponsePayload {
-        model: response_model,
-        dimension: response_dimension,
-        rows: response_rows,
-    } = response;
+#[derive(Debug)]
+struct DirectoryProcessingOutcome {
+    warnings: Vec<String>,
+    prompt_matches: Vec<PromptMatchResult>,
+    results: DirectoryMatchResults,
+}
 
-    let mut embedding_map: HashMap<usize, Vec<f32>> = HashMap::new();
-    let helper_row_count = response_rows.len();
-    for row in response_rows {
-        embedding_map.insert(row.id, row.embedding);
-    }
+#[derive(Debug)]
+struct SpreadsheetProcessingOutcome {
+    warnings: Vec<String>,
+    prompt_matches: Vec<PromptMatchResult>,
+    results: SpreadsheetMatchResults,
+}
 
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "processing-results".into(),
-            message: Some("Aligning embeddings with faculty rows…".into()),
-            processed_rows: helper_row_count,
-            total_rows: total_contexts,
-            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
-            estimated_remaining_seconds: None,
-        },
-    );
+struct CachedEmbeddingIndex {
+    path: PathBuf,
+    file_size: u64,
+    modified: Option<SystemTime>,
+    content_hash: String,
+    index: Arc<FacultyEmbeddingIndex>,
+}
 
-    let mut entries = Vec::new();
-    let mut missing_embeddings = 0usize;
+/// The parsed faculty index kept between runs, held in Tauri managed state. Matching
+/// size and modification time skip the read entirely; a matching content hash after
+/// a read skips the much slower parse.
+#[derive(Default)]
+struct EmbeddingIndexCache {
+    entry: Mutex<Option<CachedEmbeddingIndex>>,
+    file_reads: AtomicUsize,
+}
 
-    for context in contexts {
-        match embedding_map.remove(&context.row_index) {
-            Some(embedding) => {
-                entries.push(FacultyEmbeddingEntry {
-                    row_index: context.row_index,
-                    identifiers: context.identifiers,
-                    embedding,
-                });
-            }
-            None => {
-                missing_embeddings += 1;
-            }
-        }
+impl EmbeddingIndexCache {
+    fn find(
+        &self,
+        matches: impl Fn(&CachedEmbeddingIndex) -> bool,
+    ) -> Option<Arc<FacultyEmbeddingIndex>> {
+        let entry = self.entry.lock().ok()?;
+        entry
+            .as_ref()
+            .filter(|cached| matches(cached))
+            .map(|cached| Arc::clone(&cached.index))
     }
 
-    if entries.is_empty() {
-        return Err("No embeddings were generated for the faculty dataset. Confirm the Python helper executed successfully.".into());
+    fn store(&self, cached: CachedEmbeddingIndex) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = Some(cached);
+        }
     }
 
-    entries.sort_by_key(|entry| entry.row_index);
-
-    let total_rows = rows.len();
-    let embedded_rows = entries.len();
-    let skipped_rows = total_rows.saturating_sub(embedded_rows);
+    /// Drops the cached index so the next load re-reads the file. The size and
+    /// modification time catch most changes on their own, but a rewrite can land within
+    /// the filesystem's timestamp resolution with an identical size.
+    fn invalidate(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+}
 
-    let index = FacultyEmbeddingIndex {
-        model: response_model,
-        generated_at: Some(Utc::now().to_rfc3339()),
-        dimension: response_dimension,
-        total_rows: Some(total_rows),
-        embedded_rows: Some(embedded_rows),
-        skipped_rows: Some(skipped_rows),
-        embedding_columns: analysis.embedding_columns.clone(),
-        identifier_columns: analysis.identifier_columns.clone(),
-        entries,
+fn load_faculty_embedding_index(
+    app_handle: &dyn MatchingHost,
+) -> Result<Arc<FacultyEmbeddingIndex>, String> {
+    let embeddings_path = dataset_directory(app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
+    let (file_size, modified) = match fs::metadata(&embeddings_path) {
+        Ok(metadata) => (metadata.len(), metadata.modified().ok()),
+        Err(_) => (DEFAULT_FACULTY_EMBEDDINGS.len() as u64, None),
     };
 
-    let embeddings_path = dataset_directory(&app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
-    ensure_dataset_directory(&embeddings_path)?;
+    let cache = app_handle.embedding_index_cache();
+    if let Some(index) = cache.and_then(|cache| {
+        cache.find(|cached| {
+            cached.path == embeddings_path
+                && cached.file_size == file_size
+                && cached.modified == modified
+        })
+    }) {
+        return Ok(index);
+    }
 
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "saving".into(),
-            message: Some("Saving faculty embedding index…".into()),
-            processed_rows: embedded_rows,
-            total_rows: total_contexts,
-            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
-            estimated_remaining_seconds: None,
-        },
-    );
-
-    let json = serde_json::to_string_pretty(&index)
-        .map_err(|err| format!("Unable to serialize faculty embeddings: {err}"))?;
-    fs::write(&embeddings_path, json)
-        .map_err(|err| format!("Unable to write faculty embeddings: {err}"))?;
+    // The lock is never held across the read or parse, so a slow load cannot stall
+    // other commands that only need the cached index.
+    let data = if embeddings_path.exists() {
+        if let Some(cache) = cache {
+            cache.file_reads.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+        fs::read(&embeddings_path)
+            .map_err(|err| format!("Unable to read faculty embeddings: {err}"))?
+    } else {
+        DEFAULT_FACULTY_EMBEDDINGS.to_vec()
+    };
+    let content_hash = format!("{:x}", Sha256::digest(&data));
+    let index = match cache.and_then(|cache| {
+        cache.find(|cached| cached.path == embeddings_path && cached.content_hash == content_hash)
+    }) {
+        Some(index) => index,
+        None => Arc::new(
+            parse_faculty_embedding_index(&data)
+                .map_err(|err| format!("Unable to parse faculty embeddings: {err}"))?,
+        ),
+    };
 
-    let mut message = format!(
-        "Generated embeddings for {embedded_rows} faculty row{plural} using {model}.",
-        embedded_rows = embedded_rows,
-        plural = if embedded_rows == 1 { "" } else { "s" },
-        model = index.model
-    );
+    if let Some(cache) = cache {
+        cache.store(CachedEmbeddingIndex {
+            path: embeddings_path,
+            file_size,
+            modified,
+            content_hash,
+            index: Arc::clone(&index),
+        });
+    }
+    Ok(index)
+}
 
-    if skipped_due_to_text + missing_embeddings > 0 {
-        message.push_str(&format!(
-            " Skipped {count} row{plural} without usable embedding content.",
-            count = skipped_due_to_text + missing_embeddings,
-            plural = if skipped_due_to_text + missing_embeddings == 1 {
-                ""
+fn model_embedding_index_path(
+    app_handle: &dyn MatchingHost,
+    model: &str,
+) -> Result<PathBuf, String> {
+    let slug: String = model
+        .trim()
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '.' {
+                ch
             } else {
-                "s"
+                '_'
             }
-        ));
-    }
-
-    message.push_str(&format!(
-        " Saved the embedding index to {}.",
-        embeddings_path.to_string_lossy()
-    ));
-
-    emit_faculty_embedding_progress(
-        &app_handle,
-        EmbeddingProgressUpdate {
-            phase: "complete".into(),
-            message: Some(message.clone()),
-            processed_rows: embedded_rows,
-            total_rows: total_contexts,
-            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
-            estimated_remaining_seconds: None,
-        },
-    );
-
-    Ok(message)
+        })
+        .collect();
+    Ok(dataset_directory(app_handle)?
+        .join(FACULTY_EMBEDDING_MODELS_DIRECTORY)
+        .join(format!("{slug}.json")))
 }
 
-fn build_header_index_map(headers: &[String]) -> HashMap<String, usize> {
-    let mut map = HashMap::new();
-    for (index, header) in headers.iter().enumerate() {
-        let label = header.trim();
-        let normalized = if label.is_empty() {
-            format!("Column {}", index + 1)
-        } else {
-            label.to_string()
-        };
-        map.entry(normalized.to_lowercase()).or_insert(index);
-    }
-    map
+fn store_model_embedding_index(
+    app_handle: &dyn MatchingHost,
+    model: &str,
+    data: &[u8],
+) -> Result<(), String> {
+    let path = model_embedding_index_path(app_handle, model)?;
+    ensure_dataset_directory(&path)?;
+    write_file_atomically(&path, data)
+        .map_err(|err| format!("Unable to save the {model} embedding index: {err}"))
 }
 
-fn indexes_from_labels(
-    header_map: &HashMap<String, usize>,
-    labels: &[String],
-) -> Result<Vec<usize>, String> {
-    let mut indexes = Vec::new();
-
-    for label in labels {
-        let key = label.trim().to_lowercase();
-        if let Some(&index) = header_map.get(&key) {
-            indexes.push(index);
-        } else {
-            return Err(format!(
-                "The column '{label}' is not available in the faculty dataset. Re-run the dataset analysis before refreshing embeddings."
-            ));
-        }
+fn available_embedding_index_models(app_handle: &dyn MatchingHost) -> Vec<String> {
+    let mut models = BTreeSet::new();
+    if let Ok(status) = embedding_index_status(app_handle) {
+        models.insert(status.header.model);
     }
-
-    indexes.sort_unstable();
-    indexes.dedup();
-    Ok(indexes)
-}
-
-fn indexes_from_spreadsheet_labels(
-    header_map: &HashMap<String, usize>,
-    labels: &[String],
-) -> Result<Vec<usize>, String> {
-    let mut indexes = Vec::new();
-
-    for label in labels {
-        let key = label.trim().to_lowercase();
-        if let Some(&index) = header_map.get(&key) {
-            indexes.push(index);
-        } else {
-            return Err(format!(
-                "The column '{label}' is not available in the spreadsheet. Reload the preview and try again."
-            ));
+    let directory = dataset_directory(app_handle)
+        .map(|directory| directory.join(FACULTY_EMBEDDING_MODELS_DIRECTORY));
+    if let Ok(entries) =
+        directory.and_then(|directory| fs::read_dir(directory).map_err(|err| err.to_string()))
+    {
+        for entry in entries.flatten() {
+            let header = fs::read(entry.path())
+                .ok()
+                .and_then(|data| serde_json::from_slice::<FacultyEmbeddingIndexHeader>(&data).ok());
+            if let Some(header) = header {
+                models.insert(header.model);
+            }
         }
     }
-
-    indexes.sort_unstable();
-    indexes.dedup();
-    Ok(indexes)
-}
-
-static EMBEDDING_HELPER: OnceLock<Mutex<EmbeddingHelperManager>> = OnceLock::new();
-static EMBEDDING_HELPER_READY: AtomicBool = AtomicBool::new(false);
-static EMBEDDING_HELPER_WARMUP_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-
-struct EmbeddingHelperManager {
-    child: Option<CommandChild>,
-    receiver: Option<Receiver<CommandEvent>>,
-    stdout_buffer: Vec<u8>,
+    models.into_iter().collect()
 }
 
-impl EmbeddingHelperManager {
-    fn new() -> Self {
-        Self {
-            child: None,
-            receiver: None,
-            stdout_buffer: Vec::new(),
-        }
+fn load_model_embedding_index(
+    app_handle: &dyn MatchingHost,
+    model: &str,
+) -> Result<Arc<FacultyEmbeddingIndex>, AppError> {
+    let active = load_faculty_embedding_index(app_handle)?;
+    if active.model == model {
+        return Ok(active);
     }
+    drop(active);
 
-    fn ensure_process(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
-        if self.child.is_none() {
-            let (receiver, child) = spawn_embedding_helper(app_handle)?;
-            self.child = Some(child);
-            self.receiver = Some(receiver);
-            self.stdout_buffer.clear();
-        }
-
-        Ok(())
+    let path = model_embedding_index_path(app_handle, model)?;
+    if !path.exists() {
+        let available = available_embedding_index_models(app_handle);
+        return Err(AppError::MissingEmbeddings {
+            message: format!(
+                "No faculty embedding index is available for {model}. Available indexes: {}.",
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            ),
+        });
     }
-
-    fn write_with_newline(&mut self, bytes: &[u8]) -> std::io::Result<()> {
-        if let Some(child) = self.child.as_mut() {
-            let mut payload = Vec::with_capacity(bytes.len() + 1);
-            payload.extend_from_slice(bytes);
-            payload.push(b'\n');
-
-            child
-                .write(&payload)
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))
-        } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::BrokenPipe,
-                "Embedding helper is not running",
+    let data = fs::read(&path).map_err(|err| {
+        AppError::io(
+            &path,
+            format!("Unable to read the {model} embedding index: {err}"),
+        )
+    })?;
+    parse_faculty_embedding_index(&data)
+        .map(Arc::new)
+        .map_err(|err| {
+            AppError::from(format!(
+                "Unable to parse the {model} embedding index: {err}"
             ))
+        })
+}
+
+/// Parses a stored index, widening half-precision entries back to f32 and giving entries
+/// saved before faculty IDs existed their IDs.
+fn parse_faculty_embedding_index(data: &[u8]) -> Result<FacultyEmbeddingIndex, String> {
+    let mut index: FacultyEmbeddingIndex =
+        serde_json::from_slice(data).map_err(|err| err.to_string())?;
+    for entry in &mut index.entries {
+        if let Some(encoded) = entry.embedding_f16.take() {
+            let bytes = Base64Engine.decode(encoded.as_bytes()).map_err(|err| {
+                format!(
+                    "row {} has an invalid half-precision embedding: {err}",
+                    entry.row_index + 1
+                )
+            })?;
+            if bytes.len() != index.dimension * 2 {
+                return Err(format!(
+                    "row {} has a half-precision embedding of {} bytes; expected {} values ({} bytes)",
+                    entry.row_index + 1,
+                    bytes.len(),
+                    index.dimension,
+                    index.dimension * 2
+                ));
+            }
+            entry.embedding = bytes
+                .chunks_exact(2)
+                .map(|pair| half::f16::from_le_bytes([pair[0], pair[1]]).to_f32())
+                .collect();
         }
     }
+    assign_faculty_ids(&index.identifier_columns, &mut index.entries);
+    Ok(index)
+}
 
-    fn receive_event(&mut self) -> Result<Option<CommandEvent>, String> {
-        let receiver = match self.receiver.as_mut() {
-            Some(receiver) => receiver,
-            None => return Ok(None),
-        };
-
-        Ok(tauri::async_runtime::block_on(receiver.recv()))
+fn serialize_faculty_embedding_index(index: &FacultyEmbeddingIndex) -> Result<Vec<u8>, String> {
+    if index.precision == EmbeddingPrecision::F32 {
+        return serde_json::to_vec_pretty(index).map_err(|err| err.to_string());
     }
 
-    fn reset(&mut self) {
-        self.child = None;
-        self.receiver = None;
-        self.stdout_buffer.clear();
+    let mut stored = index.clone();
+    for entry in &mut stored.entries {
+        let bytes: Vec<u8> = entry
+            .embedding
+            .iter()
+            .flat_map(|&value| half::f16::from_f32(value).to_le_bytes())
+            .collect();
+        entry.embedding_f16 = Some(Base64Engine.encode(bytes));
+        entry.embedding = Vec::new();
     }
+    serde_json::to_vec_pretty(&stored).map_err(|err| err.to_string())
+}
 
-    fn shutdown(&mut self) {
-        if let Some(child) = self.child.as_mut() {
-            let _ = child.write(b"{\"command\":\"shutdown\"}\n");
-        }
-
-        if let Some(receiver) = self.receiver.as_mut() {
-            while let Some(event) = tauri::async_runtime::block_on(receiver.recv()) {
-                if matches!(event, CommandEvent::Terminated(_)) {
-                    break;
-                }
-            }
-        }
-
-        self.reset();
+fn embedding_index_status(app_handle: &dyn MatchingHost) -> Result<EmbeddingIndexStatus, String> {
+    let directory = dataset_directory(app_handle)?;
+    let embeddings_path = directory.join(FACULTY_EMBEDDINGS_NAME);
+    if !embeddings_path.exists() {
+        let header = serde_json::from_slice(DEFAULT_FACULTY_EMBEDDINGS)
+            .map_err(|err| format!("Unable to parse faculty embeddings: {err}"))?;
+        return Ok(EmbeddingIndexStatus {
+            header,
+            file_size: DEFAULT_FACULTY_EMBEDDINGS.len() as u64,
+            modified_at: None,
+            using_bundled_default: true,
+        });
     }
-}
 
-fn embedding_helper_manager() -> &'static Mutex<EmbeddingHelperManager> {
-    EMBEDDING_HELPER.get_or_init(|| Mutex::new(EmbeddingHelperManager::new()))
-}
+    let metadata = fs::metadata(&embeddings_path)
+        .map_err(|err| format!("Unable to inspect faculty embeddings: {err}"))?;
+    let file_size = metadata.len();
+    let modified_at = metadata.modified().ok().map(format_system_time);
 
-fn embedding_helper_warmup_lock() -> &'static Mutex<()> {
-    EMBEDDING_HELPER_WARMUP_LOCK.get_or_init(|| Mutex::new(()))
-}
+    let summary_path = directory.join(FACULTY_EMBEDDINGS_SUMMARY_NAME);
+    let cached = fs::read(&summary_path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<EmbeddingIndexStatus>(&data).ok())
+        .filter(|status| status.file_size == file_size && status.modified_at == modified_at);
+    if let Some(status) = cached {
+        return Ok(status);
+    }
 
-fn emit_embedding_helper_standby(app_handle: &tauri::AppHandle) {
-    emit_faculty_embedding_progress(
-        app_handle,
-        EmbeddingProgressUpdate {
-            phase: "standby".into(),
-            message: Some(FACULTY_EMBEDDING_STANDBY_MESSAGE.into()),
-            processed_rows: 0,
-            total_rows: 0,
-            elapsed_seconds: None,
-            estimated_remaining_seconds: None,
-        },
-    );
+    write_embedding_index_summary(app_handle)
 }
 
-fn perform_embedding_helper_warmup(app_handle: tauri::AppHandle) -> Result<(), String> {
-    if EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst) {
-        emit_embedding_helper_standby(&app_handle);
-        return Ok(());
+fn write_embedding_index_summary(
+    app_handle: &dyn MatchingHost,
+) -> Result<EmbeddingIndexStatus, String> {
+    if let Some(cache) = app_handle.embedding_index_cache() {
+        cache.invalidate();
     }
+    let directory = dataset_directory(app_handle)?;
+    let embeddings_path = directory.join(FACULTY_EMBEDDINGS_NAME);
+    let data = fs::read(&embeddings_path)
+        .map_err(|err| format!("Unable to read faculty embeddings: {err}"))?;
+    let header = serde_json::from_slice(&data)
+        .map_err(|err| format!("Unable to parse faculty embeddings: {err}"))?;
+    let modified_at = fs::metadata(&embeddings_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(format_system_time);
 
-    let lock = embedding_helper_warmup_lock()
-        .lock()
-        .map_err(|err| format!("Unable to warm up the embedding helper: {err}"))?;
+    let status = EmbeddingIndexStatus {
+        header,
+        file_size: data.len() as u64,
+        modified_at,
+        using_bundled_default: data == DEFAULT_FACULTY_EMBEDDINGS,
+    };
 
-    if EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst) {
-        drop(lock);
-        emit_embedding_helper_standby(&app_handle);
-        return Ok(());
-    }
+    let json = serde_json::to_string_pretty(&status)
+        .map_err(|err| format!("Unable to serialize the faculty embedding summary: {err}"))?;
+    fs::write(directory.join(FACULTY_EMBEDDINGS_SUMMARY_NAME), json)
+        .map_err(|err| format!("Unable to write the faculty embedding summary: {err}"))?;
+    Ok(status)
+}
+
+fn embed_prompt(
+    app_handle: &dyn MatchingHost,
+    index: &FacultyEmbeddingIndex,
+    prompt: &str,
+) -> Result<Vec<f32>, AppError> {
+    let model = if index.model.trim().is_empty() {
+        DEFAULT_EMBEDDING_MODEL.to_string()
+    } else {
+        index.model.clone()
+    };
 
     let payload = EmbeddingRequestPayload {
-        model: DEFAULT_EMBEDDING_MODEL.to_string(),
+        model,
         texts: vec![EmbeddingRequestRow {
             id: 0,
-            text: "Embedding helper warm-up text".into(),
+            text: prompt.to_string(),
         }],
-        item_label: Some("warm-up text".into()),
-        item_label_plural: Some("warm-up texts".into()),
+        item_label: Some("text query".into()),
+        item_label_plural: Some("text queries".into()),
     };
 
-    let result = run_embedding_helper(&app_handle, &payload);
-    drop(lock);
+    let response = run_embedding_helper(app_handle, &payload)?;
+    if response.rows.is_empty() {
+        return Err(AppError::helper_failure(
+            "The embedding helper did not return an embedding for the prompt.",
+        ));
+    }
 
-    result.and_then(|_| {
-        EMBEDDING_HELPER_READY.store(true, AtomicOrdering::SeqCst);
-        emit_embedding_helper_standby(&app_handle);
-        Ok(())
-    })
-}
+    let embedding = response.rows.into_iter().next().unwrap().embedding;
+    if embedding.len() != index.dimension {
+        return Err(AppError::StaleEmbeddings {
+            message: format!(
+                "The prompt embedding dimension ({}) does not match the faculty embedding dimension ({}).",
+                embedding.len(),
+                index.dimension
+            ),
+        });
+    }
 
-async fn warmup_embedding_helper(app_handle: tauri::AppHandle) -> Result<(), String> {
-    if EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst) {
-        emit_embedding_helper_standby(&app_handle);
-        return Ok(());
+    if response.dimension != index.dimension {
+        return Err(AppError::StaleEmbeddings {
+            message: format!(
+                "The embedding helper reported dimension {} but the faculty index uses {}.",
+                response.dimension, index.dimension
+            ),
+        });
     }
 
-    tauri::async_runtime::spawn_blocking(move || perform_embedding_helper_warmup(app_handle))
-        .await
-        .map_err(|err| format!("Unable to warm up the embedding helper: {err}"))?
+    Ok(embedding)
 }
 
-fn shutdown_embedding_helper() {
-    if let Some(lock) = EMBEDDING_HELPER.get() {
-        if let Ok(mut manager) = lock.lock() {
-            manager.shutdown();
+fn append_ad_hoc_roster_embeddings(
+    app_handle: &dyn MatchingHost,
+    index: &mut FacultyEmbeddingIndex,
+    faculty: &[AdHocRosterFaculty],
+) -> Result<Vec<usize>, AppError> {
+    let model = if index.model.trim().is_empty() {
+        DEFAULT_EMBEDDING_MODEL.to_string()
+    } else {
+        index.model.clone()
+    };
+
+    let payload = EmbeddingRequestPayload {
+        model,
+        texts: faculty
+            .iter()
+            .enumerate()
+            .map(|(id, entry)| EmbeddingRequestRow {
+                id,
+                text: entry.text.clone(),
+            })
+            .collect(),
+        item_label: Some("roster faculty description".into()),
+        item_label_plural: Some("roster faculty descriptions".into()),
+    };
+
+    let response = run_embedding_helper(app_handle, &payload)?;
+    if response.dimension != index.dimension {
+        return Err(AppError::StaleEmbeddings {
+            message: format!(
+                "The embedding helper reported dimension {} but the faculty index uses {}.",
+                response.dimension, index.dimension
+            ),
+        });
+    }
+
+    // Roster-only entries get run-local keys just past every dataset row; their source,
+    // not the key, is what marks them as having no dataset row.
+    let first_row = index
+        .entries
+        .iter()
+        .map(|entry| entry.row_index + 1)
+        .max()
+        .unwrap_or(0)
+        .max(index.total_rows.unwrap_or(0));
+    let mut added_rows = Vec::new();
+    for row in response.rows {
+        let Some(entry) = faculty.get(row.id) else {
+            continue;
+        };
+        if row.embedding.len() != index.dimension {
+            continue;
         }
+
+        let row_index = first_row + row.id;
+        let text_limit = index
+            .faculty_text_max_characters
+            .unwrap_or(FACULTY_TEXT_MAX_CHARACTERS_DEFAULT);
+        index.entries.push(FacultyEmbeddingEntry {
+            row_index,
+            faculty_id: String::new(),
+            identifiers: entry.identifiers.clone(),
+            embedding: row.embedding,
+            embedding_f16: None,
+            source: FacultySource::Roster,
+            faculty_text: Some(
+                truncate_prompt_text(&entry.text, text_limit).unwrap_or_else(|| entry.text.clone()),
+            ),
+        });
+        added_rows.push(row_index);
     }
-    EMBEDDING_HELPER_READY.store(false, AtomicOrdering::SeqCst);
+    assign_faculty_ids(&index.identifier_columns, &mut index.entries);
+
+    Ok(added_rows)
 }
 
-fn run_embedding_helper(
-    app_handle: &tauri::AppHandle,
-    payload: &EmbeddingRequestPayload,
-) -> Result<EmbeddingResponsePayload, String> {
-    let total_rows = payload.texts.len();
-    let input = serde_json::to_vec(payload)
-        .map_err(|err| format!("Unable to serialize the embedding request: {err}"))?;
+fn find_best_faculty_matches(
+    index: &FacultyEmbeddingIndex,
+    prompt_embedding: &[f32],
+    limit: usize,
+    allowed_rows: Option<&HashSet<usize>>,
+) -> Vec<FacultyMatchResult> {
+    if limit == 0 {
+        return Vec::new();
+    }
 
-    let manager_lock = embedding_helper_manager();
-    let mut manager = manager_lock
-        .lock()
-        .map_err(|err| format!("Unable to lock the embedding helper: {err}"))?;
+    // Entries are scored in parallel; the indexed collect keeps dataset order so the
+    // stable sort below breaks ties exactly as a serial pass would. Only the kept
+    // candidates are turned into results, so identifiers are cloned `limit` times.
+    let mut scores: Vec<(&FacultyEmbeddingEntry, f32)> = index
+        .entries
+        .par_iter()
+        .filter_map(|entry| {
+            if let Some(allowed) = allowed_rows {
+                if !allowed.contains(&entry.row_index) {
+                    return None;
+                }
+            }
 
-    manager.ensure_process(app_handle)?;
+            if entry.embedding.len() != prompt_embedding.len() {
+                return None;
+            }
 
-    if let Err(err) = manager.write_with_newline(&input) {
-        let message = handle_sidecar_write_error(
-            format!("Unable to send data to the embedding helper: {err}"),
-            &mut manager,
-            app_handle,
-            total_rows,
+            cosine_similarity(prompt_embedding, &entry.embedding)
+                .filter(|similarity| similarity.is_finite())
+                .map(|similarity| (entry, similarity))
+        })
+        .collect();
+
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores.truncate(limit);
+    scores
+        .into_iter()
+        .map(|(entry, similarity)| FacultyMatchResult {
+            row_index: entry.row_index,
+            faculty_id: entry.faculty_id.clone(),
+            similarity,
+            identifiers: retained_faculty_identifiers(entry),
+            faculty_text: None,
+            student_rank_for_faculty: None,
+            student_rank_total: None,
+            source: entry.source,
+        })
+        .collect()
+}
+
+/// Identifier key for a matched faculty row whose identifier cells are all blank.
+const FALLBACK_FACULTY_IDENTIFIER_KEY: &str = "Faculty";
+
+/// Labels a dataset row by its spreadsheet row number, counting the header row.
+/// Roster-only faculty have no dataset row to point at.
+fn fallback_faculty_label(source: FacultySource, row_index: usize) -> String {
+    match source {
+        FacultySource::Dataset => format!("Dataset row {}", row_index + 2),
+        FacultySource::Roster => "Roster-only faculty".into(),
+    }
+}
+
+/// Drops blank identifier values, falling back to the dataset row label when nothing is
+/// left so the entry stays traceable.
+fn retained_faculty_identifiers(entry: &FacultyEmbeddingEntry) -> HashMap<String, String> {
+    let mut identifiers = entry.identifiers.clone();
+    identifiers.retain(|_, value| !value.trim().is_empty());
+    if identifiers.is_empty() {
+        identifiers.insert(
+            FALLBACK_FACULTY_IDENTIFIER_KEY.to_string(),
+            fallback_faculty_label(entry.source, entry.row_index),
         );
-        emit_embedding_error(app_handle, total_rows, &message);
-        return Err(message);
     }
+    identifiers
+}
 
-    let output = match collect_sidecar_output(&mut manager, app_handle, total_rows, true) {
-        Ok(output) => output,
-        Err(err) => {
-            emit_embedding_error(app_handle, total_rows, &err);
-            return Err(err);
+/// Lines a match's identifiers up with the faculty columns. A row whose identifier cells
+/// are all blank carries its fallback label in the first column instead of a row of blanks.
+fn faculty_identifier_values(
+    faculty_headers: &[String],
+    faculty: &FacultyMatchResult,
+) -> Vec<String> {
+    let mut values: Vec<String> = faculty_headers
+        .iter()
+        .map(|header| faculty.identifiers.get(header).cloned().unwrap_or_default())
+        .collect();
+    if values.iter().all(|value| value.trim().is_empty()) {
+        if let (Some(first), Some(fallback)) = (
+            values.first_mut(),
+            faculty.identifiers.get(FALLBACK_FACULTY_IDENTIFIER_KEY),
+        ) {
+            first.clone_from(fallback);
         }
-    };
+    }
+    values
+}
 
-    if !sidecar_exited_successfully(&output) {
-        let error_message = trimmed_message_or_default(
-            &output.stderr,
-            "The embedding helper exited with an error.",
-        );
-        emit_embedding_error(app_handle, total_rows, &error_message);
-        return Err(error_message);
+fn count_faculty_in_scope(
+    index: &FacultyEmbeddingIndex,
+    allowed_rows: Option<&HashSet<usize>>,
+) -> usize {
+    index
+        .entries
+        .iter()
+        .filter(|entry| allowed_rows.is_none_or(|rows| rows.contains(&entry.row_index)))
+        .count()
+}
+
+fn build_run_statistics(
+    prompt_matches: &[PromptMatchResult],
+    faculty_in_scope: Option<usize>,
+) -> RunStatistics {
+    let mut best_similarities: Vec<f32> = prompt_matches
+        .iter()
+        .filter_map(|result| {
+            result
+                .faculty_matches
+                .iter()
+                .map(|faculty| faculty.similarity)
+                .filter(|value| value.is_finite())
+                .reduce(f32::max)
+        })
+        .collect();
+    best_similarities.sort_by(f32::total_cmp);
+
+    let best_similarity = (!best_similarities.is_empty()).then(|| {
+        let count = best_similarities.len();
+        let middle = count / 2;
+        let median = if count.is_multiple_of(2) {
+            (best_similarities[middle - 1] + best_similarities[middle]) / 2.0
+        } else {
+            best_similarities[middle]
+        };
+        let total: f64 = best_similarities
+            .iter()
+            .map(|value| f64::from(*value))
+            .sum();
+        SimilaritySummary {
+            mean: (total / count as f64) as f32,
+            median,
+            min: best_similarities[0],
+            max: best_similarities[count - 1],
+        }
+    });
+
+    let mut counts = [0usize; SIMILARITY_HISTOGRAM_BUCKETS];
+    for value in &best_similarities {
+        let bucket = ((value.clamp(0.0, 1.0) * SIMILARITY_HISTOGRAM_BUCKETS as f32) as usize)
+            .min(SIMILARITY_HISTOGRAM_BUCKETS - 1);
+        counts[bucket] += 1;
+    }
+    let bucket_width = 100 / SIMILARITY_HISTOGRAM_BUCKETS as u32;
+    let best_similarity_histogram = counts
+        .iter()
+        .enumerate()
+        .map(|(bucket, &count)| SimilarityBucket {
+            lower_percent: bucket as u32 * bucket_width,
+            upper_percent: (bucket as u32 + 1) * bucket_width,
+            count,
+        })
+        .collect();
+
+    let distinct_faculty = prompt_matches
+        .iter()
+        .flat_map(|result| &result.faculty_matches)
+        .map(faculty_identity_key)
+        .collect::<HashSet<_>>()
+        .len();
+
+    RunStatistics {
+        students_matched: best_similarities.len(),
+        best_similarity,
+        best_similarity_histogram,
+        distinct_faculty,
+        faculty_in_scope,
+        scope_coverage: faculty_in_scope
+            .filter(|count| *count > 0)
+            .map(|count| (distinct_faculty as f32 / count as f32).min(1.0)),
     }
+}
 
-    if output.stdout.is_empty() {
-        let error_message = trimmed_message_or_default(
-            &output.stderr,
-            if output.termination.is_some() {
-                "The embedding helper exited before returning a response."
-            } else {
-                "The embedding helper did not produce any output."
-            },
-        );
-        emit_embedding_error(app_handle, total_rows, &error_message);
-        return Err(error_message);
+/// Who a match points at: its normalized identifier values, or its row when every
+/// identifier is blank, so duplicate dataset rows for one person count once.
+fn faculty_identity_key(faculty: &FacultyMatchResult) -> String {
+    let mut columns: Vec<String> = faculty.identifiers.keys().cloned().collect();
+    columns.sort();
+    let key = embedding_import_key(&columns, &faculty.identifiers);
+    if key.is_empty() {
+        format!("{:?} row {}", faculty.source, faculty.row_index)
+    } else {
+        key
     }
+}
 
-    match serde_json::from_slice(&output.stdout) {
-        Ok(response) => Ok(response),
-        Err(err) => {
-            let stderr_message = String::from_utf8_lossy(&output.stderr);
-            let trimmed = stderr_message.trim();
-            let error_message = if trimmed.is_empty() {
-                format!("Unable to parse embedding helper output: {err}")
-            } else {
-                format!(
-                    "Unable to parse embedding helper output: {err}. Details: {}",
-                    trimmed
-                )
-            };
-            emit_embedding_error(app_handle, total_rows, &error_message);
-            Err(error_message)
+/// Flags scopes that cannot fill the requested recommendation count, and program or
+/// roster scopes naming more faculty than the embedding index holds, which usually
+/// means the embeddings predate the current dataset.
+fn faculty_scope_warnings(
+    index: &FacultyEmbeddingIndex,
+    allowed_rows: Option<&HashSet<usize>>,
+    faculty_in_scope: usize,
+    recommendations_per_student: u32,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(allowed) = allowed_rows {
+        let embedded = count_faculty_in_scope(index, Some(allowed));
+        if allowed.len() > embedded {
+            warnings.push(format!(
+                "The selected scope includes {} faculty but only {embedded} of them have embeddings. Refresh the faculty embeddings so the index matches the current dataset.",
+                allowed.len()
+            ));
         }
     }
+    let requested = recommendations_per_student as usize;
+    if faculty_in_scope > 0 && faculty_in_scope < requested {
+        warnings.push(format!(
+            "Only {faculty_in_scope} faculty {verb} in scope, so each student will receive at most {faculty_in_scope} of the requested {requested} recommendations.",
+            verb = if faculty_in_scope == 1 { "is" } else { "are" }
+        ));
+    }
+    warnings
 }
 
-fn handle_sidecar_write_error(
-    base_message: String,
-    manager: &mut EmbeddingHelperManager,
-    app_handle: &tauri::AppHandle,
-    total_rows: usize,
-) -> String {
-    let result = match collect_sidecar_output(manager, app_handle, total_rows, false) {
-        Ok(output) => summarize_sidecar_failure(&output, base_message),
-        Err(err) => format!("{base_message}\n\nAdditionally, {err}"),
-    };
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum FacultyExclusionReason {
+    DimensionMismatch,
+    InvalidScore,
+}
 
-    manager.reset();
-    result
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ExcludedFacultyEntry {
+    row_index: usize,
+    faculty_id: String,
+    identifiers: HashMap<String, String>,
+    reason: FacultyExclusionReason,
+    embedding_dimension: usize,
 }
 
-struct SidecarOutput {
-    stdout: Vec<u8>,
-    stderr: Vec<u8>,
-    termination: Option<TerminatedPayload>,
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FacultyExclusionDiagnostics {
+    expected_dimension: usize,
+    entries: Vec<ExcludedFacultyEntry>,
 }
 
-fn sidecar_exited_successfully(output: &SidecarOutput) -> bool {
-    output.termination.is_none()
+impl FacultyExclusionDiagnostics {
+    fn count(&self, reason: FacultyExclusionReason) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.reason == reason)
+            .count()
+    }
 }
 
-fn trim_trailing_newline(buffer: &mut Vec<u8>) {
-    while matches!(buffer.last(), Some(b'\n') | Some(b'\r')) {
-        buffer.pop();
+/// Lists the index entries that `find_best_faculty_matches` can never return: vectors
+/// whose length differs from the index dimension (which every prompt embedding is
+/// checked against) and vectors that are all zeros or contain non-finite values, for
+/// which no cosine similarity exists. Only rows the run could recommend are counted.
+fn find_excluded_faculty_entries(
+    index: &FacultyEmbeddingIndex,
+    allowed_rows: Option<&HashSet<usize>>,
+) -> FacultyExclusionDiagnostics {
+    let entries = index
+        .entries
+        .par_iter()
+        .filter(|entry| allowed_rows.is_none_or(|allowed| allowed.contains(&entry.row_index)))
+        .filter_map(|entry| {
+            let reason = if entry.embedding.len() != index.dimension {
+                FacultyExclusionReason::DimensionMismatch
+            } else {
+                let [_, norm, _] = dot_and_norms(&entry.embedding, &entry.embedding);
+                if norm.is_finite() && norm > 0.0 {
+                    return None;
+                }
+                FacultyExclusionReason::InvalidScore
+            };
+            let identifiers = retained_faculty_identifiers(entry);
+            Some(ExcludedFacultyEntry {
+                row_index: entry.row_index,
+                faculty_id: entry.faculty_id.clone(),
+                identifiers,
+                reason,
+                embedding_dimension: entry.embedding.len(),
+            })
+        })
+        .collect();
+
+    FacultyExclusionDiagnostics {
+        expected_dimension: index.dimension,
+        entries,
     }
 }
 
-fn collect_sidecar_output(
-    manager: &mut EmbeddingHelperManager,
-    app_handle: &tauri::AppHandle,
-    total_rows: usize,
-    expect_response: bool,
-) -> Result<SidecarOutput, String> {
-    let mut stdout = Vec::new();
-    let mut stderr = Vec::new();
+fn faculty_exclusion_warnings(diagnostics: &FacultyExclusionDiagnostics) -> Vec<String> {
+    let describe = |count: usize| {
+        if count == 1 {
+            "1 faculty entry was".to_string()
+        } else {
+            format!("{count} faculty entries were")
+        }
+    };
 
-    if !expect_response && !manager.stdout_buffer.is_empty() {
-        stdout.extend(manager.stdout_buffer.drain(..));
+    let mut warnings = Vec::new();
+    let mismatched = diagnostics.count(FacultyExclusionReason::DimensionMismatch);
+    if mismatched > 0 {
+        warnings.push(format!(
+            "{} excluded due to mismatched embedding dimensions; refresh embeddings.",
+            describe(mismatched)
+        ));
+    }
+    let invalid = diagnostics.count(FacultyExclusionReason::InvalidScore);
+    if invalid > 0 {
+        warnings.push(format!(
+            "{} excluded because their embeddings are empty or contain invalid values; refresh embeddings.",
+            describe(invalid)
+        ));
     }
+    warnings
+}
 
-    loop {
-        if expect_response {
-            if let Some(position) = manager.stdout_buffer.iter().position(|&byte| byte == b'\n') {
-                stdout.extend(manager.stdout_buffer.drain(..=position));
-                trim_trailing_newline(&mut stdout);
-                return Ok(SidecarOutput {
-                    stdout,
-                    stderr,
-                    termination: None,
-                });
-            }
+/// Scores each prompt embedding independently across the thread pool, returning the
+/// embedding alongside its matches in the original prompt order.
+fn score_prompt_embeddings(
+    index: &FacultyEmbeddingIndex,
+    embeddings: Vec<Option<Vec<f32>>>,
+    limit: usize,
+    allowed_rows: Option<&HashSet<usize>>,
+) -> Vec<Option<(Vec<f32>, Vec<FacultyMatchResult>)>> {
+    embeddings
+        .into_par_iter()
+        .map(|embedding| {
+            embedding.map(|embedding| {
+                let matches = find_best_faculty_matches(index, &embedding, limit, allowed_rows);
+                (embedding, matches)
+            })
+        })
+        .collect()
+}
+
+fn assign_student_rankings(match_sets: &mut [&mut Vec<FacultyMatchResult>]) {
+    if match_sets.is_empty() {
+        return;
+    }
+
+    let mut occurrences: HashMap<String, Vec<(usize, usize, f32)>> = HashMap::new();
+
+    for (prompt_index, matches) in match_sets.iter().enumerate() {
+        for (match_index, faculty) in matches.iter().enumerate() {
+            occurrences
+                .entry(faculty_identity_key(faculty))
+                .or_default()
+                .push((prompt_index, match_index, faculty.similarity));
         }
+    }
 
-        match manager.receive_event()? {
-            Some(CommandEvent::Stdout(bytes)) => {
-                if expect_response {
-                    manager.stdout_buffer.extend_from_slice(&bytes);
-                } else {
-                    stdout.extend_from_slice(&bytes);
-                }
+    if occurrences.is_empty() {
+        for matches in match_sets.iter_mut() {
+            for faculty in matches.iter_mut() {
+                faculty.student_rank_for_faculty = None;
+                faculty.student_rank_total = None;
             }
-            Some(CommandEvent::Stderr(bytes)) => {
-                if !emit_progress_from_line(app_handle, total_rows, &bytes) {
-                    stderr.extend_from_slice(&bytes);
-                }
+        }
+        return;
+    }
+
+    let mut rank_map: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+
+    for (_, mut entries) in occurrences {
+        entries.sort_by(|a, b| {
+            let primary = b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal);
+            if primary != Ordering::Equal {
+                return primary;
             }
-            Some(CommandEvent::Terminated(payload)) => {
-                if expect_response {
-                    stdout.extend(manager.stdout_buffer.drain(..));
-                    trim_trailing_newline(&mut stdout);
+
+            let secondary = a.0.cmp(&b.0);
+            if secondary != Ordering::Equal {
+                return secondary;
+            }
+
+            a.1.cmp(&b.1)
+        });
+
+        let total = entries.len();
+        for (position, (prompt_index, match_index, _)) in entries.into_iter().enumerate() {
+            rank_map.insert((prompt_index, match_index), (position + 1, total));
+        }
+    }
+
+    for (prompt_index, matches) in match_sets.iter_mut().enumerate() {
+        for (match_index, faculty) in matches.iter_mut().enumerate() {
+            if let Some((rank, total)) = rank_map.get(&(prompt_index, match_index)) {
+                faculty.student_rank_for_faculty = Some(*rank);
+                faculty.student_rank_total = Some(*total);
+            } else {
+                faculty.student_rank_for_faculty = None;
+                faculty.student_rank_total = None;
+            }
+        }
+    }
+}
+
+fn enrich_matches_with_faculty_text(
+    app_handle: &dyn MatchingHost,
+    index: &FacultyEmbeddingIndex,
+    matches: &mut [FacultyMatchResult],
+) -> Result<(), String> {
+    if matches.is_empty() {
+        return Ok(());
+    }
+
+    let row_indexes: HashSet<usize> = matches.iter().map(|entry| entry.row_index).collect();
+    let faculty_texts = resolve_faculty_texts(app_handle, index, &row_indexes)?;
+    for faculty_match in matches {
+        if let Some(text) = faculty_texts.get(&faculty_match.row_index) {
+            faculty_match.faculty_text = Some(text.clone());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads faculty text from the embedding index, only opening the dataset for rows
+/// embedded before the index stored their text.
+fn resolve_faculty_texts(
+    app_handle: &dyn MatchingHost,
+    index: &FacultyEmbeddingIndex,
+    row_indexes: &HashSet<usize>,
+) -> Result<HashMap<usize, String>, String> {
+    let mut faculty_texts = HashMap::new();
+    for entry in &index.entries {
+        if !row_indexes.contains(&entry.row_index) {
+            continue;
+        }
+        if let Some(text) = entry.faculty_text.as_ref() {
+            faculty_texts.insert(entry.row_index, text.clone());
+        }
+    }
+
+    let missing: HashSet<usize> = row_indexes
+        .iter()
+        .filter(|row_index| !faculty_texts.contains_key(row_index))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        faculty_texts.extend(load_faculty_texts(
+            app_handle,
+            &index.embedding_columns,
+            &missing,
+        )?);
+    }
+
+    Ok(faculty_texts)
+}
+
+fn load_faculty_texts(
+    app_handle: &dyn MatchingHost,
+    embedding_columns: &[String],
+    row_indexes: &HashSet<usize>,
+) -> Result<HashMap<usize, String>, String> {
+    let mut faculty_texts = HashMap::new();
+    if row_indexes.is_empty() || embedding_columns.is_empty() {
+        return Ok(faculty_texts);
+    }
+
+    let dataset_path = dataset_destination(app_handle)?;
+    if !dataset_path.exists() {
+        return Err("The faculty dataset could not be located.".into());
+    }
+
+    let (headers, rows) = read_full_spreadsheet(&dataset_path)?;
+    if rows.is_empty() {
+        return Err("The faculty dataset does not include any rows.".into());
+    }
+
+    let header_map = build_header_index_map(&headers);
+    let embedding_indexes = indexes_from_labels(&header_map, embedding_columns)?;
+    if embedding_indexes.is_empty() {
+        return Err(
+            "No embedding columns are available to retrieve faculty text. Re-run the dataset analysis.".into(),
+        );
+    }
+
+    for &row_index in row_indexes {
+        if let Some(row) = rows.get(row_index) {
+            let mut text_parts = Vec::new();
+            for &index in &embedding_indexes {
+                if let Some(value) = row.get(index) {
+                    let trimmed = value.trim();
+                    if !trimmed.is_empty() {
+                        text_parts.push(trimmed.to_string());
+                    }
                 }
-                manager.reset();
-                return Ok(SidecarOutput {
-                    stdout,
-                    stderr,
-                    termination: Some(payload),
-                });
             }
-            Some(CommandEvent::Error(err)) => {
-                manager.reset();
-                return Err(format!("Unable to read embedding helper output: {err}"));
+
+            if !text_parts.is_empty() {
+                faculty_texts.insert(row_index, text_parts.join("\n\n"));
             }
-            Some(_) => {}
-            None => {
-                manager.reset();
-                if expect_response {
-                    return Err(
-                        "The embedding helper output stream closed unexpectedly.".to_string()
-                    );
-                } else {
-                    trim_trailing_newline(&mut stdout);
-                    return Ok(SidecarOutput {
-                        stdout,
-                        stderr,
-                        termination: None,
-                    });
+        }
+    }
+
+    Ok(faculty_texts)
+}
+
+fn build_prompt_text_cell(text: &str, max_chars: usize) -> String {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if normalized.chars().count() <= max_chars {
+        return normalized;
+    }
+
+    let mut truncated: String = normalized
+        .chars()
+        .take(max_chars.saturating_sub(1))
+        .collect();
+    truncated.push('…');
+    truncated
+}
+
+fn load_match_faculty_texts<'a>(
+    app_handle: &dyn MatchingHost,
+    index: &FacultyEmbeddingIndex,
+    options: &MatchRunOptions,
+    match_sets: impl Iterator<Item = &'a Vec<FacultyMatchResult>>,
+    warnings: &mut Vec<String>,
+) -> HashMap<usize, String> {
+    if !options.include_faculty_text {
+        return HashMap::new();
+    }
+
+    let row_indexes: HashSet<usize> = match_sets
+        .flat_map(|matches| matches.iter().map(|entry| entry.row_index))
+        .collect();
+    match resolve_faculty_texts(app_handle, index, &row_indexes) {
+        Ok(faculty_texts) => faculty_texts,
+        Err(err) => {
+            warnings.push(format!(
+                "Unable to include faculty text in the match workbook: {err}"
+            ));
+            HashMap::new()
+        }
+    }
+}
+
+fn list_directory_files(
+    directory: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<PathBuf>, String> {
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+
+    let reader = fs::read_dir(directory).map_err(|err| {
+        format!(
+            "Unable to read the directory '{}': {err}",
+            directory.display()
+        )
+    })?;
+
+    for entry in reader {
+        match entry {
+            Ok(entry) => {
+                let path = entry.path();
+                match entry.file_type() {
+                    Ok(file_type) => {
+                        if file_type.is_file() {
+                            file_paths.push(path);
+                        }
+                    }
+                    Err(err) => warnings.push(format!(
+                        "Skipped '{}': unable to determine the file type ({err}).",
+                        path.to_string_lossy()
+                    )),
                 }
             }
+            Err(err) => warnings.push(format!(
+                "Unable to read an entry in '{}': {err}",
+                directory.display()
+            )),
         }
     }
+
+    file_paths.sort();
+    Ok(file_paths)
 }
 
-fn summarize_sidecar_failure(output: &SidecarOutput, base_message: String) -> String {
-    let mut message = base_message;
+fn process_directory_documents(
+    app_handle: &dyn MatchingHost,
+    directory: &Path,
+    index: &FacultyEmbeddingIndex,
+    options: &MatchRunOptions,
+) -> Result<DirectoryProcessingOutcome, AppError> {
+    let MatchRunOptions {
+        limit,
+        allowed_rows,
+        ..
+    } = *options;
 
-    let stderr_text = String::from_utf8_lossy(&output.stderr);
-    let stderr_trimmed = stderr_text.trim_end();
-    if !stderr_trimmed.is_empty() {
-        message = format!("{message}\n\nEmbedding helper stderr:\n{stderr_trimmed}");
+    #[derive(Debug)]
+    struct DirectoryDocumentContext {
+        result_index: usize,
+        prompt: String,
     }
 
-    let stdout_text = String::from_utf8_lossy(&output.stdout);
-    let stdout_trimmed = stdout_text.trim_end();
-    if !stdout_trimmed.is_empty() {
-        message = format!("{message}\n\nEmbedding helper stdout:\n{stdout_trimmed}");
+    #[derive(Debug)]
+    struct DirectoryDocumentResult {
+        identifier: String,
+        student_values: Vec<String>,
+        preview: String,
+        prompt_text: Option<String>,
+        prompt_label: Option<String>,
+        matches: Vec<FacultyMatchResult>,
+        status_message: Option<String>,
+        outcome: ItemOutcome,
     }
 
-    if let Some(termination) = &output.termination {
-        let status_summary = match (termination.code, termination.signal) {
-            (Some(code), Some(signal)) => format!("code {code}, signal {signal}"),
-            (Some(code), None) => format!("code {code}"),
-            (None, Some(signal)) => format!("signal {signal}"),
-            (None, None) => "no exit code recorded".to_string(),
+    emit_matching_phase(app_handle, options.request_id, "reading-input", None);
+    let mut warnings = WarningCollector::default();
+    let mut document_results: Vec<DirectoryDocumentResult> = Vec::new();
+    let mut contexts: Vec<DirectoryDocumentContext> = Vec::new();
+    let file_paths = warnings.append_with(|warnings| list_directory_files(directory, warnings))?;
+    // Full paths can reveal account and folder names, so they are left out of every output
+    // when personal information is being scrubbed.
+    let include_document_paths = !options.scrub_personal_information;
+
+    for path in file_paths {
+        let identifier = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        let mut student_values = vec![identifier.clone()];
+        if include_document_paths {
+            let absolute = std::path::absolute(&path).unwrap_or_else(|_| path.clone());
+            student_values.push(absolute.to_string_lossy().into_owned());
+        }
+
+        let mut result = DirectoryDocumentResult {
+            identifier: identifier.clone(),
+            student_values,
+            preview: String::new(),
+            prompt_text: None,
+            prompt_label: None,
+            matches: Vec::new(),
+            status_message: None,
+            outcome: ItemOutcome::Pending,
         };
-        message = format!("{message}\n\nEmbedding helper exit status: {status_summary}");
-    }
+        let mut prompt_text: Option<String> = None;
+
+        match extract_document_prompt(&path) {
+            Ok(DocumentExtractionResult {
+                text,
+                warnings: extraction_warnings,
+            }) => {
+                for warning in extraction_warnings {
+                    warnings.push(format!("{identifier}: {warning}"));
+                }
+
+                if text.trim().is_empty() {
+                    let message =
+                        format!("Skipped '{identifier}' because it did not contain readable text.");
+                    warnings.push(message.clone());
+                    result.status_message = Some(message);
+                    result.outcome = ItemOutcome::Unreadable;
+                } else {
+                    let text = if options.scrub_personal_information {
+                        let (scrubbed, redactions) = scrub_personal_information_text(&text, &[]);
+                        if let Some(summary) = redactions.describe() {
+                            warnings.push(format!("Redacted {summary} from '{identifier}'."));
+                        }
+                        scrubbed
+                    } else {
+                        text
+                    };
+                    result.preview = build_prompt_preview(&text, options.prompt_preview_characters);
+                    if result.preview.is_empty() {
+                        result.prompt_label = Some(result.identifier.clone());
+                    } else {
+                        result.prompt_label =
+                            Some(format!("{} — {}", result.identifier, result.preview));
+                    }
+                    if options.include_prompt_text || options.student_reports {
+                        result.prompt_text = Some(text.clone());
+                    }
+                    prompt_text = Some(text);
+                }
+            }
+            Err(err) => {
+                warnings.push(err.clone());
+                result.status_message = Some(err);
+                result.outcome = ItemOutcome::Unreadable;
+            }
+        }
+
+        let result_index = document_results.len();
+        if let Some(text) = prompt_text {
+            contexts.push(DirectoryDocumentContext {
+                result_index,
+                prompt: text,
+            });
+        }
+
+        document_results.push(result);
+    }
+
+    if document_results.is_empty() {
+        warnings.push("The selected directory did not contain any files to process.".into());
+    }
+
+    let mut prompt_matches = Vec::new();
+    let mut prompt_embeddings: Vec<(usize, Vec<f32>)> = Vec::new();
+
+    if !contexts.is_empty() {
+        let model_name = if index.model.trim().is_empty() {
+            DEFAULT_EMBEDDING_MODEL.to_string()
+        } else {
+            index.model.clone()
+        };
+
+        let payload = EmbeddingRequestPayload {
+            model: model_name,
+            texts: contexts
+                .iter()
+                .enumerate()
+                .map(|(id, context)| EmbeddingRequestRow {
+                    id,
+                    text: context.prompt.clone(),
+                })
+                .collect(),
+            item_label: Some("document".into()),
+            item_label_plural: Some("documents".into()),
+        };
+
+        let response =
+            run_embedding_helper_with_progress(app_handle, &payload, Some(options.request_id))?;
+        if response.dimension != index.dimension {
+            return Err(AppError::StaleEmbeddings {
+                message: format!(
+                    "The document embedding dimension ({}) does not match the faculty embedding dimension ({}).",
+                    response.dimension, index.dimension
+                ),
+            });
+        }
+        emit_matching_phase(app_handle, options.request_id, "scoring", None);
+
+        let mut embedding_map: HashMap<usize, Vec<f32>> = HashMap::new();
+        for row in response.rows {
+            embedding_map.insert(row.id, row.embedding);
+        }
+
+        let scored = score_prompt_embeddings(
+            index,
+            (0..contexts.len())
+                .map(|context_index| embedding_map.remove(&context_index))
+                .collect(),
+            limit,
+            allowed_rows,
+        );
+
+        for (context, scored) in contexts.iter().zip(scored) {
+            let identifier = document_results[context.result_index].identifier.clone();
+
+            match scored {
+                Some((embedding, matches)) => {
+                    let result = &mut document_results[context.result_index];
+                    if matches.is_empty() {
+                        result.status_message = Some("No faculty matches were returned.".into());
+                        result.outcome = ItemOutcome::NoMatches;
+                    } else {
+                        result.status_message = None;
+                        result.outcome = ItemOutcome::Matched;
+                    }
+
+                    document_results[context.result_index].matches = matches;
+                    if options.similar_prompt_threshold.is_some() {
+                        prompt_embeddings.push((context.result_index, embedding));
+                    }
+                }
+                None => {
+                    let message = "The embedding helper did not return a result for this document."
+                        .to_string();
+                    document_results[context.result_index].status_message = Some(message.clone());
+                    document_results[context.result_index].outcome = ItemOutcome::EmbeddingFailed;
+                    warnings.push(format!(
+                        "The embedding helper did not return an embedding for '{}'.",
+                        identifier
+                    ));
+                }
+            }
+        }
+    } else if !document_results.is_empty() {
+        warnings
+            .push("None of the files in the directory contained readable text to embed.".into());
+    }
+
+    let similar_prompts = match options.similar_prompt_threshold {
+        Some(threshold) => {
+            let pairs = find_similar_prompt_pairs(&prompt_embeddings, threshold);
+            warnings.append_with(|warnings| {
+                report_similar_prompt_pairs(
+                    &pairs,
+                    |index| document_results[index].identifier.clone(),
+                    warnings,
+                )
+            });
+            pairs
+                .iter()
+                .take(SIMILAR_PROMPT_PAIR_LIMIT)
+                .map(|&(first, second, similarity)| SimilarPromptPair {
+                    first_values: document_results[first].student_values.clone(),
+                    second_values: document_results[second].student_values.clone(),
+                    similarity,
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    {
+        let mut match_refs: Vec<&mut Vec<FacultyMatchResult>> = document_results
+            .iter_mut()
+            .map(|result| &mut result.matches)
+            .collect();
+        assign_student_rankings(&mut match_refs);
+    }
+
+    for result in &document_results {
+        if let Some(label) = &result.prompt_label {
+            prompt_matches.push(PromptMatchResult {
+                prompt: label.clone(),
+                faculty_matches: result.matches.clone(),
+            });
+        }
+    }
+
+    let breakdown =
+        ItemOutcomeBreakdown::from_outcomes(document_results.iter().map(|result| result.outcome));
+    let skipped_entries: Vec<SkippedEntry> = document_results
+        .iter()
+        .filter(|result| result.outcome.skipped())
+        .map(|result| SkippedEntry {
+            label: result.identifier.clone(),
+            identifier_values: result.student_values.clone(),
+            reason: result
+                .status_message
+                .clone()
+                .unwrap_or_else(|| "The document was not processed.".into()),
+        })
+        .collect();
+    let needs_attention = find_students_needing_attention(
+        document_results
+            .iter()
+            .filter(|result| !result.outcome.skipped())
+            .map(|result| {
+                (
+                    result.identifier.as_str(),
+                    result.student_values.as_slice(),
+                    result.matches.as_slice(),
+                )
+            }),
+        &index.identifier_columns,
+        options.attention_threshold,
+    );
+
+    let faculty_texts = warnings.append_with(|warnings| {
+        load_match_faculty_texts(
+            app_handle,
+            index,
+            options,
+            document_results.iter().map(|result| &result.matches),
+            warnings,
+        )
+    });
+
+    let mut student_headers = vec!["Document".to_string()];
+    let document_path_column = include_document_paths.then(|| {
+        student_headers.push(DOCUMENT_PATH_HEADER.to_string());
+        student_headers.len() - 1
+    });
+    let faculty_headers = index.identifier_columns.clone();
+    let headers = build_matches_headers(
+        &student_headers,
+        &faculty_headers,
+        options.include_raw_cosine,
+    );
+
+    let mut unmatched_preview_rows: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut match_entries: Vec<MatchEntry> = Vec::new();
+    let mut student_summary_rows: Vec<Vec<String>> = Vec::new();
+
+    for result in &document_results {
+        student_summary_rows.push(result.student_values.clone());
+
+        if result.matches.is_empty() {
+            let message = result
+                .status_message
+                .clone()
+                .unwrap_or_else(|| "No faculty matches were returned.".into());
+            let mut preview_row = Vec::new();
+            preview_row.push(String::new());
+            preview_row.push(String::new());
+            preview_row.extend(result.student_values.clone());
+            preview_row.extend(vec![String::new(); faculty_headers.len()]);
+            preview_row.push(message);
+            if options.include_raw_cosine {
+                preview_row.push(String::new());
+            }
+            preview_row.push(String::new());
+            preview_row.push(String::new());
+            unmatched_preview_rows.push((match_entries.len(), preview_row));
+            continue;
+        }
+
+        for (rank, faculty) in result.matches.iter().enumerate() {
+            let faculty_values = faculty_identifier_values(&faculty_headers, faculty);
+            let similarity = faculty.similarity;
+            let student_rank = faculty
+                .student_rank_for_faculty
+                .map(|value| (value, faculty.student_rank_total));
+
+            match_entries.push(MatchEntry {
+                student_values: result.student_values.clone(),
+                faculty_values,
+                faculty_row: faculty.row_index,
+                faculty_source: faculty.source,
+                faculty_id: faculty.faculty_id.clone(),
+                similarity: Some(similarity),
+                student_rank,
+                faculty_rank: Some(rank + 1),
+                prompt_text: result
+                    .prompt_text
+                    .as_deref()
+                    .filter(|_| options.include_prompt_text)
+                    .map(|text| build_prompt_text_cell(text, options.prompt_text_max_chars)),
+                faculty_text: faculty_texts.get(&faculty.row_index).cloned(),
+            });
+        }
+    }
+
+    sort_match_entries(&mut match_entries, options.matches_sort);
+    let (preview_rows, preview_total_rows) =
+        build_matches_preview_rows(&match_entries, unmatched_preview_rows, options);
+
+    let preview = SpreadsheetPreview {
+        headers: headers.clone(),
+        rows: preview_rows,
+        suggested_prompt_columns: Vec::new(),
+        suggested_identifier_columns: Vec::new(),
+        delimiter: None,
+        delimiter_consistency: None,
+    };
+
+    let statistics = build_run_statistics(&prompt_matches, options.faculty_in_scope);
+    emit_matching_phase(app_handle, options.request_id, "building-workbook", None);
+    let spreadsheet = warnings.append_with(|warnings| {
+        build_generated_matches(
+            &MatchExportData {
+                student_headers: &student_headers,
+                summary_student_columns: student_headers.len(),
+                student_summary_rows: &student_summary_rows,
+                faculty_headers: &faculty_headers,
+                match_entries: &match_entries,
+                skipped_entries: &skipped_entries,
+                needs_attention: &needs_attention,
+                similar_prompts: &similar_prompts,
+                statistics: &statistics,
+                document_path_column,
+            },
+            options,
+            default_directory_workbook_name(options.filename_stem),
+            warnings,
+        )
+    })?;
+
+    let student_reports = if options.student_reports {
+        let reports: Vec<StudentReport> = document_results
+            .iter()
+            .filter_map(|result| {
+                let prompt = result.prompt_text.as_deref()?;
+                let file_stem = Path::new(&result.identifier)
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or(&result.identifier)
+                    .to_string();
+                Some(StudentReport {
+                    title: result.identifier.clone(),
+                    file_stem,
+                    prompt,
+                    matches: &result.matches,
+                })
+            })
+            .collect();
+        Some(build_student_reports_archive(
+            &reports,
+            &faculty_headers,
+            options,
+        )?)
+    } else {
+        None
+    };
+
+    let results = DirectoryMatchResults {
+        processed_documents: breakdown.processed(),
+        matched_documents: breakdown.matched,
+        skipped_documents: breakdown.skipped(),
+        breakdown,
+        skipped_entries,
+        needs_attention,
+        total_rows: match_entries.len(),
+        preview_total_rows,
+        preview,
+        spreadsheet,
+        student_reports,
+    };
+
+    Ok(DirectoryProcessingOutcome {
+        warnings: warnings.into_vec(),
+        prompt_matches,
+        results,
+    })
+}
+
+struct PromptSpreadsheetRows {
+    rows: Vec<Vec<String>>,
+    /// 1-based line or worksheet row of each entry in `rows` within the source file.
+    row_numbers: Vec<usize>,
+    prompt_indexes: Vec<usize>,
+    identifier_indexes: Vec<usize>,
+}
+
+fn read_prompt_spreadsheet(
+    spreadsheet: &SpreadsheetSource,
+    prompt_columns: &[String],
+    identifier_columns: &[String],
+) -> Result<PromptSpreadsheetRows, String> {
+    let (headers, rows, row_numbers) = spreadsheet.read_rows()?;
+    let header_map = build_header_index_map(&headers);
+    let prompt_indexes = indexes_from_spreadsheet_labels(&header_map, prompt_columns)?;
+    let identifier_indexes = indexes_from_spreadsheet_labels(&header_map, identifier_columns)?;
+    Ok(PromptSpreadsheetRows {
+        rows,
+        row_numbers,
+        prompt_indexes,
+        identifier_indexes,
+    })
+}
+
+struct BoilerplateFilter {
+    manual: Option<String>,
+    prefix: String,
+    suffix: String,
+    paragraphs: Vec<String>,
+}
+
+impl BoilerplateFilter {
+    fn new(texts: &[String], detect: bool, manual: Option<&str>, row_percent: f32) -> Self {
+        let manual = manual.map(str::to_string);
+        let mut filter = Self {
+            manual,
+            prefix: String::new(),
+            suffix: String::new(),
+            paragraphs: Vec::new(),
+        };
+        if !detect || texts.len() < 2 {
+            return filter;
+        }
+
+        let texts: Vec<String> = texts
+            .iter()
+            .map(|text| filter.remove_manual(text))
+            .collect();
+        filter.prefix = common_boilerplate_affix(&texts, false);
+        filter.suffix = common_boilerplate_affix(&texts, true);
+
+        let minimum_rows = ((texts.len() as f32 * row_percent / 100.0).ceil() as usize).max(2);
+        let mut paragraph_rows: HashMap<String, usize> = HashMap::new();
+        let mut paragraph_order = Vec::new();
+        for text in &texts {
+            let mut seen = HashSet::new();
+            for paragraph in boilerplate_paragraphs(text) {
+                if paragraph.chars().count() >= BOILERPLATE_MIN_CHARS
+                    && seen.insert(paragraph.clone())
+                {
+                    let count = paragraph_rows.entry(paragraph.clone()).or_insert(0);
+                    if *count == 0 {
+                        paragraph_order.push(paragraph);
+                    }
+                    *count += 1;
+                }
+            }
+        }
+        filter.paragraphs = paragraph_order
+            .into_iter()
+            .filter(|paragraph| paragraph_rows[paragraph] >= minimum_rows)
+            .collect();
+        filter
+    }
+
+    fn remove_manual(&self, text: &str) -> String {
+        match self.manual.as_deref() {
+            Some(manual) => text.replace(manual, "").trim().to_string(),
+            None => text.trim().to_string(),
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut text = self.remove_manual(text);
+        if !self.prefix.is_empty() {
+            if let Some(rest) = text.strip_prefix(self.prefix.as_str()) {
+                text = rest.trim().to_string();
+            }
+        }
+        if !self.suffix.is_empty() {
+            if let Some(rest) = text.strip_suffix(self.suffix.as_str()) {
+                text = rest.trim().to_string();
+            }
+        }
+        if self.paragraphs.is_empty() {
+            return text;
+        }
+        let kept: Vec<&str> = text
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|paragraph| {
+                !paragraph.is_empty()
+                    && !self
+                        .paragraphs
+                        .contains(&paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+            })
+            .collect();
+        kept.join("\n\n")
+    }
+
+    fn describe(&self) -> Vec<String> {
+        self.manual
+            .iter()
+            .chain([&self.prefix, &self.suffix])
+            .chain(&self.paragraphs)
+            .map(|snippet| snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|snippet| !snippet.is_empty())
+            .map(|snippet| {
+                if snippet.chars().count() > BOILERPLATE_SNIPPET_CHARS {
+                    let truncated: String =
+                        snippet.chars().take(BOILERPLATE_SNIPPET_CHARS).collect();
+                    format!("{truncated}…")
+                } else {
+                    snippet
+                }
+            })
+            .collect()
+    }
+}
+
+fn boilerplate_paragraphs(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect()
+}
+
+fn common_boilerplate_affix(texts: &[String], suffix: bool) -> String {
+    let sequences: Vec<Vec<char>> = texts
+        .iter()
+        .map(|text| {
+            if suffix {
+                text.chars().rev().collect()
+            } else {
+                text.chars().collect()
+            }
+        })
+        .collect();
+    let Some((first, rest)) = sequences.split_first() else {
+        return String::new();
+    };
+    let mut length = first.len();
+    for sequence in rest {
+        length = length.min(
+            first
+                .iter()
+                .zip(sequence)
+                .take_while(|(a, b)| a == b)
+                .count(),
+        );
+    }
+    // Only cut at whitespace so a shared leading word fragment is never removed.
+    let shortest = sequences.iter().map(Vec::len).min().unwrap_or(0);
+    if length < shortest && !first[length].is_whitespace() {
+        while length > 0 && !first[length - 1].is_whitespace() {
+            length -= 1;
+        }
+    }
+    let affix: String = if suffix {
+        first[..length].iter().rev().collect()
+    } else {
+        first[..length].iter().collect()
+    };
+    if affix.trim().chars().count() >= BOILERPLATE_MIN_CHARS {
+        affix
+    } else {
+        String::new()
+    }
+}
+
+fn spreadsheet_prompt_parts(row: &[String], prompt_indexes: &[usize]) -> Vec<String> {
+    prompt_indexes
+        .iter()
+        .filter_map(|&index| row.get(index))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn process_prompt_spreadsheet(
+    app_handle: &dyn MatchingHost,
+    spreadsheet: &SpreadsheetSource,
+    index: &FacultyEmbeddingIndex,
+    prompt_columns: &[String],
+    identifier_columns: &[String],
+    options: &MatchRunOptions,
+) -> Result<SpreadsheetProcessingOutcome, AppError> {
+    let MatchRunOptions {
+        limit,
+        allowed_rows,
+        ..
+    } = *options;
+
+    #[derive(Debug)]
+    struct SpreadsheetRowContext {
+        result_index: usize,
+        prompt: String,
+    }
+
+    #[derive(Debug)]
+    struct SpreadsheetRowResult {
+        warning_label: String,
+        identifier_values: Vec<String>,
+        student_values: Vec<String>,
+        identifier_label: String,
+        prompt_preview: String,
+        prompt_text: Option<String>,
+        prompt_label: Option<String>,
+        matches: Vec<FacultyMatchResult>,
+        status_message: Option<String>,
+        outcome: ItemOutcome,
+    }
+
+    emit_matching_phase(app_handle, options.request_id, "reading-input", None);
+    let PromptSpreadsheetRows {
+        rows,
+        row_numbers,
+        prompt_indexes,
+        identifier_indexes,
+    } = read_prompt_spreadsheet(spreadsheet, prompt_columns, identifier_columns)?;
+    let include_row_number_column = identifier_indexes.is_empty();
+
+    let mut warnings = WarningCollector::default();
+    let mut contexts: Vec<SpreadsheetRowContext> = Vec::new();
+    let mut row_results: Vec<SpreadsheetRowResult> = Vec::new();
+
+    if rows.is_empty() {
+        warnings.push("The spreadsheet did not include any data rows to process.".into());
+    }
+
+    let boilerplate =
+        (options.strip_boilerplate || options.boilerplate_text.is_some()).then(|| {
+            let texts: Vec<String> = rows
+                .iter()
+                .map(|row| spreadsheet_prompt_parts(row, &prompt_indexes).join("\n\n"))
+                .filter(|text| !text.is_empty())
+                .collect();
+            BoilerplateFilter::new(
+                &texts,
+                options.strip_boilerplate,
+                options.boilerplate_text,
+                options.boilerplate_row_percent,
+            )
+        });
+    let mut boilerplate_rows = 0usize;
+    let mut truncated_rows = 0usize;
+
+    for (row, &row_number) in rows.iter().zip(&row_numbers) {
+        let mut identifier_values = Vec::new();
+        let mut label_segments = Vec::new();
+
+        if include_row_number_column {
+            identifier_values.push(row_number.to_string());
+            label_segments.push(format!("Row {}", row_number));
+        }
+
+        for &index in &identifier_indexes {
+            let value = row.get(index).cloned().unwrap_or_default();
+            if !value.trim().is_empty() {
+                label_segments.push(value.trim().to_string());
+            }
+            identifier_values.push(value);
+        }
+
+        let identifier_label = if label_segments.is_empty() {
+            format!("Row {}", row_number)
+        } else {
+            label_segments.join(" – ")
+        };
+
+        let warning_label = if label_segments.is_empty()
+            || (include_row_number_column && label_segments.len() == 1)
+        {
+            format!("row {}", row_number)
+        } else {
+            format!("row {} ({})", row_number, identifier_label.as_str())
+        };
+
+        let prompt_parts = spreadsheet_prompt_parts(row, &prompt_indexes);
+        let mut prompt_text = prompt_parts.join("\n\n");
+        if let Some(filter) = boilerplate.as_ref() {
+            let stripped = filter.apply(&prompt_text);
+            if stripped != prompt_text {
+                boilerplate_rows += 1;
+            }
+            prompt_text = stripped;
+        }
+
+        let mut student_values = identifier_values.clone();
+        if let Some(max_chars) = options.prompt_column_values_max_chars {
+            student_values.extend(prompt_indexes.iter().map(|&index| {
+                let value = row.get(index).map(|value| value.trim()).unwrap_or_default();
+                let value = if options.scrub_personal_information {
+                    scrub_personal_information_text(value, &identifier_values).0
+                } else {
+                    value.to_string()
+                };
+                truncate_prompt_text(&value, max_chars).unwrap_or(value)
+            }));
+        }
+
+        let mut result = SpreadsheetRowResult {
+            warning_label,
+            identifier_values,
+            student_values,
+            identifier_label,
+            prompt_preview: String::new(),
+            prompt_text: None,
+            prompt_label: None,
+            matches: Vec::new(),
+            status_message: None,
+            outcome: ItemOutcome::Pending,
+        };
+
+        if prompt_parts.is_empty() {
+            warnings.push(format!(
+                "Skipped {} because the selected prompt columns were empty.",
+                result.warning_label
+            ));
+            result.status_message =
+                Some("No prompt content was provided in the selected columns.".into());
+            result.outcome = ItemOutcome::Unreadable;
+        } else if boilerplate.is_some()
+            && prompt_text.chars().count() < BOILERPLATE_MIN_REMAINING_CHARS
+        {
+            warnings.push(format!(
+                "Skipped {} because little prompt content remained after removing boilerplate text.",
+                result.warning_label
+            ));
+            result.status_message =
+                Some("No prompt content was provided in the selected columns.".into());
+            result.outcome = ItemOutcome::Unreadable;
+        } else {
+            if options.scrub_personal_information {
+                let (scrubbed, redactions) =
+                    scrub_personal_information_text(&prompt_text, &result.identifier_values);
+                if let Some(summary) = redactions.describe() {
+                    warnings.push(format!("Redacted {summary} from {}.", result.warning_label));
+                }
+                prompt_text = scrubbed;
+            }
+            if let Some(truncated) =
+                truncate_prompt_text(&prompt_text, options.max_prompt_characters)
+            {
+                warnings.push(format!(
+                    "Truncated the prompt for {} from {} to {} characters to fit the embedding model.",
+                    result.warning_label,
+                    prompt_text.chars().count(),
+                    truncated.chars().count()
+                ));
+                truncated_rows += 1;
+                prompt_text = truncated;
+            }
+            result.prompt_preview =
+                build_prompt_preview(&prompt_text, options.prompt_preview_characters);
+            if options.include_prompt_text || options.student_reports {
+                result.prompt_text = Some(prompt_text.clone());
+            }
+            if result.prompt_preview.is_empty() {
+                result.prompt_label = Some(result.identifier_label.clone());
+            } else {
+                result.prompt_label = Some(format!(
+                    "{} — {}",
+                    result.identifier_label, result.prompt_preview
+                ));
+            }
+            let result_index = row_results.len();
+            contexts.push(SpreadsheetRowContext {
+                result_index,
+                prompt: prompt_text,
+            });
+        }
+
+        row_results.push(result);
+    }
+
+    if let Some(filter) = boilerplate.as_ref() {
+        if boilerplate_rows > 0 {
+            for snippet in filter.describe() {
+                warnings.push(format!(
+                    "Removed boilerplate text from {boilerplate_rows} row{plural}: \"{snippet}\"",
+                    plural = if boilerplate_rows == 1 { "" } else { "s" }
+                ));
+            }
+        } else if options.strip_boilerplate {
+            warnings.push(
+                "No shared boilerplate text was found in the selected prompt columns.".into(),
+            );
+        }
+    }
+
+    let mut prompt_matches = Vec::new();
+    let mut prompt_embeddings: Vec<(usize, Vec<f32>)> = Vec::new();
+
+    if !contexts.is_empty() {
+        let model_name = if index.model.trim().is_empty() {
+            DEFAULT_EMBEDDING_MODEL.to_string()
+        } else {
+            index.model.clone()
+        };
+
+        let payload = EmbeddingRequestPayload {
+            model: model_name,
+            texts: contexts
+                .iter()
+                .enumerate()
+                .map(|(id, context)| EmbeddingRequestRow {
+                    id,
+                    text: context.prompt.clone(),
+                })
+                .collect(),
+            item_label: Some("spreadsheet row".into()),
+            item_label_plural: Some("spreadsheet rows".into()),
+        };
+
+        let response =
+            run_embedding_helper_with_progress(app_handle, &payload, Some(options.request_id))?;
+        if response.dimension != index.dimension {
+            return Err(AppError::StaleEmbeddings {
+                message: format!(
+                    "The spreadsheet embedding dimension ({}) does not match the faculty embedding dimension ({}).",
+                    response.dimension, index.dimension
+                ),
+            });
+        }
+        emit_matching_phase(app_handle, options.request_id, "scoring", None);
+
+        let mut embedding_map: HashMap<usize, Vec<f32>> = HashMap::new();
+        for row in response.rows {
+            embedding_map.insert(row.id, row.embedding);
+        }
+
+        let scored = score_prompt_embeddings(
+            index,
+            (0..contexts.len())
+                .map(|context_index| embedding_map.remove(&context_index))
+                .collect(),
+            limit,
+            allowed_rows,
+        );
+
+        for (context, scored) in contexts.iter().zip(scored) {
+            let result = &mut row_results[context.result_index];
+
+            match scored {
+                Some((embedding, matches)) => {
+                    if matches.is_empty() {
+                        result.status_message = Some("No faculty matches were returned.".into());
+                        result.outcome = ItemOutcome::NoMatches;
+                    } else {
+                        result.status_message = None;
+                        result.outcome = ItemOutcome::Matched;
+                    }
+
+                    result.matches = matches;
+                    if options.similar_prompt_threshold.is_some() {
+                        prompt_embeddings.push((context.result_index, embedding));
+                    }
+                }
+                None => {
+                    let message =
+                        "The embedding helper did not return a result for this row.".to_string();
+                    warnings.push(format!(
+                        "The embedding helper did not return an embedding for {}.",
+                        result.warning_label
+                    ));
+                    result.status_message = Some(message.clone());
+                    result.outcome = ItemOutcome::EmbeddingFailed;
+                }
+            }
+        }
+    } else if !row_results.is_empty() {
+        warnings.push("None of the rows in the spreadsheet contained prompt text to embed.".into());
+    }
+
+    let similar_prompts = match options.similar_prompt_threshold {
+        Some(threshold) => {
+            let pairs = find_similar_prompt_pairs(&prompt_embeddings, threshold);
+            warnings.append_with(|warnings| {
+                report_similar_prompt_pairs(
+                    &pairs,
+                    |index| row_results[index].identifier_label.clone(),
+                    warnings,
+                )
+            });
+            pairs
+                .iter()
+                .take(SIMILAR_PROMPT_PAIR_LIMIT)
+                .map(|&(first, second, similarity)| SimilarPromptPair {
+                    first_values: row_results[first].student_values.clone(),
+                    second_values: row_results[second].student_values.clone(),
+                    similarity,
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    {
+        let mut match_refs: Vec<&mut Vec<FacultyMatchResult>> = row_results
+            .iter_mut()
+            .map(|result| &mut result.matches)
+            .collect();
+        assign_student_rankings(&mut match_refs);
+    }
+
+    for result in &row_results {
+        if let Some(label) = &result.prompt_label {
+            prompt_matches.push(PromptMatchResult {
+                prompt: label.clone(),
+                faculty_matches: result.matches.clone(),
+            });
+        }
+    }
+
+    let breakdown =
+        ItemOutcomeBreakdown::from_outcomes(row_results.iter().map(|result| result.outcome));
+    let skipped_entries: Vec<SkippedEntry> = row_results
+        .iter()
+        .filter(|result| result.outcome.skipped())
+        .map(|result| SkippedEntry {
+            label: result.identifier_label.clone(),
+            identifier_values: result.student_values.clone(),
+            reason: result
+                .status_message
+                .clone()
+                .unwrap_or_else(|| "The row was not processed.".into()),
+        })
+        .collect();
+    let needs_attention = find_students_needing_attention(
+        row_results
+            .iter()
+            .filter(|result| !result.outcome.skipped())
+            .map(|result| {
+                (
+                    result.identifier_label.as_str(),
+                    result.student_values.as_slice(),
+                    result.matches.as_slice(),
+                )
+            }),
+        &index.identifier_columns,
+        options.attention_threshold,
+    );
+
+    let faculty_texts = warnings.append_with(|warnings| {
+        load_match_faculty_texts(
+            app_handle,
+            index,
+            options,
+            row_results.iter().map(|result| &result.matches),
+            warnings,
+        )
+    });
+
+    let mut student_headers: Vec<String> = if include_row_number_column {
+        vec!["Row Number".into()]
+    } else {
+        identifier_columns
+            .iter()
+            .map(|label| label.trim().to_string())
+            .collect()
+    };
+    let summary_student_columns = student_headers.len();
+    if options.prompt_column_values_max_chars.is_some() {
+        student_headers.extend(prompt_columns.iter().map(|label| label.trim().to_string()));
+    }
+    let faculty_headers: Vec<String> = index.identifier_columns.clone();
+
+    let headers = build_matches_headers(
+        &student_headers,
+        &faculty_headers,
+        options.include_raw_cosine,
+    );
+
+    let mut match_entries: Vec<MatchEntry> = Vec::new();
+    let mut unmatched_preview_rows: Vec<(usize, Vec<String>)> = Vec::new();
+
+    for result in &row_results {
+        if result.matches.is_empty() {
+            let message = result
+                .status_message
+                .clone()
+                .unwrap_or_else(|| "No faculty matches were returned.".into());
+            let mut preview_row = Vec::new();
+            preview_row.push(String::new());
+            preview_row.push(String::new());
+            preview_row.extend(result.student_values.clone());
+            preview_row.extend(vec![String::new(); faculty_headers.len()]);
+            preview_row.push(message.clone());
+            if options.include_raw_cosine {
+                preview_row.push(String::new());
+            }
+            preview_row.push(String::new());
+            preview_row.push(String::new());
+            unmatched_preview_rows.push((match_entries.len(), preview_row));
+            continue;
+        }
+
+        for (rank, faculty) in result.matches.iter().enumerate() {
+            let faculty_values = faculty_identifier_values(&faculty_headers, faculty);
+            let similarity = faculty.similarity;
+            let student_rank = faculty
+                .student_rank_for_faculty
+                .map(|value| (value, faculty.student_rank_total));
+
+            match_entries.push(MatchEntry {
+                student_values: result.student_values.clone(),
+                faculty_values,
+                faculty_row: faculty.row_index,
+                faculty_source: faculty.source,
+                faculty_id: faculty.faculty_id.clone(),
+                similarity: Some(similarity),
+                student_rank,
+                faculty_rank: Some(rank + 1),
+                prompt_text: result
+                    .prompt_text
+                    .as_deref()
+                    .filter(|_| options.include_prompt_text)
+                    .map(|text| build_prompt_text_cell(text, options.prompt_text_max_chars)),
+                faculty_text: faculty_texts.get(&faculty.row_index).cloned(),
+            });
+        }
+    }
+
+    sort_match_entries(&mut match_entries, options.matches_sort);
+    let (preview_rows, preview_total_rows) =
+        build_matches_preview_rows(&match_entries, unmatched_preview_rows, options);
+
+    let preview = SpreadsheetPreview {
+        headers: headers.clone(),
+        rows: preview_rows,
+        suggested_prompt_columns: Vec::new(),
+        suggested_identifier_columns: Vec::new(),
+        delimiter: None,
+        delimiter_consistency: None,
+    };
+
+    let student_summary_rows: Vec<Vec<String>> = row_results
+        .iter()
+        .map(|result| result.student_values.clone())
+        .collect();
+    let statistics = build_run_statistics(&prompt_matches, options.faculty_in_scope);
+    emit_matching_phase(app_handle, options.request_id, "building-workbook", None);
+    let spreadsheet = warnings.append_with(|warnings| {
+        build_generated_matches(
+            &MatchExportData {
+                student_headers: &student_headers,
+                summary_student_columns,
+                student_summary_rows: &student_summary_rows,
+                faculty_headers: &faculty_headers,
+                match_entries: &match_entries,
+                skipped_entries: &skipped_entries,
+                needs_attention: &needs_attention,
+                similar_prompts: &similar_prompts,
+                statistics: &statistics,
+                document_path_column: None,
+            },
+            options,
+            default_matches_workbook_name(options.filename_stem),
+            warnings,
+        )
+    })?;
+
+    let student_reports = if options.student_reports {
+        let reports: Vec<StudentReport> = row_results
+            .iter()
+            .filter_map(|result| {
+                let prompt = result.prompt_text.as_deref()?;
+                let file_stem = result
+                    .identifier_values
+                    .iter()
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("_");
+                Some(StudentReport {
+                    title: result.identifier_label.clone(),
+                    file_stem: if file_stem.is_empty() {
+                        result.identifier_label.clone()
+                    } else {
+                        file_stem
+                    },
+                    prompt,
+                    matches: &result.matches,
+                })
+            })
+            .collect();
+        Some(build_student_reports_archive(
+            &reports,
+            &faculty_headers,
+            options,
+        )?)
+    } else {
+        None
+    };
+
+    let results = SpreadsheetMatchResults {
+        processed_rows: breakdown.processed(),
+        matched_rows: breakdown.matched,
+        skipped_rows: breakdown.skipped(),
+        breakdown,
+        skipped_entries,
+        needs_attention,
+        truncated_rows,
+        total_rows: match_entries.len(),
+        preview_total_rows,
+        preview,
+        spreadsheet,
+        student_reports,
+    };
+
+    Ok(SpreadsheetProcessingOutcome {
+        warnings: warnings.into_vec(),
+        prompt_matches,
+        results,
+    })
+}
+
+fn sort_match_entries(match_entries: &mut [MatchEntry], sort: MatchesSort) {
+    match sort {
+        MatchesSort::Student => {}
+        MatchesSort::Faculty => match_entries.sort_by(|a, b| {
+            a.faculty_values
+                .cmp(&b.faculty_values)
+                .then_with(|| a.student_values.cmp(&b.student_values))
+        }),
+        MatchesSort::Similarity => match_entries.sort_by(|a, b| {
+            let a_similarity = a.similarity.filter(|value| value.is_finite());
+            let b_similarity = b.similarity.filter(|value| value.is_finite());
+            b_similarity
+                .partial_cmp(&a_similarity)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.student_values.cmp(&b.student_values))
+                .then_with(|| a.faculty_values.cmp(&b.faculty_values))
+        }),
+    }
+}
+
+fn build_matches_preview_rows(
+    match_entries: &[MatchEntry],
+    unmatched_rows: Vec<(usize, Vec<String>)>,
+    options: &MatchRunOptions,
+) -> (Vec<Vec<String>>, usize) {
+    let total_rows = match_entries.len() + unmatched_rows.len();
+    let mut unmatched_rows = unmatched_rows.into_iter().peekable();
+    let mut preview_rows: Vec<Vec<String>> = Vec::new();
+
+    for (index, entry) in match_entries.iter().enumerate() {
+        if preview_rows.len() >= options.preview_row_limit {
+            break;
+        }
+        if options.matches_sort == MatchesSort::Student {
+            while let Some((_, row)) = unmatched_rows.next_if(|(position, _)| *position <= index) {
+                preview_rows.push(row);
+            }
+        }
+
+        let mut preview_row = vec![String::new(), String::new()];
+        preview_row.extend(entry.student_values.iter().cloned());
+        preview_row.extend(entry.faculty_values.iter().cloned());
+        preview_row.push(
+            entry
+                .similarity
+                .map(format_similarity_percent)
+                .unwrap_or_default(),
+        );
+        if options.include_raw_cosine {
+            preview_row.push(entry.similarity.map(format_raw_cosine).unwrap_or_default());
+        }
+        preview_row.push(
+            entry
+                .student_rank
+                .map(|(position, total)| match total {
+                    Some(limit) => format!("{position} of {limit}"),
+                    None => position.to_string(),
+                })
+                .unwrap_or_default(),
+        );
+        preview_row.push(
+            entry
+                .faculty_rank
+                .map(|rank| rank.to_string())
+                .unwrap_or_default(),
+        );
+        preview_rows.push(preview_row);
+    }
+    preview_rows.extend(unmatched_rows.map(|(_, row)| row));
+    preview_rows.truncate(options.preview_row_limit);
+
+    (preview_rows, total_rows)
+}
+
+fn build_matches_headers(
+    student_headers: &[String],
+    faculty_headers: &[String],
+    include_raw_cosine: bool,
+) -> Vec<String> {
+    let mut headers: Vec<String> = Vec::new();
+    headers.push("First reviewer".into());
+    headers.push("Reviewer".into());
+    headers.extend(student_headers.iter().cloned());
+    headers.extend(faculty_headers.iter().cloned());
+    headers.push("Similarity %".into());
+    if include_raw_cosine {
+        headers.push("Cosine (raw)".into());
+    }
+    headers.push("Student rank".into());
+    headers.push("Faculty rank".into());
+    headers
+}
+
+fn build_matches_export_headers(
+    student_headers: &[String],
+    faculty_headers: &[String],
+    options: &MatchRunOptions,
+) -> Vec<String> {
+    let mut headers =
+        build_matches_headers(student_headers, faculty_headers, options.include_raw_cosine);
+    if options.include_prompt_text {
+        headers.push("Prompt text".into());
+    }
+    if options.include_faculty_text {
+        headers.push("Faculty text".into());
+    }
+    if options.include_faculty_id {
+        headers.push("Faculty ID".into());
+    }
+    headers
+}
+
+/// Run warnings in first-seen order with exact repeats dropped as they arrive. Per-row
+/// warnings carry their row labels, so they stay distinct.
+#[derive(Debug, Default)]
+struct WarningCollector {
+    messages: Vec<String>,
+    seen: HashSet<String>,
+}
+
+impl WarningCollector {
+    fn push(&mut self, message: String) {
+        if self.seen.insert(message.clone()) {
+            self.messages.push(message);
+        }
+    }
+
+    /// Puts new messages ahead of everything collected so far.
+    fn prepend(&mut self, messages: impl IntoIterator<Item = String>) {
+        let mut leading = WarningCollector::default();
+        leading.extend(messages);
+        leading.extend(self.messages.drain(..));
+        *self = leading;
+    }
+
+    /// Lends the list to a helper that reports into a plain `Vec`, then drops any
+    /// repeats it appended.
+    fn append_with<T>(&mut self, report: impl FnOnce(&mut Vec<String>) -> T) -> T {
+        let collected = self.messages.len();
+        let result = report(&mut self.messages);
+        let appended = self.messages.split_off(collected);
+        self.extend(appended);
+        result
+    }
+
+    fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    fn iter(&self) -> std::slice::Iter<'_, String> {
+        self.messages.iter()
+    }
+
+    fn to_vec(&self) -> Vec<String> {
+        self.messages.clone()
+    }
+
+    fn into_vec(self) -> Vec<String> {
+        self.messages
+    }
+}
+
+impl Extend<String> for WarningCollector {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, messages: I) {
+        for message in messages {
+            self.push(message);
+        }
+    }
+}
+
+impl FromIterator<String> for WarningCollector {
+    fn from_iter<I: IntoIterator<Item = String>>(messages: I) -> Self {
+        let mut collector = Self::default();
+        collector.extend(messages);
+        collector
+    }
+}
+
+fn build_generated_matches(
+    data: &MatchExportData,
+    options: &MatchRunOptions,
+    default_name: String,
+    warnings: &mut Vec<String>,
+) -> Result<GeneratedSpreadsheet, String> {
+    let MatchExportData {
+        student_headers,
+        summary_student_columns,
+        student_summary_rows,
+        faculty_headers,
+        match_entries,
+        skipped_entries,
+        needs_attention,
+        similar_prompts,
+        ..
+    } = *data;
+    let (delimiter, extension, mime_type) = match options.output_format {
+        OutputFormat::Xlsx => {
+            let workbook_bytes = build_matches_workbook(data, options, warnings)?;
+            let mut spreadsheet = GeneratedSpreadsheet {
+                filename: default_name,
+                mime_type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                    .into(),
+                content: String::new(),
+                encoding: Some("base64".into()),
+                companion_files: Vec::new(),
+                saved_path: None,
+                saved_bytes: None,
+                write_duration_ms: None,
+            };
+            match options.output_path {
+                Some(destination) => write_generated_file(
+                    &mut spreadsheet,
+                    destination,
+                    &workbook_bytes,
+                    options.overwrite_output,
+                )?,
+                None => spreadsheet.content = Base64Engine.encode(workbook_bytes),
+            }
+            return Ok(spreadsheet);
+        }
+        OutputFormat::Csv => (b',', "csv", "text/csv"),
+        OutputFormat::Tsv => (b'\t', "tsv", "text/tab-separated-values"),
+    };
+
+    let stem = Path::new(&default_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("DBBS_matches")
+        .to_string();
+    let delimited_file = |suffix: &str, content: String| GeneratedSpreadsheet {
+        filename: format!("{stem}{suffix}.{extension}"),
+        mime_type: mime_type.into(),
+        content,
+        encoding: Some("utf8".into()),
+        companion_files: Vec::new(),
+        saved_path: None,
+        saved_bytes: None,
+        write_duration_ms: None,
+    };
+
+    let match_rows: Vec<Vec<String>> = match_entries
+        .iter()
+        .map(|entry| {
+            let mut row = vec![String::new(), String::new()];
+            row.extend(entry.student_values.iter().cloned());
+            row.extend(entry.faculty_values.iter().cloned());
+            row.push(
+                entry
+                    .similarity
+                    .filter(|value| value.is_finite())
+                    .map(|value| format!("{:.2}", value * 100.0))
+                    .unwrap_or_default(),
+            );
+            if options.include_raw_cosine {
+                row.push(
+                    entry
+                        .similarity
+                        .filter(|value| value.is_finite())
+                        .map(format_raw_cosine)
+                        .unwrap_or_default(),
+                );
+            }
+            row.push(
+                entry
+                    .student_rank
+                    .map(|(position, _)| position.to_string())
+                    .unwrap_or_default(),
+            );
+            row.push(
+                entry
+                    .faculty_rank
+                    .map(|rank| rank.to_string())
+                    .unwrap_or_default(),
+            );
+            if options.include_prompt_text {
+                row.push(entry.prompt_text.clone().unwrap_or_default());
+            }
+            if options.include_faculty_text {
+                row.push(entry.faculty_text.clone().unwrap_or_default());
+            }
+            if options.include_faculty_id {
+                row.push(entry.faculty_id.clone());
+            }
+            row
+        })
+        .collect();
+    let matches_content = write_delimited_table(
+        &build_matches_export_headers(student_headers, faculty_headers, options),
+        &match_rows,
+        delimiter,
+    )?;
+
+    let student_summary_headers =
+        build_student_summary_headers(&student_headers[..summary_student_columns], faculty_headers);
+    let student_stats = build_student_summary_stats(data, options.similarity_thresholds.1);
+    let student_rows: Vec<Vec<String>> = student_summary_rows
+        .iter()
+        .zip(&student_stats)
+        .map(|(identifiers, summary)| {
+            let mut row = identifiers[..summary_student_columns].to_vec();
+            row.push("0".into());
+            row.push("0".into());
+            row.extend(student_summary_stat_cells(summary));
+            row
+        })
+        .collect();
+
+    let faculty_summary_headers = build_faculty_summary_headers(faculty_headers);
+    let faculty_rows: Vec<Vec<String>> =
+        build_faculty_summary_rows(match_entries, options.faculty_programs)
+            .iter()
+            .map(|summary| {
+                let mut row = summary.faculty_values.clone();
+                row.push("0".into());
+                row.push("0".into());
+                row.extend(faculty_summary_stat_cells(summary));
+                row
+            })
+            .collect();
+
+    let mut spreadsheet = delimited_file("", matches_content);
+    spreadsheet.companion_files = vec![
+        delimited_file(
+            "_student_summary",
+            write_delimited_table(&student_summary_headers, &student_rows, delimiter)?,
+        ),
+        delimited_file(
+            "_faculty_summary",
+            write_delimited_table(&faculty_summary_headers, &faculty_rows, delimiter)?,
+        ),
+    ];
+    if !skipped_entries.is_empty() {
+        let (skipped_headers, skipped_rows) = build_skipped_rows(student_headers, skipped_entries);
+        spreadsheet.companion_files.push(delimited_file(
+            "_skipped",
+            write_delimited_table(&skipped_headers, &skipped_rows, delimiter)?,
+        ));
+    }
+    if !needs_attention.is_empty() {
+        let (attention_headers, attention_rows) =
+            build_attention_rows(student_headers, faculty_headers, needs_attention);
+        spreadsheet.companion_files.push(delimited_file(
+            "_needs_attention",
+            write_delimited_table(&attention_headers, &attention_rows, delimiter)?,
+        ));
+    }
+    if !similar_prompts.is_empty() {
+        let (similar_headers, similar_rows) =
+            build_similar_prompt_rows(student_headers, similar_prompts);
+        spreadsheet.companion_files.push(delimited_file(
+            "_similar_prompts",
+            write_delimited_table(&similar_headers, &similar_rows, delimiter)?,
+        ));
+    }
+
+    if let Some(destination) = options.output_path {
+        let output_stem = destination
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(&stem)
+            .to_string();
+        for companion in &mut spreadsheet.companion_files {
+            let suffix = companion
+                .filename
+                .strip_prefix(&stem)
+                .unwrap_or(&companion.filename)
+                .to_string();
+            let companion_path = destination.with_file_name(format!("{output_stem}{suffix}"));
+            let content = std::mem::take(&mut companion.content);
+            write_generated_file(
+                companion,
+                &companion_path,
+                content.as_bytes(),
+                options.overwrite_output,
+            )?;
+        }
+        let content = std::mem::take(&mut spreadsheet.content);
+        write_generated_file(
+            &mut spreadsheet,
+            destination,
+            content.as_bytes(),
+            options.overwrite_output,
+        )?;
+    }
+    Ok(spreadsheet)
+}
+
+fn write_generated_file(
+    file: &mut GeneratedSpreadsheet,
+    destination: &Path,
+    data: &[u8],
+    overwrite: bool,
+) -> Result<(), String> {
+    ensure_save_target_available(destination, overwrite)?;
+    let started = Instant::now();
+    write_file_atomically(destination, data).map_err(|err| {
+        format!(
+            "Unable to save the generated results to '{}': {err}",
+            destination.display()
+        )
+    })?;
+
+    if let Some(name) = destination.file_name().and_then(|name| name.to_str()) {
+        file.filename = name.to_string();
+    }
+    file.content = String::new();
+    file.saved_path = Some(destination.to_string_lossy().into_owned());
+    file.saved_bytes = Some(data.len() as u64);
+    file.write_duration_ms = Some(started.elapsed().as_millis() as u64);
+    Ok(())
+}
+
+fn write_file_atomically(destination: &Path, data: &[u8]) -> std::io::Result<()> {
+    write_file_atomically_with(destination, |writer| writer.write_all(data))
+}
+
+fn write_file_atomically_with(
+    destination: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let file_name = destination
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".into());
+    let temp_path = destination.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    let result = File::create(&temp_path)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, destination));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["bytes", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+fn build_export_bundle<W: Write + Seek>(
+    writer: W,
+    response: &SubmissionResponse,
+    run_info: &RunInfo,
+    pdf_report: Option<&GeneratedSpreadsheet>,
+) -> Result<W, String> {
+    let mut archive = zip::ZipWriter::new(writer);
+    let file_options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut artifacts: Vec<ExportBundleArtifact> = Vec::new();
+    let bundle_error = |name: &str, err: &dyn std::fmt::Display| {
+        format!("Unable to add '{name}' to the export bundle: {err}")
+    };
+
+    let results_files = response
+        .spreadsheet_results
+        .as_ref()
+        .map(|results| (&results.spreadsheet, results.student_reports.as_ref()))
+        .into_iter()
+        .chain(
+            response
+                .directory_results
+                .as_ref()
+                .map(|results| (&results.spreadsheet, results.student_reports.as_ref())),
+        );
+    for (spreadsheet, student_reports) in results_files {
+        let files = std::iter::once((spreadsheet, "Match results")).chain(
+            spreadsheet
+                .companion_files
+                .iter()
+                .map(|file| (file, "Match results companion table")),
+        );
+        for (file, description) in files {
+            add_generated_file_to_bundle(&mut archive, file, description, &mut artifacts)?;
+        }
+
+        if let Some(reports) = student_reports {
+            let data = read_generated_content(reports)?;
+            let mut reports_archive = zip::ZipArchive::new(Cursor::new(data))
+                .map_err(|err| bundle_error(&reports.filename, &err))?;
+            for index in 0..reports_archive.len() {
+                let report = reports_archive
+                    .by_index_raw(index)
+                    .map_err(|err| bundle_error(&reports.filename, &err))?;
+                let name = format!("student_reports/{}", report.name());
+                archive
+                    .raw_copy_file_rename(report, name.as_str())
+                    .map_err(|err| bundle_error(&name, &err))?;
+                artifacts.push(ExportBundleArtifact {
+                    name,
+                    description: "Student report".into(),
+                    mime_type:
+                        "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                            .into(),
+                });
+            }
+        }
+    }
+    if let Some(file) = pdf_report {
+        add_generated_file_to_bundle(&mut archive, file, "Run summary report", &mut artifacts)?;
+    }
+
+    let results_name = "results.json";
+    archive
+        .start_file(results_name, file_options)
+        .map_err(|err| bundle_error(results_name, &err))?;
+    serde_json::to_writer_pretty(
+        &mut archive,
+        &SubmissionJsonExport {
+            schema: RESULTS_JSON_SCHEMA,
+            schema_version: RESULTS_JSON_SCHEMA_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            run_info,
+            results: response,
+        },
+    )
+    .map_err(|err| bundle_error(results_name, &err))?;
+    artifacts.push(ExportBundleArtifact {
+        name: results_name.into(),
+        description: "Versioned JSON results".into(),
+        mime_type: "application/json".into(),
+    });
+
+    let warnings_name = "warnings.csv";
+    let warning_rows: Vec<Vec<String>> = response
+        .warnings
+        .iter()
+        .enumerate()
+        .map(|(index, warning)| vec![(index + 1).to_string(), warning.clone()])
+        .collect();
+    let warnings_content =
+        write_delimited_table(&["#".into(), "Warning".into()], &warning_rows, b',')?;
+    archive
+        .start_file(warnings_name, file_options)
+        .and_then(|_| {
+            archive
+                .write_all(warnings_content.as_bytes())
+                .map_err(Into::into)
+        })
+        .map_err(|err| bundle_error(warnings_name, &err))?;
+    artifacts.push(ExportBundleArtifact {
+        name: warnings_name.into(),
+        description: "Warnings produced during the run".into(),
+        mime_type: "text/csv".into(),
+    });
+
+    let skipped_entries: Vec<&SkippedEntry> = response
+        .spreadsheet_results
+        .iter()
+        .flat_map(|results| results.skipped_entries.iter())
+        .chain(
+            response
+                .directory_results
+                .iter()
+                .flat_map(|results| results.skipped_entries.iter()),
+        )
+        .collect();
+    if !skipped_entries.is_empty() {
+        let skipped_name = "skipped.csv";
+        let skipped_rows: Vec<Vec<String>> = skipped_entries
+            .iter()
+            .map(|entry| vec![entry.label.clone(), entry.reason.clone()])
+            .collect();
+        let skipped_content =
+            write_delimited_table(&["Student".into(), "Reason".into()], &skipped_rows, b',')?;
+        archive
+            .start_file(skipped_name, file_options)
+            .and_then(|_| {
+                archive
+                    .write_all(skipped_content.as_bytes())
+                    .map_err(Into::into)
+            })
+            .map_err(|err| bundle_error(skipped_name, &err))?;
+        artifacts.push(ExportBundleArtifact {
+            name: skipped_name.into(),
+            description: "Students skipped during the run and why".into(),
+            mime_type: "text/csv".into(),
+        });
+    }
+
+    let manifest_name = "manifest.json";
+    archive
+        .start_file(manifest_name, file_options)
+        .map_err(|err| bundle_error(manifest_name, &err))?;
+    serde_json::to_writer_pretty(
+        &mut archive,
+        &ExportBundleManifest {
+            schema: EXPORT_BUNDLE_SCHEMA,
+            schema_version: EXPORT_BUNDLE_SCHEMA_VERSION,
+            created_at: Utc::now().to_rfc3339(),
+            run_info,
+            artifacts,
+        },
+    )
+    .map_err(|err| bundle_error(manifest_name, &err))?;
+
+    archive
+        .finish()
+        .map_err(|err| format!("Unable to finalize the export bundle: {err}"))
+}
+
+fn add_generated_file_to_bundle<W: Write + Seek>(
+    archive: &mut zip::ZipWriter<W>,
+    file: &GeneratedSpreadsheet,
+    description: &str,
+    artifacts: &mut Vec<ExportBundleArtifact>,
+) -> Result<(), String> {
+    let data = read_generated_content(file)?;
+    archive
+        .start_file(
+            file.filename.as_str(),
+            zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated),
+        )
+        .and_then(|_| archive.write_all(&data).map_err(Into::into))
+        .map_err(|err| {
+            format!(
+                "Unable to add '{}' to the export bundle: {err}",
+                file.filename
+            )
+        })?;
+    artifacts.push(ExportBundleArtifact {
+        name: file.filename.clone(),
+        description: description.into(),
+        mime_type: file.mime_type.clone(),
+    });
+    Ok(())
+}
+
+fn read_generated_content(file: &GeneratedSpreadsheet) -> Result<Vec<u8>, String> {
+    if let Some(path) = &file.saved_path {
+        return fs::read(path).map_err(|err| format!("Unable to read '{path}': {err}"));
+    }
+    match file.encoding.as_deref() {
+        Some("base64") => Base64Engine
+            .decode(file.content.as_bytes())
+            .map_err(|err| format!("Unable to decode '{}': {err}", file.filename)),
+        _ => Ok(file.content.clone().into_bytes()),
+    }
+}
+
+fn build_pdf_report(
+    response: &SubmissionResponse,
+    run_info: &RunInfo,
+    identifier_columns: &[String],
+    faculty_programs: Option<&HashMap<usize, Vec<String>>>,
+) -> Vec<u8> {
+    let mut report = PdfReportWriter::new();
+    report.line("DBBS Faculty Match run report", 18.0, true);
+    report.space(6.0);
+    report.paragraph(&response.summary, 10.0);
+    report.space(12.0);
+
+    if let (Some(label), [result]) = (
+        response.details.student_label.as_deref(),
+        response.prompt_matches.as_slice(),
+    ) {
+        report.line("Student summary", 13.0, true);
+        let count = result.faculty_matches.len();
+        let top_match = result.faculty_matches.first().map(|faculty| {
+            let name = faculty_identifier_values(identifier_columns, faculty)
+                .iter()
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+                .join(" / ");
+            format!(
+                "; top match {name} ({})",
+                format_similarity_percent(faculty.similarity)
+            )
+        });
+        report.paragraph(
+            &format!(
+                "{label}: {count} recommendation{}{}",
+                if count == 1 { "" } else { "s" },
+                top_match.unwrap_or_default()
+            ),
+            9.0,
+        );
+        report.space(12.0);
+    }
+
+    report.line("Run parameters", 13.0, true);
+    for (label, value) in build_run_info_rows(run_info) {
+        if !value.is_empty() {
+            report.paragraph(&format!("{label}: {value}"), 9.0);
+        }
+    }
+    report.space(12.0);
+
+    let statistics = &response.statistics;
+    report.line("Best-match similarity distribution", 13.0, true);
+    if statistics.students_matched == 0 {
+        report.paragraph("No students received faculty matches.", 9.0);
+    } else {
+        let largest = statistics
+            .best_similarity_histogram
+            .iter()
+            .map(|bucket| bucket.count)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        for bucket in &statistics.best_similarity_histogram {
+            let label = format!("{}-{}%", bucket.lower_percent, bucket.upper_percent);
+            report.bar(&label, bucket.count, largest);
+        }
+        report.paragraph(
+            &format!(
+                "{} student{} with at least one match.",
+                statistics.students_matched,
+                if statistics.students_matched == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            ),
+            9.0,
+        );
+    }
+    report.space(12.0);
+
+    let mut faculty_counts: HashMap<usize, (String, usize, usize)> = HashMap::new();
+    let mut program_counts: HashMap<String, usize> = HashMap::new();
+    for result in &response.prompt_matches {
+        for (rank, faculty) in result.faculty_matches.iter().enumerate() {
+            let entry = faculty_counts.entry(faculty.row_index).or_insert_with(|| {
+                let label = faculty_identifier_values(identifier_columns, faculty)
+                    .iter()
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                (label, 0, 0)
+            });
+            entry.1 += 1;
+            if rank == 0 {
+                entry.2 += 1;
+            }
+            if let Some(programs) = faculty_programs
+                .filter(|_| faculty.source.is_dataset())
+                .and_then(|programs| programs.get(&faculty.row_index))
+            {
+                for program in programs {
+                    *program_counts.entry(program.clone()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut top_faculty: Vec<(String, usize, usize)> = faculty_counts.into_values().collect();
+    top_faculty.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| b.2.cmp(&a.2))
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    report.line(
+        &format!("Top {PDF_REPORT_TOP_FACULTY} faculty by recommendation count"),
+        13.0,
+        true,
+    );
+    if top_faculty.is_empty() {
+        report.paragraph("No faculty were recommended.", 9.0);
+    }
+    for (label, total, first) in top_faculty.iter().take(PDF_REPORT_TOP_FACULTY) {
+        report.paragraph(
+            &format!(
+                "{label}: {total} recommendation{} ({first} as top match)",
+                if *total == 1 { "" } else { "s" }
+            ),
+            9.0,
+        );
+    }
+    report.space(12.0);
+
+    if !program_counts.is_empty() {
+        let mut programs: Vec<(String, usize)> = program_counts.into_iter().collect();
+        programs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report.line("Recommendations per program", 13.0, true);
+        for (program, count) in programs {
+            report.paragraph(&format!("{program}: {count}"), 9.0);
+        }
+        report.space(12.0);
+    }
+
+    let warning_count = response.warnings.len();
+    report.line(
+        &format!(
+            "{warning_count} warning{}",
+            if warning_count == 1 { "" } else { "s" }
+        ),
+        13.0,
+        true,
+    );
+    for warning in response.warnings.iter().take(PDF_REPORT_WARNING_LIMIT) {
+        report.paragraph(&format!("- {warning}"), 9.0);
+    }
+    if warning_count > PDF_REPORT_WARNING_LIMIT {
+        report.paragraph(
+            &format!(
+                "{} more warning{} are listed in the application and workbook.",
+                warning_count - PDF_REPORT_WARNING_LIMIT,
+                if warning_count - PDF_REPORT_WARNING_LIMIT == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            ),
+            9.0,
+        );
+    }
+
+    report.finish()
+}
+
+/// Minimal single-purpose PDF writer using the standard Helvetica fonts, which
+/// every PDF reader provides, so the output is deterministic and needs no font files.
+struct PdfReportWriter {
+    pages: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    cursor: f32,
+}
+
+impl PdfReportWriter {
+    fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            current: Vec::new(),
+            cursor: PDF_PAGE_HEIGHT - PDF_PAGE_MARGIN,
+        }
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.cursor - height < PDF_PAGE_MARGIN {
+            self.pages.push(std::mem::take(&mut self.current));
+            self.cursor = PDF_PAGE_HEIGHT - PDF_PAGE_MARGIN;
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        self.cursor -= height;
+    }
+
+    fn text_at(&mut self, x: f32, size: f32, bold: bool, text: &str) {
+        let font = if bold { "F2" } else { "F1" };
+        self.current.extend_from_slice(
+            format!("BT /{font} {size} Tf {x:.1} {:.1} Td (", self.cursor).as_bytes(),
+        );
+        self.current.extend(encode_pdf_text(text));
+        self.current.extend_from_slice(b") Tj ET\n");
+    }
+
+    fn line(&mut self, text: &str, size: f32, bold: bool) {
+        let height = size * 1.4;
+        self.ensure_space(height);
+        self.cursor -= size;
+        self.text_at(PDF_PAGE_MARGIN, size, bold, text);
+        self.cursor -= height - size;
+    }
+
+    fn paragraph(&mut self, text: &str, size: f32) {
+        let width = PDF_PAGE_WIDTH - 2.0 * PDF_PAGE_MARGIN;
+        let max_chars = ((width / (size * 0.5)) as usize).max(10);
+        let mut current = String::new();
+        for word in text.split_whitespace() {
+            if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max_chars
+            {
+                self.line(&current, size, false);
+                current.clear();
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        if !current.is_empty() {
+            self.line(&current, size, false);
+        }
+    }
+
+    fn bar(&mut self, label: &str, count: usize, largest: usize) {
+        let size = 9.0;
+        let height = 14.0;
+        let label_width = 60.0;
+        let max_width = PDF_PAGE_WIDTH - 2.0 * PDF_PAGE_MARGIN - label_width - 40.0;
+        self.ensure_space(height);
+        self.cursor -= height;
+        let baseline = self.cursor + 3.0;
+        let saved = self.cursor;
+        self.cursor = baseline;
+        self.text_at(PDF_PAGE_MARGIN, size, false, label);
+        let bar_width = max_width * count as f32 / largest as f32;
+        if bar_width > 0.0 {
+            self.current.extend_from_slice(
+                format!(
+                    "0.3 0.45 0.7 rg {:.1} {:.1} {bar_width:.1} 10 re f 0 g\n",
+                    PDF_PAGE_MARGIN + label_width,
+                    baseline - 1.0
+                )
+                .as_bytes(),
+            );
+        }
+        self.text_at(
+            PDF_PAGE_MARGIN + label_width + bar_width + 4.0,
+            size,
+            false,
+            &count.to_string(),
+        );
+        self.cursor = saved;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if !self.current.is_empty() || self.pages.is_empty() {
+            self.pages.push(std::mem::take(&mut self.current));
+        }
+
+        let page_count = self.pages.len();
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let kids = (0..page_count)
+            .map(|index| format!("{} 0 R", 5 + index * 2))
+            .collect::<Vec<_>>()
+            .join(" ");
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(format!("<< /Type /Pages /Kids [{kids}] /Count {page_count} >>").into_bytes());
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+        for (index, content) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PDF_PAGE_WIDTH} {PDF_PAGE_HEIGHT}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    6 + index * 2
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend_from_slice(content);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut output = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(output.len());
+            output.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            output.extend_from_slice(object);
+            output.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = output.len();
+        output.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            output.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        output.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+                objects.len() + 1
+            )
+            .as_bytes(),
+        );
+        output
+    }
+}
+
+fn encode_pdf_text(text: &str) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        let byte = match ch {
+            '(' | ')' | '\\' => {
+                encoded.push(b'\\');
+                ch as u8
+            }
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201C}' => 0x93,
+            '\u{201D}' => 0x94,
+            '\u{2022}' => 0x95,
+            '\u{2026}' => 0x85,
+            ' '..='~' => ch as u8,
+            '\u{A0}'..='\u{FF}' => ch as u32 as u8,
+            _ if ch.is_whitespace() => b' ',
+            _ => b'?',
+        };
+        encoded.push(byte);
+    }
+    encoded
+}
+
+struct StudentReport<'a> {
+    title: String,
+    file_stem: String,
+    prompt: &'a str,
+    matches: &'a [FacultyMatchResult],
+}
+
+fn build_student_reports_archive(
+    reports: &[StudentReport],
+    faculty_headers: &[String],
+    options: &MatchRunOptions,
+) -> Result<GeneratedSpreadsheet, String> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let file_options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut used_names: HashSet<String> = HashSet::new();
+
+    for report in reports {
+        let document = build_student_report_document(report, faculty_headers, options)?;
+        let filename = unique_report_filename(&report.file_stem, &mut used_names);
+        archive
+            .start_file(filename.as_str(), file_options)
+            .map_err(|err| format!("Unable to add '{filename}' to the report archive: {err}"))?;
+        archive
+            .write_all(&document)
+            .map_err(|err| format!("Unable to add '{filename}' to the report archive: {err}"))?;
+    }
+
+    let bytes = archive
+        .finish()
+        .map_err(|err| format!("Unable to finalize the student report archive: {err}"))?
+        .into_inner();
+
+    let mut generated = GeneratedSpreadsheet {
+        filename: default_student_reports_name(),
+        mime_type: "application/zip".into(),
+        content: String::new(),
+        encoding: Some("base64".into()),
+        companion_files: Vec::new(),
+        saved_path: None,
+        saved_bytes: None,
+        write_duration_ms: None,
+    };
+    match options.output_path {
+        Some(destination) => {
+            let archive_path = derived_output_path(destination, OUTPUT_STUDENT_REPORTS_SUFFIX);
+            write_generated_file(
+                &mut generated,
+                &archive_path,
+                &bytes,
+                options.overwrite_output,
+            )?;
+        }
+        None => generated.content = Base64Engine.encode(bytes),
+    }
+    Ok(generated)
+}
+
+fn build_student_report_document(
+    report: &StudentReport,
+    faculty_headers: &[String],
+    options: &MatchRunOptions,
+) -> Result<Vec<u8>, String> {
+    let heading = Paragraph::new().add_run(Run::new().add_text(&report.title).bold().size(32));
+    let section =
+        |label: &str| Paragraph::new().add_run(Run::new().add_text(label).bold().size(24));
+
+    let prompt = report.prompt.trim();
+    let prompt_length = prompt.chars().count();
+    let mut prompt_paragraph = Paragraph::new().add_run(
+        Run::new().add_text(
+            prompt
+                .chars()
+                .take(STUDENT_REPORT_PROMPT_MAX_CHARS)
+                .collect::<String>(),
+        ),
+    );
+    if prompt_length > STUDENT_REPORT_PROMPT_MAX_CHARS {
+        prompt_paragraph = prompt_paragraph.add_run(
+            Run::new()
+                .add_text(format!(
+                    "… (excerpt; {} more characters omitted)",
+                    prompt_length - STUDENT_REPORT_PROMPT_MAX_CHARS
+                ))
+                .italic(),
+        );
+    }
+
+    let cell = |text: &str, bold: bool| {
+        let run = Run::new().add_text(text);
+        TableCell::new().add_paragraph(Paragraph::new().add_run(if bold {
+            run.bold()
+        } else {
+            run
+        }))
+    };
+    let mut header_cells = vec![cell("Rank", true)];
+    header_cells.extend(faculty_headers.iter().map(|header| cell(header, true)));
+    header_cells.push(cell("Similarity", true));
+    header_cells.push(cell("Programs", true));
+    let mut rows = vec![TableRow::new(header_cells)];
+    for (rank, faculty) in report.matches.iter().enumerate() {
+        let mut cells = vec![cell(&(rank + 1).to_string(), false)];
+        cells.extend(faculty_headers.iter().map(|header| {
+            cell(
+                faculty
+                    .identifiers
+                    .get(header)
+                    .map(String::as_str)
+                    .unwrap_or_default(),
+                false,
+            )
+        }));
+        cells.push(cell(&format_similarity_percent(faculty.similarity), false));
+        let programs = options
+            .faculty_programs
+            .filter(|_| faculty.source.is_dataset())
+            .and_then(|programs| programs.get(&faculty.row_index))
+            .map(|programs| programs.join("; "))
+            .unwrap_or_default();
+        cells.push(cell(&programs, false));
+        rows.push(TableRow::new(cells));
+    }
+
+    let mut document = Docx::new()
+        .add_paragraph(heading)
+        .add_paragraph(section("Research interests"))
+        .add_paragraph(prompt_paragraph)
+        .add_paragraph(section("Recommended faculty"));
+    document = if report.matches.is_empty() {
+        document.add_paragraph(
+            Paragraph::new().add_run(Run::new().add_text("No faculty matches were returned.")),
+        )
+    } else {
+        document.add_table(Table::new(rows))
+    };
+
+    let mut buffer = Cursor::new(Vec::new());
+    document
+        .build()
+        .pack(&mut buffer)
+        .map_err(|err| format!("Unable to build the report for '{}': {err}", report.title))?;
+    Ok(buffer.into_inner())
+}
+
+fn unique_report_filename(base: &str, used_names: &mut HashSet<String>) -> String {
+    let sanitized = base
+        .chars()
+        .map(|ch| match ch {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            ch if ch.is_control() => '_',
+            _ => ch,
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let sanitized = sanitized.trim_matches(|ch: char| ch == '.' || ch == ' ');
+    let base = if sanitized.is_empty() {
+        "Student"
+    } else {
+        sanitized
+    };
+    let base: String = base
+        .chars()
+        .take(STUDENT_REPORT_FILENAME_MAX_CHARS)
+        .collect();
+
+    let mut candidate = format!("{base}.docx");
+    let mut suffix = 2usize;
+    while !used_names.insert(candidate.to_lowercase()) {
+        candidate = format!("{base} ({suffix}).docx");
+        suffix += 1;
+    }
+    candidate
+}
+
+fn resolve_similar_prompt_threshold(threshold: Option<f32>, warnings: &mut Vec<String>) -> f32 {
+    let threshold = threshold.unwrap_or(SIMILAR_PROMPT_THRESHOLD_PERCENT);
+    if threshold.is_finite() && threshold > 0.0 && threshold <= 100.0 {
+        threshold / 100.0
+    } else {
+        warnings.push(format!(
+            "The similar prompt threshold must be above 0 and at most 100; using {SIMILAR_PROMPT_THRESHOLD_PERCENT}% instead."
+        ));
+        SIMILAR_PROMPT_THRESHOLD_PERCENT / 100.0
+    }
+}
+
+fn find_similar_prompt_pairs(
+    embeddings: &[(usize, Vec<f32>)],
+    threshold: f32,
+) -> Vec<(usize, usize, f32)> {
+    let normalized: Vec<(usize, Vec<f32>)> = embeddings
+        .iter()
+        .filter_map(|(index, embedding)| {
+            let norm = embedding
+                .iter()
+                .map(|value| f64::from(*value) * f64::from(*value))
+                .sum::<f64>()
+                .sqrt();
+            (norm > 0.0).then(|| {
+                (
+                    *index,
+                    embedding
+                        .iter()
+                        .map(|value| (f64::from(*value) / norm) as f32)
+                        .collect(),
+                )
+            })
+        })
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (position, (first, first_embedding)) in normalized.iter().enumerate() {
+        for (second, second_embedding) in &normalized[position + 1..] {
+            let similarity = dot_product(first_embedding, second_embedding) as f32;
+            if similarity >= threshold {
+                pairs.push((*first, *second, similarity));
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(Ordering::Equal));
+    pairs
+}
+
+fn report_similar_prompt_pairs(
+    pairs: &[(usize, usize, f32)],
+    label: impl Fn(usize) -> String,
+    warnings: &mut Vec<String>,
+) {
+    for &(first, second, similarity) in pairs.iter().take(SIMILAR_PROMPT_WARNING_LIMIT) {
+        warnings.push(format!(
+            "{} and {} have nearly identical prompts ({} similar).",
+            label(first),
+            label(second),
+            format_similarity_percent(similarity)
+        ));
+    }
+    if pairs.len() > SIMILAR_PROMPT_WARNING_LIMIT {
+        warnings.push(format!(
+            "{} pairs of nearly identical prompts were found in total; the first {} are listed on the Similar Prompts sheet.",
+            pairs.len(),
+            pairs.len().min(SIMILAR_PROMPT_PAIR_LIMIT)
+        ));
+    }
+}
+
+fn build_similar_prompt_rows(
+    student_headers: &[String],
+    similar_prompts: &[SimilarPromptPair],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut headers: Vec<String> = student_headers
+        .iter()
+        .map(|header| format!("First {header}"))
+        .collect();
+    headers.extend(
+        student_headers
+            .iter()
+            .map(|header| format!("Second {header}")),
+    );
+    headers.push("Similarity".into());
+    let rows = similar_prompts
+        .iter()
+        .map(|pair| {
+            let mut row = pair.first_values.clone();
+            row.resize(student_headers.len(), String::new());
+            let mut second = pair.second_values.clone();
+            second.resize(student_headers.len(), String::new());
+            row.extend(second);
+            row.push(format_similarity_percent(pair.similarity));
+            row
+        })
+        .collect();
+    (headers, rows)
+}
+
+fn resolve_attention_threshold(
+    threshold: Option<f32>,
+    low_threshold: f64,
+    warnings: &mut Vec<String>,
+) -> f64 {
+    match threshold {
+        Some(percent) if percent.is_finite() && (0.0..=100.0).contains(&percent) => {
+            f64::from(percent) / 100.0
+        }
+        Some(_) => {
+            warnings.push(format!(
+                "The needs-attention threshold must be between 0 and 100; using the low similarity threshold of {}% instead.",
+                low_threshold * 100.0
+            ));
+            low_threshold
+        }
+        None => low_threshold,
+    }
+}
+
+/// Lists processed students whose best similarity is below `threshold`, including
+/// students with no matches at all. Matches are already scored, so this is a pass
+/// over the results rather than another query.
+fn find_students_needing_attention<'a>(
+    students: impl Iterator<Item = (&'a str, &'a [String], &'a [FacultyMatchResult])>,
+    faculty_headers: &[String],
+    threshold: f64,
+) -> Vec<AttentionEntry> {
+    students
+        .filter_map(|(label, identifier_values, matches)| {
+            let best = matches
+                .iter()
+                .filter(|faculty| faculty.similarity.is_finite())
+                .max_by(|a, b| a.similarity.total_cmp(&b.similarity));
+            if best.is_some_and(|faculty| f64::from(faculty.similarity) >= threshold) {
+                return None;
+            }
+            Some(AttentionEntry {
+                label: label.to_string(),
+                identifier_values: identifier_values.to_vec(),
+                best_similarity: best.map(|faculty| faculty.similarity),
+                best_faculty: best
+                    .map(|faculty| faculty_identifier_values(faculty_headers, faculty))
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn build_attention_rows(
+    student_headers: &[String],
+    faculty_headers: &[String],
+    needs_attention: &[AttentionEntry],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut headers = student_headers.to_vec();
+    headers.push("Best similarity".into());
+    headers.extend(
+        faculty_headers
+            .iter()
+            .map(|header| format!("Best match {header}")),
+    );
+    let rows = needs_attention
+        .iter()
+        .map(|entry| {
+            let mut row = entry.identifier_values.clone();
+            row.resize(student_headers.len(), String::new());
+            row.push(
+                entry
+                    .best_similarity
+                    .map(format_similarity_percent)
+                    .unwrap_or_else(|| "No matches".into()),
+            );
+            let mut faculty = entry.best_faculty.clone();
+            faculty.resize(faculty_headers.len(), String::new());
+            row.extend(faculty);
+            row
+        })
+        .collect();
+    (headers, rows)
+}
+
+fn build_skipped_rows(
+    student_headers: &[String],
+    skipped_entries: &[SkippedEntry],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut headers = student_headers.to_vec();
+    headers.push("Reason".into());
+    let rows = skipped_entries
+        .iter()
+        .map(|entry| {
+            let mut row = entry.identifier_values.clone();
+            row.resize(student_headers.len(), String::new());
+            row.push(entry.reason.clone());
+            row
+        })
+        .collect();
+    (headers, rows)
+}
+
+fn write_delimited_table(
+    headers: &[String],
+    rows: &[Vec<String>],
+    delimiter: u8,
+) -> Result<String, String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_writer(Vec::new());
+    writer
+        .write_record(headers)
+        .map_err(|err| format!("Unable to write the results header row: {err}"))?;
+    for row in rows {
+        writer
+            .write_record(row)
+            .map_err(|err| format!("Unable to write a results row: {err}"))?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| format!("Unable to finalize the delimited results: {err}"))?;
+    String::from_utf8(bytes).map_err(|err| format!("Unable to encode the delimited results: {err}"))
+}
+
+fn build_student_summary_headers(
+    student_headers: &[String],
+    faculty_headers: &[String],
+) -> Vec<String> {
+    let mut headers = student_headers.to_vec();
+    headers.push("Total first reviewers".into());
+    headers.push("Total reviewers".into());
+    headers.extend(
+        faculty_headers
+            .iter()
+            .map(|header| format!("Top match {header}")),
+    );
+    headers.push("Top similarity %".into());
+    headers.push("Matches above threshold".into());
+    headers.push("Notes".into());
+    headers
+}
+
+fn build_student_summary_stats(
+    data: &MatchExportData,
+    high_threshold: f64,
+) -> Vec<StudentSummaryRow> {
+    let mut student_entries: HashMap<&[String], Vec<&MatchEntry>> = HashMap::new();
+    for entry in data.match_entries {
+        student_entries
+            .entry(entry.student_values.as_slice())
+            .or_default()
+            .push(entry);
+    }
+    let skipped_reasons: HashMap<&[String], &str> = data
+        .skipped_entries
+        .iter()
+        .map(|entry| (entry.identifier_values.as_slice(), entry.reason.as_str()))
+        .collect();
+
+    data.student_summary_rows
+        .iter()
+        .map(|identifiers| {
+            let entries = student_entries
+                .get(identifiers.as_slice())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let top_entry = entries
+                .iter()
+                .find(|entry| entry.faculty_rank == Some(1))
+                .or_else(|| entries.first());
+            let above_threshold = entries
+                .iter()
+                .filter(|entry| {
+                    entry
+                        .similarity
+                        .is_some_and(|value| f64::from(value) >= high_threshold)
+                })
+                .count();
+            let notes = if entries.is_empty() {
+                skipped_reasons
+                    .get(identifiers.as_slice())
+                    .copied()
+                    .unwrap_or("No faculty matches were returned.")
+                    .to_string()
+            } else {
+                String::new()
+            };
+
+            StudentSummaryRow {
+                top_faculty_values: top_entry
+                    .map(|entry| entry.faculty_values.clone())
+                    .unwrap_or_else(|| vec![String::new(); data.faculty_headers.len()]),
+                top_similarity: top_entry
+                    .and_then(|entry| entry.similarity)
+                    .filter(|value| value.is_finite()),
+                above_threshold,
+                notes,
+            }
+        })
+        .collect()
+}
+
+fn student_summary_stat_cells(summary: &StudentSummaryRow) -> Vec<String> {
+    let mut cells = summary.top_faculty_values.clone();
+    cells.push(
+        summary
+            .top_similarity
+            .map(|value| format!("{:.2}", value * 100.0))
+            .unwrap_or_default(),
+    );
+    cells.push(summary.above_threshold.to_string());
+    cells.push(summary.notes.clone());
+    cells
+}
+
+fn build_faculty_summary_headers(faculty_headers: &[String]) -> Vec<String> {
+    let mut headers = faculty_headers.to_vec();
+    headers.push("First reviewer count".into());
+    headers.push("Total reviewer count".into());
+    headers.push("Students matched".into());
+    headers.push("Average similarity %".into());
+    headers.push("Max similarity %".into());
+    headers.push("Best-matching student".into());
+    headers.push("Programs".into());
+    headers
+}
+
+fn build_faculty_summary_rows(
+    match_entries: &[MatchEntry],
+    faculty_programs: Option<&HashMap<usize, Vec<String>>>,
+) -> Vec<FacultySummaryRow> {
+    let mut faculty_positions: HashMap<&[String], usize> = HashMap::new();
+    let mut faculty_entries: Vec<Vec<&MatchEntry>> = Vec::new();
+    for entry in match_entries {
+        if entry.faculty_rank.is_none() {
+            continue;
+        }
+        let position = *faculty_positions
+            .entry(entry.faculty_values.as_slice())
+            .or_insert_with(|| {
+                faculty_entries.push(Vec::new());
+                faculty_entries.len() - 1
+            });
+        faculty_entries[position].push(entry);
+    }
+
+    faculty_entries
+        .into_iter()
+        .map(|entries| {
+            let first = entries[0];
+            let students: HashSet<&[String]> = entries
+                .iter()
+                .map(|entry| entry.student_values.as_slice())
+                .collect();
+            let similarities: Vec<f32> = entries
+                .iter()
+                .filter_map(|entry| entry.similarity)
+                .filter(|value| value.is_finite())
+                .collect();
+            let average_similarity = (!similarities.is_empty())
+                .then(|| similarities.iter().sum::<f32>() / similarities.len() as f32);
+            let best_entry = entries
+                .iter()
+                .filter(|entry| entry.similarity.is_some_and(f32::is_finite))
+                .max_by(|a, b| {
+                    a.similarity
+                        .partial_cmp(&b.similarity)
+                        .unwrap_or(Ordering::Equal)
+                });
+            let best_student = best_entry
+                .map(|entry| {
+                    entry
+                        .student_values
+                        .iter()
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" - ")
+                })
+                .unwrap_or_default();
+            let programs = faculty_programs
+                .filter(|_| first.faculty_source.is_dataset())
+                .and_then(|programs| programs.get(&first.faculty_row))
+                .map(|programs| programs.join("; "))
+                .unwrap_or_default();
+
+            FacultySummaryRow {
+                faculty_values: first.faculty_values.clone(),
+                student_count: students.len(),
+                average_similarity,
+                max_similarity: best_entry.and_then(|entry| entry.similarity),
+                best_student,
+                programs,
+            }
+        })
+        .collect()
+}
+
+fn faculty_summary_stat_cells(summary: &FacultySummaryRow) -> Vec<String> {
+    let percent = |value: Option<f32>| {
+        value
+            .map(|value| format!("{:.2}", value * 100.0))
+            .unwrap_or_default()
+    };
+    vec![
+        summary.student_count.to_string(),
+        percent(summary.average_similarity),
+        percent(summary.max_similarity),
+        summary.best_student.clone(),
+        summary.programs.clone(),
+    ]
+}
+
+fn build_mail_merge_rows(
+    faculty_headers: &[String],
+    match_entries: &[MatchEntry],
+    options: &MatchRunOptions,
+) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+    let email_col = faculty_headers
+        .iter()
+        .enumerate()
+        .position(|(col, header)| {
+            normalize_identifier_label(header).contains("email")
+                || values_look_like_emails(
+                    match_entries
+                        .iter()
+                        .filter_map(|entry| entry.faculty_values.get(col)),
+                )
+        })?;
+    let name_col = faculty_headers
+        .iter()
+        .enumerate()
+        .filter(|(col, _)| *col != email_col)
+        .find(|(_, header)| normalize_identifier_label(header).contains("name"))
+        .or_else(|| {
+            faculty_headers
+                .iter()
+                .enumerate()
+                .find(|(col, _)| *col != email_col)
+        })
+        .map(|(col, _)| col);
+
+    let mut faculty_entries: Vec<(&[String], Vec<&MatchEntry>)> = Vec::new();
+    let mut faculty_positions: HashMap<&[String], usize> = HashMap::new();
+    for entry in match_entries {
+        if entry.faculty_rank.is_none() {
+            continue;
+        }
+        let position = *faculty_positions
+            .entry(entry.faculty_values.as_slice())
+            .or_insert_with(|| {
+                faculty_entries.push((entry.faculty_values.as_slice(), Vec::new()));
+                faculty_entries.len() - 1
+            });
+        faculty_entries[position].1.push(entry);
+    }
+
+    let template = options
+        .mail_merge_template
+        .unwrap_or(DEFAULT_MAIL_MERGE_TEMPLATE);
+    let headers = vec![
+        "Email".to_string(),
+        "Name".to_string(),
+        "Students".to_string(),
+        "Message".to_string(),
+        "Note".to_string(),
+    ];
+    let rows = faculty_entries
+        .into_iter()
+        .map(|(faculty_values, mut entries)| {
+            entries.sort_by(|a, b| {
+                b.similarity
+                    .unwrap_or(f32::MIN)
+                    .partial_cmp(&a.similarity.unwrap_or(f32::MIN))
+                    .unwrap_or(Ordering::Equal)
+            });
+            let email = faculty_values
+                .get(email_col)
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default();
+            let name = name_col
+                .and_then(|col| faculty_values.get(col))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| email.clone());
+            let student_list = entries
+                .iter()
+                .map(|entry| {
+                    let student = entry
+                        .student_values
+                        .iter()
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" - ");
+                    let mut details = Vec::new();
+                    if let Some(similarity) = entry.similarity {
+                        details.push(format!(
+                            "{} similarity",
+                            format_similarity_percent(similarity)
+                        ));
+                    }
+                    if let Some(rank) = entry.faculty_rank {
+                        details.push(format!("recommendation #{rank} for this student"));
+                    }
+                    if details.is_empty() {
+                        format!("- {student}")
+                    } else {
+                        format!("- {student} ({})", details.join(", "))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let message = template
+                .replace("{faculty_name}", &name)
+                .replace("{faculty_email}", &email)
+                .replace("{student_count}", &entries.len().to_string())
+                .replace("{student_list}", &student_list);
+            let note = if email.is_empty() {
+                "No email address is on file for this faculty member.".to_string()
+            } else {
+                String::new()
+            };
+            vec![email, name, entries.len().to_string(), message, note]
+        })
+        .collect();
+
+    Some((headers, rows))
+}
+
+/// Writes a document path as a link that opens the file, falling back to plain text
+/// when Excel rejects the link, such as one past its length limit.
+fn write_document_path_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    path: &str,
+) -> Result<(), String> {
+    if !path.is_empty()
+        && sheet
+            .write_url_with_text(row, col, Url::new(document_file_url(path)), path)
+            .is_ok()
+    {
+        return Ok(());
+    }
+    sheet
+        .write_string(row, col, path)
+        .map_err(|err| format!("Unable to write a document path: {err}"))?;
+    Ok(())
+}
+
+/// Builds a `file:` URL for a local path. Windows separators become slashes, UNC shares
+/// keep their host, and anything outside the URL path characters is percent-encoded, so
+/// spaces, '#' and non-ASCII names still open the file.
+fn document_file_url(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let prefix = if path.starts_with("//") {
+        "file:"
+    } else if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    };
+    let mut url = String::from(prefix);
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:!$&'()*+,;=@".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    url
+}
+
+fn write_matches_sheet(
+    sheet: &mut Worksheet,
+    student_headers: &[String],
+    summary_student_columns: usize,
+    document_path_column: Option<usize>,
+    faculty_headers: &[String],
+    entries: &[&MatchEntry],
+    options: &MatchRunOptions,
+) -> Result<(), String> {
+    let header_format = Format::new().set_bold();
+    let percent_format = Format::new()
+        .set_num_format("0.0%")
+        .set_align(FormatAlign::Right);
+    let cosine_format = Format::new()
+        .set_num_format("0.0000")
+        .set_align(FormatAlign::Right);
+    let rank_format = Format::new().set_align(FormatAlign::Right);
+
+    let headers = build_matches_export_headers(student_headers, faculty_headers, options);
+    checked_worksheet_columns(headers.len(), "matches worksheet")?;
+    checked_worksheet_rows(entries.len(), "matches worksheet")?;
+    for (col_index, header) in headers.iter().enumerate() {
+        sheet
+            .write_string_with_format(0, col_index as u16, header, &header_format)
+            .map_err(|err| format!("Unable to write the matches header row: {err}"))?;
+    }
+    sheet
+        .set_freeze_panes(1, 0)
+        .map_err(|err| format!("Unable to freeze the matches header row: {err}"))?;
+
+    let student_offset = 2u32;
+    let faculty_offset = student_offset + student_headers.len() as u32;
+    let similarity_col = faculty_offset + faculty_headers.len() as u32;
+    let raw_cosine_col = options.include_raw_cosine.then_some(similarity_col + 1);
+    let student_rank_col = similarity_col + 1 + u32::from(options.include_raw_cosine);
+    let faculty_rank_col = student_rank_col + 1;
+    let prompt_text_col = options.include_prompt_text.then_some(faculty_rank_col + 1);
+    let faculty_text_col = options
+        .include_faculty_text
+        .then_some(faculty_rank_col + 1 + u32::from(options.include_prompt_text));
+    let faculty_id_col = options.include_faculty_id.then_some(
+        faculty_rank_col
+            + 1
+            + u32::from(options.include_prompt_text)
+            + u32::from(options.include_faculty_text),
+    );
+
+    let mut column_widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for entry in entries {
+        let values = entry
+            .student_values
+            .iter()
+            .enumerate()
+            .map(|(offset, value)| (student_offset as usize + offset, value))
+            .chain(
+                entry
+                    .faculty_values
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, value)| (faculty_offset as usize + offset, value)),
+            );
+        for (col, value) in values {
+            if let Some(width) = column_widths.get_mut(col) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+    }
+    apply_column_widths(sheet, &column_widths)?;
+
+    let wrap_format = Format::new().set_text_wrap();
+    for col in [prompt_text_col, faculty_text_col].into_iter().flatten() {
+        sheet
+            .set_column_width(col as u16, 80)
+            .map_err(|err| format!("Unable to size the match text columns: {err}"))?;
+    }
+    for offset in summary_student_columns..student_headers.len() {
+        sheet
+            .set_column_width((student_offset + offset as u32) as u16, 60)
+            .map_err(|err| format!("Unable to size the prompt value columns: {err}"))?;
+    }
+    // The faculty ID is for joining the workbook back to the dataset, not for reviewers.
+    if let Some(col) = faculty_id_col {
+        sheet
+            .set_column_hidden(col as u16)
+            .map_err(|err| format!("Unable to hide the faculty ID column: {err}"))?;
+    }
+
+    for (row_index, entry) in entries.iter().enumerate() {
+        let row = (row_index + 1) as u32;
+        for col in 0..2u16 {
+            match options.reviewer_input_mode {
+                ReviewerInputMode::Number => sheet.write_string(row, col, ""),
+                ReviewerInputMode::Checkbox => sheet.write_boolean(row, col, false),
+            }
+            .map_err(|err| format!("Unable to write the reviewer assignment columns: {err}"))?;
+        }
+
+        for (offset, value) in entry.student_values.iter().enumerate() {
+            let col = (student_offset + offset as u32) as u16;
+            if row <= EXCEL_HYPERLINK_LIMIT && document_path_column == Some(offset) {
+                write_document_path_cell(sheet, row, col, value)?;
+                continue;
+            }
+            if offset >= summary_student_columns {
+                sheet
+                    .write_string_with_format(row, col, excel_cell_text(value), &wrap_format)
+                    .map_err(|err| format!("Unable to write a prompt column value: {err}"))?;
+                continue;
+            }
+            sheet
+                .write_string(row, col, excel_cell_text(value))
+                .map_err(|err| format!("Unable to write a student identifier value: {err}"))?;
+        }
+
+        for (offset, value) in entry.faculty_values.iter().enumerate() {
+            sheet
+                .write_string(
+                    row,
+                    (faculty_offset + offset as u32) as u16,
+                    excel_cell_text(value),
+                )
+                .map_err(|err| format!("Unable to write a faculty identifier value: {err}"))?;
+        }
+
+        if let Some(value) = entry.similarity {
+            sheet
+                .write_number_with_format(
+                    row,
+                    similarity_col as u16,
+                    f64::from(value),
+                    &percent_format,
+                )
+                .map_err(|err| format!("Unable to write the similarity percentage: {err}"))?;
+        } else {
+            sheet
+                .write_string(row, similarity_col as u16, "")
+                .map_err(|err| format!("Unable to write the similarity placeholder: {err}"))?;
+        }
+
+        if let Some(col) = raw_cosine_col {
+            match entry.similarity.filter(|value| value.is_finite()) {
+                Some(value) => sheet
+                    .write_number_with_format(row, col as u16, f64::from(value), &cosine_format)
+                    .map_err(|err| format!("Unable to write the raw cosine value: {err}"))?,
+                None => sheet
+                    .write_string(row, col as u16, "")
+                    .map_err(|err| format!("Unable to write the raw cosine placeholder: {err}"))?,
+            };
+        }
+
+        if let Some((position, total)) = entry.student_rank {
+            let text = match total {
+                Some(limit) => format!("{position} of {limit}"),
+                None => position.to_string(),
+            };
+            sheet
+                .write_string_with_format(row, student_rank_col as u16, text, &rank_format)
+                .map_err(|err| format!("Unable to write the student rank: {err}"))?;
+        } else {
+            sheet
+                .write_string(row, student_rank_col as u16, "")
+                .map_err(|err| format!("Unable to write the student rank placeholder: {err}"))?;
+        }
+
+        if let Some(rank) = entry.faculty_rank {
+            sheet
+                .write_number_with_format(row, faculty_rank_col as u16, rank as f64, &rank_format)
+                .map_err(|err| format!("Unable to write the faculty rank: {err}"))?;
+        } else {
+            sheet
+                .write_string(row, faculty_rank_col as u16, "")
+                .map_err(|err| format!("Unable to write the faculty rank placeholder: {err}"))?;
+        }
+
+        let text_cells = [
+            (prompt_text_col, &entry.prompt_text),
+            (faculty_text_col, &entry.faculty_text),
+        ];
+        for (col, text) in text_cells {
+            if let (Some(col), Some(text)) = (col, text) {
+                sheet
+                    .write_string_with_format(row, col as u16, excel_cell_text(text), &wrap_format)
+                    .map_err(|err| format!("Unable to write the match text columns: {err}"))?;
+            }
+        }
+        if let Some(col) = faculty_id_col {
+            sheet
+                .write_string(row, col as u16, &entry.faculty_id)
+                .map_err(|err| format!("Unable to write the faculty ID: {err}"))?;
+        }
+    }
+
+    if !entries.is_empty() {
+        let (low, high) = options.similarity_thresholds;
+        let last_row = entries.len() as u32;
+        let rules = [
+            (
+                ConditionalFormatCellRule::LessThan(low),
+                Format::new()
+                    .set_font_color(0x9C0006)
+                    .set_background_color(0xFFC7CE),
+            ),
+            (
+                ConditionalFormatCellRule::Between(low, high),
+                Format::new()
+                    .set_font_color(0x9C5700)
+                    .set_background_color(0xFFEB9C),
+            ),
+            (
+                ConditionalFormatCellRule::GreaterThan(high),
+                Format::new()
+                    .set_font_color(0x006100)
+                    .set_background_color(0xC6EFCE),
+            ),
+        ];
+        for (rule, format) in rules {
+            let conditional_format = ConditionalFormatCell::new()
+                .set_rule(rule)
+                .set_format(format);
+            sheet
+                .add_conditional_format(
+                    1,
+                    similarity_col as u16,
+                    last_row,
+                    similarity_col as u16,
+                    &conditional_format,
+                )
+                .map_err(|err| format!("Unable to highlight the similarity column: {err}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn reviewer_assigned_value(mode: ReviewerInputMode) -> &'static str {
+    match mode {
+        ReviewerInputMode::Number => "1",
+        ReviewerInputMode::Checkbox => "TRUE",
+    }
+}
+
+fn add_reviewer_assignment_validation(
+    sheet: &mut Worksheet,
+    row_count: u32,
+    options: &MatchRunOptions,
+) -> Result<(), String> {
+    let (validation, convention) = match options.reviewer_input_mode {
+        ReviewerInputMode::Number => (
+            DataValidation::new().allow_whole_number(DataValidationRule::EqualTo(1)),
+            "Enter 1 to assign this faculty member, or leave the cell blank.",
+        ),
+        ReviewerInputMode::Checkbox => (
+            DataValidation::new()
+                .allow_list_strings(&["TRUE", "FALSE"])
+                .map_err(|err| format!("Unable to configure the reviewer checkboxes: {err}"))?,
+            "Choose TRUE to assign this faculty member, or FALSE to leave them unassigned.",
+        ),
+    };
+    let validation = validation
+        .set_input_title("Reviewer assignment")
+        .and_then(|validation| validation.set_input_message(convention))
+        .and_then(|validation| validation.set_error_title("Invalid reviewer assignment"))
+        .and_then(|validation| validation.set_error_message(convention))
+        .map_err(|err| format!("Unable to configure the reviewer validation: {err}"))?;
+    if row_count > 0 {
+        sheet
+            .add_data_validation(1, 0, row_count, 1, &validation)
+            .map_err(|err| format!("Unable to add the reviewer validation: {err}"))?;
+    }
+
+    let counted = reviewer_assigned_value(options.reviewer_input_mode);
+    let notes = [
+        (
+            0u16,
+            format!(
+                "{convention} The Student Summary and Faculty Summary sheets count a row as a \
+                 first reviewer only when this column is {counted}."
+            ),
+        ),
+        (
+            1u16,
+            format!(
+                "{convention} The summary sheets count a row as a reviewer when either \
+                 reviewer column is {counted}; any other value is ignored."
+            ),
+        ),
+    ];
+    for (col, text) in notes {
+        let note = Note::new(text).set_width(300).set_height(100);
+        sheet
+            .insert_note(0, col, &note)
+            .map_err(|err| format!("Unable to add the reviewer column notes: {err}"))?;
+    }
+    Ok(())
+}
+
+fn build_run_info_rows(run_info: &RunInfo) -> Vec<(&'static str, String)> {
+    let task_type = match run_info.task_type {
+        TaskType::Prompt => "Prompt",
+        TaskType::Document => "Document",
+        TaskType::Spreadsheet => "Spreadsheet",
+        TaskType::Directory => "Directory",
+    };
+    let faculty_scope = match (&run_info.faculty_scope, run_info.custom_faculty_mode) {
+        (FacultyScope::All, _) => "All faculty",
+        (FacultyScope::Program, _) => "Programs",
+        (FacultyScope::Custom, CustomFacultyMode::Include) => "Custom roster",
+        (FacultyScope::Custom, CustomFacultyMode::Exclude) => "All faculty except roster",
+    };
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+
+    let mut rows = vec![
+        ("Generated", run_info.generated_at.clone()),
+        ("App version", run_info.app_version.clone()),
+        ("Task type", task_type.to_string()),
+        ("Faculty scope", faculty_scope.to_string()),
+        ("Program filters", run_info.program_filters.join("; ")),
+        ("Roster path", optional(&run_info.custom_faculty_path)),
+        (
+            "Roster faculty",
+            run_info
+                .roster_faculty_count
+                .map(|count| count.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "Recommendations per student",
+            run_info.recommendations_per_student.to_string(),
+        ),
+        ("Embedding model", optional(&run_info.embedding_model)),
+        (
+            "Embeddings generated",
+            optional(&run_info.embeddings_generated_at),
+        ),
+        ("Dataset path", optional(&run_info.dataset_path)),
+        ("Dataset source", optional(&run_info.dataset_source_path)),
+        (
+            "Dataset rows",
+            run_info
+                .dataset_row_count
+                .map(|count| count.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "Low similarity threshold",
+            format!("{}%", run_info.similarity_low_threshold),
+        ),
+        (
+            "High similarity threshold",
+            format!("{}%", run_info.similarity_high_threshold),
+        ),
+        (
+            "Needs attention threshold",
+            format!("{}%", run_info.attention_threshold),
+        ),
+    ];
+    rows.push((
+        "Summary counts",
+        match run_info.summary_mode {
+            SummaryMode::Formulas => "SUMPRODUCT formulas",
+            SummaryMode::Static => "Static values (not updated by reviewer edits)",
+            SummaryMode::LiveCounts => "COUNTIFS formulas",
+        }
+        .to_string(),
+    ));
+    if let Some(limit) = run_info.prompt_text_max_chars {
+        rows.push(("Prompt text limit (characters)", limit.to_string()));
+    }
+    if let Some(limit) = run_info.student_worksheet_limit {
+        rows.push(("Student worksheet limit", limit.to_string()));
+    }
+    rows
+}
+
+fn build_run_statistics_rows(statistics: &RunStatistics) -> Vec<(&'static str, String)> {
+    let mut rows = vec![("Students matched", statistics.students_matched.to_string())];
+    if let Some(summary) = statistics.best_similarity {
+        rows.extend([
+            (
+                "Best similarity (mean)",
+                format_similarity_percent(summary.mean),
+            ),
+            (
+                "Best similarity (median)",
+                format_similarity_percent(summary.median),
+            ),
+            (
+                "Best similarity (min)",
+                format_similarity_percent(summary.min),
+            ),
+            (
+                "Best similarity (max)",
+                format_similarity_percent(summary.max),
+            ),
+            (
+                "Best similarity histogram",
+                statistics
+                    .best_similarity_histogram
+                    .iter()
+                    .map(|bucket| {
+                        format!(
+                            "{}-{}%: {}",
+                            bucket.lower_percent, bucket.upper_percent, bucket.count
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        ]);
+    }
+    rows.push((
+        "Distinct faculty recommended",
+        statistics.distinct_faculty.to_string(),
+    ));
+    if let (Some(coverage), Some(in_scope)) =
+        (statistics.scope_coverage, statistics.faculty_in_scope)
+    {
+        rows.push((
+            "Faculty scope coverage",
+            format!(
+                "{} ({} of {in_scope})",
+                format_similarity_percent(coverage),
+                statistics.distinct_faculty
+            ),
+        ));
+    }
+    rows
+}
+
+fn resolve_similarity_thresholds(
+    low: Option<f32>,
+    high: Option<f32>,
+    warnings: &mut Vec<String>,
+) -> (f64, f64) {
+    let low = low.unwrap_or(SIMILARITY_LOW_THRESHOLD_PERCENT);
+    let high = high.unwrap_or(SIMILARITY_HIGH_THRESHOLD_PERCENT);
+    let valid = low.is_finite()
+        && high.is_finite()
+        && (0.0..=100.0).contains(&low)
+        && (0.0..=100.0).contains(&high)
+        && low <= high;
+    let (low, high) = if valid {
+        (low, high)
+    } else {
+        warnings.push(format!(
+            "Similarity highlight thresholds must be between 0 and 100 with the low threshold at or below the high threshold; using {SIMILARITY_LOW_THRESHOLD_PERCENT}% and {SIMILARITY_HIGH_THRESHOLD_PERCENT}% instead."
+        ));
+        (
+            SIMILARITY_LOW_THRESHOLD_PERCENT,
+            SIMILARITY_HIGH_THRESHOLD_PERCENT,
+        )
+    };
+    (f64::from(low) / 100.0, f64::from(high) / 100.0)
+}
+
+fn checked_worksheet_rows(count: usize, context: &str) -> Result<u32, String> {
+    if count >= WORKSHEET_MAX_ROWS {
+        return Err(format!(
+            "The {context} needs {count} data rows, which exceeds the limit of {} rows per worksheet.",
+            WORKSHEET_MAX_ROWS - 1
+        ));
+    }
+    u32::try_from(count).map_err(|_| format!("The {context} has too many rows to write."))
+}
+
+fn checked_worksheet_columns(count: usize, context: &str) -> Result<u16, String> {
+    if count > WORKSHEET_MAX_COLUMNS {
+        return Err(format!(
+            "The {context} needs {count} columns, which exceeds the limit of {WORKSHEET_MAX_COLUMNS} columns per worksheet."
+        ));
+    }
+    u16::try_from(count).map_err(|_| format!("The {context} has too many columns to write."))
+}
+
+fn measure_column_widths<'a>(
+    headers: &[String],
+    rows: impl Iterator<Item = &'a Vec<String>>,
+) -> Vec<usize> {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in rows {
+        for (col, value) in row.iter().enumerate() {
+            if let Some(width) = widths.get_mut(col) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+    }
+    widths
+}
+
+fn apply_column_widths(sheet: &mut Worksheet, widths: &[usize]) -> Result<(), String> {
+    for (col, width) in widths.iter().enumerate() {
+        let width = (width + 2).clamp(WORKSHEET_COLUMN_MIN_WIDTH, WORKSHEET_COLUMN_MAX_WIDTH);
+        sheet
+            .set_column_width(col as u16, width as f64)
+            .map_err(|err| format!("Unable to size the worksheet columns: {err}"))?;
+    }
+    Ok(())
+}
+
+/// Shortens text that would exceed Excel's per-cell limit, marking the cut the same way
+/// truncated prompts are marked.
+fn excel_cell_text(value: &str) -> Cow<'_, str> {
+    match truncate_prompt_text(value, EXCEL_CELL_MAX_CHARS) {
+        Some(truncated) => Cow::Owned(truncated),
+        None => Cow::Borrowed(value),
+    }
+}
+
+fn sanitize_sheet_name(name: &str, fallback: &str, used_names: &mut HashSet<String>) -> String {
+    let cleaned = name
+        .chars()
+        .map(|ch| match ch {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => ' ',
+            ch if ch.is_control() => ' ',
+            _ => ch,
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let cleaned = cleaned.trim_matches('\'').trim();
+    let base = if cleaned.is_empty() {
+        fallback
+    } else {
+        cleaned
+    };
+
+    let truncate = |limit: usize| {
+        let mut width = 0;
+        base.chars()
+            .take_while(|ch| {
+                width += ch.len_utf16();
+                width <= limit
+            })
+            .collect::<String>()
+            .trim_end()
+            .trim_end_matches('\'')
+            .to_string()
+    };
+    let mut candidate = truncate(WORKSHEET_NAME_MAX_CHARS);
+    let mut suffix = 2usize;
+    while used_names.contains(&candidate.to_lowercase()) {
+        let suffix_text = format!(" ({suffix})");
+        candidate = format!(
+            "{}{suffix_text}",
+            truncate(WORKSHEET_NAME_MAX_CHARS - suffix_text.len())
+        );
+        suffix += 1;
+    }
+
+    used_names.insert(candidate.to_lowercase());
+    candidate
+}
+
+/// Builds the results workbook from fully collected match entries. Large match sets
+/// bound the worksheet cells (see `MATCHES_CONSTANT_MEMORY_THRESHOLD`), not the entries.
+fn build_matches_workbook(
+    data: &MatchExportData,
+    options: &MatchRunOptions,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<u8>, String> {
+    build_matches_workbook_with_limits(
+        data,
+        options,
+        warnings,
+        WORKSHEET_MAX_ROWS,
+        MATCHES_CONSTANT_MEMORY_THRESHOLD,
+    )
+}
+
+/// `max_rows` counts the header row, like `WORKSHEET_MAX_ROWS`; tests lower it and
+/// `constant_memory_threshold` to exercise the split into several Matches worksheets
+/// and the constant-memory path without building huge match sets.
+fn build_matches_workbook_with_limits(
+    data: &MatchExportData,
+    options: &MatchRunOptions,
+    warnings: &mut Vec<String>,
+    max_rows: usize,
+    constant_memory_threshold: usize,
+) -> Result<Vec<u8>, String> {
+    let MatchExportData {
+        student_headers,
+        summary_student_columns,
+        student_summary_rows,
+        faculty_headers,
+        match_entries,
+        skipped_entries,
+        needs_attention,
+        similar_prompts,
+        statistics,
+        document_path_column,
+    } = *data;
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+    let all_entries: Vec<&MatchEntry> = match_entries.iter().collect();
+    let matches_parts: Vec<&[&MatchEntry]> = if all_entries.is_empty() {
+        vec![&all_entries[..]]
+    } else {
+        all_entries.chunks(max_rows - 1).collect()
+    };
+    let mut used_sheet_names: HashSet<String> = HashSet::from(["history".to_string()]);
+    let matches_sheet_names: Vec<String> = if matches_parts.len() == 1 {
+        vec![sanitize_sheet_name(
+            "Matches",
+            "Matches",
+            &mut used_sheet_names,
+        )]
+    } else {
+        (1..=matches_parts.len())
+            .map(|part| {
+                sanitize_sheet_name(
+                    &format!("Matches ({part})"),
+                    "Matches",
+                    &mut used_sheet_names,
+                )
+            })
+            .collect()
+    };
+    let mut reserve_sheet_name =
+        |name: &str| sanitize_sheet_name(name, name, &mut used_sheet_names);
+    let student_summary_sheet_name = reserve_sheet_name("Student Summary");
+    let faculty_summary_sheet_name = reserve_sheet_name("Faculty Summary");
+    let mail_merge_sheet_name = reserve_sheet_name("Mail Merge");
+    let skipped_sheet_name = reserve_sheet_name("Skipped");
+    let attention_sheet_name = reserve_sheet_name("Needs Attention");
+    let similar_prompts_sheet_name = reserve_sheet_name("Similar Prompts");
+    let roster_sheet_name = reserve_sheet_name("Unmatched Roster");
+    let run_info_sheet_name = reserve_sheet_name("Run Info");
+    let warnings_sheet_name = reserve_sheet_name("Warnings");
+    let last_match_col = checked_worksheet_columns(
+        build_matches_export_headers(student_headers, faculty_headers, options).len(),
+        "matches worksheet",
+    )? - 1;
+    let constant_memory = match_entries.len() > constant_memory_threshold;
+    for (sheet_name, entries) in matches_sheet_names.iter().zip(&matches_parts) {
+        // A constant-memory worksheet flushes each row once the next one is written, so
+        // the filter, validation and header notes are added before any rows, and
+        // write_matches_sheet sizes columns up front and fills rows in order.
+        let matches_sheet = if constant_memory {
+            workbook.add_worksheet_with_constant_memory()
+        } else {
+            workbook.add_worksheet()
+        };
+        matches_sheet
+            .set_name(sheet_name)
+            .map_err(|err| format!("Unable to configure the matches worksheet: {err}"))?;
+        let row_count = checked_worksheet_rows(entries.len(), "matches worksheet")?;
+        matches_sheet
+            .autofilter(0, 0, row_count, last_match_col)
+            .map_err(|err| format!("Unable to add the matches filter: {err}"))?;
+        add_reviewer_assignment_validation(matches_sheet, row_count, options)?;
+        write_matches_sheet(
+            matches_sheet,
+            student_headers,
+            summary_student_columns,
+            document_path_column,
+            faculty_headers,
+            entries,
+            options,
+        )?;
+    }
+    let matches_sheet_name = matches_sheet_names[0].as_str();
+    let summary_mode = if matches_sheet_names.len() > 1 {
+        warnings.push(format!(
+            "The {} matches were split across {} worksheets to stay within the worksheet row limit; the summary reviewer counts were written as static values.",
+            match_entries.len(),
+            matches_sheet_names.len()
+        ));
+        SummaryMode::Static
+    } else {
+        options.summary_mode
+    };
+    let assigned_value = reviewer_assigned_value(options.reviewer_input_mode);
+
+    checked_worksheet_rows(student_summary_rows.len(), "student summary worksheet")?;
+    let student_offset = 2u32;
+    let faculty_offset = student_offset + student_headers.len() as u32;
+    let match_row_count = if summary_mode == SummaryMode::Static {
+        0
+    } else {
+        checked_worksheet_rows(match_entries.len(), "matches worksheet")?
+    };
+    let student_summary_headers =
+        build_student_summary_headers(&student_headers[..summary_student_columns], faculty_headers);
+    checked_worksheet_columns(student_summary_headers.len(), "student summary worksheet")?;
+    let student_stats = build_student_summary_stats(data, options.similarity_thresholds.1);
+    let summary_percent_format = Format::new()
+        .set_num_format("0.0%")
+        .set_align(FormatAlign::Right);
+
+    let student_summary_sheet = workbook.add_worksheet();
+    student_summary_sheet
+        .set_name(&student_summary_sheet_name)
+        .map_err(|err| format!("Unable to configure the student summary worksheet: {err}"))?;
+    for (col_index, header) in student_summary_headers.iter().enumerate() {
+        student_summary_sheet
+            .write_string_with_format(0, col_index as u16, header, &header_format)
+            .map_err(|err| format!("Unable to write the student summary header row: {err}"))?;
+    }
+    student_summary_sheet
+        .set_freeze_panes(1, 0)
+        .map_err(|err| format!("Unable to freeze the student summary header row: {err}"))?;
+    let student_summary_text: Vec<Vec<String>> = student_summary_rows
+        .iter()
+        .zip(&student_stats)
+        .map(|(identifiers, summary)| {
+            let mut row = identifiers[..summary_student_columns].to_vec();
+            row.extend([String::new(), String::new()]);
+            row.extend(student_summary_stat_cells(summary));
+            row
+        })
+        .collect();
+    apply_column_widths(
+        student_summary_sheet,
+        &measure_column_widths(&student_summary_headers, student_summary_text.iter()),
+    )?;
+
+    let first_reviewer_range = if match_row_count > 0 {
+        Some(excel_range_reference(
+            matches_sheet_name,
+            1,
+            0,
+            match_row_count,
+            0,
+        ))
+    } else {
+        None
+    };
+    let reviewer_range = if match_row_count > 0 {
+        Some(excel_range_reference(
+            matches_sheet_name,
+            1,
+            1,
+            match_row_count,
+            1,
+        ))
+    } else {
+        None
+    };
+
+    for (row_index, (identifiers, summary)) in
+        student_summary_rows.iter().zip(&student_stats).enumerate()
+    {
+        let row = (row_index + 1) as u32;
+        for (col_offset, value) in identifiers[..summary_student_columns].iter().enumerate() {
+            student_summary_sheet
+                .write_string(row, col_offset as u16, excel_cell_text(value))
+                .map_err(|err| {
+                    format!("Unable to write a student summary identifier value: {err}")
+                })?;
+        }
+
+        let first_col = summary_student_columns;
+        let total_col = first_col + 1;
+        let top_offset = (total_col + 1) as u16;
+        for (col_offset, value) in summary.top_faculty_values.iter().enumerate() {
+            student_summary_sheet
+                .write_string(row, top_offset + col_offset as u16, excel_cell_text(value))
+                .map_err(|err| format!("Unable to write the student's top match: {err}"))?;
+        }
+        let similarity_col = top_offset + faculty_headers.len() as u16;
+        if let Some(value) = summary.top_similarity {
+            student_summary_sheet
+                .write_number_with_format(
+                    row,
+                    similarity_col,
+                    f64::from(value),
+                    &summary_percent_format,
+                )
+                .map_err(|err| format!("Unable to write the student's top similarity: {err}"))?;
+        }
+        student_summary_sheet
+            .write_number(row, similarity_col + 1, summary.above_threshold as f64)
+            .map_err(|err| format!("Unable to write the student's threshold count: {err}"))?;
+        student_summary_sheet
+            .write_string(row, similarity_col + 2, excel_cell_text(&summary.notes))
+            .map_err(|err| format!("Unable to write the student summary notes: {err}"))?;
+
+        if match_row_count == 0 {
+            student_summary_sheet
+                .write_number(row, first_col as u16, 0.0)
+                .map_err(|err| {
+                    format!("Unable to write the student first reviewer placeholder: {err}")
+                })?;
+            student_summary_sheet
+                .write_number(row, total_col as u16, 0.0)
+                .map_err(|err| {
+                    format!("Unable to write the student reviewer count placeholder: {err}")
+                })?;
+            continue;
+        }
+
+        let key_criteria: Vec<(String, String)> = (0..summary_student_columns)
+            .map(|col_offset| {
+                (
+                    excel_range_reference(
+                        matches_sheet_name,
+                        1,
+                        student_offset + col_offset as u32,
+                        match_row_count,
+                        student_offset + col_offset as u32,
+                    ),
+                    excel_cell_reference(row, col_offset as u32, true, false),
+                )
+            })
+            .collect();
+        let (first_formula, total_formula) = build_reviewer_count_formulas(
+            summary_mode,
+            assigned_value,
+            first_reviewer_range.as_deref().unwrap(),
+            reviewer_range.as_deref().unwrap(),
+            &key_criteria,
+        );
+        student_summary_sheet
+            .write_formula(row, first_col as u16, first_formula.as_str())
+            .map_err(|err| format!("Unable to write the student first reviewer formula: {err}"))?;
+        student_summary_sheet
+            .write_formula(row, total_col as u16, total_formula.as_str())
+            .map_err(|err| format!("Unable to write the student reviewer count formula: {err}"))?;
+    }
+
+    let faculty_summary_headers = build_faculty_summary_headers(faculty_headers);
+    checked_worksheet_columns(faculty_summary_headers.len(), "faculty summary worksheet")?;
+
+    let faculty_summary_sheet = workbook.add_worksheet();
+    faculty_summary_sheet
+        .set_name(&faculty_summary_sheet_name)
+        .map_err(|err| format!("Unable to configure the faculty summary worksheet: {err}"))?;
+    for (col_index, header) in faculty_summary_headers.iter().enumerate() {
+        faculty_summary_sheet
+            .write_string_with_format(0, col_index as u16, header, &header_format)
+            .map_err(|err| format!("Unable to write the faculty summary header row: {err}"))?;
+    }
+
+    let faculty_summary_rows = build_faculty_summary_rows(match_entries, options.faculty_programs);
+    faculty_summary_sheet
+        .set_freeze_panes(1, 0)
+        .map_err(|err| format!("Unable to freeze the faculty summary header row: {err}"))?;
+    let faculty_summary_text: Vec<Vec<String>> = faculty_summary_rows
+        .iter()
+        .map(|summary| {
+            let mut row = summary.faculty_values.clone();
+            row.extend([String::new(), String::new()]);
+            row.extend(faculty_summary_stat_cells(summary));
+            row
+        })
+        .collect();
+    apply_column_widths(
+        faculty_summary_sheet,
+        &measure_column_widths(&faculty_summary_headers, faculty_summary_text.iter()),
+    )?;
+
+    for (row_index, summary) in faculty_summary_rows.iter().enumerate() {
+        let row = (row_index + 1) as u32;
+        for (col_offset, value) in summary.faculty_values.iter().enumerate() {
+            faculty_summary_sheet
+                .write_string(row, col_offset as u16, excel_cell_text(value))
+                .map_err(|err| {
+                    format!("Unable to write a faculty summary identifier value: {err}")
+                })?;
+        }
+
+        let first_col = faculty_headers.len();
+        let total_col = first_col + 1;
+        let students_col = (total_col + 1) as u16;
+        faculty_summary_sheet
+            .write_number(row, students_col, summary.student_count as f64)
+            .map_err(|err| format!("Unable to write the faculty student count: {err}"))?;
+        let similarity_cells = [
+            (students_col + 1, summary.average_similarity),
+            (students_col + 2, summary.max_similarity),
+        ];
+        for (col, value) in similarity_cells {
+            if let Some(value) = value {
+                faculty_summary_sheet
+                    .write_number_with_format(row, col, f64::from(value), &summary_percent_format)
+                    .map_err(|err| {
+                        format!("Unable to write the faculty similarity summary: {err}")
+                    })?;
+            }
+        }
+        faculty_summary_sheet
+            .write_string(
+                row,
+                students_col + 3,
+                excel_cell_text(&summary.best_student),
+            )
+            .map_err(|err| format!("Unable to write the best-matching student: {err}"))?;
+        faculty_summary_sheet
+            .write_string(row, students_col + 4, excel_cell_text(&summary.programs))
+            .map_err(|err| format!("Unable to write the faculty programs: {err}"))?;
+
+        if match_row_count == 0 {
+            faculty_summary_sheet
+                .write_number(row, first_col as u16, 0.0)
+                .map_err(|err| {
+                    format!("Unable to write the faculty first reviewer placeholder: {err}")
+                })?;
+            faculty_summary_sheet
+                .write_number(row, total_col as u16, 0.0)
+                .map_err(|err| {
+                    format!("Unable to write the faculty reviewer placeholder: {err}")
+                })?;
+            continue;
+        }
+
+        let key_criteria: Vec<(String, String)> = faculty_headers
+            .iter()
+            .enumerate()
+            .map(|(col_offset, _)| {
+                (
+                    excel_range_reference(
+                        matches_sheet_name,
+                        1,
+                        faculty_offset + col_offset as u32,
+                        match_row_count,
+                        faculty_offset + col_offset as u32,
+                    ),
+                    excel_cell_reference(row, col_offset as u32, true, false),
+                )
+            })
+            .collect();
+        let (first_formula, total_formula) = build_reviewer_count_formulas(
+            summary_mode,
+            assigned_value,
+            first_reviewer_range.as_deref().unwrap(),
+            reviewer_range.as_deref().unwrap(),
+            &key_criteria,
+        );
+        faculty_summary_sheet
+            .write_formula(row, first_col as u16, first_formula.as_str())
+            .map_err(|err| format!("Unable to write the faculty first reviewer formula: {err}"))?;
+        faculty_summary_sheet
+            .write_formula(row, total_col as u16, total_formula.as_str())
+            .map_err(|err| format!("Unable to write the faculty reviewer formula: {err}"))?;
+    }
+
+    if options.sheet_per_student && !match_entries.is_empty() {
+        let mut student_entries: Vec<(String, Vec<&MatchEntry>)> = Vec::new();
+        let mut student_positions: HashMap<&[String], usize> = HashMap::new();
+        for entry in match_entries {
+            let position = *student_positions
+                .entry(entry.student_values.as_slice())
+                .or_insert_with(|| {
+                    let label = entry.student_values[..summary_student_columns]
+                        .iter()
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" - ");
+                    student_entries.push((label, Vec::new()));
+                    student_entries.len() - 1
+                });
+            student_entries[position].1.push(entry);
+        }
+
+        if student_entries.len() > STUDENT_WORKSHEET_LIMIT {
+            warnings.push(format!(
+                "Only the first {STUDENT_WORKSHEET_LIMIT} of {} students received their own worksheet.",
+                student_entries.len()
+            ));
+        }
+
+        for (label, entries) in student_entries.iter().take(STUDENT_WORKSHEET_LIMIT) {
+            let sheet_name = sanitize_sheet_name(label, "Student", &mut used_sheet_names);
+            let student_sheet = workbook.add_worksheet();
+            student_sheet.set_name(&sheet_name).map_err(|err| {
+                format!("Unable to configure the worksheet '{sheet_name}': {err}")
+            })?;
+            write_matches_sheet(
+                student_sheet,
+                student_headers,
+                summary_student_columns,
+                document_path_column,
+                faculty_headers,
+                entries,
+                options,
+            )?;
+        }
+    }
+
+    if let Some((mail_merge_headers, mail_merge_rows)) =
+        build_mail_merge_rows(faculty_headers, match_entries, options)
+    {
+        let mail_merge_sheet = workbook.add_worksheet();
+        mail_merge_sheet
+            .set_name(&mail_merge_sheet_name)
+            .map_err(|err| format!("Unable to configure the mail merge worksheet: {err}"))?;
+        for (col_index, header) in mail_merge_headers.iter().enumerate() {
+            mail_merge_sheet
+                .write_string_with_format(0, col_index as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the mail merge header row: {err}"))?;
+        }
+        mail_merge_sheet
+            .set_freeze_panes(1, 0)
+            .map_err(|err| format!("Unable to freeze the mail merge header row: {err}"))?;
+        apply_column_widths(
+            mail_merge_sheet,
+            &measure_column_widths(&mail_merge_headers, mail_merge_rows.iter()),
+        )?;
+        let wrap_format = Format::new().set_text_wrap();
+        let message_col = mail_merge_headers.len() - 2;
+        mail_merge_sheet
+            .set_column_width(message_col as u16, 80)
+            .map_err(|err| format!("Unable to size the mail merge worksheet: {err}"))?;
+        for (row_index, values) in mail_merge_rows.iter().enumerate() {
+            let row = (row_index + 1) as u32;
+            for (col_index, value) in values.iter().enumerate() {
+                if col_index == message_col {
+                    mail_merge_sheet
+                        .write_string_with_format(
+                            row,
+                            col_index as u16,
+                            excel_cell_text(value),
+                            &wrap_format,
+                        )
+                        .map_err(|err| format!("Unable to write a mail merge message: {err}"))?;
+                } else {
+                    mail_merge_sheet
+                        .write_string(row, col_index as u16, excel_cell_text(value))
+                        .map_err(|err| format!("Unable to write a mail merge value: {err}"))?;
+                }
+            }
+        }
+    }
+
+    if !skipped_entries.is_empty() {
+        let (skipped_headers, skipped_rows) = build_skipped_rows(student_headers, skipped_entries);
+        let skipped_sheet = workbook.add_worksheet();
+        skipped_sheet
+            .set_name(&skipped_sheet_name)
+            .map_err(|err| format!("Unable to configure the skipped worksheet: {err}"))?;
+        for (col_index, header) in skipped_headers.iter().enumerate() {
+            skipped_sheet
+                .write_string_with_format(0, col_index as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the skipped header row: {err}"))?;
+        }
+        skipped_sheet
+            .set_freeze_panes(1, 0)
+            .map_err(|err| format!("Unable to freeze the skipped header row: {err}"))?;
+        apply_column_widths(
+            skipped_sheet,
+            &measure_column_widths(&skipped_headers, skipped_rows.iter()),
+        )?;
+        for (row_index, values) in skipped_rows.iter().enumerate() {
+            let row = (row_index + 1) as u32;
+            for (col_index, value) in values.iter().enumerate() {
+                skipped_sheet
+                    .write_string(row, col_index as u16, excel_cell_text(value))
+                    .map_err(|err| format!("Unable to write a skipped entry: {err}"))?;
+            }
+        }
+    }
+
+    if !needs_attention.is_empty() {
+        let (attention_headers, attention_rows) =
+            build_attention_rows(student_headers, faculty_headers, needs_attention);
+        let attention_sheet = workbook.add_worksheet();
+        attention_sheet
+            .set_name(&attention_sheet_name)
+            .map_err(|err| format!("Unable to configure the needs attention worksheet: {err}"))?;
+        attention_sheet.set_tab_color(0x9C0006);
+        for (col_index, header) in attention_headers.iter().enumerate() {
+            attention_sheet
+                .write_string_with_format(0, col_index as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the needs attention header row: {err}"))?;
+        }
+        attention_sheet
+            .set_freeze_panes(1, 0)
+            .map_err(|err| format!("Unable to freeze the needs attention header row: {err}"))?;
+        apply_column_widths(
+            attention_sheet,
+            &measure_column_widths(&attention_headers, attention_rows.iter()),
+        )?;
+        let highlight_format = Format::new()
+            .set_font_color(0x9C0006)
+            .set_background_color(0xFFC7CE);
+        for (row_index, values) in attention_rows.iter().enumerate() {
+            let row = (row_index + 1) as u32;
+            for (col_index, value) in values.iter().enumerate() {
+                attention_sheet
+                    .write_string_with_format(
+                        row,
+                        col_index as u16,
+                        excel_cell_text(value),
+                        &highlight_format,
+                    )
+                    .map_err(|err| format!("Unable to write a needs attention entry: {err}"))?;
+            }
+        }
+    }
+
+    if !similar_prompts.is_empty() {
+        let (similar_headers, similar_rows) =
+            build_similar_prompt_rows(student_headers, similar_prompts);
+        let similar_sheet = workbook.add_worksheet();
+        similar_sheet
+            .set_name(&similar_prompts_sheet_name)
+            .map_err(|err| format!("Unable to configure the similar prompts worksheet: {err}"))?;
+        for (col_index, header) in similar_headers.iter().enumerate() {
+            similar_sheet
+                .write_string_with_format(0, col_index as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the similar prompts header row: {err}"))?;
+        }
+        similar_sheet
+            .set_freeze_panes(1, 0)
+            .map_err(|err| format!("Unable to freeze the similar prompts header row: {err}"))?;
+        apply_column_widths(
+            similar_sheet,
+            &measure_column_widths(&similar_headers, similar_rows.iter()),
+        )?;
+        for (row_index, values) in similar_rows.iter().enumerate() {
+            let row = (row_index + 1) as u32;
+            for (col_index, value) in values.iter().enumerate() {
+                similar_sheet
+                    .write_string(row, col_index as u16, excel_cell_text(value))
+                    .map_err(|err| format!("Unable to write a similar prompt pair: {err}"))?;
+            }
+        }
+    }
+
+    if let Some(diagnostics) = options.roster_diagnostics {
+        let roster_sheet = workbook.add_worksheet();
+        roster_sheet
+            .set_name(&roster_sheet_name)
+            .map_err(|err| format!("Unable to configure the unmatched roster worksheet: {err}"))?;
+
+        let mut roster_headers = vec![
+            "Roster row".to_string(),
+            "Reason".to_string(),
+            "Attempted keys".to_string(),
+        ];
+        roster_headers.extend(diagnostics.headers.iter().cloned());
+        for (col_index, header) in roster_headers.iter().enumerate() {
+            roster_sheet
+                .write_string_with_format(0, col_index as u16, header, &header_format)
+                .map_err(|err| format!("Unable to write the unmatched roster header row: {err}"))?;
+        }
+        roster_sheet
+            .set_freeze_panes(1, 0)
+            .map_err(|err| format!("Unable to freeze the unmatched roster header row: {err}"))?;
+        let roster_rows: Vec<Vec<String>> = diagnostics
+            .unmatched_rows
+            .iter()
+            .map(|unmatched| {
+                let mut row = vec![
+                    unmatched.row_number.to_string(),
+                    unmatched.reason.clone(),
+                    unmatched.attempted_keys.join("; "),
+                ];
+                row.extend(unmatched.values.iter().cloned());
+                row
+            })
+            .collect();
+        apply_column_widths(
+            roster_sheet,
+            &measure_column_widths(&roster_headers, roster_rows.iter()),
+        )?;
+
+        for (row_index, unmatched) in diagnostics.unmatched_rows.iter().enumerate() {
+            let row = (row_index + 1) as u32;
+            roster_sheet
+                .write_number(row, 0, unmatched.row_number as f64)
+                .map_err(|err| format!("Unable to write the unmatched roster row number: {err}"))?;
+            roster_sheet
+                .write_string(row, 1, excel_cell_text(&unmatched.reason))
+                .map_err(|err| format!("Unable to write the unmatched roster reason: {err}"))?;
+            roster_sheet
+                .write_string(
+                    row,
+                    2,
+                    excel_cell_text(&unmatched.attempted_keys.join("; ")),
+                )
+                .map_err(|err| format!("Unable to write the unmatched roster keys: {err}"))?;
+            for (offset, value) in unmatched.values.iter().enumerate() {
+                roster_sheet
+                    .write_string(row, (3 + offset) as u16, excel_cell_text(value))
+                    .map_err(|err| format!("Unable to write an unmatched roster value: {err}"))?;
+            }
+        }
+    }
+
+    let run_info_sheet = workbook.add_worksheet();
+    run_info_sheet
+        .set_name(&run_info_sheet_name)
+        .map_err(|err| format!("Unable to configure the run info worksheet: {err}"))?;
+    for (col_index, header) in ["Setting", "Value"].iter().enumerate() {
+        run_info_sheet
+            .write_string_with_format(0, col_index as u16, *header, &header_format)
+            .map_err(|err| format!("Unable to write the run info header row: {err}"))?;
+    }
+    let run_info_rows: Vec<Vec<String>> = build_run_info_rows(options.run_info)
+        .into_iter()
+        .chain(build_run_statistics_rows(statistics))
+        .map(|(label, value)| vec![label.to_string(), value])
+        .collect();
+    apply_column_widths(
+        run_info_sheet,
+        &measure_column_widths(
+            &["Setting".to_string(), "Value".to_string()],
+            run_info_rows.iter(),
+        ),
+    )?;
+    for (row_index, values) in run_info_rows.iter().enumerate() {
+        let row = (row_index + 1) as u32;
+        for (col_index, value) in values.iter().enumerate() {
+            run_info_sheet
+                .write_string(row, col_index as u16, excel_cell_text(value))
+                .map_err(|err| format!("Unable to write the run info: {err}"))?;
+        }
+    }
+
+    let mut workbook_warnings: Vec<String> = options
+        .run_warnings
+        .iter()
+        .chain(warnings.iter())
+        .cloned()
+        .collect();
+    if let Some(diagnostics) = options
+        .roster_diagnostics
+        .filter(|diagnostics| !diagnostics.unmatched_rows.is_empty())
+    {
+        let count = diagnostics.unmatched_rows.len();
+        workbook_warnings.push(format!(
+            "{count} roster row{plural} could not be matched; see the Unmatched Roster sheet for details.",
+            plural = if count == 1 { "" } else { "s" }
+        ));
+    }
+
+    if !workbook_warnings.is_empty() {
+        let warnings_sheet = workbook.add_worksheet();
+        warnings_sheet
+            .set_name(&warnings_sheet_name)
+            .map_err(|err| format!("Unable to configure the warnings worksheet: {err}"))?;
+        warnings_sheet
+            .write_string_with_format(0, 0, "Generated", &header_format)
+            .map_err(|err| format!("Unable to write the warnings timestamp: {err}"))?;
+        warnings_sheet
+            .write_string(0, 1, Utc::now().to_rfc3339())
+            .map_err(|err| format!("Unable to write the warnings timestamp: {err}"))?;
+        for (col_index, header) in ["#", "Warning"].iter().enumerate() {
+            warnings_sheet
+                .write_string_with_format(2, col_index as u16, *header, &header_format)
+                .map_err(|err| format!("Unable to write the warnings header row: {err}"))?;
+        }
+        warnings_sheet
+            .set_column_width(0, 12)
+            .map_err(|err| format!("Unable to size the warnings worksheet: {err}"))?;
+        warnings_sheet
+            .set_column_width(1, 120)
+            .map_err(|err| format!("Unable to size the warnings worksheet: {err}"))?;
+
+        let wrap_format = Format::new().set_text_wrap();
+        for (index, warning) in workbook_warnings.iter().enumerate() {
+            let row = (index + 3) as u32;
+            warnings_sheet
+                .write_number(row, 0, (index + 1) as f64)
+                .map_err(|err| format!("Unable to write the warning number: {err}"))?;
+            warnings_sheet
+                .write_string_with_format(row, 1, excel_cell_text(warning), &wrap_format)
+                .map_err(|err| format!("Unable to write a warning: {err}"))?;
+        }
+    }
+
+    workbook
+        .save_to_buffer()
+        .map_err(|err| format!("Unable to finalize the match workbook: {err}"))
+}
+
+fn build_reviewer_count_formulas(
+    mode: SummaryMode,
+    assigned_value: &str,
+    first_range: &str,
+    reviewer_range: &str,
+    key_criteria: &[(String, String)],
+) -> (String, String) {
+    if mode == SummaryMode::LiveCounts {
+        let countifs = |conditions: &[(&str, &str)]| {
+            let arguments: Vec<String> = conditions
+                .iter()
+                .map(|(range, criteria)| format!("{range}, {criteria}"))
+                .chain(
+                    key_criteria
+                        .iter()
+                        .map(|(range, cell)| format!("{range}, {cell}")),
+                )
+                .collect();
+            format!("COUNTIFS({})", arguments.join(", "))
+        };
+        let first = countifs(&[(first_range, assigned_value)]);
+        let reviewer = countifs(&[(reviewer_range, assigned_value)]);
+        let both = countifs(&[
+            (first_range, assigned_value),
+            (reviewer_range, assigned_value),
+        ]);
+        return (format!("={first}"), format!("={first}+{reviewer}-{both}"));
+    }
+
+    let key_factors = key_criteria
+        .iter()
+        .map(|(range, cell)| format!("--({range}={cell})"));
+    let first_factors: Vec<String> = std::iter::once(format!("--({first_range}={assigned_value})"))
+        .chain(key_factors.clone())
+        .collect();
+    let total_factors: Vec<String> = std::iter::once(format!(
+        "--((( {first_range}={assigned_value})+({reviewer_range}={assigned_value}))>0)"
+    ))
+    .chain(key_factors)
+    .collect();
+    (
+        build_sumproduct_formula(&first_factors),
+        build_sumproduct_formula(&total_factors),
+    )
+}
+
+fn build_sumproduct_formula(factors: &[String]) -> String {
+    if factors.is_empty() {
+        "=0".into()
+    } else {
+        format!("=SUMPRODUCT({})", factors.join(", "))
+    }
+}
+
+fn excel_column_name(mut index: u32) -> String {
+    let mut name = String::new();
+    loop {
+        let remainder = index % 26;
+        name.push((b'A' + remainder as u8) as char);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.chars().rev().collect()
+}
+
+fn excel_cell_reference(row: u32, col: u32, absolute_col: bool, absolute_row: bool) -> String {
+    let mut reference = String::new();
+    if absolute_col {
+        reference.push('$');
+    }
+    reference.push_str(&excel_column_name(col));
+    if absolute_row {
+        reference.push('$');
+    }
+    reference.push_str(&(row + 1).to_string());
+    reference
+}
+
+fn excel_range_reference(
+    sheet: &str,
+    start_row: u32,
+    start_col: u32,
+    end_row: u32,
+    end_col: u32,
+) -> String {
+    let escaped_sheet = sheet.replace('\'', "''");
+    let start = excel_cell_reference(start_row, start_col, true, true);
+    let end = excel_cell_reference(end_row, end_col, true, true);
+    format!("'{}'!{}:{}", escaped_sheet, start, end)
+}
+
+fn default_matches_workbook_name(filename_stem: Option<&str>) -> String {
+    if let Some(stem) = filename_stem {
+        return format!("{stem}.xlsx");
+    }
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    format!("DBBS_matches_{timestamp}.xlsx")
+}
+
+fn default_directory_workbook_name(filename_stem: Option<&str>) -> String {
+    if let Some(stem) = filename_stem {
+        return format!("{stem}.xlsx");
+    }
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    format!("DBBS_directory_matches_{timestamp}.xlsx")
+}
+
+fn default_student_reports_name() -> String {
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    format!("DBBS_student_reports_{timestamp}.zip")
+}
+
+fn filename_pattern_placeholders(pattern: &str) -> Result<Vec<&str>, String> {
+    let mut placeholders = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| "The filename pattern has a '{' without a matching '}'.".to_string())?;
+        placeholders.push(&after[..end]);
+        rest = &after[end + 1..];
+    }
+    if rest.contains('}') {
+        return Err("The filename pattern has a '}' without a matching '{'.".into());
+    }
+    Ok(placeholders)
+}
+
+fn validate_filename_pattern(pattern: &str) -> Result<(), String> {
+    for placeholder in filename_pattern_placeholders(pattern)? {
+        if !FILENAME_PATTERN_PLACEHOLDERS.contains(&placeholder) {
+            let supported = FILENAME_PATTERN_PLACEHOLDERS
+                .iter()
+                .map(|name| format!("{{{name}}}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(format!(
+                "Unknown placeholder '{{{placeholder}}}' in the filename pattern. Supported placeholders are {supported}."
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn render_filename_pattern(
+    pattern: &str,
+    task_type: &TaskType,
+    faculty_scope: &FacultyScope,
+    program_filters: &[String],
+    model: Option<&str>,
+) -> String {
+    let now = Utc::now();
+    let task = match task_type {
+        TaskType::Prompt => "prompt",
+        TaskType::Document => "document",
+        TaskType::Spreadsheet => "spreadsheet",
+        TaskType::Directory => "directory",
+    };
+    let scope = match faculty_scope {
+        FacultyScope::All => "all",
+        FacultyScope::Program => "programs",
+        FacultyScope::Custom => "roster",
+    };
+    let programs = if program_filters.is_empty() {
+        "all-programs".to_string()
+    } else {
+        program_filters.join("-")
+    };
+    let model = model
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+        .unwrap_or(DEFAULT_EMBEDDING_MODEL);
+    let model = model.rsplit('/').next().unwrap_or(model);
+
+    let rendered = pattern
+        .replace("{date}", &now.format("%Y%m%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+        .replace("{task}", task)
+        .replace("{scope}", scope)
+        .replace("{programs}", &programs)
+        .replace("{model}", model);
+    let rendered = ["xlsx", "csv", "tsv"]
+        .iter()
+        .find_map(|extension| {
+            let suffix = format!(".{extension}");
+            rendered
+                .to_ascii_lowercase()
+                .ends_with(&suffix)
+                .then(|| rendered[..rendered.len() - suffix.len()].to_string())
+        })
+        .unwrap_or(rendered);
+
+    let sanitized = sanitize_filename(&rendered);
+    if sanitized.is_empty() {
+        format!("DBBS_matches_{}", now.format("%Y%m%d-%H%M%S"))
+    } else {
+        sanitized
+    }
+}
+
+/// Replaces characters that are invalid in Windows or Unix filenames, caps the
+/// length, and trims leading or trailing dots and whitespace. May return "".
+fn sanitize_filename(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|ch| match ch {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            ch if ch.is_control() => '_',
+            _ => ch,
+        })
+        .take(FILENAME_PATTERN_MAX_CHARS)
+        .collect();
+    sanitized
+        .trim_matches(|ch: char| ch == '.' || ch.is_whitespace())
+        .to_string()
+}
+
+/// Filename segment for a labelled single-student run, so several saved singles in
+/// one folder can be told apart.
+fn student_filename_segment(student_label: Option<&str>) -> String {
+    student_label
+        .map(sanitize_filename)
+        .filter(|label| !label.is_empty())
+        .map(|label| format!("_{label}"))
+        .unwrap_or_default()
+}
+
+fn default_export_bundle_name(student_label: Option<&str>) -> String {
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let student = student_filename_segment(student_label);
+    format!("DBBS_results_bundle{student}_{timestamp}.zip")
+}
+
+fn default_pdf_report_name(student_label: Option<&str>) -> String {
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let student = student_filename_segment(student_label);
+    format!("DBBS_run_report{student}_{timestamp}.pdf")
+}
+
+fn default_results_json_name(student_label: Option<&str>) -> String {
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let student = student_filename_segment(student_label);
+    format!("DBBS_results{student}_{timestamp}.json")
+}
+
+fn format_similarity_percent(value: f32) -> String {
+    if value.is_finite() {
+        format!("{:.1}%", value * 100.0)
+    } else {
+        "n/a".into()
+    }
+}
+
+fn format_raw_cosine(value: f32) -> String {
+    if value.is_finite() {
+        format!("{value:.4}")
+    } else {
+        "n/a".into()
+    }
+}
+
+fn extract_document_prompt(path: &Path) -> Result<DocumentExtractionResult, String> {
+    let data = fs::read(path)
+        .map_err(|err| format!("Unable to read document '{}': {err}", path.display()))?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    extract_document_bytes(&data, extension.as_deref())
+}
+
+fn extract_document_bytes(
+    data: &[u8],
+    extension: Option<&str>,
+) -> Result<DocumentExtractionResult, String> {
+    let mut warnings = Vec::new();
+
+    let raw_text = match extension {
+        Some("txt") => decode_text_bytes(data, &mut warnings),
+        Some("pdf") => extract_pdf_text(data)?,
+        Some("docx") => extract_docx_text(data)?,
+        Some("doc") => extract_doc_text(data, &mut warnings)?,
+        _ => detect_and_extract_unknown_document(data, &mut warnings)?,
+    };
+
+    let normalized = normalize_document_text(&raw_text);
+
+    Ok(DocumentExtractionResult {
+        text: normalized,
+        warnings,
+    })
+}
+
+fn detect_and_extract_unknown_document(
+    data: &[u8],
+    warnings: &mut Vec<String>,
+) -> Result<String, String> {
+    if looks_like_pdf(data) {
+        return extract_pdf_text(data);
+    }
+    if looks_like_docx(data) {
+        return extract_docx_text(data);
+    }
+    if looks_like_rtf(data) {
+        return extract_rtf_text(data);
+    }
+    if std::str::from_utf8(data).is_ok() {
+        return Ok(decode_text_bytes(data, warnings));
+    }
+
+    Err("The selected document format is not supported. Provide a PDF, Word document, or plain text file.".into())
+}
+
+fn extract_doc_text(data: &[u8], warnings: &mut Vec<String>) -> Result<String, String> {
+    if looks_like_docx(data) {
+        return extract_docx_text(data);
+    }
+    if looks_like_pdf(data) {
+        return extract_pdf_text(data);
+    }
+    if looks_like_rtf(data) {
+        return extract_rtf_text(data);
+    }
+
+    warnings.push(
+        "The .doc file was treated as plain text. Save the document as .docx if formatting is important.".into(),
+    );
+    Ok(decode_text_bytes(data, warnings))
+}
+
+fn extract_pdf_text(data: &[u8]) -> Result<String, String> {
+    extract_text_from_mem(data)
+        .map(|text| text.trim().to_string())
+        .map_err(|err| format!("Unable to extract text from the PDF document: {err}"))
+}
+
+fn extract_docx_text(data: &[u8]) -> Result<String, String> {
+    let package =
+        read_docx(data).map_err(|err| format!("Unable to read the DOCX document: {err}"))?;
+    let mut segments = Vec::new();
+
+    for child in &package.document.children {
+        collect_docx_child_text(child, &mut segments);
+    }
+
+    Ok(segments.join("\n"))
+}
+
+fn extract_rtf_text(data: &[u8]) -> Result<String, String> {
+    let content = String::from_utf8_lossy(data);
+    let document = RtfDocument::try_from(content.as_ref())
+        .map_err(|err| format!("Unable to parse the RTF document: {err}"))?;
+    Ok(document.get_text())
+}
+
+fn decode_text_bytes(data: &[u8], warnings: &mut Vec<String>) -> String {
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_string(),
+        Err(_) => {
+            warnings.push(
+                "The document contained invalid UTF-8 characters. Some characters were replaced during decoding.".into(),
+            );
+            String::from_utf8_lossy(data).into_owned()
+        }
+    }
+}
+
+fn normalize_document_text(text: &str) -> String {
+    let mut normalized = text.replace('\u{0000}', "");
+    normalized = normalized.trim_start_matches('\u{FEFF}').to_string();
+    normalized = normalized.replace("\r\n", "\n");
+    normalized = normalized.replace('\r', "\n");
+    normalized = normalize_typography(&normalized);
+
+    // Runs of three or more blank lines (common between PDF pages) collapse to one.
+    let mut lines: Vec<&str> = Vec::new();
+    let mut blank_run = 0usize;
+    for line in normalized.lines().map(|line| line.trim_end()) {
+        if line.is_empty() {
+            blank_run += 1;
+            continue;
+        }
+        let keep_blank = match blank_run {
+            0 => 0,
+            1 | 2 => blank_run,
+            _ => 1,
+        };
+        lines.extend(std::iter::repeat_n("", keep_blank));
+        lines.push(line);
+        blank_run = 0;
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Expands ligatures, straightens quotes and dashes, and drops soft hyphens, all of
+/// which PDF extraction tends to produce and the tokenizer handles poorly.
+fn normalize_typography(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\u{00AD}' => {}
+            '\u{FB00}' => normalized.push_str("ff"),
+            '\u{FB01}' => normalized.push_str("fi"),
+            '\u{FB02}' => normalized.push_str("fl"),
+            '\u{FB03}' => normalized.push_str("ffi"),
+            '\u{FB04}' => normalized.push_str("ffl"),
+            '\u{FB05}' | '\u{FB06}' => normalized.push_str("st"),
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => normalized.push('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => normalized.push('"'),
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => normalized.push('-'),
+            _ => normalized.push(ch),
+        }
+    }
+    normalized
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct PersonalInformationRedactions {
+    emails: usize,
+    phones: usize,
+    names: usize,
+}
+
+impl PersonalInformationRedactions {
+    fn describe(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            (self.emails, "email address", "email addresses"),
+            (self.phones, "phone number", "phone numbers"),
+            (self.names, "name", "names"),
+        ]
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, singular, plural)| {
+            format!("{count} {}", if count == 1 { singular } else { plural })
+        })
+        .collect();
+        match parts.len() {
+            0 => None,
+            1 => Some(parts[0].clone()),
+            _ => {
+                let (last, rest) = parts.split_last()?;
+                Some(format!("{} and {last}", rest.join(", ")))
+            }
+        }
+    }
+}
+
+fn scrub_personal_information_text(
+    text: &str,
+    identifier_values: &[String],
+) -> (String, PersonalInformationRedactions) {
+    let mut redactions = PersonalInformationRedactions::default();
+    let (text, emails) = redact_email_addresses(text);
+    redactions.emails = emails;
+    let (text, phones) = redact_phone_numbers(&text);
+    redactions.phones = phones;
+
+    let name_tokens: HashSet<String> = identifier_values
+        .iter()
+        .filter(|value| !value.contains('@') && !value.chars().any(|ch| ch.is_ascii_digit()))
+        .flat_map(|value| value.split(|ch: char| !ch.is_alphabetic() && ch != '\'' && ch != '-'))
+        .map(|token| {
+            token
+                .trim_matches(|ch| ch == '\'' || ch == '-')
+                .to_lowercase()
+        })
+        .filter(|token| token.chars().count() >= 2)
+        .collect();
+    let (text, names) = redact_names(&text, &name_tokens);
+    redactions.names = names;
+
+    (text, redactions)
+}
+
+fn redact_email_addresses(text: &str) -> (String, usize) {
+    let is_local = |ch: char| ch.is_ascii_alphanumeric() || "._%+-".contains(ch);
+    let is_domain = |ch: char| ch.is_ascii_alphanumeric() || ch == '.' || ch == '-';
+    let mut output = String::with_capacity(text.len());
+    let mut count = 0usize;
+    let mut copied = 0usize;
+
+    for (at, _) in text.match_indices('@') {
+        if at < copied {
+            continue;
+        }
+        let start = text[copied..at]
+            .char_indices()
+            .rev()
+            .take_while(|(_, ch)| is_local(*ch))
+            .last()
+            .map(|(offset, _)| copied + offset)
+            .unwrap_or(at);
+        let domain_length = text[at + 1..]
+            .char_indices()
+            .take_while(|(_, ch)| is_domain(*ch))
+            .last()
+            .map(|(offset, ch)| offset + ch.len_utf8())
+            .unwrap_or(0);
+        let domain = text[at + 1..at + 1 + domain_length].trim_end_matches(['.', '-']);
+        let valid_domain = domain.rsplit_once('.').is_some_and(|(name, tld)| {
+            !name.is_empty() && tld.len() >= 2 && tld.chars().all(|ch| ch.is_ascii_alphabetic())
+        });
+        if start == at || !valid_domain {
+            continue;
+        }
+        output.push_str(&text[copied..start]);
+        output.push_str("[email]");
+        copied = at + 1 + domain.len();
+        count += 1;
+    }
+    output.push_str(&text[copied..]);
+    (output, count)
+}
+
+fn redact_phone_numbers(text: &str) -> (String, usize) {
+    let is_phone_char = |ch: char| ch.is_ascii_digit() || " -.()+".contains(ch);
+    let bytes = text.as_bytes();
+    let mut output = String::with_capacity(text.len());
+    let mut count = 0usize;
+    let mut copied = 0usize;
+    let mut position = 0usize;
+
+    while position < bytes.len() {
+        let starts_run = (bytes[position].is_ascii_digit() || b"+(".contains(&bytes[position]))
+            && (position == 0 || !bytes[position - 1].is_ascii_alphanumeric());
+        if !starts_run {
+            position += 1;
+            continue;
+        }
+        let end = position
+            + text[position..]
+                .find(|ch: char| !is_phone_char(ch))
+                .unwrap_or(text.len() - position);
+        let run = &text[position..end];
+
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        let mut group_start = None;
+        for (offset, ch) in run.char_indices() {
+            match (ch.is_ascii_digit(), group_start) {
+                (true, None) => group_start = Some(offset),
+                (false, Some(start)) => {
+                    groups.push((start, offset));
+                    group_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = group_start {
+            groups.push((start, run.len()));
+        }
+
+        let followed_by_word = text[end..]
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_alphanumeric());
+        let matched = (0..groups.len()).find_map(|first| {
+            let candidate = &groups[first..];
+            let sizes: Vec<usize> = candidate.iter().map(|(start, end)| end - start).collect();
+            let digits: usize = sizes.iter().sum();
+            let valid = (10..=15).contains(&digits)
+                && if sizes.len() == 1 {
+                    digits <= 11
+                } else {
+                    sizes.len() <= 5
+                        && sizes[0] <= 3
+                        && sizes[sizes.len() - 1] == 4
+                        && (3..=4).contains(&sizes[sizes.len() - 2])
+                        && sizes.iter().all(|size| *size <= 4)
+                };
+            valid.then(|| {
+                let mut start = candidate[0].0;
+                let prefix = &run[..start];
+                let opening = prefix.trim_end_matches(' ').len();
+                if prefix[..opening].ends_with(['(', '+']) {
+                    start = opening - 1;
+                }
+                (start, candidate[candidate.len() - 1].1)
+            })
+        });
+
+        match matched {
+            Some((start, stop)) if !followed_by_word || stop < run.len() => {
+                let stop = if run[stop..].starts_with(')') {
+                    stop + 1
+                } else {
+                    stop
+                };
+                output.push_str(&text[copied..position + start]);
+                output.push_str("[phone]");
+                copied = position + stop;
+                count += 1;
+            }
+            _ => {}
+        }
+        position = end.max(position + 1);
+    }
+    output.push_str(&text[copied..]);
+    (output, count)
+}
+
+fn redact_names(text: &str, name_tokens: &HashSet<String>) -> (String, usize) {
+    if name_tokens.is_empty() {
+        return (text.to_string(), 0);
+    }
+
+    #[derive(PartialEq)]
+    enum WordKind {
+        Name,
+        Initial,
+        Other,
+    }
+
+    let mut words: Vec<(usize, usize, WordKind)> = Vec::new();
+    let mut word_start = None;
+    for (offset, ch) in text.char_indices().chain([(text.len(), ' ')]) {
+        let is_word = ch.is_alphabetic() && offset < text.len();
+        match (is_word, word_start) {
+            (true, None) => word_start = Some(offset),
+            (false, Some(start)) => {
+                let word = &text[start..offset];
+                let kind = if name_tokens.contains(&word.to_lowercase()) {
+                    WordKind::Name
+                } else if word.chars().count() == 1 && word.chars().all(char::is_uppercase) {
+                    WordKind::Initial
+                } else {
+                    WordKind::Other
+                };
+                words.push((start, offset, kind));
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    let joined = |previous: &(usize, usize, WordKind), next: &(usize, usize, WordKind)| {
+        let gap = &text[previous.1..next.0];
+        let gap = if previous.2 == WordKind::Initial {
+            gap.strip_prefix('.').unwrap_or(gap)
+        } else {
+            gap.strip_prefix(',').unwrap_or(gap)
+        };
+        !gap.is_empty() && gap.chars().all(|ch| ch == ' ')
+    };
+
+    let mut output = String::with_capacity(text.len());
+    let mut count = 0usize;
+    let mut copied = 0usize;
+    let mut index = 0usize;
+    while index < words.len() {
+        if words[index].2 != WordKind::Name {
+            index += 1;
+            continue;
+        }
+        let mut last_name = index;
+        let mut cursor = index;
+        while cursor + 1 < words.len()
+            && words[cursor + 1].2 != WordKind::Other
+            && joined(&words[cursor], &words[cursor + 1])
+        {
+            cursor += 1;
+            if words[cursor].2 == WordKind::Name {
+                last_name = cursor;
+            }
+        }
+        // Initials written before the name ("M. J. Watson") belong to it too.
+        let mut first = index;
+        while first > 0
+            && words[first - 1].2 == WordKind::Initial
+            && words[first - 1].0 >= copied
+            && joined(&words[first - 1], &words[first])
+        {
+            first -= 1;
+        }
+        output.push_str(&text[copied..words[first].0]);
+        output.push_str("[name]");
+        copied = words[last_name].1;
+        count += 1;
+        index = last_name + 1;
+    }
+    output.push_str(&text[copied..]);
+    (output, count)
+}
+
+fn looks_like_pdf(data: &[u8]) -> bool {
+    data.starts_with(b"%PDF-")
+}
+
+fn looks_like_docx(data: &[u8]) -> bool {
+    data.len() > 4 && data.starts_with(b"PK")
+}
+
+fn looks_like_rtf(data: &[u8]) -> bool {
+    let sample = String::from_utf8_lossy(data);
+    sample
+        .trim_start_matches(|c: char| c.is_ascii_whitespace())
+        .starts_with("{\\rtf")
+}
+
+fn collect_docx_child_text(child: &DocumentChild, segments: &mut Vec<String>) {
+    match child {
+        DocumentChild::Paragraph(paragraph) => {
+            if let Some(text) = collect_docx_paragraph_text(paragraph.as_ref(), segments) {
+                segments.push(text);
+            }
+        }
+        DocumentChild::Table(table) => collect_docx_table_text(table.as_ref(), segments),
+        DocumentChild::StructuredDataTag(tag) => {
+            collect_docx_structured_data_tag(tag.as_ref(), segments)
+        }
+        _ => {}
+    }
+}
+
+fn collect_docx_paragraph_text(
+    paragraph: &Paragraph,
+    segments: &mut Vec<String>,
+) -> Option<String> {
+    let mut buffer = String::new();
+    for child in &paragraph.children {
+        append_paragraph_child_text(child, &mut buffer, segments);
+    }
+
+    let trimmed = buffer.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn collect_docx_table_text(table: &Table, segments: &mut Vec<String>) {
+    for row in &table.rows {
+        let row = match row {
+            TableChild::TableRow(row) => row,
+        };
+
+        for cell in &row.cells {
+            let cell = match cell {
+                TableRowChild::TableCell(cell) => cell,
+            };
+
+            for content in &cell.children {
+                match content {
+                    TableCellContent::Paragraph(paragraph) => {
+                        if let Some(text) = collect_docx_paragraph_text(paragraph, segments) {
+                            segments.push(text);
+                        }
+                    }
+                    TableCellContent::Table(inner) => {
+                        collect_docx_table_text(inner, segments);
+                    }
+                    TableCellContent::StructuredDataTag(tag) => {
+                        collect_docx_structured_data_tag(tag.as_ref(), segments);
+                    }
+                    TableCellContent::TableOfContents(_) => {}
+                }
+            }
+        }
+    }
+}
+
+fn collect_docx_structured_data_tag(tag: &StructuredDataTag, segments: &mut Vec<String>) {
+    let mut buffer = String::new();
+    append_structured_data_tag_text(tag, &mut buffer, segments);
+    let trimmed = buffer.trim();
+    if !trimmed.is_empty() {
+        segments.push(trimmed.to_string());
+    }
+}
+
+fn append_paragraph_child_text(
+    child: &ParagraphChild,
+    buffer: &mut String,
+    segments: &mut Vec<String>,
+) {
+    match child {
+        ParagraphChild::Run(run) => append_run_text(run.as_ref(), buffer),
+        ParagraphChild::Insert(insert) => append_insert_text(insert, buffer),
+        ParagraphChild::Hyperlink(hyperlink) => {
+            for inner in &hyperlink.children {
+                append_paragraph_child_text(inner, buffer, segments);
+            }
+        }
+        ParagraphChild::StructuredDataTag(tag) => {
+            append_structured_data_tag_text(tag.as_ref(), buffer, segments);
+        }
+        ParagraphChild::BookmarkStart(_) | ParagraphChild::BookmarkEnd(_) => {}
+        ParagraphChild::CommentStart(_) | ParagraphChild::CommentEnd(_) => {}
+        ParagraphChild::Delete(_) => {}
+        ParagraphChild::PageNum(_) | ParagraphChild::NumPages(_) => {}
+    }
+}
+
+fn append_insert_text(insert: &Insert, buffer: &mut String) {
+    for child in &insert.children {
+        match child {
+            InsertChild::Run(run) => append_run_text(run.as_ref(), buffer),
+            InsertChild::Delete(_) => {}
+            InsertChild::CommentStart(_) | InsertChild::CommentEnd(_) => {}
+        }
+    }
+}
+
+fn append_structured_data_tag_text(
+    tag: &StructuredDataTag,
+    buffer: &mut String,
+    segments: &mut Vec<String>,
+) {
+    for child in &tag.children {
+        match child {
+            StructuredDataTagChild::Run(run) => append_run_text(run.as_ref(), buffer),
+            StructuredDataTagChild::Paragraph(paragraph) => {
+                if let Some(text) = collect_docx_paragraph_text(paragraph.as_ref(), segments) {
+                    if !buffer.is_empty() && !buffer.ends_with('\n') && !buffer.ends_with(' ') {
+                        buffer.push(' ');
+                    }
+                    buffer.push_str(&text);
+                }
+            }
+            StructuredDataTagChild::Table(table) => {
+                collect_docx_table_text(table.as_ref(), segments)
+            }
+            StructuredDataTagChild::StructuredDataTag(inner) => {
+                append_structured_data_tag_text(inner.as_ref(), buffer, segments);
+            }
+            StructuredDataTagChild::BookmarkStart(_) | StructuredDataTagChild::BookmarkEnd(_) => {}
+            StructuredDataTagChild::CommentStart(_) | StructuredDataTagChild::CommentEnd(_) => {}
+        }
+    }
+}
+
+fn append_run_text(run: &Run, buffer: &mut String) {
+    for child in &run.children {
+        match child {
+            RunChild::Text(text) => buffer.push_str(&text.text),
+            RunChild::Break(_) => buffer.push('\n'),
+            RunChild::Tab(_) | RunChild::PTab(_) => buffer.push('\t'),
+            RunChild::Sym(sym) => {
+                if let Ok(value) = u32::from_str_radix(&sym.char, 16) {
+                    if let Some(ch) = char::from_u32(value) {
+                        buffer.push(ch);
+                    }
+                }
+            }
+            RunChild::InstrTextString(value) => buffer.push_str(value),
+            RunChild::DeleteText(_) => {}
+            RunChild::FieldChar(_) => {}
+            RunChild::Drawing(_) => {}
+            RunChild::Shape(_) => {}
+            RunChild::CommentStart(_) | RunChild::CommentEnd(_) => {}
+            RunChild::FootnoteReference(_) => {}
+            RunChild::Shading(_) => {}
+            RunChild::InstrText(_) => {}
+            RunChild::DeleteInstrText(_) => {}
+        }
+    }
+}
+
+struct RowEmbeddingContext {
+    row_index: usize,
+    text: String,
+    identifiers: HashMap<String, String>,
+}
+
+fn default_progress_phase() -> String {
+    "embedding".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingProgressUpdate {
+    #[serde(default = "default_progress_phase")]
+    phase: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(default)]
+    processed_rows: usize,
+    #[serde(default)]
+    total_rows: usize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_seconds: Option<f64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimated_remaining_seconds: Option<f64>,
+}
+
+fn emit_faculty_embedding_progress(
+    app_handle: &dyn MatchingHost,
+    progress: EmbeddingProgressUpdate,
+) {
+    if let Ok(payload) = serde_json::to_value(progress) {
+        app_handle.emit_progress(FACULTY_EMBEDDING_PROGRESS_EVENT, payload);
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MatchingProgressUpdate {
+    request_id: String,
+    #[serde(flatten)]
+    progress: EmbeddingProgressUpdate,
+}
+
+fn emit_matching_progress(
+    app_handle: &dyn MatchingHost,
+    request_id: &str,
+    progress: EmbeddingProgressUpdate,
+) {
+    let update = MatchingProgressUpdate {
+        request_id: request_id.to_string(),
+        progress,
+    };
+    if let Ok(payload) = serde_json::to_value(update) {
+        app_handle.emit_progress(MATCHING_PROGRESS_EVENT, payload);
+    }
+}
+
+fn emit_matching_phase(
+    app_handle: &dyn MatchingHost,
+    request_id: &str,
+    phase: &str,
+    message: Option<String>,
+) {
+    match (phase, message.as_deref()) {
+        ("failed", Some(error)) => log::error!("Matching request {request_id} failed: {error}"),
+        (_, Some(detail)) => log::info!("Matching request {request_id}: {phase} ({detail})"),
+        (_, None) => log::info!("Matching request {request_id}: {phase}"),
+    }
+    emit_matching_progress(
+        app_handle,
+        request_id,
+        EmbeddingProgressUpdate {
+            phase: phase.into(),
+            message,
+            processed_rows: 0,
+            total_rows: 0,
+            elapsed_seconds: None,
+            estimated_remaining_seconds: None,
+        },
+    );
+}
+
+fn emit_embedding_error(app_handle: &dyn MatchingHost, total_rows: usize, message: &str) {
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "error".into(),
+            message: Some(message.to_string()),
+            processed_rows: 0,
+            total_rows,
+            elapsed_seconds: None,
+            estimated_remaining_seconds: None,
+        },
+    );
+}
+
+fn begin_embedding_refresh(app_handle: &dyn MatchingHost) -> Result<HelperRunGuard, AppError> {
+    begin_helper_activity(HelperActivity::EmbeddingRefresh, None).inspect_err(|err| {
+        if let AppError::Busy { message, .. } = err {
+            emit_faculty_embedding_progress(
+                app_handle,
+                EmbeddingProgressUpdate {
+                    phase: "rejected".into(),
+                    message: Some(message.clone()),
+                    processed_rows: 0,
+                    total_rows: 0,
+                    elapsed_seconds: None,
+                    estimated_remaining_seconds: None,
+                },
+            );
+        }
+    })
+}
+
+#[tauri::command]
+async fn update_faculty_embeddings(app_handle: tauri::AppHandle) -> Result<String, AppError> {
+    let run_guard = begin_embedding_refresh(&app_handle)?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let _run_guard = run_guard;
+        perform_faculty_embedding_refresh(&app_handle)
+    })
+    .await
+    .map_err(|err| format!("Embedding refresh task failed: {err}"))?;
+
+    Ok(result?)
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingImportResult {
+    message: String,
+    model: String,
+    dimension: usize,
+    imported_rows: usize,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingImportMetadata {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    dimension: Option<usize>,
+}
+
+struct ImportedEmbeddingRow {
+    label: String,
+    identifiers: HashMap<String, String>,
+    embedding: Vec<f32>,
+}
+
+struct ImportedEmbeddings {
+    model: Option<String>,
+    dimension: Option<usize>,
+    rows: Vec<ImportedEmbeddingRow>,
+}
+
+#[tauri::command]
+async fn import_faculty_embeddings(
+    app_handle: tauri::AppHandle,
+    path: String,
+) -> Result<EmbeddingImportResult, AppError> {
+    let run_guard = begin_embedding_refresh(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let _run_guard = run_guard;
+        perform_faculty_embedding_import(&app_handle, &path)
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Embedding import task failed: {err}")))?
+}
+
+fn perform_faculty_embedding_import(
+    app_handle: &dyn MatchingHost,
+    path: &str,
+) -> Result<EmbeddingImportResult, AppError> {
+    let source = resolve_existing_path(Some(path.to_string()), false, "Embedding file")
+        .map_err(|err| AppError::invalid_input("path", err))?;
+    let extension = source
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let imported =
+        match extension.as_str() {
+            "json" => read_embedding_index_import(&source)?,
+            "csv" => read_embedding_csv_import(&source)?,
+            "npy" | "npz" => read_embedding_matrix_import(&source)?,
+            _ => return Err(AppError::invalid_input(
+                "path",
+                "Import faculty embeddings from a JSON index, a CSV file, or an NPY/NPZ matrix.",
+            )),
+        };
+
+    let status = build_faculty_dataset_status(app_handle)?;
+    if !status.is_valid {
+        return Err(AppError::MissingDataset {
+            message: status.message.unwrap_or_else(|| {
+                "Provide a valid faculty dataset before importing embeddings.".into()
+            }),
+        });
+    }
+    let analysis = status.analysis.clone().ok_or_else(|| {
+        AppError::from("Run the faculty dataset analysis before importing embeddings.")
+    })?;
+    let dataset_path = dataset_destination(app_handle)?;
+    let (headers, rows) = read_full_spreadsheet(&dataset_path)?;
+    let header_map = build_header_index_map(&headers);
+    let identifier_indexes = indexes_from_labels(&header_map, &analysis.identifier_columns)?;
+
+    let mut warnings = Vec::new();
+    let mut row_errors = Vec::new();
+    let dimension = match imported.dimension {
+        Some(dimension) => dimension,
+        None => imported
+            .rows
+            .first()
+            .map(|row| row.embedding.len())
+            .unwrap_or(0),
+    };
+    if dimension == 0 {
+        return Err(AppError::invalid_input(
+            "path",
+            "The imported file does not contain any embeddings.",
+        ));
+    }
+    let model = match imported.model.filter(|model| !model.trim().is_empty()) {
+        Some(model) => model,
+        None => {
+            let model = configured_embedding_model(app_handle)?;
+            warnings.push(format!(
+                "The imported file does not name its embedding model; assuming {model}."
+            ));
+            model
+        }
+    };
+    let configured_model = configured_embedding_model(app_handle)?;
+    if model != configured_model {
+        warnings.push(format!(
+            "The imported embeddings were generated with {model}, but prompts are embedded with {configured_model}."
+        ));
+    }
+
+    let mut dataset_lookup: HashMap<String, (usize, HashMap<String, String>)> = HashMap::new();
+    for (row_index, row) in rows.iter().enumerate() {
+        let mut identifiers = HashMap::new();
+        for &index in &identifier_indexes {
+            if let Some(value) = row.get(index).map(|value| value.trim()) {
+                if !value.is_empty() {
+                    identifiers
+                        .entry(header_label(&headers, index))
+                        .or_insert_with(|| value.to_string());
+                }
+            }
+        }
+        let key = embedding_import_key(&analysis.identifier_columns, &identifiers);
+        if !key.is_empty() {
+            dataset_lookup
+                .entry(key)
+                .or_insert((row_index, identifiers));
+        }
+    }
+
+    let imported_total = imported.rows.len();
+    let mut entries = Vec::new();
+    let mut seen_faculty_ids = HashSet::new();
+    let mut misaligned = 0usize;
+    for row in imported.rows {
+        if row.embedding.len() != dimension {
+            row_errors.push(format!(
+                "{} has {} values but the import uses dimension {dimension}.",
+                row.label,
+                row.embedding.len()
+            ));
+            misaligned += 1;
+            continue;
+        }
+        if row.embedding.iter().any(|value| !value.is_finite()) {
+            row_errors.push(format!(
+                "{} contains values that are not numbers.",
+                row.label
+            ));
+            misaligned += 1;
+            continue;
+        }
+        let key = embedding_import_key(&analysis.identifier_columns, &row.identifiers);
+        match dataset_lookup.get(&key) {
+            Some((row_index, identifiers)) if seen_faculty_ids.insert(faculty_id_for_key(&key)) => {
+                entries.push(FacultyEmbeddingEntry {
+                    row_index: *row_index,
+                    faculty_id: String::new(),
+                    identifiers: identifiers.clone(),
+                    embedding: row.embedding,
+                    embedding_f16: None,
+                    source: FacultySource::Dataset,
+                    faculty_text: None,
+                });
+            }
+            Some(_) => {
+                row_errors.push(format!(
+                    "{} duplicates a faculty member that was already imported.",
+                    row.label
+                ));
+                misaligned += 1;
+            }
+            None => {
+                row_errors.push(format!(
+                    "{} does not match any faculty in the current dataset.",
+                    row.label
+                ));
+                misaligned += 1;
+            }
+        }
+    }
+
+    let misaligned_percent = if imported_total == 0 {
+        100.0
+    } else {
+        misaligned as f64 * 100.0 / imported_total as f64
+    };
+    if entries.is_empty() || misaligned_percent > EMBEDDING_IMPORT_MISALIGNED_PERCENT {
+        let mut message = format!(
+            "{misaligned} of {imported_total} imported row{plural} could not be aligned with the current faculty dataset.",
+            plural = if imported_total == 1 { "" } else { "s" }
+        );
+        for error in row_errors.iter().take(EMBEDDING_IMPORT_ROW_ERROR_LIMIT) {
+            message.push(' ');
+            message.push_str(error);
+        }
+        return Err(AppError::invalid_input("path", message));
+    }
+
+    let total_rows = rows.len();
+    let embedded_rows = entries.len();
+    if row_errors.len() > EMBEDDING_IMPORT_ROW_ERROR_LIMIT {
+        let remaining = row_errors.len() - EMBEDDING_IMPORT_ROW_ERROR_LIMIT;
+        row_errors.truncate(EMBEDDING_IMPORT_ROW_ERROR_LIMIT);
+        row_errors.push(format!(
+            "{remaining} more imported row{plural} were skipped.",
+            plural = if remaining == 1 { "" } else { "s" }
+        ));
+    }
+    warnings.extend(row_errors);
+    if embedded_rows < total_rows {
+        let missing = total_rows - embedded_rows;
+        warnings.push(format!(
+            "{missing} faculty row{plural} in the dataset have no imported embedding and will not be recommended.",
+            plural = if missing == 1 { "" } else { "s" }
+        ));
+    }
+
+    entries.sort_by_key(|entry| entry.row_index);
+    assign_faculty_ids(&analysis.identifier_columns, &mut entries);
+    let index = FacultyEmbeddingIndex {
+        model,
+        generated_at: Some(Utc::now().to_rfc3339()),
+        dimension,
+        total_rows: Some(total_rows),
+        embedded_rows: Some(embedded_rows),
+        skipped_rows: Some(total_rows - embedded_rows),
+        embedding_columns: analysis.embedding_columns.clone(),
+        identifier_columns: analysis.identifier_columns.clone(),
+        faculty_text_max_characters: None,
+        precision: configured_embedding_precision(app_handle)?,
+        entries,
+    };
+
+    let embeddings_path = dataset_directory(app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
+    ensure_dataset_directory(&embeddings_path)?;
+    let json = serialize_faculty_embedding_index(&index)
+        .map_err(|err| format!("Unable to serialize faculty embeddings: {err}"))?;
+    write_file_atomically(&embeddings_path, &json).map_err(|err| {
+        AppError::io(
+            &embeddings_path,
+            format!("Unable to write faculty embeddings: {err}"),
+        )
+    })?;
+    let _ = write_embedding_index_summary(app_handle);
+    if let Err(err) = store_model_embedding_index(app_handle, &index.model, &json) {
+        log::warn!("{err}");
+    }
+
+    log::info!(
+        "Imported {embedded_rows} faculty embeddings ({}, dimension {dimension}) from {}.",
+        index.model,
+        source.display()
+    );
+    Ok(EmbeddingImportResult {
+        message: format!(
+            "Imported embeddings for {embedded_rows} faculty row{plural} generated with {model}.",
+            plural = if embedded_rows == 1 { "" } else { "s" },
+            model = index.model
+        ),
+        model: index.model,
+        dimension,
+        imported_rows: embedded_rows,
+        warnings,
+    })
+}
+
+fn embedding_import_key(
+    identifier_columns: &[String],
+    identifiers: &HashMap<String, String>,
+) -> String {
+    let normalized: HashMap<String, &String> = identifiers
+        .iter()
+        .map(|(column, value)| (column.trim().to_lowercase(), value))
+        .collect();
+    identifier_columns
+        .iter()
+        .map(|column| {
+            normalized
+                .get(&column.trim().to_lowercase())
+                .map(|value| normalize_identifier_value(value, true))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+        .trim_matches('|')
+        .to_string()
+}
+
+/// Gives every entry that lacks one a stable `faculty_id`: a short hash of its normalized
+/// identifier values, so saved results still join back to faculty after the dataset's rows
+/// are reordered or edited. Entries sharing identifier values get "-2", "-3", … in row order.
+fn assign_faculty_ids(identifier_columns: &[String], entries: &mut [FacultyEmbeddingEntry]) {
+    let mut used: HashSet<String> = entries
+        .iter()
+        .filter(|entry| !entry.faculty_id.is_empty())
+        .map(|entry| entry.faculty_id.clone())
+        .collect();
+    let mut pending: Vec<usize> = (0..entries.len())
+        .filter(|&position| entries[position].faculty_id.is_empty())
+        .collect();
+    pending.sort_by_key(|&position| entries[position].row_index);
+    for position in pending {
+        let entry = &mut entries[position];
+        let key = embedding_import_key(identifier_columns, &entry.identifiers);
+        let base = if key.is_empty() {
+            faculty_id_for_key(&fallback_faculty_label(entry.source, entry.row_index))
+        } else {
+            faculty_id_for_key(&key)
+        };
+        let mut candidate = base.clone();
+        let mut suffix = 2usize;
+        while !used.insert(candidate.clone()) {
+            candidate = format!("{base}-{suffix}");
+            suffix += 1;
+        }
+        entry.faculty_id = candidate;
+    }
+}
+
+fn faculty_id_for_key(key: &str) -> String {
+    format!(
+        "fac-{}",
+        &sha256_hex(key.as_bytes())[..FACULTY_ID_HASH_CHARACTERS]
+    )
+}
+
+fn read_embedding_import_metadata(source: &Path) -> Option<EmbeddingImportMetadata> {
+    let stem = source.file_stem()?.to_string_lossy().into_owned();
+    let path = source.with_file_name(format!("{stem}{EMBEDDING_EXPORT_METADATA_SUFFIX}"));
+    let data = fs::read(path).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn read_embedding_index_import(source: &Path) -> Result<ImportedEmbeddings, String> {
+    let data =
+        fs::read(source).map_err(|err| format!("Unable to read the embedding index: {err}"))?;
+    let index = parse_faculty_embedding_index(&data)
+        .map_err(|err| format!("Unable to parse the embedding index: {err}"))?;
+
+    Ok(ImportedEmbeddings {
+        model: Some(index.model),
+        dimension: Some(index.dimension),
+        rows: index
+            .entries
+            .into_iter()
+            .enumerate()
+            .map(|(position, entry)| ImportedEmbeddingRow {
+                label: format!("Entry {}", position + 1),
+                identifiers: entry.identifiers,
+                embedding: entry.embedding,
+            })
+            .collect(),
+    })
+}
+
+fn read_embedding_csv_import(source: &Path) -> Result<ImportedEmbeddings, String> {
+    let metadata = read_embedding_import_metadata(source);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(source)
+        .map_err(|err| format!("Unable to open the embedding CSV: {err}"))?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| format!("Unable to read the embedding CSV header: {err}"))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+
+    let is_dimension_column = |header: &str| {
+        header
+            .trim()
+            .to_ascii_lowercase()
+            .strip_prefix("dim_")
+            .is_some_and(|suffix| suffix.parse::<usize>().is_ok())
+    };
+    let dimension_indexes: Vec<usize> = (0..headers.len())
+        .filter(|&index| is_dimension_column(&headers[index]))
+        .collect();
+    if dimension_indexes.is_empty() {
+        return Err(
+            "The embedding CSV does not contain any dim_N columns. Export it from the application or match its column layout."
+                .into(),
+        );
+    }
+    let identifier_indexes: Vec<usize> = (0..headers.len())
+        .filter(|&index| !is_dimension_column(&headers[index]))
+        .collect();
+
+    let mut rows = Vec::new();
+    for (position, record) in reader.records().enumerate() {
+        let label = format!("Row {}", position + 2);
+        let record = record.map_err(|err| format!("Unable to read {label}: {err}"))?;
+        let identifiers = identifier_indexes
+            .iter()
+            .filter_map(|&index| {
+                record
+                    .get(index)
+                    .map(|value| (headers[index].trim().to_string(), value.trim().to_string()))
+            })
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        let embedding = dimension_indexes
+            .iter()
+            .filter_map(|&index| record.get(index))
+            .map(|value| value.trim().parse::<f32>().unwrap_or(f32::NAN))
+            .collect();
+        rows.push(ImportedEmbeddingRow {
+            label,
+            identifiers,
+            embedding,
+        });
+    }
+
+    Ok(ImportedEmbeddings {
+        model: metadata
+            .as_ref()
+            .and_then(|metadata| metadata.model.clone()),
+        dimension: metadata
+            .and_then(|metadata| metadata.dimension)
+            .or(Some(dimension_indexes.len())),
+        rows,
+    })
+}
+
+fn read_embedding_matrix_import(source: &Path) -> Result<ImportedEmbeddings, String> {
+    let data =
+        fs::read(source).map_err(|err| format!("Unable to read the embedding matrix: {err}"))?;
+    let is_npz = source
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("npz"));
+    let (row_count, dimension, values) = if is_npz {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data))
+            .map_err(|err| format!("Unable to open the NPZ archive: {err}"))?;
+        let name = archive
+            .file_names()
+            .filter(|name| name.ends_with(".npy"))
+            .min()
+            .map(str::to_string)
+            .ok_or("The NPZ archive does not contain an embedding matrix.")?;
+        let mut matrix = Vec::new();
+        archive
+            .by_name(&name)
+            .and_then(|mut file| file.read_to_end(&mut matrix).map_err(Into::into))
+            .map_err(|err| format!("Unable to read '{name}' from the NPZ archive: {err}"))?;
+        read_npy_matrix(&matrix)?
+    } else {
+        read_npy_matrix(&data)?
+    };
+
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let identifiers_path =
+        source.with_file_name(format!("{stem}{EMBEDDING_EXPORT_IDENTIFIERS_SUFFIX}"));
+    if !identifiers_path.exists() {
+        return Err(format!(
+            "The embedding matrix needs a companion identifiers file named '{}'.",
+            identifiers_path.display()
+        ));
+    }
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(&identifiers_path)
+        .map_err(|err| format!("Unable to open the identifiers CSV: {err}"))?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| format!("Unable to read the identifiers CSV header: {err}"))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    let identifier_rows = reader
+        .records()
+        .map(|record| {
+            record
+                .map(|record| {
+                    headers
+                        .iter()
+                        .zip(record.iter())
+                        .filter(|(_, value)| !value.trim().is_empty())
+                        .map(|(header, value)| (header.clone(), value.trim().to_string()))
+                        .collect::<HashMap<_, _>>()
+                })
+                .map_err(|err| format!("Unable to read the identifiers CSV: {err}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if identifier_rows.len() != row_count {
+        return Err(format!(
+            "The identifiers file lists {} row{} but the embedding matrix has {row_count}.",
+            identifier_rows.len(),
+            if identifier_rows.len() == 1 { "" } else { "s" }
+        ));
+    }
+
+    let metadata = read_embedding_import_metadata(source);
+    if let Some(expected) = metadata.as_ref().and_then(|metadata| metadata.dimension) {
+        if expected != dimension {
+            return Err(format!(
+                "The embedding matrix has {dimension} columns but its metadata declares dimension {expected}."
+            ));
+        }
+    }
+
+    Ok(ImportedEmbeddings {
+        model: metadata.and_then(|metadata| metadata.model),
+        dimension: Some(dimension),
+        rows: identifier_rows
+            .into_iter()
+            .zip(values.chunks(dimension.max(1)))
+            .enumerate()
+            .map(
+                |(position, (identifiers, embedding))| ImportedEmbeddingRow {
+                    label: format!("Matrix row {}", position + 1),
+                    identifiers,
+                    embedding: embedding.to_vec(),
+                },
+            )
+            .collect(),
+    })
+}
+
+fn read_npy_matrix(data: &[u8]) -> Result<(usize, usize, Vec<f32>), String> {
+    let invalid = || "The embedding matrix is not a valid NPY file.".to_string();
+    if data.len() < 10 || !data.starts_with(b"\x93NUMPY") {
+        return Err(invalid());
+    }
+    let (header_length, header_start) = match data[6] {
+        1 => (u16::from_le_bytes([data[8], data[9]]) as usize, 10),
+        2 | 3 if data.len() >= 12 => (
+            u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize,
+            12,
+        ),
+        _ => return Err(invalid()),
+    };
+    let header = data
+        .get(header_start..header_start + header_length)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or_else(invalid)?;
+    let body = &data[header_start + header_length..];
+
+    let value_size = if header.contains("'<f4'") {
+        4
+    } else if header.contains("'<f8'") {
+        8
+    } else {
+        return Err(
+            "The embedding matrix must contain little-endian float32 or float64 values.".into(),
+        );
+    };
+    if header.contains("'fortran_order': True") {
+        return Err("The embedding matrix must be stored in C (row-major) order.".into());
+    }
+    let shape: Vec<usize> = header
+        .split_once("'shape':")
+        .and_then(|(_, rest)| rest.split_once('(').map(|(_, rest)| rest))
+        .and_then(|rest| rest.split_once(')').map(|(shape, _)| shape))
+        .ok_or_else(invalid)?
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<usize>().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let [row_count, dimension] = shape[..] else {
+        return Err("The embedding matrix must be two-dimensional (rows × dimensions).".into());
+    };
+    if body.len() < row_count * dimension * value_size {
+        return Err("The embedding matrix is truncated.".into());
+    }
+
+    let values = body
+        .chunks_exact(value_size)
+        .take(row_count * dimension)
+        .map(|chunk| match chunk {
+            [a, b, c, d] => f32::from_le_bytes([*a, *b, *c, *d]),
+            _ => f64::from_le_bytes(chunk.try_into().unwrap_or_default()) as f32,
+        })
+        .collect();
+    Ok((row_count, dimension, values))
+}
+
+#[tauri::command]
+async fn ensure_embedding_helper_ready(app_handle: tauri::AppHandle) -> Result<bool, AppError> {
+    warmup_embedding_helper(app_handle).await?;
+    Ok(EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst))
+}
+
+fn perform_faculty_embedding_refresh(app_handle: &dyn MatchingHost) -> Result<String, String> {
+    let started_at = Instant::now();
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "starting".into(),
+            message: Some("Preparing to refresh faculty embeddings…".into()),
+            processed_rows: 0,
+            total_rows: 0,
+            elapsed_seconds: Some(0.0),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    let status = build_faculty_dataset_status(app_handle)?;
+
+    if !status.is_valid {
+        let message = status.message.unwrap_or_else(|| {
+            "Provide a valid faculty dataset before refreshing embeddings.".into()
+        });
+        return Err(message);
+    }
+
+    let analysis = status.analysis.clone().ok_or_else(|| {
+        "Run the faculty dataset analysis before generating embeddings.".to_string()
+    })?;
+
+    let dataset_path = dataset_destination(app_handle)?;
+    if !dataset_path.exists() {
+        return Err("No faculty dataset is available. Restore or configure the dataset before generating embeddings.".into());
+    }
+
+    let (headers, rows) = read_full_spreadsheet(&dataset_path)?;
+
+    if rows.is_empty() {
+        return Err("The faculty dataset does not include any data rows to embed.".into());
+    }
+
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "preparing".into(),
+            message: Some(format!(
+                "Scanning {row_count} faculty row{plural} for embedding content…",
+                row_count = rows.len(),
+                plural = if rows.len() == 1 { "" } else { "s" }
+            )),
+            processed_rows: 0,
+            total_rows: rows.len(),
+            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    let header_map = build_header_index_map(&headers);
+    let embedding_indexes = indexes_from_labels(&header_map, &analysis.embedding_columns)?;
+    let identifier_indexes = indexes_from_labels(&header_map, &analysis.identifier_columns)?;
+
+    if embedding_indexes.is_empty() {
+        return Err("No embedding columns were identified for the faculty dataset.".into());
+    }
+
+    let mut contexts = Vec::new();
+    let mut skipped_due_to_text = 0usize;
+
+    for (row_index, row) in rows.iter().enumerate() {
+        let mut text_parts = Vec::new();
+        for &index in &embedding_indexes {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                text_parts.push(trimmed.to_string());
+            }
+        }
+
+        if text_parts.is_empty() {
+            skipped_due_to_text += 1;
+            continue;
+        }
+
+        let text = text_parts.join("\n\n");
+
+        let mut identifiers = HashMap::new();
+        for &index in &identifier_indexes {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                let label = header_label(&headers, index);
+                identifiers
+                    .entry(label)
+                    .or_insert_with(|| trimmed.to_string());
+            }
+        }
+
+        contexts.push(RowEmbeddingContext {
+            row_index,
+            text,
+            identifiers,
+        });
+    }
+
+    if contexts.is_empty() {
+        return Err("None of the faculty rows include embedding content. Add research interest details before refreshing embeddings.".into());
+    }
+
+    let faculty_text_limit = load_app_settings(app_handle)?
+        .faculty_text_max_characters
+        .unwrap_or(FACULTY_TEXT_MAX_CHARACTERS_DEFAULT)
+        .max(FACULTY_TEXT_MAX_CHARACTERS_MIN);
+    let total_contexts = contexts.len();
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "preparing".into(),
+            message: Some(format!(
+                "Prepared {total} faculty row{plural} for embedding.",
+                total = total_contexts,
+                plural = if total_contexts == 1 { "" } else { "s" }
+            )),
+            processed_rows: 0,
+            total_rows: total_contexts,
+            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    let request_payload = EmbeddingRequestPayload {
+        model: configured_embedding_model(app_handle)?,
+        texts: contexts
+            .iter()
+            .map(|context| EmbeddingRequestRow {
+                id: context.row_index,
+                text: context.text.clone(),
+            })
+            .collect(),
+        item_label: Some("faculty row".into()),
+        item_label_plural: Some("faculty rows".into()),
+    };
+
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "embedding".into(),
+            message: Some(format!(
+                "Starting embeddings for {total} faculty row{plural}…",
+                total = total_contexts,
+                plural = if total_contexts == 1 { "" } else { "s" }
+            )),
+            processed_rows: 0,
+            total_rows: total_contexts,
+            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    let response = run_embedding_helper(app_handle, &request_payload)?;
+
+    if response.dimension == 0 || response.rows.is_empty() {
+        return Err("The embedding helper returned an empty result. Verify the Python environment can load the PubMedBERT model.".into());
+    }
+
+    let EmbeddingResponsePayload {
+        model: response_model,
+        dimension: response_dimension,
+        rows: response_rows,
+    } = response;
+
+    let mut embedding_map: HashMap<usize, Vec<f32>> = HashMap::new();
+    let helper_row_count = response_rows.len();
+    for row in response_rows {
+        embedding_map.insert(row.id, row.embedding);
+    }
+
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "processing-results".into(),
+            message: Some("Aligning embeddings with faculty rows…".into()),
+            processed_rows: helper_row_count,
+            total_rows: total_contexts,
+            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    let mut entries = Vec::new();
+    let mut missing_embeddings = 0usize;
+
+    for context in contexts {
+        match embedding_map.remove(&context.row_index) {
+            Some(embedding) => {
+                let faculty_text =
+                    truncate_prompt_text(&context.text, faculty_text_limit).unwrap_or(context.text);
+                entries.push(FacultyEmbeddingEntry {
+                    row_index: context.row_index,
+                    faculty_id: String::new(),
+                    identifiers: context.identifiers,
+                    embedding,
+                    embedding_f16: None,
+                    source: FacultySource::Dataset,
+                    faculty_text: Some(faculty_text),
+                });
+            }
+            None => {
+                missing_embeddings += 1;
+            }
+        }
+    }
+
+    if entries.is_empty() {
+        return Err("No embeddings were generated for the faculty dataset. Confirm the Python helper executed successfully.".into());
+    }
+
+    entries.sort_by_key(|entry| entry.row_index);
+    assign_faculty_ids(&analysis.identifier_columns, &mut entries);
+
+    let total_rows = rows.len();
+    let embedded_rows = entries.len();
+    let skipped_rows = total_rows.saturating_sub(embedded_rows);
+
+    let index = FacultyEmbeddingIndex {
+        model: response_model,
+        generated_at: Some(Utc::now().to_rfc3339()),
+        dimension: response_dimension,
+        total_rows: Some(total_rows),
+        embedded_rows: Some(embedded_rows),
+        skipped_rows: Some(skipped_rows),
+        embedding_columns: analysis.embedding_columns.clone(),
+        identifier_columns: analysis.identifier_columns.clone(),
+        faculty_text_max_characters: Some(faculty_text_limit),
+        precision: configured_embedding_precision(app_handle)?,
+        entries,
+    };
+
+    let embeddings_path = dataset_directory(app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
+    ensure_dataset_directory(&embeddings_path)?;
+
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "saving".into(),
+            message: Some("Saving faculty embedding index…".into()),
+            processed_rows: embedded_rows,
+            total_rows: total_contexts,
+            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    let json = serialize_faculty_embedding_index(&index)
+        .map_err(|err| format!("Unable to serialize faculty embeddings: {err}"))?;
+    write_file_atomically(&embeddings_path, &json)
+        .map_err(|err| format!("Unable to write faculty embeddings: {err}"))?;
+    let _ = write_embedding_index_summary(app_handle);
+    if let Err(err) = store_model_embedding_index(app_handle, &index.model, &json) {
+        log::warn!("{err}");
+    }
+
+    let mut message = format!(
+        "Generated embeddings for {embedded_rows} faculty row{plural} using {model}.",
+        embedded_rows = embedded_rows,
+        plural = if embedded_rows == 1 { "" } else { "s" },
+        model = index.model
+    );
+    log::info!(
+        "Faculty embedding refresh embedded {embedded_rows} of {total_rows} rows with {} in {:.1}s.",
+        index.model,
+        started_at.elapsed().as_secs_f64()
+    );
+
+    if skipped_due_to_text + missing_embeddings > 0 {
+        message.push_str(&format!(
+            " Skipped {count} row{plural} without usable embedding content.",
+            count = skipped_due_to_text + missing_embeddings,
+            plural = if skipped_due_to_text + missing_embeddings == 1 {
+                ""
+            } else {
+                "s"
+            }
+        ));
+    }
+
+    message.push_str(&format!(
+        " Saved the embedding index to {}.",
+        embeddings_path.to_string_lossy()
+    ));
+
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "complete".into(),
+            message: Some(message.clone()),
+            processed_rows: embedded_rows,
+            total_rows: total_contexts,
+            elapsed_seconds: Some(started_at.elapsed().as_secs_f64()),
+            estimated_remaining_seconds: None,
+        },
+    );
+
+    Ok(message)
+}
+
+/// Maps each column's `header_label`, lowercased, to its index. Headers must already
+/// be padded to the widest row so unnamed trailing columns get their "Column N" key.
+fn build_header_index_map(headers: &[String]) -> HashMap<String, usize> {
+    let mut map = HashMap::new();
+    for index in 0..headers.len() {
+        map.entry(header_label(headers, index).to_lowercase())
+            .or_insert(index);
+    }
+    map
+}
+
+fn resolve_column_label(header_map: &HashMap<String, usize>, label: &str) -> Option<usize> {
+    header_map.get(&label.trim().to_lowercase()).copied()
+}
+
+fn indexes_from_labels(
+    header_map: &HashMap<String, usize>,
+    labels: &[String],
+) -> Result<Vec<usize>, String> {
+    let mut indexes = Vec::new();
+
+    for label in labels {
+        if let Some(index) = resolve_column_label(header_map, label) {
+            indexes.push(index);
+        } else {
+            return Err(format!(
+                "The column '{label}' is not available in the faculty dataset. Re-run the dataset analysis before refreshing embeddings."
+            ));
+        }
+    }
+
+    indexes.sort_unstable();
+    indexes.dedup();
+    Ok(indexes)
+}
+
+fn indexes_from_spreadsheet_labels(
+    header_map: &HashMap<String, usize>,
+    labels: &[String],
+) -> Result<Vec<usize>, String> {
+    let mut indexes = Vec::new();
+
+    for label in labels {
+        if let Some(index) = resolve_column_label(header_map, label) {
+            indexes.push(index);
+        } else {
+            return Err(format!(
+                "The column '{label}' is not available in the spreadsheet. Reload the preview and try again."
+            ));
+        }
+    }
+
+    indexes.sort_unstable();
+    indexes.dedup();
+    Ok(indexes)
+}
+
+static EMBEDDING_HELPER: OnceLock<Mutex<EmbeddingHelperManager>> = OnceLock::new();
+static EMBEDDING_HELPER_READY: AtomicBool = AtomicBool::new(false);
+static EMBEDDING_HELPER_WARMUP_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+static ACTIVE_HELPER_RUN: Mutex<Option<ActiveHelperRun>> = Mutex::new(None);
+static LAST_HELPER_ERROR: Mutex<Option<HelperErrorRecord>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HelperErrorRecord {
+    occurred_at: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+}
+
+fn record_helper_error(message: &str, exit_code: Option<i32>) {
+    if let Ok(mut last) = LAST_HELPER_ERROR.lock() {
+        *last = Some(HelperErrorRecord {
+            occurred_at: Utc::now().to_rfc3339(),
+            message: message.to_string(),
+            exit_code,
+        });
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum HelperActivity {
+    Matching,
+    EmbeddingRefresh,
+    FacultySearch,
+}
+
+struct ActiveHelperRun {
+    activity: HelperActivity,
+    request_id: Option<String>,
+}
+
+struct HelperRunGuard;
+
+impl Drop for HelperRunGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_HELPER_RUN.lock() {
+            *active = None;
+        }
+    }
+}
+
+fn begin_helper_activity(
+    activity: HelperActivity,
+    request_id: Option<&str>,
+) -> Result<HelperRunGuard, AppError> {
+    let mut active = ACTIVE_HELPER_RUN
+        .lock()
+        .map_err(|_| AppError::from("Unable to check for in-progress embedding helper work."))?;
+
+    if let Some(current) = active.as_ref() {
+        let message = match current.activity {
+            HelperActivity::Matching => {
+                "A matching run is already in progress. Wait for it to finish and try again."
+            }
+            HelperActivity::EmbeddingRefresh => {
+                "Faculty embeddings are being refreshed. Wait for the refresh to finish and try again."
+            }
+            HelperActivity::FacultySearch => {
+                "A faculty search is already running. Wait for it to finish and try again."
+            }
+        };
+        log::warn!(
+            "Rejected {activity:?} while {:?} is in progress",
+            current.activity
+        );
+        return Err(AppError::Busy {
+            activity: current.activity,
+            request_id: current.request_id.clone(),
+            message: message.to_string(),
+        });
+    }
+
+    *active = Some(ActiveHelperRun {
+        activity,
+        request_id: request_id.map(str::to_string),
+    });
+    Ok(HelperRunGuard)
+}
+
+struct EmbeddingHelperManager {
+    child: Option<CommandChild>,
+    receiver: Option<Receiver<CommandEvent>>,
+    stdout_buffer: Vec<u8>,
+}
+
+impl EmbeddingHelperManager {
+    fn new() -> Self {
+        Self {
+            child: None,
+            receiver: None,
+            stdout_buffer: Vec::new(),
+        }
+    }
+
+    fn ensure_process(&mut self, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        if self.child.is_none() {
+            let (receiver, child) = spawn_embedding_helper(app_handle)?;
+            self.child = Some(child);
+            self.receiver = Some(receiver);
+            self.stdout_buffer.clear();
+        }
+
+        Ok(())
+    }
+
+    fn write_with_newline(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        if let Some(child) = self.child.as_mut() {
+            let mut payload = Vec::with_capacity(bytes.len() + 1);
+            payload.extend_from_slice(bytes);
+            payload.push(b'\n');
+
+            child
+                .write(&payload)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "Embedding helper is not running",
+            ))
+        }
+    }
+
+    fn receive_event(&mut self) -> Result<Option<CommandEvent>, String> {
+        let receiver = match self.receiver.as_mut() {
+            Some(receiver) => receiver,
+            None => return Ok(None),
+        };
+
+        Ok(tauri::async_runtime::block_on(receiver.recv()))
+    }
+
+    fn reset(&mut self) {
+        self.child = None;
+        self.receiver = None;
+        self.stdout_buffer.clear();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            let _ = child.write(b"{\"command\":\"shutdown\"}\n");
+        }
+
+        if let Some(receiver) = self.receiver.as_mut() {
+            while let Some(event) = tauri::async_runtime::block_on(receiver.recv()) {
+                if matches!(event, CommandEvent::Terminated(_)) {
+                    break;
+                }
+            }
+        }
+
+        self.reset();
+    }
+}
+
+fn embedding_helper_manager() -> &'static Mutex<EmbeddingHelperManager> {
+    EMBEDDING_HELPER.get_or_init(|| Mutex::new(EmbeddingHelperManager::new()))
+}
+
+fn embedding_helper_warmup_lock() -> &'static Mutex<()> {
+    EMBEDDING_HELPER_WARMUP_LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn emit_embedding_helper_standby(app_handle: &dyn MatchingHost) {
+    emit_faculty_embedding_progress(
+        app_handle,
+        EmbeddingProgressUpdate {
+            phase: "standby".into(),
+            message: Some(FACULTY_EMBEDDING_STANDBY_MESSAGE.into()),
+            processed_rows: 0,
+            total_rows: 0,
+            elapsed_seconds: None,
+            estimated_remaining_seconds: None,
+        },
+    );
+}
+
+fn perform_embedding_helper_warmup(app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    if EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst) {
+        emit_embedding_helper_standby(&app_handle);
+        return Ok(());
+    }
+
+    let lock = embedding_helper_warmup_lock()
+        .lock()
+        .map_err(|err| format!("Unable to warm up the embedding helper: {err}"))?;
+
+    if EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst) {
+        drop(lock);
+        emit_embedding_helper_standby(&app_handle);
+        return Ok(());
+    }
+
+    let payload = EmbeddingRequestPayload {
+        model: configured_embedding_model(&app_handle)?,
+        texts: vec![EmbeddingRequestRow {
+            id: 0,
+            text: "Embedding helper warm-up text".into(),
+        }],
+        item_label: Some("warm-up text".into()),
+        item_label_plural: Some("warm-up texts".into()),
+    };
+
+    let result = run_embedding_helper(&app_handle, &payload);
+    drop(lock);
+
+    result.and_then(|_| {
+        EMBEDDING_HELPER_READY.store(true, AtomicOrdering::SeqCst);
+        emit_embedding_helper_standby(&app_handle);
+        Ok(())
+    })
+}
+
+async fn warmup_embedding_helper(app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    if EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst) {
+        emit_embedding_helper_standby(&app_handle);
+        return Ok(());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || perform_embedding_helper_warmup(app_handle))
+        .await
+        .map_err(|err| {
+            AppError::helper_failure(format!("Unable to warm up the embedding helper: {err}"))
+        })?
+}
+
+fn shutdown_embedding_helper() {
+    if let Some(lock) = EMBEDDING_HELPER.get() {
+        if let Ok(mut manager) = lock.lock() {
+            manager.shutdown();
+        }
+    }
+    EMBEDDING_HELPER_READY.store(false, AtomicOrdering::SeqCst);
+}
+
+fn run_embedding_helper(
+    app_handle: &dyn MatchingHost,
+    payload: &EmbeddingRequestPayload,
+) -> Result<EmbeddingResponsePayload, AppError> {
+    run_embedding_helper_with_progress(app_handle, payload, None)
+}
+
+fn run_embedding_helper_with_progress(
+    app_handle: &dyn MatchingHost,
+    payload: &EmbeddingRequestPayload,
+    request_id: Option<&str>,
+) -> Result<EmbeddingResponsePayload, AppError> {
+    let store = app_handle.prompt_embeddings();
+    if let Some(response) = store
+        .map(|store| store.replay(payload))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(response);
+    }
+
+    let total_rows = payload.texts.len();
+    let settings = load_app_settings(app_handle)?;
+    let input = serde_json::to_vec(&EmbeddingHelperRequest {
+        payload,
+        batch_size: settings.embedding_batch_size,
+        device: settings.embedding_device.as_deref(),
+    })
+    .map_err(|err| format!("Unable to serialize the embedding request: {err}"))?;
+
+    let output = app_handle.exchange_with_embedding_helper(&input, total_rows, request_id)?;
+
+    let helper_failure = |output: &SidecarOutput, message: String| {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let code = output
+            .termination
+            .as_ref()
+            .and_then(|termination| termination.code);
+        log::error!("Embedding helper request failed: {message}");
+        if !stderr.is_empty() {
+            log::warn!("Embedding helper stderr: {stderr}");
+        }
+        record_helper_error(&message, code);
+        AppError::HelperFailure {
+            code,
+            stderr,
+            message,
+        }
+    };
+
+    if !sidecar_exited_successfully(&output) {
+        let error_message = trimmed_message_or_default(
+            &output.stderr,
+            "The embedding helper exited with an error.",
+        );
+        emit_embedding_error(app_handle, total_rows, &error_message);
+        return Err(helper_failure(&output, error_message));
+    }
+
+    if output.stdout.is_empty() {
+        let error_message = trimmed_message_or_default(
+            &output.stderr,
+            if output.termination.is_some() {
+                "The embedding helper exited before returning a response."
+            } else {
+                "The embedding helper did not produce any output."
+            },
+        );
+        emit_embedding_error(app_handle, total_rows, &error_message);
+        return Err(helper_failure(&output, error_message));
+    }
+
+    match serde_json::from_slice(&output.stdout) {
+        Ok(response) => {
+            if let Some(store) = store {
+                store.record(payload, &response);
+            }
+            Ok(response)
+        }
+        Err(err) => {
+            let stderr_message = String::from_utf8_lossy(&output.stderr);
+            let trimmed = stderr_message.trim();
+            let error_message = if trimmed.is_empty() {
+                format!("Unable to parse embedding helper output: {err}")
+            } else {
+                format!(
+                    "Unable to parse embedding helper output: {err}. Details: {}",
+                    trimmed
+                )
+            };
+            emit_embedding_error(app_handle, total_rows, &error_message);
+            Err(helper_failure(&output, error_message))
+        }
+    }
+}
+
+fn exchange_with_sidecar(
+    app_handle: &tauri::AppHandle,
+    input: &[u8],
+    total_rows: usize,
+    request_id: Option<&str>,
+) -> Result<SidecarOutput, AppError> {
+    let manager_lock = embedding_helper_manager();
+    let mut manager = manager_lock.lock().map_err(|err| {
+        AppError::helper_failure(format!("Unable to lock the embedding helper: {err}"))
+    })?;
+
+    manager
+        .ensure_process(app_handle)
+        .map_err(AppError::helper_failure)?;
+
+    if let Err(err) = manager.write_with_newline(input) {
+        let message = handle_sidecar_write_error(
+            format!("Unable to send data to the embedding helper: {err}"),
+            &mut manager,
+            app_handle,
+            total_rows,
+        );
+        emit_embedding_error(app_handle, total_rows, &message);
+        return Err(AppError::helper_failure(message));
+    }
+
+    match collect_sidecar_output(&mut manager, app_handle, total_rows, true, request_id) {
+        Ok(output) => Ok(output),
+        Err(err) => {
+            emit_embedding_error(app_handle, total_rows, &err);
+            Err(AppError::helper_failure(err))
+        }
+    }
+}
+
+fn handle_sidecar_write_error(
+    base_message: String,
+    manager: &mut EmbeddingHelperManager,
+    app_handle: &tauri::AppHandle,
+    total_rows: usize,
+) -> String {
+    let result = match collect_sidecar_output(manager, app_handle, total_rows, false, None) {
+        Ok(output) => summarize_sidecar_failure(&output, base_message),
+        Err(err) => format!("{base_message}\n\nAdditionally, {err}"),
+    };
+
+    manager.reset();
+    result
+}
+
+struct SidecarOutput {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    termination: Option<TerminatedPayload>,
+}
+
+fn sidecar_exited_successfully(output: &SidecarOutput) -> bool {
+    output.termination.is_none()
+}
+
+fn trim_trailing_newline(buffer: &mut Vec<u8>) {
+    while matches!(buffer.last(), Some(b'\n') | Some(b'\r')) {
+        buffer.pop();
+    }
+}
+
+fn collect_sidecar_output(
+    manager: &mut EmbeddingHelperManager,
+    app_handle: &tauri::AppHandle,
+    total_rows: usize,
+    expect_response: bool,
+    request_id: Option<&str>,
+) -> Result<SidecarOutput, String> {
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    if !expect_response && !manager.stdout_buffer.is_empty() {
+        stdout.extend(manager.stdout_buffer.drain(..));
+    }
+
+    loop {
+        if expect_response {
+            if let Some(position) = manager.stdout_buffer.iter().position(|&byte| byte == b'\n') {
+                stdout.extend(manager.stdout_buffer.drain(..=position));
+                trim_trailing_newline(&mut stdout);
+                return Ok(SidecarOutput {
+                    stdout,
+                    stderr,
+                    termination: None,
+                });
+            }
+        }
+
+        match manager.receive_event()? {
+            Some(CommandEvent::Stdout(bytes)) => {
+                if expect_response {
+                    manager.stdout_buffer.extend_from_slice(&bytes);
+                } else {
+                    stdout.extend_from_slice(&bytes);
+                }
+            }
+            Some(CommandEvent::Stderr(bytes)) => {
+                if !emit_progress_from_line(app_handle, total_rows, &bytes, request_id) {
+                    stderr.extend_from_slice(&bytes);
+                }
+            }
+            Some(CommandEvent::Terminated(payload)) => {
+                log::warn!(
+                    "Embedding helper terminated (code {:?}, signal {:?}).",
+                    payload.code,
+                    payload.signal
+                );
+                if expect_response {
+                    stdout.extend(manager.stdout_buffer.drain(..));
+                    trim_trailing_newline(&mut stdout);
+                }
+                manager.reset();
+                return Ok(SidecarOutput {
+                    stdout,
+                    stderr,
+                    termination: Some(payload),
+                });
+            }
+            Some(CommandEvent::Error(err)) => {
+                manager.reset();
+                return Err(format!("Unable to read embedding helper output: {err}"));
+            }
+            Some(_) => {}
+            None => {
+                manager.reset();
+                if expect_response {
+                    return Err(
+                        "The embedding helper output stream closed unexpectedly.".to_string()
+                    );
+                } else {
+                    trim_trailing_newline(&mut stdout);
+                    return Ok(SidecarOutput {
+                        stdout,
+                        stderr,
+                        termination: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn summarize_sidecar_failure(output: &SidecarOutput, base_message: String) -> String {
+    let mut message = base_message;
+
+    let stderr_text = String::from_utf8_lossy(&output.stderr);
+    let stderr_trimmed = stderr_text.trim_end();
+    if !stderr_trimmed.is_empty() {
+        message = format!("{message}\n\nEmbedding helper stderr:\n{stderr_trimmed}");
+    }
+
+    let stdout_text = String::from_utf8_lossy(&output.stdout);
+    let stdout_trimmed = stdout_text.trim_end();
+    if !stdout_trimmed.is_empty() {
+        message = format!("{message}\n\nEmbedding helper stdout:\n{stdout_trimmed}");
+    }
+
+    if let Some(termination) = &output.termination {
+        let status_summary = match (termination.code, termination.signal) {
+            (Some(code), Some(signal)) => format!("code {code}, signal {signal}"),
+            (Some(code), None) => format!("code {code}"),
+            (None, Some(signal)) => format!("signal {signal}"),
+            (None, None) => "no exit code recorded".to_string(),
+        };
+        message = format!("{message}\n\nEmbedding helper exit status: {status_summary}");
+    }
+
+    message
+}
+
+fn emit_progress_from_line(
+    app_handle: &dyn MatchingHost,
+    total_rows: usize,
+    bytes: &[u8],
+    request_id: Option<&str>,
+) -> bool {
+    let text = String::from_utf8_lossy(bytes);
+    let trimmed = text.trim_end_matches(['\r', '\n']);
+    if let Some(json_str) = trimmed.strip_prefix("PROGRESS ") {
+        match serde_json::from_str::<EmbeddingProgressUpdate>(json_str.trim()) {
+            Ok(mut update) => {
+                if update.total_rows == 0 {
+                    update.total_rows = total_rows;
+                }
+                if let Some(request_id) = request_id {
+                    emit_matching_progress(app_handle, request_id, update.clone());
+                }
+                emit_faculty_embedding_progress(app_handle, update);
+                true
+            }
+            Err(_) => false,
+        }
+    } else {
+        false
+    }
+}
+
+fn trimmed_message_or_default(buffer: &[u8], default_message: &str) -> String {
+    let text = String::from_utf8_lossy(buffer);
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        default_message.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn spawn_embedding_helper(
+    app_handle: &tauri::AppHandle,
+) -> Result<(Receiver<CommandEvent>, CommandChild), String> {
+    log::info!("Launching the embedding helper.");
+    let result = app_handle
+        .shell()
+        .sidecar("embedding-helper")
+        .map_err(|err| format!("Unable to configure the embedding helper: {err}"))?
+        .env("HF_HUB_DISABLE_PROGRESS_BARS", "1")
+        .env("TOKENIZERS_PARALLELISM", "false")
+        .env("PYTHONUTF8", "1")
+        .env("PYTHONUNBUFFERED", "1")
+        .spawn()
+        .map_err(|err| format!("Unable to launch the embedding helper: {err}"));
+    match &result {
+        Ok((_, child)) => log::info!("Embedding helper started (pid {}).", child.pid()),
+        Err(err) => {
+            log::error!("{err}");
+            record_helper_error(err, None);
+        }
+    }
+    result
+}
+
+fn embedding_helper_sidecar_path() -> Option<PathBuf> {
+    let directory = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    Some(directory.join(format!("embedding-helper{}", std::env::consts::EXE_SUFFIX)))
+}
+
+#[tauri::command]
+fn get_faculty_dataset_status(
+    app_handle: tauri::AppHandle,
+    include_preview: Option<bool>,
+    include_analysis: Option<bool>,
+) -> Result<FacultyDatasetStatus, AppError> {
+    Ok(build_faculty_dataset_status_with_options(
+        &app_handle,
+        None,
+        include_preview.unwrap_or(true),
+        include_analysis.unwrap_or(true),
+    )?)
+}
+
+#[tauri::command]
+fn get_embedding_index_status(
+    app_handle: tauri::AppHandle,
+) -> Result<EmbeddingIndexStatus, String> {
+    embedding_index_status(&app_handle)
+}
+
+#[tauri::command]
+fn list_available_programs(
+    app_handle: tauri::AppHandle,
+) -> Result<AvailableProgramsResponse, String> {
+    let Some(metadata) = load_faculty_dataset_metadata(&app_handle)? else {
+        return Ok(AvailableProgramsResponse {
+            metadata_available: false,
+            programs: Vec::new(),
+        });
+    };
+
+    Ok(AvailableProgramsResponse {
+        metadata_available: true,
+        programs: count_faculty_by_program(&metadata),
+    })
+}
+
+#[tauri::command]
+fn preview_faculty_roster(
+    app_handle: tauri::AppHandle,
+    path: String,
+    column_map: Option<HashMap<String, String>>,
+    fuzzy_matching: Option<bool>,
+    match_mode: Option<RosterMatchMode>,
+    email_domain_aliases: Option<HashMap<String, String>>,
+    value_identifier: Option<String>,
+) -> Result<FacultyRosterPreviewResponse, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Provide a TSV, TXT, or Excel file to analyze for the faculty roster.".into());
+    }
+
+    let source = resolve_existing_path(Some(trimmed.to_string()), false, "Faculty roster file")?;
+
+    let metadata = load_faculty_dataset_metadata(&app_handle)?.ok_or_else(|| {
+        "The faculty dataset metadata is unavailable. Refresh the dataset analysis before selecting a roster.".to_string()
+    })?;
+
+    let strip_diacritics = configured_strip_identifier_diacritics(&app_handle)?;
+    let mut warnings = Vec::new();
+    let mut value_list_identifier = None;
+
+    let (mut headers, mut rows) =
+        match read_headerless_roster_values(&source, &metadata, strip_diacritics)? {
+            Some((values, _)) => {
+                let identifier = resolve_roster_value_identifier(
+                    &metadata,
+                    &values,
+                    value_identifier.as_deref(),
+                    strip_diacritics,
+                )?;
+                warnings.push(format!(
+                    "The roster has no header row; each line is treated as a '{identifier}' value."
+                ));
+                value_list_identifier = Some(identifier.clone());
+                (
+                    vec![identifier],
+                    values
+                        .into_iter()
+                        .take(10)
+                        .map(|value| vec![value])
+                        .collect(),
+                )
+            }
+            None => read_spreadsheet_with_limit(&source, Some(10))?,
+        };
+    align_row_lengths(&mut headers, &mut rows);
+    let (suggestions, suggestion_overlaps) = suggest_roster_identifier_columns(
+        &metadata,
+        &headers,
+        &rows,
+        strip_diacritics,
+        &mut warnings,
+    );
+
+    let match_summary = match column_map.filter(|map| !map.is_empty()) {
+        Some(column_map) => {
+            let email_domain_aliases = email_domain_aliases.unwrap_or_default();
+            let outcome = match_roster_against_dataset(
+                &source,
+                &metadata,
+                &RosterMatchOptions {
+                    column_map: &column_map,
+                    fuzzy_matching: fuzzy_matching.unwrap_or(false),
+                    match_mode: match_mode.unwrap_or_default(),
+                    value_identifier: value_list_identifier.as_deref(),
+                    email_domain_aliases: &email_domain_aliases,
+                    strip_diacritics,
+                },
+            )?;
+            warnings.extend(outcome.warnings);
+            Some(outcome.summary)
+        }
+        None => None,
+    };
+
+    let detection = value_list_identifier
+        .is_none()
+        .then(|| spreadsheet_delimiter_detection(&source))
+        .flatten();
+    let preview = SpreadsheetPreview {
+        headers: header_labels(&headers),
+        rows,
+        suggested_prompt_columns: Vec::new(),
+        suggested_identifier_columns: Vec::new(),
+        delimiter: detection.map(|detection| detection.name().into()),
+        delimiter_consistency: detection.map(|detection| detection.consistency),
+    };
+
+    Ok(FacultyRosterPreviewResponse {
+        preview,
+        suggested_identifier_matches: suggestions,
+        suggested_identifier_overlaps: suggestion_overlaps,
+        warnings,
+        match_summary,
+        value_list_identifier,
+    })
+}
+
+/// Suggests a roster column for each dataset identifier: a matching header wins, then
+/// the column whose values overlap the dataset's most, then any email-looking column
+/// for an email identifier.
+fn suggest_roster_identifier_columns(
+    metadata: &FacultyDatasetMetadata,
+    headers: &[String],
+    rows: &[Vec<String>],
+    strip_diacritics: bool,
+    warnings: &mut Vec<String>,
+) -> (HashMap<String, Option<usize>>, HashMap<String, f32>) {
+    let mut suggestions: HashMap<String, Option<usize>> = HashMap::new();
+    let mut suggestion_overlaps: HashMap<String, f32> = HashMap::new();
+
+    if metadata.analysis.identifier_columns.is_empty() {
+        warnings.push("No identifier columns are defined in the active faculty dataset.".into());
+    }
+
+    let header_map = build_header_index_map(headers);
+
+    for identifier in &metadata.analysis.identifier_columns {
+        let normalized_identifier = identifier.trim().to_lowercase();
+        let mut column_index = header_map.get(&normalized_identifier).copied();
+
+        if column_index.is_none() {
+            let normalized_target = normalize_identifier_label(identifier);
+            if !normalized_target.is_empty() {
+                for (candidate_index, header) in headers.iter().enumerate() {
+                    if normalize_identifier_label(header) == normalized_target {
+                        column_index = Some(candidate_index);
+                        break;
+                    }
+                }
+            }
+        }
+
+        if column_index.is_none() {
+            let dataset_keys: HashSet<String> = metadata
+                .memberships
+                .iter()
+                .filter_map(|membership| membership.identifiers.get(identifier))
+                .map(|value| normalize_identifier_value(value, strip_diacritics))
+                .filter(|value| !value.is_empty())
+                .collect();
+            if let Some((index, overlap)) =
+                best_overlapping_roster_column(rows, &dataset_keys, strip_diacritics)
+            {
+                column_index = Some(index);
+                suggestion_overlaps.insert(identifier.clone(), overlap * 100.0);
+            }
+        }
+
+        if column_index.is_none() {
+            let dataset_values = metadata
+                .memberships
+                .iter()
+                .filter_map(|membership| membership.identifiers.get(identifier));
+            if values_look_like_emails(dataset_values) {
+                column_index = (0..headers.len()).find(|&candidate_index| {
+                    values_look_like_emails(rows.iter().filter_map(|row| row.get(candidate_index)))
+                });
+            }
+        }
+
+        if let Some(index) = column_index {
+            suggestions.insert(identifier.clone(), Some(index));
+        } else {
+            warnings.push(format!(
+                "No roster column matched the faculty identifier '{identifier}'.",
+            ));
+            suggestions.insert(identifier.clone(), None);
+        }
+    }
+
+    (suggestions, suggestion_overlaps)
+}
+
+#[tauri::command]
+fn list_roster_mapping_presets(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<RosterMappingPreset>, String> {
+    load_roster_mapping_presets(&app_handle)
+}
+
+#[tauri::command]
+fn save_roster_mapping_preset(
+    app_handle: tauri::AppHandle,
+    name: String,
+    column_map: HashMap<String, String>,
+    match_mode: Option<RosterMatchMode>,
+) -> Result<RosterMappingPreset, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Provide a name for the roster mapping preset.".into());
+    }
+
+    let column_map: HashMap<String, String> = column_map
+        .into_iter()
+        .map(|(identifier, label)| (identifier.trim().to_string(), label.trim().to_string()))
+        .filter(|(identifier, label)| !identifier.is_empty() && !label.is_empty())
+        .collect();
+    if column_map.is_empty() {
+        return Err("Map at least one roster column to a faculty identifier.".into());
+    }
+
+    let mut presets = load_roster_mapping_presets(&app_handle)?;
+    let version = presets
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(&name))
+        .map(|preset| preset.version + 1)
+        .unwrap_or(1);
+    presets.retain(|preset| !preset.name.eq_ignore_ascii_case(&name));
+
+    let preset = RosterMappingPreset {
+        name,
+        version,
+        column_map,
+        match_mode: match_mode.unwrap_or_default(),
+        saved_at: Utc::now().to_rfc3339(),
+    };
+    presets.push(preset.clone());
+    presets.sort_by_key(|preset| preset.name.to_lowercase());
+
+    write_roster_mapping_presets(&app_handle, &presets)?;
+    Ok(preset)
+}
+
+#[tauri::command]
+fn get_app_settings(app_handle: tauri::AppHandle) -> Result<AppSettings, String> {
+    load_app_settings(&app_handle)
+}
+
+#[tauri::command]
+fn set_app_settings(
+    app_handle: tauri::AppHandle,
+    settings: AppSettings,
+) -> Result<AppSettings, String> {
+    let settings = validate_app_settings(settings)?;
+    write_app_settings(&app_handle, &settings)?;
+    log::set_max_level(settings.log_level.level_filter());
+    log::info!("Application settings updated.");
+    Ok(settings)
+}
+
+#[tauri::command]
+fn list_recent_runs(app_handle: tauri::AppHandle) -> Result<Vec<RunHistoryEntry>, String> {
+    let mut entries = load_run_history(&app_handle)?;
+    entries.reverse();
+    Ok(entries)
+}
+
+#[tauri::command]
+fn get_run_artifacts(
+    app_handle: tauri::AppHandle,
+    run_id: String,
+) -> Result<Vec<GeneratedSpreadsheet>, String> {
+    let run_id = run_id.trim();
+    if !load_run_history(&app_handle)?
+        .iter()
+        .any(|entry| entry.run_id == run_id)
+    {
+        return Err(format!("No saved run matches '{run_id}'."));
+    }
+
+    let path = run_history_directory(&app_handle)?.join(format!("{run_id}.json"));
+    let data =
+        fs::read(&path).map_err(|err| format!("Unable to read the saved run artifacts: {err}"))?;
+    serde_json::from_slice(&data)
+        .map_err(|err| format!("Unable to parse the saved run artifacts: {err}"))
+}
+
+#[tauri::command]
+async fn rerank_run(
+    app_handle: tauri::AppHandle,
+    run_id: String,
+    parameters: RerankParameters,
+) -> Result<SubmissionResponse, AppError> {
+    let request_id = Uuid::new_v4().to_string();
+    let run_guard = begin_helper_activity(HelperActivity::Matching, Some(&request_id))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let _run_guard = run_guard;
+        let result = perform_rerank(&app_handle, &run_id, parameters, &request_id);
+        match &result {
+            Ok(_) => emit_matching_phase(&app_handle, &request_id, "complete", None),
+            Err(err) => {
+                emit_matching_phase(&app_handle, &request_id, "failed", Some(err.to_string()))
+            }
+        }
+        result
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Re-ranking task failed: {err}")))?
+}
+
+fn perform_rerank(
+    app_handle: &dyn MatchingHost,
+    run_id: &str,
+    parameters: RerankParameters,
+    request_id: &str,
+) -> Result<SubmissionResponse, AppError> {
+    let run_id = run_id.trim();
+    if !load_run_history(app_handle)?
+        .iter()
+        .any(|entry| entry.run_id == run_id)
+    {
+        return Err(AppError::invalid_input(
+            "runId",
+            format!("No saved run matches '{run_id}'."),
+        ));
+    }
+
+    let path = run_history_directory(app_handle)?.join(format!("{run_id}{RUN_EMBEDDINGS_SUFFIX}"));
+    if !path.exists() {
+        return Err(AppError::MissingEmbeddings {
+            message: "This run did not save its prompt embeddings, so it must be submitted again to change its parameters.".into(),
+        });
+    }
+    let data = fs::read(&path).map_err(|err| {
+        AppError::io(
+            &path,
+            format!("Unable to read the saved prompt embeddings: {err}"),
+        )
+    })?;
+    let saved: SavedRunEmbeddings = serde_json::from_slice(&data)
+        .map_err(|err| format!("Unable to parse the saved prompt embeddings: {err}"))?;
+
+    if let Ok(index) = load_faculty_embedding_index(app_handle) {
+        if index.model != saved.model || index.dimension != saved.dimension {
+            return Err(AppError::StaleEmbeddings {
+                message: format!(
+                    "The saved run was embedded with {} ({} dimensions) but the faculty embeddings now use {} ({} dimensions). Submit the run again to re-embed it.",
+                    saved.model, saved.dimension, index.model, index.dimension
+                ),
+            });
+        }
+    }
+
+    let mut payload = saved.payload;
+    if parameters.faculty_recs_per_student.is_some() {
+        payload.faculty_recs_per_student = parameters.faculty_recs_per_student;
+    }
+    if parameters.similarity_low_threshold.is_some() {
+        payload.similarity_low_threshold = parameters.similarity_low_threshold;
+    }
+    if parameters.similarity_high_threshold.is_some() {
+        payload.similarity_high_threshold = parameters.similarity_high_threshold;
+    }
+    if parameters.faculty_scope.is_some() {
+        payload.faculty_scope = parameters.faculty_scope;
+    }
+    if let Some(program_filters) = parameters.program_filters {
+        payload.program_filters = program_filters;
+    }
+    payload.output_path = parameters.output_path;
+    payload.overwrite_output = parameters.overwrite_output;
+
+    let embeddings = saved
+        .embeddings
+        .into_iter()
+        .map(|entry| (entry.text, entry.embedding))
+        .collect();
+    let host = PromptEmbeddingHost::new(app_handle, PromptEmbeddingMode::Replay, embeddings);
+    log::info!("Re-ranking saved run {run_id} as request {request_id}.");
+    perform_matching_request(&host, payload, request_id)
+}
+
+#[tauri::command]
+fn preview_faculty_dataset_replacement(
+    path: String,
+) -> Result<FacultyDatasetPreviewResponse, AppError> {
+    let (source, _) = resolve_faculty_dataset_source(&path)?;
+
+    let preview = build_dataset_preview(&source)?;
+    let program_columns = suggest_program_columns(&preview.headers, &preview.rows);
+
+    Ok(FacultyDatasetPreviewResponse {
+        suggested_embedding_columns: preview.suggested_prompt_columns.clone(),
+        suggested_identifier_columns: preview.suggested_identifier_columns.clone(),
+        suggested_program_columns: program_columns,
+        preview,
+    })
+}
+
+#[tauri::command]
+fn replace_faculty_dataset(
+    app_handle: tauri::AppHandle,
+    path: String,
+    configuration: Option<FacultyDatasetColumnConfiguration>,
+) -> Result<FacultyDatasetStatus, AppError> {
+    import_faculty_dataset_file(
+        &app_handle,
+        &path,
+        configuration.as_ref(),
+        "Faculty dataset replaced successfully.",
+    )
+}
+
+#[tauri::command]
+fn reimport_faculty_dataset_source(
+    app_handle: tauri::AppHandle,
+    configuration: Option<FacultyDatasetColumnConfiguration>,
+) -> Result<FacultyDatasetStatus, AppError> {
+    let path = read_faculty_dataset_source_path(&app_handle)?.ok_or_else(|| {
+        AppError::invalid_input(
+            "path",
+            "The current faculty dataset has no recorded source file to re-import.",
+        )
+    })?;
+    import_faculty_dataset_file(
+        &app_handle,
+        &path,
+        configuration.as_ref(),
+        "Faculty dataset re-imported from its source file.",
+    )
+}
+
+fn import_faculty_dataset_file(
+    app_handle: &tauri::AppHandle,
+    path: &str,
+    configuration: Option<&FacultyDatasetColumnConfiguration>,
+    success_message: &str,
+) -> Result<FacultyDatasetStatus, AppError> {
+    let (source, extension) = resolve_faculty_dataset_source(path)?;
+
+    let destination = dataset_destination_for_extension(app_handle, &extension)?;
+    ensure_dataset_directory(&destination)?;
+    if let Some(directory) = destination.parent() {
+        remove_other_dataset_variants(directory, &extension)?;
+    }
+    log::info!(
+        "Replacing the faculty dataset with {}.",
+        source.to_string_lossy()
+    );
+    fs::copy(&source, &destination).map_err(|err| {
+        AppError::io(
+            &source,
+            format!("Unable to replace the faculty dataset: {err}"),
+        )
+    })?;
+
+    write_faculty_dataset_source_path(app_handle, &source)?;
+
+    let mut status = build_faculty_dataset_status_with_overrides(app_handle, configuration)?;
+    if status.message.is_none() {
+        status.message = Some(success_message.into());
+        status.message_variant = Some("success".into());
+    } else if status.message_variant.is_none() {
+        status.message_variant = Some(if status.is_valid {
+            "success".into()
+        } else {
+            "error".into()
+        });
+    }
+
+    Ok(status)
+}
+
+#[tauri::command]
+fn restore_default_faculty_dataset(
+    app_handle: tauri::AppHandle,
+) -> Result<FacultyDatasetStatus, AppError> {
+    let destination =
+        dataset_destination_for_extension(&app_handle, FACULTY_DATASET_DEFAULT_EXTENSION)?;
+    log::info!("Restoring the packaged default faculty dataset.");
+    ensure_default_faculty_dataset(&app_handle, &destination)?;
+
+    let mut status = build_faculty_dataset_status(&app_handle)?;
+    if status.message.is_none() {
+        status.message = Some("Faculty dataset reset to the packaged default.".into());
+        status.message_variant = Some("success".into());
+    } else if status.message_variant.is_none() {
+        status.message_variant = Some(if status.is_valid {
+            "success".into()
+        } else {
+            "error".into()
+        });
+    }
+
+    Ok(status)
+}
+
+fn resolve_faculty_dataset_source(path: &str) -> Result<(PathBuf, String), AppError> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err(AppError::invalid_input(
+            "path",
+            "Select a TSV, TXT, or Excel file to import for the faculty dataset.",
+        ));
+    }
+
+    let source = resolve_existing_path(Some(trimmed.to_string()), false, "Faculty dataset file")
+        .map_err(|message| AppError::invalid_input("path", message))?;
+
+    let extension = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if !FACULTY_DATASET_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(AppError::invalid_input(
+            "path",
+            "Select a tab-delimited .tsv or .txt file, or an Excel workbook (.xlsx or .xls) to replace the faculty dataset.",
+        ));
+    }
+
+    Ok((source, extension))
+}
+
+fn ensure_default_faculty_dataset(
+    app_handle: &dyn MatchingHost,
+    destination: &Path,
+) -> Result<(), String> {
+    ensure_dataset_directory(destination)?;
+    if let Some(directory) = destination.parent() {
+        remove_other_dataset_variants(directory, FACULTY_DATASET_DEFAULT_EXTENSION)?;
+    }
+    fs::write(destination, DEFAULT_FACULTY_DATASET)
+        .map_err(|err| format!("Unable to restore the default faculty dataset: {err}"))?;
+
+    let embeddings_path = dataset_directory(app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
+    ensure_dataset_directory(&embeddings_path)?;
+    write_file_atomically(&embeddings_path, DEFAULT_FACULTY_EMBEDDINGS)
+        .map_err(|err| format!("Unable to restore the default faculty embeddings: {err}"))?;
+    let _ = write_embedding_index_summary(app_handle);
+
+    let _ = clear_faculty_dataset_source_path(app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+fn save_generated_spreadsheet(
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    overwrite: Option<bool>,
+    create_directories: Option<bool>,
+) -> Result<SavedFileResult, AppError> {
+    let data = match encoding
+        .as_deref()
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("base64") => {
+            let data = Base64Engine
+                .decode(content.as_bytes())
+                .map_err(|err| format!("Unable to decode the generated spreadsheet: {err}"))?;
+            if data.is_empty() {
+                return Err("The generated spreadsheet is empty and was not saved.".into());
+            }
+            data
+        }
+        _ => content.into_bytes(),
+    };
+
+    let destination = resolve_save_destination(&path, create_directories.unwrap_or(false))?;
+    ensure_save_target_available(&destination, overwrite.unwrap_or(false))?;
+
+    write_file_atomically(&destination, &data)
+        .map_err(|err| format!("Unable to save the generated spreadsheet: {err}"))?;
+
+    let canonical = fs::canonicalize(&destination).unwrap_or(destination);
+    Ok(SavedFileResult {
+        path: canonical.to_string_lossy().into_owned(),
+        bytes: data.len() as u64,
+    })
+}
+
+/// Refuses to replace an existing file unless the caller allowed overwriting.
+fn ensure_save_target_available(destination: &Path, overwrite: bool) -> Result<(), AppError> {
+    if !overwrite && destination.exists() {
+        return Err(AppError::file_exists(destination));
+    }
+    Ok(())
+}
+
+/// A file written next to a run's output path, named after its stem.
+fn derived_output_path(destination: &Path, suffix: &str) -> PathBuf {
+    let stem = destination
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("DBBS_matches");
+    destination.with_file_name(format!("{stem}{suffix}"))
+}
+
+fn resolve_save_destination(path: &str, create_directories: bool) -> Result<PathBuf, String> {
+    let trimmed = trim_pasted_path(path);
+    if trimmed.is_empty() {
+        return Err("Select a location to save the generated spreadsheet.".into());
+    }
+
+    let destination = expand_user_path(trimmed);
+    if destination.is_dir() {
+        return Err("The selected location is a directory; choose a file name.".into());
+    }
+    if let Some(parent) = destination
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        if !parent.exists() {
+            if !create_directories {
+                return Err("The selected directory does not exist.".into());
+            }
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Unable to create the selected directory: {err}"))?;
+        }
+    }
+    Ok(destination)
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingExportMetadata {
+    model: String,
+    dimension: usize,
+    rows: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generated_at: Option<String>,
+    exported_at: String,
+    format: EmbeddingExportFormat,
+    identifier_columns: Vec<String>,
+    files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingExportResult {
+    files: Vec<SavedFileResult>,
+    rows: usize,
+    dimension: usize,
+    skipped_rows: usize,
+}
+
+#[tauri::command]
+async fn export_faculty_embeddings(
+    app_handle: tauri::AppHandle,
+    path: String,
+    format: EmbeddingExportFormat,
+    overwrite: Option<bool>,
+    create_directories: Option<bool>,
+) -> Result<EmbeddingExportResult, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        perform_embedding_export(
+            &app_handle,
+            &path,
+            format,
+            overwrite.unwrap_or(false),
+            create_directories.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|err| format!("Embedding export task failed: {err}"))?
+}
+
+fn perform_embedding_export(
+    app_handle: &dyn MatchingHost,
+    path: &str,
+    format: EmbeddingExportFormat,
+    overwrite: bool,
+    create_directories: bool,
+) -> Result<EmbeddingExportResult, AppError> {
+    let destination = resolve_save_destination(path, create_directories)?;
+    let destination = match format {
+        EmbeddingExportFormat::Csv => destination,
+        EmbeddingExportFormat::Npy => destination.with_extension("npy"),
+    };
+    let stem = destination
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "faculty_embeddings".into());
+    let identifiers_path =
+        destination.with_file_name(format!("{stem}{EMBEDDING_EXPORT_IDENTIFIERS_SUFFIX}"));
+    let metadata_path =
+        destination.with_file_name(format!("{stem}{EMBEDDING_EXPORT_METADATA_SUFFIX}"));
+
+    let mut targets = vec![destination.clone()];
+    if format == EmbeddingExportFormat::Npy {
+        targets.push(identifiers_path.clone());
+    }
+    targets.push(metadata_path.clone());
+    for target in &targets {
+        ensure_save_target_available(target, overwrite)?;
+    }
+
+    let index = load_faculty_embedding_index(app_handle)?;
+    let dimension = index.dimension;
+    let entries: Vec<&FacultyEmbeddingEntry> = index
+        .entries
+        .iter()
+        .filter(|entry| entry.embedding.len() == dimension)
+        .collect();
+    let skipped_rows = index.entries.len() - entries.len();
+    if entries.is_empty() {
+        return Err("The faculty embeddings do not contain any rows to export.".into());
+    }
+
+    let identifier_columns = if index.identifier_columns.is_empty() {
+        entries
+            .iter()
+            .flat_map(|entry| entry.identifiers.keys().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    } else {
+        index.identifier_columns.clone()
+    };
+    let identifier_values = |entry: &FacultyEmbeddingEntry| -> Vec<String> {
+        identifier_columns
+            .iter()
+            .map(|column| entry.identifiers.get(column).cloned().unwrap_or_default())
+            .collect()
+    };
+
+    match format {
+        EmbeddingExportFormat::Csv => {
+            write_file_atomically_with(&destination, |writer| {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                let mut headers = identifier_columns.clone();
+                headers.extend((0..dimension).map(|index| format!("dim_{index}")));
+                csv_writer.write_record(&headers)?;
+                for entry in &entries {
+                    let mut record = identifier_values(entry);
+                    record.extend(entry.embedding.iter().map(|value| value.to_string()));
+                    csv_writer.write_record(&record)?;
+                }
+                csv_writer.flush()
+            })
+            .map_err(|err| format!("Unable to save the faculty embeddings: {err}"))?;
+        }
+        EmbeddingExportFormat::Npy => {
+            write_file_atomically_with(&destination, |writer| {
+                writer.write_all(&npy_header(entries.len(), dimension))?;
+                for entry in &entries {
+                    for value in &entry.embedding {
+                        writer.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(|err| format!("Unable to save the faculty embeddings: {err}"))?;
+            write_file_atomically_with(&identifiers_path, |writer| {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                csv_writer.write_record(&identifier_columns)?;
+                for entry in &entries {
+                    csv_writer.write_record(identifier_values(entry))?;
+                }
+                csv_writer.flush()
+            })
+            .map_err(|err| format!("Unable to save the faculty identifiers: {err}"))?;
+        }
+    }
+
+    let data_files: Vec<&PathBuf> = targets
+        .iter()
+        .filter(|target| **target != metadata_path)
+        .collect();
+    let metadata = EmbeddingExportMetadata {
+        model: index.model.clone(),
+        dimension,
+        rows: entries.len(),
+        generated_at: index.generated_at.clone(),
+        exported_at: Utc::now().to_rfc3339(),
+        format,
+        identifier_columns,
+        files: data_files
+            .iter()
+            .filter_map(|target| target.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect(),
+    };
+    let metadata_json = serde_json::to_vec_pretty(&metadata)
+        .map_err(|err| format!("Unable to serialize the export metadata: {err}"))?;
+    write_file_atomically(&metadata_path, &metadata_json)
+        .map_err(|err| format!("Unable to save the export metadata: {err}"))?;
+
+    let files = targets
+        .into_iter()
+        .map(|target| {
+            let bytes = fs::metadata(&target).map(|meta| meta.len()).unwrap_or(0);
+            let canonical = fs::canonicalize(&target).unwrap_or(target);
+            SavedFileResult {
+                path: canonical.to_string_lossy().into_owned(),
+                bytes,
+            }
+        })
+        .collect();
+
+    log::info!(
+        "Exported {} faculty embeddings to {}.",
+        entries.len(),
+        destination.display()
+    );
+    Ok(EmbeddingExportResult {
+        files,
+        rows: entries.len(),
+        dimension,
+        skipped_rows,
+    })
+}
+
+fn npy_header(rows: usize, columns: usize) -> Vec<u8> {
+    let mut dictionary =
+        format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({rows}, {columns}), }}");
+    let unpadded = 10 + dictionary.len() + 1;
+    dictionary.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    dictionary.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&(dictionary.len() as u16).to_le_bytes());
+    header.extend_from_slice(dictionary.as_bytes());
+    header
+}
+
+#[tauri::command]
+fn analyze_spreadsheet(path: String) -> Result<SpreadsheetPreview, String> {
+    if path.trim().is_empty() {
+        return Err("Provide a spreadsheet path to analyze.".into());
+    }
+
+    let spreadsheet = resolve_existing_path(Some(path), false, "Spreadsheet file")?;
+    build_dataset_preview(&spreadsheet)
+}
+
+fn normalize_programs(programs: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut cleaned = Vec::new();
+
+    for program in programs {
+        let trimmed = program.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let key = trimmed.to_lowercase();
+        if seen.insert(key) {
+            cleaned.push(trimmed.to_string());
+        }
+    }
+
+    cleaned
+}
+
+fn normalize_columns(columns: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut cleaned = Vec::new();
+
+    for column in columns {
+        let trimmed = column.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let key = trimmed.to_lowercase();
+        if seen.insert(key) {
+            cleaned.push(trimmed.to_string());
+        }
+    }
+
+    cleaned
+}
+
+fn values_look_like_emails<'a>(values: impl Iterator<Item = &'a String>) -> bool {
+    let mut non_empty = 0usize;
+    let mut with_at = 0usize;
+    for value in values {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        non_empty += 1;
+        if trimmed.contains('@') {
+            with_at += 1;
+        }
+    }
+
+    non_empty > 0 && with_at * 2 > non_empty
+}
+
+/// Names are the only identifiers worth matching loosely: emails and ID numbers that
+/// differ by one character or share an initial belong to different people.
+fn values_look_like_names<'a>(values: impl Iterator<Item = &'a String>) -> bool {
+    let mut non_empty = 0usize;
+    let mut name_like = 0usize;
+    for value in values {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        non_empty += 1;
+        if looks_like_name_value(trimmed) {
+            name_like += 1;
+        }
+    }
+
+    non_empty > 0 && name_like * 2 > non_empty
+}
+
+fn looks_like_name_value(value: &str) -> bool {
+    !value.contains('@')
+        && !value.chars().any(|ch| ch.is_ascii_digit())
+        && value.chars().any(char::is_alphabetic)
+}
+
+const COMMON_NICKNAMES: &[(&str, &str)] = &[
+    ("al", "albert"),
+    ("alex", "alexander"),
+    ("andy", "andrew"),
+    ("ben", "benjamin"),
+    ("beth", "elizabeth"),
+    ("bill", "william"),
+    ("bob", "robert"),
+    ("bobby", "robert"),
+    ("chris", "christopher"),
+    ("dan", "daniel"),
+    ("dave", "david"),
+    ("dick", "richard"),
+    ("ed", "edward"),
+    ("greg", "gregory"),
+    ("jeff", "jeffrey"),
+    ("jen", "jennifer"),
+    ("jenny", "jennifer"),
+    ("jim", "james"),
+    ("jimmy", "james"),
+    ("joe", "joseph"),
+    ("kate", "katherine"),
+    ("katie", "katherine"),
+    ("larry", "lawrence"),
+    ("liz", "elizabeth"),
+    ("matt", "matthew"),
+    ("meg", "margaret"),
+    ("mike", "michael"),
+    ("nick", "nicholas"),
+    ("peggy", "margaret"),
+    ("rick", "richard"),
+    ("rob", "robert"),
+    ("sam", "samuel"),
+    ("steve", "steven"),
+    ("sue", "susan"),
+    ("ted", "edward"),
+    ("tom", "thomas"),
+    ("tony", "anthony"),
+    ("will", "william"),
+];
+
+const NAME_SUFFIXES: &[&str] = &["jr", "sr", "ii", "iii", "iv", "phd", "md"];
+
+enum FuzzyRosterMatch {
+    Matched {
+        row_index: usize,
+        dataset_value: String,
+        method: String,
+    },
+    Ambiguous {
+        candidates: Vec<String>,
+    },
+    NoMatch,
+}
+
+struct FuzzyRosterEntry {
+    row_index: usize,
+    display: String,
+    exact_key: String,
+    folded: String,
+    initial_key: String,
+}
+
+/// Loose lookup over name columns only. Any other mapped identifier (emails, IDs)
+/// still has to match exactly, so a near-miss on them never picks a different person.
+struct FuzzyRosterIndex<'a> {
+    entries: Vec<FuzzyRosterEntry>,
+    identifier_order: &'a [String],
+    name_identifiers: &'a HashSet<String>,
+    exact_value: &'a dyn Fn(&str, &str) -> String,
+}
+
+impl<'a> FuzzyRosterIndex<'a> {
+    fn new(
+        memberships: &[FacultyProgramMembership],
+        identifier_order: &'a [String],
+        name_identifiers: &'a HashSet<String>,
+        exact_value: &'a dyn Fn(&str, &str) -> String,
+    ) -> Self {
+        let mut index = Self {
+            entries: Vec::new(),
+            identifier_order,
+            name_identifiers,
+            exact_value,
+        };
+
+        for membership in memberships {
+            let values: Option<Vec<&String>> = identifier_order
+                .iter()
+                .map(|identifier| {
+                    membership
+                        .identifiers
+                        .get(identifier)
+                        .filter(|value| !value.trim().is_empty())
+                })
+                .collect();
+
+            if let Some(values) = values {
+                let Some((exact_key, folded)) = index.split_values(&values) else {
+                    continue;
+                };
+                index.entries.push(FuzzyRosterEntry {
+                    row_index: membership.row_index,
+                    display: values
+                        .iter()
+                        .map(|value| value.trim())
+                        .collect::<Vec<_>>()
+                        .join(" / "),
+                    exact_key,
+                    initial_key: build_initial_key(&folded),
+                    folded: folded.join("|"),
+                });
+            }
+        }
+
+        index
+    }
+
+    /// Splits values (in identifier order) into the exact key over non-name columns
+    /// and the folded name values. Returns None when a name value is blank or looks
+    /// like an email or ID, which must never be matched loosely.
+    fn split_values<S: AsRef<str>>(&self, values: &[S]) -> Option<(String, Vec<String>)> {
+        let mut exact = Vec::new();
+        let mut folded = Vec::new();
+        for (identifier, value) in self.identifier_order.iter().zip(values) {
+            let value = value.as_ref();
+            if self.name_identifiers.contains(identifier) {
+                if !looks_like_name_value(value.trim()) {
+                    return None;
+                }
+                let value = fold_identifier_value(value);
+                if value.is_empty() {
+                    return None;
+                }
+                folded.push(value);
+            } else {
+                exact.push((self.exact_value)(identifier, value));
+            }
+        }
+        (!folded.is_empty()).then(|| (exact.join("|"), folded))
+    }
+
+    fn resolve(&self, values: &[String]) -> FuzzyRosterMatch {
+        let Some((exact_key, folded)) = self.split_values(values) else {
+            return FuzzyRosterMatch::NoMatch;
+        };
+        let entries: Vec<&FuzzyRosterEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.exact_key == exact_key)
+            .collect();
+
+        let folded_key = folded.join("|");
+        let candidates: Vec<&FuzzyRosterEntry> = entries
+            .iter()
+            .copied()
+            .filter(|entry| entry.folded == folded_key)
+            .collect();
+        if !candidates.is_empty() {
+            return Self::decide(candidates, "ignoring accents and punctuation".into());
+        }
+
+        let initial_key = build_initial_key(&folded);
+        if !initial_key.is_empty() {
+            let candidates: Vec<&FuzzyRosterEntry> = entries
+                .iter()
+                .copied()
+                .filter(|entry| entry.initial_key == initial_key)
+                .collect();
+            if !candidates.is_empty() {
+                return Self::decide(candidates, "last name and first initial".into());
+            }
+        }
+
+        let max_distance = (folded_key.chars().count() / 6).clamp(1, 3);
+        let mut best_distance = usize::MAX;
+        let mut candidates: Vec<&FuzzyRosterEntry> = Vec::new();
+        for entry in entries {
+            let distance = bounded_edit_distance(&folded_key, &entry.folded, max_distance);
+            match distance {
+                Some(distance) if distance < best_distance => {
+                    best_distance = distance;
+                    candidates.clear();
+                    candidates.push(entry);
+                }
+                Some(distance) if distance == best_distance => candidates.push(entry),
+                _ => {}
+            }
+        }
+
+        if candidates.is_empty() {
+            FuzzyRosterMatch::NoMatch
+        } else {
+            Self::decide(candidates, format!("edit distance {best_distance}"))
+        }
+    }
+
+    fn decide(candidates: Vec<&FuzzyRosterEntry>, method: String) -> FuzzyRosterMatch {
+        let distinct_rows: BTreeSet<usize> =
+            candidates.iter().map(|entry| entry.row_index).collect();
+        if distinct_rows.len() == 1 {
+            let entry = candidates[0];
+            FuzzyRosterMatch::Matched {
+                row_index: entry.row_index,
+                dataset_value: entry.display.clone(),
+                method,
+            }
+        } else {
+            FuzzyRosterMatch::Ambiguous {
+                candidates: candidates
+                    .iter()
+                    .map(|entry| entry.display.clone())
+                    .collect(),
+            }
+        }
+    }
+}
+
+fn fold_identifier_value(value: &str) -> String {
+    let trimmed = value.trim();
+    let reordered = match trimmed.split_once(',') {
+        Some((last, first)) if !first.contains(',') => format!("{} {}", first, last),
+        _ => trimmed.to_string(),
+    };
+
+    reordered
+        .nfd()
+        .filter(|ch| !is_combining_mark(*ch))
+        .map(|ch| if ch.is_alphanumeric() { ch } else { ' ' })
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn build_initial_key(folded_values: &[String]) -> String {
+    let mut parts = Vec::new();
+    for value in folded_values {
+        let tokens: Vec<&str> = value
+            .split_whitespace()
+            .filter(|token| !NAME_SUFFIXES.contains(token))
+            .collect();
+        if tokens.len() < 2 {
+            return String::new();
+        }
+
+        let first = canonical_first_name(tokens[0]);
+        let initial = match first.chars().next() {
+            Some(initial) => initial,
+            None => return String::new(),
+        };
+        parts.push(format!("{} {initial}", tokens[tokens.len() - 1]));
+    }
+    parts.join("|")
+}
+
+fn canonical_first_name(name: &str) -> &str {
+    COMMON_NICKNAMES
+        .iter()
+        .find(|(nickname, _)| *nickname == name)
+        .map(|(_, canonical)| *canonical)
+        .unwrap_or(name)
+}
+
+fn bounded_edit_distance(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_distance {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0usize; b.len() + 1];
+
+    for (i, &left) in a.iter().enumerate() {
+        current[0] = i + 1;
+        let mut row_minimum = current[0];
+        for (j, &right) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left != right);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            row_minimum = row_minimum.min(current[j + 1]);
+        }
+        if row_minimum > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    if distance <= max_distance {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+fn read_spreadsheet(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    read_spreadsheet_with_limit(path, Some(10))
+}
+
+fn read_full_spreadsheet(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    read_spreadsheet_with_limit(path, None)
+}
+
+fn read_spreadsheet_with_limit(
+    path: &Path,
+    max_rows: Option<usize>,
+) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    read_numbered_spreadsheet_with_limit(path, max_rows).map(|(headers, rows, _)| (headers, rows))
+}
+
+/// Headers, non-empty data rows, and the 1-based source row number of each data row.
+/// Blank rows are dropped, so row numbers cannot be derived from positions.
+type NumberedSpreadsheetRows = (Vec<String>, Vec<Vec<String>>, Vec<usize>);
+
+fn read_numbered_spreadsheet_with_limit(
+    path: &Path,
+    max_rows: Option<usize>,
+) -> Result<NumberedSpreadsheetRows, String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if matches!(extension.as_str(), "xlsx" | "xlsm" | "xls" | "xlsb") {
+        read_excel_spreadsheet_with_limit(path, max_rows)
+    } else {
+        read_delimited_spreadsheet_with_limit(path, max_rows)
+    }
+}
+
+fn read_spreadsheet_bytes(
+    data: &[u8],
+    filename: &str,
+    max_rows: Option<usize>,
+) -> Result<NumberedSpreadsheetRows, String> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if matches!(extension.as_str(), "xlsx" | "xlsm" | "xls" | "xlsb") {
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(data))
+            .map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
+        read_workbook_rows(&mut workbook, max_rows)
+    } else {
+        let detection =
+            detect_delimiter_from_reader(data, preferred_delimiter(Path::new(filename)))?;
+        read_delimited_rows(data, detection.delimiter, max_rows)
+    }
+}
+
+fn read_delimited_spreadsheet_with_limit(
+    path: &Path,
+    max_rows: Option<usize>,
+) -> Result<NumberedSpreadsheetRows, String> {
+    let detection = detect_delimiter(path)?;
+    let file = File::open(path).map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
+    read_delimited_rows(file, detection.delimiter, max_rows)
+}
+
+/// Reads rows through one reused `StringRecord`, tracking the widest row as it goes.
+/// Blank cells, the bulk of sparse sheets, cost nothing. Interning repeated values
+/// (programs, statuses) is deferred: it only pays off once rows hold shared strings
+/// such as `Arc<str>` instead of the `Vec<String>` every caller takes today.
+fn read_delimited_rows<R: Read>(
+    source: R,
+    delimiter: u8,
+    max_rows: Option<usize>,
+) -> Result<NumberedSpreadsheetRows, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(LineTrackingReader::new(source));
+
+    let mut headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| format!("Unable to read spreadsheet headers: {err}"))?
+        .iter()
+        .map(trimmed_cell)
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut row_numbers = Vec::new();
+    let mut column_count = headers.len();
+    let mut record = csv::StringRecord::new();
+    while reader
+        .read_record(&mut record)
+        .map_err(|err| format!("Unable to read spreadsheet rows: {err}"))?
+    {
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        column_count = column_count.max(record.len());
+        rows.push(record.iter().map(trimmed_cell).collect());
+        // A record's own position is taken before the parser skips empty lines, so the
+        // line is looked up from where the record's first content actually starts.
+        let start = record.position().map_or(0, |position| position.byte());
+        row_numbers.push(
+            reader
+                .get_mut()
+                .line_starting_at_or_after(start)
+                .unwrap_or(rows.len() + 1),
+        );
+        if let Some(limit) = max_rows {
+            if rows.len() >= limit {
+                break;
+            }
+        }
+    }
+
+    align_rows_to_width(&mut headers, &mut rows, column_count);
+    Ok((headers, rows, row_numbers))
+}
+
+/// Remembers the 1-based line number of every line with content as the CSV parser reads
+/// through it. CRLF counts as one line break, a lone CR as one as well.
+struct LineTrackingReader<R> {
+    inner: R,
+    offset: u64,
+    line: usize,
+    at_line_start: bool,
+    after_cr: bool,
+    /// Byte offset and line number of each content line not yet claimed by a record.
+    content_lines: VecDeque<(u64, usize)>,
+}
+
+impl<R> LineTrackingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            line: 1,
+            at_line_start: true,
+            after_cr: false,
+            content_lines: VecDeque::new(),
+        }
+    }
+
+    /// The line of the first content at or after `offset`, forgetting earlier lines.
+    fn line_starting_at_or_after(&mut self, offset: u64) -> Option<usize> {
+        while self
+            .content_lines
+            .front()
+            .is_some_and(|&(start, _)| start < offset)
+        {
+            self.content_lines.pop_front();
+        }
+        self.content_lines.front().map(|&(_, line)| line)
+    }
+}
+
+impl<R: Read> Read for LineTrackingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let mut rest = &buf[..read];
+        while !rest.is_empty() {
+            let content = rest
+                .iter()
+                .position(|&byte| byte == b'\n' || byte == b'\r')
+                .unwrap_or(rest.len());
+            if content > 0 {
+                if self.at_line_start {
+                    self.content_lines.push_back((self.offset, self.line));
+                    self.at_line_start = false;
+                }
+                self.after_cr = false;
+            }
+            if let Some(&byte) = rest.get(content) {
+                if !(byte == b'\n' && self.after_cr) {
+                    self.line += 1;
+                    self.at_line_start = true;
+                }
+                self.after_cr = byte == b'\r';
+            }
+            let consumed = (content + 1).min(rest.len());
+            self.offset += consumed as u64;
+            rest = &rest[consumed..];
+        }
+        Ok(read)
+    }
+}
+
+/// Trims a cell without allocating for blank values.
+fn trimmed_cell(value: &str) -> String {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn read_excel_spreadsheet_with_limit(
+    path: &Path,
+    max_rows: Option<usize>,
+) -> Result<NumberedSpreadsheetRows, String> {
+    let mut workbook =
+        open_workbook_auto(path).map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
+    read_workbook_rows(&mut workbook, max_rows)
+}
+
+fn read_workbook_rows<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    max_rows: Option<usize>,
+) -> Result<NumberedSpreadsheetRows, String> {
+    let sheet_name = workbook
+        .sheet_names()
+        .get(0)
+        .cloned()
+        .ok_or_else(|| "The workbook does not contain any worksheets.".to_string())?;
+
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .ok_or_else(|| format!("Unable to read the worksheet named '{sheet_name}'."))?
+        .map_err(|err| format!("Unable to read the worksheet data: {err}"))?;
+
+    let mut rows_iter = range.rows();
+    let header_row = rows_iter
+        .next()
+        .ok_or_else(|| "The worksheet is empty.".to_string())?;
+    // The range starts at the first used cell, which may sit below blank leading rows.
+    let header_row_number = range.start().map(|(row, _)| row as usize + 1).unwrap_or(1);
+
+    let mut headers: Vec<String> = header_row.iter().map(cell_to_string).collect();
+    let mut rows = Vec::new();
+    let mut row_numbers = Vec::new();
+    // Every row of a calamine range has the same width, so no realignment pass is needed
+    // beyond padding the header row.
+    let column_count = headers.len().max(range.width());
+
+    for (offset, row) in rows_iter.enumerate() {
+        let values: Vec<String> = row.iter().map(cell_to_string).collect();
+        if values.iter().all(|value| value.is_empty()) {
+            continue;
+        }
+        rows.push(values);
+        row_numbers.push(header_row_number + offset + 1);
+        if let Some(limit) = max_rows {
+            if rows.len() >= limit {
+                break;
+            }
+        }
+    }
+
+    align_rows_to_width(&mut headers, &mut rows, column_count);
+    Ok((headers, rows, row_numbers))
+}
+
+fn cell_to_string(cell: &DataType) -> String {
+    match cell {
+        DataType::Empty => String::new(),
+        DataType::String(value) => trimmed_cell(value),
+        _ => {
+            let value = cell.to_string();
+            if value.trim().len() == value.len() {
+                value
+            } else {
+                trimmed_cell(&value)
+            }
+        }
+    }
+}
+
+fn align_row_lengths(headers: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+    let column_count = rows.iter().map(Vec::len).fold(headers.len(), usize::max);
+    align_rows_to_width(headers, rows, column_count);
+}
+
+fn align_rows_to_width(headers: &mut Vec<String>, rows: &mut [Vec<String>], column_count: usize) {
+    if headers.len() < column_count {
+        headers.resize(column_count, String::new());
+    }
+
+    for row in rows.iter_mut() {
+        if row.len() < column_count {
+            row.resize(column_count, String::new());
+        } else if row.len() > column_count {
+            row.truncate(column_count);
+        }
+    }
+}
+
+const DELIMITER_CANDIDATES: [u8; 3] = [b'\t', b',', b';'];
+const DELIMITER_SAMPLE_LINES: usize = 20;
+
+/// The delimiter chosen for a text spreadsheet, with the share of sampled data rows
+/// whose field count matched the header row under that delimiter.
+#[derive(Debug, Clone, Copy)]
+struct DelimiterDetection {
+    delimiter: u8,
+    consistency: f32,
+}
+
+impl DelimiterDetection {
+    fn name(&self) -> &'static str {
+        match self.delimiter {
+            b'\t' => "tab",
+            b',' => "comma",
+            b';' => "semicolon",
+            _ => "other",
+        }
+    }
+}
+
+/// The delimiter to fall back on, and to prefer on ties, for a file's extension.
+fn preferred_delimiter(path: &Path) -> u8 {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if extension == "csv" {
+        b','
+    } else {
+        b'\t'
+    }
+}
+
+/// Detects the delimiter of a text spreadsheet; Excel workbooks and unreadable
+/// files yield `None`.
+fn spreadsheet_delimiter_detection(path: &Path) -> Option<DelimiterDetection> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if matches!(extension.as_str(), "xlsx" | "xlsm" | "xls" | "xlsb") {
+        return None;
+    }
+    detect_delimiter(path).ok()
+}
+
+fn detect_delimiter(path: &Path) -> Result<DelimiterDetection, String> {
+    let file = File::open(path).map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
+    detect_delimiter_from_reader(BufReader::new(file), preferred_delimiter(path))
+}
+
+/// Parses the first non-empty lines with each candidate delimiter and keeps the one
+/// whose rows most consistently have as many fields as the header. Counting raw
+/// characters instead would pick commas for a TSV whose header has a comma-laden
+/// title. Ties go to `preferred`, then to whichever yields more columns.
+fn detect_delimiter_from_reader<R: BufRead>(
+    mut reader: R,
+    preferred: u8,
+) -> Result<DelimiterDetection, String> {
+    let mut sample = Vec::new();
+    let mut line = Vec::new();
+    let mut lines = 0;
+    while lines < DELIMITER_SAMPLE_LINES {
+        line.clear();
+        let bytes_read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|err| format!("Unable to inspect the spreadsheet: {err}"))?;
+        if bytes_read == 0 {
+            break;
+        }
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        sample.extend_from_slice(&line);
+        lines += 1;
+    }
+
+    let mut best: Option<(DelimiterDetection, usize)> = None;
+    for delimiter in DELIMITER_CANDIDATES {
+        let Some((consistency, columns)) = score_delimiter(&sample, delimiter) else {
+            continue;
+        };
+        let better = match best {
+            None => true,
+            Some((current, current_columns)) => {
+                if consistency != current.consistency {
+                    consistency > current.consistency
+                } else if (delimiter == preferred) != (current.delimiter == preferred) {
+                    delimiter == preferred
+                } else {
+                    columns > current_columns
+                }
+            }
+        };
+        if better {
+            best = Some((
+                DelimiterDetection {
+                    delimiter,
+                    consistency,
+                },
+                columns,
+            ));
+        }
+    }
+
+    Ok(best
+        .map(|(detection, _)| detection)
+        .unwrap_or(DelimiterDetection {
+            delimiter: preferred,
+            consistency: 1.0,
+        }))
+}
+
+/// Returns the fraction of sampled data rows matching the header's field count and
+/// the header's field count, or `None` when the delimiter never splits a line.
+fn score_delimiter(sample: &[u8], delimiter: u8) -> Option<(f32, usize)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(sample);
+
+    let mut field_counts = Vec::new();
+    let mut record = csv::ByteRecord::new();
+    // A sample cut inside a quoted field ends in a parse error; score what came before.
+    while let Ok(true) = reader.read_byte_record(&mut record) {
+        if record.iter().all(|value| value.trim_ascii().is_empty()) {
+            continue;
+        }
+        field_counts.push(record.len());
+    }
+
+    let (&header, rows) = field_counts.split_first()?;
+    if field_counts.iter().all(|count| *count < 2) {
+        return None;
+    }
+    let consistency = if rows.is_empty() {
+        1.0
+    } else {
+        rows.iter().filter(|count| **count == header).count() as f32 / rows.len() as f32
+    };
+    Some((consistency, header))
+}
+
+fn build_faculty_dataset_status(
+    app_handle: &dyn MatchingHost,
+) -> Result<FacultyDatasetStatus, String> {
+    build_faculty_dataset_status_with_overrides(app_handle, None)
+}
+
+fn build_faculty_dataset_status_with_overrides(
+    app_handle: &dyn MatchingHost,
+    overrides: Option<&FacultyDatasetColumnConfiguration>,
+) -> Result<FacultyDatasetStatus, String> {
+    build_faculty_dataset_status_with_options(app_handle, overrides, true, true)
+}
+
+/// Builds the dataset status, optionally skipping the preview read and the column
+/// analysis so frequent polls only pay for the validity and dimension checks.
+fn build_faculty_dataset_status_with_options(
+    app_handle: &dyn MatchingHost,
+    overrides: Option<&FacultyDatasetColumnConfiguration>,
+    include_preview: bool,
+    include_analysis: bool,
+) -> Result<FacultyDatasetStatus, String> {
+    let dataset_path = dataset_destination(app_handle)?;
+    let mut status = FacultyDatasetStatus {
+        path: Some(dataset_path.to_string_lossy().into_owned()),
+        canonical_path: None,
+        source_path: None,
+        source_changed: false,
+        source_missing: false,
+        last_modified: None,
+        row_count: None,
+        column_count: None,
+        is_valid: false,
+        is_default: false,
+        message: None,
+        message_variant: None,
+        preview: None,
+        analysis: None,
+    };
+
+    if !dataset_path.exists() {
+        if let Err(init_err) = ensure_default_faculty_dataset(app_handle, &dataset_path) {
+            let _ = clear_faculty_dataset_metadata(app_handle);
+            let _ = clear_faculty_dataset_source_path(app_handle);
+            status.message = Some(format!(
+                "Unable to restore the packaged faculty dataset: {init_err}"
+            ));
+            status.message_variant = Some("error".into());
+            return Ok(status);
+        }
+    }
+
+    if !dataset_path.exists() {
+        let _ = clear_faculty_dataset_metadata(app_handle);
+        let _ = clear_faculty_dataset_source_path(app_handle);
+        status.message = Some(
+            "No faculty dataset has been configured. Restore the default file to continue.".into(),
+        );
+        status.message_variant = Some("info".into());
+        return Ok(status);
+    }
+
+    status.canonical_path = dataset_path
+        .canonicalize()
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned());
+
+    match read_faculty_dataset_source_record(app_handle) {
+        Ok(Some(record)) => {
+            match compare_faculty_dataset_source(&record) {
+                SourceComparison::Unchanged => {}
+                SourceComparison::Changed(modified) => {
+                    status.source_changed = true;
+                    status.message = Some(match modified {
+                        Some(modified) => format!(
+                            "The original file was modified on {modified}; re-import to pick up changes."
+                        ),
+                        None => {
+                            "The original file has changed; re-import to pick up changes.".into()
+                        }
+                    });
+                    status.message_variant = Some("info".into());
+                }
+                SourceComparison::Missing => {
+                    status.source_missing = true;
+                    status.message = Some(format!(
+                        "The original file at {} is no longer available; the imported copy is still in use.",
+                        record.path
+                    ));
+                    status.message_variant = Some("info".into());
+                }
+            }
+            status.source_path = Some(record.path);
+        }
+        Ok(None) => {}
+        Err(err) => {
+            if status.message.is_none() {
+                status.message = Some(err);
+                status.message_variant = Some("error".into());
+            }
+        }
+    }
+
+    let metadata = fs::metadata(&dataset_path)
+        .map_err(|err| format!("Unable to inspect the faculty dataset: {err}"))?;
+    status.last_modified = metadata.modified().ok().map(format_system_time);
+
+    let bytes = fs::read(&dataset_path)
+        .map_err(|err| format!("Unable to read the faculty dataset: {err}"))?;
+    status.is_default = bytes == DEFAULT_FACULTY_DATASET;
+
+    let extension = dataset_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let dimensions = match extension.as_str() {
+        "xlsx" | "xls" => compute_excel_dimensions(&dataset_path),
+        _ => compute_delimited_dimensions(&bytes, preferred_delimiter(&dataset_path)),
+    };
+
+    match dimensions {
+        Ok((rows, columns)) => {
+            status.row_count = Some(rows);
+            status.column_count = Some(columns);
+            status.is_valid = rows > 0 && columns > 0;
+            if !status.is_valid {
+                status.message = Some("The faculty dataset does not contain any data rows.".into());
+                status.message_variant = Some("error".into());
+            }
+        }
+        Err(err) => {
+            status.message = Some(err);
+            status.message_variant = Some("error".into());
+        }
+    }
+
+    if include_preview {
+        match build_dataset_preview(&dataset_path) {
+            Ok(preview) => {
+                status.preview = Some(preview);
+            }
+            Err(err) => {
+                if status.message.is_none() {
+                    status.message = Some(err);
+                    status.message_variant = Some("error".into());
+                }
+            }
+        }
+    }
+
+    if !include_analysis {
+        // Leave the saved metadata alone; only a full analysis may invalidate it.
+        return Ok(status);
+    }
+
+    if status.is_valid {
+        match analyze_faculty_dataset(app_handle, &dataset_path, overrides) {
+            Ok(analysis) => {
+                if status.message.is_none() {
+                    if let Some(message) =
+                        describe_blank_identifier_rows(&analysis.blank_identifier_rows)
+                    {
+                        status.message = Some(message);
+                        status.message_variant = Some("info".into());
+                    }
+                }
+                status.analysis = Some(analysis);
+            }
+            Err(err) => {
+                let _ = clear_faculty_dataset_metadata(app_handle);
+                status.analysis = None;
+                status.is_valid = false;
+                if status.message.is_none() {
+                    status.message = Some(err);
+                    status.message_variant = Some("error".into());
+                }
+            }
+        }
+    } else if status.analysis.is_some() {
+        status.analysis = None;
+    }
+
+    if !status.is_valid {
+        if let Err(err) = clear_faculty_dataset_metadata(app_handle) {
+            if status.message.is_none() {
+                status.message = Some(err);
+                status.message_variant = Some("error".into());
+            }
+        }
+    }
+
+    Ok(status)
+}
+
+fn analyze_faculty_dataset(
+    app_handle: &dyn MatchingHost,
+    dataset_path: &Path,
+    overrides: Option<&FacultyDatasetColumnConfiguration>,
+) -> Result<FacultyDatasetAnalysis, String> {
+    let (mut headers, mut rows) = read_full_spreadsheet(dataset_path)?;
+    if headers.is_empty() {
+        return Err("The faculty dataset does not include any columns.".into());
+    }
+
+    align_row_lengths(&mut headers, &mut rows);
+
+    let column_count = headers.len();
+
+    let (embedding_indexes, identifier_indexes) = if let Some(config) = overrides {
+        (
+            normalize_column_selection(&config.embedding_columns, column_count),
+            normalize_column_selection(&config.identifier_columns, column_count),
+        )
+    } else {
+        suggest_spreadsheet_columns(&headers, &rows)
+    };
+
+    let program_indexes = if let Some(config) = overrides {
+        normalize_column_selection(&config.program_columns, column_count)
+    } else {
+        suggest_program_columns(&headers, &rows)
+    };
+
+    if embedding_indexes.is_empty() {
+        return Err(
+            "Select at least one column containing faculty research interests or other embedding content.".into(),
+        );
+    }
+
+    if identifier_indexes.is_empty() {
+        return Err(
+            "Select at least one column that uniquely identifies each faculty member.".into(),
+        );
+    }
+
+    let analysis = FacultyDatasetAnalysis {
+        embedding_columns: indexes_to_headers(&headers, &embedding_indexes),
+        identifier_columns: indexes_to_headers(&headers, &identifier_indexes),
+        program_columns: indexes_to_headers(&headers, &program_indexes),
+        available_programs: collect_program_values(&rows, &program_indexes),
+        blank_identifier_rows: find_blank_identifier_rows(
+            &rows,
+            &embedding_indexes,
+            &identifier_indexes,
+        ),
+    };
+
+    let memberships =
+        build_faculty_program_memberships(&headers, &rows, &identifier_indexes, &program_indexes);
+
+    write_faculty_dataset_metadata(app_handle, &analysis, &memberships)?;
+
+    Ok(analysis)
+}
+
+/// Rows that will be embedded but cannot be told apart in results, reported as
+/// spreadsheet row numbers.
+fn find_blank_identifier_rows(
+    rows: &[Vec<String>],
+    embedding_indexes: &[usize],
+    identifier_indexes: &[usize],
+) -> Vec<usize> {
+    let has_value = |row: &[String], indexes: &[usize]| {
+        indexes
+            .iter()
+            .any(|&index| row.get(index).is_some_and(|value| !value.trim().is_empty()))
+    };
+    rows.iter()
+        .enumerate()
+        .filter(|(_, row)| has_value(row, embedding_indexes) && !has_value(row, identifier_indexes))
+        .map(|(row_index, _)| row_index + 2)
+        .collect()
+}
+
+fn describe_blank_identifier_rows(rows: &[usize]) -> Option<String> {
+    let (count, listed) = (rows.len(), rows.len().min(BLANK_IDENTIFIER_ROWS_LISTED));
+    if count == 0 {
+        return None;
+    }
+    let mut numbers = rows[..listed]
+        .iter()
+        .map(|row| row.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if count > listed {
+        numbers.push_str(&format!(" and {} more", count - listed));
+    }
+    Some(format!(
+        "{count} faculty row{} {} research text but no identifier values (row{} {numbers}). \
+         Matches will show them as \"Dataset row N\" until the identifiers are filled in.",
+        if count == 1 { "" } else { "s" },
+        if count == 1 { "has" } else { "have" },
+        if count == 1 { "" } else { "s" },
+    ))
+}
+
+fn suggest_program_columns(headers: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    const PROGRAM_KEYWORDS: &[&str] = &["program", "track", "pathway", "division", "department"];
+
+    let mut program_columns: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .filter_map(|(index, header)| {
+            let lower = header.to_lowercase();
+            if lower.is_empty() {
+                return None;
+            }
+            if PROGRAM_KEYWORDS
+                .iter()
+                .any(|keyword| lower.contains(keyword))
+            {
+                Some(index)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    sort_and_dedup(&mut program_columns);
+
+    if !program_columns.is_empty() {
+        return program_columns;
+    }
+
+    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+
+    for (index, header) in headers.iter().enumerate() {
+        if header.trim().is_empty() {
+            continue;
+        }
+
+        let mut unique = BTreeSet::new();
+        let mut non_empty = 0usize;
+
+        for row in rows {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                non_empty += 1;
+                unique.insert(trimmed.to_lowercase());
+            }
+        }
+
+        if non_empty == 0 {
+            continue;
+        }
+
+        let unique_count = unique.len();
+        if unique_count > 0 && unique_count <= 25 {
+            candidates.push((index, unique_count, non_empty));
+        }
+    }
+
+    candidates.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)));
+
+    for (index, _, _) in candidates.into_iter().take(4) {
+        program_columns.push(index);
+    }
+
+    sort_and_dedup(&mut program_columns);
+    program_columns
+}
+
+fn indexes_to_headers(headers: &[String], indexes: &[usize]) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut seen = HashSet::new();
+
+    for &index in indexes {
+        if index >= headers.len() {
+            continue;
+        }
+
+        let label = header_label(headers, index);
+        let key = label.to_lowercase();
+        if seen.insert(key) {
+            values.push(label);
+        }
+    }
+
+    values
+}
+
+fn normalize_column_selection(indexes: &[usize], column_count: usize) -> Vec<usize> {
+    let mut normalized = Vec::new();
+    let mut seen = HashSet::new();
+
+    for &index in indexes {
+        if index >= column_count {
+            continue;
+        }
+
+        if seen.insert(index) {
+            normalized.push(index);
+        }
+    }
+
+    normalized
+}
+
+/// The label shown and accepted for a column: its trimmed header, or "Column N"
+/// (1-based) when the header is blank or the column only exists because a data row
+/// is wider than the header row. Previews, saved analyses and label lookups all use
+/// this so a column selected in a preview resolves during processing.
+fn header_label(headers: &[String], index: usize) -> String {
+    headers
+        .get(index)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| format!("Column {}", index + 1))
+}
+
+fn header_labels(headers: &[String]) -> Vec<String> {
+    (0..headers.len())
+        .map(|index| header_label(headers, index))
+        .collect()
+}
+
+fn collect_program_values(rows: &[Vec<String>], program_indexes: &[usize]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut values = Vec::new();
+
+    for row in rows {
+        for &index in program_indexes {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                let key = trimmed.to_lowercase();
+                if seen.insert(key) {
+                    values.push(trimmed.to_string());
+                }
+            }
+        }
+    }
+
+    values.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    values
+}
+
+fn build_faculty_program_memberships(
+    headers: &[String],
+    rows: &[Vec<String>],
+    identifier_indexes: &[usize],
+    program_indexes: &[usize],
+) -> Vec<FacultyProgramMembership> {
+    let mut memberships = Vec::new();
+
+    for (row_index, row) in rows.iter().enumerate() {
+        let mut identifiers = HashMap::new();
+        for &index in identifier_indexes {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                let label = header_label(headers, index);
+                identifiers
+                    .entry(label)
+                    .or_insert_with(|| trimmed.to_string());
+            }
+        }
+
+        let mut program_set = BTreeSet::new();
+        for &index in program_indexes {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                program_set.insert(trimmed.to_string());
+            }
+        }
+
+        if identifiers.is_empty() && program_set.is_empty() {
+            continue;
+        }
+
+        memberships.push(FacultyProgramMembership {
+            row_index,
+            identifiers,
+            programs: program_set.into_iter().collect(),
+        });
+    }
+
+    memberships
+}
+
+fn metadata_path(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    let directory = dataset_directory(app_handle)?;
+    Ok(directory.join(FACULTY_DATASET_METADATA_NAME))
+}
+
+fn write_faculty_dataset_metadata(
+    app_handle: &dyn MatchingHost,
+    analysis: &FacultyDatasetAnalysis,
+    memberships: &[FacultyProgramMembership],
+) -> Result<(), String> {
+    let path = metadata_path(app_handle)?;
+    ensure_dataset_directory(&path)?;
+    let payload = FacultyDatasetMetadata {
+        analysis: analysis.clone(),
+        memberships: memberships.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&payload)
+        .map_err(|err| format!("Unable to serialize faculty dataset metadata: {err}"))?;
+    fs::write(&path, json)
+        .map_err(|err| format!("Unable to persist faculty dataset metadata: {err}"))?;
+    Ok(())
+}
+
+fn roster_mapping_presets_path(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    let directory = dataset_directory(app_handle)?;
+    Ok(directory.join(ROSTER_MAPPING_PRESETS_NAME))
+}
+
+fn load_roster_mapping_presets(
+    app_handle: &dyn MatchingHost,
+) -> Result<Vec<RosterMappingPreset>, String> {
+    let path = roster_mapping_presets_path(app_handle)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read(&path)
+        .map_err(|err| format!("Unable to read the roster mapping presets: {err}"))?;
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_slice(&data)
+        .map_err(|err| format!("Unable to parse the roster mapping presets: {err}"))
+}
+
+fn write_roster_mapping_presets(
+    app_handle: &dyn MatchingHost,
+    presets: &[RosterMappingPreset],
+) -> Result<(), String> {
+    let path = roster_mapping_presets_path(app_handle)?;
+    ensure_dataset_directory(&path)?;
+    let json = serde_json::to_string_pretty(presets)
+        .map_err(|err| format!("Unable to serialize the roster mapping presets: {err}"))?;
+    fs::write(&path, json)
+        .map_err(|err| format!("Unable to persist the roster mapping presets: {err}"))?;
+    Ok(())
+}
+
+fn app_settings_path(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    let directory = dataset_directory(app_handle)?;
+    Ok(directory.join(APP_SETTINGS_NAME))
+}
+
+fn load_app_settings(app_handle: &dyn MatchingHost) -> Result<AppSettings, String> {
+    let path = app_settings_path(app_handle)?;
+    if !path.exists() {
+        return Ok(AppSettings::default());
+    }
+
+    let data =
+        fs::read(&path).map_err(|err| format!("Unable to read the application settings: {err}"))?;
+    if data.is_empty() {
+        return Ok(AppSettings::default());
+    }
+
+    serde_json::from_slice(&data)
+        .map_err(|err| format!("Unable to parse the application settings: {err}"))
+}
+
+fn write_app_settings(app_handle: &dyn MatchingHost, settings: &AppSettings) -> Result<(), String> {
+    let path = app_settings_path(app_handle)?;
+    ensure_dataset_directory(&path)?;
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|err| format!("Unable to serialize the application settings: {err}"))?;
+    fs::write(&path, json)
+        .map_err(|err| format!("Unable to persist the application settings: {err}"))?;
+    Ok(())
+}
+
+fn validate_app_settings(settings: AppSettings) -> Result<AppSettings, String> {
+    let mut errors = Vec::new();
+    let trimmed = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let program_filters = normalize_programs(settings.program_filters);
+    let filename_pattern = trimmed(settings.filename_pattern);
+    let embedding_model = trimmed(settings.embedding_model);
+    let embedding_device = trimmed(settings.embedding_device).map(|device| device.to_lowercase());
+
+    if settings.faculty_recs_per_student == 0 {
+        errors.push(
+            "facultyRecsPerStudent: Specify at least one faculty recommendation per student."
+                .to_string(),
+        );
+    }
+    if matches!(settings.faculty_scope, FacultyScope::Program) && program_filters.is_empty() {
+        errors.push(
+            "programFilters: Provide at least one program when the default scope is limited by program."
+                .to_string(),
+        );
+    }
+    if matches!(settings.faculty_scope, FacultyScope::Custom) {
+        errors.push(
+            "facultyScope: A faculty roster cannot be used as the default scope; choose all faculty or programs."
+                .to_string(),
+        );
+    }
+    for (field, value) in [
+        ("similarityLowThreshold", settings.similarity_low_threshold),
+        (
+            "similarityHighThreshold",
+            settings.similarity_high_threshold,
+        ),
+        ("attentionThreshold", settings.attention_threshold),
+    ] {
+        if let Some(value) = value {
+            if !value.is_finite() || !(0.0..=100.0).contains(&value) {
+                errors.push(format!(
+                    "{field}: The similarity threshold must be between 0 and 100."
+                ));
+            }
+        }
+    }
+    if let (Some(low), Some(high)) = (
+        settings.similarity_low_threshold,
+        settings.similarity_high_threshold,
+    ) {
+        if low > high {
+            errors.push(
+                "similarityLowThreshold: The low threshold must be at or below the high threshold."
+                    .to_string(),
+            );
+        }
+    }
+    if let Some(limit) = settings.preview_row_limit {
+        if limit == 0 || limit > MAX_PREVIEW_ROW_LIMIT {
+            errors.push(format!(
+                "previewRowLimit: The preview row limit must be between 1 and {MAX_PREVIEW_ROW_LIMIT}."
+            ));
+        }
+    }
+    if let Some(pattern) = filename_pattern.as_deref() {
+        if let Err(err) = validate_filename_pattern(pattern) {
+            errors.push(format!("filenamePattern: {err}"));
+        }
+    }
+    if let Some(limit) = settings.max_prompt_characters {
+        if limit < PROMPT_MAX_CHARACTERS_MIN {
+            errors.push(format!(
+                "maxPromptCharacters: The maximum prompt length must be at least {PROMPT_MAX_CHARACTERS_MIN} characters."
+            ));
+        }
+    }
+    if let Some(limit) = settings.prompt_preview_characters {
+        if !(PROMPT_PREVIEW_CHARACTERS_MIN..=PROMPT_PREVIEW_CHARACTERS_MAX).contains(&limit) {
+            errors.push(format!(
+                "promptPreviewCharacters: The prompt preview length must be between {PROMPT_PREVIEW_CHARACTERS_MIN} and {PROMPT_PREVIEW_CHARACTERS_MAX} characters."
+            ));
+        }
+    }
+    if let Some(limit) = settings.faculty_text_max_characters {
+        if limit < FACULTY_TEXT_MAX_CHARACTERS_MIN {
+            errors.push(format!(
+                "facultyTextMaxCharacters: The stored faculty text limit must be at least {FACULTY_TEXT_MAX_CHARACTERS_MIN} characters."
+            ));
+        }
+    }
+    if let Some(batch_size) = settings.embedding_batch_size {
+        if batch_size == 0 || batch_size > EMBEDDING_BATCH_SIZE_MAX {
+            errors.push(format!(
+                "embeddingBatchSize: The embedding batch size must be between 1 and {EMBEDDING_BATCH_SIZE_MAX}."
+            ));
+        }
+    }
+    if let Some(device) = embedding_device.as_deref() {
+        let valid = matches!(device, "auto" | "cpu" | "cuda" | "mps")
+            || device
+                .strip_prefix("cuda:")
+                .is_some_and(|index| index.parse::<u32>().is_ok());
+        if !valid {
+            errors.push(format!(
+                "embeddingDevice: Unsupported embedding device '{device}'. Use auto, cpu, cuda, cuda:N, or mps."
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    Ok(AppSettings {
+        version: APP_SETTINGS_VERSION,
+        program_filters,
+        filename_pattern,
+        embedding_model,
+        embedding_device,
+        ..settings
+    })
+}
+
+fn apply_app_settings(mut payload: SubmissionPayload, settings: &AppSettings) -> SubmissionPayload {
+    let faculty_scope = payload
+        .faculty_scope
+        .get_or_insert_with(|| settings.faculty_scope.clone());
+    if matches!(faculty_scope, FacultyScope::Program) && payload.program_filters.is_empty() {
+        payload.program_filters = settings.program_filters.clone();
+    }
+    payload
+        .faculty_recs_per_student
+        .get_or_insert(settings.faculty_recs_per_student);
+    if payload.similarity_low_threshold.is_none() {
+        payload.similarity_low_threshold = settings.similarity_low_threshold;
+    }
+    if payload.similarity_high_threshold.is_none() {
+        payload.similarity_high_threshold = settings.similarity_high_threshold;
+    }
+    if payload.attention_threshold.is_none() {
+        payload.attention_threshold = settings.attention_threshold;
+    }
+    if payload.preview_row_limit.is_none() {
+        payload.preview_row_limit = settings.preview_row_limit;
+    }
+    if payload.filename_pattern.is_none() {
+        payload.filename_pattern = settings.filename_pattern.clone();
+    }
+    if payload.max_prompt_characters.is_none() {
+        payload.max_prompt_characters = settings.max_prompt_characters;
+    }
+    if payload.prompt_preview_characters.is_none() {
+        payload.prompt_preview_characters = settings.prompt_preview_characters;
+    }
+    if payload.strip_identifier_diacritics.is_none() {
+        payload.strip_identifier_diacritics = settings.strip_identifier_diacritics;
+    }
+    payload
+}
+
+fn configured_embedding_model(app_handle: &dyn MatchingHost) -> Result<String, String> {
+    Ok(load_app_settings(app_handle)?
+        .embedding_model
+        .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string()))
+}
+
+fn configured_strip_identifier_diacritics(app_handle: &dyn MatchingHost) -> Result<bool, String> {
+    Ok(load_app_settings(app_handle)?
+        .strip_identifier_diacritics
+        .unwrap_or(true))
+}
+
+fn configured_embedding_precision(
+    app_handle: &dyn MatchingHost,
+) -> Result<EmbeddingPrecision, String> {
+    Ok(load_app_settings(app_handle)?
+        .embedding_precision
+        .unwrap_or(EmbeddingPrecision::F16))
+}
+
+static FILE_LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+struct FileLogger {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl FileLogger {
+    fn open(&self) -> Option<File> {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .ok()
+    }
+
+    fn rotate(&self) {
+        let _ = fs::rename(&self.path, self.path.with_extension("log.1"));
+    }
+}
+
+impl log::Log for FileLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format!(
+            "{} {:<5} {}\n",
+            Utc::now().to_rfc3339(),
+            record.level(),
+            record.args()
+        );
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+
+        let current_size = file
+            .as_ref()
+            .and_then(|handle| handle.metadata().ok())
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+        if current_size + line.len() as u64 > LOG_FILE_MAX_BYTES {
+            *file = None;
+            self.rotate();
+        }
+        if file.is_none() {
+            *file = self.open();
+        }
+        if let Some(handle) = file.as_mut() {
+            let _ = handle.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(handle) = file.as_mut() {
+                let _ = handle.flush();
+            }
+        }
+    }
+}
+
+fn log_file_path(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    Ok(dataset_directory(app_handle)?
+        .join(LOG_DIRECTORY)
+        .join(LOG_FILE_NAME))
+}
+
+fn init_file_logging(app_handle: &dyn MatchingHost) -> Result<(), String> {
+    let path = log_file_path(app_handle)?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)
+            .map_err(|err| format!("Unable to prepare the log directory: {err}"))?;
+    }
+
+    let logger = FILE_LOGGER.get_or_init(|| FileLogger {
+        path,
+        file: Mutex::new(None),
+    });
+    log::set_logger(logger).map_err(|err| format!("Unable to initialize logging: {err}"))?;
+
+    let level = load_app_settings(app_handle)
+        .map(|settings| settings.log_level)
+        .unwrap_or_default();
+    log::set_max_level(level.level_filter());
+    log::info!("Started DBBS Faculty Match {}.", app_handle.app_version());
+    Ok(())
+}
+
+#[tauri::command]
+fn get_log_file_path(app_handle: tauri::AppHandle) -> Result<String, String> {
+    Ok(log_file_path(&app_handle)?.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+fn get_recent_logs(
+    app_handle: tauri::AppHandle,
+    max_lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let limit = max_lines
+        .filter(|limit| *limit > 0)
+        .unwrap_or(LOG_RECENT_LINES_DEFAULT)
+        .min(LOG_RECENT_LINES_MAX);
+    read_recent_log_lines(&app_handle, limit)
+}
+
+fn read_recent_log_lines(
+    app_handle: &dyn MatchingHost,
+    limit: usize,
+) -> Result<Vec<String>, String> {
+    let path = log_file_path(app_handle)?;
+    if let Some(logger) = FILE_LOGGER.get() {
+        log::Log::flush(logger);
+    }
+
+    let mut lines = Vec::new();
+    for candidate in [path.clone(), path.with_extension("log.1")] {
+        if lines.len() >= limit || !candidate.exists() {
+            continue;
+        }
+        let data =
+            fs::read(&candidate).map_err(|err| format!("Unable to read the log file: {err}"))?;
+        let text = String::from_utf8_lossy(&data);
+        let mut older: Vec<String> = text
+            .lines()
+            .rev()
+            .take(limit - lines.len())
+            .map(str::to_string)
+            .collect();
+        older.reverse();
+        older.append(&mut lines);
+        lines = older;
+    }
+
+    Ok(lines)
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Diagnostics {
+    generated_at: String,
+    app_version: String,
+    crate_version: String,
+    os: String,
+    os_family: String,
+    arch: String,
+    app_data_directory: Option<String>,
+    resource_directory: Option<String>,
+    log_file_path: Option<String>,
+    embedding_helper_path: Option<String>,
+    embedding_helper_found: bool,
+    embedding_helper_ready: bool,
+    last_helper_error: Option<HelperErrorRecord>,
+    dataset_status: Option<FacultyDatasetStatus>,
+    dataset_status_error: Option<String>,
+    embedding_index: Option<EmbeddingIndexStatus>,
+    embedding_index_error: Option<String>,
+    run_history_enabled: bool,
+}
+
+fn build_diagnostics(app_handle: &tauri::AppHandle) -> Diagnostics {
+    let path_string = |path: PathBuf| path.to_string_lossy().into_owned();
+    let embedding_helper_path = embedding_helper_sidecar_path();
+    let (dataset_status, dataset_status_error) = match build_faculty_dataset_status(app_handle) {
+        Ok(status) => (
+            Some(FacultyDatasetStatus {
+                preview: None,
+                ..status
+            }),
+            None,
+        ),
+        Err(err) => (None, Some(err)),
+    };
+    let (embedding_index, embedding_index_error) = match embedding_index_status(app_handle) {
+        Ok(status) => (Some(status), None),
+        Err(err) => (None, Some(err)),
+    };
+
+    Diagnostics {
+        generated_at: Utc::now().to_rfc3339(),
+        app_version: app_handle.app_version(),
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        os_family: std::env::consts::FAMILY.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        app_data_directory: app_handle.data_directory().ok().map(path_string),
+        resource_directory: app_handle.path().resource_dir().ok().map(path_string),
+        log_file_path: log_file_path(app_handle).ok().map(path_string),
+        embedding_helper_found: embedding_helper_path
+            .as_ref()
+            .is_some_and(|path| path.is_file()),
+        embedding_helper_path: embedding_helper_path.map(path_string),
+        embedding_helper_ready: EMBEDDING_HELPER_READY.load(AtomicOrdering::SeqCst),
+        last_helper_error: LAST_HELPER_ERROR.lock().ok().and_then(|last| last.clone()),
+        dataset_status,
+        dataset_status_error,
+        embedding_index,
+        embedding_index_error,
+        run_history_enabled: run_history_enabled(),
+    }
+}
+
+#[tauri::command]
+fn get_diagnostics(app_handle: tauri::AppHandle) -> Diagnostics {
+    build_diagnostics(&app_handle)
+}
+
+#[tauri::command]
+fn export_diagnostics(app_handle: tauri::AppHandle, path: String) -> Result<String, AppError> {
+    let trimmed = trim_pasted_path(&path);
+    if trimmed.is_empty() {
+        return Err(AppError::invalid_input(
+            "path",
+            "Choose where to save the diagnostics bundle.",
+        ));
+    }
+    let destination = expand_user_path(trimmed);
+
+    let diagnostics = build_diagnostics(&app_handle);
+    let diagnostics_json = serde_json::to_vec_pretty(&diagnostics)
+        .map_err(|err| format!("Unable to serialize the diagnostics: {err}"))?;
+    let log_lines = read_recent_log_lines(&app_handle, LOG_RECENT_LINES_MAX)
+        .unwrap_or_else(|err| vec![format!("Unable to read the log file: {err}")]);
+
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let file_options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let bundle_error = |name: &str, err: &dyn std::fmt::Display| {
+        format!("Unable to add '{name}' to the diagnostics bundle: {err}")
+    };
+    for (name, data) in [
+        (DIAGNOSTICS_FILE_NAME, diagnostics_json),
+        (DIAGNOSTICS_LOG_NAME, log_lines.join("\n").into_bytes()),
+    ] {
+        archive
+            .start_file(name, file_options)
+            .map_err(|err| bundle_error(name, &err))?;
+        archive
+            .write_all(&data)
+            .map_err(|err| bundle_error(name, &err))?;
+    }
+    let buffer = archive
+        .finish()
+        .map_err(|err| format!("Unable to finish the diagnostics bundle: {err}"))?;
+
+    write_file_atomically(&destination, &buffer.into_inner()).map_err(|err| {
+        AppError::io(
+            &destination,
+            format!("Unable to save the diagnostics bundle: {err}"),
+        )
+    })?;
+    log::info!("Exported diagnostics to {}.", destination.display());
+    Ok(destination.to_string_lossy().into_owned())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AuditLogEntry {
+    timestamp: String,
+    username: Option<String>,
+    request_id: String,
+    task_type: TaskType,
+    input_paths: Vec<String>,
+    faculty_scope: Option<FacultyScope>,
+    #[serde(default)]
+    program_filters: Vec<String>,
+    #[serde(default)]
+    custom_faculty_path: Option<String>,
+    #[serde(default)]
+    processed_count: Option<usize>,
+    #[serde(default)]
+    matched_count: Option<usize>,
+    #[serde(default)]
+    skipped_count: Option<usize>,
+    #[serde(default)]
+    output_filename: Option<String>,
+    #[serde(default)]
+    warning_count: usize,
+    succeeded: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl AuditLogEntry {
+    fn for_payload(payload: &SubmissionPayload, request_id: &str) -> Self {
+        let username = ["USER", "USERNAME"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .map(|value| value.trim().to_string())
+            .find(|value| !value.is_empty());
+        let uploaded = |content: &Option<String>, filename: &Option<String>, default_name| {
+            content.as_ref().map(|_| {
+                format!(
+                    "{} (uploaded content)",
+                    inline_content_filename(filename.as_deref(), default_name)
+                )
+            })
+        };
+        let input_paths = [
+            uploaded(
+                &payload.document_content,
+                &payload.document_filename,
+                "document",
+            )
+            .or_else(|| payload.document_path.clone()),
+            uploaded(
+                &payload.spreadsheet_content,
+                &payload.spreadsheet_filename,
+                "spreadsheet.tsv",
+            )
+            .or_else(|| payload.spreadsheet_path.clone()),
+            payload.directory_path.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .collect();
+
+        Self {
+            timestamp: Utc::now().to_rfc3339(),
+            username,
+            request_id: request_id.to_string(),
+            task_type: payload.task_type.clone(),
+            input_paths,
+            faculty_scope: payload.faculty_scope.clone(),
+            program_filters: payload.program_filters.clone(),
+            custom_faculty_path: payload.custom_faculty_path.clone(),
+            processed_count: None,
+            matched_count: None,
+            skipped_count: None,
+            output_filename: None,
+            warning_count: 0,
+            succeeded: false,
+            error: None,
+        }
+    }
+
+    fn record_outcome(&mut self, result: &Result<SubmissionResponse, AppError>) {
+        match result {
+            Ok(response) => {
+                let counts = response
+                    .spreadsheet_results
+                    .as_ref()
+                    .map(|results| {
+                        (
+                            results.processed_rows,
+                            results.matched_rows,
+                            results.skipped_rows,
+                        )
+                    })
+                    .or_else(|| {
+                        response.directory_results.as_ref().map(|results| {
+                            (
+                                results.processed_documents,
+                                results.matched_documents,
+                                results.skipped_documents,
+                            )
+                        })
+                    })
+                    .unwrap_or((
+                        response.prompt_matches.len(),
+                        response.prompt_matches.len(),
+                        0,
+                    ));
+                self.faculty_scope = Some(response.details.faculty_scope.clone());
+                self.program_filters = response.details.program_filters.clone();
+                self.processed_count = Some(counts.0);
+                self.matched_count = Some(counts.1);
+                self.skipped_count = Some(counts.2);
+                self.output_filename = response.details.output_filename.clone();
+                self.warning_count = response.warnings.len();
+                self.succeeded = true;
+            }
+            Err(err) => {
+                self.succeeded = false;
+                self.error = Some(err.to_string());
+            }
+        }
+    }
+}
+
+fn audit_log_path(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    Ok(dataset_directory(app_handle)?.join(AUDIT_LOG_NAME))
+}
+
+fn append_audit_log(app_handle: &dyn MatchingHost, entry: &AuditLogEntry) -> Result<(), String> {
+    let path = audit_log_path(app_handle)?;
+    ensure_dataset_directory(&path)?;
+    let mut line = serde_json::to_string(entry)
+        .map_err(|err| format!("Unable to serialize the audit log entry: {err}"))?;
+    line.push('\n');
+
+    let current_size = fs::metadata(&path)
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    if current_size + line.len() as u64 > AUDIT_LOG_MAX_BYTES {
+        fs::rename(&path, path.with_extension("log.1"))
+            .map_err(|err| format!("Unable to rotate the audit log: {err}"))?;
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| format!("Unable to open the audit log: {err}"))?;
+    file.write_all(line.as_bytes())
+        .map_err(|err| format!("Unable to write the audit log: {err}"))
+}
+
+#[tauri::command]
+fn get_audit_log(
+    app_handle: tauri::AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<AuditLogEntry>, String> {
+    let limit = limit
+        .filter(|limit| *limit > 0)
+        .unwrap_or(AUDIT_LOG_DEFAULT_LIMIT);
+    let path = audit_log_path(&app_handle)?;
+
+    let mut entries = Vec::new();
+    for candidate in [path.clone(), path.with_extension("log.1")] {
+        if entries.len() >= limit || !candidate.exists() {
+            continue;
+        }
+        let data =
+            fs::read(&candidate).map_err(|err| format!("Unable to read the audit log: {err}"))?;
+        let text = String::from_utf8_lossy(&data);
+        let mut older: Vec<AuditLogEntry> = text
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .take(limit - entries.len())
+            .collect();
+        older.reverse();
+        older.append(&mut entries);
+        entries = older;
+    }
+
+    entries.reverse();
+    Ok(entries)
+}
+
+fn run_history_enabled() -> bool {
+    std::env::var(RUN_HISTORY_DISABLE_ENV)
+        .map(|value| {
+            let value = value.trim().to_ascii_lowercase();
+            value.is_empty() || matches!(value.as_str(), "0" | "false" | "no" | "off")
+        })
+        .unwrap_or(true)
+}
+
+fn run_history_directory(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    Ok(dataset_directory(app_handle)?.join(RUN_HISTORY_DIRECTORY))
+}
+
+fn load_run_history(app_handle: &dyn MatchingHost) -> Result<Vec<RunHistoryEntry>, String> {
+    let path = run_history_directory(app_handle)?.join(RUN_HISTORY_INDEX_NAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read(&path).map_err(|err| format!("Unable to read the run history: {err}"))?;
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_slice(&data).map_err(|err| format!("Unable to parse the run history: {err}"))
+}
+
+fn record_run_history(
+    app_handle: &dyn MatchingHost,
+    response: &SubmissionResponse,
+    saved_embeddings: Option<&SavedRunEmbeddings>,
+) -> Result<(), String> {
+    let artifacts: Vec<&GeneratedSpreadsheet> = [
+        response
+            .directory_results
+            .as_ref()
+            .map(|results| &results.spreadsheet),
+        response
+            .spreadsheet_results
+            .as_ref()
+            .map(|results| &results.spreadsheet),
+        response
+            .directory_results
+            .as_ref()
+            .and_then(|results| results.student_reports.as_ref()),
+        response
+            .spreadsheet_results
+            .as_ref()
+            .and_then(|results| results.student_reports.as_ref()),
+        response.json_export.as_ref(),
+        response.pdf_report.as_ref(),
+        response.bundle.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let directory = run_history_directory(app_handle)?;
+    fs::create_dir_all(&directory)
+        .map_err(|err| format!("Unable to create the run history directory: {err}"))?;
+
+    let created_at = Utc::now();
+    let run_id = created_at.format("%Y%m%d-%H%M%S-%3f").to_string();
+    let artifacts_json = serde_json::to_vec(&artifacts)
+        .map_err(|err| format!("Unable to serialize the run artifacts: {err}"))?;
+    fs::write(directory.join(format!("{run_id}.json")), artifacts_json)
+        .map_err(|err| format!("Unable to persist the run artifacts: {err}"))?;
+    if let Some(saved_embeddings) = saved_embeddings {
+        let embeddings_json = serde_json::to_vec(saved_embeddings)
+            .map_err(|err| format!("Unable to serialize the prompt embeddings: {err}"))?;
+        fs::write(
+            directory.join(format!("{run_id}{RUN_EMBEDDINGS_SUFFIX}")),
+            embeddings_json,
+        )
+        .map_err(|err| format!("Unable to persist the prompt embeddings: {err}"))?;
+    }
+
+    let details = serde_json::to_value(&response.details)
+        .map_err(|err| format!("Unable to serialize the run details: {err}"))?;
+    let mut entries = load_run_history(app_handle)?;
+    entries.push(RunHistoryEntry {
+        run_id,
+        created_at: created_at.to_rfc3339(),
+        summary: response.summary.clone(),
+        warnings: response.warnings.clone(),
+        details,
+        artifacts: artifacts
+            .iter()
+            .map(|artifact| RunHistoryArtifact {
+                filename: artifact.filename.clone(),
+                mime_type: artifact.mime_type.clone(),
+                saved_path: artifact.saved_path.clone(),
+            })
+            .collect(),
+        rerankable: saved_embeddings.is_some(),
+    });
+    if entries.len() > RUN_HISTORY_LIMIT {
+        let expired: Vec<RunHistoryEntry> =
+            entries.drain(..entries.len() - RUN_HISTORY_LIMIT).collect();
+        for entry in expired {
+            let _ = fs::remove_file(directory.join(format!("{}.json", entry.run_id)));
+            let _ =
+                fs::remove_file(directory.join(format!("{}{RUN_EMBEDDINGS_SUFFIX}", entry.run_id)));
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&entries)
+        .map_err(|err| format!("Unable to serialize the run history: {err}"))?;
+    fs::write(directory.join(RUN_HISTORY_INDEX_NAME), json)
+        .map_err(|err| format!("Unable to persist the run history: {err}"))?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StorageArtifact {
+    path: String,
+    category: StorageCategory,
+    bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StorageCategoryUsage {
+    category: StorageCategory,
+    bytes: u64,
+    files: usize,
+    removable: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StorageUsage {
+    directory: String,
+    total_bytes: u64,
+    categories: Vec<StorageCategoryUsage>,
+    artifacts: Vec<StorageArtifact>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StorageCleanupResult {
+    bytes_freed: u64,
+    files_removed: usize,
+    warnings: Vec<String>,
+}
+
+#[tauri::command]
+fn get_storage_usage(app_handle: tauri::AppHandle) -> Result<StorageUsage, String> {
+    let directory = dataset_directory(&app_handle)?;
+    let artifacts = collect_storage_artifacts(&app_handle)?;
+
+    let mut usage: HashMap<StorageCategory, (u64, usize)> = HashMap::new();
+    for (_, artifact) in &artifacts {
+        let entry = usage.entry(artifact.category).or_default();
+        entry.0 += artifact.bytes;
+        entry.1 += 1;
+    }
+    let mut categories: Vec<StorageCategoryUsage> = usage
+        .into_iter()
+        .map(|(category, (bytes, files))| StorageCategoryUsage {
+            category,
+            bytes,
+            files,
+            removable: category.removable(),
+        })
+        .collect();
+    categories.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+
+    Ok(StorageUsage {
+        directory: directory.to_string_lossy().into_owned(),
+        total_bytes: artifacts.iter().map(|(_, artifact)| artifact.bytes).sum(),
+        categories,
+        artifacts: artifacts
+            .into_iter()
+            .map(|(_, artifact)| artifact)
+            .collect(),
+    })
+}
+
+#[tauri::command]
+fn cleanup_storage(
+    app_handle: tauri::AppHandle,
+    categories: Vec<StorageCategory>,
+) -> Result<StorageCleanupResult, AppError> {
+    if categories.is_empty() {
+        return Err(AppError::invalid_input(
+            "categories",
+            "Select at least one storage category to clean up.",
+        ));
+    }
+    if let Some(category) = categories.iter().find(|category| !category.removable()) {
+        return Err(AppError::invalid_input(
+            "categories",
+            format!(
+                "The {} category cannot be cleaned up because the application depends on it.",
+                serde_json::to_value(category)
+                    .ok()
+                    .and_then(|value| value.as_str().map(str::to_string))
+                    .unwrap_or_default()
+            ),
+        ));
+    }
+    let _run_guard = begin_helper_activity(HelperActivity::Matching, None)?;
+
+    let directory = fs::canonicalize(dataset_directory(&app_handle)?)
+        .map_err(|err| format!("Unable to locate the application data directory: {err}"))?;
+    let selected: HashSet<StorageCategory> = categories.into_iter().collect();
+    let mut result = StorageCleanupResult {
+        bytes_freed: 0,
+        files_removed: 0,
+        warnings: Vec::new(),
+    };
+    for (path, artifact) in collect_storage_artifacts(&app_handle)? {
+        if !selected.contains(&artifact.category) {
+            continue;
+        }
+        let inside = fs::canonicalize(&path)
+            .map(|canonical| canonical.starts_with(&directory) && canonical != directory)
+            .unwrap_or(false);
+        if !inside {
+            result.warnings.push(format!(
+                "Skipped '{}' because it is outside the application data directory.",
+                path.display()
+            ));
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                result.bytes_freed += artifact.bytes;
+                result.files_removed += 1;
+            }
+            Err(err) => result
+                .warnings
+                .push(format!("Unable to remove '{}': {err}", path.display())),
+        }
+    }
+
+    if selected.contains(&StorageCategory::PromptCache)
+        && !selected.contains(&StorageCategory::RunHistory)
+    {
+        if let Err(err) = mark_run_history_not_rerankable(&app_handle) {
+            result.warnings.push(err);
+        }
+    }
+    for empty_directory in [FACULTY_EMBEDDING_MODELS_DIRECTORY, RUN_HISTORY_DIRECTORY] {
+        let _ = fs::remove_dir(directory.join(empty_directory));
+    }
+
+    log::info!(
+        "Storage cleanup removed {} file{} ({}).",
+        result.files_removed,
+        if result.files_removed == 1 { "" } else { "s" },
+        format_file_size(result.bytes_freed)
+    );
+    Ok(result)
+}
+
+fn collect_storage_artifacts(
+    app_handle: &dyn MatchingHost,
+) -> Result<Vec<(PathBuf, StorageArtifact)>, String> {
+    let directory = dataset_directory(app_handle)?;
+    let active_dataset = dataset_destination(app_handle).ok();
+    let mut artifacts = Vec::new();
+    let mut pending = vec![directory.clone()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !metadata.is_file() {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&directory) else {
+                continue;
+            };
+            let category =
+                classify_storage_path(relative, active_dataset.as_deref() == Some(&path));
+            artifacts.push((
+                path.clone(),
+                StorageArtifact {
+                    path: path.to_string_lossy().into_owned(),
+                    category,
+                    bytes: metadata.len(),
+                    modified_at: metadata.modified().ok().map(format_system_time),
+                },
+            ));
+        }
+    }
+    artifacts.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(artifacts)
+}
+
+fn classify_storage_path(relative: &Path, active_dataset: bool) -> StorageCategory {
+    let file_name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if (file_name.starts_with('.') && file_name.ends_with(".tmp")) || file_name.ends_with(".log.1")
+    {
+        return StorageCategory::Backups;
+    }
+
+    let top_level = relative
+        .components()
+        .next()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if top_level == RUN_HISTORY_DIRECTORY && file_name.ends_with(RUN_EMBEDDINGS_SUFFIX) {
+        return StorageCategory::PromptCache;
+    }
+    if let Some((_, category)) = STORAGE_ARTIFACTS
+        .iter()
+        .find(|(name, _)| *name == top_level)
+    {
+        return *category;
+    }
+
+    let is_dataset_variant = relative.parent() == Some(Path::new(""))
+        && relative
+            .file_stem()
+            .is_some_and(|stem| stem == FACULTY_DATASET_BASENAME)
+        && relative.extension().is_some_and(|extension| {
+            FACULTY_DATASET_EXTENSIONS
+                .iter()
+                .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+        });
+    match (is_dataset_variant, active_dataset) {
+        (true, true) => StorageCategory::Dataset,
+        (true, false) => StorageCategory::Backups,
+        _ => StorageCategory::Other,
+    }
+}
+
+fn mark_run_history_not_rerankable(app_handle: &dyn MatchingHost) -> Result<(), String> {
+    let mut entries = load_run_history(app_handle)?;
+    if entries.is_empty() {
+        return Ok(());
+    }
+    for entry in &mut entries {
+        entry.rerankable = false;
+    }
+    let json = serde_json::to_string_pretty(&entries)
+        .map_err(|err| format!("Unable to serialize the run history: {err}"))?;
+    fs::write(
+        run_history_directory(app_handle)?.join(RUN_HISTORY_INDEX_NAME),
+        json,
+    )
+    .map_err(|err| format!("Unable to persist the run history: {err}"))
+}
+
+fn load_faculty_dataset_metadata(
+    app_handle: &dyn MatchingHost,
+) -> Result<Option<FacultyDatasetMetadata>, String> {
+    let path = metadata_path(app_handle)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let data = fs::read(&path)
+        .map_err(|err| format!("Unable to read the faculty dataset metadata: {err}"))?;
+    if data.is_empty() {
+        return Ok(None);
+    }
+
+    let metadata = serde_json::from_slice(&data)
+        .map_err(|err| format!("Unable to parse the faculty dataset metadata: {err}"))?;
+    Ok(Some(metadata))
+}
+
+fn count_faculty_by_program(metadata: &FacultyDatasetMetadata) -> Vec<ProgramFacultyCount> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for membership in &metadata.memberships {
+        let programs: HashSet<String> = membership
+            .programs
+            .iter()
+            .map(|program| program.to_lowercase())
+            .collect();
+        for program in programs {
+            *counts.entry(program).or_default() += 1;
+        }
+    }
+
+    metadata
+        .analysis
+        .available_programs
+        .iter()
+        .map(|program| ProgramFacultyCount {
+            program: program.clone(),
+            faculty_count: counts
+                .get(&program.to_lowercase())
+                .copied()
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// How many blank-identifier dataset rows the status message lists before summarizing.
+const BLANK_IDENTIFIER_ROWS_LISTED: usize = 5;
+
+/// How many close program names a missed filter suggests.
+const PROGRAM_SUGGESTION_LIMIT: usize = 3;
+/// The largest edit distance, as a share of the longer name, that still counts as a
+/// likely typo of a program name.
+const PROGRAM_SUGGESTION_MAX_RATIO: f64 = 0.4;
+
+struct ProgramFilterOutcome {
+    rows: HashSet<usize>,
+    /// The requested filters, with each one that matched replaced by the dataset's spelling.
+    programs: Vec<String>,
+    warnings: Vec<String>,
+}
+
+fn filter_faculty_rows_by_program(
+    metadata: &FacultyDatasetMetadata,
+    programs: &[String],
+) -> ProgramFilterOutcome {
+    let mut outcome = ProgramFilterOutcome {
+        rows: HashSet::new(),
+        programs: Vec::new(),
+        warnings: Vec::new(),
+    };
+    if programs.is_empty() {
+        return outcome;
+    }
+
+    let mut known_programs: Vec<&String> = metadata.analysis.available_programs.iter().collect();
+    for membership in &metadata.memberships {
+        known_programs.extend(&membership.programs);
+    }
+    let mut canonical: HashMap<String, &str> = HashMap::new();
+    for program in &known_programs {
+        let key = normalize_program_key(program);
+        if !key.is_empty() {
+            canonical.entry(key).or_insert(program.trim());
+        }
+    }
+
+    let mut seen_keys = HashSet::new();
+    for program in programs {
+        let key = normalize_program_key(program);
+        if !seen_keys.insert(key.clone()) {
+            continue;
+        }
+        let rows: Vec<usize> = metadata
+            .memberships
+            .iter()
+            .filter(|membership| {
+                membership
+                    .programs
+                    .iter()
+                    .any(|candidate| normalize_program_key(candidate) == key)
+            })
+            .map(|membership| membership.row_index)
+            .collect();
+        if rows.is_empty() {
+            outcome.programs.push(program.trim().to_string());
+            outcome
+                .warnings
+                .push(describe_unmatched_program(program.trim(), &key, &canonical));
+            continue;
+        }
+        outcome.rows.extend(rows);
+        outcome.programs.push(
+            canonical
+                .get(&key)
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| program.trim().to_string()),
+        );
+    }
+
+    outcome
+}
+
+/// Compares program names ignoring case, spacing, punctuation and "&" versus "and".
+fn normalize_program_key(program: &str) -> String {
+    fold_identifier_characters(program, true)
+        .to_lowercase()
+        .replace('&', " and ")
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn describe_unmatched_program(
+    program: &str,
+    key: &str,
+    canonical: &HashMap<String, &str>,
+) -> String {
+    let key_length = key.chars().count();
+    let mut suggestions: Vec<(f64, &str)> = canonical
+        .iter()
+        .filter_map(|(candidate_key, name)| {
+            let longest = key_length.max(candidate_key.chars().count());
+            let max_distance = (longest as f64 * PROGRAM_SUGGESTION_MAX_RATIO).floor() as usize;
+            let distance = bounded_edit_distance(key, candidate_key, max_distance)?;
+            Some((distance as f64 / longest.max(1) as f64, *name))
+        })
+        .collect();
+    suggestions.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+    let names: Vec<String> = suggestions
+        .iter()
+        .take(PROGRAM_SUGGESTION_LIMIT)
+        .map(|(_, name)| format!("'{name}'"))
+        .collect();
+
+    match names.split_last() {
+        None => format!("No faculty matched '{program}', and no similar program names were found."),
+        Some((last, [])) => format!("No faculty matched '{program}'; did you mean {last}?"),
+        Some((last, rest)) => format!(
+            "No faculty matched '{program}'; did you mean {} or {last}?",
+            rest.join(", ")
+        ),
+    }
+}
+
+fn clear_faculty_dataset_metadata(app_handle: &dyn MatchingHost) -> Result<(), String> {
+    let path = metadata_path(app_handle)?;
+    if path.exists() {
+        fs::remove_file(&path)
+            .map_err(|err| format!("Unable to clear faculty dataset metadata: {err}"))?;
+    }
+    Ok(())
+}
+
+fn dataset_source_record_path(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    let directory = dataset_directory(app_handle)?;
+    Ok(directory.join(FACULTY_DATASET_SOURCE_NAME))
+}
+
+/// Where the imported faculty dataset came from, plus enough of a fingerprint to
+/// notice when that file has been edited since the import.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FacultyDatasetSourceRecord {
+    path: String,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    modified: Option<String>,
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+enum SourceComparison {
+    Unchanged,
+    Changed(Option<String>),
+    Missing,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn write_faculty_dataset_source_path(
+    app_handle: &dyn MatchingHost,
+    source: &Path,
+) -> Result<(), String> {
+    let record_path = dataset_source_record_path(app_handle)?;
+    ensure_dataset_directory(&record_path)?;
+    let canonical = source
+        .canonicalize()
+        .unwrap_or_else(|_| source.to_path_buf());
+    let metadata = fs::metadata(&canonical).ok();
+    let record = FacultyDatasetSourceRecord {
+        path: canonical.to_string_lossy().into_owned(),
+        size: metadata.as_ref().map(|metadata| metadata.len()),
+        modified: metadata
+            .as_ref()
+            .and_then(|metadata| metadata.modified().ok())
+            .map(format_system_time),
+        sha256: fs::read(&canonical).ok().map(|bytes| sha256_hex(&bytes)),
+    };
+    let json = serde_json::to_vec_pretty(&record)
+        .map_err(|err| format!("Unable to serialize the faculty dataset source record: {err}"))?;
+    fs::write(&record_path, json)
+        .map_err(|err| format!("Unable to record the faculty dataset source path: {err}"))?;
+    Ok(())
+}
+
+fn read_faculty_dataset_source_record(
+    app_handle: &dyn MatchingHost,
+) -> Result<Option<FacultyDatasetSourceRecord>, String> {
+    let record_path = dataset_source_record_path(app_handle)?;
+    if !record_path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&record_path)
+        .map_err(|err| format!("Unable to read the faculty dataset source path: {err}"))?;
+    let trimmed = contents.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    if trimmed.starts_with('{') {
+        return serde_json::from_str(trimmed)
+            .map(Some)
+            .map_err(|err| format!("Unable to parse the faculty dataset source record: {err}"));
+    }
+
+    // Older installs stored only the bare path.
+    Ok(Some(FacultyDatasetSourceRecord {
+        path: trimmed.to_string(),
+        size: None,
+        modified: None,
+        sha256: None,
+    }))
+}
+
+fn read_faculty_dataset_source_path(
+    app_handle: &dyn MatchingHost,
+) -> Result<Option<String>, String> {
+    Ok(read_faculty_dataset_source_record(app_handle)?.map(|record| record.path))
+}
+
+/// Compares the recorded source against the file on disk. Size and modification
+/// time are checked first so routine status polls avoid rehashing the file; the
+/// hash only decides when the metadata moved but the size did not.
+fn compare_faculty_dataset_source(record: &FacultyDatasetSourceRecord) -> SourceComparison {
+    let path = Path::new(&record.path);
+    let Ok(metadata) = fs::metadata(path) else {
+        return SourceComparison::Missing;
+    };
+    if !metadata.is_file() {
+        return SourceComparison::Missing;
+    }
+
+    let modified = metadata.modified().ok().map(format_system_time);
+    let Some(recorded_size) = record.size else {
+        return SourceComparison::Unchanged;
+    };
+    if recorded_size != metadata.len() {
+        return SourceComparison::Changed(modified);
+    }
+    if record.modified.is_some() && record.modified == modified {
+        return SourceComparison::Unchanged;
+    }
+
+    match (&record.sha256, fs::read(path)) {
+        (Some(recorded), Ok(bytes)) if *recorded != sha256_hex(&bytes) => {
+            SourceComparison::Changed(modified)
+        }
+        _ => SourceComparison::Unchanged,
+    }
+}
+
+fn clear_faculty_dataset_source_path(app_handle: &dyn MatchingHost) -> Result<(), String> {
+    let record_path = dataset_source_record_path(app_handle)?;
+    if record_path.exists() {
+        fs::remove_file(&record_path)
+            .map_err(|err| format!("Unable to clear the faculty dataset source path: {err}"))?;
+    }
+    Ok(())
+}
+
+fn dataset_destination(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    let directory = dataset_directory(app_handle)?;
+    for extension in FACULTY_DATASET_EXTENSIONS {
+        let candidate = dataset_path_with_extension(&directory, extension);
+        if candidate.exists() {
+            return Ok(candidate);
+        }
+    }
+
+    Ok(dataset_path_with_extension(
+        &directory,
+        FACULTY_DATASET_DEFAULT_EXTENSION,
+    ))
+}
+
+fn dataset_destination_for_extension(
+    app_handle: &dyn MatchingHost,
+    extension: &str,
+) -> Result<PathBuf, String> {
+    let directory = dataset_directory(app_handle)?;
+    Ok(dataset_path_with_extension(&directory, extension))
+}
+
+fn dataset_directory(app_handle: &dyn MatchingHost) -> Result<PathBuf, String> {
+    app_handle.data_directory()
+}
+
+fn dataset_path_with_extension(directory: &Path, extension: &str) -> PathBuf {
+    directory.join(format!("{}.{}", FACULTY_DATASET_BASENAME, extension))
+}
+
+fn remove_other_dataset_variants(directory: &Path, keep_extension: &str) -> Result<(), String> {
+    for extension in FACULTY_DATASET_EXTENSIONS {
+        if extension.eq_ignore_ascii_case(keep_extension) {
+            continue;
+        }
+
+        let candidate = dataset_path_with_extension(directory, extension);
+        if candidate.exists() {
+            fs::remove_file(&candidate)
+                .map_err(|err| format!("Unable to remove a previous faculty dataset: {err}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn ensure_dataset_directory(path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("Unable to prepare the faculty dataset directory: {err}"))?;
+    }
+    Ok(())
+}
+
+fn compute_delimited_dimensions(data: &[u8], preferred: u8) -> Result<(usize, usize), String> {
+    let detection = detect_delimiter_from_reader(data, preferred)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(detection.delimiter)
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(Cursor::new(data));
+
+    let headers = reader
+        .headers()
+        .map_err(|err| format!("Unable to read the faculty dataset headers: {err}"))?
+        .clone();
+
+    let mut row_count = 0usize;
+    for record in reader.records() {
+        let record =
+            record.map_err(|err| format!("Unable to read the faculty dataset rows: {err}"))?;
+        if record.iter().any(|value| !value.trim().is_empty()) {
+            row_count += 1;
+        }
+    }
+
+    Ok((row_count, headers.len()))
+}
+
+fn compute_excel_dimensions(path: &Path) -> Result<(usize, usize), String> {
+    let mut workbook =
+        open_workbook_auto(path).map_err(|err| format!("Unable to open the dataset: {err}"))?;
+
+    let sheet_name = workbook
+        .sheet_names()
+        .get(0)
+        .cloned()
+        .ok_or_else(|| "The workbook does not contain any worksheets.".to_string())?;
+
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .ok_or_else(|| format!("Unable to read the worksheet named '{sheet_name}'."))?
+        .map_err(|err| format!("Unable to read the worksheet data: {err}"))?;
+
+    let mut rows_iter = range.rows();
+    let header_row = rows_iter
+        .next()
+        .ok_or_else(|| "The worksheet is empty.".to_string())?;
+
+    let column_count = header_row.len();
+    let mut row_count = 0usize;
+
+    for row in rows_iter {
+        if row.iter().any(|cell| !cell_to_string(cell).is_empty()) {
+            row_count += 1;
+        }
+    }
+
+    Ok((row_count, column_count))
+}
+
+fn build_dataset_preview(path: &Path) -> Result<SpreadsheetPreview, String> {
+    let (mut headers, mut rows) = read_spreadsheet(path)?;
+    align_row_lengths(&mut headers, &mut rows);
+    let (prompt_columns, identifier_columns) = suggest_spreadsheet_columns(&headers, &rows);
+    let detection = spreadsheet_delimiter_detection(path);
+
+    Ok(SpreadsheetPreview {
+        headers: header_labels(&headers),
+        rows,
+        suggested_prompt_columns: prompt_columns,
+        suggested_identifier_columns: identifier_columns,
+        delimiter: detection.map(|detection| detection.name().into()),
+        delimiter_consistency: detection.map(|detection| detection.consistency),
+    })
+}
+
+fn format_system_time(time: SystemTime) -> String {
+    let datetime: DateTime<Utc> = time.into();
+    datetime.to_rfc3339()
+}
+
+fn suggest_spreadsheet_columns(
+    headers: &[String],
+    rows: &[Vec<String>],
+) -> (Vec<usize>, Vec<usize>) {
+    const PROMPT_KEYWORDS: &[&str] = &[
+        "prompt",
+        "interest",
+        "research",
+        "description",
+        "summary",
+        "essay",
+        "statement",
+        "focus",
+        "topic",
+        "goal",
+    ];
+    const IDENTIFIER_KEYWORDS: &[&str] = &[
+        "id",
+        "identifier",
+        "name",
+        "first",
+        "last",
+        "student",
+        "email",
+        "netid",
+        "number",
+        "uid",
+    ];
+
+    let mut prompt_columns = Vec::new();
+    let mut identifier_columns = Vec::new();
 
-    message
-}
+    for (index, header) in headers.iter().enumerate() {
+        let header_lower = header.to_lowercase();
+        if header_lower.is_empty() {
+            continue;
+        }
 
-fn emit_progress_from_line(app_handle: &tauri::AppHandle, total_rows: usize, bytes: &[u8]) -> bool {
-    let text = String::from_utf8_lossy(bytes);
-    let trimmed = text.trim_end_matches(['\r', '\n']);
-    if let Some(json_str) = trimmed.strip_prefix("PROGRESS ") {
-        match serde_json::from_str::<EmbeddingProgressUpdate>(json_str.trim()) {
-            Ok(mut update) => {
-                if update.total_rows == 0 {
-                    update.total_rows = total_rows;
-                }
-                emit_faculty_embedding_progress(app_handle, update);
-                true
-            }
-            Err(_) => false,
+        if PROMPT_KEYWORDS
+            .iter()
+            .any(|keyword| header_lower.contains(keyword))
+        {
+            prompt_columns.push(index);
         }
-    } else {
-        false
-    }
-}
 
-fn trimmed_message_or_default(buffer: &[u8], default_message: &str) -> String {
-    let text = String::from_utf8_lossy(buffer);
-    let trimmed = text.trim();
-    if trimmed.is_empty() {
-        default_message.to_string()
-    } else {
-        trimmed.to_string()
+        if IDENTIFIER_KEYWORDS
+            .iter()
+            .any(|keyword| header_lower.contains(keyword))
+        {
+            identifier_columns.push(index);
+        }
     }
-}
 
-fn spawn_embedding_helper(
-    app_handle: &tauri::AppHandle,
-) -> Result<(Receiver<CommandEvent>, CommandChild), String> {
-    app_handle
-        .shell()
-        .sidecar("embedding-helper")
-        .map_err(|err| format!("Unable to configure the embedding helper: {err}"))?
-        .env("HF_HUB_DISABLE_PROGRESS_BARS", "1")
-        .env("TOKENIZERS_PARALLELISM", "false")
-        .env("PYTHONUTF8", "1")
-        .env("PYTHONUNBUFFERED", "1")
-        .spawn()
-        .map_err(|err| format!("Unable to launch the embedding helper: {err}"))
-}
+    sort_and_dedup(&mut prompt_columns);
+    sort_and_dedup(&mut identifier_columns);
 
-#[tauri::command]
-fn get_faculty_dataset_status(
-    app_handle: tauri::AppHandle,
-) -> Result<FacultyDatasetStatus, String> {
-    build_faculty_dataset_status(&app_handle)
-}
+    let stats = compute_column_stats(headers, rows);
 
-#[tauri::command]
-fn preview_faculty_roster(
-    app_handle: tauri::AppHandle,
-    path: String,
-) -> Result<FacultyRosterPreviewResponse, String> {
-    let trimmed = path.trim();
-    if trimmed.is_empty() {
-        return Err("Provide a TSV, TXT, or Excel file to analyze for the faculty roster.".into());
-    }
+    if prompt_columns.is_empty() {
+        let mut candidates: Vec<&ColumnStats> = stats
+            .iter()
+            .filter(|stat| stat.non_empty > 0 && stat.numeric_ratio < 0.6)
+            .collect();
 
-    let source = resolve_existing_path(Some(trimmed.to_string()), false, "Faculty roster file")?;
-    let (mut headers, mut rows) = read_spreadsheet_with_limit(&source, Some(10))?;
-    align_row_lengths(&mut headers, &mut rows);
+        candidates.sort_by(|a, b| {
+            b.average_length
+                .partial_cmp(&a.average_length)
+                .unwrap_or(Ordering::Equal)
+                .then(b.max_length.cmp(&a.max_length))
+        });
 
-    let metadata = load_faculty_dataset_metadata(&app_handle)?.ok_or_else(|| {
-        "The faculty dataset metadata is unavailable. Refresh the dataset analysis before selecting a roster.".to_string()
-    })?;
+        let mut fallback = Vec::new();
+        for stat in &candidates {
+            if stat.average_length >= 18.0 || stat.max_length >= 60 {
+                fallback.push(stat.index);
+            }
+        }
 
-    let mut warnings = Vec::new();
-    let mut suggestions: HashMap<String, Option<usize>> = HashMap::new();
+        if fallback.is_empty() {
+            if let Some(best) = candidates.first() {
+                fallback.push(best.index);
+            }
+        }
 
-    if metadata.analysis.identifier_columns.is_empty() {
-        warnings.push("No identifier columns are defined in the active faculty dataset.".into());
+        prompt_columns = fallback;
     }
 
-    let header_map = build_header_index_map(&headers);
+    if identifier_columns.is_empty() {
+        let mut candidates: Vec<&ColumnStats> =
+            stats.iter().filter(|stat| stat.non_empty > 0).collect();
 
-    for identifier in &metadata.analysis.identifier_columns {
-        let normalized_identifier = identifier.trim().to_lowercase();
-        let mut column_index = header_map.get(&normalized_identifier).copied();
+        candidates.sort_by(|a, b| {
+            a.average_length
+                .partial_cmp(&b.average_length)
+                .unwrap_or(Ordering::Equal)
+                .then(b.non_empty.cmp(&a.non_empty))
+        });
 
-        if column_index.is_none() {
-            let normalized_target = normalize_identifier_label(identifier);
-            if !normalized_target.is_empty() {
-                for (candidate_index, header) in headers.iter().enumerate() {
-                    if normalize_identifier_label(header) == normalized_target {
-                        column_index = Some(candidate_index);
-                        break;
-                    }
-                }
+        let mut fallback = Vec::new();
+        for stat in &candidates {
+            if stat.average_length <= 36.0 || stat.numeric_ratio >= 0.5 {
+                fallback.push(stat.index);
+            }
+            if fallback.len() >= 3 {
+                break;
             }
         }
 
-        if let Some(index) = column_index {
-            suggestions.insert(identifier.clone(), Some(index));
-        } else {
-            warnings.push(format!(
-                "No roster column matched the faculty identifier '{identifier}'.",
-            ));
-            suggestions.insert(identifier.clone(), None);
+        if fallback.is_empty() {
+            if let Some(best) = candidates.first() {
+                fallback.push(best.index);
+            }
         }
-    }
-
-    let preview = SpreadsheetPreview {
-        headers,
-        rows,
-        suggested_prompt_columns: Vec::new(),
-        suggested_identifier_columns: Vec::new(),
-    };
 
-    Ok(FacultyRosterPreviewResponse {
-        preview,
-        suggested_identifier_matches: suggestions,
-        warnings,
-    })
-}
+        identifier_columns = fallback;
+    }
 
-#[tauri::command]
-fn preview_faculty_dataset_replacement(
-    path: String,
-) -> Result<FacultyDatasetPreviewResponse, String> {
-    let trimmed = path.trim();
-    if trimmed.is_empty() {
-        return Err("Select a TSV, TXT, or Excel file to import for the faculty dataset.".into());
+    if prompt_columns.is_empty() && !headers.is_empty() {
+        prompt_columns.push(headers.len() - 1);
     }
 
-    let source = resolve_existing_path(Some(trimmed.to_string()), false, "Faculty dataset file")?;
+    if identifier_columns.is_empty() && !headers.is_empty() {
+        identifier_columns.push(0);
+    }
 
-    let extension = source
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    sort_and_dedup(&mut prompt_columns);
+    sort_and_dedup(&mut identifier_columns);
 
-    if !FACULTY_DATASET_EXTENSIONS.contains(&extension.as_str()) {
-        return Err(
-            "Select a tab-delimited .tsv or .txt file, or an Excel workbook (.xlsx or .xls) to replace the faculty dataset.".into(),
-        );
-    }
+    (prompt_columns, identifier_columns)
+}
 
-    let preview = build_dataset_preview(&source)?;
-    let program_columns = suggest_program_columns(&preview.headers, &preview.rows);
+fn sort_and_dedup(values: &mut Vec<usize>) {
+    values.sort_unstable();
+    values.dedup();
+}
 
-    Ok(FacultyDatasetPreviewResponse {
-        suggested_embedding_columns: preview.suggested_prompt_columns.clone(),
-        suggested_identifier_columns: preview.suggested_identifier_columns.clone(),
-        suggested_program_columns: program_columns,
-        preview,
-    })
+struct ColumnStats {
+    index: usize,
+    non_empty: usize,
+    average_length: f64,
+    max_length: usize,
+    numeric_ratio: f64,
 }
 
-#[tauri::command]
-fn replace_faculty_dataset(
-    app_handle: tauri::AppHandle,
-    path: String,
-    configuration: Option<FacultyDatasetColumnConfiguration>,
-) -> Result<FacultyDatasetStatus, String> {
-    let trimmed = path.trim();
-    if trimmed.is_empty() {
-        return Err("Select a TSV, TXT, or Excel file to import for the faculty dataset.".into());
-    }
+fn compute_column_stats(headers: &[String], rows: &[Vec<String>]) -> Vec<ColumnStats> {
+    let column_count = headers.len();
+    let mut stats = Vec::new();
 
-    let source = resolve_existing_path(Some(trimmed.to_string()), false, "Faculty dataset file")?;
+    for index in 0..column_count {
+        let mut non_empty = 0usize;
+        let mut total_length = 0usize;
+        let mut max_length = 0usize;
+        let mut numeric_like = 0usize;
 
-    let extension = source
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+        for row in rows {
+            if let Some(value) = row.get(index) {
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                non_empty += 1;
+                let length = trimmed.chars().count();
+                total_length += length;
+                if length > max_length {
+                    max_length = length;
+                }
+                if is_numeric_like(trimmed) {
+                    numeric_like += 1;
+                }
+            }
+        }
 
-    if !FACULTY_DATASET_EXTENSIONS.contains(&extension.as_str()) {
-        return Err(
-            "Select a tab-delimited .tsv or .txt file, or an Excel workbook (.xlsx or .xls) to replace the faculty dataset.".into(),
-        );
-    }
+        let average_length = if non_empty > 0 {
+            total_length as f64 / non_empty as f64
+        } else {
+            0.0
+        };
 
-    let destination = dataset_destination_for_extension(&app_handle, &extension)?;
-    ensure_dataset_directory(&destination)?;
-    if let Some(directory) = destination.parent() {
-        remove_other_dataset_variants(directory, &extension)?;
+        let numeric_ratio = if non_empty > 0 {
+            numeric_like as f64 / non_empty as f64
+        } else {
+            0.0
+        };
+
+        stats.push(ColumnStats {
+            index,
+            non_empty,
+            average_length,
+            max_length,
+            numeric_ratio,
+        });
     }
-    fs::copy(&source, &destination)
-        .map_err(|err| format!("Unable to replace the faculty dataset: {err}"))?;
 
-    write_faculty_dataset_source_path(&app_handle, &source)?;
+    stats
+}
 
-    let mut status =
-        build_faculty_dataset_status_with_overrides(&app_handle, configuration.as_ref())?;
-    if status.message.is_none() {
-        status.message = Some("Faculty dataset replaced successfully.".into());
-        status.message_variant = Some("success".into());
-    } else if status.message_variant.is_none() {
-        status.message_variant = Some(if status.is_valid {
-            "success".into()
-        } else {
-            "error".into()
-        });
+fn is_numeric_like(value: &str) -> bool {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return false;
     }
 
-    Ok(status)
+    let has_alpha = trimmed.chars().any(|c| c.is_ascii_alphabetic());
+    !has_alpha
 }
 
-#[tauri::command]
-fn restore_default_faculty_dataset(
-    app_handle: tauri::AppHandle,
-) -> Result<FacultyDatasetStatus, String> {
-    let destination =
-        dataset_destination_for_extension(&app_handle, FACULTY_DATASET_DEFAULT_EXTENSION)?;
-    ensure_default_faculty_dataset(&app_handle, &destination)?;
+fn resolve_existing_path(
+    raw_path: Option<String>,
+    expects_directory: bool,
+    label: &str,
+) -> Result<PathBuf, String> {
+    let provided = raw_path
+        .as_deref()
+        .map(trim_pasted_path)
+        .unwrap_or_default();
 
-    let mut status = build_faculty_dataset_status(&app_handle)?;
-    if status.message.is_none() {
-        status.message = Some("Faculty dataset reset to the packaged default.".into());
-        status.message_variant = Some("success".into());
-    } else if status.message_variant.is_none() {
-        status.message_variant = Some(if status.is_valid {
-            "success".into()
-        } else {
-            "error".into()
-        });
+    if provided.is_empty() {
+        return Err(format!("{label} path is required."));
     }
 
-    Ok(status)
-}
+    let path = expand_user_path(provided);
+    let metadata = fs::metadata(&path).map_err(|err| {
+        if path.as_os_str() == provided {
+            format!("{label} was not found: {} ({err})", path.display())
+        } else {
+            format!(
+                "{label} was not found: {} (expanded from '{provided}'; {err})",
+                path.display()
+            )
+        }
+    })?;
 
-fn ensure_default_faculty_dataset(
-    app_handle: &tauri::AppHandle,
-    destination: &Path,
-) -> Result<(), String> {
-    ensure_dataset_directory(destination)?;
-    if let Some(directory) = destination.parent() {
-        remove_other_dataset_variants(directory, FACULTY_DATASET_DEFAULT_EXTENSION)?;
+    if expects_directory && !metadata.is_dir() {
+        return Err(format!(
+            "{label} is expected to be a directory: {}",
+            path.display()
+        ));
     }
-    fs::write(destination, DEFAULT_FACULTY_DATASET)
-        .map_err(|err| format!("Unable to restore the default faculty dataset: {err}"))?;
 
-    let embeddings_path = dataset_directory(app_handle)?.join(FACULTY_EMBEDDINGS_NAME);
-    ensure_dataset_directory(&embeddings_path)?;
-    fs::write(&embeddings_path, DEFAULT_FACULTY_EMBEDDINGS)
-        .map_err(|err| format!("Unable to restore the default faculty embeddings: {err}"))?;
+    if !expects_directory && !metadata.is_file() {
+        return Err(format!(
+            "{label} is expected to be a file: {}",
+            path.display()
+        ));
+    }
 
-    let _ = clear_faculty_dataset_source_path(app_handle);
-    Ok(())
+    Ok(path)
 }
 
-#[tauri::command]
-fn save_generated_spreadsheet(
-    path: String,
-    content: String,
-    encoding: Option<String>,
-) -> Result<(), String> {
+fn trim_pasted_path(path: &str) -> &str {
     let trimmed = path.trim();
-    if trimmed.is_empty() {
-        return Err("Select a location to save the generated spreadsheet.".into());
-    }
-
-    let destination = PathBuf::from(trimmed);
-    if let Some(parent) = destination.parent() {
-        if !parent.exists() {
-            return Err("The selected directory does not exist.".into());
+    for quote in ['"', '\''] {
+        if let Some(inner) = trimmed
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner.trim();
         }
     }
+    trimmed
+}
 
-    let data = match encoding
-        .as_deref()
-        .map(|value| value.trim().to_ascii_lowercase())
-        .as_deref()
-    {
-        Some("base64") => Base64Engine
-            .decode(content.as_bytes())
-            .map_err(|err| format!("Unable to decode the generated spreadsheet: {err}"))?,
-        _ => content.into_bytes(),
+fn expand_user_path(path: &str) -> PathBuf {
+    let path = trim_pasted_path(path);
+    let expanded = expand_environment_references(path);
+    let Some(expanded_str) = expanded.to_str() else {
+        return PathBuf::from(expanded);
     };
 
-    fs::write(&destination, data)
-        .map_err(|err| format!("Unable to save the generated spreadsheet: {err}"))?;
+    let Some(rest) = expanded_str.strip_prefix('~') else {
+        return PathBuf::from(expanded);
+    };
+    let split = rest.find(['/', '\\']).unwrap_or(rest.len());
+    let (user, remainder) = rest.split_at(split);
+    let remainder = remainder.trim_start_matches(['/', '\\']);
 
-    Ok(())
+    let home = if user.is_empty() {
+        home_dir()
+    } else {
+        user_home_dir(user)
+    };
+    match home {
+        Some(home) if remainder.is_empty() => home,
+        Some(home) => home.join(remainder),
+        None => PathBuf::from(expanded),
+    }
 }
 
-#[tauri::command]
-fn analyze_spreadsheet(path: String) -> Result<SpreadsheetPreview, String> {
-    if path.trim().is_empty() {
-        return Err("Provide a spreadsheet path to analyze.".into());
+fn expand_environment_references(path: &str) -> std::ffi::OsString {
+    let mut expanded = std::ffi::OsString::new();
+    let mut rest = path;
+
+    while let Some(start) = rest.find(['%', '$']) {
+        expanded.push(&rest[..start]);
+        let marker = &rest[start..];
+        let reference = if let Some(after) = marker.strip_prefix('%') {
+            after.find('%').map(|end| (&after[..end], end + 2))
+        } else if let Some(after) = marker.strip_prefix("${") {
+            after.find('}').map(|end| (&after[..end], end + 3))
+        } else {
+            let after = &marker[1..];
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            Some((&after[..end], end + 1))
+        };
+
+        match reference {
+            Some((name, consumed))
+                if !name.is_empty()
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                match std::env::var_os(name) {
+                    Some(value) => expanded.push(value),
+                    None => expanded.push(&marker[..consumed]),
+                }
+                rest = &marker[consumed..];
+            }
+            _ => {
+                expanded.push(&marker[..1]);
+                rest = &marker[1..];
+            }
+        }
     }
 
-    let spreadsheet = resolve_existing_path(Some(path), false, "Spreadsheet file")?;
-    let (mut headers, mut rows) = read_spreadsheet(&spreadsheet)?;
-    align_row_lengths(&mut headers, &mut rows);
-    let (prompt_columns, identifier_columns) = suggest_spreadsheet_columns(&headers, &rows);
+    expanded.push(rest);
+    expanded
+}
 
-    Ok(SpreadsheetPreview {
-        headers,
-        rows,
-        suggested_prompt_columns: prompt_columns,
-        suggested_identifier_columns: identifier_columns,
-    })
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|value| !value.is_empty())
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
 }
 
-fn normalize_programs(programs: Vec<String>) -> Vec<String> {
-    let mut seen = HashSet::new();
-    let mut cleaned = Vec::new();
+#[cfg(unix)]
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    let passwd = fs::read_to_string("/etc/passwd").ok();
+    let from_passwd = passwd.as_deref().and_then(|contents| {
+        contents.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            (fields.len() >= 6 && fields[0] == user).then(|| PathBuf::from(fields[5]))
+        })
+    });
 
-    for program in programs {
-        let trimmed = program.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
+    from_passwd.or_else(|| {
+        home_dir()
+            .and_then(|home| home.parent().map(|parent| parent.join(user)))
+            .filter(|candidate| candidate.is_dir())
+    })
+}
 
-        let key = trimmed.to_lowercase();
-        if seen.insert(key) {
-            cleaned.push(trimmed.to_string());
-        }
+#[cfg(not(unix))]
+fn user_home_dir(_user: &str) -> Option<PathBuf> {
+    None
+}
+
+fn validate_extension(path: &Path, allowed: &[&str], label: &str) -> Option<String> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if allowed.iter().any(|value| ext.eq_ignore_ascii_case(value)) => None,
+        Some(ext) => Some(format!(
+            "The selected {label} uses '.{ext}', which is outside the expected extensions: {}.",
+            allowed.join(", ")
+        )),
+        None => Some(format!(
+            "The selected {label} does not include an extension. Confirm it is supported."
+        )),
     }
+}
 
-    cleaned
+fn resolve_max_prompt_characters(limit: Option<usize>) -> usize {
+    limit
+        .unwrap_or(PROMPT_MAX_CHARACTERS_DEFAULT)
+        .max(PROMPT_MAX_CHARACTERS_MIN)
 }
 
-fn normalize_columns(columns: Vec<String>) -> Vec<String> {
-    let mut seen = HashSet::new();
-    let mut cleaned = Vec::new();
+fn truncate_prompt_text(text: &str, max_characters: usize) -> Option<String> {
+    if text.chars().count() <= max_characters {
+        return None;
+    }
 
-    for column in columns {
-        let trimmed = column.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
+    let budget = max_characters.saturating_sub(PROMPT_TRUNCATION_MARKER.chars().count());
+    let end = text
+        .char_indices()
+        .nth(budget)
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len());
+    let candidate = &text[..end];
+    let sentence_end = candidate
+        .char_indices()
+        .filter(|(offset, ch)| {
+            matches!(ch, '.' | '!' | '?' | '\n')
+                && candidate[offset + ch.len_utf8()..]
+                    .chars()
+                    .next()
+                    .is_none_or(char::is_whitespace)
+        })
+        .map(|(offset, ch)| offset + ch.len_utf8())
+        .next_back()
+        .filter(|offset| candidate[..*offset].chars().count() >= budget / 2);
+    let cut = sentence_end
+        .or_else(|| candidate.rfind(char::is_whitespace))
+        .unwrap_or(end);
+
+    let mut truncated = text[..cut].trim_end().to_string();
+    truncated.push_str(PROMPT_TRUNCATION_MARKER);
+    Some(truncated)
+}
 
-        let key = trimmed.to_lowercase();
-        if seen.insert(key) {
-            cleaned.push(trimmed.to_string());
+/// Shortens text to at most `max_characters` characters plus an ellipsis, cutting only
+/// between grapheme clusters so combined emoji and accented letters stay intact.
+fn build_prompt_preview(text: &str, max_characters: usize) -> String {
+    let mut characters = 0usize;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        characters += grapheme.chars().count();
+        if characters > max_characters {
+            let mut preview = text[..offset].to_string();
+            preview.push('…');
+            return preview;
         }
     }
 
-    cleaned
+    text.to_string()
 }
 
-fn normalize_identifier_value(value: &str) -> String {
-    value
-        .split_whitespace()
-        .filter(|segment| !segment.is_empty())
-        .collect::<Vec<_>>()
-        .join(" ")
-        .to_lowercase()
+fn resolve_prompt_preview_characters(limit: Option<usize>) -> usize {
+    limit
+        .unwrap_or(PROMPT_PREVIEW_CHARACTERS_DEFAULT)
+        .clamp(PROMPT_PREVIEW_CHARACTERS_MIN, PROMPT_PREVIEW_CHARACTERS_MAX)
 }
 
-fn normalize_identifier_label(value: &str) -> String {
-    value
-        .chars()
-        .filter(|ch| ch.is_alphanumeric())
-        .collect::<String>()
-        .to_lowercase()
-}
+fn build_summary(
+    task_type: &TaskType,
+    faculty_scope: &FacultyScope,
+    faculty_per_student: u32,
+    program_count: usize,
+    roster_faculty_count: Option<usize>,
+    custom_faculty_mode: CustomFacultyMode,
+    faculty_in_scope: Option<usize>,
+) -> String {
+    let input_summary = match task_type {
+        TaskType::Prompt => "a single prompt".to_string(),
+        TaskType::Document => "one document".to_string(),
+        TaskType::Spreadsheet => "a spreadsheet of prompts".to_string(),
+        TaskType::Directory => "a directory of documents".to_string(),
+    };
 
-fn read_spreadsheet(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
-    read_spreadsheet_with_limit(path, Some(10))
-}
+    let scope_summary = match faculty_scope {
+        FacultyScope::All => "the complete faculty roster".to_string(),
+        FacultyScope::Program => format!(
+            "faculty filtered to {program_count} program{}",
+            if program_count == 1 { "" } else { "s" }
+        ),
+        FacultyScope::Custom => {
+            // Only claim the roster filtered the faculty when it matched someone.
+            let roster_faculty = roster_faculty_count.filter(|count| *count > 0);
+            let roster_summary = match (custom_faculty_mode, roster_faculty) {
+                (CustomFacultyMode::Include, Some(count)) => {
+                    format!("the {count} faculty on the provided roster spreadsheet")
+                }
+                (CustomFacultyMode::Include, None) => {
+                    "a roster spreadsheet that matched no faculty".to_string()
+                }
+                (CustomFacultyMode::Exclude, Some(count)) => {
+                    format!("all faculty except the {count} on the provided roster spreadsheet")
+                }
+                (CustomFacultyMode::Exclude, None) => {
+                    "all faculty, since the roster spreadsheet matched no one to exclude"
+                        .to_string()
+                }
+            };
+            if program_count > 0 {
+                format!(
+                    "{roster_summary} limited to {program_count} program{}",
+                    if program_count == 1 { "" } else { "s" }
+                )
+            } else {
+                roster_summary
+            }
+        }
+    };
 
-fn read_full_spreadsheet(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
-    read_spreadsheet_with_limit(path, None)
+    let summary = match faculty_in_scope {
+        Some(in_scope) if in_scope < faculty_per_student as usize => format!(
+            "Ready to match {input_summary} against {scope_summary}. Each student will receive up to {in_scope} of the requested {faculty_per_student} faculty recommendation{plural}.",
+            plural = if faculty_per_student == 1 { "" } else { "s" }
+        ),
+        _ => format!(
+            "Ready to match {input_summary} against {scope_summary}. Each student will receive up to {faculty_per_student} faculty recommendation{plural}.",
+            plural = if faculty_per_student == 1 { "" } else { "s" }
+        ),
+    };
+
+    summary
 }
 
-fn read_spreadsheet_with_limit(
-    path: &Path,
-    max_rows: Option<usize>,
-) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
-    let extension = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_shell::init())
+        .manage(EmbeddingIndexCache::default())
+        .setup(|app| {
+            if let Err(err) = init_file_logging(app.handle()) {
+                eprintln!("{err}");
+            }
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = warmup_embedding_helper(handle).await {
+                    eprintln!("Failed to warm up embedding helper: {err}");
+                    log::error!("Failed to warm up embedding helper: {err}");
+                }
+            });
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            submit_matching_request,
+            validate_submission,
+            update_faculty_embeddings,
+            ensure_embedding_helper_ready,
+            analyze_spreadsheet,
+            get_faculty_dataset_status,
+            list_available_programs,
+            get_embedding_index_status,
+            preview_faculty_roster,
+            list_roster_mapping_presets,
+            save_roster_mapping_preset,
+            get_app_settings,
+            set_app_settings,
+            get_log_file_path,
+            get_recent_logs,
+            list_recent_runs,
+            get_run_artifacts,
+            rerank_run,
+            get_diagnostics,
+            export_diagnostics,
+            get_audit_log,
+            export_faculty_embeddings,
+            import_faculty_embeddings,
+            get_storage_usage,
+            cleanup_storage,
+            search_faculty,
+            evaluate_matching,
+            import_reviewed_workbook,
+            compare_models,
+            preview_faculty_dataset_replacement,
+            replace_faculty_dataset,
+            reimport_faculty_dataset_source,
+            restore_default_faculty_dataset,
+            save_generated_spreadsheet
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+
+    shutdown_embedding_helper();
+}
 
-    if matches!(extension.as_str(), "xlsx" | "xlsm" | "xls" | "xlsb") {
-        read_excel_spreadsheet_with_limit(path, max_rows)
-    } else {
-        read_delimited_spreadsheet_with_limit(path, max_rows)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use tempfile::TempDir;
+
+    /// Serializes tests that claim the process-wide helper activity slot.
+    static HELPER_ACTIVITY_LOCK: Mutex<()> = Mutex::new(());
+
+    fn dataset_metadata(identifier_columns: &[&str], rows: &[&[&str]]) -> FacultyDatasetMetadata {
+        FacultyDatasetMetadata {
+            analysis: FacultyDatasetAnalysis {
+                embedding_columns: vec!["Research".into()],
+                identifier_columns: identifier_columns.iter().map(|c| c.to_string()).collect(),
+                program_columns: Vec::new(),
+                available_programs: Vec::new(),
+                blank_identifier_rows: Vec::new(),
+            },
+            memberships: rows
+                .iter()
+                .enumerate()
+                .map(|(row_index, values)| FacultyProgramMembership {
+                    row_index,
+                    identifiers: identifier_columns
+                        .iter()
+                        .zip(values.iter())
+                        .map(|(column, value)| (column.to_string(), value.to_string()))
+                        .collect(),
+                    programs: Vec::new(),
+                })
+                .collect(),
+        }
     }
-}
 
-fn read_delimited_spreadsheet_with_limit(
-    path: &Path,
-    max_rows: Option<usize>,
-) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
-    let delimiter = detect_delimiter(path)?;
-    let mut reader = csv::ReaderBuilder::new()
-        .delimiter(delimiter)
-        .has_headers(true)
-        .flexible(true)
-        .from_path(path)
-        .map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
+    fn write_file(dir: &TempDir, name: &str, contents: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
 
-    let mut headers: Vec<String> = reader
-        .headers()
-        .map_err(|err| format!("Unable to read spreadsheet headers: {err}"))?
-        .iter()
-        .map(|value| value.trim().to_string())
-        .collect();
+    fn match_roster(
+        roster: &str,
+        metadata: &FacultyDatasetMetadata,
+        column_map: &[(&str, &str)],
+        fuzzy_matching: bool,
+    ) -> RosterMatchOutcome {
+        match_roster_file("roster.csv", roster, metadata, column_map, fuzzy_matching)
+    }
 
-    let mut rows = Vec::new();
-    for record in reader.records() {
-        let record = record.map_err(|err| format!("Unable to read spreadsheet rows: {err}"))?;
-        let values: Vec<String> = record
+    fn match_roster_file(
+        file_name: &str,
+        roster: &str,
+        metadata: &FacultyDatasetMetadata,
+        column_map: &[(&str, &str)],
+        fuzzy_matching: bool,
+    ) -> RosterMatchOutcome {
+        let dir = TempDir::new().unwrap();
+        let path = write_file(&dir, file_name, roster);
+        let column_map: HashMap<String, String> = column_map
             .iter()
-            .map(|value| value.trim().to_string())
+            .map(|(identifier, column)| (identifier.to_string(), column.to_string()))
             .collect();
-        if values.iter().all(|value| value.is_empty()) {
-            continue;
-        }
-        rows.push(values);
-        if let Some(limit) = max_rows {
-            if rows.len() >= limit {
-                break;
-            }
-        }
+        match_roster_against_dataset(
+            &path,
+            metadata,
+            &RosterMatchOptions {
+                column_map: &column_map,
+                fuzzy_matching,
+                match_mode: RosterMatchMode::All,
+                value_identifier: None,
+                email_domain_aliases: &HashMap::new(),
+                strip_diacritics: true,
+            },
+        )
+        .unwrap()
     }
 
-    align_row_lengths(&mut headers, &mut rows);
-    Ok((headers, rows))
-}
+    /// Stands in for the embedding helper: every text embeds to the vector registered for
+    /// it, or to `default_embedding` when none was.
+    struct MockHost {
+        data_dir: TempDir,
+        embeddings: HashMap<String, Vec<f32>>,
+        default_embedding: Vec<f32>,
+        helper_calls: AtomicUsize,
+        /// When set, each helper exchange announces itself and then waits to be
+        /// released, standing in for a slow embedding helper.
+        helper_gate: Option<(mpsc::Sender<()>, Mutex<mpsc::Receiver<()>>)>,
+        embedding_index_cache: EmbeddingIndexCache,
+        emitted: Mutex<Vec<(String, serde_json::Value)>>,
+    }
 
-fn read_excel_spreadsheet_with_limit(
-    path: &Path,
-    max_rows: Option<usize>,
-) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
-    let mut workbook =
-        open_workbook_auto(path).map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
+    impl MockHost {
+        fn new(default_embedding: &[f32]) -> Self {
+            Self {
+                data_dir: TempDir::new().unwrap(),
+                embeddings: HashMap::new(),
+                default_embedding: default_embedding.to_vec(),
+                helper_calls: AtomicUsize::new(0),
+                helper_gate: None,
+                embedding_index_cache: EmbeddingIndexCache::default(),
+                emitted: Mutex::new(Vec::new()),
+            }
+        }
 
-    let sheet_name = workbook
-        .sheet_names()
-        .get(0)
-        .cloned()
-        .ok_or_else(|| "The workbook does not contain any worksheets.".to_string())?;
+        /// An empty embedding makes the helper leave that text out of its response.
+        fn with_embedding(mut self, text: &str, embedding: &[f32]) -> Self {
+            self.embeddings.insert(text.into(), embedding.to_vec());
+            self
+        }
 
-    let range = workbook
-        .worksheet_range(&sheet_name)
-        .ok_or_else(|| format!("Unable to read the worksheet named '{sheet_name}'."))?
-        .map_err(|err| format!("Unable to read the worksheet data: {err}"))?;
+        /// Returns the host with a receiver that fires when the helper is entered and a
+        /// sender that lets it finish.
+        fn gated(mut self) -> (Self, mpsc::Receiver<()>, mpsc::Sender<()>) {
+            let (entered_sender, entered) = mpsc::channel();
+            let (release, release_receiver) = mpsc::channel();
+            self.helper_gate = Some((entered_sender, Mutex::new(release_receiver)));
+            (self, entered, release)
+        }
 
-    let mut rows_iter = range.rows();
-    let header_row = rows_iter
-        .next()
-        .ok_or_else(|| "The worksheet is empty.".to_string())?;
+        fn write_embedding_index(&self, index: &FacultyEmbeddingIndex) {
+            fs::write(
+                self.data_dir.path().join(FACULTY_EMBEDDINGS_NAME),
+                serialize_faculty_embedding_index(index).unwrap(),
+            )
+            .unwrap();
+        }
+    }
 
-    let mut headers: Vec<String> = header_row.iter().map(cell_to_string).collect();
-    let mut rows = Vec::new();
+    impl MatchingHost for MockHost {
+        fn data_directory(&self) -> Result<PathBuf, String> {
+            Ok(self.data_dir.path().to_path_buf())
+        }
 
-    for row in rows_iter {
-        let values: Vec<String> = row.iter().map(cell_to_string).collect();
-        if values.iter().all(|value| value.is_empty()) {
-            continue;
+        fn app_version(&self) -> String {
+            "0.0.0-test".into()
         }
-        rows.push(values);
-        if let Some(limit) = max_rows {
-            if rows.len() >= limit {
-                break;
-            }
+
+        fn emit_progress(&self, event: &str, payload: serde_json::Value) {
+            self.emitted.lock().unwrap().push((event.into(), payload));
         }
-    }
 
-    align_row_lengths(&mut headers, &mut rows);
-    Ok((headers, rows))
-}
+        fn exchange_with_embedding_helper(
+            &self,
+            input: &[u8],
+            _total_rows: usize,
+            _request_id: Option<&str>,
+        ) -> Result<SidecarOutput, AppError> {
+            self.helper_calls.fetch_add(1, AtomicOrdering::SeqCst);
+            if let Some((entered, release)) = &self.helper_gate {
+                entered.send(()).unwrap();
+                release.lock().unwrap().recv().unwrap();
+            }
+            let request: serde_json::Value = serde_json::from_slice(input).unwrap();
+            let rows: Vec<serde_json::Value> = request["texts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|row| {
+                    let text = row["text"].as_str().unwrap();
+                    let embedding = self.embeddings.get(text).unwrap_or(&self.default_embedding);
+                    (!embedding.is_empty())
+                        .then(|| serde_json::json!({ "id": row["id"], "embedding": embedding }))
+                })
+                .collect();
+            let response = serde_json::json!({
+                "model": request["model"],
+                "dimension": self.default_embedding.len(),
+                "rows": rows,
+            });
+            Ok(SidecarOutput {
+                stdout: serde_json::to_vec(&response).unwrap(),
+                stderr: Vec::new(),
+                termination: None,
+            })
+        }
 
-fn cell_to_string(cell: &DataType) -> String {
-    match cell {
-        DataType::Empty => String::new(),
-        _ => cell.to_string().trim().to_string(),
+        fn embedding_index_cache(&self) -> Option<&EmbeddingIndexCache> {
+            Some(&self.embedding_index_cache)
+        }
     }
-}
 
-fn align_row_lengths(headers: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
-    let mut column_count = headers.len();
-    for row in rows.iter() {
-        if row.len() > column_count {
-            column_count = row.len();
+    fn embedding_entry(row_index: usize, name: &str, embedding: &[f32]) -> FacultyEmbeddingEntry {
+        FacultyEmbeddingEntry {
+            row_index,
+            faculty_id: String::new(),
+            identifiers: HashMap::from([("Name".to_string(), name.to_string())]),
+            embedding: embedding.to_vec(),
+            embedding_f16: None,
+            source: FacultySource::Dataset,
+            faculty_text: None,
         }
     }
 
-    if headers.len() < column_count {
-        headers.resize(column_count, String::new());
+    fn embedding_index(entries: Vec<FacultyEmbeddingEntry>) -> FacultyEmbeddingIndex {
+        FacultyEmbeddingIndex {
+            model: DEFAULT_EMBEDDING_MODEL.into(),
+            generated_at: None,
+            dimension: entries.first().map_or(0, |entry| entry.embedding.len()),
+            total_rows: Some(entries.len()),
+            embedded_rows: Some(entries.len()),
+            skipped_rows: Some(0),
+            embedding_columns: vec!["Research".into()],
+            identifier_columns: vec!["Name".into()],
+            faculty_text_max_characters: None,
+            precision: EmbeddingPrecision::F32,
+            entries,
+        }
     }
 
-    for row in rows.iter_mut() {
-        if row.len() < column_count {
-            row.resize(column_count, String::new());
-        } else if row.len() > column_count {
-            row.truncate(column_count);
-        }
+    /// Deterministic pseudo-random vectors in [-1, 1) from a small LCG.
+    fn synthetic_embeddings(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        (0..count)
+            .map(|_| {
+                (0..dimension)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6_364_136_223_846_793_005)
+                            .wrapping_add(1_442_695_040_888_963_407);
+                        ((state >> 40) as f32 / (1u64 << 23) as f32) - 1.0
+                    })
+                    .collect()
+            })
+            .collect()
     }
-}
 
-fn detect_delimiter(path: &Path) -> Result<u8, String> {
-    let file = File::open(path).map_err(|err| format!("Unable to open the spreadsheet: {err}"))?;
-    let mut reader = BufReader::new(file);
-    let mut buffer = String::new();
+    fn synthetic_index(count: usize, dimension: usize, seed: u64) -> FacultyEmbeddingIndex {
+        embedding_index(
+            synthetic_embeddings(count, dimension, seed)
+                .iter()
+                .enumerate()
+                .map(|(row, embedding)| embedding_entry(row, &format!("Faculty {row}"), embedding))
+                .collect(),
+        )
+    }
 
-    for _ in 0..5 {
-        buffer.clear();
-        let bytes_read = reader
-            .read_line(&mut buffer)
-            .map_err(|err| format!("Unable to inspect the spreadsheet: {err}"))?;
-        if bytes_read == 0 {
-            break;
-        }
-        if buffer.trim().is_empty() {
-            continue;
-        }
+    /// The single-threaded scoring `find_best_faculty_matches` must agree with.
+    fn serial_faculty_ranking(
+        index: &FacultyEmbeddingIndex,
+        prompt: &[f32],
+        limit: usize,
+        allowed_rows: Option<&HashSet<usize>>,
+    ) -> Vec<(usize, u32)> {
+        let mut ranking: Vec<(usize, f32)> = index
+            .entries
+            .iter()
+            .filter(|entry| allowed_rows.is_none_or(|rows| rows.contains(&entry.row_index)))
+            .filter_map(|entry| {
+                cosine_similarity(prompt, &entry.embedding)
+                    .filter(|similarity| similarity.is_finite())
+                    .map(|similarity| (entry.row_index, similarity))
+            })
+            .collect();
+        ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranking.truncate(limit);
+        ranking
+            .into_iter()
+            .map(|(row, similarity)| (row, similarity.to_bits()))
+            .collect()
+    }
 
-        let counts = [
-            (b'\t', buffer.matches('\t').count()),
-            (b',', buffer.matches(',').count()),
-            (b';', buffer.matches(';').count()),
-        ];
+    fn ranking_keys(matches: &[FacultyMatchResult]) -> Vec<(usize, u32)> {
+        matches
+            .iter()
+            .map(|result| (result.row_index, result.similarity.to_bits()))
+            .collect()
+    }
 
-        if let Some((delimiter, count)) = counts.iter().max_by_key(|(_, count)| *count) {
-            if *count > 0 {
-                return Ok(*delimiter);
-            }
+    fn run_info() -> RunInfo {
+        RunInfo {
+            generated_at: "2026-01-01T00:00:00+00:00".into(),
+            app_version: "0.0.0-test".into(),
+            task_type: TaskType::Spreadsheet,
+            faculty_scope: FacultyScope::All,
+            program_filters: Vec::new(),
+            custom_faculty_path: None,
+            custom_faculty_mode: CustomFacultyMode::Include,
+            roster_faculty_count: None,
+            recommendations_per_student: 2,
+            embedding_model: Some(DEFAULT_EMBEDDING_MODEL.into()),
+            embeddings_generated_at: None,
+            dataset_path: None,
+            dataset_source_path: None,
+            dataset_row_count: Some(3),
+            similarity_low_threshold: SIMILARITY_LOW_THRESHOLD_PERCENT,
+            similarity_high_threshold: SIMILARITY_HIGH_THRESHOLD_PERCENT,
+            attention_threshold: SIMILARITY_LOW_THRESHOLD_PERCENT,
+            summary_mode: SummaryMode::Formulas,
+            prompt_text_max_chars: None,
+            student_worksheet_limit: None,
         }
     }
 
-    Ok(b'\t')
-}
+    fn run_options(run_info: &RunInfo) -> MatchRunOptions<'_> {
+        MatchRunOptions {
+            limit: 2,
+            allowed_rows: None,
+            faculty_in_scope: None,
+            roster_diagnostics: None,
+            output_format: OutputFormat::Xlsx,
+            include_prompt_text: false,
+            include_faculty_text: false,
+            include_faculty_id: false,
+            include_raw_cosine: false,
+            request_id: "test-run",
+            reviewer_input_mode: ReviewerInputMode::Number,
+            summary_mode: SummaryMode::Formulas,
+            matches_sort: MatchesSort::Student,
+            prompt_text_max_chars: PROMPT_TEXT_COLUMN_MAX_CHARS,
+            prompt_column_values_max_chars: None,
+            sheet_per_student: false,
+            similarity_thresholds: (
+                f64::from(SIMILARITY_LOW_THRESHOLD_PERCENT) / 100.0,
+                f64::from(SIMILARITY_HIGH_THRESHOLD_PERCENT) / 100.0,
+            ),
+            attention_threshold: f64::from(SIMILARITY_LOW_THRESHOLD_PERCENT) / 100.0,
+            similar_prompt_threshold: None,
+            scrub_personal_information: false,
+            strip_boilerplate: false,
+            boilerplate_text: None,
+            boilerplate_row_percent: BOILERPLATE_ROW_PERCENT,
+            max_prompt_characters: resolve_max_prompt_characters(None),
+            prompt_preview_characters: resolve_prompt_preview_characters(None),
+            run_warnings: &[],
+            run_info,
+            preview_row_limit: DEFAULT_PREVIEW_ROW_LIMIT,
+            output_path: None,
+            overwrite_output: false,
+            student_reports: false,
+            faculty_programs: None,
+            mail_merge_template: None,
+            filename_stem: None,
+        }
+    }
 
-fn build_faculty_dataset_status(
-    app_handle: &tauri::AppHandle,
-) -> Result<FacultyDatasetStatus, String> {
-    build_faculty_dataset_status_with_overrides(app_handle, None)
-}
+    fn match_entry(
+        student: &str,
+        faculty: &str,
+        faculty_row: usize,
+        similarity: f32,
+        student_rank: usize,
+        faculty_rank: usize,
+    ) -> MatchEntry {
+        MatchEntry {
+            student_values: vec![student.into()],
+            faculty_values: vec![faculty.into()],
+            faculty_row,
+            faculty_source: FacultySource::Dataset,
+            faculty_id: String::new(),
+            similarity: Some(similarity),
+            student_rank: Some((student_rank, None)),
+            faculty_rank: Some(faculty_rank),
+            prompt_text: None,
+            faculty_text: None,
+        }
+    }
 
-fn build_faculty_dataset_status_with_overrides(
-    app_handle: &tauri::AppHandle,
-    overrides: Option<&FacultyDatasetColumnConfiguration>,
-) -> Result<FacultyDatasetStatus, String> {
-    let dataset_path = dataset_destination(app_handle)?;
-    let mut status = FacultyDatasetStatus {
-        path: Some(dataset_path.to_string_lossy().into_owned()),
-        canonical_path: None,
-        source_path: None,
-        last_modified: None,
-        row_count: None,
-        column_count: None,
-        is_valid: false,
-        is_default: false,
-        message: None,
-        message_variant: None,
-        preview: None,
-        analysis: None,
-    };
+    /// Two students with two recommendations each, over three faculty.
+    fn sample_match_entries() -> Vec<MatchEntry> {
+        vec![
+            match_entry("Student A", "Robert Smith", 0, 0.61, 1, 1),
+            match_entry("Student A", "Ana Garcia", 1, 0.42, 2, 1),
+            match_entry("Student B", "Robert Smith", 0, 0.55, 1, 2),
+            match_entry("Student B", "Helen Jones", 2, 0.18, 2, 1),
+        ]
+    }
 
-    if !dataset_path.exists() {
-        if let Err(init_err) = ensure_default_faculty_dataset(app_handle, &dataset_path) {
-            let _ = clear_faculty_dataset_metadata(app_handle);
-            let _ = clear_faculty_dataset_source_path(app_handle);
-            status.message = Some(format!(
-                "Unable to restore the packaged faculty dataset: {init_err}"
-            ));
-            status.message_variant = Some("error".into());
-            return Ok(status);
+    fn export_data<'a>(
+        student_headers: &'a [String],
+        student_rows: &'a [Vec<String>],
+        faculty_headers: &'a [String],
+        entries: &'a [MatchEntry],
+        statistics: &'a RunStatistics,
+    ) -> MatchExportData<'a> {
+        MatchExportData {
+            student_headers,
+            summary_student_columns: student_headers.len(),
+            student_summary_rows: student_rows,
+            faculty_headers,
+            match_entries: entries,
+            skipped_entries: &[],
+            needs_attention: &[],
+            similar_prompts: &[],
+            statistics,
+            document_path_column: None,
         }
     }
 
-    if !dataset_path.exists() {
-        let _ = clear_faculty_dataset_metadata(app_handle);
-        let _ = clear_faculty_dataset_source_path(app_handle);
-        status.message = Some(
-            "No faculty dataset has been configured. Restore the default file to continue.".into(),
+    /// Builds the sample workbook and returns its bytes.
+    fn sample_workbook(
+        entries: &[MatchEntry],
+        options: &MatchRunOptions,
+        warnings: &mut Vec<String>,
+    ) -> Vec<u8> {
+        let student_headers = strings(&["Student"]);
+        let faculty_headers = strings(&["Name"]);
+        let student_rows = string_rows(&[&["Student A"], &["Student B"]]);
+        let statistics = RunStatistics::default();
+        let data = export_data(
+            &student_headers,
+            &student_rows,
+            &faculty_headers,
+            entries,
+            &statistics,
         );
-        status.message_variant = Some("info".into());
-        return Ok(status);
+        build_matches_workbook(&data, options, warnings).unwrap()
     }
 
-    status.canonical_path = dataset_path
-        .canonicalize()
-        .ok()
-        .map(|path| path.to_string_lossy().into_owned());
+    fn read_sheet(workbook: &[u8], sheet: &str) -> Vec<Vec<String>> {
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(workbook.to_vec())).unwrap();
+        let range = workbook.worksheet_range(sheet).unwrap().unwrap();
+        range
+            .rows()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    /// Raw worksheet XML, for the layout details calamine does not expose.
+    fn sheet_xml(workbook: &[u8], sheet_number: usize) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(workbook)).unwrap();
+        let mut xml = String::new();
+        archive
+            .by_name(&format!("xl/worksheets/sheet{sheet_number}.xml"))
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        xml
+    }
 
-    match read_faculty_dataset_source_path(app_handle) {
-        Ok(source_path) => {
-            status.source_path = source_path;
+    fn submission_details() -> SubmissionDetails {
+        SubmissionDetails {
+            request_id: "test-run".into(),
+            task_type: TaskType::Spreadsheet,
+            faculty_scope: FacultyScope::All,
+            validated_paths: Vec::new(),
+            program_filters: Vec::new(),
+            custom_faculty_path: None,
+            custom_faculty_mode: CustomFacultyMode::Include,
+            recommendations_per_student: 2,
+            prompt_preview: None,
+            spreadsheet_prompt_columns: Vec::new(),
+            spreadsheet_identifier_columns: Vec::new(),
+            faculty_roster_column_map: HashMap::new(),
+            faculty_roster_warnings: Vec::new(),
+            roster_fuzzy_matching: false,
+            roster_match_mode: RosterMatchMode::All,
+            roster_value_identifier: None,
+            roster_mapping_preset: None,
+            roster_faculty_count: None,
+            faculty_in_scope: None,
+            student_label: None,
+            embedding_model: None,
+            model_overridden: false,
+            output_filename: None,
         }
-        Err(err) => {
-            if status.message.is_none() {
-                status.message = Some(err);
-                status.message_variant = Some("error".into());
-            }
+    }
+
+    fn faculty_match(row_index: usize, name: &str, similarity: f32) -> FacultyMatchResult {
+        FacultyMatchResult {
+            row_index,
+            faculty_id: String::new(),
+            similarity,
+            identifiers: HashMap::from([("Name".to_string(), name.to_string())]),
+            faculty_text: None,
+            student_rank_for_faculty: None,
+            student_rank_total: None,
+            source: FacultySource::Dataset,
         }
     }
 
-    let metadata = fs::metadata(&dataset_path)
-        .map_err(|err| format!("Unable to inspect the faculty dataset: {err}"))?;
-    status.last_modified = metadata.modified().ok().map(format_system_time);
+    fn submission_response(
+        prompt_matches: Vec<PromptMatchResult>,
+        warnings: Vec<String>,
+    ) -> SubmissionResponse {
+        let statistics = build_run_statistics(&prompt_matches, None);
+        SubmissionResponse {
+            summary: "Matched 2 students.".into(),
+            warnings,
+            details: submission_details(),
+            prompt_matches,
+            directory_results: None,
+            spreadsheet_results: None,
+            roster_diagnostics: None,
+            faculty_exclusions: None,
+            json_export: None,
+            pdf_report: None,
+            bundle: None,
+            statistics,
+        }
+    }
 
-    let bytes = fs::read(&dataset_path)
-        .map_err(|err| format!("Unable to read the faculty dataset: {err}"))?;
-    status.is_default = bytes == DEFAULT_FACULTY_DATASET;
+    /// The text drawn on each PDF page, in drawing order.
+    fn pdf_text_lines(pdf: &[u8]) -> Vec<String> {
+        let content = String::from_utf8_lossy(pdf);
+        content
+            .lines()
+            .filter_map(|line| {
+                let start = line.find(" Td (")? + 5;
+                let end = line.rfind(") Tj ET")?;
+                Some(line[start..end].replace("\\(", "(").replace("\\)", ")"))
+            })
+            .collect()
+    }
 
-    let extension = dataset_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    fn sheet_names(workbook: &[u8]) -> Vec<String> {
+        open_workbook_auto_from_rs(Cursor::new(workbook.to_vec()))
+            .unwrap()
+            .sheet_names()
+            .to_vec()
+    }
 
-    let dimensions = match extension.as_str() {
-        "xlsx" | "xls" => compute_excel_dimensions(&dataset_path),
-        _ => compute_tsv_dimensions(&bytes),
-    };
+    #[test]
+    fn fuzzy_roster_matching_resolves_nicknames_and_accents() {
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[&["Robert A. Smith"], &["Ana Garcia"], &["Helen Jones"]],
+        );
+        let roster = "Name,Note\nBob Smith,x\n\"García, Ana\",y\nAna García,z\n";
+
+        // Accents alone are already folded by the exact join.
+        let exact = match_roster(roster, &metadata, &[("Name", "Name")], false);
+        assert_eq!(exact.matched_rows, HashSet::from([1]));
+        assert_eq!(exact.summary.unmatched_roster_rows, 2);
+
+        let fuzzy = match_roster(roster, &metadata, &[("Name", "Name")], true);
+        assert_eq!(fuzzy.matched_rows, HashSet::from([0, 1]));
+        assert!(fuzzy.warnings.iter().any(|warning| warning
+            == "Roster row 2 'Bob Smith' was fuzzy-matched to 'Robert A. Smith' (last name and first initial)."));
+        assert!(fuzzy.warnings.iter().any(|warning| warning
+            == "Roster row 3 'García, Ana' was fuzzy-matched to 'Ana Garcia' (ignoring accents and punctuation)."));
+    }
 
-    match dimensions {
-        Ok((rows, columns)) => {
-            status.row_count = Some(rows);
-            status.column_count = Some(columns);
-            status.is_valid = rows > 0 && columns > 0;
-            if !status.is_valid {
-                status.message = Some("The faculty dataset does not contain any data rows.".into());
-                status.message_variant = Some("error".into());
-            }
-        }
-        Err(err) => {
-            status.message = Some(err);
-            status.message_variant = Some("error".into());
-        }
+    #[test]
+    fn fuzzy_roster_matching_lists_ambiguous_names_without_matching() {
+        let metadata = dataset_metadata(&["Name"], &[&["John Smith"], &["Jane Smith"]]);
+        let outcome = match_roster(
+            "Name,Note\nJ. Smith,x\n",
+            &metadata,
+            &[("Name", "Name")],
+            true,
+        );
+
+        assert!(outcome.matched_rows.is_empty());
+        assert!(outcome.warnings.iter().any(|warning| warning.starts_with(
+            "Roster row 2 'J. Smith' resembles several faculty (John Smith; Jane Smith)"
+        )));
+    }
+
+    #[test]
+    fn fuzzy_roster_matching_never_loosens_emails_or_ids() {
+        let metadata = dataset_metadata(
+            &["Email", "ID"],
+            &[&["jdoe@wustl.edu", "12345"], &["jdane@wustl.edu", "54321"]],
+        );
+
+        let by_email = match_roster(
+            "Email,Note\njdoa@wustl.edu,x\njd@wustl.edu,y\n",
+            &metadata,
+            &[("Email", "Email")],
+            true,
+        );
+        assert!(by_email.matched_rows.is_empty());
+        assert!(!by_email
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("fuzzy-matched")));
+        assert!(by_email
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("Fuzzy matching was skipped")));
+
+        let by_id = match_roster("ID,Note\n12346,x\n", &metadata, &[("ID", "ID")], true);
+        assert!(by_id.matched_rows.is_empty());
+    }
+
+    #[test]
+    fn fuzzy_roster_matching_requires_exact_non_name_columns() {
+        let metadata = dataset_metadata(
+            &["Name", "Email"],
+            &[
+                &["Robert Smith", "rsmith@wustl.edu"],
+                &["Rob Smith", "rsmith2@wustl.edu"],
+            ],
+        );
+        let outcome = match_roster(
+            "Name,Email\nBob Smith,rsmith2@wustl.edu\nBob Smith,rsmith3@wustl.edu\n",
+            &metadata,
+            &[("Name", "Name"), ("Email", "Email")],
+            true,
+        );
+
+        assert_eq!(outcome.matched_rows, HashSet::from([1]));
+        assert_eq!(outcome.summary.unmatched_roster_rows, 1);
     }
 
-    match build_dataset_preview(&dataset_path) {
-        Ok(preview) => {
-            status.preview = Some(preview);
-        }
-        Err(err) => {
-            if status.message.is_none() {
-                status.message = Some(err);
-                status.message_variant = Some("error".into());
-            }
-        }
+    fn headerless_values(contents: &str, metadata: &FacultyDatasetMetadata) -> Option<Vec<String>> {
+        let dir = TempDir::new().unwrap();
+        let path = write_file(&dir, "roster.txt", contents);
+        read_headerless_roster_values(&path, metadata, true)
+            .unwrap()
+            .map(|(values, _)| values)
     }
 
-    if status.is_valid {
-        match analyze_faculty_dataset(app_handle, &dataset_path, overrides) {
-            Ok(analysis) => {
-                status.analysis = Some(analysis);
-            }
-            Err(err) => {
-                let _ = clear_faculty_dataset_metadata(app_handle);
-                status.analysis = None;
-                status.is_valid = false;
-                if status.message.is_none() {
-                    status.message = Some(err);
-                    status.message_variant = Some("error".into());
-                }
-            }
-        }
-    } else if status.analysis.is_some() {
-        status.analysis = None;
+    #[test]
+    fn headerless_roster_needs_a_second_signal_to_keep_the_first_line() {
+        let metadata = dataset_metadata(
+            &["Name", "Email"],
+            &[
+                &["Robert Smith", "rsmith@wustl.edu"],
+                &["Ana Garcia", "agarcia@wustl.edu"],
+            ],
+        );
+
+        // A text first line that is not a known label is still a header on its own.
+        assert_eq!(
+            headerless_values("Mentor List\nRobert Smith\nAna Garcia\n", &metadata),
+            None
+        );
+        assert_eq!(headerless_values("Name\nRobert Smith\n", &metadata), None);
+        assert_eq!(
+            headerless_values("Robert Smith,Ana Garcia\n", &metadata),
+            None
+        );
+
+        // The first line matches the dataset.
+        assert_eq!(
+            headerless_values("Robert Smith\nAna Garcia\n", &metadata),
+            Some(vec!["Robert Smith".to_string(), "Ana Garcia".to_string()])
+        );
+        // The first line is an email like the rest, even though nobody has that address.
+        assert_eq!(
+            headerless_values("new@wustl.edu\nagarcia@wustl.edu\n", &metadata)
+                .map(|values| values.len()),
+            Some(2)
+        );
     }
 
-    if !status.is_valid {
-        if let Err(err) = clear_faculty_dataset_metadata(app_handle) {
-            if status.message.is_none() {
-                status.message = Some(err);
-                status.message_variant = Some("error".into());
-            }
-        }
+    #[test]
+    fn headerless_roster_matches_every_line_as_a_value() {
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"], &["Ana Garcia"]]);
+        let outcome = match_roster_file(
+            "roster.txt",
+            "Robert Smith\nAna Garcia\n",
+            &metadata,
+            &[("Name", "Name")],
+            false,
+        );
+
+        assert_eq!(outcome.value_identifier.as_deref(), Some("Name"));
+        assert_eq!(outcome.matched_rows, HashSet::from([0, 1]));
     }
 
-    Ok(status)
-}
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
 
-fn analyze_faculty_dataset(
-    app_handle: &tauri::AppHandle,
-    dataset_path: &Path,
-    overrides: Option<&FacultyDatasetColumnConfiguration>,
-) -> Result<FacultyDatasetAnalysis, String> {
-    let (mut headers, mut rows) = read_full_spreadsheet(dataset_path)?;
-    if headers.is_empty() {
-        return Err("The faculty dataset does not include any columns.".into());
+    fn string_rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter().map(|row| strings(row)).collect()
     }
 
-    align_row_lengths(&mut headers, &mut rows);
+    #[test]
+    fn roster_suggestions_fall_back_to_value_overlap_for_uninformative_headers() {
+        let metadata = dataset_metadata(
+            &["Name", "Email"],
+            &[
+                &["Robert Smith", "rsmith@wustl.edu"],
+                &["Ana Garcia", "agarcia@wustl.edu"],
+                &["Helen Jones", "hjones@wustl.edu"],
+            ],
+        );
+        let headers = strings(&["Column A", "Contact", "Misc"]);
+        let rows = string_rows(&[
+            &["ana garcia", "AGarcia@wustl.edu", "x"],
+            &["Helen  Jones", "hjones@wustl.edu", "y"],
+            &["Someone Else", "other@wustl.edu", "z"],
+        ]);
+        let mut warnings = Vec::new();
+
+        let (suggestions, overlaps) =
+            suggest_roster_identifier_columns(&metadata, &headers, &rows, true, &mut warnings);
+
+        assert_eq!(suggestions["Name"], Some(0));
+        assert_eq!(suggestions["Email"], Some(1));
+        assert!((overlaps["Name"] - 200.0 / 3.0).abs() < 1e-3);
+        assert!((overlaps["Email"] - 200.0 / 3.0).abs() < 1e-3);
+        assert!(warnings.is_empty());
+    }
 
-    let column_count = headers.len();
+    #[test]
+    fn roster_suggestions_prefer_header_names_over_overlap() {
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"], &["Ana Garcia"]]);
+        let headers = strings(&["Mentor", "name"]);
+        let rows = string_rows(&[&["Robert Smith", "Bob"], &["Ana Garcia", "Ana"]]);
+        let mut warnings = Vec::new();
 
-    let (embedding_indexes, identifier_indexes) = if let Some(config) = overrides {
-        (
-            normalize_column_selection(&config.embedding_columns, column_count),
-            normalize_column_selection(&config.identifier_columns, column_count),
-        )
-    } else {
-        suggest_spreadsheet_columns(&headers, &rows)
-    };
+        let (suggestions, overlaps) =
+            suggest_roster_identifier_columns(&metadata, &headers, &rows, true, &mut warnings);
 
-    let program_indexes = if let Some(config) = overrides {
-        normalize_column_selection(&config.program_columns, column_count)
-    } else {
-        suggest_program_columns(&headers, &rows)
-    };
+        assert_eq!(suggestions["Name"], Some(1));
+        assert!(overlaps.is_empty());
+    }
 
-    if embedding_indexes.is_empty() {
-        return Err(
-            "Select at least one column containing faculty research interests or other embedding content.".into(),
+    #[test]
+    fn roster_suggestions_skip_columns_below_the_overlap_threshold() {
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"], &["Ana Garcia"]]);
+        let headers = strings(&["Column A", "Column B"]);
+        let rows = string_rows(&[&["Robert Smith", "x"], &["Nobody", "y"], &["Else", "z"]]);
+        let mut warnings = Vec::new();
+
+        let (suggestions, _) =
+            suggest_roster_identifier_columns(&metadata, &headers, &rows, true, &mut warnings);
+
+        assert_eq!(suggestions["Name"], None);
+        assert_eq!(
+            warnings,
+            ["No roster column matched the faculty identifier 'Name'."]
         );
     }
 
-    if identifier_indexes.is_empty() {
-        return Err(
-            "Select at least one column that uniquely identifies each faculty member.".into(),
+    #[test]
+    fn roster_duplicates_are_counted_for_exact_and_case_only_repeats() {
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[&["Robert Smith"], &["Ana Garcia"], &["Helen Jones"]],
+        );
+        let outcome = match_roster(
+            "Name,Note\nRobert Smith,a\nRobert Smith,b\nANA GARCIA,c\nAna Garcia,d\nHelen Jones,e\n",
+            &metadata,
+            &[("Name", "Name")],
+            false,
         );
-    }
 
-    let analysis = FacultyDatasetAnalysis {
-        embedding_columns: indexes_to_headers(&headers, &embedding_indexes),
-        identifier_columns: indexes_to_headers(&headers, &identifier_indexes),
-        program_columns: indexes_to_headers(&headers, &program_indexes),
-        available_programs: collect_program_values(&rows, &program_indexes),
-    };
+        assert_eq!(outcome.summary.duplicate_roster_rows, 2);
+        assert_eq!(outcome.summary.matched_faculty, 3);
+        assert_eq!(outcome.matched_rows, HashSet::from([0, 1, 2]));
+        assert!(outcome.warnings.iter().any(|warning| warning
+            == "2 roster rows repeated an earlier entry: Robert Smith; Ana Garcia."));
+    }
 
-    let memberships =
-        build_faculty_program_memberships(&headers, &rows, &identifier_indexes, &program_indexes);
+    #[test]
+    fn roster_only_faculty_are_marked_by_source_not_row_number() {
+        let mut index = embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.0, 1.0]),
+        ]);
+        index.total_rows = Some(4);
+        let host = MockHost::new(&[0.6, 0.8]);
+        let added = append_ad_hoc_roster_embeddings(
+            &host,
+            &mut index,
+            &[AdHocRosterFaculty {
+                identifiers: HashMap::from([("Name".to_string(), "Helen Jones".to_string())]),
+                text: "Protein folding".into(),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(added, [4]);
+        let roster_entry = index.entries.last().unwrap();
+        assert_eq!(roster_entry.source, FacultySource::Roster);
+        assert_eq!(
+            fallback_faculty_label(roster_entry.source, roster_entry.row_index),
+            "Roster-only faculty"
+        );
+        assert_eq!(
+            fallback_faculty_label(FacultySource::Dataset, 1),
+            "Dataset row 3"
+        );
 
-    write_faculty_dataset_metadata(app_handle, &analysis, &memberships)?;
+        let matches = find_best_faculty_matches(&index, &[0.6, 0.8], 3, None);
+        let serialized: Vec<serde_json::Value> = matches
+            .iter()
+            .map(|result| serde_json::to_value(result).unwrap())
+            .collect();
+        assert_eq!(serialized[0]["source"], "roster");
+        assert!(serialized[1..]
+            .iter()
+            .all(|result| result.get("source").is_none()));
+    }
 
-    Ok(analysis)
-}
+    #[test]
+    fn saved_index_entries_without_a_source_load_as_dataset_rows() {
+        let entry: FacultyEmbeddingEntry = serde_json::from_value(serde_json::json!({
+            "rowIndex": 3,
+            "identifiers": { "Name": "Robert Smith" },
+            "embedding": [1.0, 0.0],
+        }))
+        .unwrap();
+        assert_eq!(entry.source, FacultySource::Dataset);
+        assert!(serde_json::to_value(&entry)
+            .unwrap()
+            .get("source")
+            .is_none());
+    }
 
-fn suggest_program_columns(headers: &[String], rows: &[Vec<String>]) -> Vec<usize> {
-    const PROGRAM_KEYWORDS: &[&str] = &["program", "track", "pathway", "division", "department"];
+    #[test]
+    fn identifier_diacritics_setting_is_unset_by_default() {
+        let settings: AppSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.strip_identifier_diacritics, None);
+        let settings: AppSettings =
+            serde_json::from_str(r#"{"stripIdentifierDiacritics": false}"#).unwrap();
+        assert_eq!(settings.strip_identifier_diacritics, Some(false));
+    }
 
-    let mut program_columns: Vec<usize> = headers
-        .iter()
-        .enumerate()
-        .filter_map(|(index, header)| {
-            let lower = header.to_lowercase();
-            if lower.is_empty() {
-                return None;
-            }
-            if PROGRAM_KEYWORDS
-                .iter()
-                .any(|keyword| lower.contains(keyword))
-            {
-                Some(index)
-            } else {
-                None
-            }
-        })
-        .collect();
+    #[test]
+    fn excel_cells_are_capped_at_the_excel_character_limit() {
+        let short = "Protein folding";
+        assert!(matches!(excel_cell_text(short), Cow::Borrowed(value) if value == short));
 
-    sort_and_dedup(&mut program_columns);
+        let long = "Structural biology of membrane proteins. ".repeat(1_000);
+        let capped = excel_cell_text(&long);
+        assert!(capped.chars().count() <= EXCEL_CELL_MAX_CHARS);
+        assert!(capped.ends_with(PROMPT_TRUNCATION_MARKER));
 
-    if !program_columns.is_empty() {
-        return program_columns;
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        assert!(sheet.write_string(0, 0, long.as_str()).is_err());
+        sheet.write_string(0, 0, capped).unwrap();
     }
 
-    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+    #[test]
+    fn matches_workbook_keeps_its_data_layout_under_the_formatting() {
+        let info = run_info();
+        let options = run_options(&info);
+        let mut warnings = Vec::new();
+        let bytes = sample_workbook(&sample_match_entries(), &options, &mut warnings);
 
-    for (index, header) in headers.iter().enumerate() {
-        if header.trim().is_empty() {
-            continue;
+        assert_eq!(
+            sheet_names(&bytes),
+            ["Matches", "Student Summary", "Faculty Summary", "Run Info"]
+        );
+        let matches = read_sheet(&bytes, "Matches");
+        assert_eq!(
+            matches[0],
+            [
+                "First reviewer",
+                "Reviewer",
+                "Student",
+                "Name",
+                "Similarity %",
+                "Student rank",
+                "Faculty rank"
+            ]
+        );
+        let layout: Vec<(&str, &str, f64, &str, &str)> = matches[1..]
+            .iter()
+            .map(|row| {
+                (
+                    row[2].as_str(),
+                    row[3].as_str(),
+                    row[4].parse::<f64>().unwrap(),
+                    row[5].as_str(),
+                    row[6].as_str(),
+                )
+            })
+            .collect();
+        let expected = [
+            ("Student A", "Robert Smith", 0.61, "1", "1"),
+            ("Student A", "Ana Garcia", 0.42, "2", "1"),
+            ("Student B", "Robert Smith", 0.55, "1", "2"),
+            ("Student B", "Helen Jones", 0.18, "2", "1"),
+        ];
+        assert_eq!(layout.len(), expected.len());
+        for (actual, expected) in layout.iter().zip(expected) {
+            assert_eq!(
+                (actual.0, actual.1, actual.3, actual.4),
+                (expected.0, expected.1, expected.3, expected.4)
+            );
+            assert!((actual.2 - expected.2).abs() < 1e-6);
         }
 
-        let mut unique = BTreeSet::new();
-        let mut non_empty = 0usize;
-
-        for row in rows {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                non_empty += 1;
-                unique.insert(trimmed.to_lowercase());
-            }
+        let student_summary = read_sheet(&bytes, "Student Summary");
+        assert_eq!(student_summary.len(), 3);
+        assert_eq!(student_summary[1][0], "Student A");
+        assert_eq!(student_summary[1][3], "Robert Smith");
+        let faculty_summary = read_sheet(&bytes, "Faculty Summary");
+        let faculty: Vec<&str> = faculty_summary[1..]
+            .iter()
+            .map(|row| row[0].as_str())
+            .collect();
+        assert_eq!(faculty, ["Robert Smith", "Ana Garcia", "Helen Jones"]);
+
+        let matches_xml = sheet_xml(&bytes, 1);
+        assert!(matches_xml.contains(r#"<autoFilter ref="A1:G5"/>"#));
+        assert!(matches_xml.contains(r#"ySplit="1""#));
+        assert!(matches_xml.contains("<cols>"));
+        for sheet_number in 2..=3 {
+            assert!(sheet_xml(&bytes, sheet_number).contains(r#"ySplit="1""#));
         }
+    }
 
-        if non_empty == 0 {
-            continue;
-        }
+    #[test]
+    fn pdf_report_ranks_faculty_by_recommendations_deterministically() {
+        let prompt_matches = vec![
+            PromptMatchResult {
+                prompt: "Student A".into(),
+                faculty_matches: vec![
+                    faculty_match(0, "Robert Smith", 0.61),
+                    faculty_match(1, "Ana Garcia", 0.42),
+                ],
+            },
+            PromptMatchResult {
+                prompt: "Student B".into(),
+                faculty_matches: vec![
+                    faculty_match(2, "Helen Jones", 0.55),
+                    faculty_match(0, "Robert Smith", 0.18),
+                ],
+            },
+        ];
+        let response = submission_response(prompt_matches, vec!["One warning.".into()]);
+        let identifier_columns = strings(&["Name"]);
+        let info = run_info();
 
-        let unique_count = unique.len();
-        if unique_count > 0 && unique_count <= 25 {
-            candidates.push((index, unique_count, non_empty));
-        }
+        let first = build_pdf_report(&response, &info, &identifier_columns, None);
+        let second = build_pdf_report(&response, &info, &identifier_columns, None);
+        assert_eq!(first, second);
+        assert!(first.starts_with(b"%PDF-1.4\n"));
+
+        let lines = pdf_text_lines(&first);
+        let top = lines
+            .iter()
+            .position(|line| line == "Top 10 faculty by recommendation count")
+            .unwrap();
+        assert_eq!(
+            lines[top..],
+            [
+                "Top 10 faculty by recommendation count",
+                "Robert Smith: 2 recommendations (1 as top match)",
+                "Helen Jones: 1 recommendation (1 as top match)",
+                "Ana Garcia: 1 recommendation (0 as top match)",
+                "1 warning",
+                "- One warning.",
+            ]
+        );
     }
 
-    candidates.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)));
+    #[test]
+    fn matches_split_across_worksheets_past_the_row_limit() {
+        let info = run_info();
+        let options = run_options(&info);
+        let entries = sample_match_entries();
+        let student_headers = strings(&["Student"]);
+        let faculty_headers = strings(&["Name"]);
+        let student_rows = string_rows(&[&["Student A"], &["Student B"]]);
+        let statistics = RunStatistics::default();
+        let data = export_data(
+            &student_headers,
+            &student_rows,
+            &faculty_headers,
+            &entries,
+            &statistics,
+        );
+        let mut warnings = Vec::new();
+        let bytes = build_matches_workbook_with_limits(
+            &data,
+            &options,
+            &mut warnings,
+            3,
+            MATCHES_CONSTANT_MEMORY_THRESHOLD,
+        )
+        .unwrap();
+
+        assert_eq!(
+            sheet_names(&bytes),
+            [
+                "Matches (1)",
+                "Matches (2)",
+                "Student Summary",
+                "Faculty Summary",
+                "Run Info",
+                "Warnings"
+            ]
+        );
+        let parts: Vec<Vec<(String, String)>> = ["Matches (1)", "Matches (2)"]
+            .iter()
+            .map(|sheet| {
+                read_sheet(&bytes, sheet)[1..]
+                    .iter()
+                    .map(|row| (row[2].clone(), row[3].clone()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                [
+                    ("Student A".to_string(), "Robert Smith".to_string()),
+                    ("Student A".to_string(), "Ana Garcia".to_string()),
+                ],
+                [
+                    ("Student B".to_string(), "Robert Smith".to_string()),
+                    ("Student B".to_string(), "Helen Jones".to_string()),
+                ],
+            ]
+        );
+        assert_eq!(
+            warnings,
+            ["The 4 matches were split across 2 worksheets to stay within the worksheet row limit; the summary reviewer counts were written as static values."]
+        );
 
-    for (index, _, _) in candidates.into_iter().take(4) {
-        program_columns.push(index);
+        // Summary counts become plain values once the matches no longer sit in one range.
+        assert!(!sheet_xml(&bytes, 3).contains("<f>"));
+        let single = sample_workbook(&entries, &options, &mut Vec::new());
+        assert!(sheet_xml(&single, 2).contains("<f>"));
     }
 
-    sort_and_dedup(&mut program_columns);
-    program_columns
-}
+    #[test]
+    fn saving_over_an_existing_file_needs_explicit_overwrite() {
+        let dir = TempDir::new().unwrap();
+        let path = write_file(&dir, "matches.tsv", "old");
+        let save = |overwrite| {
+            save_generated_spreadsheet(
+                path.to_string_lossy().into_owned(),
+                "new".into(),
+                None,
+                overwrite,
+                None,
+            )
+        };
 
-fn indexes_to_headers(headers: &[String], indexes: &[usize]) -> Vec<String> {
-    let mut values = Vec::new();
-    let mut seen = HashSet::new();
+        let error = save(None).unwrap_err();
+        assert_eq!(error, AppError::file_exists(&path));
+        assert!(save(Some(false)).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
 
-    for &index in indexes {
-        if index >= headers.len() {
-            continue;
-        }
+        let saved = save(Some(true)).unwrap();
+        assert_eq!(saved.bytes, 3);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    }
 
-        let label = header_label(headers, index);
-        let key = label.to_lowercase();
-        if seen.insert(key) {
-            values.push(label);
+    #[test]
+    fn sheet_names_are_cleaned_truncated_and_deduplicated() {
+        let mut used = HashSet::new();
+        let mut name = |value: &str| sanitize_sheet_name(value, "Student", &mut used);
+
+        assert_eq!(name("Biology/Chemistry: [2025]?"), "Biology Chemistry 2025");
+        assert_eq!(name("  ''  "), "Student");
+        assert_eq!(name("***"), "Student (2)");
+
+        let emoji =
+            name("\u{1f9ec} Genomics \u{1f52c} Lab \u{1f9ea}\u{1f9ea}\u{1f9ea}\u{1f9ea}\u{1f9ea}");
+        assert!(emoji.encode_utf16().count() <= WORKSHEET_NAME_MAX_CHARS);
+        assert!(emoji.starts_with("\u{1f9ec} Genomics"));
+        assert!(!emoji.ends_with('\u{fffd}'));
+
+        let long = "Department of Molecular Microbiology and Immunology Students";
+        assert_eq!(long.chars().count(), 60);
+        let first = name(long);
+        let second = name(long);
+        let third = name(&format!("{long} (copy)"));
+        assert_eq!(first, "Department of Molecular Microbi");
+        assert_eq!(second, "Department of Molecular Mic (2)");
+        assert_eq!(third, "Department of Molecular Mic (3)");
+        assert_eq!(
+            name("DEPARTMENT OF MOLECULAR MICROBI"),
+            "DEPARTMENT OF MOLECULAR MIC (4)"
+        );
+
+        let mut workbook = Workbook::new();
+        for sheet_name in [&first, &second, &third, &emoji] {
+            workbook.add_worksheet().set_name(sheet_name).unwrap();
         }
+        workbook.save_to_buffer().unwrap();
     }
 
-    values
-}
-
-fn normalize_column_selection(indexes: &[usize], column_count: usize) -> Vec<usize> {
-    let mut normalized = Vec::new();
-    let mut seen = HashSet::new();
+    #[test]
+    fn app_errors_serialize_with_a_kind_tag_and_camel_case_fields() {
+        // The frontend reads `kind` to pick a recovery action and `message` to display.
+        let cases = [
+            (
+                AppError::invalid_input("facultyRecsPerStudent", "Must be at least 1."),
+                serde_json::json!({
+                    "kind": "invalidInput",
+                    "field": "facultyRecsPerStudent",
+                    "message": "Must be at least 1.",
+                }),
+            ),
+            (
+                AppError::StaleEmbeddings {
+                    message: "Refresh the faculty embeddings.".into(),
+                },
+                serde_json::json!({
+                    "kind": "staleEmbeddings",
+                    "message": "Refresh the faculty embeddings.",
+                }),
+            ),
+            (
+                AppError::HelperFailure {
+                    code: Some(3),
+                    stderr: "Traceback".into(),
+                    message: "The embedding helper exited.".into(),
+                },
+                serde_json::json!({
+                    "kind": "helperFailure",
+                    "code": 3,
+                    "stderr": "Traceback",
+                    "message": "The embedding helper exited.",
+                }),
+            ),
+            (
+                AppError::io(Path::new("faculty.xlsx"), "Unable to read."),
+                serde_json::json!({
+                    "kind": "io",
+                    "path": "faculty.xlsx",
+                    "message": "Unable to read.",
+                }),
+            ),
+            (
+                AppError::file_exists(Path::new("matches.xlsx")),
+                serde_json::json!({
+                    "kind": "fileExists",
+                    "path": "matches.xlsx",
+                    "message": "'matches.xlsx' already exists. Choose another name or allow overwriting.",
+                }),
+            ),
+            (
+                AppError::Busy {
+                    activity: HelperActivity::EmbeddingRefresh,
+                    request_id: Some("run-1".into()),
+                    message: "An embedding refresh is already running.".into(),
+                },
+                serde_json::json!({
+                    "kind": "busy",
+                    "activity": "embeddingRefresh",
+                    "requestId": "run-1",
+                    "message": "An embedding refresh is already running.",
+                }),
+            ),
+            (
+                AppError::from("Something failed."),
+                serde_json::json!({ "kind": "other", "message": "Something failed." }),
+            ),
+        ];
 
-    for &index in indexes {
-        if index >= column_count {
-            continue;
+        for (error, expected) in cases {
+            let message = error.to_string();
+            let serialized = serde_json::to_value(&error).unwrap();
+            assert_eq!(serialized, expected);
+            assert_eq!(serialized["message"], message.as_str());
         }
+    }
 
-        if seen.insert(index) {
-            normalized.push(index);
+    #[test]
+    fn faculty_search_is_rejected_while_another_search_holds_the_helper() {
+        let _lock = HELPER_ACTIVITY_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let index = embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.0, 1.0]),
+        ]);
+        let (slow_host, entered, release) = MockHost::new(&[1.0, 0.0]).gated();
+        slow_host.write_embedding_index(&index);
+        let fast_host = MockHost::new(&[0.0, 1.0]);
+        fast_host.write_embedding_index(&index);
+
+        let first = thread::spawn(move || {
+            perform_faculty_search(&slow_host, "protein folding", 1, Vec::new())
+        });
+        entered.recv().unwrap();
+
+        let error = perform_faculty_search(&fast_host, "membranes", 1, Vec::new()).unwrap_err();
+        assert!(matches!(
+            error,
+            AppError::Busy {
+                activity: HelperActivity::FacultySearch,
+                ..
+            }
+        ));
+        let error = begin_helper_activity(HelperActivity::Matching, Some("run-2")).err();
+        assert!(matches!(error, Some(AppError::Busy { .. })));
+        assert_eq!(fast_host.helper_calls.load(AtomicOrdering::SeqCst), 0);
+
+        release.send(()).unwrap();
+        let matches = first.join().unwrap().unwrap();
+        assert_eq!(matches[0].identifiers["Name"], "Robert Smith");
+        let matches = perform_faculty_search(&fast_host, "membranes", 1, Vec::new()).unwrap();
+        assert_eq!(matches[0].identifiers["Name"], "Ana Garcia");
+    }
+
+    #[test]
+    fn pasted_paths_lose_quotes_and_expand_environment_references() {
+        std::env::set_var("DBBS_TEST_PATH_ROOT", "/data/dbbs");
+        assert_eq!(
+            trim_pasted_path("  \"C:\\Users\\me\\roster.csv\"  "),
+            "C:\\Users\\me\\roster.csv"
+        );
+        assert_eq!(trim_pasted_path("'/tmp/roster.csv'"), "/tmp/roster.csv");
+
+        for reference in [
+            "%DBBS_TEST_PATH_ROOT%",
+            "$DBBS_TEST_PATH_ROOT",
+            "${DBBS_TEST_PATH_ROOT}",
+        ] {
+            assert_eq!(
+                expand_user_path(&format!("\"{reference}/roster.csv\"")),
+                PathBuf::from("/data/dbbs/roster.csv")
+            );
         }
+        assert_eq!(
+            expand_user_path("%DBBS_TEST_PATH_MISSING%/50% done/$"),
+            PathBuf::from("%DBBS_TEST_PATH_MISSING%/50% done/$")
+        );
+
+        let error = resolve_existing_path(
+            Some("$DBBS_TEST_PATH_ROOT/missing.csv".into()),
+            false,
+            "Roster",
+        )
+        .unwrap_err();
+        assert!(
+            error.starts_with(
+                "Roster was not found: /data/dbbs/missing.csv (expanded from '$DBBS_TEST_PATH_ROOT/missing.csv';"
+            ),
+            "{error}"
+        );
     }
 
-    normalized
-}
+    #[cfg(unix)]
+    #[test]
+    fn unix_paths_expand_tilde_users_and_keep_non_utf8_bytes() {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let home = home_dir().unwrap();
+        assert_eq!(expand_user_path("~"), home);
+        assert_eq!(expand_user_path("~/roster.csv"), home.join("roster.csv"));
+        let root_home = user_home_dir("root").unwrap();
+        assert_eq!(
+            expand_user_path("~root/roster.csv"),
+            root_home.join("roster.csv")
+        );
+        assert_eq!(
+            expand_user_path("~dbbs-no-such-user/roster.csv"),
+            PathBuf::from("~dbbs-no-such-user/roster.csv")
+        );
 
-fn header_label(headers: &[String], index: usize) -> String {
-    headers
-        .get(index)
-        .map(|value| value.trim())
-        .filter(|value| !value.is_empty())
-        .map(|value| value.to_string())
-        .unwrap_or_else(|| format!("Column {}", index + 1))
-}
+        let raw = std::ffi::OsString::from_vec(b"/data/caf\xe9".to_vec());
+        std::env::set_var("DBBS_TEST_PATH_LATIN1", &raw);
+        let expanded = expand_user_path("$DBBS_TEST_PATH_LATIN1/roster.csv");
+        assert_eq!(expanded.as_os_str().as_bytes(), b"/data/caf\xe9/roster.csv");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_paths_expand_percent_references() {
+        let profile = PathBuf::from(std::env::var_os("USERPROFILE").unwrap());
+        assert_eq!(
+            expand_user_path("\"%USERPROFILE%\\Documents\\roster.csv\""),
+            PathBuf::from(format!("{}\\Documents\\roster.csv", profile.display()))
+        );
+        assert_eq!(
+            expand_user_path("~\\roster.csv"),
+            profile.join("roster.csv")
+        );
+    }
+
+    #[test]
+    fn evaluation_scores_recall_and_reports_unmatched_rows() {
+        let host = MockHost::new(&[0.0, 0.0, 1.0])
+            .with_embedding("cell signaling", &[0.9, 0.3, 0.1])
+            .with_embedding("membrane transport", &[0.1, 0.2, 0.95]);
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0, 0.0]),
+            embedding_entry(1, "Ana García", &[0.0, 1.0, 0.0]),
+            embedding_entry(2, "Helen Jones", &[0.0, 0.0, 1.0]),
+        ]));
+        let assignments = write_file(
+            &host.data_dir,
+            "assignments.csv",
+            "Student,Prompt,Reviewer\n\
+             Student A,cell signaling,Robert Smith\n\
+             Student A,cell signaling,ana garcia\n\
+             Student B,membrane transport,ROBERT SMITH\n\
+             Student C,protein folding,Unknown Person\n\
+             Student D,,Helen Jones\n",
+        );
+
+        let report = perform_matching_evaluation(
+            &host,
+            EvaluationPayload {
+                assignments_path: assignments.to_string_lossy().into_owned(),
+                k_values: vec![2, 1, 0, 2],
+                student_columns: strings(&["Student"]),
+                prompt_columns: strings(&["Prompt"]),
+                document_column: None,
+                faculty_column_map: HashMap::from([("Name".to_string(), "Reviewer".to_string())]),
+                email_domain_aliases: HashMap::new(),
+                program_filters: Vec::new(),
+                include_workbook: false,
+            },
+            "evaluation-test",
+        )
+        .unwrap();
+
+        assert_eq!(report.students_evaluated, 2);
+        let ranks: Vec<(&str, &str, Option<usize>)> = report
+            .assignments
+            .iter()
+            .map(|assignment| {
+                (
+                    assignment.student.as_str(),
+                    assignment.faculty.as_str(),
+                    assignment.rank,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranks,
+            [
+                ("Student A", "Robert Smith", Some(1)),
+                ("Student A", "ana garcia", Some(2)),
+                ("Student B", "ROBERT SMITH", Some(3)),
+            ]
+        );
+        let metrics: Vec<(usize, usize)> = report
+            .metrics
+            .iter()
+            .map(|metric| (metric.k, metric.hits))
+            .collect();
+        assert_eq!(metrics, [(1, 1), (2, 2)]);
+        assert!((report.metrics[1].recall - 2.0 / 3.0).abs() < 1e-9);
+        assert!((report.mean_reciprocal_rank - (1.0 + 0.5 + 1.0 / 3.0) / 3.0).abs() < 1e-9);
+        assert_eq!(
+            report.unmatched_students,
+            [
+                "Student C (no assigned faculty found in the dataset)",
+                "Student D (no prompt text)"
+            ]
+        );
+        assert_eq!(report.unmatched_faculty, ["Unknown Person"]);
+        assert_eq!(
+            report.warnings,
+            [
+                "1 assigned faculty member could not be found in the faculty dataset.",
+                "2 students could not be evaluated."
+            ]
+        );
+    }
+    #[test]
+    fn scrubbing_redacts_emails_but_not_bare_at_signs() {
+        let (text, redactions) = scrub_personal_information_text(
+            "Contact me at mary.watson+lab@wustl.edu or m_w@mail.example.org. Not an email: a@b or x@localhost.",
+            &[],
+        );
+        assert_eq!(
+            text,
+            "Contact me at [email] or [email]. Not an email: a@b or x@localhost."
+        );
+        assert_eq!(
+            (redactions.emails, redactions.phones, redactions.names),
+            (2, 0, 0)
+        );
+    }
 
-fn collect_program_values(rows: &[Vec<String>], program_indexes: &[usize]) -> Vec<String> {
-    let mut seen = HashSet::new();
-    let mut values = Vec::new();
+    #[test]
+    fn scrubbing_redacts_phone_formats_but_not_other_numbers() {
+        let (text, redactions) = scrub_personal_information_text(
+            "Call (314) 555-0123, 314-555-0123, 314.555.0123, +1 314 555 0123 or 3145550123. \
+             Not phones: 2019-2023, 12.5 mM, 1,000,000 cells, ID 12345.",
+            &[],
+        );
+        assert_eq!(
+            text,
+            "Call [phone], [phone], [phone], [phone] or [phone]. \
+             Not phones: 2019-2023, 12.5 mM, 1,000,000 cells, ID 12345."
+        );
+        assert_eq!(redactions.phones, 5);
+    }
 
-    for row in rows {
-        for &index in program_indexes {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                let key = trimmed.to_lowercase();
-                if seen.insert(key) {
-                    values.push(trimmed.to_string());
+    #[test]
+    fn scrubbing_redacts_the_students_own_name_with_middle_initials() {
+        let identifiers = strings(&["Mary J. Watson", "mwatson@wustl.edu", "12345"]);
+        let (text, redactions) = scrub_personal_information_text(
+            "My name is Mary J. Watson. Later, M. J. Watson joined Dr. Watson's lab with Jay.",
+            &identifiers,
+        );
+        assert_eq!(
+            text,
+            "My name is [name]. Later, [name] joined Dr. [name]'s lab with Jay."
+        );
+        assert_eq!(redactions.names, 3);
+        assert_eq!(redactions.describe().as_deref(), Some("3 names"));
+    }
+
+    #[test]
+    fn parallel_scoring_matches_serial_order_for_any_thread_count() {
+        // Every faculty vector appears three times, so most similarities tie and only
+        // dataset order decides the ranking.
+        let distinct = synthetic_embeddings(200, 32, 7);
+        let entries = (0..600)
+            .map(|row| embedding_entry(row, &format!("Faculty {row}"), &distinct[row % 200]))
+            .collect();
+        let index = embedding_index(entries);
+        let allowed: HashSet<usize> = (0..600).filter(|row| row % 5 != 0).collect();
+
+        for prompt in synthetic_embeddings(5, 32, 11) {
+            for allowed_rows in [None, Some(&allowed)] {
+                let expected = serial_faculty_ranking(&index, &prompt, 50, allowed_rows);
+                for threads in [1, 2, 3, 8] {
+                    let pool = rayon::ThreadPoolBuilder::new()
+                        .num_threads(threads)
+                        .build()
+                        .unwrap();
+                    let matches = pool
+                        .install(|| find_best_faculty_matches(&index, &prompt, 50, allowed_rows));
+                    assert_eq!(ranking_keys(&matches), expected, "{threads} threads");
                 }
             }
         }
     }
 
-    values.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
-    values
-}
-
-fn build_faculty_program_memberships(
-    headers: &[String],
-    rows: &[Vec<String>],
-    identifier_indexes: &[usize],
-    program_indexes: &[usize],
-) -> Vec<FacultyProgramMembership> {
-    let mut memberships = Vec::new();
+    /// Run with `cargo test --release --lib bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_parallel_faculty_scoring() {
+        let index = synthetic_index(5_000, 768, 3);
+        let prompts = synthetic_embeddings(100, 768, 5);
 
-    for (row_index, row) in rows.iter().enumerate() {
-        let mut identifiers = HashMap::new();
-        for &index in identifier_indexes {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                let label = header_label(headers, index);
-                identifiers
-                    .entry(label)
-                    .or_insert_with(|| trimmed.to_string());
-            }
+        let started = Instant::now();
+        for prompt in &prompts {
+            std::hint::black_box(serial_faculty_ranking(&index, prompt, 10, None));
         }
+        let serial = started.elapsed();
+        let started = Instant::now();
+        for prompt in &prompts {
+            std::hint::black_box(find_best_faculty_matches(&index, prompt, 10, None));
+        }
+        let parallel = started.elapsed();
 
-        let mut program_set = BTreeSet::new();
-        for &index in program_indexes {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                program_set.insert(trimmed.to_string());
+        println!(
+            "5,000 faculty x 100 prompts: serial {serial:?}, parallel {parallel:?} ({:.1}x on {} threads)",
+            serial.as_secs_f64() / parallel.as_secs_f64(),
+            rayon::current_num_threads()
+        );
+    }
+
+    #[test]
+    fn embedding_index_cache_reads_the_file_once_and_reuses_unchanged_content() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        host.write_embedding_index(&embedding_index(vec![embedding_entry(
+            0,
+            "Robert Smith",
+            &[1.0, 0.0],
+        )]));
+        let reads = || {
+            host.embedding_index_cache
+                .file_reads
+                .load(AtomicOrdering::Relaxed)
+        };
+
+        let first = load_faculty_embedding_index(&host).unwrap();
+        let second = load_faculty_embedding_index(&host).unwrap();
+        assert_eq!(reads(), 1);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // A touched but identical file is read again but not re-parsed.
+        let path = host.data_dir.path().join(FACULTY_EMBEDDINGS_NAME);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        let touched = load_faculty_embedding_index(&host).unwrap();
+        assert_eq!(reads(), 2);
+        assert!(Arc::ptr_eq(&first, &touched));
+
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.0, 1.0]),
+        ]));
+        let changed = load_faculty_embedding_index(&host).unwrap();
+        assert_eq!(reads(), 3);
+        assert_eq!(changed.entries.len(), 2);
+    }
+
+    #[test]
+    fn half_precision_index_scores_within_a_thousandth_of_f32() {
+        let mut index = synthetic_index(200, 768, 21);
+        index.precision = EmbeddingPrecision::F16;
+        let stored = serialize_faculty_embedding_index(&index).unwrap();
+        let loaded = parse_faculty_embedding_index(&stored).unwrap();
+        assert_eq!(loaded.precision, EmbeddingPrecision::F16);
+        assert!(loaded
+            .entries
+            .iter()
+            .all(|entry| entry.embedding_f16.is_none()));
+
+        for prompt in synthetic_embeddings(10, 768, 23) {
+            for (reference, widened) in index.entries.iter().zip(&loaded.entries) {
+                assert_eq!(widened.embedding.len(), 768);
+                let expected = cosine_similarity(&prompt, &reference.embedding).unwrap();
+                let actual = cosine_similarity(&prompt, &widened.embedding).unwrap();
+                assert!(
+                    (expected - actual).abs() < 1e-3,
+                    "row {}: {expected} vs {actual}",
+                    reference.row_index
+                );
             }
-        }
 
-        if identifiers.is_empty() && program_set.is_empty() {
-            continue;
+            // The scores matching reports agree entry by entry, not just the raw cosines.
+            let full: HashMap<usize, f32> = find_best_faculty_matches(&index, &prompt, 200, None)
+                .into_iter()
+                .map(|result| (result.row_index, result.similarity))
+                .collect();
+            let half = find_best_faculty_matches(&loaded, &prompt, 200, None);
+            assert_eq!(half.len(), full.len());
+            for result in half {
+                let expected = full[&result.row_index];
+                assert!(
+                    (expected - result.similarity).abs() < 1e-3,
+                    "row {}: {expected} vs {}",
+                    result.row_index,
+                    result.similarity
+                );
+            }
         }
+    }
 
-        memberships.push(FacultyProgramMembership {
-            row_index,
-            identifiers,
-            programs: program_set.into_iter().collect(),
+    #[test]
+    fn indexes_saved_before_half_precision_still_load_as_f32() {
+        let stored = serde_json::json!({
+            "model": "legacy-model",
+            "dimension": 3,
+            "identifierColumns": ["Name"],
+            "entries": [
+                {
+                    "rowIndex": 0,
+                    "identifiers": { "Name": "Robert Smith" },
+                    "embedding": [0.1, -0.25, 0.123_456_7]
+                }
+            ]
         });
+        let index = parse_faculty_embedding_index(stored.to_string().as_bytes()).unwrap();
+
+        assert_eq!(index.precision, EmbeddingPrecision::F32);
+        assert_eq!(index.entries[0].embedding, vec![0.1, -0.25, 0.123_456_7]);
+        assert!(!index.entries[0].faculty_id.is_empty());
+        let rewritten: serde_json::Value =
+            serde_json::from_slice(&serialize_faculty_embedding_index(&index).unwrap()).unwrap();
+        assert_eq!(
+            rewritten["entries"][0]["embedding"],
+            stored["entries"][0]["embedding"]
+        );
+        assert!(rewritten["entries"][0].get("embeddingF16").is_none());
     }
 
-    memberships
-}
-
-fn metadata_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let directory = dataset_directory(app_handle)?;
-    Ok(directory.join(FACULTY_DATASET_METADATA_NAME))
-}
+    #[test]
+    fn half_precision_entries_must_match_the_index_dimension() {
+        let mut index = embedding_index(vec![embedding_entry(0, "Robert Smith", &[1.0, 0.0, 0.5])]);
+        index.precision = EmbeddingPrecision::F16;
+        let stored = serialize_faculty_embedding_index(&index).unwrap();
+        let mut value: serde_json::Value = serde_json::from_slice(&stored).unwrap();
+        let encoded = value["entries"][0]["embeddingF16"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let bytes = Base64Engine.decode(encoded).unwrap();
+
+        for corrupt in [&bytes[..bytes.len() - 1], &bytes[..bytes.len() - 2]] {
+            value["entries"][0]["embeddingF16"] = Base64Engine.encode(corrupt).into();
+            let error = parse_faculty_embedding_index(value.to_string().as_bytes()).unwrap_err();
+            assert!(
+                error.contains("expected 3 values (6 bytes)"),
+                "{} bytes: {error}",
+                corrupt.len()
+            );
+        }
+    }
 
-fn write_faculty_dataset_metadata(
-    app_handle: &tauri::AppHandle,
-    analysis: &FacultyDatasetAnalysis,
-    memberships: &[FacultyProgramMembership],
-) -> Result<(), String> {
-    let path = metadata_path(app_handle)?;
-    ensure_dataset_directory(&path)?;
-    let payload = FacultyDatasetMetadata {
-        analysis: analysis.clone(),
-        memberships: memberships.to_vec(),
-    };
-    let json = serde_json::to_string_pretty(&payload)
-        .map_err(|err| format!("Unable to serialize faculty dataset metadata: {err}"))?;
-    fs::write(&path, json)
-        .map_err(|err| format!("Unable to persist faculty dataset metadata: {err}"))?;
-    Ok(())
-}
+    #[test]
+    fn constant_memory_matches_sheet_matches_the_in_memory_workbook() {
+        let info = run_info();
+        let options = run_options(&info);
+        let entries = sample_match_entries();
+        let student_headers = strings(&["Student"]);
+        let faculty_headers = strings(&["Name"]);
+        let student_rows = string_rows(&[&["Student A"], &["Student B"]]);
+        let statistics = RunStatistics::default();
+        let data = export_data(
+            &student_headers,
+            &student_rows,
+            &faculty_headers,
+            &entries,
+            &statistics,
+        );
+        let build = |constant_memory_threshold| {
+            build_matches_workbook_with_limits(
+                &data,
+                &options,
+                &mut Vec::new(),
+                WORKSHEET_MAX_ROWS,
+                constant_memory_threshold,
+            )
+            .unwrap()
+        };
+        let in_memory = build(usize::MAX);
+        let streamed = build(0);
+
+        let names = sheet_names(&in_memory);
+        assert_eq!(sheet_names(&streamed), names);
+        for name in &names {
+            assert_eq!(
+                read_sheet(&streamed, name),
+                read_sheet(&in_memory, name),
+                "{name}"
+            );
+        }
 
-fn load_faculty_dataset_metadata(
-    app_handle: &tauri::AppHandle,
-) -> Result<Option<FacultyDatasetMetadata>, String> {
-    let path = metadata_path(app_handle)?;
-    if !path.exists() {
-        return Ok(None);
+        // The header notes, filter and validation written before the rows survive.
+        let streamed_matches = sheet_xml(&streamed, 1);
+        assert!(streamed_matches.contains(r#"<autoFilter ref="A1:G5"/>"#));
+        assert!(streamed_matches.contains(r#"sqref="A2:B5""#));
+        let comments = |workbook: &[u8]| {
+            let mut archive = zip::ZipArchive::new(Cursor::new(workbook)).unwrap();
+            let mut xml = String::new();
+            archive
+                .by_name("xl/comments1.xml")
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+            xml
+        };
+        assert_eq!(comments(&streamed), comments(&in_memory));
+        assert!(comments(&streamed).contains("first reviewer"));
     }
 
-    let data = fs::read(&path)
-        .map_err(|err| format!("Unable to read the faculty dataset metadata: {err}"))?;
-    if data.is_empty() {
-        return Ok(None);
+    /// The per-cell `trim().to_string()` reader the buffered one replaced.
+    fn unbuffered_delimited_rows(data: &[u8]) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+        let mut headers: Vec<String> = reader
+            .headers()
+            .unwrap()
+            .iter()
+            .map(|value| value.trim().to_string())
+            .collect();
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let values: Vec<String> = record
+                .unwrap()
+                .iter()
+                .map(|value| value.trim().to_string())
+                .collect();
+            if values.iter().all(|value| value.is_empty()) {
+                continue;
+            }
+            rows.push(values);
+        }
+        align_row_lengths(&mut headers, &mut rows);
+        (headers, rows)
     }
 
-    let metadata = serde_json::from_slice(&data)
-        .map_err(|err| format!("Unable to parse the faculty dataset metadata: {err}"))?;
-    Ok(Some(metadata))
-}
+    #[test]
+    fn buffered_delimited_reader_matches_the_unbuffered_one() {
+        let data = b" Name ,Program,Status\n  Ana Garcia , Biology ,\n , ,\n\nBob,\tChemistry\t,active,extra\n,,Pending \n";
+        let (headers, rows, _) = read_delimited_rows(&data[..], b',', None).unwrap();
 
-fn filter_faculty_rows_by_program(
-    memberships: &[FacultyProgramMembership],
-    programs: &[String],
-) -> HashSet<usize> {
-    if programs.is_empty() {
-        return HashSet::new();
+        assert_eq!((headers, rows), unbuffered_delimited_rows(data));
     }
 
-    let normalized_filters: HashSet<String> = programs
-        .iter()
-        .map(|program| program.to_lowercase())
-        .collect();
+    /// Run with `cargo test --release --lib bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_delimited_spreadsheet_read() {
+        let programs = ["Biology", "Chemistry", "Neuroscience", "Immunology"];
+        let statuses = ["active", "pending", ""];
+        let mut data = String::from("Name,Email,Program,Status,Advisor,Notes,Lab,Year\n");
+        for row in 0..100_000 {
+            data.push_str(&format!(
+                " Student {row} ,student{row}@example.edu,{},{},Advisor {},,{},{}\n",
+                programs[row % programs.len()],
+                statuses[row % statuses.len()],
+                row % 300,
+                if row % 4 == 0 { "" } else { " Lab " },
+                2020 + row % 6
+            ));
+            if row % 1_000 == 0 {
+                data.push_str(",,,,,,,\n");
+            }
+        }
+        let data = data.into_bytes();
+
+        let started = Instant::now();
+        let unbuffered = std::hint::black_box(unbuffered_delimited_rows(&data));
+        let before = started.elapsed();
+        let started = Instant::now();
+        let (headers, rows, _) =
+            std::hint::black_box(read_delimited_rows(&data[..], b',', None).unwrap());
+        let after = started.elapsed();
+
+        assert_eq!((headers, rows), unbuffered);
+        println!(
+            "100,000 rows x 8 columns: unbuffered {before:?}, buffered {after:?} ({:.1}x)",
+            before.as_secs_f64() / after.as_secs_f64()
+        );
+    }
 
-    let mut allowed_rows = HashSet::new();
-    for membership in memberships {
-        for program in &membership.programs {
-            let normalized_program = program.to_lowercase();
-            if normalized_filters.contains(&normalized_program) {
-                allowed_rows.insert(membership.row_index);
-                break;
+    #[test]
+    fn rows_after_blank_lines_keep_their_source_row_numbers() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let index = embedding_index(vec![embedding_entry(0, "Robert Smith", &[1.0, 0.0])]);
+        let info = run_info();
+        let options = run_options(&info);
+
+        // Line 3 has only blank cells and line 4 is empty; line 6 has no prompt.
+        let tsv = "Prompt\tNote\nfirst prompt\ta\n\t\n\nsecond prompt\tb\n\tno prompt\n";
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        for (row, values) in [
+            (0, ["Prompt", "Note"]),
+            (1, ["first prompt", "a"]),
+            (4, ["second prompt", "b"]),
+            (5, ["", "no prompt"]),
+        ] {
+            for (col, value) in values.into_iter().enumerate() {
+                if !value.is_empty() {
+                    sheet.write_string(row, col as u16, value).unwrap();
+                }
             }
         }
+        let xlsx = workbook.save_to_buffer().unwrap();
+
+        for (filename, data) in [
+            ("prompts.tsv", tsv.as_bytes().to_vec()),
+            ("prompts.xlsx", xlsx),
+        ] {
+            let outcome = process_prompt_spreadsheet(
+                &host,
+                &SpreadsheetSource::Inline {
+                    filename: filename.into(),
+                    data,
+                },
+                &index,
+                &strings(&["Prompt"]),
+                &[],
+                &options,
+            )
+            .unwrap();
+
+            assert!(
+                outcome.warnings.iter().any(|warning| warning
+                    == "Skipped row 6 because the selected prompt columns were empty."),
+                "{filename}: {:?}",
+                outcome.warnings
+            );
+            let skipped = &outcome.results.skipped_entries;
+            assert_eq!(skipped.len(), 1, "{filename}");
+            assert_eq!(skipped[0].label, "Row 6", "{filename}");
+            assert_eq!(skipped[0].identifier_values, ["6"], "{filename}");
+
+            let spreadsheet = &outcome.results.spreadsheet;
+            let bytes = Base64Engine.decode(&spreadsheet.content).unwrap();
+            let matches = read_sheet(&bytes, "Matches");
+            let row_number = matches[0]
+                .iter()
+                .position(|header| header == "Row Number")
+                .unwrap();
+            let numbers: BTreeSet<&str> = matches[1..]
+                .iter()
+                .map(|row| row[row_number].as_str())
+                .collect();
+            assert_eq!(numbers, BTreeSet::from(["2", "5"]), "{filename}");
+        }
     }
 
-    allowed_rows
-}
+    #[test]
+    fn delimited_row_numbers_count_crlf_cr_and_quoted_line_breaks() {
+        let data =
+            b"Prompt,Note\r\n\r\nfirst,\"two\r\nlines\"\r\n,\r\nsecond,x\rthird,y\n\n\nfourth,z";
+        let (_, rows, row_numbers) = read_delimited_rows(&data[..], b',', None).unwrap();
 
-fn clear_faculty_dataset_metadata(app_handle: &tauri::AppHandle) -> Result<(), String> {
-    let path = metadata_path(app_handle)?;
-    if path.exists() {
-        fs::remove_file(&path)
-            .map_err(|err| format!("Unable to clear faculty dataset metadata: {err}"))?;
+        let prompts: Vec<&str> = rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(prompts, ["first", "second", "third", "fourth"]);
+        assert_eq!(row_numbers, [3, 6, 7, 10]);
     }
-    Ok(())
-}
 
-fn dataset_source_record_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let directory = dataset_directory(app_handle)?;
-    Ok(directory.join(FACULTY_DATASET_SOURCE_NAME))
-}
+    #[test]
+    fn outcome_breakdown_counts_each_item_once() {
+        use ItemOutcome::*;
+        // (outcomes, [total, unreadable, embedding failed, matched, no matches])
+        let cases: [(&[ItemOutcome], [usize; 5]); 6] = [
+            (&[], [0, 0, 0, 0, 0]),
+            (&[Matched, Matched], [2, 0, 0, 2, 0]),
+            (&[NoMatches], [1, 0, 0, 0, 1]),
+            (&[Unreadable, EmbeddingFailed], [2, 1, 1, 0, 0]),
+            // An item that never got past reading counts as unreadable.
+            (&[Pending, Matched], [2, 1, 0, 1, 0]),
+            (
+                &[Matched, Unreadable, NoMatches, EmbeddingFailed, Matched],
+                [5, 1, 1, 2, 1],
+            ),
+        ];
 
-fn write_faculty_dataset_source_path(
-    app_handle: &tauri::AppHandle,
-    source: &Path,
-) -> Result<(), String> {
-    let record_path = dataset_source_record_path(app_handle)?;
-    ensure_dataset_directory(&record_path)?;
-    let canonical = source
-        .canonicalize()
-        .unwrap_or_else(|_| source.to_path_buf())
-        .to_string_lossy()
-        .into_owned();
-    fs::write(&record_path, canonical)
-        .map_err(|err| format!("Unable to record the faculty dataset source path: {err}"))?;
-    Ok(())
-}
+        for (outcomes, expected) in cases {
+            let breakdown = ItemOutcomeBreakdown::from_outcomes(outcomes.iter().copied());
+            assert_eq!(
+                [
+                    breakdown.total,
+                    breakdown.unreadable,
+                    breakdown.embedding_failed,
+                    breakdown.matched,
+                    breakdown.no_matches
+                ],
+                expected,
+                "{outcomes:?}"
+            );
+            assert_eq!(breakdown.processed(), expected[3] + expected[4]);
+            assert_eq!(breakdown.skipped(), expected[1] + expected[2]);
+            assert_eq!(breakdown.processed() + breakdown.skipped(), breakdown.total);
+        }
+    }
 
-fn read_faculty_dataset_source_path(
-    app_handle: &tauri::AppHandle,
-) -> Result<Option<String>, String> {
-    let record_path = dataset_source_record_path(app_handle)?;
-    if !record_path.exists() {
-        return Ok(None);
+    /// Labels on the Skipped sheet, below its header row.
+    fn skipped_sheet_labels(spreadsheet: &GeneratedSpreadsheet) -> Vec<String> {
+        let bytes = Base64Engine.decode(&spreadsheet.content).unwrap();
+        read_sheet(&bytes, "Skipped")[1..]
+            .iter()
+            .map(|row| row[0].clone())
+            .collect()
     }
 
-    let contents = fs::read_to_string(&record_path)
-        .map_err(|err| format!("Unable to read the faculty dataset source path: {err}"))?;
-    let trimmed = contents.trim();
-    if trimmed.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(trimmed.to_string()))
+    #[test]
+    fn batch_counts_and_skipped_sheet_agree_for_every_outcome() {
+        // Default embeddings match; a zero vector scores nothing; an empty one is dropped
+        // by the helper.
+        let host = MockHost::new(&[1.0, 0.0])
+            .with_embedding("zero vector", &[0.0, 0.0])
+            .with_embedding("helper drops this", &[]);
+        let index = embedding_index(vec![embedding_entry(0, "Robert Smith", &[1.0, 0.0])]);
+        let info = run_info();
+        let options = run_options(&info);
+
+        let tsv = "Student\tPrompt\nAna\tmatching prompt\nBen\t\nCal\thelper drops this\nDee\tzero vector\n";
+        let spreadsheet = process_prompt_spreadsheet(
+            &host,
+            &SpreadsheetSource::Inline {
+                filename: "prompts.tsv".into(),
+                data: tsv.as_bytes().to_vec(),
+            },
+            &index,
+            &strings(&["Prompt"]),
+            &strings(&["Student"]),
+            &options,
+        )
+        .unwrap()
+        .results;
+        let breakdown = &spreadsheet.breakdown;
+        assert_eq!(
+            [
+                breakdown.total,
+                breakdown.unreadable,
+                breakdown.embedding_failed,
+                breakdown.matched,
+                breakdown.no_matches
+            ],
+            [4, 1, 1, 1, 1]
+        );
+        assert_eq!(
+            [
+                spreadsheet.processed_rows,
+                spreadsheet.matched_rows,
+                spreadsheet.skipped_rows
+            ],
+            [2, 1, 2]
+        );
+        assert_eq!(
+            skipped_sheet_labels(&spreadsheet.spreadsheet),
+            ["Ben", "Cal"]
+        );
+
+        let directory = TempDir::new().unwrap();
+        write_file(&directory, "ana.txt", "matching prompt");
+        write_file(&directory, "ben.txt", "   ");
+        write_file(&directory, "cal.txt", "helper drops this");
+        write_file(&directory, "dee.txt", "zero vector");
+        let documents = process_directory_documents(&host, directory.path(), &index, &options)
+            .unwrap()
+            .results;
+        let breakdown = &documents.breakdown;
+        assert_eq!(
+            [
+                breakdown.total,
+                breakdown.unreadable,
+                breakdown.embedding_failed,
+                breakdown.matched,
+                breakdown.no_matches
+            ],
+            [4, 1, 1, 1, 1]
+        );
+        assert_eq!(
+            [
+                documents.processed_documents,
+                documents.matched_documents,
+                documents.skipped_documents
+            ],
+            [2, 1, 2]
+        );
+        assert_eq!(
+            skipped_sheet_labels(&documents.spreadsheet),
+            ["ben.txt", "cal.txt"]
+        );
     }
-}
 
-fn clear_faculty_dataset_source_path(app_handle: &tauri::AppHandle) -> Result<(), String> {
-    let record_path = dataset_source_record_path(app_handle)?;
-    if record_path.exists() {
-        fs::remove_file(&record_path)
-            .map_err(|err| format!("Unable to clear the faculty dataset source path: {err}"))?;
+    #[test]
+    fn roster_run_lists_each_roster_warning_once() {
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[&["Robert Smith"], &["Ana Garcia"], &["Helen Jones"]],
+        );
+        let roster = "Name\nRobert Smith\nAna Garcia\nZed Unknown\n";
+        // The roster preview's warnings come back with the submission, as the UI sends them.
+        let preview =
+            match_roster_file("roster.tsv", roster, &metadata, &[("Name", "Name")], false);
+        assert_eq!(
+            preview.warnings,
+            ["1 roster row did not match any faculty dataset entries."]
+        );
+
+        let host = MockHost::new(&[1.0, 0.0]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.6, 0.8]),
+            embedding_entry(2, "Helen Jones", &[0.0, 1.0]),
+        ]));
+        let roster_path = write_file(&host.data_dir, "roster.tsv", roster);
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "prompt",
+            "promptText": "Tumor immunology",
+            "facultyScope": "custom",
+            "customFacultyPath": roster_path,
+            "facultyRecsPerStudent": 3,
+            "facultyRosterColumnMap": { "Name": "Name" },
+            "facultyRosterWarnings": preview.warnings,
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "roster-run").unwrap();
+
+        assert_eq!(response.details.faculty_roster_warnings, preview.warnings);
+        assert_eq!(
+            response.warnings[..2],
+            [
+                "Only 2 faculty are in scope, so each student will receive at most 2 of the requested 3 recommendations.",
+                "1 roster row did not match any faculty dataset entries."
+            ]
+        );
+        let unique: HashSet<&String> = response.warnings.iter().collect();
+        assert_eq!(
+            unique.len(),
+            response.warnings.len(),
+            "{:?}",
+            response.warnings
+        );
     }
-    Ok(())
-}
 
-fn dataset_destination(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let directory = dataset_directory(app_handle)?;
-    for extension in FACULTY_DATASET_EXTENSIONS {
-        let candidate = dataset_path_with_extension(&directory, extension);
-        if candidate.exists() {
-            return Ok(candidate);
-        }
+    #[test]
+    fn warning_collector_drops_exact_repeats_in_first_seen_order() {
+        let mut warnings: WarningCollector =
+            ["b".to_string(), "a".to_string()].into_iter().collect();
+        warnings.push("b".into());
+        warnings.push("Skipped row 2 (Ana).".into());
+        warnings.push("Skipped row 3 (Ana).".into());
+        warnings.prepend(["first".to_string(), "a".to_string()]);
+        let appended = warnings.append_with(|list| {
+            list.push("b".into());
+            list.push("last".into());
+            list.len()
+        });
+
+        assert_eq!(appended, 7);
+        assert_eq!(
+            warnings.into_vec(),
+            [
+                "first",
+                "a",
+                "b",
+                "Skipped row 2 (Ana).",
+                "Skipped row 3 (Ana).",
+                "last"
+            ]
+        );
     }
 
-    Ok(dataset_path_with_extension(
-        &directory,
-        FACULTY_DATASET_DEFAULT_EXTENSION,
-    ))
-}
+    #[test]
+    fn prompt_previews_cut_between_grapheme_clusters() {
+        // Devanagari conjuncts with vowel signs, a ZWJ family, a flag and a skin tone.
+        let unit = "नमस्ते क्षत्रिय 👨‍👩‍👧‍👦 🇮🇳 👍🏽 e\u{301}";
+        let text = unit.repeat(40);
+        let boundaries: HashSet<usize> = text
+            .grapheme_indices(true)
+            .map(|(offset, _)| offset)
+            .collect();
 
-fn dataset_destination_for_extension(
-    app_handle: &tauri::AppHandle,
-    extension: &str,
-) -> Result<PathBuf, String> {
-    let directory = dataset_directory(app_handle)?;
-    Ok(dataset_path_with_extension(&directory, extension))
-}
+        for limit in 1..=unit.chars().count() * 3 {
+            let preview = build_prompt_preview(&text, limit);
+            let kept = preview.strip_suffix('…').unwrap();
+            assert!(text.starts_with(kept), "limit {limit}");
+            assert!(boundaries.contains(&kept.len()), "limit {limit}: {kept:?}");
+            assert!(kept.chars().count() <= limit, "limit {limit}");
+        }
+    }
 
-fn dataset_directory(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|err| format!("Unable to resolve the application data directory: {err}"))
-}
+    #[test]
+    fn default_prompt_preview_of_ascii_text_keeps_280_characters() {
+        let text = "abcdefghij ".repeat(40);
+        let preview = build_prompt_preview(&text, resolve_prompt_preview_characters(None));
+
+        assert_eq!(preview, format!("{}…", &text[..280]));
+        assert_eq!(build_prompt_preview("short prompt", 280), "short prompt");
+        assert_eq!(build_prompt_preview(&text[..280], 280), &text[..280]);
+        assert_eq!(
+            resolve_prompt_preview_characters(Some(1)),
+            PROMPT_PREVIEW_CHARACTERS_MIN
+        );
+        assert_eq!(
+            resolve_prompt_preview_characters(Some(usize::MAX)),
+            PROMPT_PREVIEW_CHARACTERS_MAX
+        );
+    }
 
-fn dataset_path_with_extension(directory: &Path, extension: &str) -> PathBuf {
-    directory.join(format!("{}.{}", FACULTY_DATASET_BASENAME, extension))
-}
+    /// A small three-page PDF whose text uses ligature glyphs, curly quotes and an en dash.
+    fn messy_pdf() -> Vec<u8> {
+        let pages: [&[u8]; 3] = [
+            br"BT /F1 12 Tf 72 720 Td (Pulmonary \001brosis and \002uid \003ow) Tj ET BT /F1 12 Tf 72 700 Td (\223E\004cient\224 repair \226 2024) Tj ET",
+            br"BT /F1 12 Tf 72 720 Td (Second page) Tj ET",
+            br"BT /F1 12 Tf 72 720 Td (Third page) Tj ET",
+        ];
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [4 0 R 6 0 R 8 0 R] /Count 3 >>".to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding << /Type /Encoding /BaseEncoding /WinAnsiEncoding /Differences [1 /fi /fl /ff /ffi] >> >>".to_vec(),
+        ];
+        for (index, content) in pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                    5 + index * 2
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend_from_slice(content);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
 
-fn remove_other_dataset_variants(directory: &Path, keep_extension: &str) -> Result<(), String> {
-    for extension in FACULTY_DATASET_EXTENSIONS {
-        if extension.eq_ignore_ascii_case(keep_extension) {
-            continue;
+        let mut output = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(output.len());
+            output.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            output.extend_from_slice(object);
+            output.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = output.len();
+        output.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            output.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
         }
+        output.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+                objects.len() + 1
+            )
+            .as_bytes(),
+        );
+        output
+    }
 
-        let candidate = dataset_path_with_extension(directory, extension);
-        if candidate.exists() {
-            fs::remove_file(&candidate)
-                .map_err(|err| format!("Unable to remove a previous faculty dataset: {err}"))?;
+    #[test]
+    fn document_text_normalization_expands_ligatures_and_collapses_blank_runs() {
+        let cases = [
+            ("\u{FB00}ect", "ffect"),
+            ("\u{FB01}brosis", "fibrosis"),
+            ("\u{FB02}uid", "fluid"),
+            ("e\u{FB03}cient", "efficient"),
+            ("ba\u{FB04}e", "baffle"),
+            ("\u{FB05}ress \u{FB06}ate", "stress state"),
+            (
+                "\u{201C}quoted\u{201D} \u{2018}single\u{2019}",
+                "\"quoted\" 'single'",
+            ),
+            ("2020\u{2013}2024 \u{2014} \u{2212}1", "2020-2024 - -1"),
+            ("re\u{00AD}pair", "repair"),
+            ("one\n\n\n\n\n\ntwo", "one\n\ntwo"),
+            (
+                "one\n\n\nkept \ntwo\r\n\r\nthree",
+                "one\n\n\nkept\ntwo\n\nthree",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_document_text(input), expected, "{input:?}");
         }
     }
 
-    Ok(())
-}
+    #[test]
+    fn directory_previews_of_messy_documents_are_normalized() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let index = embedding_index(vec![embedding_entry(0, "Robert Smith", &[1.0, 0.0])]);
+        let info = run_info();
+        let options = run_options(&info);
+        let directory = TempDir::new().unwrap();
+        fs::write(directory.path().join("messy.pdf"), messy_pdf()).unwrap();
+        write_file(
+            &directory,
+            "messy.txt",
+            "\u{FB01}brosis re\u{00AD}search\n\n\n\n\n\u{201C}Next\u{201D} section",
+        );
 
-fn ensure_dataset_directory(path: &Path) -> Result<(), String> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|err| format!("Unable to prepare the faculty dataset directory: {err}"))?;
+        let prompts: Vec<String> =
+            process_directory_documents(&host, directory.path(), &index, &options)
+                .unwrap()
+                .prompt_matches
+                .into_iter()
+                .map(|result| result.prompt)
+                .collect();
+
+        assert_eq!(
+            prompts,
+            [
+                "messy.pdf — Pulmonary fibrosis and fluid ffow\n\n\"Efficient\" repair - 2024\n\nSecond pageThird page",
+                "messy.txt — fibrosis research\n\n\"Next\" section",
+            ]
+        );
     }
-    Ok(())
-}
 
-fn compute_tsv_dimensions(data: &[u8]) -> Result<(usize, usize), String> {
-    let mut reader = csv::ReaderBuilder::new()
-        .delimiter(b'\t')
-        .has_headers(true)
-        .flexible(true)
-        .from_reader(Cursor::new(data));
+    #[test]
+    fn overlapping_embedding_refreshes_are_rejected_until_the_first_finishes() {
+        let _lock = HELPER_ACTIVITY_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let host = MockHost::new(&[1.0, 0.0]);
+
+        let first = begin_embedding_refresh(&host).unwrap();
+        let error = begin_embedding_refresh(&host).err();
+        let Some(AppError::Busy {
+            activity, message, ..
+        }) = error
+        else {
+            panic!("expected the second refresh to be rejected, got {error:?}");
+        };
+        assert_eq!(activity, HelperActivity::EmbeddingRefresh);
+        assert!(matches!(
+            begin_helper_activity(HelperActivity::Matching, Some("run-1")),
+            Err(AppError::Busy { .. })
+        ));
 
-    let headers = reader
-        .headers()
-        .map_err(|err| format!("Unable to read the faculty dataset headers: {err}"))?
-        .clone();
+        let emitted = host.emitted.lock().unwrap().clone();
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(emitted[0].0, FACULTY_EMBEDDING_PROGRESS_EVENT);
+        assert_eq!(emitted[0].1["phase"], "rejected");
+        assert_eq!(emitted[0].1["message"], message.as_str());
 
-    let mut row_count = 0usize;
-    for record in reader.records() {
-        let record =
-            record.map_err(|err| format!("Unable to read the faculty dataset rows: {err}"))?;
-        if record.iter().any(|value| !value.trim().is_empty()) {
-            row_count += 1;
+        drop(first);
+        let second = begin_embedding_refresh(&host);
+        assert!(second.is_ok());
+        assert_eq!(host.emitted.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn short_and_nan_faculty_vectors_are_excluded_and_reported() {
+        let mut index = embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Short Vector", &[1.0]),
+            embedding_entry(2, "Nan Vector", &[f32::NAN, 1.0]),
+            embedding_entry(3, "Ana Garcia", &[1.0, 0.0]),
+            embedding_entry(4, "Helen Jones", &[0.0, 1.0]),
+        ]);
+        index.entries[4]
+            .identifiers
+            .insert("Email".into(), " ".into());
+
+        let diagnostics = find_excluded_faculty_entries(&index, None);
+        assert_eq!(diagnostics.expected_dimension, 2);
+        assert_eq!(
+            diagnostics.count(FacultyExclusionReason::DimensionMismatch),
+            1
+        );
+        assert_eq!(diagnostics.count(FacultyExclusionReason::InvalidScore), 1);
+        let excluded: Vec<(usize, &str, FacultyExclusionReason, usize)> = diagnostics
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.row_index,
+                    entry.identifiers["Name"].as_str(),
+                    entry.reason,
+                    entry.embedding_dimension,
+                )
+            })
+            .collect();
+        assert_eq!(
+            excluded,
+            [
+                (
+                    1,
+                    "Short Vector",
+                    FacultyExclusionReason::DimensionMismatch,
+                    1
+                ),
+                (2, "Nan Vector", FacultyExclusionReason::InvalidScore, 2),
+            ]
+        );
+        assert_eq!(
+            faculty_exclusion_warnings(&diagnostics),
+            [
+                "1 faculty entry was excluded due to mismatched embedding dimensions; refresh embeddings.",
+                "1 faculty entry was excluded because their embeddings are empty or contain invalid values; refresh embeddings.",
+            ]
+        );
+        let allowed = HashSet::from([0, 2]);
+        let scoped = find_excluded_faculty_entries(&index, Some(&allowed));
+        assert_eq!(scoped.entries.len(), 1);
+        assert_eq!(scoped.entries[0].row_index, 2);
+
+        // Excluded rows never appear, and the tied rows 0 and 3 keep dataset order on
+        // every run.
+        for _ in 0..5 {
+            let matches = find_best_faculty_matches(&index, &[1.0, 0.0], 10, None);
+            let rows: Vec<usize> = matches.iter().map(|result| result.row_index).collect();
+            assert_eq!(rows, [0, 3, 4]);
+            assert!(matches.iter().all(|result| result.similarity.is_finite()));
         }
     }
 
-    Ok((row_count, headers.len()))
-}
+    #[test]
+    fn matching_run_reports_excluded_faculty_once() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[
+                &["Robert Smith"],
+                &["Short One"],
+                &["Short Two"],
+                &["Zero Vector"],
+            ],
+        );
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Short One", &[1.0]),
+            embedding_entry(2, "Short Two", &[0.5]),
+            embedding_entry(3, "Zero Vector", &[0.0, 0.0]),
+        ]));
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "prompt",
+            "promptText": "Tumor immunology",
+            "facultyRecsPerStudent": 1,
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "exclusions").unwrap();
+
+        let exclusion_warnings: Vec<&String> = response
+            .warnings
+            .iter()
+            .filter(|warning| warning.contains("refresh embeddings"))
+            .collect();
+        assert_eq!(
+            exclusion_warnings,
+            [
+                "2 faculty entries were excluded due to mismatched embedding dimensions; refresh embeddings.",
+                "1 faculty entry was excluded because their embeddings are empty or contain invalid values; refresh embeddings.",
+            ]
+        );
+        let diagnostics = response.faculty_exclusions.unwrap();
+        let rows: Vec<usize> = diagnostics
+            .entries
+            .iter()
+            .map(|entry| entry.row_index)
+            .collect();
+        assert_eq!(rows, [1, 2, 3]);
+        let matched = &response.prompt_matches[0].faculty_matches;
+        assert_eq!(matched[0].row_index, 0);
+    }
 
-fn compute_excel_dimensions(path: &Path) -> Result<(usize, usize), String> {
-    let mut workbook =
-        open_workbook_auto(path).map_err(|err| format!("Unable to open the dataset: {err}"))?;
+    #[test]
+    fn delimiters_are_chosen_by_field_count_consistency() {
+        // Each sample fooled the old most-frequent-character count.
+        let cases: [(&str, u8, &str, f32); 5] = [
+            (
+                "Name\tTitle, Department, and Program\tEmail\nAna\tBiology\ta@x.edu\nBo\tChemistry\tb@x.edu\n",
+                b'\t',
+                "tab",
+                1.0,
+            ),
+            (
+                "Name;Notes\n\"Smith, R\";\"a, b, c\"\n\"Garcia, A\";\"d, e\"\n",
+                b',',
+                "semicolon",
+                1.0,
+            ),
+            (
+                "\n\nName,Research\nAna,\"Cells; membranes; transport\"\nBo,Genomics\n",
+                b'\t',
+                "comma",
+                1.0,
+            ),
+            (
+                "Name,Research\nAna,\"Cells\nand membranes\"\nBo,Genomics\nCy\n",
+                b'\t',
+                "comma",
+                2.0 / 3.0,
+            ),
+            ("Name\nAna\nBo\n", b',', "comma", 1.0),
+        ];
+        for (sample, preferred, expected, consistency) in cases {
+            let detection = detect_delimiter_from_reader(sample.as_bytes(), preferred).unwrap();
+            assert_eq!(detection.name(), expected, "{sample:?}");
+            assert!(
+                (detection.consistency - consistency).abs() < 1e-6,
+                "{sample:?}: {}",
+                detection.consistency
+            );
+        }
+    }
 
-    let sheet_name = workbook
-        .sheet_names()
-        .get(0)
-        .cloned()
-        .ok_or_else(|| "The workbook does not contain any worksheets.".to_string())?;
+    #[test]
+    fn delimiter_ties_prefer_the_extension_default() {
+        let directory = TempDir::new().unwrap();
+        let sample = "Name,Id\tEmail\nAna,1\ta@x.edu\n";
+        let tsv = write_file(&directory, "faculty.tsv", sample);
+        let txt = write_file(&directory, "faculty.txt", sample);
+        let csv = write_file(&directory, "faculty.csv", sample);
+
+        assert_eq!(detect_delimiter(&tsv).unwrap().name(), "tab");
+        assert_eq!(detect_delimiter(&txt).unwrap().name(), "tab");
+        assert_eq!(detect_delimiter(&csv).unwrap().name(), "comma");
+        let single_column = write_file(&directory, "names.txt", "Name\nAna\n");
+        assert_eq!(detect_delimiter(&single_column).unwrap().name(), "tab");
+    }
 
-    let range = workbook
-        .worksheet_range(&sheet_name)
-        .ok_or_else(|| format!("Unable to read the worksheet named '{sheet_name}'."))?
-        .map_err(|err| format!("Unable to read the worksheet data: {err}"))?;
+    #[test]
+    fn dataset_previews_and_dimensions_use_the_detected_delimiter() {
+        let directory = TempDir::new().unwrap();
+        let path = write_file(
+            &directory,
+            "faculty.tsv",
+            "Name\tTitle, Department, and Program\tResearch\nAna\tBiology\tCells\nBo\tChemistry\tCatalysis\n",
+        );
 
-    let mut rows_iter = range.rows();
-    let header_row = rows_iter
-        .next()
-        .ok_or_else(|| "The worksheet is empty.".to_string())?;
+        let preview = build_dataset_preview(&path).unwrap();
+        assert_eq!(
+            preview.headers,
+            ["Name", "Title, Department, and Program", "Research"]
+        );
+        assert_eq!(preview.rows[1], ["Bo", "Chemistry", "Catalysis"]);
+        assert_eq!(preview.delimiter.as_deref(), Some("tab"));
+        assert_eq!(preview.delimiter_consistency, Some(1.0));
+        let serialized = serde_json::to_value(&preview).unwrap();
+        assert_eq!(serialized["delimiter"], "tab");
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(compute_delimited_dimensions(&bytes, b',').unwrap(), (2, 3));
+        let semicolons = "Name;Notes\n\"Smith, R\";\"a, b, c\"\n";
+        assert_eq!(
+            compute_delimited_dimensions(semicolons.as_bytes(), b'\t').unwrap(),
+            (1, 2)
+        );
 
-    let column_count = header_row.len();
-    let mut row_count = 0usize;
+        let metadata = dataset_metadata(&["Name"], &[&["Ana Garcia"], &["Bo Chen"]]);
+        let roster = match_roster_file(
+            "roster.tsv",
+            "Name\tTitle, Department, and Program\nBo Chen\tProfessor, Chemistry, DBBS\n",
+            &metadata,
+            &[("Name", "Name")],
+            false,
+        );
+        assert_eq!(roster.matched_rows, HashSet::from([1]));
 
-    for row in rows_iter {
-        if row.iter().any(|cell| !cell_to_string(cell).is_empty()) {
-            row_count += 1;
-        }
+        let workbook = write_file(&directory, "faculty.xlsx", "Name,Research\n");
+        assert!(spreadsheet_delimiter_detection(&workbook).is_none());
     }
 
-    Ok((row_count, column_count))
-}
-
-fn build_dataset_preview(path: &Path) -> Result<SpreadsheetPreview, String> {
-    let (mut headers, mut rows) = read_spreadsheet(path)?;
-    align_row_lengths(&mut headers, &mut rows);
-    let (prompt_columns, identifier_columns) = suggest_spreadsheet_columns(&headers, &rows);
+    #[test]
+    fn unnamed_trailing_column_from_a_wide_row_resolves_from_the_preview() {
+        // Eight headers, the third blank; only the second data row has a ninth field.
+        let tsv = "Name\tEmail\t\tA\tB\tC\tD\tE\n\
+                   Ana\ta@x.edu\tx\t1\t2\t3\t4\t5\n\
+                   Bo\tb@x.edu\ty\t1\t2\t3\t4\t5\tMembrane transport\n";
+        let directory = TempDir::new().unwrap();
+        let path = write_file(&directory, "prompts.tsv", tsv);
+
+        let preview = build_dataset_preview(&path).unwrap();
+        assert_eq!(preview.headers.len(), 9);
+        assert_eq!(preview.headers[2], "Column 3");
+        assert_eq!(preview.headers[8], "Column 9");
+        let selected = strings(&[&preview.headers[8], &preview.headers[2]]);
+
+        let (headers, _) = read_full_spreadsheet(&path).unwrap();
+        let header_map = build_header_index_map(&headers);
+        assert_eq!(indexes_from_labels(&header_map, &selected).unwrap(), [2, 8]);
+        assert_eq!(
+            indexes_from_spreadsheet_labels(&header_map, &strings(&["column 9 "])).unwrap(),
+            [8]
+        );
 
-    Ok(SpreadsheetPreview {
-        headers,
-        rows,
-        suggested_prompt_columns: prompt_columns,
-        suggested_identifier_columns: identifier_columns,
-    })
-}
+        let host = MockHost::new(&[1.0, 0.0]).with_embedding("Membrane transport", &[0.0, 1.0]);
+        let index = embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.0, 1.0]),
+        ]);
+        let info = run_info();
+        let options = run_options(&info);
+        for source in [
+            SpreadsheetSource::Path(path.clone()),
+            SpreadsheetSource::Inline {
+                filename: "prompts.tsv".into(),
+                data: tsv.as_bytes().to_vec(),
+            },
+        ] {
+            let outcome = process_prompt_spreadsheet(
+                &host,
+                &source,
+                &index,
+                &strings(&["Column 9"]),
+                &strings(&["Name"]),
+                &options,
+            )
+            .unwrap();
+
+            let skipped = &outcome.results.skipped_entries;
+            assert_eq!(skipped.len(), 1);
+            assert_eq!(skipped[0].identifier_values, ["Ana"]);
+            let bytes = Base64Engine
+                .decode(&outcome.results.spreadsheet.content)
+                .unwrap();
+            let matches = read_sheet(&bytes, "Matches");
+            assert!(matches[1].iter().any(|value| value == "Bo"), "{matches:?}");
+            assert!(
+                matches[1].iter().any(|value| value == "Ana Garcia"),
+                "{matches:?}"
+            );
+        }
+    }
 
-fn format_system_time(time: SystemTime) -> String {
-    let datetime: DateTime<Utc> = time.into();
-    datetime.to_rfc3339()
-}
+    #[test]
+    fn model_override_without_a_saved_index_names_the_available_ones() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"], &["Ana Garcia"]]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        let active = embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.0, 1.0]),
+        ]);
+        host.write_embedding_index(&active);
+        let mut saved = active.clone();
+        saved.model = "saved/model-b".into();
+        store_model_embedding_index(
+            &host,
+            &saved.model,
+            &serialize_faculty_embedding_index(&saved).unwrap(),
+        )
+        .unwrap();
+        let payload = |model: &str| -> SubmissionPayload {
+            serde_json::from_value(serde_json::json!({
+                "taskType": "prompt",
+                "promptText": "Tumor immunology",
+                "facultyRecsPerStudent": 1,
+                "modelOverride": model,
+            }))
+            .unwrap()
+        };
 
-fn suggest_spreadsheet_columns(
-    headers: &[String],
-    rows: &[Vec<String>],
-) -> (Vec<usize>, Vec<usize>) {
-    const PROMPT_KEYWORDS: &[&str] = &[
-        "prompt",
-        "interest",
-        "research",
-        "description",
-        "summary",
-        "essay",
-        "statement",
-        "focus",
-        "topic",
-        "goal",
-    ];
-    const IDENTIFIER_KEYWORDS: &[&str] = &[
-        "id",
-        "identifier",
-        "name",
-        "first",
-        "last",
-        "student",
-        "email",
-        "netid",
-        "number",
-        "uid",
-    ];
+        let error = perform_matching_request(&host, payload(" missing/model "), "override")
+            .err()
+            .unwrap();
+        let AppError::MissingEmbeddings { message } = error else {
+            panic!("expected a missing embeddings error, got {error:?}");
+        };
+        assert_eq!(
+            message,
+            format!(
+                "No faculty embedding index is available for missing/model. Available indexes: {DEFAULT_EMBEDDING_MODEL}, saved/model-b."
+            )
+        );
+        assert_eq!(host.helper_calls.load(AtomicOrdering::SeqCst), 0);
 
-    let mut prompt_columns = Vec::new();
-    let mut identifier_columns = Vec::new();
+        let response = perform_matching_request(&host, payload(&saved.model), "override").unwrap();
+        assert_eq!(
+            response.details.embedding_model.as_deref(),
+            Some("saved/model-b")
+        );
+        assert!(response.details.model_overridden);
 
-    for (index, header) in headers.iter().enumerate() {
-        let header_lower = header.to_lowercase();
-        if header_lower.is_empty() {
-            continue;
-        }
+        let response =
+            perform_matching_request(&host, payload(DEFAULT_EMBEDDING_MODEL), "override").unwrap();
+        assert_eq!(
+            response.details.embedding_model.as_deref(),
+            Some(DEFAULT_EMBEDDING_MODEL)
+        );
+    }
 
-        if PROMPT_KEYWORDS
+    #[test]
+    fn run_info_sheet_records_the_overriding_model() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"]]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        let mut index = embedding_index(vec![embedding_entry(0, "Robert Smith", &[1.0, 0.0])]);
+        host.write_embedding_index(&index);
+        index.model = "saved/model-b".into();
+        store_model_embedding_index(
+            &host,
+            &index.model,
+            &serialize_faculty_embedding_index(&index).unwrap(),
+        )
+        .unwrap();
+        let spreadsheet = write_file(&host.data_dir, "prompts.tsv", "Prompt\nCell biology\n");
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "spreadsheet",
+            "spreadsheetPath": spreadsheet,
+            "spreadsheetPromptColumns": ["Prompt"],
+            "facultyRecsPerStudent": 1,
+            "modelOverride": "saved/model-b",
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "override-sheet").unwrap();
+
+        let results = response.spreadsheet_results.unwrap();
+        let bytes = Base64Engine.decode(&results.spreadsheet.content).unwrap();
+        let run_info = read_sheet(&bytes, "Run Info");
+        let model_row = run_info
             .iter()
-            .any(|keyword| header_lower.contains(keyword))
-        {
-            prompt_columns.push(index);
-        }
+            .find(|row| row.first().is_some_and(|label| label == "Embedding model"))
+            .unwrap();
+        assert_eq!(model_row[1], "saved/model-b");
+    }
 
-        if IDENTIFIER_KEYWORDS
-            .iter()
-            .any(|keyword| header_lower.contains(keyword))
-        {
-            identifier_columns.push(index);
+    /// Copies a generated workbook's cell values into a new workbook after letting
+    /// `edit` change the Matches sheet, the way a coordinator saves their marks.
+    fn mark_reviewed_workbook(
+        workbook: &[u8],
+        edit: impl FnOnce(&mut Vec<Vec<String>>),
+    ) -> Vec<u8> {
+        let mut edit = Some(edit);
+        let mut marked = Workbook::new();
+        for name in sheet_names(workbook) {
+            let mut rows = read_sheet(workbook, &name);
+            if name == "Matches" {
+                (edit.take().unwrap())(&mut rows);
+            }
+            let sheet = marked.add_worksheet();
+            sheet.set_name(&name).unwrap();
+            for (row, values) in rows.iter().enumerate() {
+                for (col, value) in values.iter().enumerate() {
+                    if !value.is_empty() {
+                        sheet.write_string(row as u32, col as u16, value).unwrap();
+                    }
+                }
+            }
         }
+        marked.save_to_buffer().unwrap()
     }
 
-    sort_and_dedup(&mut prompt_columns);
-    sort_and_dedup(&mut identifier_columns);
+    #[test]
+    fn reviewed_workbook_round_trips_marks_into_final_assignments() {
+        let info = run_info();
+        let options = run_options(&info);
+        let generated = sample_workbook(&sample_match_entries(), &options, &mut Vec::new());
+        let reviewed = mark_reviewed_workbook(&generated, |rows| {
+            rows[0][0] = "First Reviewer(s)".into();
+            rows[0].push("Coordinator notes".into());
+            for (row, first, reviewer) in
+                [(1, "1", ""), (2, "", "1"), (3, "", "TRUE"), (4, "1", "x")]
+            {
+                rows[row][0] = first.into();
+                rows[row][1] = reviewer.into();
+            }
+            rows[2].push("Confirmed by email".into());
+        });
 
-    let stats = compute_column_stats(headers, rows);
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(reviewed.clone())).unwrap();
+        let report = read_reviewed_workbook(&mut workbook, 2, Some(1)).unwrap();
 
-    if prompt_columns.is_empty() {
-        let mut candidates: Vec<&ColumnStats> = stats
+        assert_eq!(report.sheets, ["Matches"]);
+        assert_eq!(report.rows_read, 4);
+        assert_eq!(report.student_headers, ["Student"]);
+        let students: Vec<(&str, Option<&str>, &[String])> = report
+            .students
             .iter()
-            .filter(|stat| stat.non_empty > 0 && stat.numeric_ratio < 0.6)
+            .map(|student| {
+                (
+                    student.label.as_str(),
+                    student.first_reviewer.as_deref(),
+                    student.reviewers.as_slice(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            students,
+            [
+                (
+                    "Student A",
+                    Some("Robert Smith"),
+                    &strings(&["Robert Smith", "Ana Garcia"])[..]
+                ),
+                (
+                    "Student B",
+                    Some("Helen Jones"),
+                    &strings(&["Helen Jones", "Robert Smith"])[..]
+                ),
+            ]
+        );
+        assert_eq!(report.students_complete, 2);
+        let loads: Vec<(&str, usize, usize, bool)> = report
+            .faculty_loads
+            .iter()
+            .map(|load| {
+                (
+                    load.faculty.as_str(),
+                    load.first_reviewer_count,
+                    load.reviewer_count,
+                    load.over_cap,
+                )
+            })
             .collect();
+        assert_eq!(
+            loads,
+            [
+                ("Robert Smith", 1, 2, true),
+                ("Ana Garcia", 0, 1, false),
+                ("Helen Jones", 1, 1, false),
+            ]
+        );
+        assert_eq!(
+            report.issues,
+            ["Robert Smith reviews 2 students, above the cap of 1."]
+        );
+        assert_eq!(
+            report.warnings,
+            [
+                "Treating the 'First Reviewer(s)' column on 'Matches' as 'First reviewer'.",
+                "Ignoring the 'Coordinator notes' column on 'Matches', which is not part of the generated layout.",
+                "Row 5 on 'Matches' has 'x' in the Reviewer column; only 1 or TRUE count as an assignment.",
+            ]
+        );
 
-        candidates.sort_by(|a, b| {
-            b.average_length
-                .partial_cmp(&a.average_length)
-                .unwrap_or(Ordering::Equal)
-                .then(b.max_length.cmp(&a.max_length))
-        });
+        let assignments = Base64Engine.decode(&report.assignments.content).unwrap();
+        assert_eq!(
+            sheet_names(&assignments),
+            ["Assignments", "Faculty Load", "Issues"]
+        );
+        assert_eq!(
+            read_sheet(&assignments, "Assignments"),
+            string_rows(&[
+                &["Student", "Reviewer 1", "Reviewer 2"],
+                &["Student A", "Robert Smith", "Ana Garcia"],
+                &["Student B", "Helen Jones", "Robert Smith"],
+            ])
+        );
+        assert_eq!(
+            read_sheet(&assignments, "Faculty Load")[1],
+            ["Robert Smith", "1", "2"]
+        );
 
-        let mut fallback = Vec::new();
-        for stat in &candidates {
-            if stat.average_length >= 18.0 || stat.max_length >= 60 {
-                fallback.push(stat.index);
-            }
-        }
+        // Asking for three reviewers without a cap flags both students instead.
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(reviewed)).unwrap();
+        let report = read_reviewed_workbook(&mut workbook, 3, None).unwrap();
+        assert_eq!(report.students_complete, 0);
+        assert_eq!(
+            report.issues,
+            [
+                "Student A has 2 of the 3 required reviewers.",
+                "Student B has 2 of the 3 required reviewers.",
+            ]
+        );
+        assert!(report.faculty_loads.iter().all(|load| !load.over_cap));
+    }
 
-        if fallback.is_empty() {
-            if let Some(best) = candidates.first() {
-                fallback.push(best.index);
+    #[test]
+    fn reviewed_workbook_reports_missing_and_repeated_first_reviewers() {
+        let info = run_info();
+        let options = run_options(&info);
+        let generated = sample_workbook(&sample_match_entries(), &options, &mut Vec::new());
+        let reviewed = mark_reviewed_workbook(&generated, |rows| {
+            for row in [1, 2] {
+                rows[row][0] = "1".into();
             }
-        }
+            rows[3][1] = "1".into();
+        });
 
-        prompt_columns = fallback;
-    }
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("spring matches.xlsx");
+        fs::write(&path, reviewed).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        assert!(matches!(
+            import_reviewed_workbook(path.clone(), Some(0), None),
+            Err(AppError::InvalidInput { .. })
+        ));
+        let report = import_reviewed_workbook(path, None, None).unwrap();
 
-    if identifier_columns.is_empty() {
-        let mut candidates: Vec<&ColumnStats> =
-            stats.iter().filter(|stat| stat.non_empty > 0).collect();
+        assert_eq!(
+            report.assignments.filename,
+            "spring matches_final_assignments.xlsx"
+        );
+        assert_eq!(report.required_reviewers, DEFAULT_REQUIRED_REVIEWERS);
+        assert_eq!(report.students_complete, 0);
+        assert_eq!(
+            report.issues,
+            [
+                "Student A has 2 first reviewers; only one should be marked.",
+                "Student B has 1 of the 2 required reviewers.",
+                "Student B has no first reviewer.",
+            ]
+        );
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
 
-        candidates.sort_by(|a, b| {
-            a.average_length
-                .partial_cmp(&b.average_length)
-                .unwrap_or(Ordering::Equal)
-                .then(b.non_empty.cmp(&a.non_empty))
+        let unrelated = mark_reviewed_workbook(&generated, |rows| {
+            rows[0][1] = "Checked".into();
         });
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(unrelated)).unwrap();
+        let error = read_reviewed_workbook(&mut workbook, 2, None).err();
+        assert_eq!(
+            error.as_deref(),
+            Some("The 'Matches' worksheet does not have a 'Reviewer' column.")
+        );
+    }
 
-        let mut fallback = Vec::new();
-        for stat in &candidates {
-            if stat.average_length <= 36.0 || stat.numeric_ratio >= 0.5 {
-                fallback.push(stat.index);
-            }
-            if fallback.len() >= 3 {
-                break;
-            }
+    fn program_metadata() -> FacultyDatasetMetadata {
+        let mut metadata = dataset_metadata(
+            &["Name"],
+            &[&["Robert Smith"], &["Ana Garcia"], &["Helen Jones"]],
+        );
+        let programs = [
+            &["Molecular Cell Biology"][..],
+            &["Biochemistry, Biophysics & Structural Biology"],
+            &["Neurosciences", "Molecular Cell Biology"],
+        ];
+        for (membership, programs) in metadata.memberships.iter_mut().zip(programs) {
+            membership.programs = strings(programs);
         }
+        metadata.analysis.available_programs = strings(&[
+            "Biochemistry, Biophysics & Structural Biology",
+            "Molecular Cell Biology",
+            "Neurosciences",
+        ]);
+        metadata
+    }
 
-        if fallback.is_empty() {
-            if let Some(best) = candidates.first() {
-                fallback.push(best.index);
-            }
+    #[test]
+    fn program_filters_ignore_spacing_punctuation_and_ampersands() {
+        let metadata = program_metadata();
+        let cases: [(&[&str], &[usize], &[&str], &[&str]); 5] = [
+            (
+                &["molecular  cell biology "],
+                &[0, 2],
+                &["Molecular Cell Biology"],
+                &[],
+            ),
+            (
+                &["Biochemistry Biophysics and Structural Biology"],
+                &[1],
+                &["Biochemistry, Biophysics & Structural Biology"],
+                &[],
+            ),
+            (
+                &["Molecular Cell Bio"],
+                &[],
+                &["Molecular Cell Bio"],
+                &["No faculty matched 'Molecular Cell Bio'; did you mean 'Molecular Cell Biology'?"],
+            ),
+            (
+                &["Astrophysics", "Neurosciences"],
+                &[2],
+                &["Astrophysics", "Neurosciences"],
+                &["No faculty matched 'Astrophysics', and no similar program names were found."],
+            ),
+            (
+                &["Neurosciences", " neurosciences"],
+                &[2],
+                &["Neurosciences"],
+                &[],
+            ),
+        ];
+        for (filters, rows, programs, warnings) in cases {
+            let outcome = filter_faculty_rows_by_program(&metadata, &strings(filters));
+            assert_eq!(outcome.rows, rows.iter().copied().collect(), "{filters:?}");
+            assert_eq!(outcome.programs, programs, "{filters:?}");
+            assert_eq!(outcome.warnings, warnings, "{filters:?}");
         }
+    }
+
+    #[test]
+    fn program_scoped_run_echoes_canonical_program_names() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&program_metadata()).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.6, 0.8]),
+            embedding_entry(2, "Helen Jones", &[0.0, 1.0]),
+        ]));
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "prompt",
+            "promptText": "Tumor immunology",
+            "facultyScope": "program",
+            "programFilters": ["biochemistry, biophysics and structural biology ", "Neuroscience"],
+            "facultyRecsPerStudent": 1,
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "programs").unwrap();
+
+        assert_eq!(
+            response.details.program_filters,
+            [
+                "Biochemistry, Biophysics & Structural Biology",
+                "Neuroscience"
+            ]
+        );
+        assert!(
+            response.warnings.iter().any(|warning| warning
+                == "No faculty matched 'Neuroscience'; did you mean 'Neurosciences'?"),
+            "{:?}",
+            response.warnings
+        );
+        assert_eq!(response.prompt_matches[0].faculty_matches[0].row_index, 1);
+    }
+
+    #[test]
+    fn blank_identifier_dataset_rows_are_counted_and_labelled_by_row() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let dataset = write_file(
+            &host.data_dir,
+            "faculty.tsv",
+            "Name\tEmail\tResearch\n\
+             Robert Smith\tr@x.edu\tImmunology\n\
+             \t \tProtein folding\n\
+             Ana Garcia\t\tGenomics\n",
+        );
+        let analysis = analyze_faculty_dataset(
+            &host,
+            &dataset,
+            Some(&FacultyDatasetColumnConfiguration {
+                embedding_columns: vec![2],
+                identifier_columns: vec![0, 1],
+                program_columns: Vec::new(),
+            }),
+        )
+        .unwrap();
+        assert_eq!(analysis.blank_identifier_rows, [3]);
+        assert_eq!(
+            describe_blank_identifier_rows(&analysis.blank_identifier_rows).as_deref(),
+            Some(
+                "1 faculty row has research text but no identifier values (row 3). \
+                 Matches will show them as \"Dataset row N\" until the identifiers are filled in."
+            )
+        );
+        assert_eq!(
+            describe_blank_identifier_rows(&[2, 3, 4, 5, 6, 8, 9]).as_deref(),
+            Some(
+                "7 faculty rows have research text but no identifier values (rows 2, 3, 4, 5, 6 \
+                 and 2 more). Matches will show them as \"Dataset row N\" until the identifiers \
+                 are filled in."
+            )
+        );
+
+        let blank = |row_index| {
+            let mut entry = embedding_entry(row_index, "", &[1.0, 0.0]);
+            entry.identifiers.insert("Email".into(), " ".into());
+            entry
+        };
+        let mut robert = embedding_entry(0, "Robert Smith", &[0.0, 1.0]);
+        robert.identifiers.insert("Email".into(), "r@x.edu".into());
+        let mut index = embedding_index(vec![robert, blank(1)]);
+        index.identifier_columns = strings(&["Name", "Email"]);
+
+        let matches = find_best_faculty_matches(&index, &[1.0, 0.0], 2, None);
+        assert_eq!(
+            matches[0].identifiers,
+            HashMap::from([(
+                FALLBACK_FACULTY_IDENTIFIER_KEY.to_string(),
+                "Dataset row 3".to_string()
+            )])
+        );
+        assert_eq!(matches[1].identifiers["Name"], "Robert Smith");
+
+        let info = run_info();
+        let options = run_options(&info);
+        let outcome = process_prompt_spreadsheet(
+            &host,
+            &SpreadsheetSource::Inline {
+                filename: "prompts.tsv".into(),
+                data: b"Student\tPrompt\nAlex\tFolding\nSam\tChaperones\n".to_vec(),
+            },
+            &index,
+            &strings(&["Prompt"]),
+            &strings(&["Student"]),
+            &options,
+        )
+        .unwrap();
+
+        let bytes = Base64Engine
+            .decode(&outcome.results.spreadsheet.content)
+            .unwrap();
+        let sheet = read_sheet(&bytes, "Matches");
+        let column = |name: &str| sheet[0].iter().position(|header| header == name).unwrap();
+        let (name, email) = (column("Name"), column("Email"));
+        let top_rows: Vec<(&str, &str)> = sheet[1..]
+            .iter()
+            .filter(|row| row[column("Faculty rank")] == "1")
+            .map(|row| (row[name].as_str(), row[email].as_str()))
+            .collect();
+        assert_eq!(
+            top_rows,
+            [("Dataset row 3", ""), ("Dataset row 3", "")],
+            "{sheet:?}"
+        );
 
-        identifier_columns = fallback;
+        let summary = read_sheet(&bytes, "Faculty Summary");
+        let labelled: Vec<&Vec<String>> = summary[1..]
+            .iter()
+            .filter(|row| row[0] == "Dataset row 3")
+            .collect();
+        assert_eq!(labelled.len(), 1, "{summary:?}");
     }
 
-    if prompt_columns.is_empty() && !headers.is_empty() {
-        prompt_columns.push(headers.len() - 1);
-    }
+    #[test]
+    fn run_statistics_summarize_each_students_best_match() {
+        let prompt = |faculty_matches| PromptMatchResult {
+            prompt: String::new(),
+            faculty_matches,
+        };
+        let prompt_matches = vec![
+            prompt(vec![
+                faculty_match(0, "Robert Smith", 0.95),
+                faculty_match(1, "Ana Garcia", 0.4),
+            ]),
+            prompt(vec![
+                faculty_match(1, "Ana Garcia", 0.35),
+                faculty_match(2, "Helen Jones", f32::NAN),
+            ]),
+            prompt(Vec::new()),
+            prompt(vec![
+                faculty_match(2, "Helen Jones", 0.55),
+                faculty_match(0, "Robert Smith", 0.62),
+            ]),
+        ];
 
-    if identifier_columns.is_empty() && !headers.is_empty() {
-        identifier_columns.push(0);
+        let statistics = build_run_statistics(&prompt_matches, Some(4));
+
+        assert_eq!(statistics.students_matched, 3);
+        let summary = statistics.best_similarity.unwrap();
+        for (actual, expected) in [
+            (summary.mean, (0.95 + 0.35 + 0.62) / 3.0),
+            (summary.median, 0.62),
+            (summary.min, 0.35),
+            (summary.max, 0.95),
+        ] {
+            assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+        }
+        let histogram: Vec<(u32, u32, usize)> = statistics
+            .best_similarity_histogram
+            .iter()
+            .map(|bucket| (bucket.lower_percent, bucket.upper_percent, bucket.count))
+            .collect();
+        assert_eq!(histogram.len(), SIMILARITY_HISTOGRAM_BUCKETS);
+        assert_eq!(histogram[0], (0, 10, 0));
+        assert_eq!(histogram[9], (90, 100, 1));
+        let filled: Vec<u32> = histogram
+            .iter()
+            .filter(|bucket| bucket.2 > 0)
+            .map(|bucket| bucket.0)
+            .collect();
+        assert_eq!(filled, [30, 60, 90]);
+        assert_eq!(statistics.distinct_faculty, 3);
+        assert_eq!(statistics.scope_coverage, Some(0.75));
+
+        assert_eq!(
+            build_run_statistics_rows(&statistics),
+            [
+                ("Students matched", "3".to_string()),
+                ("Best similarity (mean)", "64.0%".to_string()),
+                ("Best similarity (median)", "62.0%".to_string()),
+                ("Best similarity (min)", "35.0%".to_string()),
+                ("Best similarity (max)", "95.0%".to_string()),
+                (
+                    "Best similarity histogram",
+                    "0-10%: 0; 10-20%: 0; 20-30%: 0; 30-40%: 1; 40-50%: 0; 50-60%: 0; \
+                     60-70%: 1; 70-80%: 0; 80-90%: 0; 90-100%: 1"
+                        .to_string()
+                ),
+                ("Distinct faculty recommended", "3".to_string()),
+                ("Faculty scope coverage", "75.0% (3 of 4)".to_string()),
+            ]
+        );
     }
 
-    sort_and_dedup(&mut prompt_columns);
-    sort_and_dedup(&mut identifier_columns);
+    #[test]
+    fn run_statistics_handle_even_counts_edges_and_empty_runs() {
+        let prompt = |faculty_matches| PromptMatchResult {
+            prompt: String::new(),
+            faculty_matches,
+        };
+        let prompt_matches = vec![
+            prompt(vec![faculty_match(0, "Robert Smith", 1.0)]),
+            prompt(vec![faculty_match(0, "Robert Smith", -0.2)]),
+        ];
 
-    (prompt_columns, identifier_columns)
-}
+        let statistics = build_run_statistics(&prompt_matches, Some(1));
+        let summary = statistics.best_similarity.unwrap();
+        assert!((summary.median - 0.4).abs() < 1e-6);
+        let counts: Vec<usize> = statistics
+            .best_similarity_histogram
+            .iter()
+            .map(|bucket| bucket.count)
+            .collect();
+        assert_eq!(counts, [1, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(statistics.distinct_faculty, 1);
+        assert_eq!(statistics.scope_coverage, Some(1.0));
+
+        let empty = build_run_statistics(&[prompt(Vec::new())], Some(0));
+        assert_eq!(empty.students_matched, 0);
+        assert!(empty.best_similarity.is_none());
+        assert!(empty
+            .best_similarity_histogram
+            .iter()
+            .all(|bucket| bucket.count == 0));
+        assert_eq!(empty.scope_coverage, None);
+        assert_eq!(
+            build_run_statistics_rows(&empty),
+            [
+                ("Students matched", "0".to_string()),
+                ("Distinct faculty recommended", "0".to_string()),
+            ]
+        );
+        let serialized = serde_json::to_value(&empty).unwrap();
+        assert!(serialized.get("bestSimilarity").is_none());
+        assert!(serialized.get("scopeCoverage").is_none());
+    }
 
-fn sort_and_dedup(values: &mut Vec<usize>) {
-    values.sort_unstable();
-    values.dedup();
-}
+    #[test]
+    fn run_info_sheet_lists_the_run_statistics() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"], &["Ana Garcia"]]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.0, 1.0]),
+        ]));
+        let spreadsheet = write_file(&host.data_dir, "prompts.tsv", "Prompt\nOne\nTwo\n");
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "spreadsheet",
+            "spreadsheetPath": spreadsheet,
+            "spreadsheetPromptColumns": ["Prompt"],
+            "facultyRecsPerStudent": 1,
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "statistics").unwrap();
+
+        assert_eq!(response.statistics.students_matched, 2);
+        assert_eq!(response.statistics.distinct_faculty, 1);
+        assert_eq!(response.statistics.scope_coverage, Some(0.5));
+        let results = response.spreadsheet_results.unwrap();
+        let bytes = Base64Engine.decode(&results.spreadsheet.content).unwrap();
+        let run_info: HashMap<String, String> = read_sheet(&bytes, "Run Info")
+            .into_iter()
+            .filter(|row| row.len() > 1)
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect();
+        assert_eq!(run_info["Students matched"], "2");
+        assert_eq!(run_info["Best similarity (median)"], "100.0%");
+        assert_eq!(run_info["Faculty scope coverage"], "50.0% (1 of 2)");
+    }
 
-struct ColumnStats {
-    index: usize,
-    non_empty: usize,
-    average_length: f64,
-    max_length: usize,
-    numeric_ratio: f64,
-}
+    #[test]
+    fn roster_rows_after_blank_lines_keep_their_source_row_numbers() {
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[&["Robert Smith"], &["Ana Garcia"], &["Helen Jones"]],
+        );
+        let outcome = match_roster_file(
+            "roster.tsv",
+            "Name\nRobert Smith\n\nZed Unknown\n\n\n\"Garcia, Ana\"\nAmy Nobody\n",
+            &metadata,
+            &[("Name", "Name")],
+            true,
+        );
 
-fn compute_column_stats(headers: &[String], rows: &[Vec<String>]) -> Vec<ColumnStats> {
-    let column_count = headers.len();
-    let mut stats = Vec::new();
+        let unmatched: Vec<usize> = outcome
+            .diagnostics
+            .unmatched_rows
+            .iter()
+            .map(|row| row.row_number)
+            .collect();
+        assert_eq!(unmatched, [4, 8]);
+        assert_eq!(
+            outcome.summary.unmatched_samples,
+            ["Row 4: Zed Unknown", "Row 8: Amy Nobody"]
+        );
+        assert!(
+            outcome
+                .warnings
+                .iter()
+                .any(|warning| warning.starts_with("Roster row 7 'Garcia, Ana' was fuzzy-matched")),
+            "{:?}",
+            outcome.warnings
+        );
 
-    for index in 0..column_count {
-        let mut non_empty = 0usize;
-        let mut total_length = 0usize;
-        let mut max_length = 0usize;
-        let mut numeric_like = 0usize;
+        let outcome = match_roster_file(
+            "roster.txt",
+            "Robert Smith\n\nZed Unknown\n",
+            &metadata,
+            &[("Name", "Name")],
+            false,
+        );
+        assert_eq!(outcome.value_identifier.as_deref(), Some("Name"));
+        assert_eq!(outcome.diagnostics.unmatched_rows[0].row_number, 3);
+        assert_eq!(outcome.summary.unmatched_samples, ["Row 3: Zed Unknown"]);
+    }
 
-        for row in rows {
-            if let Some(value) = row.get(index) {
-                let trimmed = value.trim();
-                if trimmed.is_empty() {
-                    continue;
-                }
-                non_empty += 1;
-                let length = trimmed.chars().count();
-                total_length += length;
-                if length > max_length {
-                    max_length = length;
-                }
-                if is_numeric_like(trimmed) {
-                    numeric_like += 1;
-                }
-            }
+    #[test]
+    fn included_roster_with_an_unmatched_program_filter_is_not_widened() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&program_metadata()).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.6, 0.8]),
+            embedding_entry(2, "Helen Jones", &[0.0, 1.0]),
+        ]));
+        let roster_path = write_file(
+            &host.data_dir,
+            "roster.tsv",
+            "Name\nRobert Smith\nAna Garcia\n",
+        );
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "prompt",
+            "promptText": "Tumor immunology",
+            "facultyScope": "custom",
+            "customFacultyPath": roster_path,
+            "customFacultyMode": "include",
+            "programFilters": ["Astrophysics"],
+            "facultyRecsPerStudent": 2,
+            "facultyRosterColumnMap": { "Name": "Name" },
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "include-programs").unwrap();
+
+        assert_eq!(response.details.faculty_in_scope, Some(0));
+        assert!(response.prompt_matches[0].faculty_matches.is_empty());
+        let warnings = &response.warnings;
+        for expected in [
+            "No faculty matched 'Astrophysics', and no similar program names were found.",
+            "No faculty members in the dataset matched the selected programs.",
+            "The roster matched 2 faculty, but none of them are in the selected programs.",
+        ] {
+            assert!(
+                warnings.iter().any(|warning| warning == expected),
+                "{expected}: {warnings:?}"
+            );
         }
+    }
 
-        let average_length = if non_empty > 0 {
-            total_length as f64 / non_empty as f64
-        } else {
-            0.0
+    #[test]
+    fn run_outputs_do_not_replace_existing_files_without_overwrite() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(&["Name"], &[&["Robert Smith"]]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![embedding_entry(
+            0,
+            "Robert Smith",
+            &[1.0, 0.0],
+        )]));
+        let spreadsheet = write_file(&host.data_dir, "prompts.tsv", "Prompt\nCell biology\n");
+        let output_dir = TempDir::new().unwrap();
+        let output = output_dir.path().join("matches.xlsx");
+        let payload = |overwrite: bool| -> SubmissionPayload {
+            serde_json::from_value(serde_json::json!({
+                "taskType": "spreadsheet",
+                "spreadsheetPath": spreadsheet,
+                "spreadsheetPromptColumns": ["Prompt"],
+                "facultyRecsPerStudent": 1,
+                "outputPath": output,
+                "overwriteOutput": overwrite,
+                "includePdfReport": true,
+                "exportBundle": true,
+            }))
+            .unwrap()
         };
-
-        let numeric_ratio = if non_empty > 0 {
-            numeric_like as f64 / non_empty as f64
-        } else {
-            0.0
+        let expect_exists = |existing: &str| {
+            let error = perform_matching_request(&host, payload(false), "outputs").unwrap_err();
+            let AppError::FileExists { path, .. } = &error else {
+                panic!("expected an existing output file, got {error:?}");
+            };
+            assert!(path.ends_with(existing), "{path}");
         };
 
-        stats.push(ColumnStats {
-            index,
-            non_empty,
-            average_length,
-            max_length,
-            numeric_ratio,
-        });
-    }
-
-    stats
-}
+        // Each existing target stops the run before anything is written.
+        for existing in ["matches.xlsx", "matches_report.pdf", "matches_bundle.zip"] {
+            let path = write_file(&output_dir, existing, "previous");
+            expect_exists(existing);
+            fs::remove_file(&path).unwrap();
+            let left: Vec<String> = fs::read_dir(output_dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            assert!(left.is_empty(), "{existing}: {left:?}");
+        }
 
-fn is_numeric_like(value: &str) -> bool {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return false;
+        let previous = write_file(&output_dir, "matches_bundle.zip", "previous");
+        perform_matching_request(&host, payload(true), "outputs").unwrap();
+        let mut written: Vec<String> = fs::read_dir(output_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        written.sort();
+        assert_eq!(
+            written,
+            ["matches.xlsx", "matches_bundle.zip", "matches_report.pdf"]
+        );
+        let bundle = fs::read(&previous).unwrap();
+        assert!(zip::ZipArchive::new(Cursor::new(bundle)).unwrap().len() > 0);
     }
 
-    let has_alpha = trimmed.chars().any(|c| c.is_ascii_alphabetic());
-    !has_alpha
-}
+    #[test]
+    fn generated_files_keep_an_existing_destination_unless_overwriting() {
+        let dir = TempDir::new().unwrap();
+        let path = write_file(&dir, "matches_report.pdf", "old");
+        let mut file = GeneratedSpreadsheet {
+            filename: "report.pdf".into(),
+            mime_type: "application/pdf".into(),
+            content: String::new(),
+            encoding: None,
+            companion_files: Vec::new(),
+            saved_path: None,
+            saved_bytes: None,
+            write_duration_ms: None,
+        };
 
-fn resolve_existing_path(
-    raw_path: Option<String>,
-    expects_directory: bool,
-    label: &str,
-) -> Result<PathBuf, String> {
-    let provided = raw_path.as_deref().map(str::trim).unwrap_or_default();
+        let error = write_generated_file(&mut file, &path, b"new", false).unwrap_err();
+        assert!(error.contains("already exists"), "{error}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert!(file.saved_path.is_none());
 
-    if provided.is_empty() {
-        return Err(format!("{label} path is required."));
+        write_generated_file(&mut file, &path, b"new", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(file.saved_bytes, Some(3));
     }
 
-    let path = expand_home(provided);
-    let metadata =
-        fs::metadata(&path).map_err(|_| format!("{label} was not found: {}", path.display()))?;
-
-    if expects_directory && !metadata.is_dir() {
-        return Err(format!(
-            "{label} is expected to be a directory: {}",
-            path.display()
-        ));
+    #[test]
+    fn clean_roster_run_reports_its_faculty_count_without_warnings() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[&["Robert Smith"], &["Ana Garcia"], &["Helen Jones"]],
+        );
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.6, 0.8]),
+            embedding_entry(2, "Helen Jones", &[0.0, 1.0]),
+        ]));
+        let roster = write_file(
+            &host.data_dir,
+            "roster.tsv",
+            "Name\nRobert Smith\nAna Garcia\n",
+        );
+        let spreadsheet = write_file(&host.data_dir, "prompts.tsv", "Prompt\nOne\n");
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "spreadsheet",
+            "spreadsheetPath": spreadsheet,
+            "spreadsheetPromptColumns": ["Prompt"],
+            "facultyScope": "custom",
+            "customFacultyPath": roster,
+            "facultyRosterColumnMap": { "Name": "Name" },
+            "facultyRecsPerStudent": 2,
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "clean-roster").unwrap();
+
+        assert!(response.warnings.is_empty(), "{:?}", response.warnings);
+        assert_eq!(response.details.roster_faculty_count, Some(2));
+        let results = response.spreadsheet_results.unwrap();
+        let bytes = Base64Engine.decode(&results.spreadsheet.content).unwrap();
+        assert!(!sheet_names(&bytes).contains(&"Warnings".to_string()));
+        let run_info: HashMap<String, String> = read_sheet(&bytes, "Run Info")
+            .into_iter()
+            .filter(|row| row.len() > 1)
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect();
+        assert_eq!(run_info["Roster faculty"], "2");
     }
 
-    if !expects_directory && !metadata.is_file() {
-        return Err(format!(
-            "{label} is expected to be a file: {}",
-            path.display()
-        ));
+    #[test]
+    fn two_name_roster_restricts_matches_to_those_faculty() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata = dataset_metadata(
+            &["Name"],
+            &[
+                &["Robert Smith"],
+                &["Ana Garcia"],
+                &["Helen Jones"],
+                &["Wei Chen"],
+            ],
+        );
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Robert Smith", &[1.0, 0.0]),
+            embedding_entry(1, "Ana Garcia", &[0.6, 0.8]),
+            embedding_entry(2, "Helen Jones", &[0.0, 1.0]),
+            embedding_entry(3, "Wei Chen", &[0.9, 0.1]),
+        ]));
+        let roster = write_file(
+            &host.data_dir,
+            "roster.tsv",
+            "Name\nHelen Jones\nAna Garcia\n",
+        );
+        let spreadsheet = write_file(&host.data_dir, "prompts.tsv", "Prompt\nOne\n");
+        let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+            "taskType": "spreadsheet",
+            "spreadsheetPath": spreadsheet,
+            "spreadsheetPromptColumns": ["Prompt"],
+            "facultyScope": "custom",
+            "customFacultyPath": roster,
+            "facultyRosterColumnMap": { "Name": "Name" },
+            "facultyRecsPerStudent": 4,
+        }))
+        .unwrap();
+
+        let response = perform_matching_request(&host, payload, "two-name-roster").unwrap();
+
+        assert_eq!(response.details.roster_faculty_count, Some(2));
+        assert!(
+            response
+                .summary
+                .contains("the 2 faculty on the provided roster spreadsheet"),
+            "{}",
+            response.summary
+        );
+        let results = response.spreadsheet_results.unwrap();
+        let bytes = Base64Engine.decode(&results.spreadsheet.content).unwrap();
+        let matches = read_sheet(&bytes, "Matches");
+        let name = matches[0]
+            .iter()
+            .position(|header| header == "Name")
+            .unwrap();
+        let names: Vec<&str> = matches[1..].iter().map(|row| row[name].as_str()).collect();
+        assert_eq!(names, ["Ana Garcia", "Helen Jones"]);
     }
 
-    Ok(path)
-}
+    #[test]
+    fn roster_runs_follow_the_diacritics_setting() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let metadata =
+            dataset_metadata(&["Name"], &[&["Jos\u{e9} Garc\u{ed}a"], &["Robert Smith"]]);
+        fs::write(
+            metadata_path(&host).unwrap(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+        host.write_embedding_index(&embedding_index(vec![
+            embedding_entry(0, "Jos\u{e9} Garc\u{ed}a", &[1.0, 0.0]),
+            embedding_entry(1, "Robert Smith", &[0.0, 1.0]),
+        ]));
+        let roster = write_file(
+            &host.data_dir,
+            "roster.tsv",
+            "Name\nJose Garcia\nRobert Smith\n",
+        );
+        let roster_faculty = |host: &MockHost| {
+            let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+                "taskType": "prompt",
+                "promptText": "Tumor immunology",
+                "facultyScope": "custom",
+                "customFacultyPath": roster,
+                "facultyRosterColumnMap": { "Name": "Name" },
+                "facultyRecsPerStudent": 1,
+            }))
+            .unwrap();
+            perform_matching_request(host, payload, "diacritics")
+                .unwrap()
+                .details
+                .roster_faculty_count
+        };
 
-fn expand_home(path: &str) -> PathBuf {
-    if path == "~" {
-        if let Some(home) = home_dir() {
-            return home;
-        }
-        return PathBuf::from(path);
-    }
+        assert_eq!(roster_faculty(&host), Some(2));
 
-    if let Some(stripped) = path.strip_prefix("~/") {
-        if let Some(home) = home_dir() {
-            return home.join(stripped);
-        }
+        let settings = AppSettings {
+            strip_identifier_diacritics: Some(false),
+            ..AppSettings::default()
+        };
+        write_app_settings(&host, &settings).unwrap();
+        assert_eq!(roster_faculty(&host), Some(1));
     }
 
-    PathBuf::from(path)
-}
-
-fn home_dir() -> Option<PathBuf> {
-    std::env::var("HOME")
-        .map(PathBuf::from)
-        .or_else(|_| std::env::var("USERPROFILE").map(PathBuf::from))
-        .ok()
-}
+    #[test]
+    fn matching_and_embedding_refresh_take_turns_on_a_slow_helper() {
+        let _lock = HELPER_ACTIVITY_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let (host, entered, release) = MockHost::new(&[1.0, 0.0])
+            .with_embedding("Tumor immunology", &[1.0, 0.0])
+            .with_embedding("Membrane biology", &[0.0, 1.0])
+            .with_embedding("Membranes", &[0.0, 1.0])
+            .gated();
+        fs::write(
+            dataset_destination(&host).unwrap(),
+            "Name\tResearch\nRobert Smith\tTumor immunology\nAna Garcia\tMembrane biology\n",
+        )
+        .unwrap();
+        let refresh = || -> Result<String, AppError> {
+            let _guard = begin_embedding_refresh(&host)?;
+            Ok(perform_faculty_embedding_refresh(&host)?)
+        };
+        let matching = || -> Result<SubmissionResponse, AppError> {
+            let _guard = begin_helper_activity(HelperActivity::Matching, Some("run-1"))?;
+            let payload: SubmissionPayload = serde_json::from_value(serde_json::json!({
+                "taskType": "prompt",
+                "promptText": "Membranes",
+                "facultyRecsPerStudent": 1,
+            }))
+            .unwrap();
+            perform_matching_request(&host, payload, "run-1")
+        };
 
-fn validate_extension(path: &Path, allowed: &[&str], label: &str) -> Option<String> {
-    match path.extension().and_then(|ext| ext.to_str()) {
-        Some(ext) if allowed.iter().any(|value| ext.eq_ignore_ascii_case(value)) => None,
-        Some(ext) => Some(format!(
-            "The selected {label} uses '.{ext}', which is outside the expected extensions: {}.",
-            allowed.join(", ")
-        )),
-        None => Some(format!(
-            "The selected {label} does not include an extension. Confirm it is supported."
-        )),
-    }
-}
+        thread::scope(|scope| {
+            // A matching run started mid-refresh is turned away before it reaches the helper.
+            let running = scope.spawn(refresh);
+            entered.recv().unwrap();
+            let error = matching().err();
+            assert!(
+                matches!(
+                    error,
+                    Some(AppError::Busy {
+                        activity: HelperActivity::EmbeddingRefresh,
+                        ..
+                    })
+                ),
+                "{error:?}"
+            );
+            assert_eq!(host.helper_calls.load(AtomicOrdering::SeqCst), 1);
+            release.send(()).unwrap();
+            running.join().unwrap().unwrap();
+        });
+        let index = load_faculty_embedding_index(&host).unwrap();
+        let embeddings: HashMap<&str, &[f32]> = index
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.identifiers["Name"].as_str(),
+                    entry.embedding.as_slice(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            embeddings,
+            HashMap::from([
+                ("Robert Smith", [1.0, 0.0].as_slice()),
+                ("Ana Garcia", [0.0, 1.0].as_slice()),
+            ])
+        );
 
-fn build_prompt_preview(text: &str) -> String {
-    let characters: Vec<char> = text.chars().collect();
-    let max = 280usize;
-    if characters.len() <= max {
-        return text.to_string();
+        thread::scope(|scope| {
+            // And a refresh started mid-run is turned away without touching the run.
+            let running = scope.spawn(matching);
+            entered.recv().unwrap();
+            let error = refresh().err();
+            assert!(
+                matches!(
+                    error,
+                    Some(AppError::Busy {
+                        activity: HelperActivity::Matching,
+                        ..
+                    })
+                ),
+                "{error:?}"
+            );
+            assert_eq!(host.helper_calls.load(AtomicOrdering::SeqCst), 2);
+            release.send(()).unwrap();
+            let response = running.join().unwrap().unwrap();
+            let matches = &response.prompt_matches[0].faculty_matches;
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].identifiers["Name"], "Ana Garcia");
+        });
+        assert!(begin_helper_activity(HelperActivity::EmbeddingRefresh, None).is_ok());
     }
 
-    let mut preview: String = characters[..max].iter().collect();
-    preview.push('…');
-    preview
-}
+    #[test]
+    fn duplicate_identifier_rows_count_as_one_faculty_member() {
+        let blank = |row_index, similarity| {
+            let mut faculty = faculty_match(row_index, " ", similarity);
+            faculty.identifiers.insert("Email".into(), String::new());
+            faculty
+        };
+        let mut first = vec![faculty_match(0, "Robert Smith", 0.9), blank(5, 0.5)];
+        let mut second = vec![faculty_match(3, "robert  SMITH", 0.8), blank(6, 0.4)];
+        let mut third = vec![faculty_match(1, "Ana Garcia", 0.7)];
 
-fn build_summary(
-    task_type: &TaskType,
-    faculty_scope: &FacultyScope,
-    faculty_per_student: u32,
-    program_count: usize,
-    has_custom_roster: bool,
-) -> String {
-    let input_summary = match task_type {
-        TaskType::Prompt => "a single prompt".to_string(),
-        TaskType::Document => "one document".to_string(),
-        TaskType::Spreadsheet => "a spreadsheet of prompts".to_string(),
-        TaskType::Directory => "a directory of documents".to_string(),
-    };
+        assign_student_rankings(&mut [&mut first, &mut second, &mut third]);
 
-    let scope_summary = match faculty_scope {
-        FacultyScope::All => "the complete faculty roster".to_string(),
-        FacultyScope::Program => format!(
-            "faculty filtered to {program_count} program{}",
-            if program_count == 1 { "" } else { "s" }
-        ),
-        FacultyScope::Custom => {
-            if has_custom_roster {
-                "the provided faculty roster spreadsheet".to_string()
-            } else {
-                "a custom faculty roster".to_string()
-            }
-        }
-    };
+        let ranks = |matches: &[FacultyMatchResult]| -> Vec<(Option<usize>, Option<usize>)> {
+            matches
+                .iter()
+                .map(|faculty| (faculty.student_rank_for_faculty, faculty.student_rank_total))
+                .collect()
+        };
+        // Rows 0 and 3 are the same person; the blank rows stay apart by row.
+        assert_eq!(ranks(&first), [(Some(1), Some(2)), (Some(1), Some(1))]);
+        assert_eq!(ranks(&second), [(Some(2), Some(2)), (Some(1), Some(1))]);
+        assert_eq!(ranks(&third), [(Some(1), Some(1))]);
+
+        let prompt_matches: Vec<PromptMatchResult> = [first, second, third]
+            .into_iter()
+            .map(|faculty_matches| PromptMatchResult {
+                prompt: String::new(),
+                faculty_matches,
+            })
+            .collect();
+        let statistics = build_run_statistics(&prompt_matches, Some(5));
+        assert_eq!(statistics.distinct_faculty, 4);
+        assert_eq!(statistics.scope_coverage, Some(0.8));
+    }
 
-    let summary = format!(
-        "Ready to match {input_summary} against {scope_summary}. Each student will receive up to {faculty_per_student} faculty recommendation{plural}.",
-        plural = if faculty_per_student == 1 { "" } else { "s" }
-    );
+    #[test]
+    fn document_file_urls_encode_spaces_anchors_and_windows_paths() {
+        assert_eq!(
+            document_file_url("/Users/ana/Lab notes #2.pdf"),
+            "file:///Users/ana/Lab%20notes%20%232.pdf"
+        );
+        assert_eq!(
+            document_file_url("C:\\Users\\Ana Lee\\report 100%.docx"),
+            "file:///C:/Users/Ana%20Lee/report%20100%25.docx"
+        );
+        assert_eq!(
+            document_file_url("\\\\server\\share\\Jos\u{e9}.txt"),
+            "file://server/share/Jos%C3%A9.txt"
+        );
+    }
 
-    summary
-}
+    #[test]
+    fn directory_workbooks_link_document_paths_only_when_not_scrubbed() {
+        let host = MockHost::new(&[1.0, 0.0]);
+        let index = embedding_index(vec![embedding_entry(0, "Robert Smith", &[1.0, 0.0])]);
+        let directory = TempDir::new().unwrap();
+        let document = write_file(&directory, "Lab notes #2.txt", "matching prompt");
+        let info = run_info();
+        let workbook = |scrub| {
+            let mut options = run_options(&info);
+            options.scrub_personal_information = scrub;
+            let results = process_directory_documents(&host, directory.path(), &index, &options)
+                .unwrap()
+                .results;
+            Base64Engine.decode(&results.spreadsheet.content).unwrap()
+        };
+        let hyperlink_targets = |bytes: &[u8]| -> Vec<String> {
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+            let Ok(mut rels) = archive.by_name("xl/worksheets/_rels/sheet1.xml.rels") else {
+                return Vec::new();
+            };
+            let mut xml = String::new();
+            rels.read_to_string(&mut xml).unwrap();
+            xml.split("<Relationship ")
+                .filter(|relationship| relationship.contains("relationships/hyperlink\""))
+                .filter_map(|relationship| relationship.split("Target=\"").nth(1))
+                .filter_map(|rest| rest.split('"').next())
+                .map(str::to_string)
+                .collect()
+        };
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
-            let handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                if let Err(err) = warmup_embedding_helper(handle).await {
-                    eprintln!("Failed to warm up embedding helper: {err}");
-                }
-            });
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            submit_matching_request,
-            update_faculty_embeddings,
-            ensure_embedding_helper_ready,
-            analyze_spreadsheet,
-            get_faculty_dataset_status,
-            preview_faculty_roster,
-            preview_faculty_dataset_replacement,
-            replace_faculty_dataset,
-            restore_default_faculty_dataset,
-            save_generated_spreadsheet
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        let linked = workbook(false);
+        let headers = &read_sheet(&linked, "Matches")[0];
+        assert!(headers.contains(&DOCUMENT_PATH_HEADER.to_string()));
+        let targets = hyperlink_targets(&linked);
+        assert_eq!(targets.len(), 1, "{targets:?}");
+        let expected = document_file_url(&document.to_string_lossy());
+        assert!(
+            expected.ends_with(&targets[0]),
+            "{expected} vs {}",
+            targets[0]
+        );
+        assert!(
+            targets[0].ends_with("/Lab%20notes%20%232.txt"),
+            "{}",
+            targets[0]
+        );
 
-    shutdown_embedding_helper();
+        let scrubbed = workbook(true);
+        let headers = &read_sheet(&scrubbed, "Matches")[0];
+        assert!(!headers.contains(&DOCUMENT_PATH_HEADER.to_string()));
+        assert!(hyperlink_targets(&scrubbed).is_empty());
+    }
 }
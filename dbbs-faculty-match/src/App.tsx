@@ -1,7 +1,7 @@
 import { FormEvent, useCallback, useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
-import { open, save } from "@tauri-apps/plugin-dialog";
+import { ask, open, save } from "@tauri-apps/plugin-dialog";
 import "./App.css";
 
 type TaskType = "prompt" | "document" | "spreadsheet" | "directory";
@@ -98,6 +98,7 @@ interface SubmissionDetails {
 
 interface FacultyMatchResult {
   rowIndex: number;
+  facultyId: string;
   similarity: number;
   identifiers: Record<string, string>;
   facultyText?: string;
@@ -249,6 +250,30 @@ const formatStudentRank = (
   return `#${rank}`;
 };
 
+// Matches the `kind` tag of an error returned by a backend command.
+const isErrorKind = (error: unknown, kind: string): boolean =>
+  typeof error === "object" &&
+  error !== null &&
+  "kind" in error &&
+  error.kind === kind;
+
+const describeError = (error: unknown): string => {
+  if (error instanceof Error) {
+    return error.message;
+  }
+
+  if (
+    typeof error === "object" &&
+    error !== null &&
+    "message" in error &&
+    typeof error.message === "string"
+  ) {
+    return error.message;
+  }
+
+  return String(error);
+};
+
 function App() {
   const [taskType, setTaskType] = useState<TaskType>("prompt");
   const [promptText, setPromptText] = useState("");
@@ -386,10 +411,7 @@ function App() {
         );
         applyDatasetStatus(status);
       } catch (statusError) {
-        const message =
-          statusError instanceof Error
-            ? statusError.message
-            : String(statusError);
+        const message = describeError(statusError);
         setDatasetStatus(null);
         setDatasetBanner({
           variant: "error",
@@ -416,10 +438,7 @@ function App() {
         if (!isMounted) {
           return;
         }
-        const message =
-          warmupError instanceof Error
-            ? warmupError.message
-            : String(warmupError);
+        const message = describeError(warmupError);
         setIsEmbeddingHelperReady(false);
         setEmbeddingStatus({
           variant: "error",
@@ -528,10 +547,7 @@ function App() {
         const message = await invoke<string>("update_faculty_embeddings");
         setEmbeddingStatus({ variant: "success", message });
       } catch (updateError) {
-        const message =
-          updateError instanceof Error
-            ? updateError.message
-            : String(updateError);
+        const message = describeError(updateError);
         setEmbeddingStatus({ variant: "error", message });
       } finally {
         setIsUpdatingEmbeddings(false);
@@ -558,10 +574,7 @@ function App() {
       }
       return null;
     } catch (selectionError) {
-      const message =
-        selectionError instanceof Error
-          ? selectionError.message
-          : String(selectionError);
+      const message = describeError(selectionError);
       setDatasetBanner({
         variant: "error",
         message: `Unable to open a file dialog: ${message}`,
@@ -591,10 +604,7 @@ function App() {
         [...preview.suggestedProgramColumns].sort((a, b) => a - b),
       );
     } catch (previewError) {
-      const message =
-        previewError instanceof Error
-          ? previewError.message
-          : String(previewError);
+      const message = describeError(previewError);
       setDatasetConfigurationError(message);
     } finally {
       setIsLoadingDatasetConfiguration(false);
@@ -672,10 +682,7 @@ function App() {
 
       await runEmbeddingRefresh(status);
     } catch (replacementError) {
-      const message =
-        replacementError instanceof Error
-          ? replacementError.message
-          : String(replacementError);
+      const message = describeError(replacementError);
       setDatasetConfigurationError(message);
       setDatasetBanner({
         variant: "error",
@@ -698,10 +705,7 @@ function App() {
         await runEmbeddingRefresh(status);
       }
     } catch (restoreError) {
-      const message =
-        restoreError instanceof Error
-          ? restoreError.message
-          : String(restoreError);
+      const message = describeError(restoreError);
       setDatasetBanner({
         variant: "error",
         message: `Unable to restore the packaged dataset: ${message}`,
@@ -797,10 +801,7 @@ function App() {
       }
       return null;
     } catch (selectionError) {
-      const message =
-        selectionError instanceof Error
-          ? selectionError.message
-          : String(selectionError);
+      const message = describeError(selectionError);
       setError(`Unable to open a file dialog: ${message}`);
       return null;
     }
@@ -860,10 +861,7 @@ function App() {
       setRosterWarnings(response.warnings ?? []);
       setRosterIdentifierMapping(mapping);
     } catch (analysisError) {
-      const message =
-        analysisError instanceof Error
-          ? analysisError.message
-          : String(analysisError);
+      const message = describeError(analysisError);
       setRosterPreview(null);
       setRosterWarnings([]);
       setRosterIdentifierMapping({});
@@ -901,10 +899,7 @@ function App() {
       setSelectedIdentifierColumns(identifierSuggestions);
       setSelectedPromptColumns(promptSuggestions);
     } catch (analysisError) {
-      const message =
-        analysisError instanceof Error
-          ? analysisError.message
-          : String(analysisError);
+      const message = describeError(analysisError);
       setSpreadsheetPreview(null);
       setSpreadsheetPreviewError(message);
       setSelectedIdentifierColumns([]);
@@ -1207,10 +1202,7 @@ function App() {
 
       setResult(response);
     } catch (submissionError) {
-      const message =
-        submissionError instanceof Error
-          ? submissionError.message
-          : String(submissionError);
+      const message = describeError(submissionError);
       setError(message);
   } finally {
     setIsSubmitting(false);
@@ -1266,18 +1258,36 @@ function App() {
           return;
         }
 
-        await invoke("save_generated_spreadsheet", {
+        const saveArgs = {
           path: selectedPath,
           content: spreadsheet.content,
           encoding: spreadsheet.encoding ?? null,
-        });
+        };
+        try {
+          await invoke("save_generated_spreadsheet", {
+            ...saveArgs,
+            overwrite: false,
+          });
+        } catch (saveError) {
+          if (!isErrorKind(saveError, "fileExists")) {
+            throw saveError;
+          }
+          const replace = await ask(
+            `${selectedPath} already exists. Do you want to replace it?`,
+            { title: "Replace existing file?", kind: "warning" },
+          );
+          if (!replace) {
+            return;
+          }
+          await invoke("save_generated_spreadsheet", {
+            ...saveArgs,
+            overwrite: true,
+          });
+        }
 
         setError(null);
       } catch (downloadError) {
-        const message =
-          downloadError instanceof Error
-            ? downloadError.message
-            : String(downloadError);
+        const message = describeError(downloadError);
         setError(`Unable to save the ${context} results: ${message}`);
       }
     },
@@ -2393,7 +2403,7 @@ function App() {
                           return (
                             <li
                               className="match-list-item"
-                              key={`${faculty.rowIndex}-${rank}`}
+                              key={`${faculty.facultyId}-${rank}`}
                             >
                               <div className="match-list-header">
                                 <div className="match-rankings">
@@ -2414,7 +2424,7 @@ function App() {
                                 {hasIdentifiers ? (
                                   sortedIdentifierEntries.map(([label, value]) => (
                                     <span
-                                      key={`${faculty.rowIndex}-${label}`}
+                                      key={`${faculty.facultyId}-${label}`}
                                       className="match-identifier"
                                     >
                                       <span className="match-identifier-label">
@@ -2426,9 +2436,9 @@ function App() {
                                 ) : (
                                   <span className="match-identifier">
                                     <span className="match-identifier-label">
-                                      Row index:
+                                      Faculty ID:
                                     </span>{" "}
-                                    {faculty.rowIndex}
+                                    {faculty.facultyId}
                                   </span>
                                 )}
                               </div>